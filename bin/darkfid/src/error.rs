@@ -16,9 +16,7 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use serde_json::Value;
-
-use darkfi::rpc::jsonrpc::{ErrorCode::ServerError, JsonError, JsonResult};
+use darkfi::rpc::{jsonrpc::ErrorCode::ServerError, registry::MethodError};
 
 /// Custom RPC errors available for darkfid.
 /// Please sort them sensefully.
@@ -36,6 +34,7 @@ pub enum RpcError {
     // Transaction-related errors
     TxSimulationFail = -32110,
     TxBroadcastFail = -32111,
+    TxNotInMempool = -32112,
 
     // State-related errors,
     NotSynced = -32120,
@@ -63,6 +62,7 @@ fn to_tuple(e: RpcError) -> (i64, String) {
         // Transaction-related errors
         RpcError::TxSimulationFail => "Failed simulating transaction state change",
         RpcError::TxBroadcastFail => "Failed broadcasting transaction",
+        RpcError::TxNotInMempool => "Transaction not found in mempool",
         // State-related errors
         RpcError::NotSynced => "Blockchain is not synced",
         RpcError::UnknownSlot => "Did not find slot",
@@ -75,12 +75,9 @@ fn to_tuple(e: RpcError) -> (i64, String) {
     (e as i64, msg.to_string())
 }
 
-pub fn server_error(e: RpcError, id: Value, msg: Option<&str>) -> JsonResult {
-    let (code, default_msg) = to_tuple(e);
-
-    if let Some(message) = msg {
-        return JsonError::new(ServerError(code), Some(message.to_string()), id).into()
+impl From<RpcError> for MethodError {
+    fn from(e: RpcError) -> Self {
+        let (code, msg) = to_tuple(e);
+        MethodError::new(ServerError(code), Some(msg))
     }
-
-    JsonError::new(ServerError(code), Some(default_msg), id).into()
 }
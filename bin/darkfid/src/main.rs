@@ -19,9 +19,8 @@
 use std::str::FromStr;
 
 use async_std::sync::{Arc, Mutex};
-use async_trait::async_trait;
 use darkfi_sdk::crypto::PublicKey;
-use log::{error, info};
+use log::{error, info, warn};
 use structopt_toml::{serde::Deserialize, structopt::StructOpt, StructOptToml};
 use url::Url;
 
@@ -36,29 +35,42 @@ use darkfi::{
         proto::{ProtocolProposal, ProtocolSync, ProtocolSyncConsensus, ProtocolTx},
         task::{block_sync_task, proposal_task},
         validator::ValidatorStatePtr,
-        ValidatorState,
+        MempoolOrdering, ValidatorState,
     },
     net,
     net::P2pPtr,
     rpc::{
+        auth::{create_cookie, AuthSettings},
         clock_sync::check_clock,
-        jsonrpc::{
-            ErrorCode::{InvalidParams, MethodNotFound},
-            JsonError, JsonRequest, JsonResult,
-        },
-        server::{listen_and_serve, RequestHandler},
+        registry::{MethodRegistry, RegistryHandler},
+        server::{listen_and_serve_with_settings, ServerSettings},
     },
     util::path::expand_path,
     wallet::{walletdb::init_wallet, WalletPtr},
+    zk::KeyCache,
     Error, Result,
 };
 
 mod error;
-use error::{server_error, RpcError};
+use error::RpcError;
 
 const CONFIG_FILE: &str = "darkfid_config.toml";
 const CONFIG_FILE_CONTENTS: &str = include_str!("../darkfid_config.toml");
 
+/// JSON-RPC methods callable without authentication, unless configured otherwise
+const RPC_PUBLIC_METHODS: &[&str] = &[
+    "ping",
+    "clock",
+    "get_info",
+    "get_consensus_info",
+    "blockchain.*",
+    "tx.simulate",
+    "tx.broadcast",
+    "mempool.list",
+    "mempool.get_tx",
+    "rpc.discover",
+];
+
 #[derive(Clone, Debug, Deserialize, StructOpt, StructOptToml)]
 #[serde(default)]
 #[structopt(name = "darkfid", about = cli_desc!())]
@@ -91,10 +103,54 @@ struct Args {
     /// Path to blockchain database
     database: String,
 
+    #[structopt(long, default_value = "~/.config/darkfi/zk_key_cache")]
+    /// Path to the ZK proof key cache directory
+    key_cache: String,
+
     #[structopt(long, default_value = "tcp://127.0.0.1:8340")]
-    /// JSON-RPC listen URL
+    /// JSON-RPC listen URL (tcp://, tcp+tls://, unix://, http:// or ws://)
     rpc_listen: Url,
 
+    #[structopt(long, default_value = "8388608")]
+    /// Maximum size in bytes of a JSON-RPC request or batch
+    rpc_max_request_size: usize,
+
+    #[structopt(long, default_value = "100")]
+    /// Maximum number of requests in a JSON-RPC batch
+    rpc_max_batch_size: usize,
+
+    #[structopt(long, default_value = "16")]
+    /// Maximum number of JSON-RPC requests handled concurrently per connection
+    rpc_max_in_flight: usize,
+
+    #[structopt(long)]
+    /// Origin allowed to make JSON-RPC requests over HTTP or websockets (repeatable flag)
+    rpc_allowed_origin: Vec<String>,
+
+    #[structopt(long, default_value = "~/.config/darkfi/darkfid_rpc.cookie")]
+    /// Path to the cookie file holding a JSON-RPC token for local clients
+    rpc_cookie: String,
+
+    #[structopt(long)]
+    /// JSON-RPC token granting access to all methods (repeatable flag)
+    rpc_auth_token: Vec<String>,
+
+    #[structopt(long)]
+    /// Hex-encoded TLS client key granting access to all JSON-RPC methods (repeatable flag)
+    rpc_tls_client_key: Vec<String>,
+
+    #[structopt(long)]
+    /// JSON-RPC method or `prefix.*` pattern open to anyone (repeatable flag)
+    rpc_public_method: Option<Vec<String>>,
+
+    #[structopt(long)]
+    /// Disable JSON-RPC authentication, opening all methods to anyone
+    rpc_no_auth: bool,
+
+    #[structopt(long)]
+    /// Print the OpenRPC schema of the JSON-RPC API and exit
+    rpc_schema: bool,
+
     #[structopt(long)]
     /// P2P accept addresses for the consensus protocol (repeatable flag)
     consensus_p2p_accept: Vec<Url>,
@@ -159,6 +215,18 @@ struct Args {
     /// Enable channel log
     channel_log: bool,
 
+    #[structopt(long, default_value = "1000")]
+    /// Maximum number of pending transactions kept in the mempool
+    mempool_max_txs: usize,
+
+    #[structopt(long, default_value = "3600")]
+    /// Seconds after which a pending transaction is dropped from the mempool
+    mempool_tx_expiry: u64,
+
+    #[structopt(long, default_value = "fee")]
+    /// Priority of pending transactions in the mempool (fee, age)
+    mempool_ordering: String,
+
     #[structopt(long)]
     /// Whitelisted cashier public key (repeatable flag)
     cashier_pub: Vec<String>,
@@ -186,74 +254,15 @@ pub struct Darkfid {
 
 // JSON-RPC methods
 mod rpc_blockchain;
+mod rpc_mempool;
 mod rpc_misc;
+mod rpc_net;
 mod rpc_tx;
 mod rpc_wallet;
 
 // Internal methods
 //mod internal;
 
-#[async_trait]
-impl RequestHandler for Darkfid {
-    async fn handle_request(&self, req: JsonRequest) -> JsonResult {
-        if !req.params.is_array() {
-            return JsonError::new(InvalidParams, None, req.id).into()
-        }
-
-        let params = req.params.as_array().unwrap();
-
-        match req.method.as_str() {
-            // =====================
-            // Miscellaneous methods
-            // =====================
-            Some("ping") => return self.misc_pong(req.id, params).await,
-            Some("clock") => return self.misc_clock(req.id, params).await,
-            Some("get_info") => return self.misc_get_info(req.id, params).await,
-            Some("get_consensus_info") => return self.misc_get_consensus_info(req.id, params).await,
-
-            // ==================
-            // Blockchain methods
-            // ==================
-            Some("blockchain.get_slot") => return self.blockchain_get_slot(req.id, params).await,
-            Some("blockchain.get_tx") => return self.blockchain_get_tx(req.id, params).await,
-            Some("blockchain.last_known_slot") => {
-                return self.blockchain_last_known_slot(req.id, params).await
-            }
-            Some("blockchain.subscribe_blocks") => {
-                return self.blockchain_subscribe_blocks(req.id, params).await
-            }
-            Some("blockchain.lookup_zkas") => {
-                return self.blockchain_lookup_zkas(req.id, params).await
-            }
-            Some("blockchain.was_erroneous_tx") => {
-                return self.blockchain_was_erroneous_tx(req.id, params).await
-            }
-
-            // ===================
-            // Transaction methods
-            // ===================
-            Some("tx.simulate") => return self.tx_simulate(req.id, params).await,
-            Some("tx.broadcast") => return self.tx_broadcast(req.id, params).await,
-
-            // ==============
-            // Wallet methods
-            // ==============
-            Some("wallet.exec_sql") => return self.wallet_exec_sql(req.id, params).await,
-            Some("wallet.query_row_single") => {
-                return self.wallet_query_row_single(req.id, params).await
-            }
-            Some("wallet.query_row_multi") => {
-                return self.wallet_query_row_multi(req.id, params).await
-            }
-
-            // ==============
-            // Invalid method
-            // ==============
-            Some(_) | None => return JsonError::new(MethodNotFound, None, req.id).into(),
-        }
-    }
-}
-
 impl Darkfid {
     pub async fn new(
         validator_state: ValidatorStatePtr,
@@ -263,10 +272,27 @@ impl Darkfid {
     ) -> Self {
         Self { synced: Mutex::new(false), consensus_p2p, sync_p2p, wallet, validator_state }
     }
+
+    /// Registry of the JSON-RPC methods served by darkfid
+    pub fn rpc_methods() -> MethodRegistry<Self> {
+        let mut methods = MethodRegistry::new("darkfid", env!("CARGO_PKG_VERSION"));
+        rpc_misc::register(&mut methods);
+        rpc_net::register(&mut methods);
+        rpc_blockchain::register(&mut methods);
+        rpc_tx::register(&mut methods);
+        rpc_mempool::register(&mut methods);
+        rpc_wallet::register(&mut methods);
+        methods
+    }
 }
 
 async_daemonize!(realmain);
 async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
+    if args.rpc_schema {
+        println!("{:#}", Darkfid::rpc_methods().discover());
+        return Ok(())
+    }
+
     if args.consensus && args.clock_sync {
         // We verify that if peer/seed nodes are configured, their rpc config also exists
         if ((!args.consensus_p2p_peer.is_empty() && args.consensus_peer_rpc.is_empty()) ||
@@ -340,6 +366,11 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
         info!("Node is configured to run in single-node mode!");
     }
 
+    let mempool_ordering = MempoolOrdering::from_str(&args.mempool_ordering)?;
+
+    // Initialize the ZK proof key cache
+    let key_cache = Arc::new(KeyCache::new(Some(expand_path(&args.key_cache)?))?);
+
     // Initialize validator state
     let state = ValidatorState::new(
         &sled_db,
@@ -348,12 +379,20 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
         genesis_data,
         initial_distribution,
         wallet.clone(),
+        key_cache,
         faucet_pubkeys,
         args.consensus,
         args.single_node,
     )
     .await?;
 
+    {
+        let mut lock = state.write().await;
+        lock.mempool.max_txs = args.mempool_max_txs;
+        lock.mempool.tx_expiry = args.mempool_tx_expiry;
+        lock.mempool.ordering = mempool_ordering;
+    }
+
     let sync_p2p = {
         info!("Registering block sync P2P protocols...");
         let sync_network_settings = net::Settings {
@@ -443,8 +482,36 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
 
     // JSON-RPC server
     info!("Starting JSON-RPC server");
+    let rpc_cookie = expand_path(&args.rpc_cookie)?;
+    let rpc_auth = if args.rpc_no_auth {
+        warn!(
+            "JSON-RPC authentication is disabled, anyone reaching the listener can use the wallet"
+        );
+        None
+    } else {
+        let mut tokens = args.rpc_auth_token;
+        tokens.push(create_cookie(&rpc_cookie)?);
+        info!("Wrote JSON-RPC cookie to {:?}", rpc_cookie);
+
+        let public_methods = match args.rpc_public_method {
+            Some(methods) => methods,
+            None => RPC_PUBLIC_METHODS.iter().map(|m| m.to_string()).collect(),
+        };
+
+        Some(AuthSettings { tokens, client_keys: args.rpc_tls_client_key, public_methods })
+    };
+
+    let rpc_settings = ServerSettings {
+        max_request_size: args.rpc_max_request_size,
+        max_batch_size: args.rpc_max_batch_size,
+        max_in_flight: args.rpc_max_in_flight,
+        allowed_origins: args.rpc_allowed_origin,
+        auth: rpc_auth.clone(),
+    };
+    let rpc_handler = Arc::new(RegistryHandler::new(darkfid.clone(), Darkfid::rpc_methods()));
     let _ex = ex.clone();
-    ex.spawn(listen_and_serve(args.rpc_listen, darkfid.clone(), _ex)).detach();
+    ex.spawn(listen_and_serve_with_settings(args.rpc_listen, rpc_handler, rpc_settings, _ex))
+        .detach();
 
     info!("Starting sync P2P network");
     sync_p2p.clone().unwrap().start(ex.clone()).await?;
@@ -501,5 +568,9 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
     wallet.conn.close().await;
     info!("Closed wallet connection");
 
+    if rpc_auth.is_some() {
+        let _ = std::fs::remove_file(&rpc_cookie);
+    }
+
     Ok(())
 }
@@ -19,29 +19,73 @@
 use darkfi_sdk::{crypto::ContractId, db::SMART_CONTRACT_ZKAS_DB_NAME};
 use darkfi_serial::{deserialize, serialize};
 use log::{debug, error};
-use serde_json::{json, Value};
 
-use darkfi::rpc::jsonrpc::{
-    ErrorCode::{InternalError, InvalidParams, ParseError},
-    JsonError, JsonResponse, JsonResult, JsonSubscriber,
+use darkfi::{
+    rpc::{
+        jsonrpc::{
+            ErrorCode::{InternalError, InvalidParams, ParseError},
+            JsonNotification,
+        },
+        registry::{MethodRegistry, MethodResult},
+    },
+    system::SubscriberPtr,
 };
 
 use super::Darkfid;
-use crate::{server_error, RpcError};
+use crate::RpcError;
+
+pub fn register(methods: &mut MethodRegistry<Darkfid>) {
+    methods.method(
+        "blockchain.get_slot",
+        "Queries the blockchain database for a block in the given slot. \
+         Returns the serialized block upon success.",
+        &["slot"],
+        |d, (slot,)| async move { d.blockchain_get_slot(slot).await },
+    );
+
+    methods.method(
+        "blockchain.get_tx",
+        "Queries the blockchain database for a transaction with the given \
+         hex-encoded hash. Returns the serialized transaction upon success.",
+        &["tx_hash"],
+        |d, (tx_hash,)| async move { d.blockchain_get_tx(tx_hash).await },
+    );
+
+    methods.method(
+        "blockchain.last_known_slot",
+        "Queries the blockchain database to find the last known slot.",
+        &[],
+        |d, ()| async move { d.blockchain_last_known_slot().await },
+    );
+
+    methods.subscription(
+        "blockchain.subscribe_blocks",
+        "Initializes a subscription to new incoming blocks. Once a subscription \
+         is established, `darkfid` will send JSON-RPC notifications of new \
+         incoming blocks to the subscriber, on the same connection.",
+        &[],
+        |d, ()| async move { d.blockchain_subscribe_blocks().await },
+    );
+
+    methods.method(
+        "blockchain.lookup_zkas",
+        "Performs a lookup of zkas bincodes for a given contract ID and returns \
+         all of them, including their namespace.",
+        &["contract_id"],
+        |d, (contract_id,)| async move { d.blockchain_lookup_zkas(contract_id).await },
+    );
+
+    methods.method(
+        "blockchain.was_erroneous_tx",
+        "Queries the blockchain database to check if the provided transaction \
+         hash exists in the erroneous transactions set.",
+        &["tx_hash"],
+        |d, (tx_hash,)| async move { d.blockchain_was_erroneous_tx(tx_hash).await },
+    );
+}
 
 impl Darkfid {
-    // RPCAPI:
-    // Queries the blockchain database for a block in the given slot.
-    // Returns a readable block upon success.
-    //
-    // --> {"jsonrpc": "2.0", "method": "blockchain.get_slot", "params": [0], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": {...}, "id": 1}
-    pub async fn blockchain_get_slot(&self, id: Value, params: &[Value]) -> JsonResult {
-        if params.len() != 1 || !params[0].is_u64() {
-            return JsonError::new(InvalidParams, None, id).into()
-        }
-
-        let slot = params[0].as_u64().unwrap();
+    pub async fn blockchain_get_slot(&self, slot: u64) -> MethodResult<Vec<u8>> {
         let validator_state = self.validator_state.read().await;
 
         let blocks = match validator_state.blockchain.get_blocks_by_slot(&[slot]) {
@@ -51,38 +95,20 @@ impl Darkfid {
             }
             Err(e) => {
                 error!("[RPC] blockchain.get_slot: Failed fetching block by slot: {}", e);
-                return JsonError::new(InternalError, None, id).into()
+                return Err(InternalError.into())
             }
         };
 
         if blocks.is_empty() {
-            return server_error(RpcError::UnknownSlot, id, None)
+            return Err(RpcError::UnknownSlot.into())
         }
 
-        JsonResponse::new(json!(serialize(&blocks[0])), id).into()
+        Ok(serialize(&blocks[0]))
     }
 
-    // RPCAPI:
-    // Queries the blockchain database for a block in the given slot.
-    // Returns a readable block upon success.
-    //
-    // --> {"jsonrpc": "2.0", "method": "blockchain.get_tx", "params": ["TxHash"], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": {...}, "id": 1}
-    pub async fn blockchain_get_tx(&self, id: Value, params: &[Value]) -> JsonResult {
-        if params.len() != 1 {
-            return JsonError::new(InvalidParams, None, id).into()
-        }
-
-        let tx_hash_str = if let Some(tx_hash_str) = params[0].as_str() {
-            tx_hash_str
-        } else {
-            return JsonError::new(InvalidParams, None, id).into()
-        };
-
-        let tx_hash = if let Ok(tx_hash) = blake3::Hash::from_hex(tx_hash_str) {
-            tx_hash
-        } else {
-            return JsonError::new(ParseError, None, id).into()
+    pub async fn blockchain_get_tx(&self, tx_hash: String) -> MethodResult<Vec<u8>> {
+        let Ok(tx_hash) = blake3::Hash::from_hex(tx_hash) else {
+            return Err(ParseError.into())
         };
 
         let validator_state = self.validator_state.read().await;
@@ -94,7 +120,7 @@ impl Darkfid {
             }
             Err(e) => {
                 error!("[RPC] blockchain.get_tx: Failed fetching tx by hash: {}", e);
-                return JsonError::new(InternalError, None, id).into()
+                return Err(InternalError.into())
             }
         };
         // This would be an logic error somewhere
@@ -102,61 +128,33 @@ impl Darkfid {
         // and strict was used during .get()
         let tx = txs[0].as_ref().unwrap();
 
-        JsonResponse::new(json!(serialize(tx)), id).into()
+        Ok(serialize(tx))
     }
 
-    // RPCAPI:
-    // Queries the blockchain database to find the last known slot
-    //
-    // --> {"jsonrpc": "2.0", "method": "blockchain.last_known_slot", "params": [], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": 1234, "id": 1}
-    pub async fn blockchain_last_known_slot(&self, id: Value, params: &[Value]) -> JsonResult {
-        if !params.is_empty() {
-            return JsonError::new(InvalidParams, None, id).into()
-        }
-
+    pub async fn blockchain_last_known_slot(&self) -> MethodResult<u64> {
         let blockchain = { self.validator_state.read().await.blockchain.clone() };
         let Ok(last_slot) = blockchain.last() else {
-                return JsonError::new(InternalError, None, id).into()
+            return Err(InternalError.into())
         };
 
-        JsonResponse::new(json!(last_slot.0), id).into()
+        Ok(last_slot.0)
     }
 
-    // RPCAPI:
-    // Initializes a subscription to new incoming blocks.
-    // Once a subscription is established, `darkfid` will send JSON-RPC notifications of
-    // new incoming blocks to the subscriber.
-    //
-    // --> {"jsonrpc": "2.0", "method": "blockchain.subscribe_blocks", "params": [], "id": 1}
-    // <-- {"jsonrpc": "2.0", "method": "blockchain.subscribe_blocks", "params": [`blockinfo`]}
-    pub async fn blockchain_subscribe_blocks(&self, id: Value, params: &[Value]) -> JsonResult {
-        if !params.is_empty() {
-            return JsonError::new(InvalidParams, None, id).into()
-        }
-
-        let blocks_subscriber =
-            self.validator_state.read().await.subscribers.get("blocks").unwrap().clone();
-
-        JsonSubscriber::new(blocks_subscriber).into()
+    pub async fn blockchain_subscribe_blocks(
+        &self,
+    ) -> MethodResult<SubscriberPtr<JsonNotification>> {
+        Ok(self.validator_state.read().await.subscribers.get("blocks").unwrap().clone())
     }
 
-    // RPCAPI:
-    // Performs a lookup of zkas bincodes for a given contract ID and returns all of
-    // them, including their namespace.
-    //
-    // --> {"jsonrpc": "2.0", "method": "blockchain.lookup_zkas", "params": ["6Ef42L1KLZXBoxBuCDto7coi9DA2D2SRtegNqNU4sd74"], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": [["Foo", [...]], ["Bar", [...]]], "id": 1}
-    pub async fn blockchain_lookup_zkas(&self, id: Value, params: &[Value]) -> JsonResult {
-        if params.len() != 1 || !params[0].is_string() {
-            return JsonError::new(InvalidParams, None, id).into()
-        }
-
-        let contract_id = match ContractId::try_from(params[0].as_str().unwrap()) {
+    pub async fn blockchain_lookup_zkas(
+        &self,
+        contract_id: String,
+    ) -> MethodResult<Vec<(String, Vec<u8>)>> {
+        let contract_id = match ContractId::try_from(contract_id.as_str()) {
             Ok(v) => v,
             Err(e) => {
                 error!("[RPC] blockchain.lookup_zkas: Error decoding string to ContractId: {}", e);
-                return JsonError::new(InvalidParams, None, id).into()
+                return Err(InvalidParams.into())
             }
         };
 
@@ -164,7 +162,7 @@ impl Darkfid {
 
         let Ok(zkas_db) = blockchain.contracts.lookup(&blockchain.sled_db, &contract_id, SMART_CONTRACT_ZKAS_DB_NAME) else {
             error!("[RPC] blockchain.lookup_zkas: Did not find zkas db for ContractId: {}", contract_id);
-            return server_error(RpcError::ContractZkasDbNotFound, id, None)
+            return Err(RpcError::ContractZkasDbNotFound.into())
         };
 
         let mut ret: Vec<(String, Vec<u8>)> = vec![];
@@ -173,36 +171,26 @@ impl Darkfid {
             debug!("Iterating over zkas db");
             let Ok((zkas_ns, zkas_bincode)) = i else {
                 error!("Internal sled error iterating db");
-                return JsonError::new(InternalError, None, id).into()
+                return Err(InternalError.into())
             };
 
             let Ok(zkas_ns) = deserialize(&zkas_ns) else {
-                return JsonError::new(InternalError, None, id).into()
+                return Err(InternalError.into())
             };
 
             ret.push((zkas_ns, zkas_bincode.to_vec()));
         }
 
-        JsonResponse::new(json!(ret), id).into()
+        Ok(ret)
     }
 
-    // RPCAPI:
-    // Queries the blockchain database to check if the provided transaction hash exists
-    // in the erroneous transactions set.
-    //
-    // --> {"jsonrpc": "2.0", "method": "blockchain.was_erroneous_tx", "params": [[tx_hash bytes]], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": bool, "id": 1}
-    pub async fn blockchain_was_erroneous_tx(&self, id: Value, params: &[Value]) -> JsonResult {
-        if params.len() != 1 || !params[0].is_array() {
-            return JsonError::new(InvalidParams, None, id).into()
-        }
-        let hash_bytes: [u8; 32] = serde_json::from_value(params[0].clone()).unwrap();
-        let tx_hash = blake3::Hash::try_from(hash_bytes).unwrap();
+    pub async fn blockchain_was_erroneous_tx(&self, tx_hash: [u8; 32]) -> MethodResult<bool> {
+        let tx_hash = blake3::Hash::from(tx_hash);
         let blockchain = { self.validator_state.read().await.blockchain.clone() };
         let Ok(result) = blockchain.was_erroneous_tx(&tx_hash) else {
-                return JsonError::new(InternalError, None, id).into()
+            return Err(InternalError.into())
         };
 
-        JsonResponse::new(json!(result), id).into()
+        Ok(result)
     }
 }
@@ -0,0 +1,125 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use darkfi_sdk::pasta::group::ff::PrimeField;
+use darkfi_serial::serialize;
+use log::{error, info};
+use serde_json::{json, Value};
+
+use darkfi::{
+    blockchain::PendingTx,
+    rpc::{
+        jsonrpc::ErrorCode::{InternalError, ParseError},
+        registry::{MethodRegistry, MethodResult},
+    },
+};
+
+use super::Darkfid;
+use crate::RpcError;
+
+pub fn register(methods: &mut MethodRegistry<Darkfid>) {
+    methods.method(
+        "mempool.list",
+        "Lists the transactions in the mempool, from highest to lowest \
+         priority. Each entry holds the transaction hash, the fee it pays, the \
+         UNIX time it was received, its number of calls, its serialized size in \
+         bytes, and the nullifiers it reveals.",
+        &[],
+        |d, ()| async move { d.mempool_list().await },
+    );
+
+    methods.method(
+        "mempool.get_tx",
+        "Fetches a transaction from the mempool, given its hash. Returns the \
+         same summary as `mempool.list`, along with the base58-encoded \
+         serialized transaction under `tx`.",
+        &["tx_hash"],
+        |d, (tx_hash,)| async move { d.mempool_get_tx(tx_hash).await },
+    );
+
+    methods.method(
+        "mempool.drop_tx",
+        "Drops a transaction from the mempool, given its hash. \
+         Returns `true` upon success.",
+        &["tx_hash"],
+        |d, (tx_hash,)| async move { d.mempool_drop_tx(tx_hash).await },
+    );
+}
+
+/// Summary of a pending transaction, as returned by the mempool methods
+fn pending_tx_json(tx_hash: &blake3::Hash, pending: &PendingTx) -> Value {
+    let nullifiers: Vec<String> =
+        pending.nullifiers.iter().map(|n| bs58::encode(n.to_repr()).into_string()).collect();
+
+    json!({
+        "tx_hash": tx_hash.to_hex().as_str(),
+        "fee": pending.fee,
+        "received": pending.received.0,
+        "calls": pending.tx.calls.len(),
+        "size": serialize(&pending.tx).len(),
+        "nullifiers": nullifiers,
+    })
+}
+
+impl Darkfid {
+    pub async fn mempool_list(&self) -> MethodResult<Vec<Value>> {
+        let validator_state = self.validator_state.read().await;
+        let entries: Vec<Value> = validator_state
+            .mempool
+            .ordered()
+            .into_iter()
+            .map(|(tx_hash, pending)| pending_tx_json(&tx_hash, pending))
+            .collect();
+
+        Ok(entries)
+    }
+
+    pub async fn mempool_get_tx(&self, tx_hash: String) -> MethodResult<Value> {
+        let Ok(tx_hash) = blake3::Hash::from_hex(tx_hash) else {
+            return Err(ParseError.into())
+        };
+
+        let validator_state = self.validator_state.read().await;
+        let Some(pending) = validator_state.mempool.get(&tx_hash) else {
+            return Err(RpcError::TxNotInMempool.into())
+        };
+
+        let mut entry = pending_tx_json(&tx_hash, pending);
+        entry["tx"] = json!(bs58::encode(&serialize(&pending.tx)).into_string());
+
+        Ok(entry)
+    }
+
+    pub async fn mempool_drop_tx(&self, tx_hash: String) -> MethodResult<bool> {
+        let Ok(tx_hash) = blake3::Hash::from_hex(tx_hash) else {
+            return Err(ParseError.into())
+        };
+
+        match self.validator_state.write().await.mempool.remove(&tx_hash) {
+            Ok(Some(_)) => {}
+            Ok(None) => return Err(RpcError::TxNotInMempool.into()),
+            Err(e) => {
+                error!("[RPC] mempool.drop_tx: Failed removing tx from mempool: {}", e);
+                return Err(InternalError.into())
+            }
+        }
+
+        info!("[RPC] mempool.drop_tx: Dropped transaction {}", tx_hash);
+        Ok(true)
+    }
+}
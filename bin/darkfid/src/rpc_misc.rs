@@ -19,54 +19,58 @@
 use serde_json::{json, Value};
 
 use darkfi::{
-    rpc::jsonrpc::{JsonResponse, JsonResult},
+    rpc::registry::{MethodRegistry, MethodResult},
     util::time::Timestamp,
 };
 
 use super::Darkfid;
 
+pub fn register(methods: &mut MethodRegistry<Darkfid>) {
+    methods.method("ping", "Returns a `pong` to the `ping` request.", &[], |d, ()| async move {
+        d.misc_pong().await
+    });
+
+    methods.method(
+        "clock",
+        "Returns current system clock as a UNIX timestamp.",
+        &[],
+        |d, ()| async move { d.misc_clock().await },
+    );
+
+    methods.method("get_info", "Returns sync P2P network information.", &[], |d, ()| async move {
+        d.misc_get_info().await
+    });
+
+    methods.method(
+        "get_consensus_info",
+        "Returns consensus P2P network information.",
+        &[],
+        |d, ()| async move { d.misc_get_consensus_info().await },
+    );
+}
+
 impl Darkfid {
-    // RPCAPI:
-    // Returns a `pong` to the `ping` request.
-    //
-    // --> {"jsonrpc": "2.0", "method": "ping", "params": [], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": "pong", "id": 1}
-    pub async fn misc_pong(&self, id: Value, _params: &[Value]) -> JsonResult {
-        JsonResponse::new(json!("pong"), id).into()
+    pub async fn misc_pong(&self) -> MethodResult<String> {
+        Ok("pong".to_string())
     }
 
-    // RPCAPI:
-    // Returns current system clock in `Timestamp` format.
-    //
-    // --> {"jsonrpc": "2.0", "method": "clock", "params": [], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": {...}, "id": 1}
-    pub async fn misc_clock(&self, id: Value, _params: &[Value]) -> JsonResult {
-        JsonResponse::new(json!(Timestamp::current_time()), id).into()
+    pub async fn misc_clock(&self) -> MethodResult<i64> {
+        Ok(Timestamp::current_time().0)
     }
 
-    // RPCAPI:
-    // Returns sync P2P network information.
-    //
-    // --> {"jsonrpc": "2.0", "method": "get_info", "params": [], "id": 42}
-    // <-- {"jsonrpc": "2.0", result": {"nodeID": [], "nodeinfo": [], "id": 42}
-    pub async fn misc_get_info(&self, id: Value, _params: &[Value]) -> JsonResult {
-        let resp = match &self.sync_p2p {
+    pub async fn misc_get_info(&self) -> MethodResult<Value> {
+        let info = match &self.sync_p2p {
             Some(p2p) => p2p.get_info().await,
             None => json!([]),
         };
-        JsonResponse::new(resp, id).into()
+        Ok(info)
     }
 
-    // RPCAPI:
-    // Returns consensus P2P network information.
-    //
-    // --> {"jsonrpc": "2.0", "method": "get_consensus_info", "params": [], "id": 42}
-    // <-- {"jsonrpc": "2.0", result": {"nodeID": [], "nodeinfo": [], "id": 42}
-    pub async fn misc_get_consensus_info(&self, id: Value, _params: &[Value]) -> JsonResult {
-        let resp = match &self.consensus_p2p {
+    pub async fn misc_get_consensus_info(&self) -> MethodResult<Value> {
+        let info = match &self.consensus_p2p {
             Some(p2p) => p2p.get_info().await,
             None => json!([]),
         };
-        JsonResponse::new(resp, id).into()
+        Ok(info)
     }
 }
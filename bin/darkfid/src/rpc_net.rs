@@ -0,0 +1,85 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use serde_json::{json, Value};
+
+use darkfi::rpc::registry::{MethodRegistry, MethodResult};
+
+use super::Darkfid;
+
+pub fn register(methods: &mut MethodRegistry<Darkfid>) {
+    methods.method(
+        "net.get_bans",
+        "Lists the currently banned hosts and TLS keys of the sync and \
+         consensus P2P networks, as `{\"sync\": [...], \"consensus\": [...]}` \
+         with the `target`, `until` and `reason` of each ban.",
+        &[],
+        |d, ()| async move { d.net_get_bans().await },
+    );
+
+    methods.method(
+        "net.ban",
+        "Bans a host or hex-encoded TLS key on both P2P networks, for the \
+         given number of seconds or the configured ban duration, and \
+         disconnects matching peers.",
+        &["target", "seconds"],
+        |d, (target, seconds)| async move { d.net_ban(target, seconds).await },
+    );
+
+    methods.method(
+        "net.unban",
+        "Lifts the ban of a host or hex-encoded TLS key on both P2P networks. \
+         Returns `false` if it wasn't banned on either of them.",
+        &["target"],
+        |d, (target,)| async move { d.net_unban(target).await },
+    );
+}
+
+impl Darkfid {
+    pub async fn net_get_bans(&self) -> MethodResult<Value> {
+        let sync = match &self.sync_p2p {
+            Some(p2p) => json!(p2p.banlist().list().await),
+            None => json!([]),
+        };
+
+        let consensus = match &self.consensus_p2p {
+            Some(p2p) => json!(p2p.banlist().list().await),
+            None => json!([]),
+        };
+
+        Ok(json!({"sync": sync, "consensus": consensus}))
+    }
+
+    pub async fn net_ban(&self, target: String, seconds: Option<u64>) -> MethodResult<bool> {
+        for p2p in self.sync_p2p.iter().chain(self.consensus_p2p.iter()) {
+            let seconds = seconds.unwrap_or(p2p.settings().ban_seconds);
+            p2p.ban(&target, seconds, "Banned over RPC").await;
+        }
+
+        Ok(true)
+    }
+
+    pub async fn net_unban(&self, target: String) -> MethodResult<bool> {
+        let mut unbanned = false;
+        for p2p in self.sync_p2p.iter().chain(self.consensus_p2p.iter()) {
+            unbanned |= p2p.unban(&target).await;
+        }
+
+        Ok(unbanned)
+    }
+}
@@ -18,40 +18,56 @@
 
 use darkfi_serial::{deserialize, serialize};
 use log::{error, warn};
-use serde_json::{json, Value};
+use serde_json::Value;
 
 use darkfi::{
-    rpc::jsonrpc::{ErrorCode::InvalidParams, JsonError, JsonResponse, JsonResult},
+    rpc::registry::{MethodRegistry, MethodResult},
     tx::Transaction,
 };
 
 use super::Darkfid;
-use crate::{server_error, RpcError};
+use crate::RpcError;
+
+pub fn register(methods: &mut MethodRegistry<Darkfid>) {
+    methods.method(
+        "tx.simulate",
+        "Simulate a network state transition with the given base58-encoded \
+         transaction. Returns a report of the verification, where `valid` tells \
+         if the transaction passed, and `error` why it was rejected otherwise. \
+         For every executed call, the report holds the ZK proofs requested by \
+         its metadata and their verification results, the signing public keys \
+         and signature verification result, the outcome of each contract section \
+         (contract error code and message, logs, gas used and fee paid), and \
+         the size of the produced state update.",
+        &["tx"],
+        |d, (tx,)| async move { d.tx_simulate(tx).await },
+    );
+
+    methods.method(
+        "tx.broadcast",
+        "Broadcast a given base58-encoded transaction to the P2P network. \
+         The function will first simulate the state transition in order to \
+         see if the transaction is actually valid, and in turn it will return \
+         an error if this is the case. Otherwise, the transaction ID will be \
+         returned.",
+        &["tx"],
+        |d, (tx,)| async move { d.tx_broadcast(tx).await },
+    );
+}
 
 impl Darkfid {
-    // RPCAPI:
-    // Simulate a network state transition with the given transaction.
-    // Returns `true` if the transaction is valid, otherwise, a corresponding
-    // error.
-    //
-    // --> {"jsonrpc": "2.0", "method": "tx.simulate", "params": ["base58encodedTX"], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": true, "id": 1}
-    pub async fn tx_simulate(&self, id: Value, params: &[Value]) -> JsonResult {
-        if params.len() != 1 || !params[0].is_string() {
-            return JsonError::new(InvalidParams, None, id).into()
-        }
-
+    pub async fn tx_simulate(&self, tx: String) -> MethodResult<Value> {
         if !(*self.synced.lock().await) {
             error!("[RPC] tx.simulate: Blockchain is not synced");
-            return server_error(RpcError::NotSynced, id, None)
+            return Err(RpcError::NotSynced.into())
         }
 
         // Try to deserialize the transaction
-        let tx_bytes = match bs58::decode(params[0].as_str().unwrap().trim()).into_vec() {
+        let tx_bytes = match bs58::decode(tx.trim()).into_vec() {
             Ok(v) => v,
             Err(e) => {
                 error!("[RPC] tx.simulate: Failed decoding base58 transaction: {}", e);
-                return server_error(RpcError::ParseError, id, None)
+                return Err(RpcError::ParseError.into())
             }
         };
 
@@ -59,43 +75,39 @@ impl Darkfid {
             Ok(v) => v,
             Err(e) => {
                 error!("[RPC] tx.simulate: Failed deserializing bytes into Transaction: {}", e);
-                return server_error(RpcError::ParseError, id, None)
+                return Err(RpcError::ParseError.into())
             }
         };
 
         // Simulate state transition
-        if let Err(e) = self.validator_state.read().await.verify_transactions(&[tx], false).await {
-            error!("[RPC] tx.broadcast: Failed to validate state transition: {}", e);
-            return server_error(RpcError::TxSimulationFail, id, None)
+        let validator_state = self.validator_state.read().await;
+        let report = match validator_state.verify_transactions_report(&[tx], false).await {
+            Ok((_, mut reports)) => reports.pop().unwrap(),
+            Err(e) => {
+                error!("[RPC] tx.simulate: Failed to validate state transition: {}", e);
+                return Err(RpcError::TxSimulationFail.into())
+            }
         };
 
-        JsonResponse::new(json!(true), id).into()
-    }
-
-    // RPCAPI:
-    // Broadcast a given transaction to the P2P network.
-    // The function will first simulate the state transition in order to see
-    // if the transaction is actually valid, and in turn it will return an
-    // error if this is the case. Otherwise, a transaction ID will be returned.
-    //
-    // --> {"jsonrpc": "2.0", "method": "tx.broadcast", "params": ["base58encodedTX"], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": "txID...", "id": 1}
-    pub async fn tx_broadcast(&self, id: Value, params: &[Value]) -> JsonResult {
-        if params.len() != 1 || !params[0].is_string() {
-            return JsonError::new(InvalidParams, None, id).into()
+        if let Some(e) = &report.error {
+            error!("[RPC] tx.simulate: Transaction failed state transition: {}", e);
         }
 
+        Ok(report.to_json())
+    }
+
+    pub async fn tx_broadcast(&self, tx: String) -> MethodResult<String> {
         if !(*self.synced.lock().await) {
             error!("[RPC] tx.transfer: Blockchain is not synced");
-            return server_error(RpcError::NotSynced, id, None)
+            return Err(RpcError::NotSynced.into())
         }
 
         // Try to deserialize the transaction
-        let tx_bytes = match bs58::decode(params[0].as_str().unwrap().trim()).into_vec() {
+        let tx_bytes = match bs58::decode(tx.trim()).into_vec() {
             Ok(v) => v,
             Err(e) => {
                 error!("[RPC] tx.broadcast: Failed decoding base58 transaction: {}", e);
-                return server_error(RpcError::ParseError, id, None)
+                return Err(RpcError::ParseError.into())
             }
         };
 
@@ -103,38 +115,43 @@ impl Darkfid {
             Ok(v) => v,
             Err(e) => {
                 error!("[RPC] tx.broadcast: Failed deserializing bytes into Transaction: {}", e);
-                return server_error(RpcError::ParseError, id, None)
+                return Err(RpcError::ParseError.into())
             }
         };
 
         if self.consensus_p2p.is_some() {
             // Consider we're participating in consensus here?
             // The append_tx function performs a state transition check.
-            if !self.validator_state.write().await.append_tx(tx.clone()).await {
+            if !self.validator_state.write().await.append_tx(tx.clone()).await.unwrap_or(false) {
                 error!("[RPC] tx.broadcast: Failed to append transaction to mempool");
-                return server_error(RpcError::TxBroadcastFail, id, None)
+                return Err(RpcError::TxBroadcastFail.into())
             }
         } else {
             // We'll perform the state transition check here.
-            if let Err(e) =
-                self.validator_state.read().await.verify_transactions(&[tx.clone()], false).await
+            match self.validator_state.read().await.verify_transactions(&[tx.clone()], false).await
             {
-                error!("[RPC] tx.broadcast: Failed to validate state transition: {}", e);
-                return server_error(RpcError::TxSimulationFail, id, None)
+                Ok(erroneous_txs) if erroneous_txs.is_empty() => {}
+                Ok(_) => {
+                    error!("[RPC] tx.broadcast: Transaction failed state transition");
+                    return Err(RpcError::TxSimulationFail.into())
+                }
+                Err(e) => {
+                    error!("[RPC] tx.broadcast: Failed to validate state transition: {}", e);
+                    return Err(RpcError::TxSimulationFail.into())
+                }
             };
         }
 
         if let Some(sync_p2p) = &self.sync_p2p {
             if let Err(e) = sync_p2p.broadcast(tx.clone()).await {
                 error!("[RPC] tx.broadcast: Failed broadcasting transaction: {}", e);
-                return server_error(RpcError::TxBroadcastFail, id, None)
+                return Err(RpcError::TxBroadcastFail.into())
             }
         } else {
             warn!("[RPC] tx.broadcast: No sync P2P network, not broadcasting transaction.");
-            return server_error(RpcError::TxBroadcastFail, id, None)
+            return Err(RpcError::TxBroadcastFail.into())
         }
 
-        let tx_hash = blake3::hash(&serialize(&tx)).to_hex().as_str().to_string();
-        JsonResponse::new(json!(tx_hash), id).into()
+        Ok(blake3::hash(&serialize(&tx)).to_hex().as_str().to_string())
     }
 }
@@ -21,40 +21,55 @@ use serde_json::{json, Value};
 use sqlx::Row;
 
 use darkfi::{
-    rpc::jsonrpc::{
-        ErrorCode::{InternalError, InvalidParams, ParseError},
-        JsonError, JsonResponse, JsonResult,
+    rpc::{
+        jsonrpc::ErrorCode::{InternalError, InvalidParams, ParseError},
+        registry::{MethodRegistry, MethodResult, RawParams},
     },
     wallet::walletdb::QueryType,
 };
 
-use super::{error::RpcError, server_error, Darkfid};
+use super::{error::RpcError, Darkfid};
+
+pub fn register(methods: &mut MethodRegistry<Darkfid>) {
+    methods.method(
+        "wallet.query_row_single",
+        "Attempts to query for a single row in a given table. The first param \
+         is the SQL query, followed by pairs of `column_type` and `column_name` \
+         params so we know how to decode the SQL data. The `column_type` field \
+         is a type available in the `WalletDb` API as an enum called \
+         `QueryType`. This function will return the values of the first row it \
+         finds. If a row is not found, the returned result will be a JSON-RPC \
+         error. NOTE: This is obviously vulnerable to SQL injection.",
+        &["params"],
+        |d, RawParams(params)| async move { d.wallet_query_row_single(&params).await },
+    );
+
+    methods.method(
+        "wallet.query_row_multi",
+        "Attempts to query for all available rows in a given table. The params \
+         are the same as in `wallet.query_row_single`. If there are any values \
+         found, they will be returned in a paired array. If not, an empty array \
+         will be returned.",
+        &["params"],
+        |d, RawParams(params)| async move { d.wallet_query_row_multi(&params).await },
+    );
+
+    methods.method(
+        "wallet.exec_sql",
+        "Executes an arbitrary SQL query on the wallet, and returns `true` on \
+         success. Values to bind can optionally follow the query in pairs of \
+         `column_type` and value, like in `wallet.query_row_single`.",
+        &["params"],
+        |d, RawParams(params)| async move { d.wallet_exec_sql(&params).await },
+    );
+}
 
 impl Darkfid {
-    // RPCAPI:
-    // Attempts to query for a single row in a given table.
-    // The parameters given contain paired metadata so we know how to decode the SQL data.
-    // An example of `params` is as such:
-    // ```
-    // params[0] -> "sql query"
-    // params[1] -> column_type
-    // params[2] -> "column_name"
-    // ...
-    // params[n-1] -> column_type
-    // params[n] -> "column_name"
-    // ```
-    // This function will fetch the first row it finds, if any. The `column_type` field
-    // is a type available in the `WalletDb` API as an enum called `QueryType`. If a row
-    // is not found, the returned result will be a JSON-RPC error.
-    // NOTE: This is obviously vulnerable to SQL injection. Open to interesting solutions.
-    //
-    // --> {"jsonrpc": "2.0", "method": "wallet.query_row_single", "params": [...], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": ["va", "lu", "es", ...], "id": 1}
-    pub async fn wallet_query_row_single(&self, id: Value, params: &[Value]) -> JsonResult {
+    pub async fn wallet_query_row_single(&self, params: &[Value]) -> MethodResult<Vec<Value>> {
         // We need at least 3 params for something we want to fetch, and we want them in pairs.
         // Also the first param should be a String
         if params.len() < 3 || params[1..].len() % 2 != 0 || !params[0].is_string() {
-            return JsonError::new(InvalidParams, None, id).into()
+            return Err(InvalidParams.into())
         }
 
         // The remaining pairs should be typed properly too
@@ -62,12 +77,12 @@ impl Darkfid {
         let mut names: Vec<&str> = vec![];
         for pair in params[1..].chunks(2) {
             if !pair[0].is_u64() || !pair[1].is_string() {
-                return JsonError::new(InvalidParams, None, id).into()
+                return Err(InvalidParams.into())
             }
 
             let typ = pair[0].as_u64().unwrap();
             if typ >= QueryType::Last as u64 {
-                return JsonError::new(InvalidParams, None, id).into()
+                return Err(InvalidParams.into())
             }
 
             types.push((typ as u8).into());
@@ -79,7 +94,7 @@ impl Darkfid {
             Ok(v) => v,
             Err(e) => {
                 error!("[RPC] wallet.query_row_single: Failed to acquire wallet connection: {}", e);
-                return JsonError::new(InternalError, None, id).into()
+                return Err(InternalError.into())
             }
         };
 
@@ -97,14 +112,14 @@ impl Darkfid {
                 QueryType::Integer => {
                     let Some(ref row) = row else {
                         error!("[RPC] wallet.query_row_single: Got None for QueryType::Integer");
-                        return server_error(RpcError::NoRowsFoundInWallet, id, None)
+                        return Err(RpcError::NoRowsFoundInWallet.into())
                     };
 
                     let value: i32 = match row.try_get(col) {
                         Ok(v) => v,
                         Err(e) => {
                             error!("[RPC] wallet.query_row_single: {}", e);
-                            return JsonError::new(ParseError, None, id).into()
+                            return Err(ParseError.into())
                         }
                     };
 
@@ -115,14 +130,14 @@ impl Darkfid {
                 QueryType::Blob => {
                     let Some(ref row) = row else {
                         error!("[RPC] wallet.query_row_single: Got None for QueryType::Blob");
-                        return server_error(RpcError::NoRowsFoundInWallet, id, None)
+                        return Err(RpcError::NoRowsFoundInWallet.into())
                     };
 
                     let value: Vec<u8> = match row.try_get(col) {
                         Ok(v) => v,
                         Err(e) => {
                             error!("[RPC] wallet.query_row_single: {}", e);
-                            return JsonError::new(ParseError, None, id).into()
+                            return Err(ParseError.into())
                         }
                     };
 
@@ -140,7 +155,7 @@ impl Darkfid {
                         Ok(v) => v,
                         Err(e) => {
                             error!("[RPC] wallet.query_row_single: {}", e);
-                            return JsonError::new(ParseError, None, id).into()
+                            return Err(ParseError.into())
                         }
                     };
 
@@ -158,7 +173,7 @@ impl Darkfid {
                         Ok(v) => v,
                         Err(e) => {
                             error!("[RPC] wallet.query_row_single: {}", e);
-                            return JsonError::new(ParseError, None, id).into()
+                            return Err(ParseError.into())
                         }
                     };
 
@@ -170,23 +185,14 @@ impl Darkfid {
             }
         }
 
-        JsonResponse::new(json!(ret), id).into()
+        Ok(ret)
     }
 
-    // RPCAPI:
-    // Attempts to query for all available rows in a given table.
-    // The parameters given contain paired metadata so we know how to decode the SQL data.
-    // They're the same as above in `wallet.query_row_single`.
-    // If there are any values found, they will be returned in a paired array. If not, an
-    // empty array will be returned.
-    //
-    // --> {"jsonrpc": "2.0", "method": "wallet.query_row_multi", "params": [...], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": [["va", "lu"], ["es", "es"], ...], "id": 1}
-    pub async fn wallet_query_row_multi(&self, id: Value, params: &[Value]) -> JsonResult {
+    pub async fn wallet_query_row_multi(&self, params: &[Value]) -> MethodResult<Vec<Vec<Value>>> {
         // We need at least 3 params for something we want to fetch, and we want them in pairs.
         // Also the first param (the query) should be a String.
         if params.len() < 3 || params[1..].len() % 2 != 0 || !params[0].is_string() {
-            return JsonError::new(InvalidParams, None, id).into()
+            return Err(InvalidParams.into())
         }
 
         // The remaining pairs should be typed properly too
@@ -194,12 +200,12 @@ impl Darkfid {
         let mut names: Vec<&str> = vec![];
         for pair in params[1..].chunks(2) {
             if !pair[0].is_u64() || !pair[1].is_string() {
-                return JsonError::new(InvalidParams, None, id).into()
+                return Err(InvalidParams.into())
             }
 
             let typ = pair[0].as_u64().unwrap();
             if typ >= QueryType::Last as u64 {
-                return JsonError::new(InvalidParams, None, id).into()
+                return Err(InvalidParams.into())
             }
 
             types.push((typ as u8).into());
@@ -211,7 +217,7 @@ impl Darkfid {
             Ok(v) => v,
             Err(e) => {
                 error!("[RPC] wallet.query_row_multi: Failed to acquire wallet connection: {}", e);
-                return JsonError::new(InternalError, None, id).into()
+                return Err(InternalError.into())
             }
         };
 
@@ -220,7 +226,7 @@ impl Darkfid {
             Ok(v) => v,
             Err(e) => {
                 error!("[RPC] wallet.query_row_multi: Failed to execute SQL query: {}", e);
-                return JsonError::new(InternalError, None, id).into()
+                return Err(InternalError.into())
             }
         };
 
@@ -238,7 +244,7 @@ impl Darkfid {
                             Ok(v) => v,
                             Err(e) => {
                                 error!("[RPC] wallet.query_row_multi: {}", e);
-                                return JsonError::new(ParseError, None, id).into()
+                                return Err(ParseError.into())
                             }
                         };
 
@@ -250,7 +256,7 @@ impl Darkfid {
                             Ok(v) => v,
                             Err(e) => {
                                 error!("[RPC] wallet.query_row_multi: {}", e);
-                                return JsonError::new(ParseError, None, id).into()
+                                return Err(ParseError.into())
                             }
                         };
 
@@ -282,22 +288,16 @@ impl Darkfid {
             ret.push(row_ret);
         }
 
-        JsonResponse::new(json!(ret), id).into()
+        Ok(ret)
     }
 
-    // RPCAPI:
-    // Executes an arbitrary SQL query on the wallet, and returns `true` on success.
-    // `params[1..]` can optionally be provided in pairs like in `wallet.query_row_single`.
-    //
-    // --> {"jsonrpc": "2.0", "method": "wallet.exec_sql", "params": ["CREATE TABLE ..."], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": true, "id": 1}
-    pub async fn wallet_exec_sql(&self, id: Value, params: &[Value]) -> JsonResult {
+    pub async fn wallet_exec_sql(&self, params: &[Value]) -> MethodResult<bool> {
         if params.is_empty() || !params[0].is_string() {
-            return JsonError::new(InvalidParams, None, id).into()
+            return Err(InvalidParams.into())
         }
 
         if params.len() > 1 && params[1..].len() % 2 != 0 {
-            return JsonError::new(InvalidParams, None, id).into()
+            return Err(InvalidParams.into())
         }
 
         let query = params[0].as_str().unwrap();
@@ -306,7 +306,7 @@ impl Darkfid {
 
         for pair in params[1..].chunks(2) {
             if !pair[0].is_u64() || pair[0].as_u64().unwrap() >= QueryType::Last as u64 {
-                return JsonError::new(InvalidParams, None, id).into()
+                return Err(InvalidParams.into())
             }
 
             let typ = (pair[0].as_u64().unwrap() as u8).into();
@@ -316,7 +316,7 @@ impl Darkfid {
                         Ok(v) => v,
                         Err(e) => {
                             error!("[RPC] wallet.exec_sql: Failed casting value to i32: {}", e);
-                            return JsonError::new(ParseError, None, id).into()
+                            return Err(ParseError.into())
                         }
                     };
 
@@ -327,7 +327,7 @@ impl Darkfid {
                         Ok(v) => v,
                         Err(e) => {
                             error!("[RPC] wallet.exec_sql: Failed casting value to Vec<u8>: {}", e);
-                            return JsonError::new(ParseError, None, id).into()
+                            return Err(ParseError.into())
                         }
                     };
 
@@ -342,7 +342,7 @@ impl Darkfid {
                                 "[RPC] wallet.exec_sql: Failed casting value to Option<i32>: {}",
                                 e
                             );
-                            return JsonError::new(ParseError, None, id).into()
+                            return Err(ParseError.into())
                         }
                     };
 
@@ -354,14 +354,14 @@ impl Darkfid {
                         Ok(v) => v,
                         Err(e) => {
                             error!("[RPC] wallet.exec_sql: Failed casting value to Option<Vec<u8>>: {}", e);
-                            return JsonError::new(ParseError, None, id).into()
+                            return Err(ParseError.into())
                         }
                     };
 
                     query = query.bind(val);
                 }
 
-                _ => return JsonError::new(InvalidParams, None, id).into(),
+                _ => return Err(InvalidParams.into()),
             }
         }
 
@@ -370,15 +370,15 @@ impl Darkfid {
             Ok(v) => v,
             Err(e) => {
                 error!("[RPC] wallet.exec_sql: Failed to acquire wallet connection: {}", e);
-                return JsonError::new(InternalError, None, id).into()
+                return Err(InternalError.into())
             }
         };
 
         if let Err(e) = query.execute(&mut conn).await {
             error!("[RPC] wallet.exec_sql: Failed to execute sql query: {}", e);
-            return JsonError::new(InternalError, None, id).into()
+            return Err(InternalError.into())
         };
 
-        JsonResponse::new(json!(true), id).into()
+        Ok(true)
     }
 }
@@ -20,6 +20,7 @@ use std::{path::Path, process::exit};
 use async_std::{fs::File, io::WriteExt};
 use darkfi::{util::parse::decode_base10, Result};
 use darkfi_sdk::crypto::TokenId;
+use serde_json::Value;
 
 pub fn parse_value_pair(s: &str) -> Result<(u64, u64)> {
     let v: Vec<&str> = s.split(':').collect();
@@ -60,6 +61,70 @@ pub fn parse_token_pair(s: &str) -> Result<(TokenId, TokenId)> {
     Ok((tok0.unwrap(), tok1.unwrap()))
 }
 
+/// Describe the result of a verification step in a simulation report
+fn check_status(check: &Value) -> String {
+    match check["verified"].as_bool() {
+        Some(true) => "verified".to_string(),
+        Some(false) => format!("failed: {}", check["error"].as_str().unwrap_or("unknown error")),
+        None => "not verified".to_string(),
+    }
+}
+
+/// Pretty-print a transaction simulation report returned by `tx.simulate`
+pub fn print_simulation_report(report: &Value) {
+    println!("Transaction ID: {}", report["tx_hash"].as_str().unwrap_or_default());
+    match report["error"].as_str() {
+        None => println!("State: valid"),
+        Some(e) => println!("State: invalid ({})", e),
+    }
+    println!("Fee: {} (paid by calls: {})", report["fee"], report["fee_paid"]);
+    println!("Gas used: {}", report["gas_used"]);
+
+    let calls = report["calls"].as_array().cloned().unwrap_or_default();
+    for (i, call) in calls.iter().enumerate() {
+        println!();
+        println!("Call {}: {}", i, call["contract_id"].as_str().unwrap_or_default());
+
+        for section in call["sections"].as_array().cloned().unwrap_or_default() {
+            let name = section["section"].as_str().unwrap_or_default();
+            match section["error"].as_str() {
+                None => println!("  {}: ok, {} gas", name, section["gas_used"]),
+                Some(e) => println!("  {}: failed, {} gas: {}", name, section["gas_used"], e),
+            }
+            if section["code"].is_i64() {
+                println!("    Error code: {}", section["code"]);
+            }
+            for log in section["logs"].as_array().cloned().unwrap_or_default() {
+                println!("    Log: {}", log.as_str().unwrap_or_default());
+            }
+        }
+
+        for proof in call["proofs"].as_array().cloned().unwrap_or_default() {
+            println!(
+                "  ZK proof {} ({} public inputs): {}",
+                proof["namespace"].as_str().unwrap_or_default(),
+                proof["public_inputs"],
+                check_status(&proof)
+            );
+        }
+
+        let signatures = &call["signatures"];
+        let pubkeys = signatures["public_keys"].as_array().cloned().unwrap_or_default();
+        println!("  Signatures ({} public keys): {}", pubkeys.len(), check_status(signatures));
+        for pubkey in pubkeys {
+            println!("    {}", pubkey.as_str().unwrap_or_default());
+        }
+
+        if let Some(size) = call["update_size"].as_u64() {
+            println!("  State update: {} bytes", size);
+        }
+
+        if let Some(e) = call["error"].as_str() {
+            println!("  Error: {}", e);
+        }
+    }
+}
+
 /// Fun police go away
 pub async fn kaching() -> Result<()> {
     #[cfg(feature = "play")]
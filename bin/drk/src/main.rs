@@ -41,11 +41,17 @@ use url::Url;
 
 use darkfi::{
     cli_desc,
-    rpc::{client::RpcClient, jsonrpc::JsonRequest},
+    rpc::{
+        auth::{read_cookie, RpcCredentials},
+        client::RpcClient,
+        jsonrpc::JsonRequest,
+    },
     util::{
         cli::{get_log_config, get_log_level},
         parse::encode_base10,
+        path::expand_path,
     },
+    zk::KeyCache,
 };
 
 /// Airdrop methods
@@ -54,6 +60,9 @@ mod rpc_airdrop;
 /// Payment methods
 mod rpc_transfer;
 
+/// Token methods
+mod rpc_token;
+
 /// Swap methods
 mod rpc_swap;
 use rpc_swap::PartialSwapData;
@@ -66,7 +75,7 @@ mod rpc_blockchain;
 
 /// CLI utility functions
 mod cli_util;
-use cli_util::{parse_token_pair, parse_value_pair};
+use cli_util::{parse_token_pair, parse_value_pair, print_simulation_report};
 
 /// Wallet functionality related to DAO
 mod wallet_dao;
@@ -86,6 +95,14 @@ struct Args {
     /// darkfid JSON-RPC endpoint
     endpoint: Url,
 
+    #[arg(long, default_value = "~/.config/darkfi/zk_key_cache")]
+    /// Path to the ZK proof key cache directory
+    key_cache: String,
+
+    #[arg(long, default_value = "~/.config/darkfi/darkfid_rpc.cookie")]
+    /// Path to the darkfid JSON-RPC cookie file, used if it exists
+    rpc_cookie: String,
+
     #[command(subcommand)]
     command: Subcmd,
 }
@@ -187,6 +204,9 @@ enum Subcmd {
     /// Read a transaction from stdin and broadcast it
     Broadcast,
 
+    /// Read a transaction from stdin and print how darkfid verifies it
+    Simulate,
+
     /// Subscribe to incoming blocks from darkfid
     ///
     /// This subscription will listen for incoming blocks from darkfid and look
@@ -221,6 +241,10 @@ enum Subcmd {
     /// Manage Token aliases
     #[command(subcommand)]
     Alias(AliasSubcmd),
+
+    /// Token mint operations
+    #[command(subcommand)]
+    Token(TokenSubcmd),
 }
 
 #[derive(Subcommand)]
@@ -384,14 +408,50 @@ enum AliasSubcmd {
     },
 }
 
+#[derive(Subcommand)]
+enum TokenSubcmd {
+    /// Generate a new mint authority and print the ID of the token it mints
+    GenerateMint,
+
+    /// List the mint authorities in the wallet
+    List,
+
+    /// Mint tokens using a mint authority from the wallet
+    Mint {
+        /// Token ID to mint
+        token: String,
+
+        /// Amount to mint
+        amount: String,
+
+        /// Recipient of the minted tokens
+        recipient: String,
+
+        #[clap(long)]
+        /// Fix the token supply so it can't be minted anymore
+        fixed_supply: bool,
+    },
+}
+
 pub struct Drk {
     pub rpc_client: RpcClient,
+    pub rpc_credentials: RpcCredentials,
+    pub key_cache: KeyCache,
 }
 
 impl Drk {
-    async fn new(endpoint: Url) -> Result<Self> {
-        let rpc_client = RpcClient::new(endpoint).await?;
-        Ok(Self { rpc_client })
+    async fn new(endpoint: Url, key_cache: &str, rpc_cookie: &str) -> Result<Self> {
+        // Without the cookie, only the public methods of darkfid are available
+        let rpc_cookie = expand_path(rpc_cookie)?;
+        let rpc_credentials = if rpc_cookie.exists() {
+            RpcCredentials::token(read_cookie(&rpc_cookie)?)
+        } else {
+            RpcCredentials::default()
+        };
+
+        let rpc_client = RpcClient::new_with_credentials(endpoint, rpc_credentials.clone()).await?;
+        let key_cache = KeyCache::new(Some(expand_path(key_cache)?))?;
+        Ok(Self { rpc_client, rpc_credentials, key_cache })
     }
 
     async fn ping(&self) -> Result<()> {
@@ -417,7 +477,7 @@ async fn main() -> Result<()> {
 
     match args.command {
         Subcmd::Ping => {
-            let drk = Drk::new(args.endpoint).await?;
+            let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
             drk.ping().await.with_context(|| "Failed to ping darkfid RPC endpoint")?;
 
             Ok(())
@@ -453,7 +513,7 @@ async fn main() -> Result<()> {
                 exit(2);
             }
 
-            let drk = Drk::new(args.endpoint).await?;
+            let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
 
             if initialize {
                 drk.initialize_money().await?;
@@ -614,7 +674,7 @@ async fn main() -> Result<()> {
             };
 
             let coin = Coin::from(elem);
-            let drk = Drk::new(args.endpoint).await?;
+            let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
             drk.unspend_coin(&coin).await.with_context(|| "Failed to mark coin as unspent")?;
 
             Ok(())
@@ -622,7 +682,7 @@ async fn main() -> Result<()> {
 
         Subcmd::Airdrop { faucet_endpoint, amount, token, address } => {
             let amount = f64::from_str(&amount).with_context(|| "Invalid amount")?;
-            let drk = Drk::new(args.endpoint).await?;
+            let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
             let token_id = drk.get_token(token).await.with_context(|| "Invalid Token ID")?;
 
             let address = match address {
@@ -645,7 +705,7 @@ async fn main() -> Result<()> {
         Subcmd::Transfer { amount, token, recipient, dao, dao_bulla } => {
             let _ = f64::from_str(&amount).with_context(|| "Invalid amount")?;
             let rcpt = PublicKey::from_str(&recipient).with_context(|| "Invalid recipient")?;
-            let drk = Drk::new(args.endpoint).await?;
+            let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
             let token_id = drk.get_token(token).await.with_context(|| "Invalid Token ID")?;
 
             let tx = drk
@@ -659,7 +719,7 @@ async fn main() -> Result<()> {
         }
 
         Subcmd::Otc(cmd) => {
-            let drk = Drk::new(args.endpoint).await?;
+            let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
 
             match cmd {
                 OtcSubcmd::Init { value_pair, token_pair } => {
@@ -731,7 +791,7 @@ async fn main() -> Result<()> {
             let bytes = bs58::decode(&buf.trim()).into_vec()?;
             let tx = deserialize(&bytes)?;
 
-            let drk = Drk::new(args.endpoint).await?;
+            let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
 
             let txid =
                 drk.broadcast_tx(&tx).await.with_context(|| "Failed to broadcast transaction")?;
@@ -741,8 +801,25 @@ async fn main() -> Result<()> {
             Ok(())
         }
 
+        Subcmd::Simulate => {
+            eprintln!("Reading transaction from stdin...");
+            let mut buf = String::new();
+            stdin().read_to_string(&mut buf)?;
+            let bytes = bs58::decode(&buf.trim()).into_vec()?;
+            let tx = deserialize(&bytes)?;
+
+            let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
+
+            let report =
+                drk.simulate_tx(&tx).await.with_context(|| "Failed to simulate tx")?;
+
+            print_simulation_report(&report);
+
+            Ok(())
+        }
+
         Subcmd::Subscribe => {
-            let drk = Drk::new(args.endpoint.clone()).await?;
+            let drk = Drk::new(args.endpoint.clone(), &args.key_cache, &args.rpc_cookie).await?;
 
             drk.subscribe_blocks(args.endpoint)
                 .await
@@ -752,7 +829,7 @@ async fn main() -> Result<()> {
         }
 
         Subcmd::Scan { reset, list, checkpoint } => {
-            let drk = Drk::new(args.endpoint).await?;
+            let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
 
             if reset {
                 eprintln!("Reset requested.");
@@ -797,7 +874,7 @@ async fn main() -> Result<()> {
                 let approval_ratio_base = 100_u64;
                 let approval_ratio_quot = (approval_ratio * approval_ratio_base as f64) as u64;
 
-                let drk = Drk::new(args.endpoint).await?;
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
                 let gov_token_id =
                     drk.get_token(gov_token_id).await.with_context(|| "Invalid Token ID")?;
 
@@ -836,7 +913,7 @@ async fn main() -> Result<()> {
                 let bytes = bs58::decode(&buf.trim()).into_vec()?;
                 let dao_params: DaoParams = deserialize(&bytes)?;
 
-                let drk = Drk::new(args.endpoint).await?;
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
 
                 drk.import_dao(dao_name, dao_params)
                     .await
@@ -846,7 +923,7 @@ async fn main() -> Result<()> {
             }
 
             DaoSubcmd::List { dao_id } => {
-                let drk = Drk::new(args.endpoint).await?;
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
 
                 drk.dao_list(dao_id).await.with_context(|| "Failed to list DAO")?;
 
@@ -854,7 +931,7 @@ async fn main() -> Result<()> {
             }
 
             DaoSubcmd::Balance { dao_id } => {
-                let drk = Drk::new(args.endpoint).await?;
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
 
                 let balmap =
                     drk.dao_balance(dao_id).await.with_context(|| "Failed to fetch DAO balance")?;
@@ -888,7 +965,7 @@ async fn main() -> Result<()> {
             }
 
             DaoSubcmd::Mint { dao_id } => {
-                let drk = Drk::new(args.endpoint).await?;
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
 
                 let tx = drk.dao_mint(dao_id).await.with_context(|| "Failed to mint DAO")?;
                 println!("{}", bs58::encode(&serialize(&tx)).into_string());
@@ -899,7 +976,7 @@ async fn main() -> Result<()> {
                 let _ = f64::from_str(&amount).with_context(|| "Invalid amount")?;
                 let amount = decode_base10(&amount, 8, true)?;
                 let rcpt = PublicKey::from_str(&recipient).with_context(|| "Invalid recipient")?;
-                let drk = Drk::new(args.endpoint).await?;
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
                 let token_id = drk.get_token(token_id).await.with_context(|| "Invalid Token ID")?;
 
                 let tx = drk
@@ -912,7 +989,7 @@ async fn main() -> Result<()> {
             }
 
             DaoSubcmd::Proposals { dao_id } => {
-                let drk = Drk::new(args.endpoint).await?;
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
 
                 let proposals = drk.get_dao_proposals(dao_id).await?;
 
@@ -924,7 +1001,7 @@ async fn main() -> Result<()> {
             }
 
             DaoSubcmd::Proposal { dao_id, proposal_id } => {
-                let drk = Drk::new(args.endpoint).await?;
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
 
                 let proposals = drk.get_dao_proposals(dao_id).await?;
                 let Some(proposal) = proposals.iter().find(|x| x.id == proposal_id) else {
@@ -938,7 +1015,7 @@ async fn main() -> Result<()> {
             }
 
             DaoSubcmd::Vote { dao_id, proposal_id, vote, vote_weight } => {
-                let drk = Drk::new(args.endpoint).await?;
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
 
                 let _ = f64::from_str(&vote_weight).with_context(|| "Invalid vote weight")?;
                 let weight = decode_base10(&vote_weight, 8, true)?;
@@ -962,7 +1039,7 @@ async fn main() -> Result<()> {
             }
 
             DaoSubcmd::Exec { dao_id, proposal_id } => {
-                let drk = Drk::new(args.endpoint).await?;
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
                 let dao = drk.get_dao_by_id(dao_id).await?;
                 let proposal = drk.get_dao_proposal_by_id(proposal_id).await?;
                 assert!(proposal.dao_bulla == dao.bulla());
@@ -982,7 +1059,7 @@ async fn main() -> Result<()> {
             ExplorerSubcmd::FetchTx { tx_hash } => {
                 let tx_hash = blake3::Hash::from_hex(&tx_hash)?;
 
-                let drk = Drk::new(args.endpoint).await?;
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
 
                 let tx = if let Some(tx) =
                     drk.get_tx(&tx_hash).await.with_context(|| "Failed to fetch transaction")?
@@ -1012,10 +1089,11 @@ async fn main() -> Result<()> {
                 let bytes = bs58::decode(&buf.trim()).into_vec()?;
                 let tx = deserialize(&bytes)?;
 
-                let drk = Drk::new(args.endpoint).await?;
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
 
-                let is_valid =
+                let report =
                     drk.simulate_tx(&tx).await.with_context(|| "Failed to simulate tx")?;
+                let is_valid = report["valid"].as_bool().unwrap_or(false);
 
                 println!("Transaction ID: {}", tx.hash());
                 println!("State: {}", if is_valid { "valid" } else { "invalid" });
@@ -1033,7 +1111,7 @@ async fn main() -> Result<()> {
 
                 let token_id =
                     TokenId::try_from(token.as_str()).with_context(|| "Invalid Token ID")?;
-                let drk = Drk::new(args.endpoint).await?;
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
                 drk.add_alias(alias, token_id).await?;
 
                 Ok(())
@@ -1047,7 +1125,7 @@ async fn main() -> Result<()> {
                     None => None,
                 };
 
-                let drk = Drk::new(args.endpoint).await?;
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
                 let map = drk.get_aliases(alias, token_id).await?;
 
                 // Create a prettytable with the new data:
@@ -1068,11 +1146,58 @@ async fn main() -> Result<()> {
             }
 
             AliasSubcmd::Remove { alias } => {
-                let drk = Drk::new(args.endpoint).await?;
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
                 drk.remove_alias(alias).await?;
 
                 Ok(())
             }
         },
+
+        Subcmd::Token(cmd) => match cmd {
+            TokenSubcmd::GenerateMint => {
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
+                let token_id = drk.generate_mint_authority().await?;
+                println!("Token ID: {}", token_id);
+
+                Ok(())
+            }
+
+            TokenSubcmd::List => {
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
+                let authorities = drk.get_mint_authorities().await?;
+
+                // Create a prettytable with the new data:
+                let mut table = Table::new();
+                table.set_format(*format::consts::FORMAT_NO_BORDER_LINE_SEPARATOR);
+                table.set_titles(row!["Token ID", "Frozen"]);
+                for (token_id, _, is_frozen) in authorities.iter() {
+                    table.add_row(row![token_id, is_frozen]);
+                }
+
+                if table.is_empty() {
+                    println!("No mint authorities found");
+                } else {
+                    println!("{}", table);
+                }
+
+                Ok(())
+            }
+
+            TokenSubcmd::Mint { token, amount, recipient, fixed_supply } => {
+                let _ = f64::from_str(&amount).with_context(|| "Invalid amount")?;
+                let rcpt = PublicKey::from_str(&recipient).with_context(|| "Invalid recipient")?;
+                let drk = Drk::new(args.endpoint, &args.key_cache, &args.rpc_cookie).await?;
+                let token_id = drk.get_token(token).await.with_context(|| "Invalid Token ID")?;
+
+                let tx = drk
+                    .mint_token(&amount, token_id, rcpt, fixed_supply)
+                    .await
+                    .with_context(|| "Failed to create token mint transaction")?;
+
+                println!("{}", bs58::encode(&serialize(&tx)).into_string());
+
+                Ok(())
+            }
+        },
     }
 }
@@ -31,7 +31,7 @@ use darkfi::{
 use darkfi_money_contract::client::{MONEY_INFO_COL_LAST_SCANNED_SLOT, MONEY_INFO_TABLE};
 use darkfi_sdk::crypto::ContractId;
 use darkfi_serial::{deserialize, serialize};
-use serde_json::json;
+use serde_json::{json, Value};
 use signal_hook::consts::{SIGINT, SIGQUIT, SIGTERM};
 use signal_hook_async_std::Signals;
 use url::Url;
@@ -60,7 +60,8 @@ impl Drk {
         let subscriber = Subscriber::new();
         let subscription = subscriber.clone().subscribe().await;
 
-        let rpc_client = RpcClient::new(endpoint).await?;
+        let rpc_client =
+            RpcClient::new_with_credentials(endpoint, self.rpc_credentials.clone()).await?;
 
         let req = JsonRequest::new("blockchain.subscribe_blocks", json!([]));
         task::spawn(async move { rpc_client.subscribe(req, subscriber).await.unwrap() });
@@ -187,14 +188,18 @@ impl Drk {
         Ok(txid)
     }
 
-    /// Simulate the transaction with the state machine
-    pub async fn simulate_tx(&self, tx: &Transaction) -> Result<bool> {
+    /// Simulate the transaction with the state machine, and return the
+    /// verification report
+    pub async fn simulate_tx(&self, tx: &Transaction) -> Result<Value> {
         let params = json!([bs58::encode(&serialize(tx)).into_string()]);
         let req = JsonRequest::new("tx.simulate", params);
         let rep = self.rpc_client.request(req).await?;
 
-        let is_valid = serde_json::from_value(rep)?;
-        Ok(is_valid)
+        if !rep["valid"].is_boolean() {
+            return Err(anyhow!("[simulate_tx] Unexpected response from darkfid: {}", rep))
+        }
+
+        Ok(rep)
     }
 
     /// Queries darkfid for a block with given slot
@@ -19,7 +19,7 @@
 use anyhow::{anyhow, Result};
 use darkfi::{
     tx::Transaction,
-    zk::{empty_witnesses, halo2::Field, ProvingKey, ZkCircuit},
+    zk::halo2::Field,
     zkas::ZkBinary,
 };
 use darkfi_dao_contract::{
@@ -72,12 +72,10 @@ impl Drk {
             return Err(anyhow!("DAO Mint circuit not found"));
         };
 
-        let dao_mint_zkbin = ZkBinary::decode(&dao_mint_zkbin.1)?;
-        let k = 13;
-        let dao_mint_circuit =
-            ZkCircuit::new(empty_witnesses(&dao_mint_zkbin), dao_mint_zkbin.clone());
+        let dao_mint_bincode = &dao_mint_zkbin.1;
+        let dao_mint_zkbin = ZkBinary::decode(dao_mint_bincode)?;
         eprintln!("Creating DAO Mint proving key");
-        let dao_mint_pk = ProvingKey::build(k, &dao_mint_circuit);
+        let dao_mint_pk = self.key_cache.proving_key(dao_mint_bincode)?;
 
         let (params, proofs) =
             dao_client::make_mint_call(&dao_info, &dao.secret_key, &dao_mint_zkbin, &dao_mint_pk)?;
@@ -86,7 +84,7 @@ impl Drk {
         params.encode(&mut data)?;
         let calls = vec![ContractCall { contract_id: *DAO_CONTRACT_ID, data }];
         let proofs = vec![proofs];
-        let mut tx = Transaction { calls, proofs, signatures: vec![] };
+        let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
         let sigs = tx.create_sigs(&mut OsRng, &[dao.secret_key])?;
         tx.signatures = vec![sigs];
 
@@ -159,19 +157,15 @@ impl Drk {
             return Err(anyhow!("Propose Main circuit not found"))
         };
 
-        let propose_burn_zkbin = ZkBinary::decode(&propose_burn_zkbin.1)?;
-        let propose_main_zkbin = ZkBinary::decode(&propose_main_zkbin.1)?;
-
-        let k = 13;
-        let propose_burn_circuit =
-            ZkCircuit::new(empty_witnesses(&propose_burn_zkbin), propose_burn_zkbin.clone());
-        let propose_main_circuit =
-            ZkCircuit::new(empty_witnesses(&propose_main_zkbin), propose_main_zkbin.clone());
+        let propose_burn_bincode = &propose_burn_zkbin.1;
+        let propose_burn_zkbin = ZkBinary::decode(propose_burn_bincode)?;
+        let propose_main_bincode = &propose_main_zkbin.1;
+        let propose_main_zkbin = ZkBinary::decode(propose_main_bincode)?;
 
         eprintln!("Creating Propose Burn circuit proving key");
-        let propose_burn_pk = ProvingKey::build(k, &propose_burn_circuit);
+        let propose_burn_pk = self.key_cache.proving_key(propose_burn_bincode)?;
         eprintln!("Creating Propose Main circuit proving key");
-        let propose_main_pk = ProvingKey::build(k, &propose_main_circuit);
+        let propose_main_pk = self.key_cache.proving_key(propose_main_bincode)?;
 
         // Now create the parameters for the proposal tx
         let signature_secret = SecretKey::random(&mut OsRng);
@@ -239,7 +233,7 @@ impl Drk {
         params.encode(&mut data)?;
         let calls = vec![ContractCall { contract_id: *DAO_CONTRACT_ID, data }];
         let proofs = vec![proofs];
-        let mut tx = Transaction { calls, proofs, signatures: vec![] };
+        let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
         let sigs = tx.create_sigs(&mut OsRng, &vec![signature_secret])?;
         tx.signatures = vec![sigs];
 
@@ -347,19 +341,15 @@ impl Drk {
             return Err(anyhow!("DAO Vote Main circuit not found"))
         };
 
-        let dao_vote_burn_zkbin = ZkBinary::decode(&dao_vote_burn_zkbin.1)?;
-        let dao_vote_main_zkbin = ZkBinary::decode(&dao_vote_main_zkbin.1)?;
-
-        let k = 13;
-        let dao_vote_burn_circuit =
-            ZkCircuit::new(empty_witnesses(&dao_vote_burn_zkbin), dao_vote_burn_zkbin.clone());
-        let dao_vote_main_circuit =
-            ZkCircuit::new(empty_witnesses(&dao_vote_main_zkbin), dao_vote_main_zkbin.clone());
+        let dao_vote_burn_bincode = &dao_vote_burn_zkbin.1;
+        let dao_vote_burn_zkbin = ZkBinary::decode(dao_vote_burn_bincode)?;
+        let dao_vote_main_bincode = &dao_vote_main_zkbin.1;
+        let dao_vote_main_zkbin = ZkBinary::decode(dao_vote_main_bincode)?;
 
         eprintln!("Creating DAO Vote Burn proving key");
-        let dao_vote_burn_pk = ProvingKey::build(k, &dao_vote_burn_circuit);
+        let dao_vote_burn_pk = self.key_cache.proving_key(dao_vote_burn_bincode)?;
         eprintln!("Creating DAO Vote Main proving key");
-        let dao_vote_main_pk = ProvingKey::build(k, &dao_vote_main_circuit);
+        let dao_vote_main_pk = self.key_cache.proving_key(dao_vote_main_bincode)?;
 
         let (params, proofs) = call.make(
             &dao_vote_burn_zkbin,
@@ -372,7 +362,7 @@ impl Drk {
         params.encode(&mut data)?;
         let calls = vec![ContractCall { contract_id: *DAO_CONTRACT_ID, data }];
         let proofs = vec![proofs];
-        let mut tx = Transaction { calls, proofs, signatures: vec![] };
+        let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
         let sigs = tx.create_sigs(&mut OsRng, &input_secrets)?;
         tx.signatures = vec![sigs];
 
@@ -482,15 +472,14 @@ impl Drk {
         let Some(burn_zkbin) = zkas_bins.iter().find(|x| x.0 == MONEY_CONTRACT_ZKAS_BURN_NS_V1) else {
             return Err(anyhow!("Money Burn circuit not found"))
         };
-        let mint_zkbin = ZkBinary::decode(&mint_zkbin.1)?;
-        let burn_zkbin = ZkBinary::decode(&burn_zkbin.1)?;
-        let k = 13;
-        let mint_circuit = ZkCircuit::new(empty_witnesses(&mint_zkbin), mint_zkbin.clone());
-        let burn_circuit = ZkCircuit::new(empty_witnesses(&burn_zkbin), burn_zkbin.clone());
+        let mint_bincode = &mint_zkbin.1;
+        let mint_zkbin = ZkBinary::decode(mint_bincode)?;
+        let burn_bincode = &burn_zkbin.1;
+        let burn_zkbin = ZkBinary::decode(burn_bincode)?;
         eprintln!("Creating Money Mint circuit proving key");
-        let mint_pk = ProvingKey::build(k, &mint_circuit);
+        let mint_pk = self.key_cache.proving_key(mint_bincode)?;
         eprintln!("Creating Money Burn circuit proving key");
-        let burn_pk = ProvingKey::build(k, &burn_circuit);
+        let burn_pk = self.key_cache.proving_key(burn_bincode)?;
 
         let (xfer_params, xfer_proofs) =
             xfer_call.make(&mint_zkbin, &mint_pk, &burn_zkbin, &burn_pk)?;
@@ -503,10 +492,10 @@ impl Drk {
         let Some(exec_zkbin) = zkas_bins.iter().find(|x| x.0 == DAO_CONTRACT_ZKAS_DAO_EXEC_NS) else {
             return Err(anyhow!("DAO Exec circuit not found"))
         };
-        let exec_zkbin = ZkBinary::decode(&exec_zkbin.1)?;
-        let exec_circuit = ZkCircuit::new(empty_witnesses(&exec_zkbin), exec_zkbin.clone());
+        let exec_bincode = &exec_zkbin.1;
+        let exec_zkbin = ZkBinary::decode(exec_bincode)?;
         eprintln!("Creating DAO Exec circuit proving key");
-        let exec_pk = ProvingKey::build(k, &exec_circuit);
+        let exec_pk = self.key_cache.proving_key(exec_bincode)?;
 
         // Count votes
         let mut total_yes_vote_value = 0;
@@ -577,6 +566,8 @@ impl Drk {
 
         let mut tx = Transaction {
             calls: vec![xfer_call, exec_call],
+            gas_limits: vec![],
+            fee: 0,
             proofs: vec![xfer_proofs, exec_proofs],
             signatures: vec![],
         };
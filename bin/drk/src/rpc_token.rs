@@ -0,0 +1,98 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use anyhow::{anyhow, Result};
+use darkfi::{tx::Transaction, util::parse::decode_base10, zkas::ZkBinary};
+use darkfi_money_contract::{
+    client::build_token_mint_tx, MoneyFunction, MONEY_CONTRACT_ZKAS_TOKEN_MINT_NS_V1,
+};
+use darkfi_sdk::{
+    crypto::{contract_id::MONEY_CONTRACT_ID, Keypair, PublicKey, TokenId},
+    pasta::pallas,
+    tx::ContractCall,
+};
+use darkfi_serial::Encodable;
+use rand::rngs::OsRng;
+
+use super::Drk;
+
+impl Drk {
+    /// Create a token mint transaction. Returns the transaction object on success.
+    pub async fn mint_token(
+        &self,
+        amount: &str,
+        token_id: TokenId,
+        recipient: PublicKey,
+        fixed_supply: bool,
+    ) -> Result<Transaction> {
+        // We need the mint authority for the given token in our wallet
+        let authorities = self.get_mint_authorities().await?;
+        let Some((_, mint_authority, is_frozen)) = authorities.iter().find(|x| x.0 == token_id)
+        else {
+            return Err(anyhow!("Did not find mint authority for token ID: {}", token_id))
+        };
+
+        if *is_frozen {
+            return Err(anyhow!("Token ID {} has a fixed supply", token_id))
+        }
+
+        // FIXME: Do not hardcode 8 decimals
+        let amount = decode_base10(amount, 8, false)?;
+        if amount == 0 {
+            return Err(anyhow!("Cannot mint zero tokens"))
+        }
+
+        let mint_authority = Keypair::new(*mint_authority);
+        let contract_id = *MONEY_CONTRACT_ID;
+
+        let zkas_bins = self.lookup_zkas(&contract_id).await?;
+
+        let Some(token_mint_zkbin) =
+            zkas_bins.iter().find(|x| x.0 == MONEY_CONTRACT_ZKAS_TOKEN_MINT_NS_V1) else {
+            return Err(anyhow!("Token mint circuit not found"))
+        };
+
+        let token_mint_bincode = &token_mint_zkbin.1;
+        let token_mint_zkbin = ZkBinary::decode(token_mint_bincode)?;
+
+        eprintln!("Creating token mint circuit proving key");
+        let token_mint_pk = self.key_cache.proving_key(token_mint_bincode)?;
+
+        let (params, proofs) = build_token_mint_tx(
+            &mint_authority,
+            &recipient,
+            amount,
+            pallas::Base::zero(),
+            pallas::Base::zero(),
+            fixed_supply,
+            &token_mint_zkbin,
+            &token_mint_pk,
+        )?;
+
+        // Encode and sign the transaction
+        let mut data = vec![MoneyFunction::Mint as u8];
+        params.encode(&mut data)?;
+        let calls = vec![ContractCall { contract_id, data }];
+        let proofs = vec![proofs];
+        let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
+        let sigs = tx.create_sigs(&mut OsRng, &[mint_authority.secret])?;
+        tx.signatures = vec![sigs];
+
+        Ok(tx)
+    }
+}
@@ -29,9 +29,11 @@ use darkfi_money_contract::{
         MONEY_COINS_COL_USER_DATA, MONEY_COINS_COL_VALUE, MONEY_COINS_COL_VALUE_BLIND,
         MONEY_COINS_TABLE, MONEY_INFO_COL_LAST_SCANNED_SLOT, MONEY_INFO_TABLE,
         MONEY_KEYS_COL_IS_DEFAULT, MONEY_KEYS_COL_KEY_ID, MONEY_KEYS_COL_PUBLIC,
-        MONEY_KEYS_COL_SECRET, MONEY_KEYS_TABLE, MONEY_TREE_COL_TREE, MONEY_TREE_TABLE,
+        MONEY_KEYS_COL_SECRET, MONEY_KEYS_TABLE, MONEY_TOKENS_COL_IS_FROZEN,
+        MONEY_TOKENS_COL_MINT_AUTHORITY, MONEY_TOKENS_COL_TOKEN_ID, MONEY_TOKENS_TABLE,
+        MONEY_TREE_COL_TREE, MONEY_TREE_TABLE,
     },
-    model::{MoneyTransferParams, Output},
+    model::{MoneyFeeParams, MoneyMintParams, MoneyTransferParams, Output},
     MoneyFunction,
 };
 use darkfi_sdk::{
@@ -517,6 +519,24 @@ impl Drk {
 
                 continue
             }
+
+            if call.contract_id == cid && call.data[0] == MoneyFunction::Fee as u8 {
+                eprintln!("Found Money::Fee in call {}", i);
+                let params: MoneyFeeParams = deserialize(&call.data[1..])?;
+                nullifiers.push(params.input.nullifier);
+                outputs.push(params.output);
+                continue
+            }
+
+            if call.contract_id == cid && call.data[0] == MoneyFunction::Mint as u8 {
+                eprintln!("Found Money::Mint in call {}", i);
+                let params: MoneyMintParams = deserialize(&call.data[1..])?;
+                if params.fixed_supply {
+                    self.mark_token_frozen(&params.token_id).await?;
+                }
+                outputs.push(params.output);
+                continue
+            }
         }
 
         let secrets = self.get_money_secrets().await?;
@@ -769,4 +789,100 @@ impl Drk {
 
         Ok(())
     }
+
+    /// Generate a new token mint authority and place it into the wallet.
+    /// Returns the ID of the token it mints.
+    pub async fn generate_mint_authority(&self) -> Result<TokenId> {
+        eprintln!("Generating a new token mint authority");
+        let mint_authority = SecretKey::random(&mut OsRng);
+        let token_id = TokenId::derive(mint_authority);
+
+        let query = format!(
+            "INSERT INTO {} ({}, {}, {}) VALUES (?1, ?2, ?3);",
+            MONEY_TOKENS_TABLE,
+            MONEY_TOKENS_COL_MINT_AUTHORITY,
+            MONEY_TOKENS_COL_TOKEN_ID,
+            MONEY_TOKENS_COL_IS_FROZEN,
+        );
+
+        let params = json!([
+            query,
+            QueryType::Blob as u8,
+            serialize(&mint_authority),
+            QueryType::Blob as u8,
+            serialize(&token_id),
+            QueryType::Integer as u8,
+            0,
+        ]);
+
+        let req = JsonRequest::new("wallet.exec_sql", params);
+        let rep = self.rpc_client.request(req).await?;
+
+        if rep == true {
+            eprintln!("Successfully added new mint authority to wallet");
+        } else {
+            eprintln!("[generate_mint_authority] Got unexpected reply from darkfid: {}", rep);
+        }
+
+        Ok(token_id)
+    }
+
+    /// Fetch all token mint authorities from the wallet, along with the
+    /// ID of the token they mint and whether its supply is fixed.
+    pub async fn get_mint_authorities(&self) -> Result<Vec<(TokenId, SecretKey, bool)>> {
+        let query = format!("SELECT * FROM {}", MONEY_TOKENS_TABLE);
+        let params = json!([
+            query,
+            QueryType::Blob as u8,
+            MONEY_TOKENS_COL_MINT_AUTHORITY,
+            QueryType::Blob as u8,
+            MONEY_TOKENS_COL_TOKEN_ID,
+            QueryType::Integer as u8,
+            MONEY_TOKENS_COL_IS_FROZEN,
+        ]);
+
+        let req = JsonRequest::new("wallet.query_row_multi", params);
+        let rep = self.rpc_client.request(req).await?;
+
+        // The returned thing should be an array of found rows.
+        let Some(rows) = rep.as_array() else {
+            return Err(anyhow!("[get_mint_authorities] Unexpected response from darkfid: {}", rep))
+        };
+
+        let mut ret = Vec::with_capacity(rows.len());
+        for row in rows {
+            let Some(row) = row.as_array() else {
+                return Err(anyhow!("[get_mint_authorities] Unexpected row from darkfid: {:?}", row))
+            };
+
+            let mint_authority_bytes: Vec<u8> = serde_json::from_value(row[0].clone())?;
+            let mint_authority: SecretKey = deserialize(&mint_authority_bytes)?;
+
+            let token_id_bytes: Vec<u8> = serde_json::from_value(row[1].clone())?;
+            let token_id: TokenId = deserialize(&token_id_bytes)?;
+
+            let is_frozen: u64 = serde_json::from_value(row[2].clone())?;
+
+            ret.push((token_id, mint_authority, is_frozen > 0));
+        }
+
+        Ok(ret)
+    }
+
+    /// Mark a token in the wallet as having a fixed supply.
+    /// Tokens we don't hold the mint authority of are skipped.
+    pub async fn mark_token_frozen(&self, token_id: &TokenId) -> Result<()> {
+        let query = format!(
+            "UPDATE {} SET {} = ?1 WHERE {} = ?2;",
+            MONEY_TOKENS_TABLE, MONEY_TOKENS_COL_IS_FROZEN, MONEY_TOKENS_COL_TOKEN_ID
+        );
+
+        let params =
+            json!([query, QueryType::Integer as u8, 1, QueryType::Blob as u8, serialize(token_id)]);
+
+        let req = JsonRequest::new("wallet.exec_sql", params);
+        let _ = self.rpc_client.request(req).await?;
+
+        Ok(())
+    }
 }
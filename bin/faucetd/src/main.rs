@@ -23,11 +23,10 @@ use async_std::{
     sync::{Arc, Mutex, RwLock},
     task,
 };
-use async_trait::async_trait;
 use chrono::Utc;
 use darkfi::{
     tx::Transaction,
-    zk::{halo2::Field, proof::ProvingKey, vm::ZkCircuit, vm_stack::empty_witnesses},
+    zk::{halo2::Field, proof::ProvingKey, KeyCache},
     zkas::ZkBinary,
 };
 use darkfi_money_contract::{
@@ -50,7 +49,6 @@ use darkfi_sdk::{
 use darkfi_serial::{deserialize, serialize, Encodable};
 use log::{debug, error, info};
 use rand::rngs::OsRng;
-use serde_json::{json, Value};
 use signal_hook::consts::{SIGHUP, SIGINT, SIGQUIT, SIGTERM};
 use signal_hook_async_std::Signals;
 use sqlx::Row;
@@ -72,11 +70,9 @@ use darkfi::{
     net,
     net::P2pPtr,
     rpc::{
-        jsonrpc::{
-            ErrorCode::{InternalError, InvalidParams, MethodNotFound},
-            JsonError, JsonRequest, JsonResponse, JsonResult,
-        },
-        server::{listen_and_serve, RequestHandler},
+        jsonrpc::ErrorCode::InternalError,
+        registry::{MethodRegistry, MethodResult, RegistryHandler},
+        server::listen_and_serve,
     },
     util::{
         async_util::sleep,
@@ -88,7 +84,7 @@ use darkfi::{
 };
 
 mod error;
-use error::{server_error, RpcError};
+use error::RpcError;
 
 const CONFIG_FILE: &str = "faucetd_config.toml";
 const CONFIG_FILE_CONTENTS: &str = include_str!("../faucetd_config.toml");
@@ -117,6 +113,10 @@ struct Args {
     /// Path to blockchain database
     database: String,
 
+    #[structopt(long, default_value = "~/.config/darkfi/zk_key_cache")]
+    /// Path to the ZK proof key cache directory
+    key_cache: String,
+
     #[structopt(long, default_value = "tcp://127.0.0.1:9340")]
     /// JSON-RPC listen URL
     rpc_listen: Url,
@@ -169,6 +169,10 @@ struct Args {
     /// Airdrop amount limit
     airdrop_limit: String, // We convert this to u64 with decode_base10
 
+    #[structopt(long)]
+    /// Print the OpenRPC schema of the JSON-RPC API and exit
+    rpc_schema: bool,
+
     #[structopt(short, parse(from_occurrences))]
     /// Increase verbosity (-vvv supported)
     verbose: u8,
@@ -189,22 +193,6 @@ pub struct Faucetd {
     proving_keys: ProvingKeyMap,
 }
 
-#[async_trait]
-impl RequestHandler for Faucetd {
-    async fn handle_request(&self, req: JsonRequest) -> JsonResult {
-        if !req.params.is_array() {
-            return JsonError::new(InvalidParams, None, req.id).into()
-        }
-
-        let params = req.params.as_array().unwrap();
-
-        match req.method.as_str() {
-            Some("airdrop") => return self.airdrop(req.id, params).await,
-            Some(_) | None => return JsonError::new(MethodNotFound, None, req.id).into(),
-        }
-    }
-}
-
 impl Faucetd {
     pub async fn new(
         validator_state: ValidatorStatePtr,
@@ -238,17 +226,15 @@ impl Faucetd {
             return Err(Error::ZkasBincodeNotFound);
         };
 
-        let mint_zkbin = ZkBinary::decode(&mint_zkbin)?;
-        let burn_zkbin = ZkBinary::decode(&burn_zkbin)?;
-
-        let k = 13;
-        let mint_circuit = ZkCircuit::new(empty_witnesses(&mint_zkbin), mint_zkbin.clone());
-        let burn_circuit = ZkCircuit::new(empty_witnesses(&burn_zkbin), burn_zkbin.clone());
+        let key_cache = { validator_state.read().await.key_cache.clone() };
 
         info!("Creating mint circuit proving key");
-        let mint_provingkey = ProvingKey::build(k, &mint_circuit);
+        let mint_provingkey = key_cache.proving_key(&mint_zkbin)?;
         info!("Creating burn circuit proving key");
-        let burn_provingkey = ProvingKey::build(k, &burn_circuit);
+        let burn_provingkey = key_cache.proving_key(&burn_zkbin)?;
+
+        let mint_zkbin = ZkBinary::decode(&mint_zkbin)?;
+        let burn_zkbin = ZkBinary::decode(&burn_zkbin)?;
 
         {
             let provingkeys = vec![
@@ -353,58 +339,62 @@ impl Faucetd {
         Ok(keypair)
     }
 
-    // RPCAPI:
-    // Processes an airdrop request and airdrops requested token and amount to address.
-    // Returns the transaction ID upon success.
-    // Params:
-    // 0: base58 encoded address of the recipient
-    // 1: Amount to airdrop in form of f64
-    // 2: base58 encoded token ID to airdrop
-    //
-    // --> {"jsonrpc": "2.0", "method": "airdrop", "params": ["1DarkFi...", 1.42, "1F00b4r..."], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": "txID", "id": 1}
-    async fn airdrop(&self, id: Value, params: &[Value]) -> JsonResult {
-        if params.len() != 3 ||
-            !params[0].is_string() ||
-            !params[1].is_f64() ||
-            !params[2].is_string()
-        {
-            return JsonError::new(InvalidParams, None, id).into()
-        }
+    /// Registry of the JSON-RPC methods served by faucetd
+    pub fn rpc_methods() -> MethodRegistry<Self> {
+        let mut methods = MethodRegistry::new("faucetd", env!("CARGO_PKG_VERSION"));
+
+        methods.method(
+            "airdrop",
+            "Processes an airdrop request and airdrops requested token and amount \
+             to the base58 encoded address. Returns the transaction ID upon success.",
+            &["address", "amount", "token_id"],
+            |f, (address, amount, token_id)| async move {
+                f.airdrop(address, amount, token_id).await
+            },
+        );
+
+        methods
+    }
 
+    async fn airdrop(
+        &self,
+        address: String,
+        amount: f64,
+        token_id: String,
+    ) -> MethodResult<String> {
         if !(*self.synced.lock().await) {
             error!("airdrop(): Blockchain is not yet synced");
-            return JsonError::new(InternalError, None, id).into()
+            return Err(InternalError.into())
         }
 
-        let pubkey = match PublicKey::from_str(params[0].as_str().unwrap()) {
+        let pubkey = match PublicKey::from_str(&address) {
             Ok(v) => v,
             Err(e) => {
                 error!("airdrop(): Failed parsing PublicKey from String: {}", e);
-                return server_error(RpcError::ParseError, id)
+                return Err(RpcError::ParseError.into())
             }
         };
 
-        let amount = params[1].as_f64().unwrap().to_string();
+        let amount = amount.to_string();
         let amount = match decode_base10(&amount, 8, true) {
             Ok(v) => v,
             Err(_) => {
                 error!("airdrop(): Failed parsing amount from string");
-                return server_error(RpcError::ParseError, id)
+                return Err(RpcError::ParseError.into())
             }
         };
 
         if amount > self.airdrop_limit {
-            return server_error(RpcError::AmountExceedsLimit, id)
+            return Err(RpcError::AmountExceedsLimit.into())
         }
 
         // Here we allow the faucet to mint arbitrary token IDs.
         // TODO: Revert this to native token when we have contracts for minting tokens.
-        let token_id = match TokenId::try_from(params[2].as_str().unwrap()) {
+        let token_id = match TokenId::try_from(token_id.as_str()) {
             Ok(v) => v,
             Err(e) => {
                 error!("airdrop(): Failed parsing TokenID from string: {}", e);
-                return server_error(RpcError::ParseError, id)
+                return Err(RpcError::ParseError.into())
             }
         };
 
@@ -413,7 +403,7 @@ impl Faucetd {
         let map = self.airdrop_map.lock().await;
         if let Some(last_airdrop) = map.get(&pubkey.to_bytes()) {
             if now - last_airdrop <= self.airdrop_timeout {
-                return server_error(RpcError::TimeLimitReached, id)
+                return Err(RpcError::TimeLimitReached.into())
             }
         };
         drop(map);
@@ -424,17 +414,17 @@ impl Faucetd {
             let proving_keys_r = self.proving_keys.read().await;
             let Some(arr) = proving_keys_r.get(&cid.to_bytes()) else {
                 error!("Contract ID {} not found in proving keys hashmap", cid);
-                return server_error(RpcError::InternalError, id)
+                return Err(RpcError::InternalError.into())
             };
 
             let Some(mint_data) = arr.iter().find(|x| x.0 == MONEY_CONTRACT_ZKAS_MINT_NS_V1) else {
                 error!("{} proof data not found in vector", MONEY_CONTRACT_ZKAS_MINT_NS_V1);
-                return server_error(RpcError::InternalError, id)
+                return Err(RpcError::InternalError.into())
             };
 
             let Some(burn_data) = arr.iter().find(|x| x.0 == MONEY_CONTRACT_ZKAS_BURN_NS_V1) else {
                 error!("{} prof data not found in vector", MONEY_CONTRACT_ZKAS_BURN_NS_V1);
-                return server_error(RpcError::InternalError, id)
+                return Err(RpcError::InternalError.into())
             };
 
             (mint_data.2.clone(), mint_data.1.clone(), burn_data.2.clone(), burn_data.1.clone())
@@ -460,7 +450,7 @@ impl Faucetd {
             Ok(v) => v,
             Err(e) => {
                 error!("Failed to build transfer tx params: {}", e);
-                return server_error(RpcError::InternalError, id)
+                return Err(RpcError::InternalError.into())
             }
         };
 
@@ -469,7 +459,7 @@ impl Faucetd {
         params.encode(&mut data).unwrap();
         let calls = vec![ContractCall { contract_id: cid, data }];
         let proofs = vec![proofs];
-        let mut tx = Transaction { calls, proofs, signatures: vec![] };
+        let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
         let sigs = tx.create_sigs(&mut OsRng, &secret_keys).unwrap();
         tx.signatures = vec![sigs];
 
@@ -478,13 +468,13 @@ impl Faucetd {
             self.validator_state.read().await.verify_transactions(&[tx.clone()], false).await
         {
             error!("airdrop(): Failed to verify transaction before broadcasting: {}", e);
-            return JsonError::new(InternalError, None, id).into()
+            return Err(InternalError.into())
         }
 
         // Broadcast transaction to the network.
         if let Err(e) = self.sync_p2p.broadcast(tx.clone()).await {
             error!("airdrop(): Failed broadcasting transaction: {}", e);
-            return JsonError::new(InternalError, None, id).into()
+            return Err(InternalError.into())
         };
 
         // Add/Update this airdrop into the hashmap
@@ -493,7 +483,7 @@ impl Faucetd {
         drop(map);
 
         let tx_hash = blake3::hash(&serialize(&tx)).to_hex().as_str().to_string();
-        JsonResponse::new(json!(tx_hash), id).into()
+        Ok(tx_hash)
     }
 }
 
@@ -545,6 +535,11 @@ async fn handle_signals(
 
 async_daemonize!(realmain);
 async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
+    if args.rpc_schema {
+        println!("{:#}", Faucetd::rpc_methods().discover());
+        return Ok(())
+    }
+
     let cfg_path = get_config_path(args.config, CONFIG_FILE)?;
 
     // Signal handling for config reload and graceful termination.
@@ -594,6 +589,9 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
         faucet_pubkeys.push(pk);
     }
 
+    // Initialize the ZK proof key cache
+    let key_cache = Arc::new(KeyCache::new(Some(expand_path(&args.key_cache)?))?);
+
     // Initialize validator state
     let state = ValidatorState::new(
         &sled_db,
@@ -602,6 +600,7 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
         genesis_data,
         initial_distribution,
         wallet.clone(),
+        key_cache,
         faucet_pubkeys,
         false,
         false,
@@ -662,7 +661,8 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
     // JSON-RPC server
     info!("Starting JSON-RPC server");
     let _ex = ex.clone();
-    ex.spawn(listen_and_serve(args.rpc_listen, faucetd.clone(), _ex)).detach();
+    let rpc_handler = Arc::new(RegistryHandler::new(faucetd.clone(), Faucetd::rpc_methods()));
+    ex.spawn(listen_and_serve(args.rpc_listen, rpc_handler, _ex)).detach();
 
     info!("Starting sync P2P network");
     sync_p2p.clone().start(ex.clone()).await?;
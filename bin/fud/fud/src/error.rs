@@ -16,9 +16,7 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use serde_json::Value;
-
-use darkfi::rpc::jsonrpc::{ErrorCode::ServerError, JsonError, JsonResult};
+use darkfi::rpc::{jsonrpc::ErrorCode::ServerError, registry::MethodError};
 
 pub enum RpcError {
     UnknownKey = -35107,
@@ -42,7 +40,9 @@ fn to_tuple(e: RpcError) -> (i64, String) {
     (e as i64, msg.to_string())
 }
 
-pub fn server_error(e: RpcError, id: Value) -> JsonResult {
-    let (code, msg) = to_tuple(e);
-    JsonError::new(ServerError(code), Some(msg), id).into()
+impl From<RpcError> for MethodError {
+    fn from(e: RpcError) -> Self {
+        let (code, msg) = to_tuple(e);
+        MethodError::new(ServerError(code), Some(msg))
+    }
 }
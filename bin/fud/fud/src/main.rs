@@ -19,7 +19,6 @@
 use std::{collections::HashSet, fs, path::PathBuf};
 
 use async_std::sync::Arc;
-use async_trait::async_trait;
 use darkfi_serial::serialize;
 use log::{debug, error, info, warn};
 use serde_json::{json, Value};
@@ -31,18 +30,15 @@ use darkfi::{
     dht::{waiting_for_response, Dht, DhtPtr},
     net,
     rpc::{
-        jsonrpc::{
-            ErrorCode::{InvalidParams, MethodNotFound},
-            JsonError, JsonRequest, JsonResponse, JsonResult,
-        },
-        server::{listen_and_serve, RequestHandler},
+        registry::{MethodRegistry, MethodResult, RegistryHandler},
+        server::listen_and_serve,
     },
     util::path::expand_path,
     Result,
 };
 
 mod error;
-use error::{server_error, RpcError};
+use error::RpcError;
 
 const CONFIG_FILE: &str = "fud_config.toml";
 const CONFIG_FILE_CONTENTS: &str = include_str!("../fud_config.toml");
@@ -175,11 +171,48 @@ impl Fud {
         Ok(())
     }
 
-    // RPCAPI:
-    // Returns all folder contents, with file changes.
-    // --> {"jsonrpc": "2.0", "method": "list", "params": [], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": "[[files],[new],[deleted]", "id": 1}
-    pub async fn list(&self, id: Value, _params: &[Value]) -> JsonResult {
+    /// Registry of the JSON-RPC methods served by fud
+    pub fn rpc_methods() -> MethodRegistry<Self> {
+        let mut methods = MethodRegistry::new("fud", env!("CARGO_PKG_VERSION"));
+
+        methods.method(
+            "list",
+            "Returns all folder contents, with file changes, as \
+             `[[files], [new], [deleted]]`.",
+            &[],
+            |f, ()| async move { f.list().await },
+        );
+
+        methods.method(
+            "sync",
+            "Iterate contents folder and dht for potential changes.",
+            &[],
+            |f, ()| async move { f.sync().await },
+        );
+
+        methods.method(
+            "get",
+            "Checks if provided key exists and retrieve it from the local map or \
+             queries the network. Returns the path of the file holding it.",
+            &["name"],
+            |f, (name,)| async move { f.get(name).await },
+        );
+
+        methods.method(
+            "ping",
+            "Replies to a ping method.",
+            &[],
+            |f, ()| async move { f.pong().await },
+        );
+
+        methods.method("get_info", "Retrieves P2P network information.", &[], |f, ()| async move {
+            f.get_info().await
+        });
+
+        methods
+    }
+
+    pub async fn list(&self) -> MethodResult<Value> {
         let mut content = HashSet::new();
         let mut new = HashSet::new();
         let mut deleted = HashSet::new();
@@ -210,14 +243,10 @@ impl Fud {
             deleted.insert(key.to_string());
         }
 
-        JsonResponse::new(json!((content, new, deleted)), id).into()
+        Ok(json!((content, new, deleted)))
     }
 
-    // RPCAPI:
-    // Iterate contents folder and dht for potential changes.
-    // --> {"jsonrpc": "2.0", "method": "sync", "params": [], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": "true", "id": 1}
-    pub async fn sync(&self, id: Value, _params: &[Value]) -> JsonResult {
+    pub async fn sync(&self) -> MethodResult<bool> {
         info!("Sync process started");
 
         let entries = fs::read_dir(&self.folder).unwrap();
@@ -241,7 +270,7 @@ impl Fud {
                 let value: Vec<u8> = std::fs::read(e.path()).unwrap();
                 if let Err(e) = lock.insert(key_hash, value).await {
                     error!("Failed to insert key: {}", e);
-                    return server_error(RpcError::KeyInsertFail, id)
+                    return Err(RpcError::KeyInsertFail.into())
                 }
             }
 
@@ -264,26 +293,16 @@ impl Fud {
                     },
                     Err(e) => {
                         error!("Failed to remove key: {}", e);
-                        return server_error(RpcError::KeyRemoveFail, id)
+                        return Err(RpcError::KeyRemoveFail.into())
                     }
                 }
             }
         }
 
-        JsonResponse::new(json!(true), id).into()
+        Ok(true)
     }
 
-    // RPCAPI:
-    // Checks if provided key exists and retrieve it from the local map or queries the network.
-    // Returns key or not found message.
-    // --> {"jsonrpc": "2.0", "method": "get", "params": ["name"], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": "path", "id": 1}
-    async fn get(&self, id: Value, params: &[Value]) -> JsonResult {
-        if params.len() != 1 || !params[0].is_string() {
-            return JsonError::new(InvalidParams, None, id).into()
-        }
-
-        let key = params[0].as_str().unwrap().to_string();
+    async fn get(&self, key: String) -> MethodResult<Value> {
         let key_hash = blake3::hash(&serialize(&key));
 
         // We execute this sequence to prevent lock races between threads
@@ -291,7 +310,7 @@ impl Fud {
         let exists = self.dht.read().await.contains_key(key_hash);
         if exists.is_none() {
             info!("Did not find key: {}", key);
-            return server_error(RpcError::UnknownKey, id)
+            return Err(RpcError::UnknownKey.into())
         }
 
         // Check if key is local or should query network
@@ -299,10 +318,10 @@ impl Fud {
         let local = exists.unwrap();
         if local {
             match self.dht.read().await.get(key_hash) {
-                Some(_) => return JsonResponse::new(json!(path), id).into(),
+                Some(_) => return Ok(json!(path)),
                 None => {
                     info!("Did not find key: {}", key);
-                    return server_error(RpcError::UnknownKey, id)
+                    return Err(RpcError::UnknownKey.into())
                 }
             }
         }
@@ -310,7 +329,7 @@ impl Fud {
         info!("Key doesn't exist locally, querring network...");
         if let Err(e) = self.dht.read().await.request_key(key_hash).await {
             error!("Failed to query key: {}", e);
-            return server_error(RpcError::QueryFailed, id)
+            return Err(RpcError::QueryFailed.into())
         }
 
         info!("Waiting response...");
@@ -324,63 +343,34 @@ impl Fud {
                             self.dht.write().await.insert(resp.key, resp.value.clone()).await
                         {
                             error!("Failed to insert key: {}", e);
-                            return server_error(RpcError::KeyInsertFail, id)
+                            return Err(RpcError::KeyInsertFail.into())
                         }
 
                         if let Err(e) = std::fs::write(path.clone(), resp.value) {
                             error!("Failed to generate file for key: {}", e);
-                            return server_error(RpcError::FileGenerationFail, id)
+                            return Err(RpcError::FileGenerationFail.into())
                         }
-                        JsonResponse::new(json!(path), id).into()
+                        Ok(json!(path))
                     }
                     None => {
                         info!("Did not find key: {}", key);
-                        server_error(RpcError::UnknownKey, id)
+                        Err(RpcError::UnknownKey.into())
                     }
                 }
             }
             Err(e) => {
                 error!("Error while waiting network response: {}", e);
-                server_error(RpcError::WaitingNetworkError, id)
+                Err(RpcError::WaitingNetworkError.into())
             }
         }
     }
 
-    // RPCAPI:
-    // Replies to a ping method.
-    // --> {"jsonrpc": "2.0", "method": "ping", "params": [], "id": 42}
-    // <-- {"jsonrpc": "2.0", "result": "pong", "id": 42}
-    async fn pong(&self, id: Value, _params: &[Value]) -> JsonResult {
-        JsonResponse::new(json!("pong"), id).into()
+    async fn pong(&self) -> MethodResult<String> {
+        Ok("pong".to_string())
     }
 
-    // RPCAPI:
-    // Retrieves P2P network information.
-    // --> {"jsonrpc": "2.0", "method": "get_info", "params": [], "id": 42}
-    // <-- {"jsonrpc": "2.0", result": {"nodeID": [], "nodeinfo": [], "id": 42}
-    async fn get_info(&self, id: Value, _params: &[Value]) -> JsonResult {
-        let resp = self.dht.read().await.p2p.get_info().await;
-        JsonResponse::new(resp, id).into()
-    }
-}
-
-#[async_trait]
-impl RequestHandler for Fud {
-    async fn handle_request(&self, req: JsonRequest) -> JsonResult {
-        if !req.params.is_array() {
-            return JsonError::new(InvalidParams, None, req.id).into()
-        }
-
-        let params = req.params.as_array().unwrap();
-
-        match req.method.as_str() {
-            Some("list") => return self.list(req.id, params).await,
-            Some("sync") => return self.sync(req.id, params).await,
-            Some("get") => return self.get(req.id, params).await,
-            Some("ping") => return self.pong(req.id, params).await,
-            Some("get_info") => return self.get_info(req.id, params).await,
-            Some(_) | None => return JsonError::new(MethodNotFound, None, req.id).into(),
-        }
+    async fn get_info(&self) -> MethodResult<Value> {
+        Ok(self.dht.read().await.p2p.get_info().await)
     }
 }
 
@@ -421,7 +411,8 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
     // JSON-RPC server
     info!("Starting JSON-RPC server");
     let _ex = ex.clone();
-    ex.spawn(listen_and_serve(args.rpc_listen, fud.clone(), _ex)).detach();
+    let rpc_handler = Arc::new(RegistryHandler::new(fud.clone(), Fud::rpc_methods()));
+    ex.spawn(listen_and_serve(args.rpc_listen, rpc_handler, _ex)).detach();
 
     info!("Starting sync P2P network");
     p2p.clone().start(ex.clone()).await?;
@@ -26,7 +26,7 @@ use structopt_toml::StructOptToml;
 
 use darkfi::{
     async_daemonize, net,
-    rpc::server::listen_and_serve,
+    rpc::{registry::RegistryHandler, server::listen_and_serve},
     system::{Subscriber, SubscriberPtr},
     util::{file::save_json_file, path::expand_path},
     Result,
@@ -185,9 +185,11 @@ async fn realmain(settings: Args, executor: Arc<smol::Executor<'_>>) -> Result<(
     let rpc_listen_addr = settings.rpc_listen.clone();
     let rpc_interface =
         Arc::new(JsonRpcInterface { addr: rpc_listen_addr.clone(), p2p: p2p.clone() });
+    let rpc_handler =
+        Arc::new(RegistryHandler::new(rpc_interface, JsonRpcInterface::rpc_methods()));
     let _ex = executor.clone();
     executor
-        .spawn(async move { listen_and_serve(rpc_listen_addr, rpc_interface, _ex).await })
+        .spawn(async move { listen_and_serve(rpc_listen_addr, rpc_handler, _ex).await })
         .detach();
 
     //
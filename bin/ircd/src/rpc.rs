@@ -16,17 +16,12 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use async_trait::async_trait;
-use log::debug;
 use serde_json::{json, Value};
 use url::Url;
 
 use darkfi::{
     net,
-    rpc::{
-        jsonrpc::{ErrorCode, JsonError, JsonRequest, JsonResponse, JsonResult},
-        server::RequestHandler,
-    },
+    rpc::registry::{MethodRegistry, MethodResult},
 };
 
 pub struct JsonRpcInterface {
@@ -34,38 +29,68 @@ pub struct JsonRpcInterface {
     pub p2p: net::P2pPtr,
 }
 
-#[async_trait]
-impl RequestHandler for JsonRpcInterface {
-    async fn handle_request(&self, req: JsonRequest) -> JsonResult {
-        if req.params.as_array().is_none() {
-            return JsonError::new(ErrorCode::InvalidRequest, None, req.id).into()
-        }
+impl JsonRpcInterface {
+    /// Registry of the JSON-RPC methods served by ircd
+    pub fn rpc_methods() -> MethodRegistry<Self> {
+        let mut methods = MethodRegistry::new("ircd", env!("CARGO_PKG_VERSION"));
+
+        methods.method(
+            "ping",
+            "Replies to a ping method.",
+            &[],
+            |r, ()| async move { r.pong().await },
+        );
+
+        methods.method("get_info", "Retrieves P2P network information.", &[], |r, ()| async move {
+            r.get_info().await
+        });
+
+        methods.method(
+            "net.get_bans",
+            "Lists the currently banned hosts and TLS keys, with the `target`, \
+             `until` and `reason` of each ban.",
+            &[],
+            |r, ()| async move { r.net_get_bans().await },
+        );
 
-        debug!(target: "RPC", "--> {}", serde_json::to_string(&req).unwrap());
+        methods.method(
+            "net.ban",
+            "Bans a host or hex-encoded TLS key, for the given number of seconds \
+             or the configured ban duration, and disconnects matching peers.",
+            &["target", "seconds"],
+            |r, (target, seconds)| async move { r.net_ban(target, seconds).await },
+        );
 
-        match req.method.as_str() {
-            Some("ping") => self.pong(req.id, req.params).await,
-            Some("get_info") => self.get_info(req.id, req.params).await,
-            Some(_) | None => JsonError::new(ErrorCode::MethodNotFound, None, req.id).into(),
-        }
+        methods.method(
+            "net.unban",
+            "Lifts the ban of a host or hex-encoded TLS key. \
+             Returns `false` if it wasn't banned.",
+            &["target"],
+            |r, (target,)| async move { r.net_unban(target).await },
+        );
+
+        methods
     }
-}
 
-impl JsonRpcInterface {
-    // RPCAPI:
-    // Replies to a ping method.
-    // --> {"jsonrpc": "2.0", "method": "ping", "params": [], "id": 42}
-    // <-- {"jsonrpc": "2.0", "result": "pong", "id": 42}
-    async fn pong(&self, id: Value, _params: Value) -> JsonResult {
-        JsonResponse::new(json!("pong"), id).into()
+    async fn pong(&self) -> MethodResult<String> {
+        Ok("pong".to_string())
+    }
+
+    async fn get_info(&self) -> MethodResult<Value> {
+        Ok(self.p2p.get_info().await)
+    }
+
+    async fn net_get_bans(&self) -> MethodResult<Value> {
+        Ok(json!(self.p2p.banlist().list().await))
+    }
+
+    async fn net_ban(&self, target: String, seconds: Option<u64>) -> MethodResult<bool> {
+        let seconds = seconds.unwrap_or(self.p2p.settings().ban_seconds);
+        self.p2p.ban(&target, seconds, "Banned over RPC").await;
+        Ok(true)
     }
 
-    // RPCAPI:
-    // Retrieves P2P network information.
-    // --> {"jsonrpc": "2.0", "method": "get_info", "params": [], "id": 42}
-    // <-- {"jsonrpc": "2.0", result": {"nodeID": [], "nodeinfo": [], "id": 42}
-    async fn get_info(&self, id: Value, _params: Value) -> JsonResult {
-        let resp = self.p2p.get_info().await;
-        JsonResponse::new(resp, id).into()
+    async fn net_unban(&self, target: String) -> MethodResult<bool> {
+        Ok(self.p2p.unban(&target).await)
     }
 }
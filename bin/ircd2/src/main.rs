@@ -30,7 +30,7 @@ use darkfi::{
         view::View,
     },
     net,
-    rpc::server::listen_and_serve,
+    rpc::{registry::RegistryHandler, server::listen_and_serve},
     system::Subscriber,
     util::{file::save_json_file, path::expand_path},
     Result,
@@ -132,9 +132,11 @@ async fn realmain(settings: Args, executor: Arc<smol::Executor<'_>>) -> Result<(
     let rpc_listen_addr = settings.rpc_listen.clone();
     let rpc_interface =
         Arc::new(JsonRpcInterface { addr: rpc_listen_addr.clone(), p2p: p2p.clone() });
+    let rpc_handler =
+        Arc::new(RegistryHandler::new(rpc_interface, JsonRpcInterface::rpc_methods()));
     let _ex = executor.clone();
     executor
-        .spawn(async move { listen_and_serve(rpc_listen_addr, rpc_interface, _ex).await })
+        .spawn(async move { listen_and_serve(rpc_listen_addr, rpc_handler, _ex).await })
         .detach();
 
     ////////////////////
@@ -16,17 +16,12 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use async_trait::async_trait;
-use log::debug;
-use serde_json::{json, Value};
+use serde_json::Value;
 use url::Url;
 
 use darkfi::{
     net,
-    rpc::{
-        jsonrpc::{ErrorCode, JsonError, JsonRequest, JsonResponse, JsonResult},
-        server::RequestHandler,
-    },
+    rpc::registry::{MethodRegistry, MethodResult},
 };
 
 pub struct JsonRpcInterface {
@@ -34,38 +29,30 @@ pub struct JsonRpcInterface {
     pub p2p: net::P2pPtr,
 }
 
-#[async_trait]
-impl RequestHandler for JsonRpcInterface {
-    async fn handle_request(&self, req: JsonRequest) -> JsonResult {
-        if req.params.as_array().is_none() {
-            return JsonError::new(ErrorCode::InvalidRequest, None, req.id).into()
-        }
-
-        debug!(target: "RPC", "--> {}", serde_json::to_string(&req).unwrap());
-
-        match req.method.as_str() {
-            Some("ping") => self.pong(req.id, req.params).await,
-            Some("get_info") => self.get_info(req.id, req.params).await,
-            Some(_) | None => JsonError::new(ErrorCode::MethodNotFound, None, req.id).into(),
-        }
+impl JsonRpcInterface {
+    /// Registry of the JSON-RPC methods served by ircd2
+    pub fn rpc_methods() -> MethodRegistry<Self> {
+        let mut methods = MethodRegistry::new("ircd2", env!("CARGO_PKG_VERSION"));
+
+        methods.method(
+            "ping",
+            "Replies to a ping method.",
+            &[],
+            |r, ()| async move { r.pong().await },
+        );
+
+        methods.method("get_info", "Retrieves P2P network information.", &[], |r, ()| async move {
+            r.get_info().await
+        });
+
+        methods
     }
-}
 
-impl JsonRpcInterface {
-    // RPCAPI:
-    // Replies to a ping method.
-    // --> {"jsonrpc": "2.0", "method": "ping", "params": [], "id": 42}
-    // <-- {"jsonrpc": "2.0", "result": "pong", "id": 42}
-    async fn pong(&self, id: Value, _params: Value) -> JsonResult {
-        JsonResponse::new(json!("pong"), id).into()
+    async fn pong(&self) -> MethodResult<String> {
+        Ok("pong".to_string())
     }
 
-    // RPCAPI:
-    // Retrieves P2P network information.
-    // --> {"jsonrpc": "2.0", "method": "get_info", "params": [], "id": 42}
-    // <-- {"jsonrpc": "2.0", result": {"nodeID": [], "nodeinfo": [], "id": 42}
-    async fn get_info(&self, id: Value, _params: Value) -> JsonResult {
-        let resp = self.p2p.get_info().await;
-        JsonResponse::new(resp, id).into()
+    async fn get_info(&self) -> MethodResult<Value> {
+        Ok(self.p2p.get_info().await)
     }
 }
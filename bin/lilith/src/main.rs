@@ -22,7 +22,6 @@ use std::{
 };
 
 use async_std::sync::Arc;
-use async_trait::async_trait;
 use log::{error, info, warn};
 use serde_json::{json, Value};
 use structopt_toml::StructOptToml;
@@ -32,11 +31,9 @@ use darkfi::{
     async_daemonize, net,
     net::P2pPtr,
     rpc::{
-        jsonrpc::{
-            ErrorCode::{InvalidParams, MethodNotFound},
-            JsonError, JsonNotification, JsonRequest, JsonResponse, JsonResult, JsonSubscriber,
-        },
-        server::{listen_and_serve, RequestHandler},
+        jsonrpc::JsonNotification,
+        registry::{MethodRegistry, MethodResult, RegistryHandler},
+        server::listen_and_serve,
     },
     system::{Subscriber, SubscriberPtr},
     util::{
@@ -100,11 +97,35 @@ impl Lilith {
         spawns
     }
 
-    // RPCAPI:
-    // Returns all spawned networks names with their node addresses.
-    // --> {"jsonrpc": "2.0", "method": "spawns", "params": [], "id": 42}
-    // <-- {"jsonrpc": "2.0", "result": "{spawns}", "id": 42}
-    async fn spawns(&self, id: Value, _params: &[Value]) -> JsonResult {
+    /// Registry of the JSON-RPC methods served by lilith
+    fn rpc_methods() -> MethodRegistry<Self> {
+        let mut methods = MethodRegistry::new("lilith", env!("CARGO_PKG_VERSION"));
+
+        methods.method(
+            "spawns",
+            "Returns all spawned networks names with their node addresses.",
+            &[],
+            |l, ()| async move { l.spawns().await },
+        );
+
+        methods.method(
+            "ping",
+            "Replies to a ping method.",
+            &[],
+            |l, ()| async move { l.pong().await },
+        );
+
+        methods.subscription(
+            "blockchain.notify_blocks",
+            "Create a new subscriber for new blocks to notify connected peer.",
+            &[],
+            |l, ()| async move { l.blockchain_notify_blocks().await },
+        );
+
+        methods
+    }
+
+    async fn spawns(&self) -> MethodResult<Value> {
         // Building urls string
         let mut urls_vec = vec![];
         for url in &self.urls {
@@ -122,28 +143,15 @@ impl Lilith {
             "urls": urls_vec,
             "spawns": spawns,
         });
-        JsonResponse::new(json, id).into()
+        Ok(json)
     }
 
-    // RPCAPI:
-    // Replies to a ping method.
-    // --> {"jsonrpc": "2.0", "method": "ping", "params": [], "id": 42}
-    // <-- {"jsonrpc": "2.0", "result": "pong", "id": 42}
-    async fn pong(&self, id: Value, _params: &[Value]) -> JsonResult {
-        JsonResponse::new(json!("pong"), id).into()
+    async fn pong(&self) -> MethodResult<String> {
+        Ok("pong".to_string())
     }
 
-    // RPCAPI:
-    // Create a new subscriber for new blocks to notify connected peer.
-    //
-    // --> {"jsonrpc": "2.0", "method": "blockchain.notify_blocks", "params": [], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": {...}, "id": 1}
-    pub async fn blockchain_notify_blocks(&self, id: Value, params: &[Value]) -> JsonResult {
-        if !params.is_empty() {
-            return JsonError::new(InvalidParams, None, id).into()
-        }
-
-        JsonSubscriber::new(self.subscriber.clone()).into()
+    pub async fn blockchain_notify_blocks(&self) -> MethodResult<SubscriberPtr<JsonNotification>> {
+        Ok(self.subscriber.clone())
     }
 }
 
@@ -158,26 +166,6 @@ async fn simulate_blocks(subscriber: SubscriberPtr<JsonNotification>) {
     }
 }
 
-#[async_trait]
-impl RequestHandler for Lilith {
-    async fn handle_request(&self, req: JsonRequest) -> JsonResult {
-        if !req.params.is_array() {
-            return JsonError::new(InvalidParams, None, req.id).into()
-        }
-
-        let params = req.params.as_array().unwrap();
-
-        match req.method.as_str() {
-            Some("spawns") => return self.spawns(req.id, params).await,
-            Some("ping") => return self.pong(req.id, params).await,
-            Some("blockchain.notify_blocks") => {
-                return self.blockchain_notify_blocks(req.id, params).await
-            }
-            Some(_) | None => return JsonError::new(MethodNotFound, None, req.id).into(),
-        }
-    }
-}
-
 async fn spawn_network(
     name: &str,
     info: NetInfo,
@@ -345,7 +333,8 @@ async fn realmain(args: Args, ex: Arc<smol::Executor<'_>>) -> Result<()> {
     // JSON-RPC server
     info!("Starting JSON-RPC server");
     let _ex = ex.clone();
-    ex.spawn(listen_and_serve(args.rpc_listen, lilith.clone(), _ex)).detach();
+    let rpc_handler = Arc::new(RegistryHandler::new(lilith.clone(), Lilith::rpc_methods()));
+    ex.spawn(listen_and_serve(args.rpc_listen, rpc_handler, _ex)).detach();
 
     // JSON-RPC notifications simulation
     ex.spawn(simulate_blocks(subscriber)).detach();
@@ -16,9 +16,7 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use serde_json::Value;
-
-use darkfi::rpc::jsonrpc::{ErrorCode, JsonError, JsonResponse, JsonResult};
+use darkfi::rpc::{jsonrpc::ErrorCode, registry::MethodError};
 
 #[derive(Debug, thiserror::Error)]
 pub enum TaudError {
@@ -58,26 +56,21 @@ impl From<std::io::Error> for TaudError {
     }
 }
 
-pub fn to_json_result(res: TaudResult<Value>, id: Value) -> JsonResult {
-    match res {
-        Ok(v) => JsonResponse::new(v, id).into(),
-        Err(err) => match err {
+impl From<TaudError> for MethodError {
+    fn from(err: TaudError) -> MethodError {
+        match err {
             TaudError::InvalidId => {
-                JsonError::new(ErrorCode::InvalidParams, Some("invalid task id".into()), id).into()
+                MethodError::new(ErrorCode::InvalidParams, Some("invalid task id".into()))
             }
             TaudError::InvalidData(e) | TaudError::SerdeJsonError(e) => {
-                JsonError::new(ErrorCode::InvalidParams, Some(e), id).into()
+                MethodError::new(ErrorCode::InvalidParams, Some(e))
             }
             TaudError::InvalidDueTime => {
-                JsonError::new(ErrorCode::InvalidParams, Some("invalid due time".into()), id).into()
-            }
-            TaudError::EncryptionError(e) => {
-                JsonError::new(ErrorCode::InternalError, Some(e), id).into()
-            }
-            TaudError::Darkfi(e) => {
-                JsonError::new(ErrorCode::InternalError, Some(e.to_string()), id).into()
+                MethodError::new(ErrorCode::InvalidParams, Some("invalid due time".into()))
             }
-            TaudError::IoError(e) => JsonError::new(ErrorCode::InternalError, Some(e), id).into(),
-        },
+            TaudError::EncryptionError(e) => MethodError::new(ErrorCode::InternalError, Some(e)),
+            TaudError::Darkfi(e) => MethodError::new(ErrorCode::InternalError, Some(e.to_string())),
+            TaudError::IoError(e) => MethodError::new(ErrorCode::InternalError, Some(e)),
+        }
     }
 }
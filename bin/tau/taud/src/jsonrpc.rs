@@ -19,7 +19,6 @@
 use std::{collections::HashMap, fs::create_dir_all, path::PathBuf};
 
 use async_std::sync::Mutex;
-use async_trait::async_trait;
 use crypto_box::SalsaBox;
 use log::{debug, warn};
 use serde::{Deserialize, Serialize};
@@ -27,16 +26,13 @@ use serde_json::{json, Value};
 
 use darkfi::{
     net,
-    rpc::{
-        jsonrpc::{ErrorCode, JsonError, JsonRequest, JsonResult},
-        server::RequestHandler,
-    },
+    rpc::registry::{MethodError, MethodRegistry, RpcSchema},
     util::{path::expand_path, time::Timestamp},
     Error,
 };
 
 use crate::{
-    error::{to_json_result, TaudError, TaudResult},
+    error::{TaudError, TaudResult},
     month_tasks::MonthTasks,
     task_info::{Comment, TaskInfo},
     util::find_free_id,
@@ -62,33 +58,21 @@ struct BaseTaskInfo {
     rank: Option<f32>,
 }
 
-#[async_trait]
-impl RequestHandler for JsonRpcInterface {
-    async fn handle_request(&self, req: JsonRequest) -> JsonResult {
-        if !req.params.is_array() {
-            return JsonError::new(ErrorCode::InvalidParams, None, req.id).into()
-        }
-
-        let params = req.params.as_array().unwrap();
-
-        let rep = match req.method.as_str() {
-            Some("add") => self.add(params).await,
-            Some("get_ids") => self.get_ids(params).await,
-            Some("update") => self.update(params).await,
-            Some("set_state") => self.set_state(params).await,
-            Some("set_comment") => self.set_comment(params).await,
-            Some("get_task_by_id") => self.get_task_by_id(params).await,
-            Some("switch_ws") => self.switch_ws(params).await,
-            Some("get_ws") => self.get_ws(params).await,
-            Some("export") => self.export_to(params).await,
-            Some("import") => self.import_from(params).await,
-            Some("get_stop_tasks") => self.get_stop_tasks(params).await,
-            Some("ping") => self.pong(params).await,
-            Some("get_info") => self.get_info(params).await,
-            Some(_) | None => return JsonError::new(ErrorCode::MethodNotFound, None, req.id).into(),
-        };
-
-        to_json_result(rep, req.id)
+impl RpcSchema for BaseTaskInfo {
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {
+                "title": {"type": "string"},
+                "tags": {"type": "array", "items": {"type": "string"}},
+                "desc": {"type": "string"},
+                "assign": {"type": "array", "items": {"type": "string"}},
+                "project": {"type": "array", "items": {"type": "string"}},
+                "due": {"type": ["integer", "null"]},
+                "rank": {"type": ["number", "null"]},
+            },
+            "required": ["title", "tags", "desc", "assign", "project"],
+        })
     }
 }
 
@@ -104,42 +88,104 @@ impl JsonRpcInterface {
         Self { dataset_path, nickname, workspace, workspaces, notify_queue_sender, p2p }
     }
 
-    // RPCAPI:
-    // Replies to a ping method.
-    // --> {"jsonrpc": "2.0", "method": "ping", "params": [], "id": 42}
-    // <-- {"jsonrpc": "2.0", "result": "pong", "id": 42}
-    async fn pong(&self, _params: &[Value]) -> TaudResult<Value> {
-        Ok(json!("pong"))
+    /// Registry of the JSON-RPC methods served by taud
+    pub fn rpc_methods() -> MethodRegistry<Self> {
+        let mut methods = MethodRegistry::new("taud", env!("CARGO_PKG_VERSION"));
+
+        methods.method("ping", "Replies to a ping method.", &[], |t, ()| async move {
+            t.pong().await.map_err(MethodError::from)
+        });
+
+        methods.method("get_info", "Retrieves P2P network information.", &[], |t, ()| async move {
+            t.get_info().await.map_err(MethodError::from)
+        });
+
+        methods.method(
+            "add",
+            "Add new task and returns `true` upon success.",
+            &["task"],
+            |t, (task,)| async move { t.add(task).await.map_err(MethodError::from) },
+        );
+
+        methods.method("get_ids", "List tasks.", &[], |t, ()| async move {
+            t.get_ids().await.map_err(MethodError::from)
+        });
+
+        methods.method(
+            "update",
+            "Update the given fields of a task and returns `true` upon success.",
+            &["task_id", "fields"],
+            |t, (task_id, fields)| async move {
+                t.update(task_id, fields).await.map_err(MethodError::from)
+            },
+        );
+
+        methods.method(
+            "set_state",
+            "Set state for a task and returns `true` upon success.",
+            &["task_id", "state"],
+            |t, (task_id, state)| async move {
+                t.set_state(task_id, state).await.map_err(MethodError::from)
+            },
+        );
+
+        methods.method(
+            "set_comment",
+            "Set comment for a task and returns `true` upon success.",
+            &["task_id", "comment"],
+            |t, (task_id, comment)| async move {
+                t.set_comment(task_id, comment).await.map_err(MethodError::from)
+            },
+        );
+
+        methods.method(
+            "get_task_by_id",
+            "Get a task by id.",
+            &["task_id"],
+            |t, (task_id,)| async move { t.get_task_by_id(task_id).await.map_err(MethodError::from) },
+        );
+
+        methods.method(
+            "get_stop_tasks",
+            "Get the stopped tasks of the given month, or of the current one.",
+            &["month"],
+            |t, (month,)| async move { t.get_stop_tasks(month).await.map_err(MethodError::from) },
+        );
+
+        methods.method(
+            "switch_ws",
+            "Switch tasks workspace.",
+            &["workspace"],
+            |t, (ws,)| async move { t.switch_ws(ws).await.map_err(MethodError::from) },
+        );
+
+        methods.method("get_ws", "Get workspace.", &[], |t, ()| async move {
+            t.get_ws().await.map_err(MethodError::from)
+        });
+
+        methods.method("export", "Export tasks.", &["path"], |t, (path,)| async move {
+            t.export_to(path).await.map_err(MethodError::from)
+        });
+
+        methods.method("import", "Import tasks.", &["path"], |t, (path,)| async move {
+            t.import_from(path).await.map_err(MethodError::from)
+        });
+
+        methods
+    }
+
+    async fn pong(&self) -> TaudResult<String> {
+        Ok("pong".to_string())
     }
 
-    // RPCAPI:
-    // Retrieves P2P network information.
-    // --> {"jsonrpc": "2.0", "method": "get_info", "params": [], "id": 42}
-    // <-- {"jsonrpc": "2.0", result": {"nodeID": [], "nodeinfo": [], "id": 42}
-    async fn get_info(&self, _params: &[Value]) -> TaudResult<Value> {
+    async fn get_info(&self) -> TaudResult<Value> {
         let resp = self.p2p.get_info().await;
         Ok(resp)
     }
 
-    // RPCAPI:
-    // Add new task and returns `true` upon success.
-    // --> {"jsonrpc": "2.0", "method": "add",
-    //      "params":
-    //          [{
-    //          "title": "..",
-    //          "desc": "..",
-    //          assign: [..],
-    //          project: [..],
-    //          "due": ..,
-    //          "rank": ..
-    //          }],
-    //      "id": 1
-    //      }
-    // <-- {"jsonrpc": "2.0", "result": true, "id": 1}
-    async fn add(&self, params: &[Value]) -> TaudResult<Value> {
-        debug!(target: "tau", "JsonRpc::add() params {:?}", params);
-
-        let task: BaseTaskInfo = serde_json::from_value(params[0].clone())?;
+    async fn add(&self, task: BaseTaskInfo) -> TaudResult<bool> {
+        debug!(target: "tau", "JsonRpc::add() task {:?}", task);
+
         let mut new_task: TaskInfo = TaskInfo::new(
             self.workspace.lock().await.clone(),
             &task.title,
@@ -154,61 +200,40 @@ impl JsonRpcInterface {
         new_task.set_tags(&task.tags);
 
         self.notify_queue_sender.send(new_task).await.map_err(Error::from)?;
-        Ok(json!(true))
+        Ok(true)
     }
 
-    // RPCAPI:
-    // List tasks
-    // --> {"jsonrpc": "2.0", "method": "get_ids", "params": [], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": [task_id, ...], "id": 1}
-    async fn get_ids(&self, params: &[Value]) -> TaudResult<Value> {
-        debug!(target: "tau", "JsonRpc::get_ids() params {:?}", params);
+    async fn get_ids(&self) -> TaudResult<Vec<u32>> {
+        debug!(target: "tau", "JsonRpc::get_ids()");
 
         let ws = self.workspace.lock().await.clone();
         let tasks = MonthTasks::load_current_tasks(&self.dataset_path, ws, false)?;
 
         let task_ids: Vec<u32> = tasks.iter().map(|task| task.get_id()).collect();
 
-        Ok(json!(task_ids))
+        Ok(task_ids)
     }
 
-    // RPCAPI:
-    // Update task and returns `true` upon success.
-    // --> {"jsonrpc": "2.0", "method": "update", "params": [task_id, {"title": "new title"} ], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": true, "id": 1}
-    async fn update(&self, params: &[Value]) -> TaudResult<Value> {
-        debug!(target: "tau", "JsonRpc::update() params {:?}", params);
-
-        if params.len() != 2 {
-            return Err(TaudError::InvalidData("len of params should be 2".into()))
-        }
+    async fn update(&self, task_id: u64, fields: Value) -> TaudResult<bool> {
+        debug!(target: "tau", "JsonRpc::update() task {} fields {:?}", task_id, fields);
 
         let ws = self.workspace.lock().await.clone();
-        let task = self.check_params_for_update(&params[0], &params[1], ws)?;
+        let task = self.check_params_for_update(task_id, &fields, ws)?;
 
         self.notify_queue_sender.send(task).await.map_err(Error::from)?;
 
-        Ok(json!(true))
+        Ok(true)
     }
 
-    // RPCAPI:
-    // Set state for a task and returns `true` upon success.
-    // --> {"jsonrpc": "2.0", "method": "set_state", "params": [task_id, state], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": true, "id": 1}
-    async fn set_state(&self, params: &[Value]) -> TaudResult<Value> {
+    async fn set_state(&self, task_id: u64, state: String) -> TaudResult<bool> {
         // Allowed states for a task
         let states = ["stop", "start", "open", "pause"];
 
-        debug!(target: "tau", "JsonRpc::set_state() params {:?}", params);
+        debug!(target: "tau", "JsonRpc::set_state() task {} state {}", task_id, state);
 
-        if params.len() != 2 {
-            return Err(TaudError::InvalidData("len of params should be 2".into()))
-        }
-
-        let state: String = serde_json::from_value(params[1].clone())?;
         let ws = self.workspace.lock().await.clone();
 
-        let mut task: TaskInfo = self.load_task_by_id(&params[0], ws)?;
+        let mut task: TaskInfo = self.load_task_by_id(task_id, ws)?;
 
         if states.contains(&state.as_str()) {
             task.set_state(&state);
@@ -216,60 +241,38 @@ impl JsonRpcInterface {
 
         self.notify_queue_sender.send(task).await.map_err(Error::from)?;
 
-        Ok(json!(true))
+        Ok(true)
     }
 
-    // RPCAPI:
-    // Set comment for a task and returns `true` upon success.
-    // --> {"jsonrpc": "2.0", "method": "set_comment", "params": [task_id, comment_content], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": true, "id": 1}
-    async fn set_comment(&self, params: &[Value]) -> TaudResult<Value> {
-        debug!(target: "tau", "JsonRpc::set_comment() params {:?}", params);
-
-        if params.len() != 2 {
-            return Err(TaudError::InvalidData("len of params should be 3".into()))
-        }
-
-        let comment_content: String = serde_json::from_value(params[1].clone())?;
+    async fn set_comment(&self, task_id: u64, comment_content: String) -> TaudResult<bool> {
+        debug!(
+            target: "tau",
+            "JsonRpc::set_comment() task {} comment {}", task_id, comment_content
+        );
 
         let ws = self.workspace.lock().await.clone();
-        let mut task: TaskInfo = self.load_task_by_id(&params[0], ws)?;
+        let mut task: TaskInfo = self.load_task_by_id(task_id, ws)?;
 
         task.set_comment(Comment::new(&comment_content, &self.nickname));
 
         self.notify_queue_sender.send(task).await.map_err(Error::from)?;
 
-        Ok(json!(true))
+        Ok(true)
     }
 
-    // RPCAPI:
-    // Get a task by id.
-    // --> {"jsonrpc": "2.0", "method": "get_task_by_id", "params": [task_id], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": "task", "id": 1}
-    async fn get_task_by_id(&self, params: &[Value]) -> TaudResult<Value> {
-        debug!(target: "tau", "JsonRpc::get_task_by_id() params {:?}", params);
-
-        if params.len() != 1 {
-            return Err(TaudError::InvalidData("len of params should be 1".into()))
-        }
+    async fn get_task_by_id(&self, task_id: u64) -> TaudResult<Value> {
+        debug!(target: "tau", "JsonRpc::get_task_by_id() task {}", task_id);
 
         let ws = self.workspace.lock().await.clone();
-        let task: TaskInfo = self.load_task_by_id(&params[0], ws)?;
+        let task: TaskInfo = self.load_task_by_id(task_id, ws)?;
 
         Ok(json!(task))
     }
 
-    // RPCAPI:
-    // Get all tasks.
-    // --> {"jsonrpc": "2.0", "method": "get_stop_tasks", "params": [task_id], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": "task", "id": 1}
-    async fn get_stop_tasks(&self, params: &[Value]) -> TaudResult<Value> {
-        debug!(target: "tau", "JsonRpc::get_stop_tasks() params {:?}", params);
+    async fn get_stop_tasks(&self, month: Option<i64>) -> TaudResult<Value> {
+        debug!(target: "tau", "JsonRpc::get_stop_tasks() month {:?}", month);
 
-        if params.len() != 1 {
-            return Err(TaudError::InvalidData("len of params should be 1".into()))
-        }
-        let month = params[0].as_i64().map(Timestamp);
+        let month = month.map(Timestamp);
         let ws = self.workspace.lock().await.clone();
 
         let tasks = MonthTasks::load_stop_tasks(&self.dataset_path, ws, month.as_ref())?;
@@ -277,22 +280,9 @@ impl JsonRpcInterface {
         Ok(json!(tasks))
     }
 
-    // RPCAPI:
-    // Switch tasks workspace.
-    // --> {"jsonrpc": "2.0", "method": "switch_ws", "params": [workspace], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": "true", "id": 1}
-    async fn switch_ws(&self, params: &[Value]) -> TaudResult<Value> {
-        debug!(target: "tau", "JsonRpc::switch_ws() params {:?}", params);
-
-        if params.len() != 1 {
-            return Err(TaudError::InvalidData("len of params should be 1".into()))
-        }
+    async fn switch_ws(&self, ws: String) -> TaudResult<bool> {
+        debug!(target: "tau", "JsonRpc::switch_ws() workspace {}", ws);
 
-        if !params[0].is_string() {
-            return Err(TaudError::InvalidData("Invalid workspace".into()))
-        }
-
-        let ws = params[0].as_str().unwrap().to_string();
         let mut s = self.workspace.lock().await;
 
         if self.workspaces.contains_key(&ws) {
@@ -301,36 +291,20 @@ impl JsonRpcInterface {
             warn!("Workspace \"{}\" is not configured", ws);
         }
 
-        Ok(json!(true))
+        Ok(true)
     }
 
-    // RPCAPI:
-    // Get workspace.
-    // --> {"jsonrpc": "2.0", "method": "get_ws", "params": [], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": "workspace", "id": 1}
-    async fn get_ws(&self, params: &[Value]) -> TaudResult<Value> {
-        debug!(target: "tau", "JsonRpc::switch_ws() params {:?}", params);
+    async fn get_ws(&self) -> TaudResult<String> {
+        debug!(target: "tau", "JsonRpc::get_ws()");
         let ws = self.workspace.lock().await.clone();
-        Ok(json!(ws))
+        Ok(ws)
     }
 
-    // RPCAPI:
-    // Export tasks.
-    // --> {"jsonrpc": "2.0", "method": "export_to", "params": [path], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": "true", "id": 1}
-    async fn export_to(&self, params: &[Value]) -> TaudResult<Value> {
-        debug!(target: "tau", "JsonRpc::export_to() params {:?}", params);
-
-        if params.len() != 1 {
-            return Err(TaudError::InvalidData("len of params should be 1".into()))
-        }
-
-        if !params[0].is_string() {
-            return Err(TaudError::InvalidData("Invalid path".into()))
-        }
+    async fn export_to(&self, path: String) -> TaudResult<bool> {
+        debug!(target: "tau", "JsonRpc::export_to() path {}", path);
 
         // mkdir datastore_path if not exists
-        let path = expand_path(params[0].as_str().unwrap())?.join("exported_tasks");
+        let path = expand_path(&path)?.join("exported_tasks");
         create_dir_all(path.join("month")).map_err(Error::from)?;
         create_dir_all(path.join("task")).map_err(Error::from)?;
 
@@ -341,25 +315,13 @@ impl JsonRpcInterface {
             task.save(&path)?;
         }
 
-        Ok(json!(true))
+        Ok(true)
     }
 
-    // RPCAPI:
-    // Import tasks.
-    // --> {"jsonrpc": "2.0", "method": "import_from", "params": [path], "id": 1}
-    // <-- {"jsonrpc": "2.0", "result": "true", "id": 1}
-    async fn import_from(&self, params: &[Value]) -> TaudResult<Value> {
-        debug!(target: "tau", "JsonRpc::import_from() params {:?}", params);
-
-        if params.len() != 1 {
-            return Err(TaudError::InvalidData("len of params should be 1".into()))
-        }
-
-        if !params[0].is_string() {
-            return Err(TaudError::InvalidData("Invalid path".into()))
-        }
+    async fn import_from(&self, path: String) -> TaudResult<bool> {
+        debug!(target: "tau", "JsonRpc::import_from() path {}", path);
 
-        let path = expand_path(params[0].as_str().unwrap())?.join("exported_tasks");
+        let path = expand_path(&path)?.join("exported_tasks");
         let ws = self.workspace.lock().await.clone();
 
         let mut task_ids: Vec<u32> =
@@ -383,11 +345,10 @@ impl JsonRpcInterface {
             task_ids.push(task.id);
             self.notify_queue_sender.send(task).await.map_err(Error::from)?;
         }
-        Ok(json!(true))
+        Ok(true)
     }
 
-    fn load_task_by_id(&self, task_id: &Value, ws: String) -> TaudResult<TaskInfo> {
-        let task_id: u64 = serde_json::from_value(task_id.clone())?;
+    fn load_task_by_id(&self, task_id: u64, ws: String) -> TaudResult<TaskInfo> {
         let tasks = MonthTasks::load_current_tasks(&self.dataset_path, ws, false)?;
         let task = tasks.into_iter().find(|t| (t.get_id() as u64) == task_id);
 
@@ -396,7 +357,7 @@ impl JsonRpcInterface {
 
     fn check_params_for_update(
         &self,
-        task_id: &Value,
+        task_id: u64,
         fields: &Value,
         ws: String,
     ) -> TaudResult<TaskInfo> {
@@ -37,7 +37,7 @@ use structopt_toml::StructOptToml;
 use darkfi::{
     async_daemonize, net,
     raft::{NetMsg, ProtocolRaft, Raft, RaftSettings},
-    rpc::server::listen_and_serve,
+    rpc::{registry::RegistryHandler, server::listen_and_serve},
     util::path::expand_path,
     Error, Result,
 };
@@ -276,8 +276,10 @@ async fn realmain(settings: Args, executor: Arc<smol::Executor<'_>>) -> Result<(
         workspaces.clone(),
         p2p.clone(),
     ));
+    let rpc_handler =
+        Arc::new(RegistryHandler::new(rpc_interface, JsonRpcInterface::rpc_methods()));
     let _ex = executor.clone();
-    executor.spawn(listen_and_serve(settings.rpc_listen.clone(), rpc_interface, _ex)).detach();
+    executor.spawn(listen_and_serve(settings.rpc_listen.clone(), rpc_handler, _ex)).detach();
 
     //
     // Waiting Exit signal
@@ -17,8 +17,8 @@
  */
 
 use std::{
-    fs::{read_to_string, File},
-    io::Write,
+    fs::{read, read_to_string, File},
+    io::{self, Write},
     process::exit,
 };
 
@@ -26,7 +26,8 @@ use clap::Parser as ClapParser;
 
 use darkfi::{
     cli_desc,
-    zkas::{Analyzer, Compiler, Lexer, Parser, ZkBinary},
+    zk::{stats::Usage, CircuitStats},
+    zkas::{self, Diagnostic, Severity, ZkBinary, ZkasResult, MAX_K},
 };
 
 #[derive(clap::Parser)]
@@ -52,14 +53,166 @@ struct Args {
     #[clap(short = 'e')]
     examine: bool,
 
-    /// ZK script to compile
+    /// Circuit size as `2^k` rows, recorded in the binary.
+    /// Defaults to the smallest size the circuit fits in.
+    #[clap(short = 'k')]
+    k: Option<u32>,
+
+    /// Report the rows and columns used by the circuit
+    #[clap(long)]
+    stats: bool,
+
+    /// Disassemble a compiled binary instead of compiling
+    #[clap(long)]
+    inspect: bool,
+
+    /// ZK script to compile, or binary to inspect
     input: String,
 }
 
+/// Coloured terminal renderer for compiler diagnostics
+struct Emitter<'a> {
+    file: &'a str,
+    lines: Vec<&'a str>,
+}
+
+impl<'a> Emitter<'a> {
+    fn new(file: &'a str, source: &'a str) -> Self {
+        // For nice error reporting, we keep references to source lines.
+        Self { file, lines: source.lines().collect() }
+    }
+
+    fn fmt(&self, diag: &Diagnostic) -> String {
+        let (ln, col) = (diag.span.line, diag.span.column);
+        let mut msg = match self.lines.get(ln.wrapping_sub(1)) {
+            Some(line) => {
+                let err_msg = format!("{} (line {}, column {})", diag.message, ln, col);
+                let dbg_msg = format!("{}:{}:{}: {}", self.file, ln, col, line);
+                let pad = dbg_msg.split(": ").next().unwrap().len() + col + 1;
+                let caret = format!("{:width$}^", "", width = pad);
+                format!("{}\n{}\n{}\n", err_msg, dbg_msg, caret)
+            }
+            None => format!("{}\n\n\n", diag.message),
+        };
+
+        for note in &diag.notes {
+            msg.push_str(&format!("\x1b[1mnote:\x1b[0m {}\n", note));
+        }
+
+        msg
+    }
+
+    fn emit(&self, diag: &Diagnostic) {
+        let stderr = io::stderr();
+        let mut handle = stderr.lock();
+
+        let color = match diag.severity {
+            Severity::Error => "31",
+            Severity::Warning => "33",
+        };
+
+        write!(
+            handle,
+            "\x1b[{};1m{} {}:\x1b[0m {}",
+            color,
+            diag.stage,
+            diag.severity,
+            self.fmt(diag)
+        )
+        .unwrap();
+        handle.flush().unwrap();
+    }
+
+    /// Print the diagnostics of a compilation, and exit if it failed.
+    fn check<T>(&self, result: ZkasResult<(T, Vec<Diagnostic>)>) -> T {
+        match result {
+            Ok((v, warnings)) => {
+                warnings.iter().for_each(|d| self.emit(d));
+                v
+            }
+            Err(diagnostics) => {
+                diagnostics.iter().for_each(|d| self.emit(d));
+                exit(1);
+            }
+        }
+    }
+}
+
+/// Decode a compiled binary and print it as zkas source code.
+fn inspect(filename: &str) -> ! {
+    let bincode = match read(filename) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Error: Failed reading from \"{}\". {}", filename, e);
+            exit(1);
+        }
+    };
+
+    match ZkBinary::decode(&bincode) {
+        Ok(zkbin) => {
+            print!("{}", zkbin.disassemble());
+            exit(0);
+        }
+        Err(e) => {
+            eprintln!("Error: Failed decoding \"{}\". {}", filename, e);
+            exit(1);
+        }
+    }
+}
+
+/// Print the resources used by the circuit, opcode by opcode.
+fn print_stats(zkbin: &ZkBinary, stats: &CircuitStats) {
+    println!("Circuit size: k = {} ({} rows)", stats.k, 1_u64 << stats.k);
+    println!("Rows used: {} (+ {} reserved)", stats.rows, stats.reserved_rows);
+    println!(
+        "Columns: {} advice, {} fixed, {} instance, {} selectors",
+        stats.advice_columns, stats.fixed_columns, stats.instance_columns, stats.selectors
+    );
+    println!("Gates: {}, lookup arguments: {}", stats.gates, stats.lookups);
+    println!();
+
+    println!(
+        "{:>6}  {:<24} {:>8} {:>8} {:>8} {:>8} {:>8}",
+        "line", "opcode", "regions", "rows", "cells", "columns", "lookups"
+    );
+
+    let row = |line: String, name: &str, usage: &Usage| {
+        println!(
+            "{:>6}  {:<24} {:>8} {:>8} {:>8} {:>8} {:>8}",
+            line,
+            name,
+            usage.regions,
+            usage.rows,
+            usage.advice_cells,
+            usage.advice_columns,
+            usage.lookup_rows
+        );
+    };
+
+    row("".to_string(), "(setup)", &stats.setup);
+    for (idx, (opcode, usage)) in stats.opcodes.iter().enumerate() {
+        // Source lines are only known if the binary has debug info
+        let line = match &zkbin.debug_info {
+            Some(debug_info) => debug_info.opcodes[idx].0.to_string(),
+            None => "".to_string(),
+        };
+        row(line, opcode.name(), usage);
+    }
+}
+
 fn main() {
     let args = Args::parse();
 
     let filename = args.input.as_str();
+    if args.inspect {
+        inspect(filename);
+    }
+
+    if let Some(k) = args.k.filter(|k| *k > MAX_K) {
+        eprintln!("Error: k = {} exceeds the maximum of {}", k, MAX_K);
+        exit(1);
+    }
+
     let source = match read_to_string(filename) {
         Ok(v) => v,
         Err(e) => {
@@ -71,49 +224,55 @@ fn main() {
     // Clean up tabs, and convert CRLF to LF.
     let source = source.replace('\t', "    ").replace("\r\n", "\n");
 
-    // ANCHOR: zkas
-    // The lexer goes over the input file and separates its content into
-    // tokens that get fed into a parser.
-    let lexer = Lexer::new(filename, source.chars());
-    let tokens = lexer.lex();
-
-    // The parser goes over the tokens provided by the lexer and builds
-    // the initial AST, not caring much about the semantics, just enforcing
-    // syntax and general structure.
-    let parser = Parser::new(filename, source.chars(), tokens);
-    let (namespace, constants, witnesses, statements) = parser.parse();
-
-    // The analyzer goes through the initial AST provided by the parser and
-    // converts return and variable types to their correct forms, and also
-    // checks that the semantics of the ZK script are correct.
-    let mut analyzer = Analyzer::new(filename, source.chars(), constants, witnesses, statements);
-    analyzer.analyze_types();
-
-    if args.interactive {
-        analyzer.analyze_semantic();
-    }
+    // Every stage reports all the problems it finds as diagnostics,
+    // which we render to the terminal.
+    let emitter = Emitter::new(filename, &source);
+
+    if args.interactive || args.evaluate {
+        let mut analyzer = emitter.check(zkas::analyze(&source));
+
+        if args.interactive {
+            let result = analyzer.analyze_semantic().map(|v| (v, vec![]));
+            emitter.check(result);
+        }
 
-    if args.evaluate {
-        println!("{:#?}", analyzer.constants);
-        println!("{:#?}", analyzer.witnesses);
-        println!("{:#?}", analyzer.statements);
-        println!("{:#?}", analyzer.stack);
-        exit(0);
+        if args.evaluate {
+            println!("{:#?}", analyzer.constants);
+            println!("{:#?}", analyzer.witnesses);
+            println!("{:#?}", analyzer.statements);
+            println!("{:#?}", analyzer.stack);
+            exit(0);
+        }
     }
 
-    let compiler = Compiler::new(
-        filename,
-        source.chars(),
-        namespace,
-        analyzer.constants,
-        analyzer.witnesses,
-        analyzer.statements,
-        analyzer.literals,
-        !args.strip,
-    );
+    let mut bincode = emitter.check(zkas::compile(&source, args.k.unwrap_or(0), !args.strip));
 
-    let bincode = compiler.compile();
-    // ANCHOR_END: zkas
+    // Run the compiled circuit through the zkvm to measure how many rows
+    // it needs, and record the smallest k it fits in, unless one was given.
+    let mut zkbin = ZkBinary::decode(&bincode).unwrap();
+    let stats = match CircuitStats::measure(&zkbin) {
+        Ok(v) => v,
+        Err(e) => {
+            eprintln!("Error: Failed to synthesize the circuit. {}", e);
+            exit(1);
+        }
+    };
+
+    match args.k {
+        Some(k) if k < stats.k => {
+            eprintln!("Error: The circuit needs k >= {}, but k = {} was given", stats.k, k);
+            exit(1);
+        }
+        Some(_) => {}
+        None if stats.k > MAX_K => {
+            eprintln!("Error: The circuit needs k = {}, above the maximum of {}", stats.k, MAX_K);
+            exit(1);
+        }
+        None => {
+            bincode = zkas::compile(&source, stats.k, !args.strip).unwrap().0;
+            zkbin = ZkBinary::decode(&bincode).unwrap();
+        }
+    }
 
     let output = match args.output {
         Some(o) => o,
@@ -135,8 +294,11 @@ fn main() {
 
     println!("Wrote output to {}", &output);
 
+    if args.stats {
+        print_stats(&zkbin, &stats);
+    }
+
     if args.examine {
-        let zkbin = ZkBinary::decode(&bincode).unwrap();
         println!("{:#?}", zkbin);
     }
 }
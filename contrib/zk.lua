@@ -43,6 +43,7 @@ local instruction = token('instruction', word_match{
   'base_add', 'base_mul', 'base_sub',
   'poseidon_hash', 'merkle_root',
   'range_check', 'less_than_strict', 'less_than_loose', 'bool_check',
+  'get_bit', 'cond_select', 'zero_cond', 'is_zero', 'is_equal',
   'witness_base',
   'constrain_equal_base', 'constrain_equal_point',
   'constrain_instance',
@@ -1,5 +1,6 @@
 #!/usr/bin/env python3
-from sys import argv
+import json
+from sys import argv, stdin
 
 
 def main(path):
@@ -61,5 +62,57 @@ def main(path):
         print("```")
 
 
+def schema_type(schema):
+    if "anyOf" in schema:
+        return " or ".join(schema_type(i) for i in schema["anyOf"])
+    if "prefixItems" in schema:
+        return "[%s]" % ", ".join(schema_type(i) for i in schema["prefixItems"])
+    if schema.get("type") == "array":
+        return "[%s]" % schema_type(schema["items"])
+    return schema.get("type", "any")
+
+
+def openrpc(path):
+    """Render the OpenRPC document returned by `rpc.discover`"""
+    f = stdin if path == "-" else open(path, "r")
+    schema = json.load(f)
+
+    groups = {}
+    for method in schema["methods"]:
+        name = method["name"]
+        group = name.split(".")[0] if "." in name else "misc"
+        groups.setdefault(group, []).append(method)
+
+    for (group, methods) in groups.items():
+        print(f"## {group} methods\n")
+        for i in methods:
+            print(f"* [`{i['name']}`](#{i['name'].replace('.', '')})")
+
+        print("\n")
+        for i in methods:
+            print(f"### `{i['name']}`\n")
+            print(f"{i['description']}\n")
+
+            if i["params"]:
+                print("| Param | Type | Required |")
+                print("|-------|------|----------|")
+                for param in i["params"]:
+                    required = "yes" if param["required"] else "no"
+                    print(f"| `{param['name']}` | `{schema_type(param['schema'])}` | {required} |")
+                print()
+
+            result = i["result"]
+            print(f"Returns `{schema_type(result['schema'])}`", end="")
+            print(f": {result['description']}" if "description" in result else ".")
+
+            params = ", ".join(f"<{p['name']}>" for p in i["params"])
+            print("\n```json")
+            print(f'--> {{"jsonrpc": "2.0", "method": "{i["name"]}", "params": [{params}], "id": 1}}')
+            print("```\n")
+
+
 if __name__ == "__main__":
-    main(argv[1])
+    if argv[1] == "--openrpc":
+        openrpc(argv[2])
+    else:
+        main(argv[1])
@@ -155,10 +155,10 @@ fn validate(
         debug!(target: "demo", "{}::exec() contract called", contract_name);
 
         let mut runtime = Runtime::new(wasm_bytes, blockchain.clone(), call.contract_id)?;
-        let update = runtime.exec(&payload)?;
+        let update = runtime.exec(&payload)?.into_result()?;
         updates.push(update);
 
-        let metadata = runtime.metadata(&payload)?;
+        let metadata = runtime.metadata(&payload)?.into_result()?;
         let mut decoder = Cursor::new(&metadata);
         let zk_public_values: Vec<(String, Vec<pallas::Base>)> = Decodable::decode(&mut decoder)?;
         let signature_public_keys: Vec<pallas::Point> = Decodable::decode(&mut decoder)?;
@@ -180,7 +180,7 @@ fn validate(
 
         let mut runtime = Runtime::new(wasm_bytes, blockchain.clone(), call.contract_id)?;
 
-        runtime.apply(&update)?;
+        runtime.apply(&update)?.into_result()?;
     }
 
     Ok(())
@@ -20,6 +20,7 @@
 
 use darkfi::{
     zk::{
+        circuit_k,
         proof::{Proof, ProvingKey, VerifyingKey},
         vm::{Witness, ZkCircuit},
         vm_stack::empty_witnesses,
@@ -46,8 +47,9 @@ fn main() -> Result<()> {
     // Prover
     // ======
     // Bigger k = more rows, but slower circuit
-    // Number of rows is 2^k
-    let k = 13;
+    // Number of rows is 2^k, and zkas records the smallest k that
+    // fits the circuit in the binary.
+    let k = circuit_k(&zkbin)?;
 
     // Witness values
     let value = 42;
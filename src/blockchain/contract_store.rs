@@ -17,13 +17,15 @@ r* This program is distributed in the hope that it will be useful,
  */
 
 use darkfi_sdk::crypto::ContractId;
-use darkfi_serial::{deserialize, serialize};
+use darkfi_serial::{deserialize, serialize, SerialDecodable, SerialEncodable};
 use log::{debug, error};
 
 use crate::{Error, Result};
 
 const SLED_CONTRACTS_TREE: &[u8] = b"_contracts";
 const SLED_BINCODE_TREE: &[u8] = b"_wasm_bincode";
+const SLED_CONTRACT_VERSIONS_TREE: &[u8] = b"_contract_versions";
+const SLED_FROZEN_CONTRACTS_TREE: &[u8] = b"_frozen_contracts";
 
 /// The `WasmStore` is a `sled` tree that stores the wasm bincode for deployed
 /// contracts.
@@ -63,6 +65,124 @@ impl WasmStore {
     }
 }
 
+/// A deployed version of a contract: its wasm bincode, and the zkas circuits
+/// found in its zkas db after it was deployed.
+#[derive(Clone, Debug, PartialEq, Eq, SerialEncodable, SerialDecodable)]
+pub struct ContractVersion {
+    /// Compiled wasm bincode
+    pub wasm_bincode: Vec<u8>,
+    /// zkas circuits, as `(namespace, bincode)` pairs
+    pub zkas_bincodes: Vec<(String, Vec<u8>)>,
+}
+
+/// The `ContractVersionStore` is a `sled` tree that stores the history of
+/// every deployment and upgrade of contracts.
+#[derive(Clone)]
+pub struct ContractVersionStore(sled::Tree);
+
+impl ContractVersionStore {
+    /// Opens or creates a `ContractVersionStore`.
+    /// The layout looks like this:
+    /// ```plaintext
+    ///  tree: "_contract_versions"
+    ///   key: ContractId || version (u32, big-endian)
+    /// value: ContractVersion
+    /// ```
+    /// Versions start at 0 for the initial deployment, and are only appended.
+    pub fn new(db: &sled::Db) -> Result<Self> {
+        let tree = db.open_tree(SLED_CONTRACT_VERSIONS_TREE)?;
+        Ok(Self(tree))
+    }
+
+    fn key(contract_id: &ContractId, version: u32) -> Vec<u8> {
+        let mut key = serialize(contract_id);
+        key.extend_from_slice(&version.to_be_bytes());
+        key
+    }
+
+    /// Append a new version for a given ContractId, and return its number.
+    pub fn insert(&self, contract_id: &ContractId, version: &ContractVersion) -> Result<u32> {
+        let number = match self.latest(contract_id)? {
+            Some(v) => v + 1,
+            None => 0,
+        };
+
+        if let Err(e) = self.0.insert(Self::key(contract_id, number), serialize(version)) {
+            error!(target: "blockchain::contractstore", "Failed to insert ContractVersion: {}", e);
+            return Err(e.into())
+        }
+
+        Ok(number)
+    }
+
+    /// Fetches a given version of a ContractId.
+    /// Returns an error if the version is not found.
+    pub fn get(&self, contract_id: &ContractId, version: u32) -> Result<ContractVersion> {
+        if let Some(found) = self.0.get(Self::key(contract_id, version))? {
+            return Ok(deserialize(&found)?)
+        }
+
+        Err(Error::ContractVersionNotFound(contract_id.to_string(), version))
+    }
+
+    /// Fetches all versions of a ContractId, oldest first.
+    pub fn get_all(&self, contract_id: &ContractId) -> Result<Vec<ContractVersion>> {
+        let mut ret = vec![];
+        for found in self.0.scan_prefix(serialize(contract_id)) {
+            let (_, version) = found?;
+            ret.push(deserialize(&version)?);
+        }
+
+        Ok(ret)
+    }
+
+    /// Returns the number of the latest version of a ContractId, or `None`
+    /// if it was never deployed.
+    pub fn latest(&self, contract_id: &ContractId) -> Result<Option<u32>> {
+        let Some((key, _)) = self.0.scan_prefix(serialize(contract_id)).next_back().transpose()?
+        else {
+            return Ok(None)
+        };
+
+        let version: [u8; 4] = key[key.len() - 4..].try_into().unwrap();
+        Ok(Some(u32::from_be_bytes(version)))
+    }
+}
+
+/// The `FrozenContractStore` is a `sled` tree that stores the contracts which
+/// were frozen, so their wasm bincode can't be upgraded anymore.
+#[derive(Clone)]
+pub struct FrozenContractStore(sled::Tree);
+
+impl FrozenContractStore {
+    /// Opens or creates a `FrozenContractStore`.
+    /// The layout looks like this:
+    /// ```plaintext
+    ///  tree: "_frozen_contracts"
+    ///   key: ContractId
+    /// value: ()
+    /// ```
+    pub fn new(db: &sled::Db) -> Result<Self> {
+        let tree = db.open_tree(SLED_FROZEN_CONTRACTS_TREE)?;
+        Ok(Self(tree))
+    }
+
+    /// Permanently freeze a given ContractId.
+    pub fn freeze(&self, contract_id: &ContractId) -> Result<()> {
+        if let Err(e) = self.0.insert(serialize(contract_id), &[]) {
+            error!(target: "blockchain::contractstore", "Failed to freeze contract: {}", e);
+            return Err(e.into())
+        }
+
+        Ok(())
+    }
+
+    /// Check if a given ContractId is frozen.
+    pub fn is_frozen(&self, contract_id: &ContractId) -> Result<bool> {
+        Ok(self.0.contains_key(serialize(contract_id))?)
+    }
+}
+
 /// The `ContractStateStore` is a `sled` tree that stores pointers to contracts'
 /// databases. See the rustdoc for the impl functions for more info.
 #[derive(Clone)]
@@ -204,3 +324,44 @@ impl ContractStateStore {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use darkfi_sdk::pasta::pallas;
+
+    #[test]
+    fn contract_version_history() -> Result<()> {
+        let db = sled::Config::new().temporary(true).open()?;
+        let store = ContractVersionStore::new(&db)?;
+        let contract_a = ContractId::from(pallas::Base::from(1));
+        let contract_b = ContractId::from(pallas::Base::from(2));
+
+        assert_eq!(store.latest(&contract_a)?, None);
+        assert!(store.get(&contract_a, 0).is_err());
+
+        let v0 = ContractVersion { wasm_bincode: vec![0], zkas_bincodes: vec![] };
+        let v1 = ContractVersion {
+            wasm_bincode: vec![1],
+            zkas_bincodes: vec![("Circuit".to_string(), vec![1, 2, 3])],
+        };
+
+        assert_eq!(store.insert(&contract_a, &v0)?, 0);
+        assert_eq!(store.insert(&contract_b, &v0)?, 0);
+        assert_eq!(store.insert(&contract_a, &v1)?, 1);
+
+        assert_eq!(store.latest(&contract_a)?, Some(1));
+        assert_eq!(store.latest(&contract_b)?, Some(0));
+        assert_eq!(store.get(&contract_a, 1)?, v1);
+        assert_eq!(store.get_all(&contract_a)?, vec![v0.clone(), v1]);
+        assert_eq!(store.get_all(&contract_b)?, vec![v0]);
+
+        let frozen = FrozenContractStore::new(&db)?;
+        assert!(!frozen.is_frozen(&contract_a)?);
+        frozen.freeze(&contract_a)?;
+        assert!(frozen.is_frozen(&contract_a)?);
+        assert!(!frozen.is_frozen(&contract_b)?);
+
+        Ok(())
+    }
+}
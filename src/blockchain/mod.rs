@@ -32,10 +32,12 @@ pub mod slot_checkpoint_store;
 pub use slot_checkpoint_store::SlotCheckpointStore;
 
 pub mod tx_store;
-pub use tx_store::{ErroneousTxStore, TxStore};
+pub use tx_store::{ErroneousTxStore, PendingTx, PendingTxStore, TxStore};
 
 pub mod contract_store;
-pub use contract_store::{ContractStateStore, WasmStore};
+pub use contract_store::{
+    ContractStateStore, ContractVersion, ContractVersionStore, FrozenContractStore, WasmStore,
+};
 
 /// Structure holding all sled trees that define the concept of Blockchain.
 #[derive(Clone)]
@@ -54,10 +56,16 @@ pub struct Blockchain {
     pub transactions: TxStore,
     /// Erroneous transactions sled tree
     pub erroneous_txs: ErroneousTxStore,
+    /// Pending (mempool) transactions sled tree
+    pub pending_txs: PendingTxStore,
     /// Contract states
     pub contracts: ContractStateStore,
     /// Wasm bincodes
     pub wasm_bincode: WasmStore,
+    /// Deployed versions of contracts
+    pub contract_versions: ContractVersionStore,
+    /// Contracts that can't be upgraded anymore
+    pub frozen_contracts: FrozenContractStore,
 }
 
 impl Blockchain {
@@ -69,8 +77,11 @@ impl Blockchain {
         let slot_checkpoints = SlotCheckpointStore::new(db)?;
         let transactions = TxStore::new(db)?;
         let erroneous_txs = ErroneousTxStore::new(db)?;
+        let pending_txs = PendingTxStore::new(db)?;
         let contracts = ContractStateStore::new(db)?;
         let wasm_bincode = WasmStore::new(db)?;
+        let contract_versions = ContractVersionStore::new(db)?;
+        let frozen_contracts = FrozenContractStore::new(db)?;
 
         Ok(Self {
             sled_db: db.clone(),
@@ -80,8 +91,11 @@ impl Blockchain {
             slot_checkpoints,
             transactions,
             erroneous_txs,
+            pending_txs,
             contracts,
             wasm_bincode,
+            contract_versions,
+            frozen_contracts,
         })
     }
 
@@ -16,12 +16,14 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use darkfi_serial::{deserialize, serialize};
+use darkfi_sdk::pasta::pallas;
+use darkfi_serial::{deserialize, serialize, SerialDecodable, SerialEncodable};
 
-use crate::{tx::Transaction, Error, Result};
+use crate::{tx::Transaction, util::time::Timestamp, Error, Result};
 
 const SLED_TX_TREE: &[u8] = b"_transactions";
 const SLED_ERRONEOUS_TX_TREE: &[u8] = b"_erroneous_transactions";
+const SLED_PENDING_TX_TREE: &[u8] = b"_pending_transactions";
 
 /// The `TxStore` is a `sled` tree storing all the blockchain's
 /// transactions where the key is the transaction hash, and the value is
@@ -160,3 +162,76 @@ impl ErroneousTxStore {
         Ok(txs)
     }
 }
+
+/// A transaction waiting in the mempool, along with the data used to
+/// order and index it.
+#[derive(Debug, Clone, PartialEq, SerialEncodable, SerialDecodable)]
+pub struct PendingTx {
+    /// The pending transaction
+    pub tx: Transaction,
+    /// Fee paid by the transaction calls
+    pub fee: u64,
+    /// Time the transaction was received
+    pub received: Timestamp,
+    /// Nullifiers revealed by the transaction
+    pub nullifiers: Vec<pallas::Base>,
+}
+
+impl PendingTx {
+    /// Create a `PendingTx` received at the current time.
+    pub fn new(tx: Transaction, fee: u64, nullifiers: Vec<pallas::Base>) -> Self {
+        Self { tx, fee, received: Timestamp::current_time(), nullifiers }
+    }
+}
+
+/// The `PendingTxStore` is a `sled` tree storing the mempool transactions,
+/// so they survive a restart. The key is the transaction hash, and the
+/// value is the serialized [`PendingTx`].
+#[derive(Clone)]
+pub struct PendingTxStore(sled::Tree);
+
+impl PendingTxStore {
+    /// Opens a new or existing `PendingTxStore` on the given sled database.
+    pub fn new(db: &sled::Db) -> Result<Self> {
+        let tree = db.open_tree(SLED_PENDING_TX_TREE)?;
+        Ok(Self(tree))
+    }
+
+    /// Insert a [`PendingTx`] into the pendingtxstore.
+    /// On success, the function returns the hash of its transaction,
+    /// which is used as the key.
+    pub fn insert(&self, pending: &PendingTx) -> Result<blake3::Hash> {
+        let tx_hash = blake3::hash(&serialize(&pending.tx));
+        self.0.insert(tx_hash.as_bytes(), serialize(pending))?;
+        Ok(tx_hash)
+    }
+
+    /// Remove the given tx hashes from the pendingtxstore. With sled, the
+    /// operation is done as a batch. Missing hashes are ignored.
+    pub fn remove(&self, tx_hashes: &[blake3::Hash]) -> Result<()> {
+        let mut batch = sled::Batch::default();
+
+        for tx_hash in tx_hashes {
+            batch.remove(tx_hash.as_bytes());
+        }
+
+        self.0.apply_batch(batch)?;
+        Ok(())
+    }
+
+    /// Retrieve all pending transactions from the pendingtxstore in the form
+    /// of a tuple (`tx_hash`, `pending_tx`).
+    /// Be careful as this will try to load everything in memory.
+    pub fn get_all(&self) -> Result<Vec<(blake3::Hash, PendingTx)>> {
+        let mut txs = vec![];
+
+        for tx in self.0.iter() {
+            let (key, value) = tx?;
+            let hash_bytes: [u8; 32] = key.as_ref().try_into().unwrap();
+            let pending = deserialize(&value)?;
+            txs.push((hash_bytes.into(), pending));
+        }
+
+        Ok(txs)
+    }
+}
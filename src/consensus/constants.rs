@@ -98,12 +98,15 @@ pub const SYNC_MAX_RETRIES: u64 = 10;
 /// Transactions included in a block cap
 pub const TXS_CAP: usize = 50;
 
+/// Maximum number of transactions kept in the mempool
+pub const MEMPOOL_MAX_TXS: usize = 1000;
+
+/// Time in seconds after which a pending transaction is dropped from the mempool
+pub const MEMPOOL_TX_EXPIRY: u64 = 3600;
+
 /// Block leader reward
 pub const REWARD: u64 = 1;
 
-/// Leader proofs k for zk proof rows (rows=2^k)
-pub const LEADER_PROOF_K: u32 = 13;
-
 // TODO: Describe these constants
 pub const RADIX_BITS: usize = 76;
 
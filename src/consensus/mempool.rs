@@ -0,0 +1,320 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{cmp::Ordering, collections::HashMap, str::FromStr};
+
+use darkfi_sdk::{
+    crypto::{contract_id::MONEY_CONTRACT_ID, ContractId},
+    pasta::{group::ff::PrimeField, pallas},
+};
+use darkfi_serial::serialize;
+use log::{debug, info};
+
+use super::constants::{MEMPOOL_MAX_TXS, MEMPOOL_TX_EXPIRY};
+use crate::{
+    blockchain::{PendingTx, PendingTxStore},
+    tx::Transaction,
+    Error, Result,
+};
+
+/// zkas namespace of the Money contract circuit revealing a coin nullifier
+/// as its first public input.
+const MONEY_BURN_NS: &str = "Burn_V1";
+
+/// Collect the nullifiers revealed by a contract call, given the ZK proof
+/// namespaces and public inputs returned by its metadata.
+pub fn revealed_nullifiers(
+    contract_id: &ContractId,
+    zkp_pub: &[(String, Vec<pallas::Base>)],
+) -> Vec<pallas::Base> {
+    if *contract_id != *MONEY_CONTRACT_ID {
+        return vec![]
+    }
+
+    zkp_pub
+        .iter()
+        .filter(|(namespace, public_inputs)| {
+            namespace == MONEY_BURN_NS && !public_inputs.is_empty()
+        })
+        .map(|(_, public_inputs)| public_inputs[0])
+        .collect()
+}
+
+/// How pending transactions are prioritized, both for block proposals
+/// and for eviction when the mempool is full.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MempoolOrdering {
+    /// Highest fee first, older transactions first on equal fees
+    Fee,
+    /// Older transactions first
+    Age,
+}
+
+impl FromStr for MempoolOrdering {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "fee" => Ok(Self::Fee),
+            "age" => Ok(Self::Age),
+            _ => Err(Error::ParseFailed("Invalid mempool ordering, use \"fee\" or \"age\"")),
+        }
+    }
+}
+
+/// Pool of verified transactions waiting to be included in a block.
+/// Transactions are indexed by their hash and by the nullifiers they
+/// reveal, so double-spends of pending coins are rejected early, and
+/// they are mirrored in a sled tree so they survive a restart.
+pub struct Mempool {
+    /// Persistent copy of the pending transactions
+    store: PendingTxStore,
+    /// Pending transactions indexed by their hash
+    txs: HashMap<blake3::Hash, PendingTx>,
+    /// Revealed nullifiers, pointing to the transaction revealing them
+    nullifiers: HashMap<[u8; 32], blake3::Hash>,
+    /// Maximum number of pending transactions, or 0 for no limit
+    pub max_txs: usize,
+    /// Seconds after which a pending transaction expires
+    pub tx_expiry: u64,
+    /// Priority of pending transactions
+    pub ordering: MempoolOrdering,
+}
+
+impl Mempool {
+    /// Create a `Mempool` loading the transactions found in the given store.
+    pub fn new(store: PendingTxStore) -> Result<Self> {
+        let mut mempool = Self {
+            store,
+            txs: HashMap::new(),
+            nullifiers: HashMap::new(),
+            max_txs: MEMPOOL_MAX_TXS,
+            tx_expiry: MEMPOOL_TX_EXPIRY,
+            ordering: MempoolOrdering::Fee,
+        };
+
+        for (tx_hash, pending) in mempool.store.get_all()? {
+            mempool.index(tx_hash, pending);
+        }
+        debug!(target: "consensus::mempool", "Loaded {} pending transactions", mempool.len());
+
+        Ok(mempool)
+    }
+
+    /// Number of pending transactions.
+    pub fn len(&self) -> usize {
+        self.txs.len()
+    }
+
+    /// Check if there are no pending transactions.
+    pub fn is_empty(&self) -> bool {
+        self.txs.is_empty()
+    }
+
+    /// Check if the mempool contains a given transaction hash.
+    pub fn contains(&self, tx_hash: &blake3::Hash) -> bool {
+        self.txs.contains_key(tx_hash)
+    }
+
+    /// Fetch a pending transaction by its hash.
+    pub fn get(&self, tx_hash: &blake3::Hash) -> Option<&PendingTx> {
+        self.txs.get(tx_hash)
+    }
+
+    /// Find a pending transaction revealing any of the given nullifiers.
+    pub fn conflict(&self, nullifiers: &[pallas::Base]) -> Option<blake3::Hash> {
+        nullifiers.iter().find_map(|n| self.nullifiers.get(&n.to_repr()).copied())
+    }
+
+    /// Add a verified transaction to the mempool.
+    /// Fails if it reveals a nullifier of another pending transaction, or
+    /// if the mempool is full and the transaction has the lowest priority.
+    /// On success, returns the hashes of the transactions evicted to make
+    /// room for it.
+    pub fn insert(&mut self, pending: PendingTx) -> Result<Vec<blake3::Hash>> {
+        let tx_hash = blake3::hash(&serialize(&pending.tx));
+
+        if let Some(other) = self.conflict(&pending.nullifiers) {
+            return Err(Error::MempoolDoubleSpend(
+                tx_hash.to_hex().as_str().to_string(),
+                other.to_hex().as_str().to_string(),
+            ))
+        }
+
+        let mut evicted = vec![];
+        if self.max_txs > 0 && self.txs.len() >= self.max_txs {
+            let (lowest_hash, lowest) = self.ordered().pop().unwrap();
+            if self.compare(&(tx_hash, &pending), &(lowest_hash, lowest)) != Ordering::Less {
+                return Err(Error::MempoolFull(tx_hash.to_hex().as_str().to_string()))
+            }
+
+            info!(target: "consensus::mempool", "Evicting transaction {}", lowest_hash);
+            self.remove(&lowest_hash)?;
+            evicted.push(lowest_hash);
+        }
+
+        self.store.insert(&pending)?;
+        self.index(tx_hash, pending);
+
+        Ok(evicted)
+    }
+
+    /// Remove a transaction from the mempool, returning it if it was found.
+    pub fn remove(&mut self, tx_hash: &blake3::Hash) -> Result<Option<PendingTx>> {
+        let Some(pending) = self.txs.remove(tx_hash) else { return Ok(None) };
+
+        for nullifier in &pending.nullifiers {
+            self.nullifiers.remove(&nullifier.to_repr());
+        }
+        self.store.remove(&[*tx_hash])?;
+
+        Ok(Some(pending))
+    }
+
+    /// Remove the given transactions from the mempool, if they exist.
+    pub fn remove_txs(&mut self, txs: &[Transaction]) -> Result<()> {
+        for tx in txs {
+            self.remove(&blake3::hash(&serialize(tx)))?;
+        }
+
+        Ok(())
+    }
+
+    /// Drop pending transactions older than the expiry time, and return
+    /// their hashes.
+    pub fn prune_expired(&mut self) -> Result<Vec<blake3::Hash>> {
+        let expired: Vec<blake3::Hash> = self
+            .txs
+            .iter()
+            .filter(|(_, pending)| pending.received.elapsed() > self.tx_expiry)
+            .map(|(tx_hash, _)| *tx_hash)
+            .collect();
+
+        for tx_hash in &expired {
+            info!(target: "consensus::mempool", "Dropping expired transaction {}", tx_hash);
+            self.remove(tx_hash)?;
+        }
+
+        Ok(expired)
+    }
+
+    /// Pending transactions with their hashes, from highest to lowest priority.
+    pub fn ordered(&self) -> Vec<(blake3::Hash, &PendingTx)> {
+        let mut ordered: Vec<(blake3::Hash, &PendingTx)> =
+            self.txs.iter().map(|(tx_hash, pending)| (*tx_hash, pending)).collect();
+        ordered.sort_by(|a, b| self.compare(a, b));
+        ordered
+    }
+
+    /// Pending transactions, from highest to lowest priority.
+    pub fn txs(&self) -> Vec<Transaction> {
+        self.ordered().into_iter().map(|(_, pending)| pending.tx.clone()).collect()
+    }
+
+    /// Compare two pending transactions, where [`Ordering::Less`] means the
+    /// first one has the higher priority. Hashes break ties, so the order
+    /// is the same across nodes.
+    fn compare(&self, a: &(blake3::Hash, &PendingTx), b: &(blake3::Hash, &PendingTx)) -> Ordering {
+        let by_fee = b.1.fee.cmp(&a.1.fee);
+        let by_age = a.1.received.0.cmp(&b.1.received.0);
+
+        let ordering = match self.ordering {
+            MempoolOrdering::Fee => by_fee.then(by_age),
+            MempoolOrdering::Age => by_age.then(by_fee),
+        };
+
+        ordering.then_with(|| a.0.as_bytes().cmp(b.0.as_bytes()))
+    }
+
+    /// Add a transaction to the in-memory indexes.
+    fn index(&mut self, tx_hash: blake3::Hash, pending: PendingTx) {
+        for nullifier in &pending.nullifiers {
+            self.nullifiers.insert(nullifier.to_repr(), tx_hash);
+        }
+        self.txs.insert(tx_hash, pending);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::time::Timestamp;
+
+    fn pending(fee: u64, received: i64, nullifiers: &[u64]) -> PendingTx {
+        let tx = Transaction {
+            calls: vec![],
+            gas_limits: vec![],
+            fee,
+            proofs: vec![],
+            signatures: vec![],
+        };
+        let nullifiers = nullifiers.iter().map(|n| pallas::Base::from(*n)).collect();
+        PendingTx { tx, fee, received: Timestamp(received), nullifiers }
+    }
+
+    fn hash(pending: &PendingTx) -> blake3::Hash {
+        blake3::hash(&serialize(&pending.tx))
+    }
+
+    #[test]
+    fn mempool_ordering_and_eviction() -> Result<()> {
+        let db = sled::Config::new().temporary(true).open()?;
+        let now = Timestamp::current_time().0;
+        let mut mempool = Mempool::new(PendingTxStore::new(&db)?)?;
+        mempool.max_txs = 2;
+
+        let cheap = pending(1, now - 10, &[1]);
+        let rich = pending(10, now, &[2]);
+        let richer = pending(20, now, &[3]);
+
+        assert!(mempool.insert(cheap.clone())?.is_empty());
+        assert!(mempool.insert(rich.clone())?.is_empty());
+        assert_eq!(mempool.txs(), vec![rich.tx.clone(), cheap.tx.clone()]);
+
+        // Spending a pending nullifier is rejected
+        assert!(matches!(
+            mempool.insert(pending(30, now, &[2])),
+            Err(Error::MempoolDoubleSpend(..))
+        ));
+
+        // The cheapest transaction gets evicted, and it can't come back while full
+        assert_eq!(mempool.insert(richer.clone())?, vec![hash(&cheap)]);
+        assert!(matches!(mempool.insert(cheap.clone()), Err(Error::MempoolFull(_))));
+        assert!(mempool.conflict(&[pallas::Base::from(1)]).is_none());
+
+        // Ordering by age puts the oldest transaction first
+        mempool.ordering = MempoolOrdering::Age;
+        mempool.remove(&hash(&richer))?;
+        assert_eq!(mempool.insert(cheap.clone())?, vec![]);
+        assert_eq!(mempool.txs(), vec![cheap.tx.clone(), rich.tx.clone()]);
+
+        // Pending transactions survive a reload
+        let mut mempool = Mempool::new(PendingTxStore::new(&db)?)?;
+        assert_eq!(mempool.len(), 2);
+        assert_eq!(mempool.get(&hash(&rich)), Some(&rich));
+        assert_eq!(mempool.conflict(&[pallas::Base::from(2)]), Some(hash(&rich)));
+
+        // Expired transactions are dropped
+        mempool.tx_expiry = 5;
+        assert_eq!(mempool.prune_expired()?, vec![hash(&cheap)]);
+        assert_eq!(mempool.txs(), vec![rich.tx]);
+        assert_eq!(PendingTxStore::new(&db)?.get_all()?.len(), 1);
+
+        Ok(())
+    }
+}
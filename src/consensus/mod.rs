@@ -39,6 +39,14 @@ pub use state::SlotCheckpoint;
 pub mod validator;
 pub use validator::{ValidatorState, ValidatorStatePtr};
 
+/// Pending transactions pool
+pub mod mempool;
+pub use mempool::{Mempool, MempoolOrdering};
+
+/// Transaction verification reports
+pub mod report;
+pub use report::{CallReport, TxReport};
+
 /// P2P net protocols
 pub mod proto;
 
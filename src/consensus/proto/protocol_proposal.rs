@@ -25,10 +25,10 @@ use url::Url;
 use crate::{
     consensus::{BlockProposal, ValidatorStatePtr},
     net::{
-        ChannelPtr, MessageSubscription, P2pPtr, ProtocolBase, ProtocolBasePtr,
-        ProtocolJobsManager, ProtocolJobsManagerPtr,
+        constants::BAN_SCORE_INVALID, ChannelPtr, MessageSubscription, P2pPtr, ProtocolBase,
+        ProtocolBasePtr, ProtocolJobsManager, ProtocolJobsManagerPtr,
     },
-    Result,
+    Error, Result,
 };
 
 pub struct ProtocolProposal {
@@ -36,6 +36,7 @@ pub struct ProtocolProposal {
     jobsman: ProtocolJobsManagerPtr,
     state: ValidatorStatePtr,
     p2p: P2pPtr,
+    channel: ChannelPtr,
     channel_address: Url,
 }
 
@@ -55,9 +56,10 @@ impl ProtocolProposal {
 
         Ok(Arc::new(Self {
             proposal_sub,
-            jobsman: ProtocolJobsManager::new("ProposalProtocol", channel),
+            jobsman: ProtocolJobsManager::new("ProposalProtocol", channel.clone()),
             state,
             p2p,
+            channel,
             channel_address,
         }))
     }
@@ -121,6 +123,20 @@ impl ProtocolProposal {
                         "receive_proposal error: {}",
                         e
                     );
+
+                    // Timing related errors can happen to honest peers,
+                    // but these mean the proposal itself is invalid.
+                    if matches!(
+                        e,
+                        Error::InvalidSignature |
+                            Error::LeaderProofVerification |
+                            Error::ProposalHashesMissmatchError |
+                            Error::ProposalHeadersMissmatchError |
+                            Error::ProposalTxsExceedCapError
+                    ) {
+                        let reason = format!("Invalid proposal: {}", e);
+                        self.channel.increase_ban_score(BAN_SCORE_INVALID, &reason).await;
+                    }
                     continue
                 }
             }
@@ -99,7 +99,7 @@ impl ProtocolSyncConsensus {
             for fork in &lock.consensus.forks {
                 forks.push(fork.clone().into());
             }
-            let unconfirmed_txs = lock.unconfirmed_txs.clone();
+            let unconfirmed_txs = lock.mempool.txs();
             let slot_checkpoints = lock.consensus.slot_checkpoints.clone();
             let mut f_history = vec![];
             for f in &lock.consensus.f_history {
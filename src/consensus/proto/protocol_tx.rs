@@ -26,11 +26,11 @@ use crate::{
     consensus::ValidatorStatePtr,
     net,
     net::{
-        ChannelPtr, MessageSubscription, P2pPtr, ProtocolBase, ProtocolBasePtr,
-        ProtocolJobsManager, ProtocolJobsManagerPtr,
+        constants::BAN_SCORE_INVALID, ChannelPtr, MessageSubscription, P2pPtr, ProtocolBase,
+        ProtocolBasePtr, ProtocolJobsManager, ProtocolJobsManagerPtr,
     },
     tx::Transaction,
-    Result,
+    Error, Result,
 };
 
 pub struct ProtocolTx {
@@ -38,6 +38,7 @@ pub struct ProtocolTx {
     jobsman: ProtocolJobsManagerPtr,
     state: ValidatorStatePtr,
     p2p: P2pPtr,
+    channel: ChannelPtr,
     channel_address: Url,
 }
 
@@ -65,9 +66,10 @@ impl ProtocolTx {
 
         Ok(Arc::new(Self {
             tx_sub,
-            jobsman: ProtocolJobsManager::new("TxProtocol", channel),
+            jobsman: ProtocolJobsManager::new("TxProtocol", channel.clone()),
             state,
             p2p,
+            channel,
             channel_address,
         }))
     }
@@ -102,15 +104,33 @@ impl ProtocolTx {
 
             let tx_copy = (*tx).clone();
 
-            // Nodes use unconfirmed_txs vector as seen_txs pool.
-            if self.state.write().await.append_tx(tx_copy.clone()).await {
-                if let Err(e) = self.p2p.broadcast_with_exclude(tx_copy, &exclude_list).await {
-                    error!(
+            // Nodes use the mempool as seen_txs pool.
+            let appended = self.state.write().await.append_tx(tx_copy.clone()).await;
+            match appended {
+                Ok(true) => {
+                    if let Err(e) = self.p2p.broadcast_with_exclude(tx_copy, &exclude_list).await {
+                        error!(
+                            target: "consensus::protocol_tx::handle_receive_tx()",
+                            "p2p broadcast fail: {}",
+                            e
+                        );
+                    };
+                }
+                Ok(false) => {}
+                // Only transactions no chain state would make valid are the
+                // peer's fault. Others may just be stale, or our failure.
+                Err(Error::MempoolMalformedTx(..)) => {
+                    self.channel
+                        .increase_ban_score(BAN_SCORE_INVALID, "Malformed transaction")
+                        .await
+                }
+                Err(e) => {
+                    debug!(
                         target: "consensus::protocol_tx::handle_receive_tx()",
-                        "p2p broadcast fail: {}",
+                        "Not relaying transaction: {}",
                         e
                     );
-                };
+                }
             }
         }
     }
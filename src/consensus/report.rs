@@ -0,0 +1,244 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use darkfi_sdk::{
+    crypto::{ContractId, PublicKey},
+    pasta::pallas,
+};
+use serde_json::{json, Value};
+
+use crate::{
+    runtime::vm_runtime::{CallError, CallOutcome},
+    tx::{Transaction, ZkpFailure},
+};
+
+/// Result of a verification step. `None` means the step was not reached,
+/// or its result is unknown.
+pub type CheckResult = Option<std::result::Result<(), String>>;
+
+/// Verification of a ZK proof requested by a contract call's metadata
+#[derive(Clone, Debug)]
+pub struct ProofReport {
+    /// zkas namespace of the circuit
+    pub namespace: String,
+    /// Number of public inputs the metadata provided
+    pub public_inputs: usize,
+    /// Result of verifying the proof
+    pub verified: CheckResult,
+}
+
+/// Verification of a single contract call in a transaction
+#[derive(Clone, Debug)]
+pub struct CallReport {
+    /// The contract being called
+    pub contract_id: ContractId,
+    /// ZK proofs requested by the call's metadata
+    pub proofs: Vec<ProofReport>,
+    /// Public keys the call's metadata requested signatures from
+    pub signature_pubkeys: Vec<PublicKey>,
+    /// Result of verifying the call's signatures
+    pub signatures: CheckResult,
+    /// Outcomes of the contract sections that were called, in order
+    pub outcomes: Vec<CallOutcome>,
+    /// Size of the state update produced by "exec"
+    pub update_size: Option<usize>,
+    /// Why the call failed outside of contract execution
+    pub error: Option<String>,
+}
+
+impl CallReport {
+    pub fn new(contract_id: ContractId) -> Self {
+        Self {
+            contract_id,
+            proofs: vec![],
+            signature_pubkeys: vec![],
+            signatures: None,
+            outcomes: vec![],
+            update_size: None,
+            error: None,
+        }
+    }
+
+    /// Describe why the call failed, if it did
+    pub fn failure(&self) -> Option<String> {
+        if let Some(e) = &self.error {
+            return Some(e.clone())
+        }
+
+        self.outcomes.iter().find_map(|o| {
+            o.error.as_ref().map(|e| format!("\"{}\" failed: {}", o.section.name(), e))
+        })
+    }
+
+    fn to_json(&self) -> Value {
+        let proofs: Vec<Value> = self
+            .proofs
+            .iter()
+            .map(|p| {
+                let mut proof = check_json(&p.verified);
+                proof["namespace"] = json!(p.namespace);
+                proof["public_inputs"] = json!(p.public_inputs);
+                proof
+            })
+            .collect();
+
+        let mut signatures = check_json(&self.signatures);
+        signatures["public_keys"] =
+            json!(self.signature_pubkeys.iter().map(|pk| pk.to_string()).collect::<Vec<_>>());
+
+        let sections: Vec<Value> = self.outcomes.iter().map(outcome_json).collect();
+
+        json!({
+            "contract_id": self.contract_id.to_string(),
+            "proofs": proofs,
+            "signatures": signatures,
+            "sections": sections,
+            "update_size": self.update_size,
+            "error": self.failure(),
+        })
+    }
+}
+
+/// Verification of a transaction, as done by
+/// [`ValidatorState::verify_transactions_report`](super::ValidatorState::verify_transactions_report).
+#[derive(Clone, Debug)]
+pub struct TxReport {
+    /// Hash of the transaction
+    pub tx_hash: blake3::Hash,
+    /// Fee set in the transaction
+    pub fee: u64,
+    /// Reports of the calls that were executed
+    pub calls: Vec<CallReport>,
+    /// Gas used by the calls
+    pub gas_used: u64,
+    /// Fee paid by the calls
+    pub fee_paid: u64,
+    /// Nullifiers revealed by the calls
+    pub nullifiers: Vec<pallas::Base>,
+    /// Whether the state updates were written
+    pub applied: bool,
+    /// Why the transaction was rejected, if it was
+    pub error: Option<String>,
+    /// Whether the transaction was rejected for being malformed, e.g. a bad
+    /// proof or signature, which no chain state would make valid
+    pub malformed: bool,
+}
+
+impl TxReport {
+    pub fn new(tx: &Transaction) -> Self {
+        Self {
+            tx_hash: tx.hash(),
+            fee: tx.fee,
+            calls: vec![],
+            gas_used: 0,
+            fee_paid: 0,
+            nullifiers: vec![],
+            applied: false,
+            error: None,
+            malformed: false,
+        }
+    }
+
+    /// Whether the transaction passed verification
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Mark the transaction as rejected. Only the first reason is kept.
+    pub fn fail(&mut self, error: impl ToString) {
+        self.error.get_or_insert_with(|| error.to_string());
+    }
+
+    /// Mark the transaction as rejected for being malformed.
+    pub fn fail_malformed(&mut self, error: impl ToString) {
+        self.malformed = true;
+        self.fail(error);
+    }
+
+    /// Mark the transaction as rejected because of its first failing call.
+    pub fn fail_call(&mut self) {
+        let failure = self.calls.iter().enumerate().find_map(|(i, c)| Some((i, c.failure()?)));
+        match failure {
+            Some((idx, reason)) => self.fail(format!("Call {} failed: {}", idx, reason)),
+            None => self.fail("A call failed"),
+        }
+    }
+
+    /// Record the result of verifying the transaction's ZK proofs. Without a
+    /// failure, all the proofs were verified.
+    pub fn set_proofs(&mut self, failure: Option<&ZkpFailure>) {
+        let Some(failure) = failure else {
+            for proof in self.calls.iter_mut().flat_map(|c| c.proofs.iter_mut()) {
+                proof.verified = Some(Ok(()));
+            }
+            return
+        };
+
+        if let Some((call_idx, proof_idx)) = failure.proof {
+            let proof = self.calls.get_mut(call_idx).and_then(|c| c.proofs.get_mut(proof_idx));
+            if let Some(proof) = proof {
+                proof.verified = Some(Err(failure.error.to_string()));
+            }
+        }
+
+        let error = format!("ZK proof verification failed: {}", failure.error);
+        if failure.malformed {
+            self.fail_malformed(error);
+        } else {
+            self.fail(error);
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "tx_hash": self.tx_hash.to_hex().as_str(),
+            "valid": self.is_ok(),
+            "error": self.error,
+            "fee": self.fee,
+            "fee_paid": self.fee_paid,
+            "gas_used": self.gas_used,
+            "applied": self.applied,
+            "calls": self.calls.iter().map(|c| c.to_json()).collect::<Vec<_>>(),
+        })
+    }
+}
+
+fn check_json(result: &CheckResult) -> Value {
+    match result {
+        None => json!({"verified": null, "error": null}),
+        Some(Ok(())) => json!({"verified": true, "error": null}),
+        Some(Err(e)) => json!({"verified": false, "error": e}),
+    }
+}
+
+fn outcome_json(outcome: &CallOutcome) -> Value {
+    let (code, message) = match &outcome.error {
+        Some(CallError::Contract { code, message, .. }) => (json!(code), json!(message)),
+        Some(CallError::Trap(_)) | None => (Value::Null, Value::Null),
+    };
+
+    json!({
+        "section": outcome.section.name(),
+        "error": outcome.error.as_ref().map(|e| e.to_string()),
+        "code": code,
+        "message": message,
+        "logs": outcome.logs,
+        "gas_used": outcome.gas_used,
+        "fee_paid": outcome.fee_paid,
+    })
+}
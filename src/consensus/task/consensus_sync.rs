@@ -145,7 +145,14 @@ pub async fn consensus_sync_task(p2p: P2pPtr, state: ValidatorStatePtr) -> Resul
     }
     lock.consensus.bootstrap_slot = response.bootstrap_slot;
     lock.consensus.forks = forks;
-    lock.unconfirmed_txs = response.unconfirmed_txs.clone();
+    // Our own pending transactions may have been finalized meanwhile, and
+    // the peer's ones go through the same checks as any received tx.
+    lock.revalidate_mempool().await?;
+    for tx in &response.unconfirmed_txs {
+        if let Err(e) = lock.append_tx(tx.clone()).await {
+            warn!(target: "consensus::consensus_sync", "Ignoring pending transaction from peer: {}", e);
+        }
+    }
     lock.consensus.slot_checkpoints = response.slot_checkpoints.clone();
     lock.consensus.previous_leaders = 1;
     let mut f_history = vec![];
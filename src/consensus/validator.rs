@@ -22,13 +22,15 @@ use async_std::sync::{Arc, RwLock};
 use darkfi_sdk::{
     crypto::{
         constants::MERKLE_DEPTH,
-        contract_id::{DAO_CONTRACT_ID, MONEY_CONTRACT_ID},
+        contract_id::{DAO_CONTRACT_ID, DEPLOY_CONTRACT_ID, MONEY_CONTRACT_ID},
         schnorr::{SchnorrPublic, SchnorrSecret},
-        MerkleNode, PublicKey, SecretKey,
+        ContractId, MerkleNode, PublicKey, SecretKey,
     },
     db::SMART_CONTRACT_ZKAS_DB_NAME,
+    deploy::{DeployFunction, DeployParams, FreezeParams, MAX_WASM_BINCODE_SIZE},
     incrementalmerkletree::{bridgetree::BridgeTree, Tree},
     pasta::{group::ff::PrimeField, pallas},
+    tx::ContractCall,
 };
 use darkfi_serial::{deserialize, serialize, Decodable, Encodable, WriteExt};
 use halo2_proofs::arithmetic::Field;
@@ -39,25 +41,28 @@ use serde_json::json;
 use super::{
     constants,
     lead_coin::LeadCoin,
+    mempool::{revealed_nullifiers, Mempool},
+    report::{CallReport, ProofReport, TxReport},
     state::{ConsensusState, Fork, SlotCheckpoint, StateCheckpoint},
     BlockInfo, BlockProposal, Header, LeadInfo, LeadProof,
 };
 
 use crate::{
-    blockchain::Blockchain,
+    blockchain::{Blockchain, ContractVersion, PendingTx},
     rpc::jsonrpc::JsonNotification,
-    runtime::vm_runtime::Runtime,
+    runtime::{
+        import::db::PendingWrites,
+        vm_runtime::{CallOutcome, Runtime},
+    },
     system::{Subscriber, SubscriberPtr},
     tx::Transaction,
     util::time::Timestamp,
     wallet::WalletPtr,
     zk::{
         proof::{ProvingKey, VerifyingKey},
-        vm::ZkCircuit,
-        vm_stack::empty_witnesses,
+        KeyCache,
     },
-    zkas::ZkBinary,
-    Error, Result,
+    Error, Result, VerifyFailed,
 };
 
 /// Atomic pointer to validator state.
@@ -76,7 +81,7 @@ pub struct ValidatorState {
     /// Canonical (finalized) blockchain
     pub blockchain: Blockchain,
     /// Pending transactions
-    pub unconfirmed_txs: Vec<Transaction>,
+    pub mempool: Mempool,
     /// A map of various subscribers exporting live info from the blockchain
     /// TODO: Instead of JsonNotification, it can be an enum of internal objects,
     ///       and then we don't have to deal with json in this module but only
@@ -86,6 +91,8 @@ pub struct ValidatorState {
     pub verifying_keys: VerifyingKeyMap,
     /// Wallet interface
     pub wallet: WalletPtr,
+    /// Cache of ZK proof keys
+    pub key_cache: Arc<KeyCache>,
     /// Flag signalling node has finished initial sync
     pub synced: bool,
     /// Flag to enable single-node mode
@@ -100,6 +107,7 @@ impl ValidatorState {
         genesis_data: blake3::Hash,
         initial_distribution: u64,
         wallet: WalletPtr,
+        key_cache: Arc<KeyCache>,
         faucet_pubkeys: Vec<PublicKey>,
         enable_participation: bool,
         single_node: bool,
@@ -114,19 +122,12 @@ impl ValidatorState {
             wallet.exec_sql(include_str!("consensus_coin.sql")).await?;
         }
 
-        debug!(target: "consensus::validator", "Generating leader proof keys with k: {}", constants::LEADER_PROOF_K);
+        debug!(target: "consensus::validator", "Generating leader proof keys");
         let bincode = include_bytes!("../../proof/lead.zk.bin");
-        let zkbin = ZkBinary::decode(bincode)?;
-        let witnesses = empty_witnesses(&zkbin);
-        let circuit = ZkCircuit::new(witnesses, zkbin);
-
-        let lead_verifying_key = VerifyingKey::build(constants::LEADER_PROOF_K, &circuit);
+        let lead_verifying_key = key_cache.verifying_key(bincode)?;
         // We only need this proving key if we're going to participate in the consensus.
-        let lead_proving_key = if enable_participation {
-            Some(ProvingKey::build(constants::LEADER_PROOF_K, &circuit))
-        } else {
-            None
-        };
+        let lead_proving_key =
+            if enable_participation { Some(key_cache.proving_key(bincode)?) } else { None };
 
         let blockchain = Blockchain::new(db, genesis_ts, genesis_data)?;
         let consensus = ConsensusState::new(
@@ -139,7 +140,7 @@ impl ValidatorState {
             single_node,
         )?;
 
-        let unconfirmed_txs = vec![];
+        let mempool = Mempool::new(blockchain.pending_txs.clone())?;
 
         // -----NATIVE WASM CONTRACTS-----
         // This is the current place where native contracts are being deployed.
@@ -189,28 +190,30 @@ impl ValidatorState {
             // initialize verifying keys for them.
             info!(target: "consensus::validator", "Creating ZK verifying keys for {} zkas circuits", nc.0);
             info!(target: "consensus::validator", "Looking up zkas db for {} (ContractID: {})", nc.0, nc.1);
-            let zkas_db = blockchain.contracts.lookup(
-                &blockchain.sled_db,
-                &nc.1,
-                SMART_CONTRACT_ZKAS_DB_NAME,
-            )?;
+            let zkas_bincodes = contract_zkas_bincodes(&blockchain, &nc.1)?;
 
             let mut vks = vec![];
-            for i in zkas_db.iter() {
-                info!(target: "consensus::validator", "Iterating over zkas db");
-                let (zkas_ns, zkas_bincode) = i?;
-                info!(target: "consensus::validator", "Deserializing namespace");
-                let zkas_ns: String = deserialize(&zkas_ns)?;
+            for (zkas_ns, zkas_bincode) in zkas_bincodes.iter() {
                 info!(target: "consensus::validator", "Creating VerifyingKey for zkas circuit with namespace {}", zkas_ns);
-                let zkbin = ZkBinary::decode(&zkas_bincode)?;
-                let circuit = ZkCircuit::new(empty_witnesses(&zkbin), zkbin);
-                // FIXME: This k=13 man...
-                let vk = VerifyingKey::build(13, &circuit);
-                vks.push((zkas_ns, vk));
+                let vk = key_cache.verifying_key(zkas_bincode)?;
+                vks.push((zkas_ns.clone(), vk));
             }
 
             info!(target: "consensus::validator", "Finished creating VerifyingKey objects for {} (ContractID: {})", nc.0, nc.1);
             verifying_keys.insert(nc.1.to_bytes(), vks);
+
+            // Native contracts are redeployed on every run, so we only add a
+            // version to their history when something changed.
+            let version = ContractVersion { wasm_bincode: nc.2, zkas_bincodes };
+            let changed = match blockchain.contract_versions.latest(&nc.1)? {
+                Some(v) => blockchain.contract_versions.get(&nc.1, v)? != version,
+                None => true,
+            };
+
+            if changed {
+                let v = blockchain.contract_versions.insert(&nc.1, &version)?;
+                info!(target: "consensus::validator", "Recorded version {} of {}", v, nc.0);
+            }
         }
         info!(target: "consensus::validator", "Finished deployment of native wasm contracts");
         // -----NATIVE WASM CONTRACTS-----
@@ -225,43 +228,95 @@ impl ValidatorState {
             lead_verifying_key,
             consensus,
             blockchain,
-            unconfirmed_txs,
+            mempool,
             subscribers,
             verifying_keys: Arc::new(RwLock::new(verifying_keys)),
             wallet,
+            key_cache,
             synced: false,
             single_node,
         }));
 
+        // Pending transactions loaded from the database might have become
+        // invalid while we were down.
+        state.write().await.revalidate_mempool().await?;
+
         Ok(state)
     }
 
     /// The node retrieves a transaction, validates its state transition,
-    /// and appends it to the unconfirmed transactions list.
-    pub async fn append_tx(&mut self, tx: Transaction) -> bool {
+    /// and appends it to the mempool.
+    /// Returns `false` if the transaction was already seen, if it conflicts
+    /// with a pending transaction, or if the mempool has no room for it,
+    /// and an error if it's invalid.
+    pub async fn append_tx(&mut self, tx: Transaction) -> Result<bool> {
         let tx_hash = blake3::hash(&serialize(&tx));
         let tx_in_txstore = match self.blockchain.transactions.contains(&tx_hash) {
             Ok(v) => v,
             Err(e) => {
                 error!(target: "consensus::validator", "append_tx(): Failed querying txstore: {}", e);
-                return false
+                return Ok(false)
             }
         };
 
-        if self.unconfirmed_txs.contains(&tx) || tx_in_txstore {
+        if self.mempool.contains(&tx_hash) || tx_in_txstore {
             info!(target: "consensus::validator", "append_tx(): We have already seen this tx.");
-            return false
+            return Ok(false)
         }
 
         info!(target: "consensus::validator", "append_tx(): Starting state transition validation");
-        if let Err(e) = self.verify_transactions(&[tx.clone()], false).await {
-            error!(target: "consensus::validator", "append_tx(): Failed to verify transaction: {}", e);
-            return false
+        let report = match self.verify_transactions_report(&[tx.clone()], false).await {
+            Ok((_, mut reports)) => reports.pop().unwrap(),
+            Err(e) => {
+                error!(target: "consensus::validator", "append_tx(): Failed to verify transaction: {}", e);
+                return Err(e)
+            }
         };
 
+        if let Some(e) = report.error {
+            error!(target: "consensus::validator", "append_tx(): Transaction {} is invalid: {}", tx_hash, e);
+            let tx_hash = tx_hash.to_hex().as_str().to_string();
+            if report.malformed {
+                return Err(Error::MempoolMalformedTx(tx_hash, e))
+            }
+            return Err(Error::MempoolInvalidTx(tx_hash, e))
+        }
+
+        let pending = PendingTx::new(tx, report.fee_paid, report.nullifiers);
+        match self.mempool.insert(pending) {
+            Ok(_) => {}
+            Err(e @ (Error::MempoolDoubleSpend(..) | Error::MempoolFull(_))) => {
+                warn!(target: "consensus::validator", "append_tx(): {}", e);
+                return Ok(false)
+            }
+            Err(e) => return Err(e),
+        }
+
         info!(target: "consensus::validator", "append_tx(): Appended tx to mempool");
-        self.unconfirmed_txs.push(tx);
-        true
+        Ok(true)
+    }
+
+    /// Verify the transactions of the mempool against the current state,
+    /// and drop the ones that were already included in a block or are no
+    /// longer valid.
+    pub async fn revalidate_mempool(&mut self) -> Result<()> {
+        self.mempool.prune_expired()?;
+
+        for tx in self.mempool.txs() {
+            let tx_hash = blake3::hash(&serialize(&tx));
+            if self.blockchain.transactions.contains(&tx_hash)? {
+                debug!(target: "consensus::validator", "revalidate_mempool(): Transaction {} is finalized", tx_hash);
+                self.mempool.remove(&tx_hash)?;
+                continue
+            }
+
+            if !self.verify_transactions(&[tx], false).await?.is_empty() {
+                warn!(target: "consensus::validator", "revalidate_mempool(): Dropping invalid transaction {}", tx_hash);
+                self.mempool.remove(&tx_hash)?;
+            }
+        }
+
+        Ok(())
     }
 
     /// Generate a block proposal for the current slot, containing all
@@ -282,6 +337,7 @@ impl ValidatorState {
         }
 
         // Generate proposal
+        self.mempool.prune_expired()?;
         let unproposed_txs = self.unproposed_txs(fork_index);
         let mut tree = BridgeTree::<MerkleNode, MERKLE_DEPTH>::new(100);
         // The following is pretty weird, so something better should be done.
@@ -339,17 +395,17 @@ impl ValidatorState {
         Ok(Some((BlockProposal::new(header, unproposed_txs, lead_info), coin, derived_blind)))
     }
 
-    /// Retrieve all unconfirmed transactions not proposed in previous blocks
-    /// of provided index chain.
+    /// Retrieve the highest priority mempool transactions not proposed in
+    /// previous blocks of provided index chain.
     pub fn unproposed_txs(&self, index: i64) -> Vec<Transaction> {
         let unproposed_txs = if index == -1 {
             // If index is -1 (canonical blockchain) a new fork will be generated,
             // therefore all unproposed transactions can be included in the proposal.
-            self.unconfirmed_txs.clone()
+            self.mempool.txs()
         } else {
             // We iterate over the fork chain proposals to find already proposed
             // transactions and remove them from the local unproposed_txs vector.
-            let mut filtered_txs = self.unconfirmed_txs.clone();
+            let mut filtered_txs = self.mempool.txs();
             let chain = &self.consensus.forks[index as usize];
             for state_checkpoint in &chain.sequence {
                 for tx in &state_checkpoint.proposal.block.txs {
@@ -609,15 +665,9 @@ impl ValidatorState {
         Ok(true)
     }
 
-    /// Remove provided transactions vector from unconfirmed_txs if they exist.
+    /// Remove provided transactions vector from the mempool if they exist.
     pub fn remove_txs(&mut self, transactions: &Vec<Transaction>) -> Result<()> {
-        for tx in transactions {
-            if let Some(pos) = self.unconfirmed_txs.iter().position(|txs| txs == tx) {
-                self.unconfirmed_txs.remove(pos);
-            }
-        }
-
-        Ok(())
+        self.mempool.remove_txs(transactions)
     }
 
     /// Node checks if any of the fork chains can be finalized.
@@ -823,7 +873,7 @@ impl ValidatorState {
         info!(target: "consensus::validator", "consensus: Sending notification about finalized block");
         blocks_subscriber.notify(notif).await;
 
-        info!(target: "consensus::validator", "receive_finalized_block(): Removing block transactions from unconfirmed_txs");
+        info!(target: "consensus::validator", "receive_finalized_block(): Removing block transactions from mempool");
         self.remove_txs(&block.txs)?;
 
         Ok(true)
@@ -873,38 +923,228 @@ impl ValidatorState {
         Ok(())
     }
 
+    /// Check a call to [`DEPLOY_CONTRACT_ID`] against the current state, and
+    /// return the public key whose signature the call needs.
+    fn verify_deploy_call(&self, call: &ContractCall) -> Result<PublicKey> {
+        let Some(func) = call.data.first() else {
+            return Err(VerifyFailed::InvalidDeploy("Missing function".to_string()).into())
+        };
+
+        match DeployFunction::try_from(*func)? {
+            DeployFunction::Deploy => {
+                let params: DeployParams = deserialize(&call.data[1..])?;
+                let contract_id = params.contract_id();
+
+                if self.blockchain.frozen_contracts.is_frozen(&contract_id)? {
+                    let e = format!("Contract {} is frozen", contract_id);
+                    return Err(VerifyFailed::InvalidDeploy(e).into())
+                }
+
+                if params.wasm_bincode.len() > MAX_WASM_BINCODE_SIZE {
+                    let e = format!(
+                        "Wasm bincode of {} bytes exceeds the maximum of {}",
+                        params.wasm_bincode.len(),
+                        MAX_WASM_BINCODE_SIZE
+                    );
+                    return Err(VerifyFailed::InvalidDeploy(e).into())
+                }
+
+                // Make sure the bincode can be instantiated before accepting it
+                Runtime::new(&params.wasm_bincode, self.blockchain.clone(), contract_id)?;
+
+                Ok(params.public_key)
+            }
+
+            DeployFunction::Freeze => {
+                let params: FreezeParams = deserialize(&call.data[1..])?;
+                let contract_id = params.contract_id();
+
+                if self.blockchain.contract_versions.latest(&contract_id)?.is_none() {
+                    let e = format!("Contract {} is not deployed", contract_id);
+                    return Err(VerifyFailed::InvalidDeploy(e).into())
+                }
+
+                if self.blockchain.frozen_contracts.is_frozen(&contract_id)? {
+                    let e = format!("Contract {} is already frozen", contract_id);
+                    return Err(VerifyFailed::InvalidDeploy(e).into())
+                }
+
+                Ok(params.public_key)
+            }
+        }
+    }
+
+    /// Apply a verified call to [`DEPLOY_CONTRACT_ID`]. A deployment of an
+    /// existing contract is an upgrade, which runs its migration and adds a
+    /// new version to its history. The verifying keys of the contract's zkas
+    /// circuits are replaced with the ones of the new version. They're built
+    /// before anything is written, so a contract with circuits we can't build
+    /// keys for is rejected as a whole.
+    async fn apply_deploy_call(&self, call: &ContractCall) -> Result<()> {
+        match DeployFunction::try_from(call.data[0])? {
+            DeployFunction::Deploy => {
+                let params: DeployParams = deserialize(&call.data[1..])?;
+                let contract_id = params.contract_id();
+
+                let mut runtime =
+                    Runtime::new(&params.wasm_bincode, self.blockchain.clone(), contract_id)?;
+
+                match self.blockchain.contract_versions.latest(&contract_id)? {
+                    Some(version) => {
+                        info!(target: "consensus::validator", "Upgrading contract {} from version {}", contract_id, version);
+                        runtime.prepare_upgrade(&params.payload, version)?;
+                    }
+                    None => {
+                        info!(target: "consensus::validator", "Deploying contract {}", contract_id);
+                        runtime.prepare_deploy(&params.payload)?;
+                    }
+                }
+
+                // The zkas db as the deployment leaves it, if it touched it at all
+                let zkas_bincodes = match runtime.pending_db(SMART_CONTRACT_ZKAS_DB_NAME)? {
+                    Some(entries) => {
+                        let mut ret = vec![];
+                        for (zkas_ns, zkas_bincode) in entries {
+                            ret.push((deserialize::<String>(&zkas_ns)?, zkas_bincode));
+                        }
+                        ret
+                    }
+                    None => contract_zkas_bincodes(&self.blockchain, &contract_id)?,
+                };
+
+                let mut vks = vec![];
+                for (zkas_ns, zkas_bincode) in zkas_bincodes.iter() {
+                    info!(target: "consensus::validator", "Creating VerifyingKey for zkas circuit with namespace {}", zkas_ns);
+                    vks.push((zkas_ns.clone(), self.key_cache.verifying_key(zkas_bincode)?));
+                }
+
+                runtime.write_deployment()?;
+                self.verifying_keys.write().await.insert(contract_id.to_bytes(), vks);
+
+                let version = ContractVersion { wasm_bincode: params.wasm_bincode, zkas_bincodes };
+                let version = self.blockchain.contract_versions.insert(&contract_id, &version)?;
+                info!(target: "consensus::validator", "Contract {} is now at version {}", contract_id, version);
+            }
+
+            DeployFunction::Freeze => {
+                let params: FreezeParams = deserialize(&call.data[1..])?;
+                let contract_id = params.contract_id();
+                self.blockchain.frozen_contracts.freeze(&contract_id)?;
+                info!(target: "consensus::validator", "Contract {} is now frozen", contract_id);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Apply a state update with the update section of the given contract.
+    /// For this we instantiate the runtimes again.
+    // TODO: Optimize this
+    fn apply_update(
+        &self,
+        contract_id: ContractId,
+        calls: &[ContractCall],
+        gas_limit: u64,
+        update: &[u8],
+        pending: &mut PendingWrites,
+    ) -> Result<CallOutcome> {
+        let wasm = self.blockchain.wasm_bincode.get(contract_id)?;
+        info!(target: "consensus::validator", "Found wasm bincode for {}", contract_id);
+
+        let mut runtime = Runtime::new(&wasm, self.blockchain.clone(), contract_id)?;
+        runtime.set_tx_calls(calls);
+        runtime.set_gas_limit(gas_limit);
+        runtime.set_pending_writes(std::mem::take(pending));
+        let outcome = runtime.apply(update);
+        *pending = runtime.take_pending_writes();
+        outcome
+    }
+
     /// Validate signatures, wasm execution, and zk proofs for given transactions.
     /// If all of those succeed, try to execute a state update for the contract calls.
     /// Currently the verifications are sequential, and the function will skip a
     /// transaction if any of the verifications fail.
     /// The function takes a boolean called `write` which tells it to actually write
-    /// the state transitions to the database.
+    /// the state transitions to the database. When not writing, the zk proofs of
+    /// all the transactions are verified together at the end, in batches.
+    /// Returns the transactions that failed verification.
+    pub async fn verify_transactions(
+        &self,
+        txs: &[Transaction],
+        write: bool,
+    ) -> Result<Vec<Transaction>> {
+        Ok(self.verify_transactions_report(txs, write).await?.0)
+    }
+
+    /// Same as [`Self::verify_transactions`], but also returns a [`TxReport`]
+    /// for every given transaction, describing what was verified for each of
+    /// its calls, and why the transaction was rejected, if it was.
     // TODO: Currently we keep erroneous transactions in the vector and blocks,
     //       in order to apply max fee logic in the future, to prevent spamming.
     // TODO: This should be paralellized as if even one tx in the batch fails to verify,
     //       we can skip it.
-    pub async fn verify_transactions(
+    pub async fn verify_transactions_report(
         &self,
         txs: &[Transaction],
         write: bool,
-    ) -> Result<Vec<Transaction>> {
+    ) -> Result<(Vec<Transaction>, Vec<TxReport>)> {
         info!(target: "consensus::validator", "Verifying {} transaction(s)", txs.len());
         let mut erroneous_txs = vec![];
-        for tx in txs {
+        let mut reports = Vec::with_capacity(txs.len());
+        // Transactions with zk proofs left to verify in a batch
+        let mut deferred_zkps = vec![];
+        for (tx_idx, tx) in txs.iter().enumerate() {
             let tx_hash = blake3::hash(&serialize(tx));
             info!(target: "consensus::validator", "Verifying transaction {}", tx_hash);
+            reports.push(TxReport::new(tx));
+            let report = reports.last_mut().unwrap();
 
             // Table of public inputs used for ZK proof verification
             let mut zkp_table = vec![];
             // Table of public keys used for signature verification
             let mut sig_table = vec![];
-            // State updates produced by contract execcution
+            // State updates produced by contract execcution, along with those
+            // of the contracts each call invoked
             let mut updates = vec![];
+            // Gas used and fee paid by the calls
+            let mut gas_used = 0;
+            let mut fee_paid: u64 = 0;
+
+            if let Err(e) = tx.verify_gas_limits() {
+                error!(target: "consensus::validator", "Transaction {} is invalid: {}", tx_hash, e);
+                warn!(target: "consensus::validator", "Skipping transaction {}", tx_hash);
+                report.fail_malformed(e);
+                erroneous_txs.push(tx.clone());
+                continue
+            }
 
             // Iterate over all calls to get the metadata
             let mut skip = false;
             for (idx, call) in tx.calls.iter().enumerate() {
                 info!(target: "consensus::validator", "Executing contract call {}", idx);
+                report.calls.push(CallReport::new(call.contract_id));
+                let call_report = report.calls.last_mut().unwrap();
+
+                // Deploy calls are handled by us instead of a wasm contract. Their
+                // only requirement is a signature from the contract's deploy key.
+                if call.contract_id == *DEPLOY_CONTRACT_ID {
+                    match self.verify_deploy_call(call) {
+                        Ok(signature_pubkey) => {
+                            call_report.signature_pubkeys = vec![signature_pubkey];
+                            zkp_table.push(vec![]);
+                            sig_table.push(vec![signature_pubkey]);
+                            updates.push((vec![], vec![]));
+                            continue
+                        }
+                        Err(e) => {
+                            error!(target: "consensus::validator", "Invalid deploy call {}: {}", idx, e);
+                            call_report.error = Some(e.to_string());
+                            skip = true;
+                            break
+                        }
+                    }
+                }
+
                 let wasm = match self.blockchain.wasm_bincode.get(call.contract_id) {
                     Ok(v) => {
                         info!(target: "consensus::validator", "Found wasm bincode for {}", call.contract_id);
@@ -916,6 +1156,7 @@ impl ValidatorState {
                             "Could not find wasm bincode for contract {}: {}",
                             call.contract_id, e
                         );
+                        call_report.error = Some(e.to_string());
                         skip = true;
                         break
                     }
@@ -936,16 +1177,31 @@ impl ValidatorState {
                                 "Failed to instantiate WASM runtime for contract {}: {}",
                                 call.contract_id, e
                             );
+                            call_report.error = Some(e.to_string());
                             skip = true;
                             break
                         }
                     };
+                runtime.set_tx_calls(&tx.calls);
+                // The "metadata" and "exec" calls share the gas limit of the call
+                runtime.set_gas_limit(tx.gas_limit(idx));
 
                 info!(target: "consensus::validator", "Executing \"metadata\" call");
                 let metadata = match runtime.metadata(&payload) {
-                    Ok(v) => v,
+                    Ok(outcome) if outcome.is_ok() => {
+                        gas_used += outcome.gas_used;
+                        call_report.outcomes.push(outcome.clone());
+                        outcome.return_data
+                    }
+                    Ok(outcome) => {
+                        log_failed_call(&outcome);
+                        call_report.outcomes.push(outcome);
+                        skip = true;
+                        break
+                    }
                     Err(e) => {
                         error!(target: "consensus::validator", "Failed to execute \"metadata\" call: {}", e);
+                        call_report.error = Some(e.to_string());
                         skip = true;
                         break
                     }
@@ -959,6 +1215,7 @@ impl ValidatorState {
                     Ok(v) => v,
                     Err(e) => {
                         error!(target: "consensus::validator", "Failed to decode ZK public inputs from metadata: {}", e);
+                        call_report.error = Some(format!("Invalid metadata: {}", e));
                         skip = true;
                         break
                     }
@@ -968,6 +1225,7 @@ impl ValidatorState {
                     Ok(v) => v,
                     Err(e) => {
                         error!(target: "consensus::validator", "Failed to decode signature pubkeys from metadata: {}", e);
+                        call_report.error = Some(format!("Invalid metadata: {}", e));
                         skip = true;
                         break
                     }
@@ -975,6 +1233,16 @@ impl ValidatorState {
 
                 // TODO: Make sure we've read all the bytes above.
                 info!(target: "consensus::validator", "Successfully executed \"metadata\" call");
+                call_report.proofs = zkp_pub
+                    .iter()
+                    .map(|(namespace, public_inputs)| ProofReport {
+                        namespace: namespace.clone(),
+                        public_inputs: public_inputs.len(),
+                        verified: None,
+                    })
+                    .collect();
+                call_report.signature_pubkeys = sig_pub.clone();
+                report.nullifiers.extend(revealed_nullifiers(&call.contract_id, &zkp_pub));
                 zkp_table.push(zkp_pub);
                 sig_table.push(sig_pub);
 
@@ -982,9 +1250,25 @@ impl ValidatorState {
                 // runtime and the same payload.
                 info!(target: "consensus::validator", "Executing \"exec\" call");
                 match runtime.exec(&payload) {
-                    Ok(v) => {
+                    Ok(outcome) if outcome.is_ok() => {
                         info!(target: "consensus::validator", "Successfully executed \"exec\" call");
-                        updates.push(v);
+                        gas_used += outcome.gas_used;
+                        call_report.update_size = Some(outcome.return_data.len());
+                        call_report.outcomes.push(outcome.clone());
+                        let Some(v) = fee_paid.checked_add(outcome.fee_paid) else {
+                            error!(target: "consensus::validator", "Fee paid by tx {} overflowed", tx_hash);
+                            call_report.error = Some("Fee paid overflowed".to_string());
+                            skip = true;
+                            break
+                        };
+                        fee_paid = v;
+                        updates.push((outcome.return_data, outcome.invoked_updates));
+                    }
+                    Ok(outcome) => {
+                        log_failed_call(&outcome);
+                        call_report.outcomes.push(outcome);
+                        skip = true;
+                        break
                     }
                     Err(e) => {
                         error!(
@@ -992,18 +1276,32 @@ impl ValidatorState {
                             "Failed to execute \"exec\" call for contract id {}: {}",
                             call.contract_id, e
                         );
+                        call_report.error = Some(e.to_string());
                         skip = true;
                         break
                     }
                 };
                 // At this point we're done with the call and move on to the next one.
             }
+            report.gas_used = gas_used;
+            report.fee_paid = fee_paid;
             if skip {
                 warn!(target: "consensus::validator", "Skipping transaction {}", tx_hash);
+                report.fail_call();
                 erroneous_txs.push(tx.clone());
                 continue
             }
 
+            if fee_paid != tx.fee {
+                let e = VerifyFailed::FeeMismatch(fee_paid, tx.fee);
+                error!(target: "consensus::validator", "Transaction {} is invalid: {}", tx_hash, e);
+                warn!(target: "consensus::validator", "Skipping transaction {}", tx_hash);
+                report.fail(e);
+                erroneous_txs.push(tx.clone());
+                continue
+            }
+            info!(target: "consensus::validator", "Transaction {} used {} gas and paid a fee of {}", tx_hash, gas_used, fee_paid);
+
             // When we're done looping and executing over the tx's contract calls, we
             // move on with verification. First we verify the signatures as that's
             // cheaper, and then finally we verify the ZK proofs.
@@ -1011,97 +1309,141 @@ impl ValidatorState {
             if sig_table.len() != tx.signatures.len() {
                 error!(target: "consensus::validator", "Incorrect number of signatures in tx {}", tx_hash);
                 warn!(target: "consensus::validator", "Skipping transaction {}", tx_hash);
+                report.fail_malformed(format!(
+                    "Transaction has signatures for {} calls, but {} calls",
+                    tx.signatures.len(),
+                    sig_table.len()
+                ));
                 erroneous_txs.push(tx.clone());
                 continue
             }
 
-            match tx.verify_sigs(sig_table) {
-                Ok(()) => {
-                    info!(target: "consensus::validator", "Signatures verification for tx {} successful", tx_hash)
-                }
+            let sig_results = match tx.verify_call_sigs(&sig_table) {
+                Ok(v) => v,
                 Err(e) => {
                     error!(target: "consensus::validator", "Signature verification for tx {} failed: {}", tx_hash, e);
                     warn!(target: "consensus::validator", "Skipping transaction {}", tx_hash);
+                    report.fail(e);
                     erroneous_txs.push(tx.clone());
                     continue
                 }
             };
 
+            for (call_report, result) in report.calls.iter_mut().zip(sig_results.iter()) {
+                call_report.signatures = Some(result.as_ref().map_err(|e| e.to_string()).copied());
+            }
+
+            if let Some(idx) = sig_results.iter().position(|r| r.is_err()) {
+                error!(target: "consensus::validator", "Signature verification for tx {} failed on call {}", tx_hash, idx);
+                warn!(target: "consensus::validator", "Skipping transaction {}", tx_hash);
+                report.fail_malformed(format!("Signature verification failed for call {}", idx));
+                erroneous_txs.push(tx.clone());
+                continue
+            }
+            info!(target: "consensus::validator", "Signatures verification for tx {} successful", tx_hash);
+
             // NOTE: When it comes to the ZK proofs, we first do a lookup of the
             // verifying keys, but if we do not find them, we'll generate them
             // inside of this function. This can be kinda expensive, so open to
             // alternatives.
-            info!(target: "consensus::validator", "Verifying ZK proofs for transaction {}", tx_hash);
-            match tx.verify_zkps(self.verifying_keys.clone(), zkp_table).await {
-                Ok(()) => {
-                    info!(target: "consensus::validator", "ZK proof verification for tx {} successful", tx_hash)
-                }
-                Err(e) => {
-                    error!(target: "consensus::validator", "ZK proof verification for tx {} failed: {}", tx_hash, e);
-                    warn!(target: "consensus::validator", "Skipping transaction {}", tx_hash);
-                    erroneous_txs.push(tx.clone());
-                    continue
-                }
-            };
+            // When we're not writing, no transaction's state updates are visible
+            // to the following ones, so we leave the ZK proofs for the end, and
+            // verify those of all the transactions together.
+            if write {
+                info!(target: "consensus::validator", "Verifying ZK proofs for transaction {}", tx_hash);
+                let batch = [(tx, &zkp_table)];
+                match Transaction::verify_zkps_batch(&batch, self.verifying_keys.clone())
+                    .await
+                    .pop()
+                {
+                    None => {
+                        info!(target: "consensus::validator", "ZK proof verification for tx {} successful", tx_hash);
+                        report.set_proofs(None);
+                    }
+                    Some(failure) => {
+                        error!(target: "consensus::validator", "ZK proof verification for tx {} failed: {}", tx_hash, failure.error);
+                        warn!(target: "consensus::validator", "Skipping transaction {}", tx_hash);
+                        report.set_proofs(Some(&failure));
+                        erroneous_txs.push(tx.clone());
+                        continue
+                    }
+                };
+            } else {
+                info!(target: "consensus::validator", "Deferring ZK proof verification for transaction {}", tx_hash);
+                deferred_zkps.push((tx_idx, tx, zkp_table));
+            }
 
             // After the verifications stage passes, if we're told to write, we
             // apply the state updates.
             assert!(tx.calls.len() == updates.len());
             if write {
                 info!(target: "consensus::validator", "Performing state updates");
-                for (call, update) in tx.calls.iter().zip(updates.iter()) {
-                    // For this we instantiate the runtimes again.
-                    // TODO: Optimize this
-                    // TODO: Sum up the gas costs of previous calls during execution
-                    //       and verification and these.
-                    let wasm = match self.blockchain.wasm_bincode.get(call.contract_id) {
-                        Ok(v) => {
-                            info!(target: "consensus::validator", "Found wasm bincode for {}", call.contract_id);
-                            v
-                        }
-                        Err(e) => {
-                            error!(
-                                target: "consensus::validator",
-                                "Could not find wasm bincode for contract {}: {}",
-                                call.contract_id, e
-                            );
+                for (idx, (call, update)) in tx.calls.iter().zip(updates.iter()).enumerate() {
+                    let call_report = &mut report.calls[idx];
+                    if call.contract_id == *DEPLOY_CONTRACT_ID {
+                        if let Err(e) = self.apply_deploy_call(call).await {
+                            error!(target: "consensus::validator", "Failed to apply deploy call: {}", e);
+                            call_report.error = Some(e.to_string());
                             skip = true;
                             break
                         }
-                    };
+                        continue
+                    }
 
-                    let mut runtime =
-                        match Runtime::new(&wasm, self.blockchain.clone(), call.contract_id) {
-                            Ok(v) => v,
+                    // The call's own update is applied first, and then those of the
+                    // contracts it invoked, sharing the gas limit of the call. Their
+                    // writes are only written once all of them succeeded.
+                    let (update, invoked_updates) = update;
+                    let mut gas_left = tx.gas_limit(idx);
+                    let mut pending = PendingWrites::default();
+                    let call_updates = std::iter::once((call.contract_id, update))
+                        .chain(invoked_updates.iter().map(|(contract_id, u)| (*contract_id, u)));
+                    for (contract_id, update) in call_updates {
+                        info!(target: "consensus::validator", "Executing \"apply\" call of contract {}", contract_id);
+                        match self.apply_update(
+                            contract_id,
+                            &tx.calls,
+                            gas_left,
+                            update,
+                            &mut pending,
+                        ) {
+                            Ok(outcome) if outcome.is_ok() => {
+                                info!(target: "consensus::validator", "State update applied successfully");
+                                gas_left = gas_left.saturating_sub(outcome.gas_used);
+                                call_report.outcomes.push(outcome);
+                            }
+                            Ok(outcome) => {
+                                log_failed_call(&outcome);
+                                call_report.outcomes.push(outcome);
+                                skip = true;
+                                break
+                            }
                             Err(e) => {
-                                error!(
-                                    target: "consensus::validator",
-                                    "Failed to instantiate WASM runtime for contract {}: {}",
-                                    call.contract_id, e
-                                );
+                                error!(target: "consensus::validator", "Failed to apply state update of contract {}: {}", contract_id, e);
+                                call_report.error = Some(e.to_string());
                                 skip = true;
                                 break
                             }
                         };
+                    }
+                    if skip {
+                        break
+                    }
 
-                    info!(target: "consensus::validator", "Executing \"apply\" call");
-                    match runtime.apply(update) {
-                        // TODO: FIXME: This should be done in an atomic tx/batch
-                        Ok(()) => {
-                            info!(target: "consensus::validator", "State update applied successfully")
-                        }
-                        Err(e) => {
-                            error!(target: "consensus::validator", "Failed to apply state update: {}", e);
-                            skip = true;
-                            break
-                        }
-                    };
+                    if let Err(e) = pending.write() {
+                        error!(target: "consensus::validator", "Failed to write state updates: {}", e);
+                        call_report.error = Some(e.to_string());
+                        skip = true;
+                        break
+                    }
                 }
                 if skip {
                     warn!(target: "consensus::validator", "Skipping transaction {}", tx_hash);
+                    report.fail_call();
                     erroneous_txs.push(tx.clone());
                     continue
                 }
+                report.applied = true;
             } else {
                 info!(target: "consensus::validator", "Skipping apply of state updates because write=false");
             }
@@ -1109,7 +1451,26 @@ impl ValidatorState {
             info!(target: "consensus::validator", "Transaction {} verified successfully", tx_hash);
         }
 
-        Ok(erroneous_txs)
+        if !deferred_zkps.is_empty() {
+            info!(target: "consensus::validator", "Verifying ZK proofs for {} transaction(s)", deferred_zkps.len());
+            let batch: Vec<_> = deferred_zkps.iter().map(|(_, tx, table)| (*tx, table)).collect();
+            let failures =
+                Transaction::verify_zkps_batch(&batch, self.verifying_keys.clone()).await;
+
+            for (batch_idx, (tx_idx, tx, _)) in deferred_zkps.iter().enumerate() {
+                let failure = failures.iter().find(|f| f.tx_idx == batch_idx);
+                reports[*tx_idx].set_proofs(failure);
+
+                if let Some(failure) = failure {
+                    let tx_hash = blake3::hash(&serialize(*tx));
+                    error!(target: "consensus::validator", "ZK proof verification for tx {} failed: {}", tx_hash, failure.error);
+                    warn!(target: "consensus::validator", "Skipping transaction {}", tx_hash);
+                    erroneous_txs.push((*tx).clone());
+                }
+            }
+        }
+
+        Ok((erroneous_txs, reports))
     }
 
     /// Append to canonical state received finalized slot checkpoints from block sync task.
@@ -1156,3 +1517,40 @@ impl ValidatorState {
         Ok(true)
     }
 }
+
+/// Read the zkas circuits a deployed contract keeps in its zkas db, as
+/// `(namespace, bincode)` pairs. Contracts without circuits have none.
+fn contract_zkas_bincodes(
+    blockchain: &Blockchain,
+    contract_id: &ContractId,
+) -> Result<Vec<(String, Vec<u8>)>> {
+    let zkas_db = match blockchain.contracts.lookup(
+        &blockchain.sled_db,
+        contract_id,
+        SMART_CONTRACT_ZKAS_DB_NAME,
+    ) {
+        Ok(v) => v,
+        Err(Error::ContractNotFound(_)) | Err(Error::ContractStateNotFound) => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+
+    let mut ret = vec![];
+    for i in zkas_db.iter() {
+        let (zkas_ns, zkas_bincode) = i?;
+        let zkas_ns: String = deserialize(&zkas_ns)?;
+        ret.push((zkas_ns, zkas_bincode.to_vec()));
+    }
+
+    Ok(ret)
+}
+
+/// Log why a contract call failed, along with what the contract logged.
+fn log_failed_call(outcome: &CallOutcome) {
+    let section = outcome.section.name();
+    if let Some(e) = &outcome.error {
+        error!(target: "consensus::validator", "Failed to execute \"{}\" call: {}", section, e);
+    }
+    for msg in &outcome.logs {
+        debug!(target: "consensus::validator", "Contract log: {}", msg);
+    }
+}
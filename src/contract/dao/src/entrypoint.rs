@@ -26,7 +26,7 @@ use darkfi_sdk::{
     db::{
         db_contains_key, db_del, db_get, db_init, db_lookup, db_set, SMART_CONTRACT_ZKAS_DB_NAME,
     },
-    error::{ContractError, ContractResult},
+    error::ContractResult,
     merkle_add, msg, set_return_data, ContractCall,
 };
 use darkfi_serial::{deserialize, serialize, Decodable, Encodable, WriteExt};
@@ -41,6 +41,7 @@ use crate::{
         DaoBlindAggregateVote, DaoExecParams, DaoExecUpdate, DaoMintParams, DaoMintUpdate,
         DaoProposeParams, DaoProposeUpdate, DaoVoteParams, DaoVoteUpdate,
     },
+    error::DaoError,
     DaoFunction, DAO_CONTRACT_ZKAS_DAO_EXEC_NS, DAO_CONTRACT_ZKAS_DAO_MINT_NS,
     DAO_CONTRACT_ZKAS_DAO_PROPOSE_BURN_NS, DAO_CONTRACT_ZKAS_DAO_PROPOSE_MAIN_NS,
     DAO_CONTRACT_ZKAS_DAO_VOTE_BURN_NS, DAO_CONTRACT_ZKAS_DAO_VOTE_MAIN_NS,
@@ -173,7 +174,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
             let bulla_db = db_lookup(cid, DB_DAO_BULLAS)?;
             if db_contains_key(bulla_db, &serialize(&dao_bulla))? {
                 msg!("DAO already exists: {:?}", dao_bulla);
-                return Err(ContractError::Custom(1))
+                return Err(DaoError::DaoAlreadyExists.into())
             }
 
             let update = DaoMintUpdate { dao_bulla: params.dao_bulla };
@@ -195,7 +196,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
             for input in &params.inputs {
                 if !db_contains_key(coin_roots_db, &serialize(&input.merkle_root))? {
                     msg!("Invalid input Merkle root: {}", input.merkle_root);
-                    return Err(ContractError::Custom(2))
+                    return Err(DaoError::InvalidInputMerkleRoot.into())
                 }
             }
 
@@ -203,7 +204,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
             let dao_roots_db = db_lookup(cid, DB_DAO_MERKLE_ROOTS)?;
             if !db_contains_key(dao_roots_db, &serialize(&params.dao_merkle_root))? {
                 msg!("Invalid DAO Merkle root: {}", params.dao_merkle_root);
-                return Err(ContractError::Custom(3))
+                return Err(DaoError::InvalidDaoMerkleRoot.into())
             }
 
             let proposal_db = db_lookup(cid, DB_PROPOSAL_BULLAS)?;
@@ -211,7 +212,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
             // Otherwise it will reset voting again
             if db_contains_key(proposal_db, &serialize(&params.proposal_bulla))? {
                 msg!("Proposal already exists: {:?}", params.proposal_bulla);
-                return Err(ContractError::Custom(4))
+                return Err(DaoError::ProposalAlreadyExists.into())
             }
 
             let update = DaoProposeUpdate { proposal_bulla: params.proposal_bulla };
@@ -233,7 +234,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
             let proposal_votes_db = db_lookup(cid, DB_PROPOSAL_BULLAS)?;
             let Some(proposal_votes) = db_get(proposal_votes_db, &serialize(&params.proposal_bulla))? else {
                 msg!("Invalid proposal {:?}", params.proposal_bulla);
-                return Err(ContractError::Custom(4))
+                return Err(DaoError::ProposalNonexistent.into())
             };
             let mut proposal_votes: DaoBlindAggregateVote = deserialize(&proposal_votes)?;
 
@@ -247,12 +248,12 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
             for input in &params.inputs {
                 if !db_contains_key(money_roots_db, &serialize(&input.merkle_root))? {
                     msg!("Invalid input Merkle root: {:?}", input.merkle_root);
-                    return Err(ContractError::Custom(5))
+                    return Err(DaoError::InvalidInputMerkleRoot.into())
                 }
 
                 if db_contains_key(money_nullifier_db, &serialize(&input.nullifier))? {
                     msg!("Coin is already spent");
-                    return Err(ContractError::Custom(6))
+                    return Err(DaoError::CoinAlreadySpent.into())
                 }
 
                 // Prefix nullifier with proposal bulla so nullifiers from different proposals
@@ -263,7 +264,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
                     db_contains_key(dao_vote_nulls_db, &null_key)?
                 {
                     msg!("Attempted double vote");
-                    return Err(ContractError::Custom(7))
+                    return Err(DaoError::DoubleVote.into())
                 }
 
                 proposal_votes.all_vote_commit += input.vote_commit;
@@ -324,7 +325,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
             let proposal_db = db_lookup(cid, DB_PROPOSAL_BULLAS)?;
             let Some(proposal_votes) = db_get(proposal_db, &serialize(&params.proposal))? else {
                 msg!("Proposal {:?} not found in db", params.proposal);
-                return Err(ContractError::Custom(1));
+                return Err(DaoError::ProposalNonexistent.into());
             };
             let proposal_votes: DaoBlindAggregateVote = deserialize(&proposal_votes)?;
 
@@ -0,0 +1,53 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use darkfi_sdk::error::ContractError;
+
+/// Errors returned by the DAO contract. They reach the runtime as
+/// [`ContractError::Custom`] with the variant's code, along with its message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum DaoError {
+    DaoAlreadyExists = 1,
+    InvalidInputMerkleRoot = 2,
+    InvalidDaoMerkleRoot = 3,
+    ProposalAlreadyExists = 4,
+    ProposalNonexistent = 5,
+    CoinAlreadySpent = 6,
+    DoubleVote = 7,
+}
+
+impl DaoError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::DaoAlreadyExists => "DAO already exists",
+            Self::InvalidInputMerkleRoot => "Invalid input Merkle root",
+            Self::InvalidDaoMerkleRoot => "Invalid DAO Merkle root",
+            Self::ProposalAlreadyExists => "Proposal already exists",
+            Self::ProposalNonexistent => "Proposal does not exist",
+            Self::CoinAlreadySpent => "Coin is already spent",
+            Self::DoubleVote => "Attempted double vote",
+        }
+    }
+}
+
+impl From<DaoError> for ContractError {
+    fn from(err: DaoError) -> Self {
+        Self::custom(err as u32, err.message())
+    }
+}
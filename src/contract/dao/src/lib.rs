@@ -30,6 +30,9 @@ pub mod dao_client;
 
 pub mod dao_model;
 
+/// Errors returned by the contract
+pub mod error;
+
 #[cfg(feature = "client")]
 /// Transaction building API for clients interacting with money contract
 pub mod money_client;
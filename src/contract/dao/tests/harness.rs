@@ -15,7 +15,7 @@
  * You should have received a copy of the GNU Affero General Public License
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use darkfi::{
     consensus::{
@@ -23,7 +23,7 @@ use darkfi::{
         TESTNET_GENESIS_TIMESTAMP, TESTNET_INITIAL_DISTRIBUTION,
     },
     wallet::WalletDb,
-    zk::{empty_witnesses, ProvingKey, ZkCircuit},
+    zk::{KeyCache, ProvingKey},
     zkas::ZkBinary,
     Result,
 };
@@ -119,6 +119,8 @@ impl DaoTestHarness {
 
         let alice_sled_db = sled::Config::new().temporary(true).open()?;
 
+        let key_cache = Arc::new(KeyCache::new(None)?);
+
         let alice_state = ValidatorState::new(
             &alice_sled_db,
             *TESTNET_BOOTSTRAP_TIMESTAMP,
@@ -126,6 +128,7 @@ impl DaoTestHarness {
             *TESTNET_GENESIS_HASH_BYTES,
             *TESTNET_INITIAL_DISTRIBUTION,
             alice_wallet,
+            key_cache.clone(),
             faucet_pubkeys,
             false,
             false,
@@ -149,71 +152,50 @@ impl DaoTestHarness {
 
         info!(target: "dao", "Decoding bincode");
 
-        let money_mint_zkbin =
+        let money_mint_bincode =
             money_db_handle.get(&serialize(&MONEY_CONTRACT_ZKAS_MINT_NS_V1))?.unwrap();
-        let money_mint_zkbin = ZkBinary::decode(&money_mint_zkbin)?;
-        let money_mint_witnesses = empty_witnesses(&money_mint_zkbin);
-        let money_mint_circuit = ZkCircuit::new(money_mint_witnesses, money_mint_zkbin.clone());
+        let money_mint_zkbin = ZkBinary::decode(&money_mint_bincode)?;
 
-        let money_burn_zkbin =
+        let money_burn_bincode =
             money_db_handle.get(&serialize(&MONEY_CONTRACT_ZKAS_BURN_NS_V1))?.unwrap();
-        let money_burn_zkbin = ZkBinary::decode(&money_burn_zkbin)?;
-        let money_burn_witnesses = empty_witnesses(&money_burn_zkbin);
-        let money_burn_circuit = ZkCircuit::new(money_burn_witnesses, money_burn_zkbin.clone());
+        let money_burn_zkbin = ZkBinary::decode(&money_burn_bincode)?;
 
-        let dao_mint_zkbin =
+        let dao_mint_bincode =
             dao_db_handle.get(&serialize(&DAO_CONTRACT_ZKAS_DAO_MINT_NS))?.unwrap();
-        let dao_mint_zkbin = ZkBinary::decode(&dao_mint_zkbin)?;
-        let dao_mint_witnesses = empty_witnesses(&dao_mint_zkbin);
-        let dao_mint_circuit = ZkCircuit::new(dao_mint_witnesses, dao_mint_zkbin.clone());
+        let dao_mint_zkbin = ZkBinary::decode(&dao_mint_bincode)?;
 
-        let dao_propose_burn_zkbin =
+        let dao_propose_burn_bincode =
             dao_db_handle.get(&serialize(&DAO_CONTRACT_ZKAS_DAO_PROPOSE_BURN_NS))?.unwrap();
-        let dao_propose_burn_zkbin = ZkBinary::decode(&dao_propose_burn_zkbin)?;
-        let dao_propose_burn_witnesses = empty_witnesses(&dao_propose_burn_zkbin);
-        let dao_propose_burn_circuit =
-            ZkCircuit::new(dao_propose_burn_witnesses, dao_propose_burn_zkbin.clone());
+        let dao_propose_burn_zkbin = ZkBinary::decode(&dao_propose_burn_bincode)?;
 
-        let dao_propose_main_zkbin =
+        let dao_propose_main_bincode =
             dao_db_handle.get(&serialize(&DAO_CONTRACT_ZKAS_DAO_PROPOSE_MAIN_NS))?.unwrap();
-        let dao_propose_main_zkbin = ZkBinary::decode(&dao_propose_main_zkbin)?;
-        let dao_propose_main_witnesses = empty_witnesses(&dao_propose_main_zkbin);
-        let dao_propose_main_circuit =
-            ZkCircuit::new(dao_propose_main_witnesses, dao_propose_main_zkbin.clone());
+        let dao_propose_main_zkbin = ZkBinary::decode(&dao_propose_main_bincode)?;
 
-        let dao_vote_burn_zkbin =
+        let dao_vote_burn_bincode =
             dao_db_handle.get(&serialize(&DAO_CONTRACT_ZKAS_DAO_VOTE_BURN_NS))?.unwrap();
-        let dao_vote_burn_zkbin = ZkBinary::decode(&dao_vote_burn_zkbin)?;
-        let dao_vote_burn_witnesses = empty_witnesses(&dao_vote_burn_zkbin);
-        let dao_vote_burn_circuit =
-            ZkCircuit::new(dao_vote_burn_witnesses, dao_vote_burn_zkbin.clone());
+        let dao_vote_burn_zkbin = ZkBinary::decode(&dao_vote_burn_bincode)?;
 
-        let dao_vote_main_zkbin =
+        let dao_vote_main_bincode =
             dao_db_handle.get(&serialize(&DAO_CONTRACT_ZKAS_DAO_VOTE_MAIN_NS))?.unwrap();
-        let dao_vote_main_zkbin = ZkBinary::decode(&dao_vote_main_zkbin)?;
-        let dao_vote_main_witnesses = empty_witnesses(&dao_vote_main_zkbin);
-        let dao_vote_main_circuit =
-            ZkCircuit::new(dao_vote_main_witnesses, dao_vote_main_zkbin.clone());
+        let dao_vote_main_zkbin = ZkBinary::decode(&dao_vote_main_bincode)?;
 
-        let dao_exec_zkbin =
+        let dao_exec_bincode =
             dao_db_handle.get(&serialize(&DAO_CONTRACT_ZKAS_DAO_EXEC_NS))?.unwrap();
-        let dao_exec_zkbin = ZkBinary::decode(&dao_exec_zkbin)?;
-        let dao_exec_witnesses = empty_witnesses(&dao_exec_zkbin);
-        let dao_exec_circuit = ZkCircuit::new(dao_exec_witnesses, dao_exec_zkbin.clone());
+        let dao_exec_zkbin = ZkBinary::decode(&dao_exec_bincode)?;
 
         info!(target: "dao", "Creating zk proving keys");
 
-        let k = 13;
         let mut proving_keys = HashMap::<[u8; 32], Vec<(&str, ProvingKey)>>::new();
 
-        let money_mint_pk = ProvingKey::build(k, &money_mint_circuit);
-        let money_burn_pk = ProvingKey::build(k, &money_burn_circuit);
-        let dao_mint_pk = ProvingKey::build(k, &dao_mint_circuit);
-        let dao_propose_burn_pk = ProvingKey::build(k, &dao_propose_burn_circuit);
-        let dao_propose_main_pk = ProvingKey::build(k, &dao_propose_main_circuit);
-        let dao_vote_burn_pk = ProvingKey::build(k, &dao_vote_burn_circuit);
-        let dao_vote_main_pk = ProvingKey::build(k, &dao_vote_main_circuit);
-        let dao_exec_pk = ProvingKey::build(k, &dao_exec_circuit);
+        let money_mint_pk = key_cache.proving_key(&money_mint_bincode)?;
+        let money_burn_pk = key_cache.proving_key(&money_burn_bincode)?;
+        let dao_mint_pk = key_cache.proving_key(&dao_mint_bincode)?;
+        let dao_propose_burn_pk = key_cache.proving_key(&dao_propose_burn_bincode)?;
+        let dao_propose_main_pk = key_cache.proving_key(&dao_propose_main_bincode)?;
+        let dao_vote_burn_pk = key_cache.proving_key(&dao_vote_burn_bincode)?;
+        let dao_vote_main_pk = key_cache.proving_key(&dao_vote_main_bincode)?;
+        let dao_exec_pk = key_cache.proving_key(&dao_exec_bincode)?;
 
         let pks = vec![
             (MONEY_CONTRACT_ZKAS_MINT_NS_V1, money_mint_pk.clone()),
@@ -94,7 +94,7 @@ async fn integration_test() -> Result<()> {
     params.encode(&mut data)?;
     let calls = vec![ContractCall { contract_id: dao_th.dao_contract_id, data }];
     let proofs = vec![proofs];
-    let mut tx = Transaction { calls, proofs, signatures: vec![] };
+    let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
     let sigs = tx.create_sigs(&mut OsRng, &[dao_th.dao_kp.secret])?;
     tx.signatures = vec![sigs];
 
@@ -165,7 +165,7 @@ async fn integration_test() -> Result<()> {
     params.encode(&mut data)?;
     let calls = vec![ContractCall { contract_id, data }];
     let proofs = vec![proofs];
-    let mut tx = Transaction { calls, proofs, signatures: vec![] };
+    let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
     let sigs = tx.create_sigs(&mut OsRng, &vec![dao_th.faucet_kp.secret])?;
     tx.signatures = vec![sigs];
 
@@ -286,7 +286,7 @@ async fn integration_test() -> Result<()> {
     params.encode(&mut data)?;
     let calls = vec![ContractCall { contract_id, data }];
     let proofs = vec![proofs];
-    let mut tx = Transaction { calls, proofs, signatures: vec![] };
+    let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
     let sigs = tx.create_sigs(&mut OsRng, &vec![dao_th.faucet_kp.secret])?;
     tx.signatures = vec![sigs];
 
@@ -421,7 +421,7 @@ async fn integration_test() -> Result<()> {
     params.encode(&mut data)?;
     let calls = vec![ContractCall { contract_id, data }];
     let proofs = vec![proofs];
-    let mut tx = Transaction { calls, proofs, signatures: vec![] };
+    let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
     let sigs = tx.create_sigs(&mut OsRng, &vec![signature_secret])?;
     tx.signatures = vec![sigs];
 
@@ -526,7 +526,7 @@ async fn integration_test() -> Result<()> {
     params.encode(&mut data)?;
     let calls = vec![ContractCall { contract_id, data }];
     let proofs = vec![proofs];
-    let mut tx = Transaction { calls, proofs, signatures: vec![] };
+    let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
     let sigs = tx.create_sigs(&mut OsRng, &vec![signature_secret])?;
     tx.signatures = vec![sigs];
 
@@ -594,7 +594,7 @@ async fn integration_test() -> Result<()> {
     params.encode(&mut data)?;
     let calls = vec![ContractCall { contract_id, data }];
     let proofs = vec![proofs];
-    let mut tx = Transaction { calls, proofs, signatures: vec![] };
+    let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
     let sigs = tx.create_sigs(&mut OsRng, &vec![signature_secret])?;
     tx.signatures = vec![sigs];
 
@@ -659,7 +659,7 @@ async fn integration_test() -> Result<()> {
     params.encode(&mut data)?;
     let calls = vec![ContractCall { contract_id, data }];
     let proofs = vec![proofs];
-    let mut tx = Transaction { calls, proofs, signatures: vec![] };
+    let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
     let sigs = tx.create_sigs(&mut OsRng, &vec![signature_secret])?;
     tx.signatures = vec![sigs];
 
@@ -847,6 +847,8 @@ async fn integration_test() -> Result<()> {
 
     let mut tx = Transaction {
         calls: vec![xfer_call, exec_call],
+        gas_limits: vec![],
+        fee: 0,
         proofs: vec![xfer_proofs, exec_proofs],
         signatures: vec![],
     };
@@ -39,6 +39,7 @@ use darkfi_sdk::crypto::{
     pasta_prelude::*,
     pedersen_commitment_base, pedersen_commitment_u64, poseidon_hash, Keypair, MerkleNode,
     MerklePosition, MerkleTree, Nullifier, PublicKey, SecretKey, TokenId, ValueBlind, ValueCommit,
+    DARK_TOKEN_ID,
 };
 use darkfi_serial::{serialize, Decodable, Encodable, SerialDecodable, SerialEncodable};
 use halo2_proofs::circuit::Value;
@@ -46,8 +47,8 @@ use log::{debug, error, info};
 use rand::rngs::OsRng;
 
 use crate::model::{
-    ClearInput, Input, MoneyStakeParams, MoneyTransferParams, MoneyUnstakeParams, Output,
-    StakedInput, StakedOutput,
+    ClearInput, Input, MoneyFeeParams, MoneyMintParams, MoneyStakeParams, MoneyTransferParams,
+    MoneyUnstakeParams, Output, StakedInput, StakedOutput,
 };
 
 // Wallet SQL table constant names. These have to represent the SQL schema.
@@ -81,6 +82,11 @@ pub const MONEY_COINS_COL_NULLIFIER: &str = "nullifier";
 pub const MONEY_COINS_COL_LEAF_POSITION: &str = "leaf_position";
 pub const MONEY_COINS_COL_MEMO: &str = "memo";
 
+pub const MONEY_TOKENS_TABLE: &str = "money_tokens";
+pub const MONEY_TOKENS_COL_MINT_AUTHORITY: &str = "mint_authority";
+pub const MONEY_TOKENS_COL_TOKEN_ID: &str = "token_id";
+pub const MONEY_TOKENS_COL_IS_FROZEN: &str = "is_frozen";
+
 pub const MONEY_ALIASES_TABLE: &str = "money_aliases";
 pub const MONEY_ALIASES_COL_ALIAS: &str = "alias";
 pub const MONEY_ALIASES_COL_TOKEN_ID: &str = "token_id";
@@ -483,6 +489,127 @@ pub fn create_transfer_burn_proof(
     Ok((proof, revealed))
 }
 
+pub struct TokenMintRevealed {
+    pub mint_public: PublicKey,
+    pub token_id: TokenId,
+    pub fixed_supply: bool,
+    pub coin: Coin,
+    pub value_commit: ValueCommit,
+    pub token_commit: ValueCommit,
+}
+
+impl TokenMintRevealed {
+    #[allow(clippy::too_many_arguments)]
+    pub fn compute(
+        mint_authority: SecretKey,
+        supply: u64,
+        fixed_supply: bool,
+        value_blind: ValueBlind,
+        token_blind: ValueBlind,
+        serial: pallas::Base,
+        spend_hook: pallas::Base,
+        user_data: pallas::Base,
+        coin_blind: pallas::Base,
+        public_key: PublicKey,
+    ) -> Self {
+        let mint_public = PublicKey::from_secret(mint_authority);
+        let token_id = TokenId::derive(mint_authority);
+
+        // The minted coin is the same as one minted by a payment
+        let transfer = TransferMintRevealed::compute(
+            supply,
+            token_id,
+            value_blind,
+            token_blind,
+            serial,
+            spend_hook,
+            user_data,
+            coin_blind,
+            public_key,
+        );
+
+        Self {
+            mint_public,
+            token_id,
+            fixed_supply,
+            coin: transfer.coin,
+            value_commit: transfer.value_commit,
+            token_commit: transfer.token_commit,
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<pallas::Base> {
+        let (mint_x, mint_y) = self.mint_public.xy();
+        let valcom_coords = self.value_commit.to_affine().coordinates().unwrap();
+        let tokcom_coords = self.token_commit.to_affine().coordinates().unwrap();
+
+        // NOTE: It's important to keep this order the same as the `constrain_instance`
+        //       calls in the zkas code.
+        vec![
+            mint_x,
+            mint_y,
+            self.token_id.inner(),
+            pallas::Base::from(self.fixed_supply as u64),
+            self.coin.inner(),
+            *valcom_coords.x(),
+            *valcom_coords.y(),
+            *tokcom_coords.x(),
+            *tokcom_coords.y(),
+        ]
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn create_token_mint_proof(
+    zkbin: &ZkBinary,
+    pk: &ProvingKey,
+    mint_authority: SecretKey,
+    supply: u64,
+    fixed_supply: bool,
+    value_blind: ValueBlind,
+    token_blind: ValueBlind,
+    serial: pallas::Base,
+    spend_hook: pallas::Base,
+    user_data: pallas::Base,
+    coin_blind: pallas::Base,
+    public_key: PublicKey,
+) -> Result<(Proof, TokenMintRevealed)> {
+    let revealed = TokenMintRevealed::compute(
+        mint_authority,
+        supply,
+        fixed_supply,
+        value_blind,
+        token_blind,
+        serial,
+        spend_hook,
+        user_data,
+        coin_blind,
+        public_key,
+    );
+
+    let (rcpt_x, rcpt_y) = public_key.xy();
+
+    // NOTE: It's important to keep these in the same order as the zkas code.
+    let prover_witnesses = vec![
+        Witness::Base(Value::known(mint_authority.inner())),
+        Witness::Base(Value::known(pallas::Base::from(supply))),
+        Witness::Base(Value::known(pallas::Base::from(fixed_supply as u64))),
+        Witness::Base(Value::known(rcpt_x)),
+        Witness::Base(Value::known(rcpt_y)),
+        Witness::Base(Value::known(serial)),
+        Witness::Base(Value::known(coin_blind)),
+        Witness::Base(Value::known(spend_hook)),
+        Witness::Base(Value::known(user_data)),
+        Witness::Scalar(Value::known(value_blind)),
+        Witness::Scalar(Value::known(token_blind)),
+    ];
+
+    let circuit = ZkCircuit::new(prover_witnesses, zkbin.clone());
+    let proof = Proof::create(pk, &[circuit], &revealed.to_vec(), &mut OsRng)?;
+
+    Ok((proof, revealed))
+}
+
 struct StakeLeadMintRevealed {
     pub value_commit: ValueCommit,
     pub pk: pallas::Base,
@@ -1063,6 +1190,223 @@ pub fn build_transfer_tx(
     Ok((params, zk_proofs, signature_secrets, spent_coins))
 }
 
+/// Build money contract fee payment parameters with the given data:
+/// * `keypair` - Caller's keypair, receiving the change
+/// * `fee` - Fee to pay
+/// * `coins` - Set of coins we're able to spend
+/// * `tree` - Current Merkle tree of coins
+/// * `mint_zkbin` - ZkBinary of the mint circuit
+/// * `mint_pk` - Proving key for the ZK mint proof
+/// * `burn_zkbin` - ZkBinary of the burn circuit
+/// * `burn_pk` - Proving key for the ZK burn proof
+#[allow(clippy::too_many_arguments)]
+#[allow(clippy::type_complexity)]
+pub fn build_fee_tx(
+    keypair: &Keypair,
+    fee: u64,
+    coins: &[OwnCoin],
+    tree: &MerkleTree,
+    mint_zkbin: &ZkBinary,
+    mint_pk: &ProvingKey,
+    burn_zkbin: &ZkBinary,
+    burn_pk: &ProvingKey,
+) -> Result<(MoneyFeeParams, Vec<Proof>, Vec<SecretKey>, Vec<OwnCoin>)> {
+    debug!(target: "money", "Building fee payment");
+
+    debug!(target: "money", "Money::build_fee_tx(): Building anonymous input");
+    // We'll take the smallest native coin that can pay the fee
+    let Some(coin) = coins
+        .iter()
+        .filter(|x| x.note.token_id == *DARK_TOKEN_ID && x.note.value >= fee)
+        .min_by_key(|x| x.note.value)
+    else {
+        error!(target: "money", "Money::build_fee_tx(): Did not find a coin with enough value to pay the fee");
+        return Err(ClientFailed::NotEnoughValue(fee).into())
+    };
+
+    let leaf_position = coin.leaf_position;
+    let root = tree.root(0).unwrap();
+    let merkle_path = tree.authentication_path(leaf_position, &root).unwrap();
+
+    let spent_coins = vec![coin.clone()];
+
+    let input_blind = ValueBlind::random(&mut OsRng);
+    let output_blind = ValueBlind::random(&mut OsRng);
+    // The input and output must commit to the native token with the same blind
+    let token_blind = ValueBlind::random(&mut OsRng);
+
+    // The ephemeral secret key we're using here.
+    let signature_secret = SecretKey::random(&mut OsRng);
+
+    let spend_hook = pallas::Base::zero();
+    let user_data = pallas::Base::zero();
+    let user_data_blind = pallas::Base::random(&mut OsRng);
+
+    let mut zk_proofs = vec![];
+
+    info!(target: "money", "Creating fee burn proof for input");
+    let (proof, revealed) = create_transfer_burn_proof(
+        burn_zkbin,
+        burn_pk,
+        coin.note.value,
+        coin.note.token_id,
+        input_blind,
+        token_blind,
+        coin.note.serial,
+        spend_hook,
+        user_data,
+        user_data_blind,
+        coin.note.coin_blind,
+        coin.secret,
+        leaf_position,
+        merkle_path,
+        signature_secret,
+    )?;
+
+    let input = Input {
+        value_commit: revealed.value_commit,
+        token_commit: revealed.token_commit,
+        nullifier: revealed.nullifier,
+        merkle_root: revealed.merkle_root,
+        spend_hook: revealed.spend_hook,
+        user_data_enc: revealed.user_data_enc,
+        signature_public: revealed.signature_public,
+    };
+
+    zk_proofs.push(proof);
+
+    let change = coin.note.value - fee;
+    let serial = pallas::Base::random(&mut OsRng);
+    let coin_blind = pallas::Base::random(&mut OsRng);
+
+    info!(target: "money", "Creating fee mint proof for change output");
+    let (proof, revealed) = create_transfer_mint_proof(
+        mint_zkbin,
+        mint_pk,
+        change,
+        *DARK_TOKEN_ID,
+        output_blind,
+        token_blind,
+        serial,
+        spend_hook,
+        user_data,
+        coin_blind,
+        keypair.public,
+    )?;
+
+    zk_proofs.push(proof);
+
+    // Encrypted note
+    let note = Note {
+        serial,
+        value: change,
+        token_id: *DARK_TOKEN_ID,
+        spend_hook,
+        user_data,
+        coin_blind,
+        value_blind: output_blind,
+        token_blind,
+        memo: vec![],
+    };
+
+    let encrypted_note = note.encrypt(&keypair.public)?;
+
+    let output = Output {
+        value_commit: revealed.value_commit,
+        token_commit: revealed.token_commit,
+        coin: revealed.coin.inner(),
+        ciphertext: encrypted_note.ciphertext,
+        ephem_public: encrypted_note.ephem_public,
+    };
+
+    // The fee commitment takes up the difference of the value blinds
+    let fee_blind = input_blind - output_blind;
+    let params = MoneyFeeParams { input, output, fee, fee_blind, token_blind };
+
+    // Now we should have all the params, zk proofs, and signature secrets.
+    // We return it all and let the caller deal with it.
+    Ok((params, zk_proofs, vec![signature_secret], spent_coins))
+}
+
+/// Build money contract token mint parameters with the given data:
+/// * `mint_authority` - Keypair of the mint authority, from which the token ID is derived
+/// * `recipient` - Public key of the recipient
+/// * `amount` - Amount of tokens to mint
+/// * `spend_hook` - Spend hook
+/// * `user_data` - User data
+/// * `fixed_supply` - Disallow any further mints of the token
+/// * `token_mint_zkbin` - ZkBinary of the token mint circuit
+/// * `token_mint_pk` - Proving key for the ZK token mint proof
+///
+/// The call has to be signed with the mint authority's secret key.
+#[allow(clippy::too_many_arguments)]
+pub fn build_token_mint_tx(
+    mint_authority: &Keypair,
+    recipient: &PublicKey,
+    amount: u64,
+    spend_hook: pallas::Base,
+    user_data: pallas::Base,
+    fixed_supply: bool,
+    token_mint_zkbin: &ZkBinary,
+    token_mint_pk: &ProvingKey,
+) -> Result<(MoneyMintParams, Vec<Proof>)> {
+    debug!(target: "money", "Building token mint");
+    assert!(amount != 0);
+
+    let value_blind = ValueBlind::random(&mut OsRng);
+    let token_blind = ValueBlind::random(&mut OsRng);
+    let serial = pallas::Base::random(&mut OsRng);
+    let coin_blind = pallas::Base::random(&mut OsRng);
+
+    info!(target: "money", "Creating token mint proof");
+    let (proof, revealed) = create_token_mint_proof(
+        token_mint_zkbin,
+        token_mint_pk,
+        mint_authority.secret,
+        amount,
+        fixed_supply,
+        value_blind,
+        token_blind,
+        serial,
+        spend_hook,
+        user_data,
+        coin_blind,
+        *recipient,
+    )?;
+
+    // Encrypted note
+    let note = Note {
+        serial,
+        value: amount,
+        token_id: revealed.token_id,
+        spend_hook,
+        user_data,
+        coin_blind,
+        value_blind,
+        token_blind,
+        memo: vec![],
+    };
+
+    let encrypted_note = note.encrypt(recipient)?;
+
+    let output = Output {
+        value_commit: revealed.value_commit,
+        token_commit: revealed.token_commit,
+        coin: revealed.coin.inner(),
+        ciphertext: encrypted_note.ciphertext,
+        ephem_public: encrypted_note.ephem_public,
+    };
+
+    let params = MoneyMintParams {
+        mint_public: revealed.mint_public,
+        token_id: revealed.token_id,
+        fixed_supply,
+        output,
+    };
+
+    Ok((params, vec![proof]))
+}
+
 pub fn build_stake_tx(
     //pubkey: &PublicKey,
     coins: &[OwnCoin],
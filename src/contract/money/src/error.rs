@@ -0,0 +1,61 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use darkfi_sdk::error::ContractError;
+
+/// Errors returned by the Money contract. They reach the runtime as
+/// [`ContractError::Custom`] with the variant's code, along with its message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u32)]
+pub enum MoneyError {
+    ClearInputUnauthorised = 1,
+    MerkleRootNotFound = 2,
+    DuplicateNullifier = 3,
+    SpendHookOutOfBounds = 4,
+    SpendHookMismatch = 5,
+    DuplicateCoin = 6,
+    ValueMismatch = 7,
+    TokenMismatch = 8,
+    NonNativeToken = 9,
+    NonNativeFee = 10,
+    TokenSupplyFixed = 11,
+}
+
+impl MoneyError {
+    pub fn message(&self) -> &'static str {
+        match self {
+            Self::ClearInputUnauthorised => "Clear input was not signed by a faucet",
+            Self::MerkleRootNotFound => "Merkle root not found in previous state",
+            Self::DuplicateNullifier => "Nullifier already exists, coin is spent",
+            Self::SpendHookOutOfBounds => "Spend hook refers to a call past the last one",
+            Self::SpendHookMismatch => "Invoked contract does not match the spend hook",
+            Self::DuplicateCoin => "Coin already exists",
+            Self::ValueMismatch => "Value commitments do not match",
+            Self::TokenMismatch => "Token commitments do not match",
+            Self::NonNativeToken => "Only the native token can be staked or unstaked",
+            Self::NonNativeFee => "Fees can only be paid in the native token",
+            Self::TokenSupplyFixed => "Token has a fixed supply and can't be minted",
+        }
+    }
+}
+
+impl From<MoneyError> for ContractError {
+    fn from(err: MoneyError) -> Self {
+        Self::custom(err as u32, err.message())
+    }
+}
@@ -25,7 +25,9 @@ use darkfi_sdk::{
     db::{db_contains_key, db_get, db_init, db_lookup, db_set, SMART_CONTRACT_ZKAS_DB_NAME},
     error::ContractResult,
     merkle::merkle_add,
-    msg, set_return_data, ContractCall,
+    msg, set_return_data,
+    util::pay_fee,
+    ContractCall,
 };
 
 use darkfi_sdk::error::ContractError;
@@ -41,6 +43,7 @@ pub enum MoneyFunction {
     Stake = 0x02,
     Unstake = 0x03,
     Mint = 0x04,
+    Fee = 0x05,
 }
 
 impl TryFrom<u8> for MoneyFunction {
@@ -53,6 +56,7 @@ impl TryFrom<u8> for MoneyFunction {
             0x02 => Ok(Self::Stake),
             0x03 => Ok(Self::Unstake),
             0x04 => Ok(Self::Mint),
+            0x05 => Ok(Self::Fee),
             _ => Err(ContractError::InvalidFunction),
         }
     }
@@ -61,10 +65,15 @@ impl TryFrom<u8> for MoneyFunction {
 /// Structures and object definitions
 pub mod model;
 
+/// Errors returned by the contract
+pub mod error;
+#[cfg(not(feature = "no-entrypoint"))]
+use error::MoneyError;
+
 #[cfg(not(feature = "no-entrypoint"))]
 use model::{
-    MoneyStakeParams, MoneyStakeUpdate, MoneyTransferParams, MoneyTransferUpdate,
-    MoneyUnstakeParams,
+    MoneyFeeParams, MoneyMintParams, MoneyMintUpdate, MoneyStakeParams, MoneyStakeUpdate,
+    MoneyTransferParams, MoneyTransferUpdate, MoneyUnstakeParams,
 };
 
 #[cfg(feature = "client")]
@@ -357,9 +366,94 @@ fn get_metadata(_cid: ContractId, ix: &[u8]) -> ContractResult {
             set_return_data(&metadata)?;
             Ok(())
         }
+
+        MoneyFunction::Fee => {
+            let params: MoneyFeeParams = deserialize(&self_.data[1..])?;
+
+            let mut zk_public_values: Vec<(String, Vec<pallas::Base>)> = vec![];
+            let mut signature_pubkeys: Vec<PublicKey> = vec![];
+
+            let input = &params.input;
+            let value_coords = input.value_commit.to_affine().coordinates().unwrap();
+            let token_coords = input.token_commit.to_affine().coordinates().unwrap();
+            let (sig_x, sig_y) = input.signature_public.xy();
+
+            zk_public_values.push((
+                MONEY_CONTRACT_ZKAS_BURN_NS_V1.to_string(),
+                vec![
+                    input.nullifier.inner(),
+                    *value_coords.x(),
+                    *value_coords.y(),
+                    *token_coords.x(),
+                    *token_coords.y(),
+                    input.merkle_root.inner(),
+                    input.user_data_enc,
+                    sig_x,
+                    sig_y,
+                ],
+            ));
+
+            signature_pubkeys.push(input.signature_public);
+
+            let output = &params.output;
+            let value_coords = output.value_commit.to_affine().coordinates().unwrap();
+            let token_coords = output.token_commit.to_affine().coordinates().unwrap();
+
+            zk_public_values.push((
+                MONEY_CONTRACT_ZKAS_MINT_NS_V1.to_string(),
+                vec![
+                    output.coin,
+                    *value_coords.x(),
+                    *value_coords.y(),
+                    *token_coords.x(),
+                    *token_coords.y(),
+                ],
+            ));
+
+            let mut metadata = vec![];
+            zk_public_values.encode(&mut metadata)?;
+            signature_pubkeys.encode(&mut metadata)?;
+
+            // Using this, we pass the above data to the host.
+            set_return_data(&metadata)?;
+            Ok(())
+        }
+
         MoneyFunction::Mint => {
-            msg!("[Mint] Entered match arm");
-            unimplemented!();
+            let params: MoneyMintParams = deserialize(&self_.data[1..])?;
+
+            let mut zk_public_values: Vec<(String, Vec<pallas::Base>)> = vec![];
+            let mut signature_pubkeys: Vec<PublicKey> = vec![];
+
+            let (mint_x, mint_y) = params.mint_public.xy();
+            let value_coords = params.output.value_commit.to_affine().coordinates().unwrap();
+            let token_coords = params.output.token_commit.to_affine().coordinates().unwrap();
+
+            zk_public_values.push((
+                MONEY_CONTRACT_ZKAS_TOKEN_MINT_NS_V1.to_string(),
+                vec![
+                    mint_x,
+                    mint_y,
+                    params.token_id.inner(),
+                    pallas::Base::from(params.fixed_supply as u64),
+                    params.output.coin,
+                    *value_coords.x(),
+                    *value_coords.y(),
+                    *token_coords.x(),
+                    *token_coords.y(),
+                ],
+            ));
+
+            // The mint authority has to sign the transaction
+            signature_pubkeys.push(params.mint_public);
+
+            let mut metadata = vec![];
+            zk_public_values.encode(&mut metadata)?;
+            signature_pubkeys.encode(&mut metadata)?;
+
+            // Using this, we pass the above data to the host.
+            set_return_data(&metadata)?;
+            Ok(())
         }
     }
 }
@@ -401,7 +495,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
 
                 if !faucet_pubkeys.contains(&pk) {
                     msg!("[Transfer] Error: Clear input {} has invalid faucet pubkey", i);
-                    return Err(ContractError::Custom(20))
+                    return Err(MoneyError::ClearInputUnauthorised.into())
                 }
 
                 valcom_total += pedersen_commitment_u64(input.value, input.value_blind);
@@ -415,7 +509,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
                 // in a previous state.
                 if !db_contains_key(coin_roots_db, &serialize(&input.merkle_root))? {
                     msg!("[Transfer] Error: Merkle root not found in previous state (input {})", i);
-                    return Err(ContractError::Custom(21))
+                    return Err(MoneyError::MerkleRootNotFound.into())
                 }
 
                 // The nullifiers should not already exist. It is the double-spend protection.
@@ -423,7 +517,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
                     db_contains_key(nullifiers_db, &serialize(&input.nullifier))?
                 {
                     msg!("[Transfer] Error: Duplicate nullifier found in input {}", i);
-                    return Err(ContractError::Custom(22))
+                    return Err(MoneyError::DuplicateNullifier.into())
                 }
 
                 // Check the invoked contract if spend hook is set
@@ -436,7 +530,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
                             call.len(),
                             i
                         );
-                        return Err(ContractError::Custom(23))
+                        return Err(MoneyError::SpendHookOutOfBounds.into())
                     }
 
                     let next = &call[next_call_idx as usize];
@@ -446,7 +540,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
                              in input {}",
                             i
                         );
-                        return Err(ContractError::Custom(24))
+                        return Err(MoneyError::SpendHookMismatch.into())
                     }
                 }
 
@@ -460,7 +554,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
                 // TODO: Should we have coins in a sled tree too to check dupes?
                 if new_coins.contains(&Coin::from(output.coin)) {
                     msg!("[Transfer] Error: Duplicate coin found in output {}", i);
-                    return Err(ContractError::Custom(25))
+                    return Err(MoneyError::DuplicateCoin.into())
                 }
 
                 // FIXME: Needs some work on types and their place within all these libraries
@@ -471,7 +565,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
             // If the accumulator is not back in its initial state, there's a value mismatch.
             if valcom_total != pallas::Point::identity() {
                 msg!("[Transfer] Error: Value commitments do not result in identity");
-                return Err(ContractError::Custom(26))
+                return Err(MoneyError::ValueMismatch.into())
             }
 
             // Verify that the token commitments are all for the same token
@@ -488,7 +582,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
 
             if failed_tokcom {
                 msg!("[Transfer] Error: Token commitments do not match");
-                return Err(ContractError::Custom(25))
+                return Err(MoneyError::TokenMismatch.into())
             }
 
             // Create a state update
@@ -524,22 +618,22 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
             // So that's how we check the value and token commitments
             if params.inputs[0].value_commit != params.outputs[1].value_commit {
                 msg!("[OtcSwap] Error: Value commitments for input 0 and output 1 do not match");
-                return Err(ContractError::Custom(24))
+                return Err(MoneyError::ValueMismatch.into())
             }
 
             if params.inputs[1].value_commit != params.outputs[0].value_commit {
                 msg!("[OtcSwap] Error: Value commitments for input 1 and output 0 do not match");
-                return Err(ContractError::Custom(24))
+                return Err(MoneyError::ValueMismatch.into())
             }
 
             if params.inputs[0].token_commit != params.outputs[1].token_commit {
                 msg!("[OtcSwap] Error: Token commitments for input 0 and output 1 do not match");
-                return Err(ContractError::Custom(25))
+                return Err(MoneyError::TokenMismatch.into())
             }
 
             if params.inputs[1].token_commit != params.outputs[0].token_commit {
                 msg!("[OtcSwap] Error: Token commitments for input 1 and output 0 do not match");
-                return Err(ContractError::Custom(25))
+                return Err(MoneyError::TokenMismatch.into())
             }
 
             msg!("[OtcSwap] Iterating over anonymous inputs");
@@ -548,7 +642,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
                 // existed in a previous state.
                 if !db_contains_key(coin_roots_db, &serialize(&input.merkle_root))? {
                     msg!("[OtcSwap] Error: Merkle root not found in previous state (input {})", i);
-                    return Err(ContractError::Custom(21))
+                    return Err(MoneyError::MerkleRootNotFound.into())
                 }
 
                 // The nullifiers should not already exist. It is the double-spend protection.
@@ -556,7 +650,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
                     db_contains_key(nullifiers_db, &serialize(&input.nullifier))?
                 {
                     msg!("[OtcSwap] Error: Duplicate nullifier found in input {}", i);
-                    return Err(ContractError::Custom(22))
+                    return Err(MoneyError::DuplicateNullifier.into())
                 }
 
                 new_nullifiers.push(input.nullifier);
@@ -568,7 +662,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
                 // TODO: Should we have coins in a sled tree too to check dupes?
                 if new_coins.contains(&Coin::from(output.coin)) {
                     msg!("[OtcSwap] Error: Duplicate coin found in output {}", i);
-                    return Err(ContractError::Custom(23))
+                    return Err(MoneyError::DuplicateCoin.into())
                 }
 
                 // FIXME: Needs some work on types and their place within all these libraries
@@ -597,7 +691,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
             let tokcom = pedersen_commitment_base(DARK_TOKEN_ID.inner(), params.token_blind);
             if params.inputs.iter().any(|input| input.token_commit != tokcom) {
                 msg!("[Stake] Error: Tried to stake non-native token. Unable to proceed");
-                return Err(ContractError::Custom(26))
+                return Err(MoneyError::NonNativeToken.into())
             }
 
             let nullifiers_db = db_lookup(cid, MONEY_CONTRACT_LEAD_NULLIFIERS_TREE)?;
@@ -615,7 +709,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
                 // in a previous state.
                 if !db_contains_key(coin_roots_db, &serialize(&input.merkle_root))? {
                     msg!("[Stake] Error: Merkle root not found in previous state (input {})", i);
-                    return Err(ContractError::Custom(21))
+                    return Err(MoneyError::MerkleRootNotFound.into())
                 }
 
                 // The nullifiers should not already exist. It is the double-spend protection.
@@ -623,7 +717,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
                     db_contains_key(nullifiers_db, &serialize(&input.nullifier))?
                 {
                     msg!("[Stake] Error: Duplicate nullifier found in input {}", i);
-                    return Err(ContractError::Custom(22))
+                    return Err(MoneyError::DuplicateNullifier.into())
                 }
 
                 new_nullifiers.push(input.nullifier);
@@ -636,7 +730,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
                 // TODO: Should we have coins in a sled tree too to check dupes?
                 if new_coins.contains(&Coin::from(output.coin_commit_hash)) {
                     msg!("[Stake] Error: Duplicate coin found in output {}", i);
-                    return Err(ContractError::Custom(23))
+                    return Err(MoneyError::DuplicateCoin.into())
                 }
                 new_coins.push(Coin::from(output.coin_commit_hash));
                 valcom_total -= output.value_commit;
@@ -645,7 +739,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
             // If the accumulator is not back in its initial state, there's a value mismatch.
             if valcom_total != pallas::Point::identity() {
                 msg!("[Stake] Error: Value commitments do not result in identity");
-                return Err(ContractError::Custom(24))
+                return Err(MoneyError::ValueMismatch.into())
             }
 
             // Create a state update
@@ -669,7 +763,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
             let tokcom = pedersen_commitment_base(DARK_TOKEN_ID.inner(), params.token_blind);
             if params.outputs.iter().any(|output| output.token_commit != tokcom) {
                 msg!("[Stake] Error: Tried to unstake non-native token. Unable to proceed");
-                return Err(ContractError::Custom(26))
+                return Err(MoneyError::NonNativeToken.into())
             }
 
             let nullifiers_db = db_lookup(cid, MONEY_CONTRACT_LEAD_NULLIFIERS_TREE)?;
@@ -688,14 +782,14 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
                 // in a previous state.
                 if !db_contains_key(coin_roots_db, &serialize(&input.coin_commit_root))? {
                     msg!("[Unstake] Error: Merkle root not found in previous state (input {})", i);
-                    return Err(ContractError::Custom(21))
+                    return Err(MoneyError::MerkleRootNotFound.into())
                 }
 
                 //TODO adde sk root to db.
                 /*
                     if !db_contains_key(sk_roots_db, &serialize(&input.sk_root))? {
                         msg!("[Unstake] Error: sk merkle root not found in previous state (input {})", i);
-                        return Err(ContractError::Custom(21))
+                        return Err(MoneyError::MerkleRootNotFound.into())
                 }
 
                     */
@@ -705,7 +799,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
                     db_contains_key(nullifiers_db, &serialize(&input.nullifier))?
                 {
                     msg!("[Unstake] Error: Duplicate nullifier found in input {}", i);
-                    return Err(ContractError::Custom(22))
+                    return Err(MoneyError::DuplicateNullifier.into())
                 }
 
                 new_nullifiers.push(input.nullifier);
@@ -718,7 +812,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
                 // TODO: Should we have coins in a sled tree too to check dupes?
                 if new_coins.contains(&Coin::from(output.coin)) {
                     msg!("[Unstake] Error: Duplicate coin found in output {}", i);
-                    return Err(ContractError::Custom(23))
+                    return Err(MoneyError::DuplicateCoin.into())
                 }
                 new_coins.push(Coin::from(output.coin));
                 valcom_total -= output.value_commit;
@@ -727,7 +821,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
             // If the accumulator is not back in its initial state, there's a value mismatch.
             if valcom_total != pallas::Point::identity() {
                 msg!("[UnStake] Error: Value commitments do not result in identity");
-                return Err(ContractError::Custom(24))
+                return Err(MoneyError::ValueMismatch.into())
             }
 
             // Create a state update
@@ -741,9 +835,83 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
             Ok(())
         }
 
+        MoneyFunction::Fee => {
+            msg!("[Fee] Entered match arm");
+            let params: MoneyFeeParams = deserialize(&self_.data[1..])?;
+
+            // Fees are paid in the native token
+            let tokcom = pedersen_commitment_base(DARK_TOKEN_ID.inner(), params.token_blind);
+            if params.input.token_commit != tokcom || params.output.token_commit != tokcom {
+                msg!("[Fee] Error: Tried to pay fee with non-native token");
+                return Err(MoneyError::NonNativeFee.into())
+            }
+
+            let nullifiers_db = db_lookup(cid, MONEY_CONTRACT_NULLIFIERS_TREE)?;
+            let coin_roots_db = db_lookup(cid, MONEY_CONTRACT_COIN_ROOTS_TREE)?;
+
+            // The Merkle root is used to know whether this is a coin that existed
+            // in a previous state.
+            if !db_contains_key(coin_roots_db, &serialize(&params.input.merkle_root))? {
+                msg!("[Fee] Error: Merkle root not found in previous state");
+                return Err(MoneyError::MerkleRootNotFound.into())
+            }
+
+            // The nullifier should not already exist. It is the double-spend protection.
+            if db_contains_key(nullifiers_db, &serialize(&params.input.nullifier))? {
+                msg!("[Fee] Error: Duplicate nullifier found in input");
+                return Err(MoneyError::DuplicateNullifier.into())
+            }
+
+            // The input pays for the change output, and the fee which is burned
+            let valcom_total = params.input.value_commit -
+                params.output.value_commit -
+                pedersen_commitment_u64(params.fee, params.fee_blind);
+
+            if valcom_total != pallas::Point::identity() {
+                msg!("[Fee] Error: Value commitments do not result in identity");
+                return Err(MoneyError::ValueMismatch.into())
+            }
+
+            pay_fee(params.fee)?;
+
+            // Create a state update. The change coin is added like a payment's.
+            let update = MoneyTransferUpdate {
+                nullifiers: vec![params.input.nullifier],
+                coins: vec![Coin::from(params.output.coin)],
+            };
+            let mut update_data = vec![];
+            update_data.write_u8(MoneyFunction::Fee as u8)?;
+            update.encode(&mut update_data)?;
+            set_return_data(&update_data)?;
+            msg!("[Fee] State update set!");
+
+            Ok(())
+        }
+
         MoneyFunction::Mint => {
             msg!("[Mint] Entered match arm");
-            unimplemented!();
+            let params: MoneyMintParams = deserialize(&self_.data[1..])?;
+
+            // Tokens with a fixed supply can't be minted anymore
+            let fixed_supply_db = db_lookup(cid, MONEY_CONTRACT_FIXED_SUPPLY_TREE)?;
+            if db_contains_key(fixed_supply_db, &serialize(&params.token_id))? {
+                msg!("[Mint] Error: Token {} has a fixed supply", params.token_id);
+                return Err(MoneyError::TokenSupplyFixed.into())
+            }
+
+            // The ZK proof enforces that the token ID is derived from the
+            // mint authority, and that the output commits to it.
+            let frozen_token = if params.fixed_supply { Some(params.token_id) } else { None };
+
+            // Create a state update
+            let update = MoneyMintUpdate { coin: Coin::from(params.output.coin), frozen_token };
+            let mut update_data = vec![];
+            update_data.write_u8(MoneyFunction::Mint as u8)?;
+            update.encode(&mut update_data)?;
+            set_return_data(&update_data)?;
+            msg!("[Mint] State update set!");
+
+            Ok(())
         }
     }
 }
@@ -751,7 +919,7 @@ fn process_instruction(cid: ContractId, ix: &[u8]) -> ContractResult {
 #[cfg(not(feature = "no-entrypoint"))]
 fn process_update(cid: ContractId, update_data: &[u8]) -> ContractResult {
     match MoneyFunction::try_from(update_data[0])? {
-        MoneyFunction::Transfer | MoneyFunction::OtcSwap => {
+        MoneyFunction::Transfer | MoneyFunction::OtcSwap | MoneyFunction::Fee => {
             let update: MoneyTransferUpdate = deserialize(&update_data[1..])?;
 
             let info_db = db_lookup(cid, MONEY_CONTRACT_INFO_TREE)?;
@@ -798,8 +966,27 @@ fn process_update(cid: ContractId, update_data: &[u8]) -> ContractResult {
         }
 
         MoneyFunction::Mint => {
-            msg!("[Mint] Entered match arm");
-            unimplemented!();
+            let update: MoneyMintUpdate = deserialize(&update_data[1..])?;
+
+            let info_db = db_lookup(cid, MONEY_CONTRACT_INFO_TREE)?;
+            let coin_roots_db = db_lookup(cid, MONEY_CONTRACT_COIN_ROOTS_TREE)?;
+            let fixed_supply_db = db_lookup(cid, MONEY_CONTRACT_FIXED_SUPPLY_TREE)?;
+
+            if let Some(token_id) = update.frozen_token {
+                msg!("Fixing the supply of token {}", token_id);
+                db_set(fixed_supply_db, &serialize(&token_id), &[])?;
+            }
+
+            msg!("Adding coin {:?} to Merkle tree", update.coin);
+            let coins = vec![MerkleNode::from(update.coin.inner())];
+            merkle_add(
+                info_db,
+                coin_roots_db,
+                &serialize(&MONEY_CONTRACT_COIN_MERKLE_TREE),
+                &coins,
+            )?;
+
+            Ok(())
         }
     }
 }
@@ -82,6 +82,45 @@ pub struct MoneyTransferParams {
     pub outputs: Vec<Output>,
 }
 
+/// Input and change output of a fee payment. The difference between the
+/// input and output values is the fee, which is burned.
+#[derive(Clone, Debug, SerialEncodable, SerialDecodable)]
+pub struct MoneyFeeParams {
+    /// Anonymous input paying the fee
+    pub input: Input,
+    /// Anonymous output holding the change
+    pub output: Output,
+    /// Fee paid
+    pub fee: u64,
+    /// Blind of the fee's value commitment, i.e. the difference between
+    /// the input and output value blinds
+    pub fee_blind: ValueBlind,
+    /// Token blind to reveal token ID
+    pub token_blind: ValueBlind,
+}
+
+/// Parameters for minting a token
+#[derive(Clone, Debug, SerialEncodable, SerialDecodable)]
+pub struct MoneyMintParams {
+    /// Public key of the mint authority, which signs the call
+    pub mint_public: PublicKey,
+    /// Minted token, derived from the mint authority
+    pub token_id: TokenId,
+    /// Disallow any further mints of the token
+    pub fixed_supply: bool,
+    /// Anonymous output holding the minted supply
+    pub output: Output,
+}
+
+/// State update produced by a token mint
+#[derive(Clone, Debug, SerialEncodable, SerialDecodable)]
+pub struct MoneyMintUpdate {
+    /// Minted coin
+    pub coin: Coin,
+    /// Token whose supply became fixed with this mint, if any
+    pub frozen_token: Option<TokenId>,
+}
+
 /// State update produced by a payment
 #[derive(Clone, Debug, SerialEncodable, SerialDecodable)]
 pub struct MoneyTransferUpdate {
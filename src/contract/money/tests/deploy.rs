@@ -0,0 +1,139 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Integration test for the contract deployment lifecycle.
+//!
+//! The Money contract wasm is deployed under a new deploy key, upgraded,
+//! and frozen. We check that only the deploy key can do this, that every
+//! deployment is kept in the version history, and that frozen contracts
+//! can't be upgraded anymore.
+
+use darkfi::{tx::Transaction, Result};
+use darkfi_sdk::{
+    crypto::{ContractId, Keypair, SecretKey, DEPLOY_CONTRACT_ID},
+    deploy::{DeployFunction, DeployParams, FreezeParams},
+    ContractCall,
+};
+use darkfi_serial::{serialize, Encodable};
+use log::info;
+use rand::rngs::OsRng;
+
+use darkfi_money_contract::MONEY_CONTRACT_ZKAS_MINT_NS_V1;
+
+mod harness;
+use harness::{init_logger, MoneyTestHarness};
+
+/// Build a transaction with a single deploy call, signed with `signer`
+fn deploy_call_tx(data: Vec<u8>, signer: &SecretKey) -> Result<Transaction> {
+    let calls = vec![ContractCall { contract_id: *DEPLOY_CONTRACT_ID, data }];
+    let proofs = vec![vec![]];
+    let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
+    let sigs = tx.create_sigs(&mut OsRng, &[*signer])?;
+    tx.signatures = vec![sigs];
+    Ok(tx)
+}
+
+fn deploy_tx(
+    th: &MoneyTestHarness,
+    deploy_kp: &Keypair,
+    signer: &SecretKey,
+) -> Result<Transaction> {
+    let params = DeployParams {
+        public_key: deploy_kp.public,
+        wasm_bincode: include_bytes!("../money_contract.wasm").to_vec(),
+        payload: serialize(&th.faucet_pubkeys),
+    };
+
+    let mut data = vec![DeployFunction::Deploy as u8];
+    params.encode(&mut data)?;
+    deploy_call_tx(data, signer)
+}
+
+fn freeze_tx(deploy_kp: &Keypair, signer: &SecretKey) -> Result<Transaction> {
+    let params = FreezeParams { public_key: deploy_kp.public };
+    let mut data = vec![DeployFunction::Freeze as u8];
+    params.encode(&mut data)?;
+    deploy_call_tx(data, signer)
+}
+
+#[async_std::test]
+async fn contract_deploy_lifecycle() -> Result<()> {
+    init_logger()?;
+
+    let th = MoneyTestHarness::new().await?;
+    let state = th.alice_state.read().await;
+    let deploy_kp = Keypair::random(&mut OsRng);
+    let contract_id = ContractId::derive(deploy_kp.secret);
+
+    info!(target: "money", "[Alice] ==================================");
+    info!(target: "money", "[Alice] Rejecting deployment by a wrong key");
+    info!(target: "money", "[Alice] ==================================");
+    let tx = deploy_tx(&th, &deploy_kp, &th.alice_kp.secret)?;
+    assert_eq!(state.verify_transactions(&[tx], true).await?.len(), 1);
+    assert_eq!(state.blockchain.contract_versions.latest(&contract_id)?, None);
+    assert!(state.blockchain.wasm_bincode.get(contract_id).is_err());
+
+    info!(target: "money", "[Alice] ===================");
+    info!(target: "money", "[Alice] Deploying contract");
+    info!(target: "money", "[Alice] ===================");
+    let tx = deploy_tx(&th, &deploy_kp, &deploy_kp.secret)?;
+    assert!(state.verify_transactions(&[tx], true).await?.is_empty());
+    assert_eq!(state.blockchain.contract_versions.latest(&contract_id)?, Some(0));
+    assert!(state.blockchain.wasm_bincode.get(contract_id).is_ok());
+
+    // The circuits of the new contract can be used for verification
+    let version = state.blockchain.contract_versions.get(&contract_id, 0)?;
+    assert!(version.zkas_bincodes.iter().any(|x| x.0 == MONEY_CONTRACT_ZKAS_MINT_NS_V1));
+    let vks = state.verifying_keys.read().await;
+    assert_eq!(vks.get(&contract_id.to_bytes()).unwrap().len(), version.zkas_bincodes.len());
+    drop(vks);
+
+    info!(target: "money", "[Alice] ===================");
+    info!(target: "money", "[Alice] Upgrading contract");
+    info!(target: "money", "[Alice] ===================");
+    let tx = deploy_tx(&th, &deploy_kp, &deploy_kp.secret)?;
+    assert!(state.verify_transactions(&[tx], true).await?.is_empty());
+    assert_eq!(state.blockchain.contract_versions.latest(&contract_id)?, Some(1));
+    assert_eq!(state.blockchain.contract_versions.get_all(&contract_id)?.len(), 2);
+
+    info!(target: "money", "[Alice] ================================");
+    info!(target: "money", "[Alice] Rejecting freeze by a wrong key");
+    info!(target: "money", "[Alice] ================================");
+    let tx = freeze_tx(&deploy_kp, &th.alice_kp.secret)?;
+    assert_eq!(state.verify_transactions(&[tx], true).await?.len(), 1);
+    assert!(!state.blockchain.frozen_contracts.is_frozen(&contract_id)?);
+
+    info!(target: "money", "[Alice] ==================");
+    info!(target: "money", "[Alice] Freezing contract");
+    info!(target: "money", "[Alice] ==================");
+    let tx = freeze_tx(&deploy_kp, &deploy_kp.secret)?;
+    assert!(state.verify_transactions(&[tx], true).await?.is_empty());
+    assert!(state.blockchain.frozen_contracts.is_frozen(&contract_id)?);
+
+    info!(target: "money", "[Alice] ==========================================");
+    info!(target: "money", "[Alice] Rejecting upgrade and freeze of frozen contract");
+    info!(target: "money", "[Alice] ==========================================");
+    let tx = deploy_tx(&th, &deploy_kp, &deploy_kp.secret)?;
+    assert_eq!(state.verify_transactions(&[tx], true).await?.len(), 1);
+    let tx = freeze_tx(&deploy_kp, &deploy_kp.secret)?;
+    assert_eq!(state.verify_transactions(&[tx], true).await?.len(), 1);
+    assert_eq!(state.blockchain.contract_versions.latest(&contract_id)?, Some(1));
+
+    // Thanks for reading
+    Ok(())
+}
@@ -0,0 +1,138 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Integration test for paying transaction fees with the Money contract.
+//!
+//! We airdrop native tokens to Alice, who then pays a fee with them. The
+//! transaction is only valid when the fee it declares matches the fee paid
+//! by the Money::Fee call.
+
+use darkfi::{tx::Transaction, Result};
+use darkfi_sdk::{
+    crypto::{merkle_prelude::*, poseidon_hash, MerkleNode, Nullifier, DARK_TOKEN_ID},
+    ContractCall,
+};
+use darkfi_serial::Encodable;
+use log::info;
+use rand::rngs::OsRng;
+
+use darkfi_money_contract::{
+    client::{build_fee_tx, Coin, EncryptedNote, OwnCoin},
+    MoneyFunction,
+};
+
+mod harness;
+use harness::{init_logger, MoneyTestHarness};
+
+#[async_std::test]
+async fn money_contract_fee() -> Result<()> {
+    init_logger()?;
+
+    const ALICE_INITIAL: u64 = 100;
+    const FEE: u64 = 10;
+
+    let mut th = MoneyTestHarness::new().await?;
+
+    info!(target: "money", "[Faucet] Airdropping native tokens to Alice");
+    let (airdrop_tx, airdrop_params) =
+        th.airdrop(ALICE_INITIAL, *DARK_TOKEN_ID, &th.alice_kp.public)?;
+    let erroneous_txs =
+        th.alice_state.read().await.verify_transactions(&[airdrop_tx], true).await?;
+    assert!(erroneous_txs.is_empty());
+    th.alice_merkle_tree.append(&MerkleNode::from(airdrop_params.outputs[0].coin));
+    let leaf_position = th.alice_merkle_tree.witness().unwrap();
+
+    let output = &airdrop_params.outputs[0];
+    let e_note =
+        EncryptedNote { ciphertext: output.ciphertext.clone(), ephem_public: output.ephem_public };
+    let note = e_note.decrypt(&th.alice_kp.secret)?;
+    let alice_oc = OwnCoin {
+        coin: Coin::from(output.coin),
+        note: note.clone(),
+        secret: th.alice_kp.secret,
+        nullifier: Nullifier::from(poseidon_hash([th.alice_kp.secret.inner(), note.serial])),
+        leaf_position,
+    };
+
+    info!(target: "money", "[Alice] Building Money::Fee params");
+    let (params, proofs, secret_keys, _spent_coins) = build_fee_tx(
+        &th.alice_kp,
+        FEE,
+        &[alice_oc],
+        &th.alice_merkle_tree,
+        &th.mint_zkbin,
+        &th.mint_pk,
+        &th.burn_zkbin,
+        &th.burn_pk,
+    )?;
+
+    let mut data = vec![MoneyFunction::Fee as u8];
+    params.encode(&mut data)?;
+    let calls = vec![ContractCall { contract_id: th.money_contract_id, data }];
+
+    info!(target: "money", "[Alice] Declaring a fee other than the one paid");
+    let mut tx = Transaction {
+        calls: calls.clone(),
+        gas_limits: vec![],
+        fee: FEE - 1,
+        proofs: vec![proofs.clone()],
+        signatures: vec![],
+    };
+    let sigs = tx.create_sigs(&mut OsRng, &secret_keys)?;
+    tx.signatures = vec![sigs];
+    let erroneous_txs = th.alice_state.read().await.verify_transactions(&[tx], true).await?;
+    assert_eq!(erroneous_txs.len(), 1);
+
+    info!(target: "money", "[Alice] Setting a gas limit too low to execute the call");
+    let mut tx = Transaction {
+        calls: calls.clone(),
+        gas_limits: vec![1],
+        fee: FEE,
+        proofs: vec![proofs.clone()],
+        signatures: vec![],
+    };
+    let sigs = tx.create_sigs(&mut OsRng, &secret_keys)?;
+    tx.signatures = vec![sigs];
+    let erroneous_txs = th.alice_state.read().await.verify_transactions(&[tx], true).await?;
+    assert_eq!(erroneous_txs.len(), 1);
+
+    info!(target: "money", "[Alice] Paying the fee");
+    let mut tx = Transaction {
+        calls,
+        gas_limits: vec![],
+        fee: FEE,
+        proofs: vec![proofs],
+        signatures: vec![],
+    };
+    let sigs = tx.create_sigs(&mut OsRng, &secret_keys)?;
+    tx.signatures = vec![sigs];
+    let erroneous_txs = th.alice_state.read().await.verify_transactions(&[tx], true).await?;
+    assert!(erroneous_txs.is_empty());
+    th.alice_merkle_tree.append(&MerkleNode::from(params.output.coin));
+
+    // Alice gets the change back
+    let e_note = EncryptedNote {
+        ciphertext: params.output.ciphertext.clone(),
+        ephem_public: params.output.ephem_public,
+    };
+    let note = e_note.decrypt(&th.alice_kp.secret)?;
+    assert_eq!(note.value, ALICE_INITIAL - FEE);
+    assert_eq!(note.token_id, *DARK_TOKEN_ID);
+
+    Ok(())
+}
@@ -15,7 +15,7 @@
  * You should have received a copy of the GNU Affero General Public License
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
-use std::collections::HashMap;
+use std::{collections::HashMap, sync::Arc};
 
 use darkfi::{
     consensus::{
@@ -24,7 +24,7 @@ use darkfi::{
     },
     tx::Transaction,
     wallet::WalletDb,
-    zk::{empty_witnesses, ProvingKey, ZkCircuit},
+    zk::{KeyCache, ProvingKey},
     zkas::ZkBinary,
     Result,
 };
@@ -43,6 +43,7 @@ use rand::rngs::OsRng;
 use darkfi_money_contract::{
     client::build_transfer_tx, model::MoneyTransferParams, MoneyFunction,
     MONEY_CONTRACT_ZKAS_BURN_NS_V1, MONEY_CONTRACT_ZKAS_MINT_NS_V1,
+    MONEY_CONTRACT_ZKAS_TOKEN_MINT_NS_V1,
 };
 
 pub fn init_logger() -> Result<()> {
@@ -78,6 +79,8 @@ pub struct MoneyTestHarness {
     pub burn_zkbin: ZkBinary,
     pub mint_pk: ProvingKey,
     pub burn_pk: ProvingKey,
+    pub token_mint_zkbin: ZkBinary,
+    pub token_mint_pk: ProvingKey,
     pub faucet_merkle_tree: MerkleTree,
     pub alice_merkle_tree: MerkleTree,
     pub bob_merkle_tree: MerkleTree,
@@ -102,6 +105,9 @@ impl MoneyTestHarness {
         let bob_sled_db = sled::Config::new().temporary(true).open()?;
         let charlie_sled_db = sled::Config::new().temporary(true).open()?;
 
+        // All the nodes share a key cache, so each key is only built once
+        let key_cache = Arc::new(KeyCache::new(None)?);
+
         let faucet_state = ValidatorState::new(
             &faucet_sled_db,
             *TESTNET_BOOTSTRAP_TIMESTAMP,
@@ -109,6 +115,7 @@ impl MoneyTestHarness {
             *TESTNET_GENESIS_HASH_BYTES,
             *TESTNET_INITIAL_DISTRIBUTION,
             faucet_wallet,
+            key_cache.clone(),
             faucet_pubkeys.clone(),
             false,
             false,
@@ -122,6 +129,7 @@ impl MoneyTestHarness {
             *TESTNET_GENESIS_HASH_BYTES,
             *TESTNET_INITIAL_DISTRIBUTION,
             alice_wallet,
+            key_cache.clone(),
             faucet_pubkeys.clone(),
             false,
             false,
@@ -135,6 +143,7 @@ impl MoneyTestHarness {
             *TESTNET_GENESIS_HASH_BYTES,
             *TESTNET_INITIAL_DISTRIBUTION,
             bob_wallet,
+            key_cache.clone(),
             faucet_pubkeys.clone(),
             false,
             false,
@@ -148,6 +157,7 @@ impl MoneyTestHarness {
             *TESTNET_GENESIS_HASH_BYTES,
             *TESTNET_INITIAL_DISTRIBUTION,
             charlie_wallet,
+            key_cache.clone(),
             faucet_pubkeys.clone(),
             false,
             false,
@@ -165,25 +175,26 @@ impl MoneyTestHarness {
 
         let mint_zkbin = db_handle.get(&serialize(&MONEY_CONTRACT_ZKAS_MINT_NS_V1))?.unwrap();
         let burn_zkbin = db_handle.get(&serialize(&MONEY_CONTRACT_ZKAS_BURN_NS_V1))?.unwrap();
-        info!(target: "money_harness", "Decoding bincode");
-        let mint_zkbin = ZkBinary::decode(&mint_zkbin)?;
-        let burn_zkbin = ZkBinary::decode(&burn_zkbin)?;
-        let mint_witnesses = empty_witnesses(&mint_zkbin);
-        let burn_witnesses = empty_witnesses(&burn_zkbin);
-        let mint_circuit = ZkCircuit::new(mint_witnesses, mint_zkbin.clone());
-        let burn_circuit = ZkCircuit::new(burn_witnesses, burn_zkbin.clone());
+        let token_mint_zkbin =
+            db_handle.get(&serialize(&MONEY_CONTRACT_ZKAS_TOKEN_MINT_NS_V1))?.unwrap();
 
         info!(target: "money_harness", "Creating zk proving keys");
-        let k = 13;
         let mut proving_keys = HashMap::<[u8; 32], Vec<(&str, ProvingKey)>>::new();
-        let mint_pk = ProvingKey::build(k, &mint_circuit);
-        let burn_pk = ProvingKey::build(k, &burn_circuit);
+        let mint_pk = key_cache.proving_key(&mint_zkbin)?;
+        let burn_pk = key_cache.proving_key(&burn_zkbin)?;
+        let token_mint_pk = key_cache.proving_key(&token_mint_zkbin)?;
         let pks = vec![
             (MONEY_CONTRACT_ZKAS_MINT_NS_V1, mint_pk.clone()),
             (MONEY_CONTRACT_ZKAS_BURN_NS_V1, burn_pk.clone()),
+            (MONEY_CONTRACT_ZKAS_TOKEN_MINT_NS_V1, token_mint_pk.clone()),
         ];
         proving_keys.insert(money_contract_id.inner().to_repr(), pks);
 
+        info!(target: "money_harness", "Decoding bincode");
+        let mint_zkbin = ZkBinary::decode(&mint_zkbin)?;
+        let burn_zkbin = ZkBinary::decode(&burn_zkbin)?;
+        let token_mint_zkbin = ZkBinary::decode(&token_mint_zkbin)?;
+
         let faucet_merkle_tree = MerkleTree::new(100);
         let alice_merkle_tree = MerkleTree::new(100);
         let bob_merkle_tree = MerkleTree::new(100);
@@ -205,6 +216,8 @@ impl MoneyTestHarness {
             burn_pk,
             mint_zkbin,
             burn_zkbin,
+            token_mint_zkbin,
+            token_mint_pk,
             faucet_merkle_tree,
             alice_merkle_tree,
             bob_merkle_tree,
@@ -217,9 +230,21 @@ impl MoneyTestHarness {
         amount: u64,
         token_id: TokenId,
         rcpt: &PublicKey,
+    ) -> Result<(Transaction, MoneyTransferParams)> {
+        self.airdrop_from(&self.faucet_kp, amount, token_id, rcpt)
+    }
+
+    /// Build an airdrop whose clear input is signed by `signer`, which is
+    /// only valid if `signer` is a faucet.
+    pub fn airdrop_from(
+        &self,
+        signer: &Keypair,
+        amount: u64,
+        token_id: TokenId,
+        rcpt: &PublicKey,
     ) -> Result<(Transaction, MoneyTransferParams)> {
         let (params, proofs, secret_keys, _) = build_transfer_tx(
-            &self.faucet_kp,
+            signer,
             rcpt,
             amount,
             token_id,
@@ -241,7 +266,7 @@ impl MoneyTestHarness {
         params.encode(&mut data)?;
         let calls = vec![ContractCall { contract_id, data }];
         let proofs = vec![proofs];
-        let mut tx = Transaction { calls, proofs, signatures: vec![] };
+        let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
         let sigs = tx.create_sigs(&mut OsRng, &secret_keys)?;
         tx.signatures = vec![sigs];
 
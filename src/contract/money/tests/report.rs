@@ -0,0 +1,138 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Integration test for the per-call verification report of transactions.
+//!
+//! The Faucet builds airdrops to Alice that fail verification in different
+//! ways: one carries the ZK proof of another airdrop, one is signed with the
+//! wrong key, and one has its clear input signed by Alice, who is not a
+//! faucet. We check that the JSON report of each call tells what failed,
+//! and that the Money contract's error code and message reach the report
+//! as they do for `tx.simulate`.
+
+use darkfi::{runtime::vm_runtime::CallError, Result};
+use darkfi_sdk::{
+    crypto::{pallas, pasta_prelude::*, TokenId},
+    error::ContractError,
+};
+use log::info;
+use rand::rngs::OsRng;
+
+use darkfi_money_contract::error::MoneyError;
+
+mod harness;
+use harness::{init_logger, MoneyTestHarness};
+
+#[async_std::test]
+async fn money_contract_tx_report() -> Result<()> {
+    init_logger()?;
+
+    let th = MoneyTestHarness::new().await?;
+    let token_id = TokenId::from(pallas::Base::random(&mut OsRng));
+
+    info!(target: "money", "[Faucet] Building an airdrop with the proof of another one");
+    let (mut tx, _) = th.airdrop(100, token_id, &th.alice_kp.public)?;
+    let (other_tx, _) = th.airdrop(200, token_id, &th.alice_kp.public)?;
+    tx.proofs = other_tx.proofs;
+    tx.signatures = vec![tx.create_sigs(&mut OsRng, &[th.faucet_kp.secret])?];
+
+    let (_, reports) = th.alice_state.read().await.verify_transactions_report(&[tx], false).await?;
+    let report = reports[0].to_json();
+    assert_eq!(report["valid"], false);
+    assert_eq!(report["applied"], false);
+    let call = &report["calls"][0];
+    assert_eq!(call["sections"][0]["section"], "__metadata");
+    assert_eq!(call["sections"][1]["section"], "__entrypoint");
+    assert!(call["sections"][1]["error"].is_null());
+    assert_eq!(call["signatures"]["verified"], true);
+    assert_eq!(call["proofs"][0]["verified"], false);
+    assert!(call["proofs"][0]["error"].is_string());
+    assert!(report["error"].as_str().unwrap().contains("ZK proof verification failed"));
+
+    info!(target: "money", "[Faucet] Building an airdrop signed with the wrong key");
+    let (mut tx, _) = th.airdrop(100, token_id, &th.alice_kp.public)?;
+    tx.signatures = vec![tx.create_sigs(&mut OsRng, &[th.alice_kp.secret])?];
+
+    let (_, reports) = th.alice_state.read().await.verify_transactions_report(&[tx], false).await?;
+    let report = reports[0].to_json();
+    assert_eq!(report["valid"], false);
+    let call = &report["calls"][0];
+    assert_eq!(call["signatures"]["verified"], false);
+    assert_eq!(call["signatures"]["public_keys"][0], th.faucet_kp.public.to_string());
+    // Proofs are only verified once the signatures are
+    assert!(call["proofs"][0]["verified"].is_null());
+    assert!(report["error"].as_str().unwrap().contains("Signature verification failed"));
+
+    info!(target: "money", "[Alice] Building an airdrop without being a faucet");
+    let (tx, _) = th.airdrop_from(&th.alice_kp, 100, token_id, &th.alice_kp.public)?;
+
+    let (_, reports) = th.alice_state.read().await.verify_transactions_report(&[tx], false).await?;
+    let report = reports[0].to_json();
+    assert_eq!(report["valid"], false);
+    let call = &report["calls"][0];
+    assert!(call["sections"][0]["error"].is_null());
+    let exec = &call["sections"][1];
+    assert_eq!(exec["section"], "__entrypoint");
+    assert_eq!(exec["code"], MoneyError::ClearInputUnauthorised as i64);
+    assert_eq!(exec["message"], MoneyError::ClearInputUnauthorised.message());
+    assert!(exec["logs"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .any(|l| l.as_str().unwrap().contains("faucet")));
+    // Nothing else is checked after a call fails
+    assert!(call["signatures"]["verified"].is_null());
+    assert!(call["proofs"].as_array().unwrap().iter().all(|p| p["verified"].is_null()));
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn money_contract_error_outcome() -> Result<()> {
+    init_logger()?;
+
+    let th = MoneyTestHarness::new().await?;
+    let token_id = TokenId::from(pallas::Base::random(&mut OsRng));
+
+    // Simulate the transaction like `tx.simulate` does, without writing
+    info!(target: "money", "[Alice] Simulating an airdrop without being a faucet");
+    let (tx, _) = th.airdrop_from(&th.alice_kp, 100, token_id, &th.alice_kp.public)?;
+    let (erroneous_txs, reports) =
+        th.alice_state.read().await.verify_transactions_report(&[tx], false).await?;
+    assert_eq!(erroneous_txs.len(), 1);
+
+    let error = MoneyError::ClearInputUnauthorised;
+    let outcomes = &reports[0].calls[0].outcomes;
+    assert_eq!(outcomes.len(), 2);
+    assert!(outcomes[0].is_ok());
+    match &outcomes[1].error {
+        Some(CallError::Contract { code, error: ContractError::Custom(custom), message }) => {
+            assert_eq!(*code, error as i64);
+            assert_eq!(*custom, error as u32);
+            assert_eq!(message.as_deref(), Some(error.message()));
+        }
+        e => panic!("Expected a Money contract error, got {:?}", e),
+    }
+
+    // The transaction's error names the failing section and the message
+    let reason = reports[0].error.as_ref().unwrap();
+    assert!(reason.contains("__entrypoint"));
+    assert!(reason.contains(error.message()));
+
+    Ok(())
+}
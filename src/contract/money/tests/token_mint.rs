@@ -0,0 +1,165 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Integration test for minting custom tokens with the Money contract.
+//!
+//! Alice holds a mint authority and mints tokens to Bob, first with an open
+//! supply and then fixing it. We check that only the mint authority can
+//! mint, that no mints are possible once the supply is fixed, and that Bob
+//! can spend the minted coins.
+
+use darkfi::{tx::Transaction, Result};
+use darkfi_sdk::{
+    crypto::{
+        merkle_prelude::*, pallas, pasta_prelude::*, poseidon_hash, Keypair, MerkleNode,
+        MerkleTree, Nullifier, SecretKey, TokenId,
+    },
+    ContractCall,
+};
+use darkfi_serial::Encodable;
+use log::info;
+use rand::rngs::OsRng;
+
+use darkfi_money_contract::{
+    client::{build_token_mint_tx, build_transfer_tx, Coin, EncryptedNote, OwnCoin},
+    model::MoneyMintParams,
+    MoneyFunction,
+};
+
+mod harness;
+use harness::{init_logger, MoneyTestHarness};
+
+/// Build a token mint transaction, signed with `signer`
+fn mint_tx(
+    th: &MoneyTestHarness,
+    mint_authority: &Keypair,
+    signer: &SecretKey,
+    amount: u64,
+    fixed_supply: bool,
+) -> Result<(Transaction, MoneyMintParams)> {
+    let (params, proofs) = build_token_mint_tx(
+        mint_authority,
+        &th.bob_kp.public,
+        amount,
+        pallas::Base::zero(),
+        pallas::Base::zero(),
+        fixed_supply,
+        &th.token_mint_zkbin,
+        &th.token_mint_pk,
+    )?;
+
+    let mut data = vec![MoneyFunction::Mint as u8];
+    params.encode(&mut data)?;
+    let calls = vec![ContractCall { contract_id: th.money_contract_id, data }];
+    let proofs = vec![proofs];
+    let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
+    let sigs = tx.create_sigs(&mut OsRng, &[*signer])?;
+    tx.signatures = vec![sigs];
+
+    Ok((tx, params))
+}
+
+#[async_std::test]
+async fn money_contract_token_mint() -> Result<()> {
+    init_logger()?;
+
+    const FIRST_MINT: u64 = 1000;
+    const SECOND_MINT: u64 = 500;
+
+    let th = MoneyTestHarness::new().await?;
+    let mut tree = MerkleTree::new(100);
+    let mut bob_owncoins = vec![];
+
+    let mint_authority = Keypair::random(&mut OsRng);
+    let token_id = TokenId::derive(mint_authority.secret);
+
+    info!(target: "money", "[Alice] Minting tokens signed by a key other than the mint authority");
+    let (tx, _) = mint_tx(&th, &mint_authority, &th.alice_kp.secret, FIRST_MINT, false)?;
+    let erroneous_txs = th.alice_state.read().await.verify_transactions(&[tx], true).await?;
+    assert_eq!(erroneous_txs.len(), 1);
+
+    info!(target: "money", "[Alice] Minting tokens to Bob");
+    let (tx, params) = mint_tx(&th, &mint_authority, &mint_authority.secret, FIRST_MINT, false)?;
+    assert_eq!(params.token_id, token_id);
+    let erroneous_txs = th.alice_state.read().await.verify_transactions(&[tx], true).await?;
+    assert!(erroneous_txs.is_empty());
+    tree.append(&MerkleNode::from(params.output.coin));
+    bob_owncoins.push((params, tree.witness().unwrap()));
+
+    info!(target: "money", "[Alice] Minting tokens to Bob and fixing the supply");
+    let (tx, params) = mint_tx(&th, &mint_authority, &mint_authority.secret, SECOND_MINT, true)?;
+    let erroneous_txs = th.alice_state.read().await.verify_transactions(&[tx], true).await?;
+    assert!(erroneous_txs.is_empty());
+    tree.append(&MerkleNode::from(params.output.coin));
+    bob_owncoins.push((params, tree.witness().unwrap()));
+
+    info!(target: "money", "[Alice] Minting tokens after fixing the supply");
+    let (tx, _) = mint_tx(&th, &mint_authority, &mint_authority.secret, FIRST_MINT, false)?;
+    let erroneous_txs = th.alice_state.read().await.verify_transactions(&[tx], true).await?;
+    assert_eq!(erroneous_txs.len(), 1);
+
+    // Bob builds `OwnCoin`s from the mints
+    let mut owncoins = vec![];
+    for (params, leaf_position) in bob_owncoins {
+        let e_note = EncryptedNote {
+            ciphertext: params.output.ciphertext.clone(),
+            ephem_public: params.output.ephem_public,
+        };
+        let note = e_note.decrypt(&th.bob_kp.secret)?;
+        assert_eq!(note.token_id, token_id);
+
+        owncoins.push(OwnCoin {
+            coin: Coin::from(params.output.coin),
+            note: note.clone(),
+            secret: th.bob_kp.secret,
+            nullifier: Nullifier::from(poseidon_hash([th.bob_kp.secret.inner(), note.serial])),
+            leaf_position,
+        });
+    }
+
+    info!(target: "money", "[Bob] Sending the minted tokens to Alice");
+    let (params, proofs, secret_keys, spent_coins) = build_transfer_tx(
+        &th.bob_kp,
+        &th.alice_kp.public,
+        FIRST_MINT + SECOND_MINT,
+        token_id,
+        pallas::Base::zero(),
+        pallas::Base::zero(),
+        pallas::Base::random(&mut OsRng),
+        &owncoins,
+        &tree,
+        &th.mint_zkbin,
+        &th.mint_pk,
+        &th.burn_zkbin,
+        &th.burn_pk,
+        false,
+    )?;
+    assert_eq!(spent_coins.len(), 2);
+
+    let mut data = vec![MoneyFunction::Transfer as u8];
+    params.encode(&mut data)?;
+    let calls = vec![ContractCall { contract_id: th.money_contract_id, data }];
+    let proofs = vec![proofs];
+    let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
+    let sigs = tx.create_sigs(&mut OsRng, &secret_keys)?;
+    tx.signatures = vec![sigs];
+    let erroneous_txs = th.alice_state.read().await.verify_transactions(&[tx], true).await?;
+    assert!(erroneous_txs.is_empty());
+
+    Ok(())
+}
@@ -16,7 +16,7 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::{env, str::FromStr};
+use std::{env, str::FromStr, time::Instant};
 
 use darkfi::{tx::Transaction, Result};
 use darkfi_sdk::{
@@ -116,7 +116,7 @@ async fn alice2alice_random_amounts() -> Result<()> {
         params.encode(&mut data)?;
         let calls = vec![ContractCall { contract_id, data }];
         let proofs = vec![proofs];
-        let mut tx = Transaction { calls, proofs, signatures: vec![] };
+        let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
         let sigs = tx.create_sigs(&mut OsRng, &secret_keys)?;
         tx.signatures = vec![sigs];
 
@@ -258,7 +258,8 @@ async fn alice2alice_random_amounts_multiplecoins() -> Result<()> {
             params.encode(&mut data)?;
             let calls = vec![ContractCall { contract_id, data }];
             let proofs = vec![proofs];
-            let mut tx = Transaction { calls, proofs, signatures: vec![] };
+            let mut tx =
+                Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
             let sigs = tx.create_sigs(&mut OsRng, &secret_keys)?;
             tx.signatures = vec![sigs];
 
@@ -305,3 +306,113 @@ async fn alice2alice_random_amounts_multiplecoins() -> Result<()> {
 
     Ok(())
 }
+
+#[async_std::test]
+async fn alice2alice_batch_verification() -> Result<()> {
+    init_logger()?;
+
+    const ALICE_AIRDROP: u64 = 1000;
+
+    // n transactions to build
+    let mut n = 3;
+    for arg in env::args() {
+        match usize::from_str(&arg) {
+            Ok(v) => {
+                n = v;
+                break
+            }
+            Err(_) => continue,
+        };
+    }
+    // We need at least two transactions to swap proofs between them
+    let n = n.max(2);
+
+    let mut th = MoneyTestHarness::new().await?;
+    let token_id = TokenId::from(pallas::Base::random(&mut OsRng));
+    let contract_id = *MONEY_CONTRACT_ID;
+
+    let (airdrop_tx, airdrop_params) = th.airdrop(ALICE_AIRDROP, token_id, &th.alice_kp.public)?;
+
+    th.alice_state.read().await.verify_transactions(&[airdrop_tx.clone()], true).await?;
+    th.alice_merkle_tree.append(&MerkleNode::from(airdrop_params.outputs[0].coin));
+    let leaf_position = th.alice_merkle_tree.witness().unwrap();
+
+    let ciphertext = airdrop_params.outputs[0].ciphertext.clone();
+    let ephem_public = airdrop_params.outputs[0].ephem_public;
+    let e_note = EncryptedNote { ciphertext, ephem_public };
+    let note = e_note.decrypt(&th.alice_kp.secret)?;
+
+    let owncoins = vec![OwnCoin {
+        coin: Coin::from(airdrop_params.outputs[0].coin),
+        note: note.clone(),
+        secret: th.alice_kp.secret,
+        nullifier: Nullifier::from(poseidon_hash([th.alice_kp.secret.inner(), note.serial])),
+        leaf_position,
+    }];
+
+    // Since we never write the state transitions, every transaction
+    // can spend the same coin.
+    let mut txs = vec![];
+    let mut tx_secret_keys = vec![];
+    for i in 0..n {
+        info!(target: "money", "Building Alice2Alice transfer tx {}", i);
+
+        let amount = rand::thread_rng().gen_range(1..ALICE_AIRDROP);
+        let (params, proofs, secret_keys, _) = build_transfer_tx(
+            &th.alice_kp,
+            &th.alice_kp.public,
+            amount,
+            token_id,
+            pallas::Base::zero(),
+            pallas::Base::zero(),
+            pallas::Base::random(&mut OsRng),
+            &owncoins,
+            &th.alice_merkle_tree,
+            &th.mint_zkbin,
+            &th.mint_pk,
+            &th.burn_zkbin,
+            &th.burn_pk,
+            false,
+        )?;
+
+        let mut data = vec![MoneyFunction::Transfer as u8];
+        params.encode(&mut data)?;
+        let calls = vec![ContractCall { contract_id, data }];
+        let proofs = vec![proofs];
+        let mut tx = Transaction { calls, gas_limits: vec![], fee: 0, proofs, signatures: vec![] };
+        let sigs = tx.create_sigs(&mut OsRng, &secret_keys)?;
+        tx.signatures = vec![sigs];
+
+        txs.push(tx);
+        tx_secret_keys.push(secret_keys);
+    }
+
+    let state = th.alice_state.read().await;
+
+    // Verify the transactions one by one
+    let timer = Instant::now();
+    for tx in &txs {
+        assert!(state.verify_transactions(&[tx.clone()], false).await?.is_empty());
+    }
+    let sequential = timer.elapsed();
+
+    // Verify them all at once, batching the proofs
+    let timer = Instant::now();
+    assert!(state.verify_transactions(&txs, false).await?.is_empty());
+    let batched = timer.elapsed();
+
+    info!(target: "money", "Verified {} transactions one by one in {:?}", n, sequential);
+    info!(target: "money", "Verified {} transactions in a batch in {:?}", n, batched);
+
+    // Replace a proof with one belonging to another transaction, and make
+    // sure the fallback singles out the offending transaction.
+    let mut bad_tx = txs[0].clone();
+    bad_tx.proofs[0][0] = txs[1].proofs[0][0].clone();
+    bad_tx.signatures = vec![bad_tx.create_sigs(&mut OsRng, &tx_secret_keys[0])?];
+    txs[0] = bad_tx.clone();
+
+    let erroneous_txs = state.verify_transactions(&txs, false).await?;
+    assert_eq!(erroneous_txs, vec![bad_tx]);
+
+    Ok(())
+}
@@ -142,6 +142,9 @@ pub enum Error {
     #[error("Malformed packet")]
     MalformedPacket,
 
+    #[error("Payload of {0} message exceeds size limit: {1} bytes")]
+    MessageTooLarge(String, usize),
+
     #[error("Socks proxy error: {0}")]
     SocksError(String),
 
@@ -158,9 +161,18 @@ pub enum Error {
     #[error("Tor error: {0}")]
     TorError(String),
 
+    #[error("Nym error: {0}")]
+    NymError(String),
+
+    #[error("Memory transport error: {0}")]
+    MemoryTransportError(String),
+
     #[error("Node is not connected to other nodes.")]
     NetworkNotConnected,
 
+    #[error("TLS identity error: {0}")]
+    TlsIdentityError(String),
+
     // =============
     // Crypto errors
     // =============
@@ -224,6 +236,12 @@ pub enum Error {
     #[error("Unexpected JSON-RPC data received: {0}")]
     UnexpectedJsonRpc(String),
 
+    #[error("JSON-RPC message exceeds the size limit of {0} bytes")]
+    JsonRpcMessageTooLarge(usize),
+
+    #[error("HTTP error: {0}")]
+    HttpError(String),
+
     #[error("Received proposal from unknown node")]
     UnknownNodeError,
 
@@ -307,9 +325,27 @@ pub enum Error {
     #[error("Contract already initialized")]
     ContractAlreadyInitialized,
 
+    #[error("Version {1} of contract {0} not found in database")]
+    ContractVersionNotFound(String, u32),
+
     #[error("zkas bincode not found in sled database")]
     ZkasBincodeNotFound,
 
+    // ==============
+    // Mempool errors
+    // ==============
+    #[error("Transaction {0} is invalid: {1}")]
+    MempoolInvalidTx(String, String),
+
+    #[error("Transaction {0} is malformed: {1}")]
+    MempoolMalformedTx(String, String),
+
+    #[error("Transaction {0} reveals a nullifier already revealed by pending transaction {1}")]
+    MempoolDoubleSpend(String, String),
+
+    #[error("Mempool is full and transaction {0} does not outrank any pending one")]
+    MempoolFull(String),
+
     // =============
     // Wallet errors
     // =============
@@ -349,9 +385,8 @@ pub enum Error {
     #[error("wasm runtime out of memory")]
     WasmerOomError(String),
 
-    // TODO: FIXME: The strings are wrong
     #[cfg(feature = "darkfi-sdk")]
-    #[error("contract initialize error")]
+    #[error("Contract error: {0}")]
     ContractError(darkfi_sdk::error::ContractError),
 
     #[cfg(feature = "wasm-runtime")]
@@ -474,6 +509,15 @@ pub enum VerifyFailed {
     #[error("Failed verifying zk proofs: {0}")]
     ProofVerifyFailed(String),
 
+    #[error("Invalid gas limits: {0}")]
+    InvalidGasLimits(String),
+
+    #[error("Fee paid by the calls ({0}) does not match the transaction fee ({1})")]
+    FeeMismatch(u64, u64),
+
+    #[error("Invalid contract deployment call: {0}")]
+    InvalidDeploy(String),
+
     #[error("Internal error: {0}")]
     InternalError(String),
 }
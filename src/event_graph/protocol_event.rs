@@ -28,6 +28,7 @@ use super::get_current_time;
 use crate::{
     event_graph::model::{Event, EventId, ModelPtr},
     net,
+    net::constants::{BAN_SCORE_INVALID, BAN_SCORE_MALFORMED},
     util::async_util::sleep,
     Result,
 };
@@ -35,6 +36,7 @@ use crate::{
 const UNREAD_EVENT_EXPIRE_TIME: u64 = 3600; // in seconds
 const SIZE_OF_SEEN_BUFFER: usize = 65536;
 const MAX_CONFIRM: u8 = 3;
+const MAX_FUTURE_EVENT_TIME: u64 = 600_000; // in milliseconds
 
 #[derive(Clone)]
 struct RingBuffer<T> {
@@ -223,6 +225,11 @@ impl ProtocolEvent {
             let event = self.event_sub.receive().await?;
             let mut event = (*event).to_owned();
 
+            if event.timestamp > get_current_time() + MAX_FUTURE_EVENT_TIME {
+                self.channel.increase_ban_score(BAN_SCORE_INVALID, "Event from the future").await;
+                continue
+            }
+
             // This could be better
 
             if !self.seen_event.push(&event.hash()).await {
@@ -250,7 +257,10 @@ impl ProtocolEvent {
         loop {
             let inv = self.inv_sub.receive().await?;
             let inv = (*inv).to_owned();
-            let inv_item = inv.invs[0].clone();
+            let Some(inv_item) = inv.invs.first().cloned() else {
+                self.channel.increase_ban_score(BAN_SCORE_MALFORMED, "Empty inv").await;
+                continue
+            };
 
             // for inv in inv.invs.iter() {
             if !self.seen_inv.push(&inv_item.id).await {
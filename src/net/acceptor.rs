@@ -19,12 +19,15 @@
 use std::{env, fs};
 
 use async_std::sync::{Arc, Mutex};
-use log::{error, info};
+use log::{error, info, warn};
 use smol::Executor;
 use url::Url;
 
 use super::{
-    transport::{TcpTransport, TorTransport, Transport, TransportListener, TransportName},
+    transport::{
+        MemoryTransport, NymTransport, TcpTransport, TorTransport, Transport, TransportListener,
+        TransportName,
+    },
     Channel, ChannelPtr, SessionWeakPtr,
 };
 use crate::{
@@ -82,7 +85,9 @@ impl Acceptor {
                         self.accept(Box::new(listener), executor);
                     }
                     Some(u) if u == "tls" => {
-                        let tls_listener = $transport.upgrade_listener(listener)?.await?;
+                        let session = self.session.lock().await.clone().unwrap();
+                        let tls = session.upgrade().unwrap().p2p().tls_upgrade(None)?;
+                        let tls_listener = $transport.upgrade_listener(tls, listener)?.await?;
                         self.accept(Box::new(tls_listener), executor);
                     }
                     Some(u) => return Err(Error::UnsupportedTransportUpgrade(u)),
@@ -132,6 +137,17 @@ impl Acceptor {
 
                 accept!(listener, transport, upgrade);
             }
+            TransportName::Nym(upgrade) => {
+                // The accept url points to the local nym-client websocket
+                let transport = NymTransport::new(NymTransport::get_client_env()?);
+                let listener = transport.clone().listen_on(accept_url.clone());
+                accept!(listener, transport, upgrade);
+            }
+            TransportName::Memory(upgrade) => {
+                let transport = MemoryTransport::new(None);
+                let listener = transport.clone().listen_on(accept_url.clone());
+                accept!(listener, transport, upgrade);
+            }
             _ => unimplemented!(),
         }
         Ok(())
@@ -165,8 +181,17 @@ impl Acceptor {
         loop {
             match listener.next().await {
                 Ok((stream, url)) => {
-                    let channel =
-                        Channel::new(stream, url, self.session.lock().await.clone().unwrap()).await;
+                    let session = self.session.lock().await.clone().unwrap();
+                    let p2p = session.upgrade().unwrap().p2p();
+                    let channel = Channel::new(stream, url, session).await;
+
+                    // Drop connections from banned peers
+                    if p2p.is_channel_banned(&channel).await {
+                        warn!(target: "net::acceptor", "Rejected banned peer {}", channel.address());
+                        channel.stop().await;
+                        continue
+                    }
+
                     self.channel_subscriber.notify(Ok(channel)).await;
                 }
                 Err(e) => {
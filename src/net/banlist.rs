@@ -0,0 +1,128 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::collections::HashMap;
+
+use async_std::sync::{Arc, Mutex};
+use log::debug;
+use serde::Serialize;
+
+use crate::util::time::Timestamp;
+
+/// Atomic pointer to the ban list.
+pub type BanListPtr = Arc<BanList>;
+
+/// A ban of a misbehaving peer.
+#[derive(Clone, Debug, Serialize)]
+pub struct Ban {
+    /// Banned host, or hex-encoded TLS key of the banned peer
+    pub target: String,
+    /// Time the ban expires
+    pub until: Timestamp,
+    /// Why the peer was banned
+    pub reason: String,
+}
+
+/// List of time-limited bans. Peers are banned by their host and, when
+/// connected over TLS, by their identity key, so they can't dodge a ban
+/// by moving to another address.
+pub struct BanList {
+    bans: Mutex<HashMap<String, Ban>>,
+}
+
+impl BanList {
+    /// Create a new, empty ban list.
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self { bans: Mutex::new(HashMap::new()) })
+    }
+
+    /// Ban a target for the given number of seconds. Extends an existing
+    /// ban if the new one lasts longer.
+    pub async fn ban(&self, target: &str, seconds: u64, reason: &str) {
+        debug!(target: "net::banlist::ban()", "Banning {} for {}s: {}", target, seconds, reason);
+        let mut until = Timestamp::current_time();
+        until.add(seconds as i64);
+
+        let mut bans = self.bans.lock().await;
+        if let Some(ban) = bans.get(target) {
+            if ban.until.0 >= until.0 {
+                return
+            }
+        }
+
+        bans.insert(
+            target.to_string(),
+            Ban { target: target.to_string(), until, reason: reason.to_string() },
+        );
+    }
+
+    /// Lift the ban of a target. Returns `false` if it wasn't banned.
+    pub async fn unban(&self, target: &str) -> bool {
+        self.bans.lock().await.remove(target).is_some()
+    }
+
+    /// Check if a target is currently banned.
+    pub async fn is_banned(&self, target: &str) -> bool {
+        let mut bans = self.bans.lock().await;
+
+        match bans.get(target) {
+            Some(ban) if ban.until.0 > Timestamp::current_time().0 => true,
+            Some(_) => {
+                bans.remove(target);
+                false
+            }
+            None => false,
+        }
+    }
+
+    /// Return the active bans, dropping the expired ones.
+    pub async fn list(&self) -> Vec<Ban> {
+        let now = Timestamp::current_time();
+        let mut bans = self.bans.lock().await;
+        bans.retain(|_, ban| ban.until.0 > now.0);
+        bans.values().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BanList;
+
+    #[async_std::test]
+    async fn test_banlist() {
+        let banlist = BanList::new();
+
+        banlist.ban("185.60.216.35", 60, "malformed messages").await;
+        assert!(banlist.is_banned("185.60.216.35").await);
+        assert!(!banlist.is_banned("185.60.216.36").await);
+
+        // Shorter bans don't override longer ones
+        banlist.ban("185.60.216.35", 0, "invalid transaction").await;
+        assert!(banlist.is_banned("185.60.216.35").await);
+        assert_eq!(banlist.list().await[0].reason, "malformed messages");
+
+        // Expired bans are dropped
+        banlist.ban("facebook.com", 0, "invalid transaction").await;
+        assert!(!banlist.is_banned("facebook.com").await);
+        assert_eq!(banlist.list().await.len(), 1);
+
+        assert!(banlist.unban("185.60.216.35").await);
+        assert!(!banlist.unban("185.60.216.35").await);
+        assert!(banlist.list().await.is_empty());
+    }
+}
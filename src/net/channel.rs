@@ -16,26 +16,32 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::collections::HashMap;
+
 use async_std::sync::{Arc, Mutex};
+use ed25519_compact::PublicKey;
 use futures::{
     io::{ReadHalf, WriteHalf},
     AsyncReadExt,
 };
-use log::{debug, error, info};
+use log::{debug, error, info, warn};
 use rand::Rng;
 use serde_json::json;
 use smol::Executor;
 use url::Url;
 
 use super::{
+    constants::{BAN_SCORE_DROPPED, BAN_SCORE_MALFORMED},
     message,
     message_subscriber::{MessageSubscription, MessageSubsystem},
+    protocol::{FeatureBitflag, FEATURE_NONE},
+    rate_limit::TokenBucket,
     transport::TransportStream,
     Session, SessionBitflag, SessionWeakPtr,
 };
 use crate::{
     system::{StoppableTask, StoppableTaskPtr, Subscriber, SubscriberPtr, Subscription},
-    util::time::NanoTimestamp,
+    util::time::{NanoTimestamp, Timestamp},
     Error, Result,
 };
 
@@ -45,10 +51,24 @@ pub type ChannelPtr = Arc<Channel>;
 struct ChannelInfo {
     random_id: u32,
     remote_node_id: String,
+    // Net protocol version negotiated during the handshake
+    protocol_version: u32,
+    // Feature bits advertised by the remote
+    remote_features: FeatureBitflag,
+    // Addresses the remote accepts connections on
+    remote_connect_addrs: Vec<Url>,
+    // Remote clock offset from ours, in seconds
+    clock_offset: i64,
     last_msg: String,
     last_status: String,
     // Message log which is cleared on querying get_info
     log: Option<Mutex<Vec<(NanoTimestamp, String, String)>>>,
+    // Inbound messages dropped for exceeding their size limit
+    dropped_oversized: u64,
+    // Inbound messages dropped for exceeding the rate limits
+    dropped_rate_limited: u64,
+    // Dropped inbound messages per command
+    dropped_commands: HashMap<String, u64>,
 }
 
 impl ChannelInfo {
@@ -61,10 +81,25 @@ impl ChannelInfo {
         Self {
             random_id: rand::thread_rng().gen(),
             remote_node_id: String::new(),
+            protocol_version: 0,
+            remote_features: FEATURE_NONE,
+            remote_connect_addrs: vec![],
+            clock_offset: 0,
             last_msg: String::new(),
             last_status: String::new(),
             log,
+            dropped_oversized: 0,
+            dropped_rate_limited: 0,
+            dropped_commands: HashMap::new(),
+        }
+    }
+
+    fn record_drop(&mut self, command: &str, oversized: bool) {
+        match oversized {
+            true => self.dropped_oversized += 1,
+            false => self.dropped_rate_limited += 1,
         }
+        *self.dropped_commands.entry(command.to_string()).or_insert(0) += 1;
     }
 
     // ANCHOR: get_info
@@ -82,9 +117,18 @@ impl ChannelInfo {
         json!({
             "random_id": self.random_id,
             "remote_node_id": self.remote_node_id,
+            "protocol_version": self.protocol_version,
+            "remote_features": self.remote_features,
+            "remote_connect_addrs": self.remote_connect_addrs,
+            "clock_offset": self.clock_offset,
             "last_msg": self.last_msg,
             "last_status": self.last_status,
             "log": log,
+            "dropped": {
+                "oversized": self.dropped_oversized,
+                "rate_limited": self.dropped_rate_limited,
+                "commands": self.dropped_commands,
+            },
         })
     }
     // ANCHOR_END: get_info
@@ -101,6 +145,10 @@ pub struct Channel {
     stopped: Mutex<bool>,
     info: Mutex<ChannelInfo>,
     session: SessionWeakPtr,
+    /// Key the remote peer authenticated with, if the stream is TLS
+    remote_key: Option<PublicKey>,
+    /// Misbehaviour score of the remote peer
+    ban_score: Mutex<u32>,
 }
 
 impl Channel {
@@ -112,6 +160,7 @@ impl Channel {
         address: Url,
         session: SessionWeakPtr,
     ) -> Arc<Self> {
+        let remote_key = stream.peer_key();
         let (reader, writer) = stream.split();
         let reader = Mutex::new(reader);
         let writer = Mutex::new(writer);
@@ -131,11 +180,19 @@ impl Channel {
             stopped: Mutex::new(false),
             info: Mutex::new(ChannelInfo::new(channel_log)),
             session,
+            remote_key,
+            ban_score: Mutex::new(0),
         })
     }
 
     pub async fn get_info(&self) -> serde_json::Value {
-        self.info.lock().await.get_info().await
+        let mut info = self.info.lock().await.get_info().await;
+        info["remote_key"] = match &self.remote_key {
+            Some(key) => serde_json::Value::String(hex::encode(key.as_ref())),
+            None => serde_json::Value::Null,
+        };
+        info["ban_score"] = json!(*self.ban_score.lock().await);
+        info
     }
 
     /// Starts the channel. Runs a receive loop to start receiving messages or
@@ -273,6 +330,75 @@ impl Channel {
         self.address.clone()
     }
 
+    /// Return the key the remote peer authenticated with during the TLS
+    /// handshake. Protocols should use this to authorize peers instead of
+    /// relying on their address. `None` for channels not using TLS.
+    pub fn remote_key(&self) -> Option<PublicKey> {
+        self.remote_key
+    }
+
+    /// Return the misbehaviour score of the remote peer.
+    pub async fn ban_score(&self) -> u32 {
+        *self.ban_score.lock().await
+    }
+
+    /// Raise the misbehaviour score of the remote peer. Protocols call this
+    /// on malformed or invalid messages. Once the score crosses the
+    /// configured threshold, the peer gets banned and the channel stopped.
+    pub async fn increase_ban_score(&self, points: u32, reason: &str) {
+        let p2p = self.session().p2p();
+
+        let score = {
+            let mut ban_score = self.ban_score.lock().await;
+            *ban_score = ban_score.saturating_add(points);
+            *ban_score
+        };
+
+        warn!(
+            target: "net::channel::increase_ban_score()",
+            "Ban score of {} raised by {} to {}: {}",
+            self.address(),
+            points,
+            score,
+            reason
+        );
+
+        if score >= p2p.settings().ban_threshold {
+            p2p.ban_channel(self, reason).await;
+        }
+    }
+
+    /// Record the version information the remote sent during the handshake,
+    /// along with the protocol version negotiated with it.
+    pub async fn set_remote_version(&self, version: &message::VersionMessage, negotiated: u32) {
+        let info = &mut *self.info.lock().await;
+        info.remote_node_id = version.node_id.clone();
+        info.protocol_version = negotiated;
+        info.remote_features = version.features;
+        info.remote_connect_addrs = version.connect_addrs.clone();
+        info.clock_offset = match version.timestamp.0 {
+            0 => 0,
+            timestamp => timestamp - Timestamp::current_time().0,
+        };
+    }
+
+    /// Return the net protocol version negotiated with the remote, which is
+    /// the lowest of both versions. Protocols should use it to stay
+    /// compatible with older peers.
+    pub async fn protocol_version(&self) -> u32 {
+        self.info.lock().await.protocol_version
+    }
+
+    /// Return the feature bits the remote advertised.
+    pub async fn remote_features(&self) -> FeatureBitflag {
+        self.info.lock().await.remote_features
+    }
+
+    /// Return the addresses the remote advertised it accepts connections on.
+    pub async fn remote_connect_addrs(&self) -> Vec<Url> {
+        self.info.lock().await.remote_connect_addrs.clone()
+    }
+
     pub async fn remote_node_id(&self) -> String {
         self.info.lock().await.remote_node_id.clone()
     }
@@ -311,9 +437,15 @@ impl Channel {
 
         let reader = &mut *self.reader.lock().await;
 
+        let settings = self.session().p2p().settings();
+        let mut channel_limit =
+            TokenBucket::new(settings.channel_msg_rate, settings.channel_msg_burst);
+        let mut command_limits: HashMap<String, TokenBucket> = HashMap::new();
+
         loop {
-            let packet = match message::read_packet(reader).await {
-                Ok(packet) => packet,
+            let packet = match self.read_packet(reader).await {
+                Ok(Some(packet)) => packet,
+                Ok(None) => continue,
                 Err(err) => {
                     if Self::is_eof_error(err.clone()) {
                         info!(
@@ -349,11 +481,72 @@ impl Channel {
                 };
             }
 
+            // Drop the message if the peer is flooding us, overall or with
+            // this specific command. Only commands we have a dispatcher for
+            // get their own bucket, so peers can't grow the map at will.
+            let command_allowed =
+                match self.message_subsystem.max_bytes(&packet.command).await.is_some() {
+                    true => command_limits
+                        .entry(packet.command.clone())
+                        .or_insert_with(|| {
+                            TokenBucket::new(settings.command_msg_rate, settings.command_msg_burst)
+                        })
+                        .take(),
+                    false => true,
+                };
+            if !channel_limit.take() || !command_allowed {
+                self.drop_message(&packet.command, false).await;
+                continue
+            }
+
             // Send result to our subscribers
-            self.message_subsystem.notify(&packet.command, packet.payload).await;
+            if self.message_subsystem.notify(&packet.command, packet.payload).await.is_err() {
+                let reason = format!("Malformed {} message", packet.command);
+                self.increase_ban_score(BAN_SCORE_MALFORMED, &reason).await;
+            }
         }
     }
 
+    /// Read the next inbound packet. Packets whose payload exceeds the size
+    /// limit of their command are skipped without buffering them, in which
+    /// case `None` is returned. Payloads above [`message::MAX_PAYLOAD_SIZE`]
+    /// are not even skipped, and error out instead.
+    async fn read_packet(
+        &self,
+        reader: &mut ReadHalf<Box<dyn TransportStream>>,
+    ) -> Result<Option<message::Packet>> {
+        let header = message::read_header(reader).await?;
+        if header.payload_len > message::MAX_PAYLOAD_SIZE {
+            return Err(Error::MessageTooLarge(header.command, header.payload_len))
+        }
+
+        let max_bytes = self
+            .message_subsystem
+            .max_bytes(&header.command)
+            .await
+            .unwrap_or(message::MAX_PAYLOAD_SIZE);
+
+        if header.payload_len > max_bytes {
+            message::skip_payload(reader, header.payload_len).await?;
+            self.drop_message(&header.command, true).await;
+            return Ok(None)
+        }
+
+        let payload = message::read_payload(reader, header.payload_len).await?;
+        Ok(Some(message::Packet { command: header.command, payload }))
+    }
+
+    /// Record a dropped inbound message and raise the peer's ban score.
+    async fn drop_message(&self, command: &str, oversized: bool) {
+        self.info.lock().await.record_drop(command, oversized);
+
+        let reason = match oversized {
+            true => format!("Oversized {} message", command),
+            false => format!("Rate limited {} message", command),
+        };
+        self.increase_ban_score(BAN_SCORE_DROPPED, &reason).await;
+    }
+
     /// Handle network errors. Panic if error passes silently, otherwise
     /// broadcast the error.
     async fn handle_stop(self: Arc<Self>, result: Result<()>) {
@@ -23,7 +23,9 @@ use log::error;
 use url::Url;
 
 use super::{
-    transport::{TcpTransport, TorTransport, Transport, TransportName},
+    transport::{
+        MemoryTransport, NymTransport, TcpTransport, TorTransport, Transport, TransportName,
+    },
     Channel, ChannelPtr, SessionWeakPtr, SettingsPtr,
 };
 use crate::{Error, Result};
@@ -78,7 +80,9 @@ impl Connector {
                             .await
                     }
                     Some(u) if u == "tls" => {
-                        let stream = $transport.upgrade_dialer(stream?)?.await;
+                        let p2p = self.session.upgrade().unwrap().p2p();
+                        let tls = p2p.tls_upgrade(Some(&connect_url))?;
+                        let stream = $transport.upgrade_dialer(tls, stream?)?.await;
                         Channel::new(Box::new(stream?), connect_url, self.session.clone()).await
                     }
                     Some(u) => return Err(Error::UnsupportedTransportUpgrade(u)),
@@ -106,6 +110,25 @@ impl Connector {
 
                 connect!(stream, transport, upgrade)
             }
+            TransportName::Nym(upgrade) => {
+                let transport = NymTransport::new(NymTransport::get_client_env()?);
+                let stream = transport.clone().dial(connect_url.clone(), Some(timeout));
+                connect!(stream, transport, upgrade)
+            }
+            TransportName::Memory(upgrade) => {
+                // Make ourselves known by the node our inbound address is on
+                let local = self
+                    .settings
+                    .inbound
+                    .iter()
+                    .find(|url| url.scheme().starts_with("memory"))
+                    .and_then(|url| url.host_str())
+                    .and_then(|host| host.split('.').next())
+                    .map(String::from);
+                let transport = MemoryTransport::new(local);
+                let stream = transport.clone().dial(connect_url.clone(), Some(timeout));
+                connect!(stream, transport, upgrade)
+            }
             _ => unimplemented!(),
         }
     }
@@ -16,6 +16,21 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+/// Net protocol version we speak, advertised in the version handshake
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Most addresses we take from the version message of an inbound peer
+pub const MAX_CONNECT_ADDRS: usize = 8;
+
+/// Ban score added for a message that fails to decode
+pub const BAN_SCORE_MALFORMED: u32 = 50;
+
+/// Ban score added for a message that fails validation
+pub const BAN_SCORE_INVALID: u32 = 10;
+
+/// Ban score added for a message dropped by size or rate limits
+pub const BAN_SCORE_DROPPED: u32 = 1;
+
 /// Localnet addresses
 pub const LOCALNET: [&str; 5] = ["localhost", "0.0.0.0", "[::]", "127.0.0.1", "[::1]"];
 
@@ -17,25 +17,139 @@
  */
 
 use std::{
-    collections::{HashMap, HashSet},
+    collections::{hash_map::DefaultHasher, HashMap},
+    fmt,
+    hash::{Hash, Hasher},
     net::IpAddr,
+    path::Path,
 };
 
 use async_std::sync::{Arc, Mutex};
 use ipnet::{Ipv4Net, Ipv6Net};
 use iprange::IpRange;
 use log::{debug, error, warn};
-use url::Url;
+use rand::{seq::SliceRandom, Rng};
+use url::{Host, Url};
 
-use super::constants::{IP4_PRIV_RANGES, IP6_PRIV_RANGES, LOCALNET};
-use crate::util::encoding::base32;
+use super::{
+    constants::{IP4_PRIV_RANGES, IP6_PRIV_RANGES, LOCALNET},
+    transport::NymAddress,
+};
+use crate::{
+    util::{
+        encoding::base32,
+        file::{load_file, save_file},
+        time::Timestamp,
+    },
+    Error, Result,
+};
 
 /// Pointer to hosts class.
 pub type HostsPtr = Arc<Hosts>;
 
+/// Number of buckets greylisted hosts are spread over
+const GREY_BUCKETS: u64 = 256;
+/// Number of grey buckets a single source group can put hosts into
+const GREY_BUCKETS_PER_SOURCE: u64 = 16;
+/// Maximum number of hosts in a grey bucket
+const GREY_BUCKET_SIZE: usize = 32;
+/// Number of buckets whitelisted hosts are spread over
+const WHITE_BUCKETS: u64 = 64;
+/// Maximum number of hosts in a white bucket
+const WHITE_BUCKET_SIZE: usize = 32;
+/// Failed attempts after which a host we never connected to is dropped
+const MAX_GREY_FAILURES: u32 = 3;
+/// Failed attempts after which a whitelisted host is demoted back to grey
+const MAX_WHITE_FAILURES: u32 = 10;
+/// Hosts attempted this recently are unlikely to be picked again
+const RECENT_ATTEMPT_SECONDS: i64 = 600;
+
+/// Trust level of a known host.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HostTier {
+    /// Learned from a peer or seed, never connected to
+    Grey,
+    /// We had at least one successful connection to it
+    White,
+    /// An outbound peer we were connected to when the list was last saved
+    Anchor,
+}
+
+impl fmt::Display for HostTier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                Self::Grey => "grey",
+                Self::White => "white",
+                Self::Anchor => "anchor",
+            }
+        )
+    }
+}
+
+impl TryFrom<&str> for HostTier {
+    type Error = Error;
+
+    fn try_from(tier: &str) -> Result<Self> {
+        match tier {
+            "grey" => Ok(Self::Grey),
+            "white" => Ok(Self::White),
+            "anchor" => Ok(Self::Anchor),
+            _ => Err(Error::ParseFailed("Invalid host tier")),
+        }
+    }
+}
+
+/// Information we keep about a known host.
+#[derive(Clone, Debug)]
+pub struct HostInfo {
+    /// Trust level of the host
+    pub tier: HostTier,
+    /// Peer that told us about this host, `None` if configured locally
+    pub source: Option<Url>,
+    /// Last time the host was advertised to us
+    pub last_seen: Timestamp,
+    /// Last time we tried connecting to the host
+    pub last_attempt: Timestamp,
+    /// Last time we successfully connected to the host
+    pub last_success: Timestamp,
+    /// Number of successful connections
+    pub successes: u32,
+    /// Number of failed attempts since the last success
+    pub failures: u32,
+    /// Bucket of the host's tier it's placed in
+    bucket: u64,
+}
+
+impl HostInfo {
+    /// Relative chance of picking this host for an outbound connection.
+    /// Backs off exponentially with failures and avoids recent attempts.
+    fn chance(&self, now: Timestamp) -> f64 {
+        let mut chance = 1.0;
+
+        if now.0 - self.last_attempt.0 < RECENT_ATTEMPT_SECONDS {
+            chance *= 0.01;
+        }
+
+        chance * 0.66_f64.powi(self.failures.min(8) as i32)
+    }
+}
+
 /// Manages a store of network addresses.
+///
+/// Hosts are kept in three tiers, similar to Bitcoin's addrman. Addresses
+/// we learn about start out greylisted and are spread over buckets chosen
+/// by a keyed hash of the network group of the host and of the peer that
+/// sent it, so a single peer can only fill a small part of the greylist.
+/// A successful outbound connection whitelists the host, and outbound
+/// peers we're connected to when the list is saved become anchors, which
+/// are tried first on the next start.
 pub struct Hosts {
-    addrs: Mutex<HashSet<Url>>,
+    addrs: Mutex<HashMap<Url, HostInfo>>,
+    /// Secret key of the bucket hash, so peers can't predict placement
+    secret: u64,
     localnet: bool,
     ipv4_range: IpRange<Ipv4Net>,
     ipv6_range: IpRange<Ipv6Net>,
@@ -54,27 +168,38 @@ impl Hosts {
         ipv4_range.simplify();
         ipv6_range.simplify();
 
-        Arc::new(Self { addrs: Mutex::new(HashSet::new()), localnet, ipv4_range, ipv6_range })
+        Arc::new(Self {
+            addrs: Mutex::new(HashMap::new()),
+            secret: rand::thread_rng().gen(),
+            localnet,
+            ipv4_range,
+            ipv6_range,
+        })
     }
 
-    /// Add a new host to the host list, after filtering.
+    /// Add new locally configured hosts to the host list, after filtering.
     pub async fn store(&self, input_addrs: Vec<Url>) {
         debug!(target: "net::hosts::store()", "hosts::store() [Start]");
-        let addrs = if !self.localnet {
-            let filtered = filter_localnet(input_addrs);
-            let filtered = filter_invalid(&self.ipv4_range, &self.ipv6_range, filtered);
-            filtered.into_iter().map(|(k, _)| k).collect()
-        } else {
-            debug!(target: "net::hosts::store()", "hosts::store() [Localnet mode, skipping filterring.]");
-            input_addrs
-        };
+        let addrs = self.filter(input_addrs);
         let mut addrs_map = self.addrs.lock().await;
         for addr in addrs {
-            addrs_map.insert(addr);
+            self.insert_grey(&mut addrs_map, addr, None);
         }
         debug!(target: "net::hosts::store()", "hosts::store() [End]");
     }
 
+    /// Add new hosts advertised by the peer at `source` to the host list,
+    /// after filtering.
+    pub async fn store_from(&self, source: &Url, input_addrs: Vec<Url>) {
+        debug!(target: "net::hosts::store_from()", "hosts::store_from() [Start]");
+        let addrs = self.filter(input_addrs);
+        let mut addrs_map = self.addrs.lock().await;
+        for addr in addrs {
+            self.insert_grey(&mut addrs_map, addr, Some(source.clone()));
+        }
+        debug!(target: "net::hosts::store_from()", "hosts::store_from() [End]");
+    }
+
     /// Add a new hosts external adders to the host list, after filtering and verifying
     /// the address url resolves to the provided connection address.
     pub async fn store_ext(&self, connection_addr: Url, input_addrs: Vec<Url>) {
@@ -82,32 +207,359 @@ impl Hosts {
         let addrs = if !self.localnet {
             let filtered = filter_localnet(input_addrs);
             let filtered = filter_invalid(&self.ipv4_range, &self.ipv6_range, filtered);
-            filter_non_resolving(connection_addr, filtered)
+            filter_non_resolving(connection_addr.clone(), filtered)
         } else {
             debug!(target: "net::hosts::store_ext()", "hosts::store_ext() [Localnet mode, skipping filterring.]");
             input_addrs
         };
         let mut addrs_map = self.addrs.lock().await;
         for addr in addrs {
-            addrs_map.insert(addr);
+            self.insert_grey(&mut addrs_map, addr, Some(connection_addr.clone()));
         }
         debug!(target: "net::hosts::store_ext()", "hosts::store_ext() [End]");
     }
 
+    /// Filter localnet and invalid hosts, unless in localnet mode.
+    fn filter(&self, input_addrs: Vec<Url>) -> Vec<Url> {
+        if self.localnet {
+            debug!(target: "net::hosts::filter()", "hosts::filter() [Localnet mode, skipping filterring.]");
+            return input_addrs
+        }
+
+        let filtered = filter_localnet(input_addrs);
+        let filtered = filter_invalid(&self.ipv4_range, &self.ipv6_range, filtered);
+        filtered.into_iter().map(|(k, _)| k).collect()
+    }
+
+    /// Greylist a host, or refresh it if already known. If its bucket is
+    /// full, the worst host in the bucket is evicted to make room.
+    fn insert_grey(&self, addrs: &mut HashMap<Url, HostInfo>, addr: Url, source: Option<Url>) {
+        let now = Timestamp::current_time();
+
+        if let Some(info) = addrs.get_mut(&addr) {
+            info.last_seen = now;
+            return
+        }
+
+        let bucket = self.grey_bucket(&addr, source.as_ref());
+        self.make_room(addrs, HostTier::Grey, bucket, GREY_BUCKET_SIZE);
+
+        addrs.insert(
+            addr,
+            HostInfo {
+                tier: HostTier::Grey,
+                source,
+                last_seen: now,
+                last_attempt: Timestamp(0),
+                last_success: Timestamp(0),
+                successes: 0,
+                failures: 0,
+                bucket,
+            },
+        );
+    }
+
+    /// Evict the worst host of a full bucket. Whitelisted hosts get
+    /// demoted to the greylist instead of being forgotten.
+    fn make_room(
+        &self,
+        addrs: &mut HashMap<Url, HostInfo>,
+        tier: HostTier,
+        bucket: u64,
+        size: usize,
+    ) {
+        let in_bucket: Vec<(&Url, &HostInfo)> =
+            addrs.iter().filter(|(_, i)| i.tier == tier && i.bucket == bucket).collect();
+
+        if in_bucket.len() < size {
+            return
+        }
+
+        // Most failures first, then least recently seen
+        let worst = in_bucket
+            .into_iter()
+            .max_by(|(_, a), (_, b)| {
+                a.failures.cmp(&b.failures).then(b.last_seen.0.cmp(&a.last_seen.0))
+            })
+            .map(|(url, _)| url.clone())
+            .unwrap();
+
+        debug!(target: "net::hosts::make_room()", "hosts::make_room() [Evicting {} host {}]", tier, worst);
+        let info = addrs.remove(&worst).unwrap();
+        if tier == HostTier::White {
+            self.insert_grey(addrs, worst, info.source);
+        }
+    }
+
     /// Return the list of hosts.
     pub async fn load_all(&self) -> Vec<Url> {
-        self.addrs.lock().await.iter().cloned().collect()
+        self.addrs.lock().await.keys().cloned().collect()
+    }
+
+    /// Return the hosts in the order outbound connections should try them.
+    /// Anchors come first, followed by white and grey hosts interleaved,
+    /// each randomly ordered with a bias against hosts that failed or were
+    /// attempted recently.
+    pub async fn load_candidates(&self) -> Vec<Url> {
+        let now = Timestamp::current_time();
+        let addrs = self.addrs.lock().await;
+        let mut rng = rand::thread_rng();
+
+        let mut anchors = vec![];
+        let mut white = vec![];
+        let mut grey = vec![];
+
+        for (url, info) in addrs.iter() {
+            let key = rng.gen::<f64>() * info.chance(now);
+            match info.tier {
+                HostTier::Anchor => anchors.push((key, url.clone())),
+                HostTier::White => white.push((key, url.clone())),
+                HostTier::Grey => grey.push((key, url.clone())),
+            }
+        }
+
+        anchors.shuffle(&mut rng);
+        white.sort_by(|a, b| b.0.total_cmp(&a.0));
+        grey.sort_by(|a, b| b.0.total_cmp(&a.0));
+
+        let mut candidates: Vec<Url> = anchors.into_iter().map(|(_, url)| url).collect();
+        let mut white = white.into_iter().map(|(_, url)| url);
+        let mut grey = grey.into_iter().map(|(_, url)| url);
+        loop {
+            match (white.next(), grey.next()) {
+                (None, None) => break,
+                (w, g) => candidates.extend(w.into_iter().chain(g)),
+            }
+        }
+
+        candidates
+    }
+
+    /// Return the information we keep about a host.
+    pub async fn get(&self, url: &Url) -> Option<HostInfo> {
+        self.addrs.lock().await.get(url).cloned()
+    }
+
+    /// Record a successful outbound connection to a host, whitelisting it.
+    pub async fn mark_success(&self, url: &Url) {
+        let now = Timestamp::current_time();
+        let mut addrs = self.addrs.lock().await;
+
+        let Some(info) = addrs.get_mut(url) else { return };
+        info.last_attempt = now;
+        info.last_success = now;
+        info.successes += 1;
+        info.failures = 0;
+
+        if info.tier != HostTier::Grey {
+            return
+        }
+
+        let bucket = self.white_bucket(url);
+        let mut info = addrs.remove(url).unwrap();
+        self.make_room(&mut addrs, HostTier::White, bucket, WHITE_BUCKET_SIZE);
+        debug!(target: "net::hosts::mark_success()", "hosts::mark_success() [Whitelisting {}]", url);
+        info.tier = HostTier::White;
+        info.bucket = bucket;
+        addrs.insert(url.clone(), info);
+    }
+
+    /// Record a failed connection attempt to a host. Hosts we never
+    /// connected to are dropped after a few failures, whitelisted ones
+    /// get demoted, and anchors lose their anchor status.
+    pub async fn mark_failure(&self, url: &Url) {
+        let mut addrs = self.addrs.lock().await;
+
+        let Some(info) = addrs.get_mut(url) else { return };
+        info.last_attempt = Timestamp::current_time();
+        info.failures += 1;
+
+        match info.tier {
+            HostTier::Grey if info.successes == 0 && info.failures >= MAX_GREY_FAILURES => {
+                debug!(target: "net::hosts::mark_failure()", "hosts::mark_failure() [Dropping {}]", url);
+                addrs.remove(url);
+            }
+            HostTier::White if info.failures >= MAX_WHITE_FAILURES => {
+                debug!(target: "net::hosts::mark_failure()", "hosts::mark_failure() [Demoting {}]", url);
+                let info = addrs.remove(url).unwrap();
+                self.insert_grey(&mut addrs, url.clone(), info.source);
+            }
+            HostTier::Anchor => info.tier = HostTier::White,
+            _ => {}
+        }
+    }
+
+    /// Make the given hosts the anchors, demoting previous anchors that
+    /// aren't in the list to the whitelist. Only whitelisted hosts can
+    /// become anchors.
+    pub async fn set_anchors(&self, urls: &[Url]) {
+        for (url, info) in self.addrs.lock().await.iter_mut() {
+            match info.tier {
+                HostTier::Grey => {}
+                _ if urls.contains(url) => info.tier = HostTier::Anchor,
+                _ => info.tier = HostTier::White,
+            }
+        }
     }
 
     /// Remove an Url from the list
     pub async fn remove(&self, url: &Url) -> bool {
-        self.addrs.lock().await.remove(url)
+        self.addrs.lock().await.remove(url).is_some()
     }
 
     /// Check if the host list is empty.
     pub async fn is_empty(&self) -> bool {
         self.addrs.lock().await.is_empty()
     }
+
+    /// Load a host list previously written by [`Hosts::save_hostlist`].
+    /// Greylisted hosts are placed in fresh buckets, since the bucket
+    /// secret changes on every start.
+    pub async fn load_hostlist(&self, path: &Path) -> Result<()> {
+        if !path.exists() {
+            return Ok(())
+        }
+
+        let contents = load_file(path)?;
+        let mut addrs = self.addrs.lock().await;
+
+        for line in contents.lines() {
+            if line.is_empty() || line.starts_with('#') {
+                continue
+            }
+
+            let (url, mut info) = match parse_hostlist_line(line) {
+                Ok(v) => v,
+                Err(e) => {
+                    warn!(target: "net::hosts::load_hostlist()", "hosts::load_hostlist() [Skipping malformed line: {} ({})]", line, e);
+                    continue
+                }
+            };
+
+            info.bucket = match info.tier {
+                HostTier::Grey => {
+                    let bucket = self.grey_bucket(&url, info.source.as_ref());
+                    self.make_room(&mut addrs, HostTier::Grey, bucket, GREY_BUCKET_SIZE);
+                    bucket
+                }
+                _ => {
+                    let bucket = self.white_bucket(&url);
+                    self.make_room(&mut addrs, HostTier::White, bucket, WHITE_BUCKET_SIZE);
+                    bucket
+                }
+            };
+
+            addrs.insert(url, info);
+        }
+
+        debug!(target: "net::hosts::load_hostlist()", "hosts::load_hostlist() [Loaded {} hosts from {:?}]", addrs.len(), path);
+        Ok(())
+    }
+
+    /// Save the host list to the given file, one tab-separated host per line.
+    pub async fn save_hostlist(&self, path: &Path) -> Result<()> {
+        let mut contents = String::from(
+            "# tier\turl\tsource\tlast_seen\tlast_attempt\tlast_success\tsuccesses\tfailures\n",
+        );
+
+        for (url, info) in self.addrs.lock().await.iter() {
+            let source = match &info.source {
+                Some(s) => s.to_string(),
+                None => "-".to_string(),
+            };
+            contents.push_str(&format!(
+                "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\n",
+                info.tier,
+                url,
+                source,
+                info.last_seen.0,
+                info.last_attempt.0,
+                info.last_success.0,
+                info.successes,
+                info.failures
+            ));
+        }
+
+        save_file(path, &contents)
+    }
+
+    /// Grey bucket of a host, derived from the network groups of the host
+    /// and of the peer that sent it.
+    fn grey_bucket(&self, addr: &Url, source: Option<&Url>) -> u64 {
+        let source_group = match source {
+            Some(s) => network_group(s),
+            None => "local".to_string(),
+        };
+
+        let slot = keyed_hash(self.secret, &(&source_group, network_group(addr))) %
+            GREY_BUCKETS_PER_SOURCE;
+        keyed_hash(self.secret, &(&source_group, slot)) % GREY_BUCKETS
+    }
+
+    /// White bucket of a host, derived from its network group.
+    fn white_bucket(&self, addr: &Url) -> u64 {
+        keyed_hash(self.secret, &network_group(addr)) % WHITE_BUCKETS
+    }
+}
+
+/// Parse a line of a saved host list.
+fn parse_hostlist_line(line: &str) -> Result<(Url, HostInfo)> {
+    let data: Vec<&str> = line.split('\t').collect();
+    if data.len() != 8 {
+        return Err(Error::ParseFailed("Wrong number of host list fields"))
+    }
+
+    let source = match data[2] {
+        "-" => None,
+        s => Some(Url::parse(s)?),
+    };
+
+    Ok((
+        Url::parse(data[1])?,
+        HostInfo {
+            tier: HostTier::try_from(data[0])?,
+            source,
+            last_seen: Timestamp(data[3].parse()?),
+            last_attempt: Timestamp(data[4].parse()?),
+            last_success: Timestamp(data[5].parse()?),
+            successes: data[6].parse()?,
+            failures: data[7].parse()?,
+            bucket: 0,
+        },
+    ))
+}
+
+/// Network group of a host, used to limit how much of the host list a
+/// single network can occupy: the /16 of IPv4 addresses, the /32 of IPv6
+/// addresses, and the last two labels of domains. Onion and Nym addresses
+/// are their own group.
+fn network_group(url: &Url) -> String {
+    match url.host() {
+        Some(Host::Ipv4(ip)) => {
+            let octets = ip.octets();
+            format!("{}.{}", octets[0], octets[1])
+        }
+        Some(Host::Ipv6(ip)) => {
+            let segments = ip.segments();
+            format!("{:x}:{:x}", segments[0], segments[1])
+        }
+        Some(Host::Domain(domain)) => {
+            if domain.ends_with(".onion") || url.scheme().starts_with("nym") {
+                return domain.to_string()
+            }
+            let labels: Vec<&str> = domain.rsplitn(3, '.').collect();
+            labels.into_iter().take(2).rev().collect::<Vec<&str>>().join(".")
+        }
+        None => String::new(),
+    }
+}
+
+/// Hash a value keyed with the given secret.
+fn keyed_hash<T: Hash>(secret: u64, value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    secret.hash(&mut hasher);
+    value.hash(&mut hasher);
+    hasher.finish()
 }
 
 /// Auxiliary function to filter localnet hosts.
@@ -149,6 +601,19 @@ fn filter_invalid(
             }
         };
 
+        // Validate Nym addresses, which don't resolve to anything
+        if addr.scheme().starts_with("nym") {
+            match is_valid_nym(domain) {
+                true => {
+                    filtered.insert(addr.clone(), vec![]);
+                }
+                false => {
+                    warn!(target: "net::hosts::filter_invalid()", "hosts::filter_invalid() [Got invalid nym address: {}]", addr)
+                }
+            }
+            continue
+        }
+
         // Validate onion domain
         if domain.ends_with("onion") {
             match is_valid_onion(domain) {
@@ -212,7 +677,7 @@ fn filter_invalid(
 
 /// Filters `input_addrs` keys to whatever has at least one `IpAddr` that is
 /// the same as `connection_addr`'s IP address.
-/// Skips .onion domains.
+/// Skips .onion domains and Nym addresses.
 fn filter_non_resolving(connection_addr: Url, input_addrs: HashMap<Url, Vec<IpAddr>>) -> Vec<Url> {
     debug!(target: "net::hosts::filter_non_resolving()", "hosts::filter_non_resolving() [Input addresses: {:?}]", input_addrs);
     debug!(target: "net::hosts::filter_non_resolving()", "hosts::filter_non_resolving() [Connection address: {}]", connection_addr);
@@ -249,6 +714,12 @@ fn filter_non_resolving(connection_addr: Url, input_addrs: HashMap<Url, Vec<IpAd
             continue
         }
 
+        // Same goes for Nym addresses.
+        if addr.scheme().starts_with("nym") {
+            filtered.push(addr.clone());
+            continue
+        }
+
         // Checking IP validity. If at least one IP matches, we consider it fine.
         let mut valid = false;
         for ip in resolves {
@@ -296,6 +767,12 @@ fn is_valid_onion(onion: &str) -> bool {
     base32::decode(&onion.to_uppercase()).is_some()
 }
 
+/// Validate a given Nym address, in the `<identity>.<encryption>.<gateway>`
+/// form used in Url hosts. Checks that each part decodes to a 32 byte key.
+fn is_valid_nym(addr: &str) -> bool {
+    NymAddress::from_host(addr).is_ok()
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
@@ -309,7 +786,12 @@ mod tests {
 
     use crate::net::{
         constants::{IP4_PRIV_RANGES, IP6_PRIV_RANGES},
-        hosts::{filter_invalid, filter_localnet, filter_non_resolving, is_valid_onion},
+        hosts::{
+            filter_invalid, filter_localnet, filter_non_resolving, is_valid_nym, is_valid_onion,
+            network_group, HostTier, Hosts, GREY_BUCKETS_PER_SOURCE, GREY_BUCKET_SIZE,
+            MAX_GREY_FAILURES,
+        },
+        transport::NymAddress,
     };
 
     #[test]
@@ -482,4 +964,113 @@ mod tests {
         // Invalid onion
         assert!(!is_valid_onion("facebook.com"));
     }
+
+    #[test]
+    fn test_is_valid_nym() {
+        let addr = NymAddress([7u8; 96]).to_host();
+        // Valid address
+        assert!(is_valid_nym(&addr));
+        // Missing gateway
+        let (id_enc, _) = addr.rsplit_once('.').unwrap();
+        assert!(!is_valid_nym(id_enc));
+        // Invalid encoding
+        assert!(!is_valid_nym("facebook.com.onion"));
+
+        // Nym addresses go through filtering without resolution
+        let url = Url::parse(&format!("nym://{}", addr)).unwrap();
+        let filtered = filter_invalid(&IpRange::new(), &IpRange::new(), vec![url.clone()]);
+        assert!(filtered.contains_key(&url));
+    }
+
+    #[test]
+    fn test_network_group() {
+        let group = |s: &str| network_group(&Url::parse(s).unwrap());
+        assert_eq!(group("tcp://185.60.216.35:13333"), "185.60");
+        assert_eq!(group("tcp://[2a03:2880:f12d:83:face:b00c:0:25de]:13333"), "2a03:2880");
+        assert_eq!(group("tls://a.b.facebook.com:13333"), "facebook.com");
+        assert_eq!(group("tls://facebook.com:13333"), "facebook.com");
+        assert_eq!(
+            group("tor://facebookwkhpilnemxj7asaniu7vnjjbiltxjqhye3mhbshg7kx5tfyd.onion:13333"),
+            "facebookwkhpilnemxj7asaniu7vnjjbiltxjqhye3mhbshg7kx5tfyd.onion"
+        );
+    }
+
+    #[async_std::test]
+    async fn test_hosts_source_buckets() {
+        let hosts = Hosts::new(true);
+        let source = Url::parse("tcp://185.60.216.35:13333").unwrap();
+
+        // A single source floods us with hosts from many networks
+        let addrs: Vec<Url> = (0..4096)
+            .map(|i| Url::parse(&format!("tcp://{}.{}.0.1:13333", i / 256, i % 256)).unwrap())
+            .collect();
+        hosts.store_from(&source, addrs).await;
+
+        // It can only fill the buckets reserved for its group
+        let stored = hosts.load_all().await.len();
+        assert!(stored <= (GREY_BUCKETS_PER_SOURCE as usize) * GREY_BUCKET_SIZE);
+
+        // Other sources still have room
+        let other = Url::parse("tcp://1.2.3.4:13333").unwrap();
+        let addr = Url::parse("tcp://4.3.2.1:13333").unwrap();
+        hosts.store_from(&other, vec![addr.clone()]).await;
+        assert_eq!(hosts.get(&addr).await.unwrap().source, Some(other));
+    }
+
+    #[async_std::test]
+    async fn test_hosts_tiers() {
+        let hosts = Hosts::new(true);
+        let grey = Url::parse("tcp://127.0.0.1:13333").unwrap();
+        let white = Url::parse("tcp://127.0.0.2:13333").unwrap();
+        let anchor = Url::parse("tcp://127.0.0.3:13333").unwrap();
+        hosts.store(vec![grey.clone(), white.clone(), anchor.clone()]).await;
+
+        hosts.mark_success(&white).await;
+        hosts.mark_success(&anchor).await;
+        hosts.set_anchors(&[anchor.clone(), grey.clone()]).await;
+        assert_eq!(hosts.get(&grey).await.unwrap().tier, HostTier::Grey);
+        assert_eq!(hosts.get(&white).await.unwrap().tier, HostTier::White);
+        assert_eq!(hosts.get(&anchor).await.unwrap().tier, HostTier::Anchor);
+
+        // Anchors are tried first
+        assert_eq!(hosts.load_candidates().await[0], anchor);
+
+        // Failing anchors lose their status, failing whitelisted hosts are kept
+        hosts.mark_failure(&anchor).await;
+        assert_eq!(hosts.get(&anchor).await.unwrap().tier, HostTier::White);
+        for _ in 0..MAX_GREY_FAILURES {
+            hosts.mark_failure(&white).await;
+        }
+        assert_eq!(hosts.get(&white).await.unwrap().failures, MAX_GREY_FAILURES);
+
+        // Greylisted hosts that never worked are dropped
+        for _ in 0..MAX_GREY_FAILURES {
+            hosts.mark_failure(&grey).await;
+        }
+        assert!(hosts.get(&grey).await.is_none());
+    }
+
+    #[async_std::test]
+    async fn test_hostlist_persistence() {
+        let path = std::env::temp_dir().join("darkfi_test_hostlist.tsv");
+        let source = Url::parse("tcp://127.0.0.1:13333").unwrap();
+        let grey = Url::parse("tcp://127.0.0.2:13333").unwrap();
+        let white = Url::parse("tcp://127.0.0.3:13333").unwrap();
+
+        let hosts = Hosts::new(true);
+        hosts.store_from(&source, vec![grey.clone(), white.clone()]).await;
+        hosts.mark_success(&white).await;
+        hosts.save_hostlist(&path).await.unwrap();
+
+        let loaded = Hosts::new(true);
+        loaded.load_hostlist(&path).await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let info = loaded.get(&grey).await.unwrap();
+        assert_eq!(info.tier, HostTier::Grey);
+        assert_eq!(info.source, Some(source));
+        let info = loaded.get(&white).await.unwrap();
+        assert_eq!(info.tier, HostTier::White);
+        assert_eq!(info.successes, 1);
+    }
 }
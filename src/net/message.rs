@@ -16,18 +16,34 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::io;
+
 use darkfi_serial::{Decodable, Encodable, SerialDecodable, SerialEncodable, VarInt};
 use futures::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use log::debug;
 use url::Url;
 
-use crate::{Error, Result};
+use super::protocol::FeatureBitflag;
+use crate::{util::time::Timestamp, Error, Result};
 
 const MAGIC_BYTES: [u8; 4] = [0xd9, 0xef, 0xb6, 0x7d];
 
+/// Maximum length of a packet command.
+pub const MAX_COMMAND_LENGTH: usize = 64;
+
+/// Maximum payload size of a packet. Also used as the limit of messages
+/// that don't declare their own, and of commands we have no dispatcher for.
+pub const MAX_PAYLOAD_SIZE: usize = 8 * 1024 * 1024;
+
 /// Generic message template.
 pub trait Message: 'static + Encodable + Decodable + Send + Sync {
     fn name() -> &'static str;
+
+    /// Maximum encoded size of the message. Peers announcing a bigger
+    /// payload for this command get their packet dropped unread.
+    fn max_bytes() -> usize {
+        MAX_PAYLOAD_SIZE
+    }
 }
 
 /// Outbound keep-alive message.
@@ -60,9 +76,48 @@ pub struct ExtAddrsMessage {
 }
 
 /// Requests version information of outbound connection.
-#[derive(SerialEncodable, SerialDecodable)]
+///
+/// Fields following `node_id` were added with protocol version 1. Nodes
+/// predating it only send `node_id` and ignore trailing data, so new fields
+/// must always be appended, and default when missing on decoding.
+#[derive(Clone, Debug, SerialEncodable)]
 pub struct VersionMessage {
     pub node_id: String,
+    /// Highest net protocol version the node speaks
+    pub version: u32,
+    /// Feature bits of the protocols the node supports
+    pub features: FeatureBitflag,
+    /// Time of the node when sending the message
+    pub timestamp: Timestamp,
+    /// Addresses the node accepts connections on
+    pub connect_addrs: Vec<Url>,
+}
+
+impl VersionMessage {
+    /// Version message of a node predating the structured handshake.
+    fn legacy(node_id: String) -> Self {
+        Self { node_id, version: 0, features: 0, timestamp: Timestamp(0), connect_addrs: vec![] }
+    }
+}
+
+impl Decodable for VersionMessage {
+    fn decode<D: io::Read>(mut d: D) -> core::result::Result<Self, io::Error> {
+        let node_id = String::decode(&mut d)?;
+
+        let version = match u32::decode(&mut d) {
+            Ok(version) => version,
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(Self::legacy(node_id)),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            node_id,
+            version,
+            features: Decodable::decode(&mut d)?,
+            timestamp: Decodable::decode(&mut d)?,
+            connect_addrs: Decodable::decode(&mut d)?,
+        })
+    }
 }
 
 /// Sends version information to inbound connection. Response to VersionMessage.
@@ -76,42 +131,70 @@ impl Message for PingMessage {
     fn name() -> &'static str {
         "ping"
     }
+
+    fn max_bytes() -> usize {
+        16
+    }
 }
 
 impl Message for PongMessage {
     fn name() -> &'static str {
         "pong"
     }
+
+    fn max_bytes() -> usize {
+        16
+    }
 }
 
 impl Message for GetAddrsMessage {
     fn name() -> &'static str {
         "getaddr"
     }
+
+    fn max_bytes() -> usize {
+        16
+    }
 }
 
 impl Message for AddrsMessage {
     fn name() -> &'static str {
         "addr"
     }
+
+    fn max_bytes() -> usize {
+        1024 * 1024
+    }
 }
 
 impl Message for ExtAddrsMessage {
     fn name() -> &'static str {
         "extaddr"
     }
+
+    fn max_bytes() -> usize {
+        64 * 1024
+    }
 }
 
 impl Message for VersionMessage {
     fn name() -> &'static str {
         "version"
     }
+
+    fn max_bytes() -> usize {
+        16 * 1024
+    }
 }
 
 impl Message for VerackMessage {
     fn name() -> &'static str {
         "verack"
     }
+
+    fn max_bytes() -> usize {
+        1024
+    }
 }
 
 /// Packets are the base type read from the network. Converted to messages and
@@ -121,8 +204,26 @@ pub struct Packet {
     pub payload: Vec<u8>,
 }
 
+/// Header of an inbound packet, read before its payload so the payload
+/// size can be checked against the limit of the announced command.
+pub struct PacketHeader {
+    pub command: String,
+    pub payload_len: usize,
+}
+
 /// Reads and decodes an inbound payload.
 pub async fn read_packet<R: AsyncRead + Unpin + Sized>(stream: &mut R) -> Result<Packet> {
+    let header = read_header(stream).await?;
+    if header.payload_len > MAX_PAYLOAD_SIZE {
+        return Err(Error::MessageTooLarge(header.command, header.payload_len))
+    }
+
+    let payload = read_payload(stream, header.payload_len).await?;
+    Ok(Packet { command: header.command, payload })
+}
+
+/// Reads and decodes the header of an inbound packet.
+pub async fn read_header<R: AsyncRead + Unpin + Sized>(stream: &mut R) -> Result<PacketHeader> {
     // Packets have a 4 byte header of magic digits
     // This is used for network debugging
     let mut magic = [0u8; 4];
@@ -137,6 +238,9 @@ pub async fn read_packet<R: AsyncRead + Unpin + Sized>(stream: &mut R) -> Result
 
     // The type of the message
     let command_len = VarInt::decode_async(stream).await?.0 as usize;
+    if command_len > MAX_COMMAND_LENGTH {
+        return Err(Error::MalformedPacket)
+    }
     let mut cmd = vec![0u8; command_len];
     if command_len > 0 {
         stream.read_exact(&mut cmd).await?;
@@ -146,6 +250,14 @@ pub async fn read_packet<R: AsyncRead + Unpin + Sized>(stream: &mut R) -> Result
 
     let payload_len = VarInt::decode_async(stream).await?.0 as usize;
 
+    Ok(PacketHeader { command: cmd, payload_len })
+}
+
+/// Reads the payload of an inbound packet, following its header.
+pub async fn read_payload<R: AsyncRead + Unpin + Sized>(
+    stream: &mut R,
+    payload_len: usize,
+) -> Result<Vec<u8>> {
     // The message-dependent data (see message types)
     let mut payload = vec![0u8; payload_len];
     if payload_len > 0 {
@@ -153,7 +265,25 @@ pub async fn read_packet<R: AsyncRead + Unpin + Sized>(stream: &mut R) -> Result
     }
     debug!(target: "net::message", "read payload {} bytes", payload_len);
 
-    Ok(Packet { command: cmd, payload })
+    Ok(payload)
+}
+
+/// Reads and discards the payload of an inbound packet without buffering
+/// it, keeping the stream in sync after dropping a packet.
+pub async fn skip_payload<R: AsyncRead + Unpin + Sized>(
+    stream: &mut R,
+    payload_len: usize,
+) -> Result<()> {
+    let mut buf = [0u8; 4096];
+    let mut remaining = payload_len;
+    while remaining > 0 {
+        let len = remaining.min(buf.len());
+        stream.read_exact(&mut buf[..len]).await?;
+        remaining -= len;
+    }
+    debug!(target: "net::message", "skipped payload {} bytes", payload_len);
+
+    Ok(())
 }
 
 /// Sends an outbound packet by writing data to TCP stream.
@@ -180,3 +310,82 @@ pub async fn send_packet<W: AsyncWrite + Unpin + Sized>(
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::io::Cursor;
+
+    #[async_std::test]
+    async fn test_packet_size_limits() {
+        let ping = PingMessage { nonce: 42 };
+        let mut payload = vec![];
+        ping.encode(&mut payload).unwrap();
+        assert!(payload.len() <= PingMessage::max_bytes());
+
+        let mut buf = vec![];
+        send_packet(&mut buf, Packet { command: "ping".to_string(), payload: payload.clone() })
+            .await
+            .unwrap();
+        send_packet(&mut buf, Packet { command: "pong".to_string(), payload: vec![0u8; 100] })
+            .await
+            .unwrap();
+
+        // Skipping a payload keeps the stream in sync
+        let mut stream = Cursor::new(buf);
+        let header = read_header(&mut stream).await.unwrap();
+        assert_eq!(header.command, "ping");
+        skip_payload(&mut stream, header.payload_len).await.unwrap();
+        let packet = read_packet(&mut stream).await.unwrap();
+        assert_eq!(packet.command, "pong");
+        assert_eq!(packet.payload.len(), 100);
+
+        // Announcing a huge payload errors out before allocating it
+        let mut buf = vec![];
+        buf.extend_from_slice(&MAGIC_BYTES);
+        VarInt(4).encode(&mut buf).unwrap();
+        buf.extend_from_slice(b"ping");
+        VarInt(u64::MAX).encode(&mut buf).unwrap();
+        let mut stream = Cursor::new(buf);
+        assert!(matches!(read_packet(&mut stream).await, Err(Error::MessageTooLarge(_, _))));
+
+        // So does an overlong command
+        let mut buf = vec![];
+        buf.extend_from_slice(&MAGIC_BYTES);
+        VarInt(MAX_COMMAND_LENGTH as u64 + 1).encode(&mut buf).unwrap();
+        let mut stream = Cursor::new(buf);
+        assert!(matches!(read_packet(&mut stream).await, Err(Error::MalformedPacket)));
+    }
+
+    #[test]
+    fn test_version_message_compat() {
+        let version = VersionMessage {
+            node_id: "alice".to_string(),
+            version: 1,
+            features: 0b101,
+            timestamp: Timestamp(1680000000),
+            connect_addrs: vec![Url::parse("tcp+tls://alice.dark.fi:26661").unwrap()],
+        };
+        let mut payload = vec![];
+        version.encode(&mut payload).unwrap();
+        let decoded = VersionMessage::decode(&payload[..]).unwrap();
+        assert_eq!(decoded.version, 1);
+        assert_eq!(decoded.features, 0b101);
+        assert_eq!(decoded.timestamp, Timestamp(1680000000));
+        assert_eq!(decoded.connect_addrs, version.connect_addrs);
+
+        // Legacy nodes only send their node id
+        let mut payload = vec![];
+        "bob".to_string().encode(&mut payload).unwrap();
+        let decoded = VersionMessage::decode(&payload[..]).unwrap();
+        assert_eq!(decoded.node_id, "bob");
+        assert_eq!(decoded.version, 0);
+        assert_eq!(decoded.features, 0);
+
+        // Truncated new fields are still malformed
+        let mut payload = vec![];
+        version.encode(&mut payload).unwrap();
+        payload.truncate(payload.len() - 4);
+        assert!(VersionMessage::decode(&payload[..]).is_err());
+    }
+}
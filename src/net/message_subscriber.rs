@@ -59,10 +59,12 @@ impl<M: Message> MessageSubscription<M> {
 #[async_trait]
 /// Generic interface for message dispatcher.
 trait MessageDispatcherInterface: Send + Sync {
-    async fn trigger(&self, payload: Vec<u8>);
+    async fn trigger(&self, payload: Vec<u8>) -> Result<()>;
 
     async fn trigger_error(&self, err: Error);
 
+    fn max_bytes(&self) -> usize;
+
     fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
 }
 
@@ -147,14 +149,16 @@ impl<M: Message> MessageDispatcher<M> {
 // Local implementation of the Message Dispatcher Interface.
 impl<M: Message> MessageDispatcherInterface for MessageDispatcher<M> {
     /// Internal function to deserialize data into a message type and dispatch it across subscriber channels.
-    async fn trigger(&self, payload: Vec<u8>) {
+    /// Returns an error if the data doesn't decode.
+    async fn trigger(&self, payload: Vec<u8>) -> Result<()> {
         // deserialize data into type
         // send down the pipes
         let cursor = Cursor::new(payload);
         match M::decode(cursor) {
             Ok(message) => {
                 let message = Ok(Arc::new(message));
-                self._trigger_all(message).await
+                self._trigger_all(message).await;
+                Ok(())
             }
             Err(err) => {
                 debug!(
@@ -162,6 +166,7 @@ impl<M: Message> MessageDispatcherInterface for MessageDispatcher<M> {
                     "Unable to decode data. Dropping...: {}",
                     err
                 );
+                Err(Error::MalformedPacket)
             }
         }
     }
@@ -171,6 +176,11 @@ impl<M: Message> MessageDispatcherInterface for MessageDispatcher<M> {
         self._trigger_all(Err(err)).await;
     }
 
+    /// Maximum payload size of the dispatched message type.
+    fn max_bytes(&self) -> usize {
+        M::max_bytes()
+    }
+
     /// Converts to Any trait. Enables the dynamic modification of static types.
     fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
         self
@@ -219,24 +229,29 @@ impl MessageSubsystem {
     }
 
     /// Transmits a payload to a dispatcher. Returns an error if the payload
-    /// fails to transmit.
-    pub async fn notify(&self, command: &str, payload: Vec<u8>) {
+    /// fails to decode.
+    pub async fn notify(&self, command: &str, payload: Vec<u8>) -> Result<()> {
         let dispatcher = self.dispatchers.lock().await.get(command).cloned();
 
         match dispatcher {
-            Some(dispatcher) => {
-                dispatcher.trigger(payload).await;
-            }
+            Some(dispatcher) => dispatcher.trigger(payload).await,
             None => {
                 warn!(
                     target: "net::message_subscriber::notify()",
                     "Command '{}' did not find a dispatcher",
                     command
                 );
+                Ok(())
             }
         }
     }
 
+    /// Returns the maximum payload size of the message registered for
+    /// `command`, or `None` if there is no dispatcher for it.
+    pub async fn max_bytes(&self, command: &str) -> Option<usize> {
+        self.dispatchers.lock().await.get(command).map(|dispatcher| dispatcher.max_bytes())
+    }
+
     /// Transmits an error message across dispatchers.
     pub async fn trigger_error(&self, err: Error) {
         // TODO: this could be parallelized
@@ -260,6 +275,7 @@ impl Default for MessageSubsystem {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::net::message::MAX_PAYLOAD_SIZE;
     use darkfi_serial::{Decodable, Encodable};
     use std::io;
 
@@ -306,7 +322,9 @@ mod tests {
         // receive message and publish
         //   1. based on string, lookup relevant dispatcher interface
         //   2. publish data there
-        subsystem.notify("verver", payload).await;
+        subsystem.notify("verver", payload).await.unwrap();
+        assert_eq!(subsystem.max_bytes("verver").await, Some(MAX_PAYLOAD_SIZE));
+        assert_eq!(subsystem.max_bytes("unknown").await, None);
 
         // receive
         //    1. do a get easy
@@ -21,6 +21,11 @@
 /// connections and to handle network errors.
 pub mod acceptor;
 
+/// Time-limited bans of misbehaving peers. Protocols raise a channel's ban
+/// score on malformed or invalid messages, and the peer gets banned once
+/// the score crosses the configured threshold.
+pub mod banlist;
+
 /// Async channel that handles the sending of messages across the network.
 /// Public interface is used to create new channels, to stop and start
 /// a channel, and to send messages.
@@ -92,6 +97,10 @@ pub mod p2p;
 /// asynchronous execution of the protocols.
 pub mod protocol;
 
+/// Token buckets used by channels to rate limit inbound messages, both
+/// overall and per message command.
+pub mod rate_limit;
+
 /// Defines the interaction between nodes during a connection. Consists of an
 /// inbound session, which describes how to set up an incoming connection, and
 /// an outbound session, which describes setting up an outbound connection. Also
@@ -103,6 +112,10 @@ pub mod session;
 /// Network configuration settings.
 pub mod settings;
 
+/// Harness running several nodes on one executor over the simulated
+/// `memory://` transport, for deterministic tests of sessions and protocols.
+pub mod simulation;
+
 /// Network transport implementations.
 pub mod transport;
 
@@ -110,13 +123,17 @@ pub mod transport;
 pub mod constants;
 
 pub use acceptor::{Acceptor, AcceptorPtr};
+pub use banlist::{Ban, BanList, BanListPtr};
 pub use channel::{Channel, ChannelPtr};
 pub use connector::Connector;
 pub use hosts::{Hosts, HostsPtr};
 pub use message::Message;
 pub use message_subscriber::MessageSubscription;
 pub use p2p::{P2p, P2pPtr};
-pub use protocol::{ProtocolBase, ProtocolBasePtr, ProtocolJobsManager, ProtocolJobsManagerPtr};
+pub use protocol::{
+    FeatureBitflag, ProtocolBase, ProtocolBasePtr, ProtocolJobsManager, ProtocolJobsManagerPtr,
+    FEATURE_NONE,
+};
 pub use session::{
     Session, SessionBitflag, SessionWeakPtr, SESSION_ALL, SESSION_INBOUND, SESSION_MANUAL,
     SESSION_OUTBOUND, SESSION_SEED,
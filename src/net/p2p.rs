@@ -31,15 +31,20 @@ use url::Url;
 
 use crate::{
     system::{Subscriber, SubscriberPtr, Subscription},
-    util::async_util::sleep,
+    util::{async_util::sleep, path::expand_path},
     Result,
 };
 
 use super::{
+    constants::LOCALNET,
     message::Message,
     protocol::{register_default_protocols, ProtocolRegistry},
-    session::{InboundSession, ManualSession, OutboundSession, SeedSyncSession, Session},
-    Channel, ChannelPtr, Hosts, HostsPtr, Settings, SettingsPtr,
+    session::{
+        InboundSession, ManualSession, OutboundSession, SeedSyncSession, Session, SESSION_INBOUND,
+        SESSION_OUTBOUND,
+    },
+    transport::{pinned_peer_key, KnownPeers, PeerVerification, TlsIdentity, TlsUpgrade},
+    BanList, BanListPtr, Channel, ChannelPtr, Hosts, HostsPtr, Settings, SettingsPtr,
 };
 
 /// List of channels that are awaiting connection.
@@ -83,6 +88,7 @@ pub struct P2p {
     // Used both internally and externally
     stop_subscriber: SubscriberPtr<()>,
     hosts: HostsPtr,
+    banlist: BanListPtr,
     protocol_registry: ProtocolRegistry,
 
     // We keep a reference to the sessions used for get info
@@ -94,6 +100,11 @@ pub struct P2p {
 
     settings: SettingsPtr,
 
+    /// TLS identity this node presents to its peers
+    tls_identity: TlsIdentity,
+    /// Trust-on-first-use store of outbound peer keys, if configured
+    known_peers: Option<Arc<KnownPeers>>,
+
     /// Flag to check if on discovery mode
     discovery: Mutex<bool>,
 }
@@ -106,21 +117,46 @@ impl P2p {
     /// address protocols.
     ///
     /// Creates a weak pointer to self that is used by all sessions to access the p2p parent class.
+    ///
+    /// Panics if a configured TLS identity or known peers file can't be loaded,
+    /// since silently running under another identity would break peers that
+    /// pinned our key.
     pub async fn new(settings: Settings) -> Arc<Self> {
         let settings = Arc::new(settings);
 
+        let tls_identity = match &settings.identity_path {
+            Some(path) => TlsIdentity::load_or_generate(&expand_path(path).unwrap()),
+            None => TlsIdentity::generate(),
+        }
+        .unwrap_or_else(|e| panic!("Failed loading P2P TLS identity: {}", e));
+
+        let known_peers = settings.known_peers_path.as_ref().map(|path| {
+            KnownPeers::new(Some(expand_path(path).unwrap()))
+                .unwrap_or_else(|e| panic!("Failed loading P2P known peers: {}", e))
+        });
+
+        let hosts = Hosts::new(settings.localnet);
+        if let Some(path) = &settings.hostlist {
+            if let Err(e) = hosts.load_hostlist(&expand_path(path).unwrap()).await {
+                warn!(target: "net::p2p::new()", "Failed loading host list: {}", e);
+            }
+        }
+
         let self_ = Arc::new(Self {
             pending: Mutex::new(HashSet::new()),
             channels: Mutex::new(HashMap::new()),
             channel_subscriber: Subscriber::new(),
             stop_subscriber: Subscriber::new(),
-            hosts: Hosts::new(settings.localnet),
+            hosts,
+            banlist: BanList::new(),
             protocol_registry: ProtocolRegistry::new(),
             session_manual: Mutex::new(None),
             session_inbound: Mutex::new(None),
             session_outbound: Mutex::new(None),
             state: Mutex::new(P2pState::Open),
             settings,
+            tls_identity,
+            known_peers,
             discovery: Mutex::new(false),
         });
 
@@ -145,6 +181,7 @@ impl P2p {
 
         json!({
             "external_addr": format!("{:?}", ext_addr_vec),
+            "identity": hex::encode(self.tls_identity.public_key().as_ref()),
             "session_manual": self.session_manual().await.get_info().await,
             "session_inbound": self.session_inbound().await.get_info().await,
             "session_outbound": self.session_outbound().await.get_info().await,
@@ -201,6 +238,10 @@ impl P2p {
         let outbound = self.session_outbound().await;
         outbound.clone().start(executor.clone()).await?;
 
+        // Periodically persist the host list, if configured.
+        // The task gets cancelled when dropped.
+        let hostlist_task = executor.spawn(self.clone().hostlist_save_loop());
+
         let stop_sub = self.subscribe_stop().await;
         // Wait for stop signal
         stop_sub.receive().await;
@@ -210,11 +251,50 @@ impl P2p {
         inbound.stop().await;
         outbound.stop().await;
 
+        drop(hostlist_task);
+        self.save_hostlist().await;
+
         debug!(target: "net::p2p::run()", "P2p::run() [END]");
         Ok(())
     }
     // ANCHOR_END: run
 
+    /// Save the host list every `hostlist_save_seconds`.
+    async fn hostlist_save_loop(self: Arc<Self>) {
+        loop {
+            sleep(self.settings.hostlist_save_seconds).await;
+            self.save_hostlist().await;
+        }
+    }
+
+    /// Save the host list to the configured file, making the currently
+    /// connected outbound peers its anchors.
+    pub async fn save_hostlist(&self) {
+        let Some(path) = &self.settings.hostlist else { return };
+
+        let anchors: Vec<Url> = self
+            .channels
+            .lock()
+            .await
+            .values()
+            .filter(|c| c.session_type_id() == SESSION_OUTBOUND)
+            .map(|c| c.address())
+            .collect();
+        self.hosts.set_anchors(&anchors).await;
+
+        let path = match expand_path(path) {
+            Ok(p) => p,
+            Err(e) => {
+                error!(target: "net::p2p::save_hostlist()", "Invalid host list path {}: {}", path, e);
+                return
+            }
+        };
+
+        if let Err(e) = self.hosts.save_hostlist(&path).await {
+            error!(target: "net::p2p::save_hostlist()", "Failed saving host list: {}", e);
+        }
+    }
+
     /// Wait for outbound connections to be established.
     pub async fn wait_for_outbound(self: Arc<Self>, executor: Arc<Executor<'_>>) -> Result<()> {
         debug!(target: "net::p2p::wait_for_outbound()", "P2p::wait_for_outbound() [BEGIN]");
@@ -460,6 +540,120 @@ impl P2p {
         self.hosts.clone()
     }
 
+    /// Return an atomic pointer to the ban list.
+    pub fn banlist(&self) -> BanListPtr {
+        self.banlist.clone()
+    }
+
+    /// Ban a host or hex-encoded TLS key for the given number of seconds.
+    /// Connected channels matching the target get stopped, and the host is
+    /// dropped from the host list.
+    pub async fn ban(&self, target: &str, seconds: u64, reason: &str) {
+        self.banlist.ban(target, seconds, reason).await;
+
+        let mut banned = vec![];
+        for channel in self.channels.lock().await.values() {
+            if Self::ban_targets(channel).iter().any(|t| t == target) {
+                banned.push(channel.clone());
+            }
+        }
+
+        for channel in banned {
+            channel.stop().await;
+        }
+
+        for addr in self.hosts.load_all().await {
+            if addr.host_str() == Some(target) {
+                self.hosts.remove(&addr).await;
+            }
+        }
+    }
+
+    /// Lift a ban. Returns `false` if the target wasn't banned.
+    pub async fn unban(&self, target: &str) -> bool {
+        self.banlist.unban(target).await
+    }
+
+    /// Ban the peer behind a channel for the configured duration, by its
+    /// host and its TLS key if it has one, and stop the channel.
+    pub async fn ban_channel(&self, channel: &Channel, reason: &str) {
+        warn!(target: "net::p2p::ban_channel()", "Banning {}: {}", channel.address(), reason);
+        for target in Self::ban_targets(channel) {
+            self.ban(&target, self.settings.ban_seconds, reason).await;
+        }
+        channel.stop().await;
+    }
+
+    /// Check if the peer behind a channel is banned.
+    pub async fn is_channel_banned(&self, channel: &Channel) -> bool {
+        for target in Self::ban_targets(channel) {
+            if self.banlist.is_banned(&target).await {
+                return true
+            }
+        }
+        false
+    }
+
+    /// Check if the host of an address is banned.
+    pub async fn is_addr_banned(&self, addr: &Url) -> bool {
+        match addr.host_str() {
+            Some(host) => self.banlist.is_banned(host).await,
+            None => false,
+        }
+    }
+
+    /// What to ban for the peer behind a channel: its TLS key if it has
+    /// one, and its host. Inbound connections from loopback addresses are
+    /// proxied, e.g. through a Tor hidden service, so their host is shared
+    /// by all peers using the proxy and doesn't get banned.
+    fn ban_targets(channel: &Channel) -> Vec<String> {
+        let mut targets = vec![];
+
+        if let Some(key) = channel.remote_key() {
+            targets.push(hex::encode(key.as_ref()));
+        }
+
+        let addr = channel.address();
+        if let Some(host) = addr.host_str() {
+            let proxied = LOCALNET.contains(&host) && channel.session_type_id() == SESSION_INBOUND;
+            if !proxied {
+                targets.push(host.to_string());
+            }
+        }
+
+        targets
+    }
+
+    /// Return the TLS identity this node presents to its peers.
+    pub fn tls_identity(&self) -> &TlsIdentity {
+        &self.tls_identity
+    }
+
+    /// Create a [`TlsUpgrade`] for a connection. For outbound connections
+    /// `peer` is the dialed URL: a key pinned in it takes precedence, then
+    /// the known peers store is used if configured. Inbound connections
+    /// accept any key, which protocols can check through `Channel::remote_key()`.
+    pub fn tls_upgrade(&self, peer: Option<&Url>) -> Result<TlsUpgrade> {
+        let Some(url) = peer else {
+            return Ok(TlsUpgrade::new(&self.tls_identity, PeerVerification::Any))
+        };
+
+        let verification = if let Some(key) = pinned_peer_key(url)? {
+            PeerVerification::Pinned(key)
+        } else if let Some(known_peers) = &self.known_peers {
+            let host = url.host_str().unwrap_or_default();
+            let peer = match url.port() {
+                Some(port) => format!("{}:{}", host, port),
+                None => host.to_string(),
+            };
+            PeerVerification::TrustOnFirstUse(known_peers.clone(), peer)
+        } else {
+            PeerVerification::Any
+        };
+
+        Ok(TlsUpgrade::new(&self.tls_identity, verification))
+    }
+
     pub fn protocol_registry(&self) -> &ProtocolRegistry {
         &self.protocol_registry
     }
@@ -62,6 +62,12 @@ pub mod protocol_seed;
 /// run the protocol. Nodes send a version message and wait for a version
 /// acknowledgement, while asynchronously waiting for version info from the
 /// other node and sending the version acknowledgement.
+///
+/// Version messages carry the net protocol version, the feature bits of the
+/// protocols the node supports, its time and the addresses it accepts
+/// connections on. Both sides speak the lowest of their protocol versions,
+/// and protocols registered with feature bits are only started if the remote
+/// advertised them.
 pub mod protocol_version;
 
 pub mod protocol_base;
@@ -74,7 +80,7 @@ pub use protocol_seed::ProtocolSeed;
 pub use protocol_version::ProtocolVersion;
 
 pub use protocol_base::{ProtocolBase, ProtocolBasePtr};
-pub use protocol_registry::ProtocolRegistry;
+pub use protocol_registry::{FeatureBitflag, ProtocolRegistry, FEATURE_NONE};
 
 use super::{
     session::{SESSION_ALL, SESSION_SEED},
@@ -97,7 +97,7 @@ impl ProtocolAddress {
                 "received {} addrs",
                 addrs_msg.addrs.len()
             );
-            self.hosts.store(addrs_msg.addrs.clone()).await;
+            self.hosts.store_from(&self.channel.address(), addrs_msg.addrs.clone()).await;
         }
     }
 
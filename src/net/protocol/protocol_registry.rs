@@ -30,8 +30,15 @@ use super::{
 type Constructor =
     Box<dyn Fn(ChannelPtr, P2pPtr) -> BoxFuture<'static, ProtocolBasePtr> + Send + Sync>;
 
+/// Feature bits advertised during the version handshake. A protocol
+/// registered with feature bits is only started on channels whose remote
+/// advertised all of them. Bits 0-15 are reserved for the net core,
+/// applications should use the upper bits.
+pub type FeatureBitflag = u64;
+pub const FEATURE_NONE: FeatureBitflag = 0;
+
 pub struct ProtocolRegistry {
-    protocol_constructors: Mutex<Vec<(SessionBitflag, Constructor)>>,
+    protocol_constructors: Mutex<Vec<(SessionBitflag, FeatureBitflag, Constructor)>>,
 }
 
 impl Default for ProtocolRegistry {
@@ -50,21 +57,49 @@ impl ProtocolRegistry {
     where
         C: 'static + Fn(ChannelPtr, P2pPtr) -> F + Send + Sync,
         F: 'static + Future<Output = ProtocolBasePtr> + Send,
+    {
+        self.register_with_features(session_flags, FEATURE_NONE, constructor).await
+    }
+
+    /// Register a protocol that only runs on channels whose remote supports
+    /// `features`. The features also get advertised to our peers.
+    pub async fn register_with_features<C, F>(
+        &self,
+        session_flags: SessionBitflag,
+        features: FeatureBitflag,
+        constructor: C,
+    ) where
+        C: 'static + Fn(ChannelPtr, P2pPtr) -> F + Send + Sync,
+        F: 'static + Future<Output = ProtocolBasePtr> + Send,
     {
         let constructor = move |channel, p2p| {
             Box::pin(constructor(channel, p2p)) as BoxFuture<'static, ProtocolBasePtr>
         };
-        self.protocol_constructors.lock().await.push((session_flags, Box::new(constructor)));
+        self.protocol_constructors.lock().await.push((
+            session_flags,
+            features,
+            Box::new(constructor),
+        ));
+    }
+
+    /// Feature bits of all registered protocols, which we advertise during
+    /// the version handshake.
+    pub async fn features(&self) -> FeatureBitflag {
+        self.protocol_constructors.lock().await.iter().fold(FEATURE_NONE, |acc, (_, f, _)| acc | f)
     }
 
+    /// Construct the protocols registered for the session, along with the
+    /// features they require. They get constructed before the handshake so
+    /// they can start buffering messages, and are only started once the
+    /// remote features are known.
     pub async fn attach(
         &self,
         selector_id: SessionBitflag,
         channel: ChannelPtr,
         p2p: P2pPtr,
-    ) -> Vec<ProtocolBasePtr> {
-        let mut protocols: Vec<ProtocolBasePtr> = Vec::new();
-        for (session_flags, construct) in self.protocol_constructors.lock().await.iter() {
+    ) -> Vec<(FeatureBitflag, ProtocolBasePtr)> {
+        let mut protocols: Vec<(FeatureBitflag, ProtocolBasePtr)> = Vec::new();
+        for (session_flags, features, construct) in self.protocol_constructors.lock().await.iter() {
             // Skip protocols that are not registered for this session
             if selector_id & session_flags == 0 {
                 debug!(target: "net::protocol_registry", "Skipping {selector_id:#b}, {session_flags:#b}");
@@ -74,7 +109,7 @@ impl ProtocolRegistry {
             let protocol: ProtocolBasePtr = construct(channel.clone(), p2p.clone()).await;
             debug!(target: "net::protocol_registry", "Attached {}", protocol.name());
 
-            protocols.push(protocol)
+            protocols.push((*features, protocol))
         }
         protocols
     }
@@ -22,10 +22,17 @@ use std::{sync::Arc, time::Duration};
 use log::*;
 use smol::Executor;
 
-use crate::{Error, Result};
+use crate::{util::time::Timestamp, Error, Result};
 
-use super::super::{
-    message, message_subscriber::MessageSubscription, ChannelPtr, HostsPtr, SettingsPtr,
+use super::{
+    super::{
+        constants::{MAX_CONNECT_ADDRS, PROTOCOL_VERSION},
+        message,
+        message_subscriber::MessageSubscription,
+        session::SESSION_INBOUND,
+        ChannelPtr, HostsPtr, SettingsPtr,
+    },
+    FeatureBitflag,
 };
 
 /// Implements the protocol version handshake sent out by nodes at the beginning
@@ -36,13 +43,20 @@ pub struct ProtocolVersion {
     verack_sub: MessageSubscription<message::VerackMessage>,
     settings: SettingsPtr,
     hosts: HostsPtr,
+    /// Feature bits we advertise to the remote
+    features: FeatureBitflag,
 }
 
 impl ProtocolVersion {
     /// Create a new version protocol. Makes a version and version
     /// acknowledgement subscription, then adds them to a version protocol
     /// instance.
-    pub async fn new(channel: ChannelPtr, settings: SettingsPtr, hosts: HostsPtr) -> Arc<Self> {
+    pub async fn new(
+        channel: ChannelPtr,
+        settings: SettingsPtr,
+        hosts: HostsPtr,
+        features: FeatureBitflag,
+    ) -> Arc<Self> {
         // Creates a version subscription.
         let version_sub = channel
             .clone()
@@ -57,7 +71,7 @@ impl ProtocolVersion {
             .await
             .expect("Missing verack dispatcher!");
 
-        Arc::new(Self { channel, version_sub, verack_sub, settings, hosts })
+        Arc::new(Self { channel, version_sub, verack_sub, settings, hosts, features })
     }
 
     /// Start version information exchange. Start the timer. Send version info
@@ -76,6 +90,7 @@ impl ProtocolVersion {
         .await;
 
         if let Err(_e) = result {
+            self.hosts.mark_failure(&self.channel.address()).await;
             return Err(Error::ChannelTimeout)
         }
 
@@ -88,85 +103,80 @@ impl ProtocolVersion {
         debug!(target: "net::protocol_version::exchange_versions()", "START");
 
         let send = executor.spawn(self.clone().send_version());
-        let recv = executor.spawn(self.recv_version());
+        let recv = executor.spawn(self.clone().recv_version());
 
-        send.await?;
-        recv.await?;
+        let verack = send.await?;
+        let version = recv.await?;
+
+        // Validate peer app version against ours, if configured.
+        // Seeds version gets ignored.
+        if !self.settings.seeds.contains(&self.channel.address()) {
+            self.check_app_version(&verack.app, version.version).await?;
+        }
 
         debug!(target: "net::protocol_version::exchange_versions()", "END");
         Ok(())
     }
 
-    /// Send version info and wait for version acknowledgement
-    /// and ensures the app version is the same, if configured.
-    async fn send_version(self: Arc<Self>) -> Result<()> {
+    /// Send version info and wait for version acknowledgement.
+    async fn send_version(self: Arc<Self>) -> Result<Arc<message::VerackMessage>> {
         debug!(target: "net::protocol_version::send_version()", "START");
 
-        let version = message::VersionMessage { node_id: self.settings.node_id.clone() };
+        let version = message::VersionMessage {
+            node_id: self.settings.node_id.clone(),
+            version: PROTOCOL_VERSION,
+            features: self.features,
+            timestamp: Timestamp::current_time(),
+            connect_addrs: self.settings.external_addr.clone(),
+        };
 
         self.channel.clone().send(version).await?;
 
         // Wait for version acknowledgement
         let verack_msg = self.verack_sub.receive().await?;
 
-        // Validate peer received version against our version, if configured.
-        // Seeds version gets ignored.
-        if !self.settings.seeds.contains(&self.channel.address()) {
-            match &self.settings.app_version {
-                Some(app_version) => {
-                    debug!(
-                        target: "net::protocol_version::send_version()",
-                        "App version: {}, received version: {}",
-                        app_version,
-                        verack_msg.app
-                    );
-                    // Version format: MAJOR.MINOR.PATCH
-                    let app_versions: Vec<&str> = app_version.split('.').collect();
-                    let verack_msg_versions: Vec<&str> = verack_msg.app.split('.').collect();
-                    // Check for malformed versions
-                    if app_versions.len() != 3 || verack_msg_versions.len() != 3 {
-                        error!(
-                            target: "net::protocol_version::send_version()",
-                            "Malformed version detected. Disconnecting from channel."
-                        );
-                        self.hosts.remove(&self.channel.address()).await;
-                        self.channel.stop().await;
-                        return Err(Error::ChannelStopped)
-                    }
-                    // Ignore PATCH version
-                    if app_versions[0] != verack_msg_versions[0] ||
-                        app_versions[1] != verack_msg_versions[1]
-                    {
-                        error!(
-                            target: "net::protocol_version::send_version()",
-                            "Wrong app version from ({}). Disconnecting from channel.",
-                            self.channel.address()
-                        );
-                        self.hosts.remove(&self.channel.address()).await;
-                        self.channel.stop().await;
-                        return Err(Error::ChannelStopped)
-                    }
-                }
-                None => {
-                    debug!(
-                        target: "net::protocol_version::send_version()",
-                        "App version not set, ignoring received"
-                    )
-                }
-            }
-        }
-
         debug!(target: "net::protocol_version::send_version()", "END");
-        Ok(())
+        Ok(verack_msg)
     }
 
     /// Recieve version info, check the message is okay and send version
     /// acknowledgement with app version attached.
-    async fn recv_version(self: Arc<Self>) -> Result<()> {
+    async fn recv_version(self: Arc<Self>) -> Result<Arc<message::VersionMessage>> {
         debug!(target: "net::protocol_version::recv_version()", "START");
         // Receive version message
         let version = self.version_sub.receive().await?;
-        self.channel.set_remote_node_id(version.node_id.clone()).await;
+
+        // Every version is accepted for now, version 0 being nodes predating
+        // the structured handshake. Speak the highest one both sides support.
+        let negotiated = version.version.min(PROTOCOL_VERSION);
+        self.channel.set_remote_version(&version, negotiated).await;
+        debug!(
+            target: "net::protocol_version::recv_version()",
+            "Negotiated protocol version {} with ({}), remote features: {:#b}",
+            negotiated,
+            self.channel.address(),
+            version.features
+        );
+
+        // Inbound peers tell us where they can be reached. These are checked
+        // like the external addresses they would send us with ProtocolAddress.
+        if self.channel.session_type_id() & SESSION_INBOUND != 0 &&
+            !version.connect_addrs.is_empty()
+        {
+            if version.connect_addrs.len() > MAX_CONNECT_ADDRS {
+                warn!(
+                    target: "net::protocol_version::recv_version()",
+                    "Peer ({}) advertised {} addresses, keeping the first {}",
+                    self.channel.address(),
+                    version.connect_addrs.len(),
+                    MAX_CONNECT_ADDRS
+                );
+            }
+
+            let connect_addrs =
+                version.connect_addrs.iter().take(MAX_CONNECT_ADDRS).cloned().collect();
+            self.hosts.store_ext(self.channel.address(), connect_addrs).await;
+        }
 
         // Send version acknowledgement
         let verack =
@@ -174,6 +184,100 @@ impl ProtocolVersion {
         self.channel.clone().send(verack).await?;
 
         debug!(target: "net::protocol_version::recv_version()", "END");
-        Ok(())
+        Ok(version)
+    }
+
+    /// Ensure the peer app version is compatible with ours, if configured.
+    async fn check_app_version(&self, remote_app: &str, remote_version: u32) -> Result<()> {
+        let Some(app_version) = &self.settings.app_version else {
+            debug!(
+                target: "net::protocol_version::check_app_version()",
+                "App version not set, ignoring received"
+            );
+            return Ok(())
+        };
+
+        debug!(
+            target: "net::protocol_version::check_app_version()",
+            "App version: {}, received version: {}",
+            app_version,
+            remote_app
+        );
+
+        match app_versions_compatible(app_version, remote_app, remote_version) {
+            Some(true) => Ok(()),
+            Some(false) => {
+                error!(
+                    target: "net::protocol_version::check_app_version()",
+                    "Wrong app version from ({}). Disconnecting from channel.",
+                    self.channel.address()
+                );
+                self.reject().await
+            }
+            None => {
+                error!(
+                    target: "net::protocol_version::check_app_version()",
+                    "Malformed version detected. Disconnecting from channel."
+                );
+                self.reject().await
+            }
+        }
+    }
+
+    /// Drop the peer after a failed handshake.
+    async fn reject<T>(&self) -> Result<T> {
+        self.hosts.mark_failure(&self.channel.address()).await;
+        self.channel.stop().await;
+        Err(Error::ChannelStopped)
+    }
+}
+
+/// Parse an app version of the form `MAJOR.MINOR.PATCH`. The patch may
+/// carry a suffix, like `0.4.1-beta`.
+fn parse_app_version(version: &str) -> Option<(u64, u64)> {
+    let parts: Vec<&str> = version.split('.').collect();
+    if parts.len() != 3 || parts[2].is_empty() {
+        return None
+    }
+
+    Some((parts[0].parse().ok()?, parts[1].parse().ok()?))
+}
+
+/// Check whether the app versions of two nodes are compatible. Returns
+/// `None` if either is malformed.
+///
+/// Peers speaking the structured handshake negotiate capabilities through
+/// feature bits, so only a different MAJOR version is incompatible, which
+/// lets MINOR upgrades roll out without disconnects. Legacy peers also
+/// need a matching MINOR version. PATCH versions are always ignored.
+fn app_versions_compatible(ours: &str, theirs: &str, remote_version: u32) -> Option<bool> {
+    let (our_major, our_minor) = parse_app_version(ours)?;
+    let (their_major, their_minor) = parse_app_version(theirs)?;
+
+    if remote_version == 0 {
+        return Some(our_major == their_major && our_minor == their_minor)
+    }
+
+    Some(our_major == their_major)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_app_versions_compatible() {
+        assert_eq!(app_versions_compatible("0.4.1", "0.4.2", 1), Some(true));
+        assert_eq!(app_versions_compatible("0.4.1", "0.5.0", 1), Some(true));
+        assert_eq!(app_versions_compatible("0.4.1", "1.4.1", 1), Some(false));
+        assert_eq!(app_versions_compatible("0.4.1", "0.4.0-beta", 1), Some(true));
+
+        // Legacy peers need a matching MINOR version
+        assert_eq!(app_versions_compatible("0.4.1", "0.4.2", 0), Some(true));
+        assert_eq!(app_versions_compatible("0.4.1", "0.5.0", 0), Some(false));
+
+        assert_eq!(app_versions_compatible("0.4.1", "0.4", 1), None);
+        assert_eq!(app_versions_compatible("0.4.1", "a.4.1", 1), None);
+        assert_eq!(app_versions_compatible("", "0.4.1", 1), None);
     }
 }
@@ -0,0 +1,96 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::time::Instant;
+
+/// Token bucket used to rate limit inbound messages. Holds up to `burst`
+/// tokens and refills `rate` tokens per second. A `rate` of 0 disables
+/// the limit.
+#[derive(Clone, Debug)]
+pub struct TokenBucket {
+    rate: f64,
+    burst: f64,
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    /// Create a new full bucket.
+    pub fn new(rate: u32, burst: u32) -> Self {
+        let burst = burst.max(1) as f64;
+        Self { rate: rate as f64, burst, tokens: burst, last_refill: Instant::now() }
+    }
+
+    /// Take a token from the bucket. Returns `false` if it's empty, in
+    /// which case the message should be dropped.
+    pub fn take(&mut self) -> bool {
+        self.take_at(Instant::now())
+    }
+
+    fn take_at(&mut self, now: Instant) -> bool {
+        if self.rate == 0.0 {
+            return true
+        }
+
+        let elapsed = now.saturating_duration_since(self.last_refill).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.burst);
+        self.last_refill = now;
+
+        if self.tokens < 1.0 {
+            return false
+        }
+
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_token_bucket() {
+        let mut bucket = TokenBucket::new(10, 5);
+        let start = bucket.last_refill;
+
+        // The burst is available right away
+        for _ in 0..5 {
+            assert!(bucket.take_at(start));
+        }
+        assert!(!bucket.take_at(start));
+
+        // 10 tokens per second refill one every 100ms
+        assert!(bucket.take_at(start + Duration::from_millis(100)));
+        assert!(!bucket.take_at(start + Duration::from_millis(150)));
+
+        // Refilling never exceeds the burst
+        let later = start + Duration::from_secs(60);
+        for _ in 0..5 {
+            assert!(bucket.take_at(later));
+        }
+        assert!(!bucket.take_at(later));
+
+        // A zero rate disables the limit
+        let mut bucket = TokenBucket::new(0, 0);
+        for _ in 0..1000 {
+            assert!(bucket.take());
+        }
+    }
+}
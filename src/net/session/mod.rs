@@ -131,9 +131,14 @@ pub trait Session: Sync {
             p2p.protocol_registry().attach(self.type_id(), channel.clone(), p2p.clone()).await;
 
         // Perform the handshake protocol
-        let protocol_version =
-            ProtocolVersion::new(channel.clone(), p2p.settings().clone(), p2p.hosts().clone())
-                .await;
+        let features = p2p.protocol_registry().features().await;
+        let protocol_version = ProtocolVersion::new(
+            channel.clone(),
+            p2p.settings().clone(),
+            p2p.hosts().clone(),
+            features,
+        )
+        .await;
         let handshake_task =
             self.perform_handshake_protocols(protocol_version, channel.clone(), executor.clone());
 
@@ -146,10 +151,22 @@ pub trait Session: Sync {
         // Now the channel is ready
         debug!(target: "net", "Session handshake complete. Activating remaining protocols");
 
-        // Now start all the protocols
+        // Now start all the protocols the remote supports
         // They are responsible for managing their own lifetimes and
         // correctly self destructing when the channel ends.
-        for protocol in protocols {
+        let remote_features = channel.remote_features().await;
+        for (features, protocol) in protocols {
+            if features & remote_features != features {
+                debug!(
+                    target: "net",
+                    "Remote {} lacks features {:#b}, not starting {}",
+                    channel.address(),
+                    features,
+                    protocol.name()
+                );
+                continue
+            }
+
             // Activate protocol
             protocol.start(executor.clone()).await?;
         }
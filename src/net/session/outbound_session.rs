@@ -21,7 +21,6 @@ use std::fmt;
 use async_std::sync::{Arc, Mutex, Weak};
 use async_trait::async_trait;
 use log::{debug, error, info, warn};
-use rand::seq::SliceRandom;
 use serde_json::{json, Value};
 use smol::Executor;
 use url::Url;
@@ -204,8 +203,13 @@ impl OutboundSession {
         let transports = if outbound_transports.contains(&addr_transport) {
             vec![addr_transport]
         } else {
-            warn!(target: "net::outbound_session", "#{} address {} transport is not in accepted outbound transports, will try with: {:?}", slot_number, addr, outbound_transports);
-            outbound_transports.clone()
+            let transports: Vec<TransportName> = outbound_transports
+                .iter()
+                .filter(|t| t.is_compatible(&addr_transport))
+                .cloned()
+                .collect();
+            warn!(target: "net::outbound_session", "#{} address {} transport is not in accepted outbound transports, will try with: {:?}", slot_number, addr, transports);
+            transports
         };
 
         for transport in transports {
@@ -215,7 +219,13 @@ impl OutboundSession {
             info!(target: "net::outbound_session", "#{} connecting to outbound [{}]", slot_number, transport_addr);
             match connector.connect(transport_addr.clone()).await {
                 Ok(channel) => {
-                    // Blacklist goes here
+                    // The peer might be banned by its TLS key under another address
+                    if self.p2p().is_channel_banned(&channel).await {
+                        warn!(target: "net::outbound_session", "#{} outbound [{}] is banned", slot_number, transport_addr);
+                        channel.stop().await;
+                        continue
+                    }
+
                     info!(target: "net::outbound_session", "#{} connected to outbound [{}]", slot_number, transport_addr);
 
                     let stop_sub = channel.subscribe_stop().await;
@@ -223,9 +233,11 @@ impl OutboundSession {
                         continue
                     }
 
+                    // Handshake failures are recorded in hosts by the version protocol
                     self.register_channel(channel.clone(), executor.clone()).await?;
 
                     // Channel is now connected but not yet setup
+                    self.p2p().hosts().mark_success(&addr).await;
 
                     // Remove pending lock since register_channel will add the channel to p2p
                     self.p2p().remove_pending(&addr).await;
@@ -251,8 +263,8 @@ impl OutboundSession {
             }
         }
 
-        // Remove url from hosts
-        self.p2p().hosts().remove(&addr).await;
+        // Record the failure, dropping the url from hosts if it keeps failing
+        self.p2p().hosts().mark_failure(&addr).await;
 
         {
             let info = &mut self.slot_info.lock().await[slot_number as usize];
@@ -269,7 +281,7 @@ impl OutboundSession {
         Err(Error::ConnectFailed)
     }
 
-    /// Loops through host addresses to find a outbound address that we can
+    /// Loops through host candidates to find a outbound address that we can
     /// connect to. Checks whether address is valid by making sure it isn't
     /// our own inbound address, then checks whether it is already connected
     /// (exists) or connecting (pending). If no address was found, we try to
@@ -279,20 +291,19 @@ impl OutboundSession {
             let p2p = self.p2p();
             let self_inbound_addr = p2p.settings().external_addr.clone();
 
-            let mut addrs;
-
-            {
-                let hosts = p2p.hosts().load_all().await;
-                addrs = hosts;
-            }
-
-            addrs.shuffle(&mut rand::thread_rng());
+            // Candidates are ordered by tier and past connection attempts
+            let addrs = p2p.hosts().load_candidates().await;
 
             for addr in addrs {
                 if p2p.exists(&addr).await? {
                     continue
                 }
 
+                // Skip banned hosts
+                if p2p.is_addr_banned(&addr).await {
+                    continue
+                }
+
                 // Check if address is in peers list
                 if p2p.settings().peers.contains(&addr) {
                     continue
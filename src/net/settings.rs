@@ -67,6 +67,31 @@ pub struct Settings {
     pub peer_discovery: bool,
     /// Enable channel logging
     pub channel_log: bool,
+    /// Path to the node's TLS identity keypair, generated if missing.
+    /// When unset, a new ephemeral identity is used on every start.
+    pub identity_path: Option<String>,
+    /// Path to the trust-on-first-use store of peer TLS keys.
+    /// When unset, outbound peers are only verified if their key is
+    /// pinned in the URL.
+    pub known_peers_path: Option<String>,
+    /// Path to the file the host list is persisted in.
+    /// When unset, known hosts are lost on restart.
+    pub hostlist: Option<String>,
+    /// Host list saving interval
+    pub hostlist_save_seconds: u64,
+    /// Ban score at which a misbehaving peer gets banned
+    pub ban_threshold: u32,
+    /// Ban duration
+    pub ban_seconds: u64,
+    /// Inbound messages per second allowed on a channel, 0 for no limit
+    pub channel_msg_rate: u32,
+    /// Inbound messages a channel may burst above its rate
+    pub channel_msg_burst: u32,
+    /// Inbound messages per second allowed for each command on a channel,
+    /// 0 for no limit
+    pub command_msg_rate: u32,
+    /// Inbound messages a command may burst above its rate
+    pub command_msg_burst: u32,
 }
 
 impl Default for Settings {
@@ -89,6 +114,16 @@ impl Default for Settings {
             localnet: false,
             peer_discovery: true,
             channel_log: false,
+            identity_path: None,
+            known_peers_path: None,
+            hostlist: None,
+            hostlist_save_seconds: 300,
+            ban_threshold: 100,
+            ban_seconds: 86400,
+            channel_msg_rate: 1000,
+            channel_msg_burst: 2000,
+            command_msg_rate: 200,
+            command_msg_burst: 400,
         }
     }
 }
@@ -176,6 +211,47 @@ pub struct SettingsOpt {
     #[serde(default)]
     #[structopt(long)]
     pub channel_log: bool,
+
+    /// Path to the node's TLS identity keypair, generated if missing
+    #[structopt(long)]
+    pub identity_path: Option<String>,
+
+    /// Path to the trust-on-first-use store of peer TLS keys
+    #[structopt(long)]
+    pub known_peers_path: Option<String>,
+
+    /// Path to the file the host list is persisted in
+    #[structopt(long)]
+    pub hostlist: Option<String>,
+
+    /// Host list saving interval
+    #[structopt(skip)]
+    pub hostlist_save_seconds: Option<u64>,
+
+    /// Ban score at which a misbehaving peer gets banned
+    #[structopt(skip)]
+    pub ban_threshold: Option<u32>,
+
+    /// Ban duration
+    #[structopt(skip)]
+    pub ban_seconds: Option<u64>,
+
+    /// Inbound messages per second allowed on a channel, 0 for no limit
+    #[structopt(skip)]
+    pub channel_msg_rate: Option<u32>,
+
+    /// Inbound messages a channel may burst above its rate
+    #[structopt(skip)]
+    pub channel_msg_burst: Option<u32>,
+
+    /// Inbound messages per second allowed for each command on a channel,
+    /// 0 for no limit
+    #[structopt(skip)]
+    pub command_msg_rate: Option<u32>,
+
+    /// Inbound messages a command may burst above its rate
+    #[structopt(skip)]
+    pub command_msg_burst: Option<u32>,
 }
 
 impl From<SettingsOpt> for Settings {
@@ -198,6 +274,16 @@ impl From<SettingsOpt> for Settings {
             localnet: settings_opt.localnet,
             peer_discovery: settings_opt.peer_discovery,
             channel_log: settings_opt.channel_log,
+            identity_path: settings_opt.identity_path,
+            known_peers_path: settings_opt.known_peers_path,
+            hostlist: settings_opt.hostlist,
+            hostlist_save_seconds: settings_opt.hostlist_save_seconds.unwrap_or(300),
+            ban_threshold: settings_opt.ban_threshold.unwrap_or(100),
+            ban_seconds: settings_opt.ban_seconds.unwrap_or(86400),
+            channel_msg_rate: settings_opt.channel_msg_rate.unwrap_or(1000),
+            channel_msg_burst: settings_opt.channel_msg_burst.unwrap_or(2000),
+            command_msg_rate: settings_opt.command_msg_rate.unwrap_or(200),
+            command_msg_burst: settings_opt.command_msg_burst.unwrap_or(400),
         }
     }
 }
@@ -0,0 +1,209 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{future::Future, time::Duration};
+
+use async_std::sync::{Arc, Mutex};
+use log::error;
+use smol::{Executor, Task, Timer};
+use url::Url;
+
+use super::{
+    transport::{MemoryNetwork, TransportName},
+    P2p, P2pPtr, Settings,
+};
+use crate::{Error, Result};
+
+/// Port simulated nodes listen on.
+pub const SIMULATION_PORT: u16 = 26661;
+
+/// Step the simulated clock advances by while waiting for a condition.
+pub const SIMULATION_TICK: Duration = Duration::from_millis(10);
+
+/// Harness running several [`P2p`] nodes on one executor, connected
+/// through a simulated [`MemoryNetwork`] instead of the OS network stack.
+///
+/// Node `i` is named `node<i>`, and listens and advertises itself on
+/// `memory://node<i>.<network>:26661`. Every simulation should use its own
+/// network name, so tests running in parallel don't see each other.
+///
+/// Link latency runs on the simulated clock of the network, which the
+/// harness advances while it waits, and loss comes from the network's
+/// seeded RNG. Timers of the nodes themselves, like reconnection delays,
+/// still run on the wall clock, so each tick also lets that much real
+/// time pass.
+pub struct Simulation {
+    network: Arc<MemoryNetwork>,
+    nodes: Vec<P2pPtr>,
+    executor: Arc<Executor<'static>>,
+    tasks: Mutex<Vec<Task<()>>>,
+}
+
+impl Simulation {
+    /// Create `n` nodes on the network with the given name. `configure`
+    /// gets called with each node index and its default settings, which
+    /// accept inbound connections, only dial simulated addresses and allow
+    /// localnet hosts.
+    pub async fn new(
+        network: &str,
+        n: usize,
+        executor: Arc<Executor<'static>>,
+        configure: impl Fn(usize, &mut Settings),
+    ) -> Self {
+        // Start off a clean network
+        MemoryNetwork::remove(network);
+        let network = MemoryNetwork::get(network);
+
+        let mut nodes = Vec::with_capacity(n);
+        for i in 0..n {
+            let address = Self::node_address(network.name(), i);
+            let mut settings = Settings {
+                inbound: vec![address.clone()],
+                external_addr: vec![address],
+                node_id: Self::node_name(i),
+                outbound_transports: vec![TransportName::Memory(None)],
+                localnet: true,
+                outbound_retry_seconds: 1,
+                ..Default::default()
+            };
+            configure(i, &mut settings);
+            nodes.push(P2p::new(settings).await);
+        }
+
+        Self { network, nodes, executor, tasks: Mutex::new(vec![]) }
+    }
+
+    /// Name of the node with the given index, used to define partitions.
+    pub fn node_name(i: usize) -> String {
+        format!("node{}", i)
+    }
+
+    /// Address node `i` listens on in the given network.
+    pub fn node_address(network: &str, i: usize) -> Url {
+        Url::parse(&format!("memory://{}.{}:{}", Self::node_name(i), network, SIMULATION_PORT))
+            .unwrap()
+    }
+
+    /// Address of the node with the given index.
+    pub fn address(&self, i: usize) -> Url {
+        Self::node_address(self.network.name(), i)
+    }
+
+    /// The simulated network the nodes are connected through.
+    pub fn network(&self) -> &Arc<MemoryNetwork> {
+        &self.network
+    }
+
+    /// The node with the given index.
+    pub fn node(&self, i: usize) -> P2pPtr {
+        self.nodes[i].clone()
+    }
+
+    /// All nodes of the simulation.
+    pub fn nodes(&self) -> &[P2pPtr] {
+        &self.nodes
+    }
+
+    /// Start the nodes in order, waiting for each of them to listen before
+    /// starting the next, so nodes can use lower indexed ones as seeds or
+    /// peers.
+    pub async fn start(&self) -> Result<()> {
+        for (i, node) in self.nodes.iter().enumerate() {
+            // Starting syncs with the seeds, which needs the clock to move
+            self.advancing(node.clone().start(self.executor.clone())).await?;
+
+            let node = node.clone();
+            let executor = self.executor.clone();
+            let task = self.executor.spawn(async move {
+                if let Err(e) = node.run(executor).await {
+                    error!(target: "net::simulation", "Node {} stopped with error: {}", i, e);
+                }
+            });
+            self.tasks.lock().await.push(task);
+
+            let name = Self::node_name(i);
+            let network = self.network.clone();
+            self.wait_for(Duration::from_secs(10), || {
+                let listening = network.is_listening(&name, SIMULATION_PORT);
+                async move { listening }
+            })
+            .await?;
+        }
+
+        Ok(())
+    }
+
+    /// Stop all nodes and wait for them to shut down.
+    pub async fn stop(&self) {
+        for node in &self.nodes {
+            node.stop().await;
+        }
+
+        for task in self.tasks.lock().await.drain(..) {
+            task.await;
+        }
+    }
+
+    /// Poll `condition` once per tick of the simulated clock, until it
+    /// holds or `timeout` of simulated time passes, which is an error.
+    pub async fn wait_for<F, Fut>(&self, timeout: Duration, condition: F) -> Result<()>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = bool>,
+    {
+        let deadline = self.network.now() + timeout;
+        loop {
+            if condition().await {
+                return Ok(())
+            }
+
+            if self.network.now() >= deadline {
+                return Err(Error::TimeoutError)
+            }
+
+            self.network.advance(SIMULATION_TICK);
+            Timer::after(SIMULATION_TICK).await;
+        }
+    }
+
+    /// Run `future` to completion while advancing the simulated clock.
+    pub async fn advancing<T>(&self, future: impl Future<Output = T>) -> T {
+        let ticks = async {
+            loop {
+                self.network.advance(SIMULATION_TICK);
+                Timer::after(SIMULATION_TICK).await;
+            }
+        };
+
+        smol::future::or(future, ticks).await
+    }
+
+    /// Wait until every node has at least `count` connected channels.
+    pub async fn wait_for_connections(&self, timeout: Duration, count: usize) -> Result<()> {
+        let nodes = &self.nodes;
+        self.wait_for(timeout, || async move {
+            for node in nodes {
+                if node.connections_count().await < count {
+                    return false
+                }
+            }
+            true
+        })
+        .await
+    }
+}
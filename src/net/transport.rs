@@ -19,6 +19,7 @@
 use std::{net::SocketAddr, time::Duration};
 
 use async_trait::async_trait;
+use ed25519_compact::PublicKey;
 use futures::prelude::*;
 use futures_rustls::{TlsAcceptor, TlsStream};
 use url::Url;
@@ -26,7 +27,9 @@ use url::Url;
 use crate::Result;
 
 mod upgrade_tls;
-pub use upgrade_tls::TlsUpgrade;
+pub use upgrade_tls::{
+    peer_public_key, pinned_peer_key, KnownPeers, PeerVerification, TlsIdentity, TlsUpgrade,
+};
 
 mod tcp;
 pub use tcp::TcpTransport;
@@ -34,9 +37,15 @@ pub use tcp::TcpTransport;
 mod tor;
 pub use tor::TorTransport;
 
+mod nym;
+pub use nym::{NymAddress, NymTransport};
+
 mod unix;
 pub use unix::UnixTransport;
 
+mod memory;
+pub use memory::{LinkConditions, MemoryNetwork, MemoryTransport};
+
 /// A helper function to convert SocketAddr to Url and add scheme
 pub(crate) fn socket_addr_to_url(addr: SocketAddr, scheme: &str) -> Result<Url> {
     let url = Url::parse(&format!("{}://{}", scheme, addr))?;
@@ -44,7 +53,13 @@ pub(crate) fn socket_addr_to_url(addr: SocketAddr, scheme: &str) -> Result<Url>
 }
 
 /// Used as wrapper for stream used by Transport trait
-pub trait TransportStream: AsyncWrite + AsyncRead + Unpin + Send + Sync {}
+pub trait TransportStream: AsyncWrite + AsyncRead + Unpin + Send + Sync {
+    /// Return the authenticated key of the remote peer, if the
+    /// stream was upgraded with TLS.
+    fn peer_key(&self) -> Option<PublicKey> {
+        None
+    }
+}
 
 /// Used as wrapper for listener used by Transport trait
 #[async_trait]
@@ -57,6 +72,7 @@ pub enum TransportName {
     Tcp(Option<String>),
     Tor(Option<String>),
     Nym(Option<String>),
+    Memory(Option<String>),
     Unix,
 }
 
@@ -69,9 +85,20 @@ impl TransportName {
             Self::Tor(Some(opt)) => format!("tor+{}", opt),
             Self::Nym(None) => "nym".into(),
             Self::Nym(Some(opt)) => format!("nym+{}", opt),
+            Self::Memory(None) => "memory".into(),
+            Self::Memory(Some(opt)) => format!("memory+{}", opt),
             Self::Unix => "unix".into(),
         }
     }
+
+    /// Check if addresses of this transport can be dialed using the other
+    /// transport. Nym and simulated memory addresses live in their own
+    /// address spaces, so they can't be swapped with IP or onion based
+    /// transports.
+    pub fn is_compatible(&self, other: &Self) -> bool {
+        matches!(self, Self::Nym(_)) == matches!(other, Self::Nym(_)) &&
+            matches!(self, Self::Memory(_)) == matches!(other, Self::Memory(_))
+    }
 }
 
 impl TryFrom<&str> for TransportName {
@@ -85,6 +112,8 @@ impl TryFrom<&str> for TransportName {
             "tor+tls" => Self::Tor(Some("tls".into())),
             "nym" => Self::Nym(None),
             "nym+tls" => Self::Nym(Some("tls".into())),
+            "memory" => Self::Memory(None),
+            "memory+tls" => Self::Memory(Some("tls".into())),
             "unix" => Self::Unix,
             n => return Err(crate::Error::UnsupportedTransport(n.into())),
         };
@@ -103,7 +132,9 @@ impl TryFrom<Url> for TransportName {
 /// The `Transport` trait serves as a base for implementing transport protocols.
 /// Base transports can optionally be upgraded with TLS in order to support encryption.
 /// The implementation of our TLS authentication can be found in the
-/// [`upgrade_tls`](TlsUpgrade) module.
+/// [`upgrade_tls`](TlsUpgrade) module. The caller provides the [`TlsUpgrade`]
+/// so it can decide which identity is presented and how the remote key
+/// gets verified.
 pub trait Transport {
     type Acceptor;
     type Connector;
@@ -118,7 +149,11 @@ pub trait Transport {
     where
         Self: Sized;
 
-    fn upgrade_listener(self, acceptor: Self::Acceptor) -> Result<Self::TlsListener>
+    fn upgrade_listener(
+        self,
+        tls: TlsUpgrade,
+        acceptor: Self::Acceptor,
+    ) -> Result<Self::TlsListener>
     where
         Self: Sized;
 
@@ -126,7 +161,7 @@ pub trait Transport {
     where
         Self: Sized;
 
-    fn upgrade_dialer(self, stream: Self::Connector) -> Result<Self::TlsDialer>
+    fn upgrade_dialer(self, tls: TlsUpgrade, stream: Self::Connector) -> Result<Self::TlsDialer>
     where
         Self: Sized;
 }
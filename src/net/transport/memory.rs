@@ -0,0 +1,623 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    collections::{HashMap, HashSet},
+    io,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    time::Duration,
+};
+
+use async_trait::async_trait;
+use futures::prelude::*;
+use futures_rustls::{TlsAcceptor, TlsStream};
+use lazy_static::lazy_static;
+use log::debug;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use smol::channel::{Receiver, Sender};
+use url::Url;
+
+use super::{TlsUpgrade, Transport, TransportListener, TransportStream};
+use crate::{Error, Result};
+
+lazy_static! {
+    /// Simulated networks, by name.
+    static ref MEMORY_NETWORKS: Mutex<HashMap<String, Arc<MemoryNetwork>>> =
+        Mutex::new(HashMap::new());
+}
+
+/// Name of the network used by hosts without a dot.
+const DEFAULT_NETWORK: &str = "default";
+
+/// Split the host of a `memory://` URL into its node and network names.
+/// `memory://alice.sim:1` is node `alice` on network `sim`.
+fn split_host(url: &Url) -> Result<(String, String)> {
+    let Some(host) = url.host_str() else {
+        return Err(Error::MemoryTransportError(format!("Missing host in {}", url)))
+    };
+
+    Ok(match host.rsplit_once('.') {
+        Some((node, network)) => (node.to_string(), network.to_string()),
+        None => (host.to_string(), DEFAULT_NETWORK.to_string()),
+    })
+}
+
+/// Conditions of the links of a simulated network.
+#[derive(Clone, Debug, Default)]
+pub struct LinkConditions {
+    /// Simulated time it takes for written data to arrive, and for dials
+    /// to connect
+    pub latency: Duration,
+    /// Probability of a dial or a written chunk getting lost. Streams are
+    /// reliable, so a lost chunk is retransmitted after another round trip,
+    /// while a lost dial fails.
+    pub loss: f64,
+}
+
+/// Link between two simulated nodes, kept so partitions can cut it.
+struct MemoryLink {
+    nodes: (String, String),
+    senders: (Sender<Chunk>, Sender<Chunk>),
+}
+
+impl MemoryLink {
+    fn is_closed(&self) -> bool {
+        self.senders.0.is_closed() && self.senders.1.is_closed()
+    }
+
+    fn close(&self) {
+        self.senders.0.close();
+        self.senders.1.close();
+    }
+}
+
+struct MemoryNetworkState {
+    conditions: LinkConditions,
+    listeners: HashMap<(String, u16), Sender<(MemoryStream, Url)>>,
+    links: Vec<MemoryLink>,
+    /// Groups of nodes which can only reach nodes in the same group.
+    /// Nodes in no group can reach everyone.
+    partitions: Vec<HashSet<String>>,
+    rng: StdRng,
+    next_port: u16,
+    /// Simulated time since the network was created
+    now: Duration,
+    /// Pending timers, by id, with their deadline
+    timers: HashMap<u64, (Duration, Waker)>,
+    next_timer: u64,
+}
+
+/// In-process simulated network, connecting [`MemoryTransport`] streams
+/// with configurable latency, loss and partitions. Loss decisions come
+/// from a seeded RNG, so a network seeded the same way makes the same
+/// decisions on every run.
+///
+/// Latency is measured on a simulated clock, which only moves when
+/// [`advance`](Self::advance) is called. Without latency, data and dials
+/// go through right away.
+pub struct MemoryNetwork {
+    name: String,
+    state: Mutex<MemoryNetworkState>,
+}
+
+impl MemoryNetwork {
+    /// Return the network with the given name, creating it if needed.
+    pub fn get(name: &str) -> Arc<Self> {
+        MEMORY_NETWORKS
+            .lock()
+            .unwrap()
+            .entry(name.to_string())
+            .or_insert_with(|| {
+                Arc::new(Self {
+                    name: name.to_string(),
+                    state: Mutex::new(MemoryNetworkState {
+                        conditions: LinkConditions::default(),
+                        listeners: HashMap::new(),
+                        links: vec![],
+                        partitions: vec![],
+                        rng: StdRng::seed_from_u64(0),
+                        next_port: 49152,
+                        now: Duration::ZERO,
+                        timers: HashMap::new(),
+                        next_timer: 0,
+                    }),
+                })
+            })
+            .clone()
+    }
+
+    /// Remove the network with the given name. Existing streams stay
+    /// usable, but new dials and listeners get a fresh network.
+    pub fn remove(name: &str) {
+        MEMORY_NETWORKS.lock().unwrap().remove(name);
+    }
+
+    /// Return the name of the network, used as last label of its hosts.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Set the conditions applied to all links of the network.
+    pub fn set_conditions(&self, conditions: LinkConditions) {
+        self.state.lock().unwrap().conditions = conditions;
+    }
+
+    /// Current simulated time of the network.
+    pub fn now(&self) -> Duration {
+        self.state.lock().unwrap().now
+    }
+
+    /// Move the simulated clock forward, delivering the data and dials
+    /// due by then.
+    pub fn advance(&self, by: Duration) {
+        let mut due = vec![];
+        {
+            let mut state = self.state.lock().unwrap();
+            state.now += by;
+            let now = state.now;
+            state.timers.retain(|_, (deadline, waker)| {
+                if *deadline > now {
+                    return true
+                }
+                due.push(waker.clone());
+                false
+            });
+        }
+
+        for waker in due {
+            waker.wake();
+        }
+    }
+
+    /// Wait until the simulated clock reaches `deadline`.
+    fn sleep_until(self: &Arc<Self>, deadline: Duration) -> MemoryTimer {
+        MemoryTimer { network: self.clone(), deadline, id: None }
+    }
+
+    /// Reseed the RNG deciding which dials and chunks get lost.
+    pub fn set_seed(&self, seed: u64) {
+        self.state.lock().unwrap().rng = StdRng::seed_from_u64(seed);
+    }
+
+    /// Split the nodes into groups which can't reach each other. Links
+    /// crossing groups get cut, and dials across them fail.
+    pub fn partition(&self, groups: &[&[&str]]) {
+        let mut state = self.state.lock().unwrap();
+        state.partitions =
+            groups.iter().map(|g| g.iter().map(|n| n.to_string()).collect()).collect();
+
+        let partitions = state.partitions.clone();
+        state.links.retain(|link| {
+            if is_reachable(&partitions, &link.nodes.0, &link.nodes.1) {
+                return true
+            }
+            debug!(target: "net::memory", "Cutting link {} <-> {}", link.nodes.0, link.nodes.1);
+            link.close();
+            false
+        });
+    }
+
+    /// Remove all partitions.
+    pub fn heal(&self) {
+        self.state.lock().unwrap().partitions.clear();
+    }
+
+    /// Check if a listener accepts connections on the given node and port.
+    pub fn is_listening(&self, node: &str, port: u16) -> bool {
+        let state = self.state.lock().unwrap();
+        state.listeners.get(&(node.to_string(), port)).map_or(false, |l| !l.is_closed())
+    }
+
+    /// Number of open links between nodes of the network.
+    pub fn link_count(&self) -> usize {
+        let mut state = self.state.lock().unwrap();
+        state.links.retain(|link| !link.is_closed());
+        state.links.len()
+    }
+
+    fn listen(self: &Arc<Self>, node: String, port: u16) -> Result<MemoryListener> {
+        let mut state = self.state.lock().unwrap();
+        let key = (node, port);
+        if state.listeners.get(&key).map_or(false, |l| !l.is_closed()) {
+            return Err(Error::MemoryTransportError(format!(
+                "Address {}.{}:{} already in use",
+                key.0, self.name, key.1
+            )))
+        }
+
+        let (sender, receiver) = smol::channel::unbounded();
+        state.listeners.insert(key.clone(), sender);
+        Ok(MemoryListener { network: self.clone(), addr: key, incoming: receiver })
+    }
+
+    async fn dial(
+        self: &Arc<Self>,
+        local: Option<String>,
+        node: String,
+        port: u16,
+    ) -> Result<MemoryStream> {
+        let (connected, lost) = {
+            let mut state = self.state.lock().unwrap();
+            let loss = state.conditions.loss;
+            (state.now + state.conditions.latency * 2, state.rng.gen_bool(loss.clamp(0.0, 1.0)))
+        };
+
+        // Connecting takes a round trip
+        self.sleep_until(connected).await;
+
+        let mut state = self.state.lock().unwrap();
+
+        let local = match local {
+            Some(local) => local,
+            None => format!("anon{}", state.next_port),
+        };
+
+        let unreachable = || {
+            Error::MemoryTransportError(format!("{}.{}:{} is unreachable", node, self.name, port))
+        };
+
+        if lost || !is_reachable(&state.partitions, &local, &node) {
+            return Err(unreachable())
+        }
+
+        let Some(listener) = state.listeners.get(&(node.clone(), port)).cloned() else {
+            return Err(Error::MemoryTransportError(format!(
+                "Connection to {}.{}:{} refused",
+                node, self.name, port
+            )))
+        };
+
+        let (dialer_send, acceptor_recv) = smol::channel::unbounded();
+        let (acceptor_send, dialer_recv) = smol::channel::unbounded();
+
+        let now = state.now;
+        let dialer = MemoryStream::new(self.clone(), dialer_send.clone(), dialer_recv, now);
+        let acceptor = MemoryStream::new(self.clone(), acceptor_send.clone(), acceptor_recv, now);
+
+        // The dialer gets an ephemeral port, like over TCP
+        let ephemeral = state.next_port;
+        state.next_port = state.next_port.checked_add(1).unwrap_or(49152);
+        let dialer_url = Url::parse(&format!("memory://{}.{}:{}", local, self.name, ephemeral))?;
+
+        if listener.try_send((acceptor, dialer_url)).is_err() {
+            return Err(unreachable())
+        }
+
+        state.links.retain(|link| !link.is_closed());
+        state
+            .links
+            .push(MemoryLink { nodes: (local, node), senders: (dialer_send, acceptor_send) });
+
+        Ok(dialer)
+    }
+
+    /// Compute when a chunk written now arrives, given when the previous
+    /// chunk of the stream arrives, so chunks never overtake each other.
+    fn delivery_time(&self, previous: Duration) -> Duration {
+        let mut state = self.state.lock().unwrap();
+        let latency = state.conditions.latency;
+        let loss = state.conditions.loss.clamp(0.0, 1.0);
+
+        // Every loss costs another round trip until the chunk makes it.
+        // Total loss would never deliver, so cap the retransmissions.
+        let mut delay = latency;
+        for _ in 0..16 {
+            if !state.rng.gen_bool(loss) {
+                break
+            }
+            delay += latency * 2;
+        }
+
+        previous.max(state.now + delay)
+    }
+}
+
+/// Future waiting for the simulated clock of a network to reach a deadline.
+struct MemoryTimer {
+    network: Arc<MemoryNetwork>,
+    deadline: Duration,
+    id: Option<u64>,
+}
+
+impl Future for MemoryTimer {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let network = self.network.clone();
+        let mut state = network.state.lock().unwrap();
+        if state.now >= self.deadline {
+            if let Some(id) = self.id.take() {
+                state.timers.remove(&id);
+            }
+            return Poll::Ready(())
+        }
+
+        let id = match self.id {
+            Some(id) => id,
+            None => {
+                let id = state.next_timer;
+                state.next_timer += 1;
+                self.id = Some(id);
+                id
+            }
+        };
+        state.timers.insert(id, (self.deadline, cx.waker().clone()));
+        Poll::Pending
+    }
+}
+
+impl Drop for MemoryTimer {
+    fn drop(&mut self) {
+        if let Some(id) = self.id {
+            self.network.state.lock().unwrap().timers.remove(&id);
+        }
+    }
+}
+
+/// Check if two nodes are in the same partition, or either isn't in any.
+fn is_reachable(partitions: &[HashSet<String>], a: &str, b: &str) -> bool {
+    let group_a = partitions.iter().position(|g| g.contains(a));
+    let group_b = partitions.iter().position(|g| g.contains(b));
+    match (group_a, group_b) {
+        (Some(a), Some(b)) => a == b,
+        _ => true,
+    }
+}
+
+/// Data written to a stream, along with the simulated time it arrives at.
+type Chunk = (Duration, Vec<u8>);
+
+/// Bidirectional byte stream over a simulated network.
+pub struct MemoryStream {
+    network: Arc<MemoryNetwork>,
+    outgoing: Sender<Chunk>,
+    incoming: Receiver<Chunk>,
+    last_delivery: Duration,
+    delay: Option<MemoryTimer>,
+    read_buf: Vec<u8>,
+    read_pos: usize,
+}
+
+impl MemoryStream {
+    fn new(
+        network: Arc<MemoryNetwork>,
+        outgoing: Sender<Chunk>,
+        incoming: Receiver<Chunk>,
+        now: Duration,
+    ) -> Self {
+        Self {
+            network,
+            outgoing,
+            incoming,
+            last_delivery: now,
+            delay: None,
+            read_buf: vec![],
+            read_pos: 0,
+        }
+    }
+}
+
+impl AsyncRead for MemoryStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.read_pos == self.read_buf.len() {
+            // Wait for the next chunk, then for it to arrive
+            if self.delay.is_none() {
+                match self.incoming.poll_next_unpin(cx) {
+                    Poll::Ready(Some((arrival, data))) => {
+                        self.read_buf = data;
+                        self.read_pos = 0;
+                        self.delay = Some(self.network.sleep_until(arrival));
+                    }
+                    Poll::Ready(None) => return Poll::Ready(Ok(0)),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+
+            match self.delay.as_mut().unwrap().poll_unpin(cx) {
+                Poll::Ready(_) => self.delay = None,
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        let n = std::cmp::min(buf.len(), self.read_buf.len() - self.read_pos);
+        buf[..n].copy_from_slice(&self.read_buf[self.read_pos..self.read_pos + n]);
+        self.read_pos += n;
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl AsyncWrite for MemoryStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let arrival = self.network.delivery_time(self.last_delivery);
+        self.last_delivery = arrival;
+
+        if self.outgoing.try_send((arrival, buf.to_vec())).is_err() {
+            return Poll::Ready(Err(io::Error::new(io::ErrorKind::BrokenPipe, "Link closed")))
+        }
+
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        self.outgoing.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Drop for MemoryStream {
+    fn drop(&mut self) {
+        // Let the remote read EOF once it drained what we wrote
+        self.outgoing.close();
+        self.incoming.close();
+    }
+}
+
+impl TransportStream for MemoryStream {}
+
+/// Listener accepting streams dialed on a simulated network.
+pub struct MemoryListener {
+    network: Arc<MemoryNetwork>,
+    addr: (String, u16),
+    incoming: Receiver<(MemoryStream, Url)>,
+}
+
+impl MemoryListener {
+    pub async fn accept(&self) -> Result<(MemoryStream, Url)> {
+        Ok(self.incoming.recv().await?)
+    }
+}
+
+impl Drop for MemoryListener {
+    fn drop(&mut self) {
+        // Further dials get refused
+        self.incoming.close();
+        let mut state = self.network.state.lock().unwrap();
+        if state.listeners.get(&self.addr).map_or(false, |l| l.is_closed()) {
+            state.listeners.remove(&self.addr);
+        }
+    }
+}
+
+#[async_trait]
+impl TransportListener for MemoryListener {
+    async fn next(&self) -> Result<(Box<dyn TransportStream>, Url)> {
+        let (stream, url) = self.accept().await?;
+        Ok((Box::new(stream), url))
+    }
+}
+
+#[async_trait]
+impl TransportListener for (TlsAcceptor, MemoryListener) {
+    async fn next(&self) -> Result<(Box<dyn TransportStream>, Url)> {
+        let (stream, mut url) = self.1.accept().await?;
+        url.set_scheme("memory+tls").unwrap();
+
+        match self.0.accept(stream).await {
+            Ok(stream) => Ok((Box::new(TlsStream::Server(stream)), url)),
+            Err(_) => Err(Error::AcceptTlsConnectionFailed(url.to_string())),
+        }
+    }
+}
+
+/// Implements an in-process simulated transport, for testing nodes
+/// without touching the OS network stack.
+///
+/// Urls look like `memory://<node>.<network>:<port>`, where the network
+/// names the [`MemoryNetwork`] the node lives on. The network sets the
+/// latency, loss and partitions applied to its links. Dialers are known
+/// to the network by the node name passed to the constructor, which is
+/// what partitions are defined with.
+#[derive(Clone)]
+pub struct MemoryTransport {
+    local: Option<String>,
+}
+
+impl MemoryTransport {
+    /// Creates a new MemoryTransport
+    ///
+    /// # Arguments
+    ///
+    /// * `local` - node name of the dialer, usually the node its inbound
+    ///   `memory://` address listens on. Anonymous dialers are not
+    ///   affected by partitions.
+    pub fn new(local: Option<String>) -> Self {
+        Self { local }
+    }
+
+    pub async fn do_dial(self, url: Url) -> Result<MemoryStream> {
+        let (node, network) = split_host(&url)?;
+        let Some(port) = url.port() else {
+            return Err(Error::MemoryTransportError(format!("Missing port in {}", url)))
+        };
+
+        MemoryNetwork::get(&network).dial(self.local, node, port).await
+    }
+
+    pub fn do_listen(self, url: Url) -> Result<MemoryListener> {
+        let (node, network) = split_host(&url)?;
+        let Some(port) = url.port() else {
+            return Err(Error::MemoryTransportError(format!("Missing port in {}", url)))
+        };
+
+        MemoryNetwork::get(&network).listen(node, port)
+    }
+}
+
+impl Transport for MemoryTransport {
+    type Acceptor = MemoryListener;
+    type Connector = MemoryStream;
+
+    type Listener = Pin<Box<dyn Future<Output = Result<Self::Acceptor>> + Send>>;
+    type Dial = Pin<Box<dyn Future<Output = Result<Self::Connector>> + Send>>;
+
+    type TlsListener = Pin<Box<dyn Future<Output = Result<(TlsAcceptor, Self::Acceptor)>> + Send>>;
+    type TlsDialer = Pin<Box<dyn Future<Output = Result<TlsStream<Self::Connector>>> + Send>>;
+
+    fn listen_on(self, url: Url) -> Result<Self::Listener> {
+        match url.scheme() {
+            "memory" | "memory+tls" => {}
+            x => return Err(Error::UnsupportedTransport(x.to_string())),
+        }
+        let listener = self.do_listen(url);
+        Ok(Box::pin(async move { listener }))
+    }
+
+    fn upgrade_listener(
+        self,
+        tls: TlsUpgrade,
+        acceptor: Self::Acceptor,
+    ) -> Result<Self::TlsListener> {
+        Ok(Box::pin(tls.upgrade_listener_tls(acceptor)))
+    }
+
+    fn dial(self, url: Url, timeout: Option<Duration>) -> Result<Self::Dial> {
+        match url.scheme() {
+            "memory" | "memory+tls" => {}
+            x => return Err(Error::UnsupportedTransport(x.to_string())),
+        }
+
+        debug!(target: "net::memory", "{} transport: dialing {}", url.scheme(), url);
+        let dial = self.do_dial(url);
+        match timeout {
+            Some(t) => Ok(Box::pin(async move { Ok(async_std::future::timeout(t, dial).await??) })),
+            None => Ok(Box::pin(dial)),
+        }
+    }
+
+    fn upgrade_dialer(
+        self,
+        tls: TlsUpgrade,
+        connector: Self::Connector,
+    ) -> Result<Self::TlsDialer> {
+        Ok(Box::pin(tls.upgrade_dialer_tls(connector)))
+    }
+}
@@ -0,0 +1,660 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    io,
+    pin::Pin,
+    sync::atomic::{AtomicUsize, Ordering},
+    task::{Context, Poll},
+    time::Duration,
+};
+
+use async_std::{
+    net::TcpStream,
+    sync::{Arc, Mutex},
+};
+use async_trait::async_trait;
+use async_tungstenite::tungstenite::Message as WsMessage;
+use futures::{channel::mpsc, prelude::*, ready, stream::SplitStream};
+use futures_rustls::{TlsAcceptor, TlsStream};
+use lazy_static::lazy_static;
+use log::{debug, error, info, warn};
+use rand::Rng;
+use smol::channel::{Receiver, Sender};
+use url::Url;
+
+use super::{TlsUpgrade, Transport, TransportListener, TransportStream};
+use crate::{Error, Result};
+
+/// Length of a binary Nym recipient: identity key, encryption key and gateway key.
+pub const NYM_ADDRESS_LEN: usize = 96;
+
+// nym-client binary websocket API tags
+const REQUEST_SEND: u8 = 0x00;
+const REQUEST_SELF_ADDRESS: u8 = 0x03;
+const RESPONSE_ERROR: u8 = 0x00;
+const RESPONSE_RECEIVED: u8 = 0x01;
+const RESPONSE_SELF_ADDRESS: u8 = 0x02;
+
+// Frames we multiplex our streams with inside of mixnet messages
+const FRAME_OPEN: u8 = 0x00;
+const FRAME_DATA: u8 = 0x01;
+const FRAME_CLOSE: u8 = 0x02;
+
+/// Set on connection ids owned by the accepting side, so a client talking
+/// to itself doesn't mix up both ends of a stream.
+const ACCEPTOR_BIT: u64 = 1 << 63;
+
+/// How far ahead of the next expected frame a data frame may be sequenced.
+/// Frames beyond the window close the stream.
+const MAX_WINDOW: u64 = 1024;
+
+/// Most bytes of data buffered per stream, out of order or waiting to be
+/// read, above which the stream is closed.
+const MAX_BUFFERED_BYTES: usize = 4 * 1024 * 1024;
+
+/// Most requests queued for nym-client, above which writers wait.
+const MAX_OUTGOING: usize = 1024;
+
+/// Most inbound streams waiting to be accepted, above which new ones
+/// are refused.
+const MAX_PENDING_ACCEPTS: usize = 64;
+
+/// Most concurrent streams per nym-client.
+const MAX_STREAMS: usize = 256;
+
+lazy_static! {
+    /// Connections to nym-client websockets, shared by all dialers and
+    /// listeners using the same client, since it only serves one at a time.
+    static ref NYM_CLIENTS: Mutex<HashMap<Url, Arc<NymClient>>> = Mutex::new(HashMap::new());
+}
+
+/// Binary Nym mixnet address.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NymAddress(pub [u8; NYM_ADDRESS_LEN]);
+
+impl NymAddress {
+    /// Parse a Nym address from the host part of a `nym://` URL. The host holds
+    /// the usual `<identity>.<encryption>@<gateway>` recipient, with the `@`
+    /// replaced by a `.` so the whole recipient fits in the URL host.
+    pub fn from_host(host: &str) -> Result<Self> {
+        let parts: Vec<&str> = host.split('.').collect();
+        if parts.len() != 3 {
+            return Err(Error::NymError(format!("Invalid Nym address: {}", host)))
+        }
+
+        let mut addr = [0u8; NYM_ADDRESS_LEN];
+        for (i, part) in parts.iter().enumerate() {
+            let bytes = bs58::decode(part).into_vec()?;
+            if bytes.len() != 32 {
+                return Err(Error::NymError(format!("Invalid Nym address: {}", host)))
+            }
+            addr[i * 32..(i + 1) * 32].copy_from_slice(&bytes);
+        }
+
+        Ok(Self(addr))
+    }
+
+    /// Encode the address into its URL host form.
+    pub fn to_host(&self) -> String {
+        let parts: Vec<String> =
+            self.0.chunks(32).map(|chunk| bs58::encode(chunk).into_string()).collect();
+        parts.join(".")
+    }
+}
+
+/// Per-stream receiving state. Mixnet messages can arrive out of order,
+/// so data frames are sequenced and buffered until they can be delivered.
+struct NymConnection {
+    sender: Sender<Vec<u8>>,
+    next_seq: u64,
+    pending: BTreeMap<u64, Vec<u8>>,
+    /// Total size of the frames in `pending`
+    pending_bytes: usize,
+    /// Total size of the frames delivered but not yet read by the stream
+    queued_bytes: Arc<AtomicUsize>,
+}
+
+/// Shared connection to a local nym-client websocket.
+struct NymClient {
+    self_address: NymAddress,
+    outgoing: mpsc::Sender<Vec<u8>>,
+    connections: std::sync::Mutex<HashMap<u64, NymConnection>>,
+    accepted: (Sender<NymStream>, Receiver<NymStream>),
+}
+
+impl NymClient {
+    /// Return the client connected to the given nym-client websocket,
+    /// connecting to it if needed.
+    async fn get(client_url: &Url) -> Result<Arc<Self>> {
+        let mut clients = NYM_CLIENTS.lock().await;
+        if let Some(client) = clients.get(client_url) {
+            return Ok(client.clone())
+        }
+
+        let client = Self::connect(client_url).await?;
+        clients.insert(client_url.clone(), client.clone());
+        Ok(client)
+    }
+
+    async fn connect(client_url: &Url) -> Result<Arc<Self>> {
+        let socket_addr = client_url.socket_addrs(|| None)?[0];
+        debug!(target: "net::nym", "Connecting to nym-client at {}", client_url);
+
+        let stream = TcpStream::connect(socket_addr).await?;
+        let (ws, _) = async_tungstenite::client_async(client_url.as_str(), stream).await?;
+        let (mut ws_writer, mut ws_reader) = ws.split();
+
+        // Ask the client for our own address, which remote peers reply to.
+        ws_writer.send(WsMessage::Binary(vec![REQUEST_SELF_ADDRESS])).await?;
+        let self_address = loop {
+            match ws_reader.next().await {
+                Some(Ok(WsMessage::Binary(msg)))
+                    if msg.len() == 1 + NYM_ADDRESS_LEN && msg[0] == RESPONSE_SELF_ADDRESS =>
+                {
+                    let mut addr = [0u8; NYM_ADDRESS_LEN];
+                    addr.copy_from_slice(&msg[1..]);
+                    break NymAddress(addr)
+                }
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(e.into()),
+                None => return Err(Error::NymError("nym-client closed the connection".into())),
+            }
+        };
+        info!(target: "net::nym", "Nym self address: {}", self_address.to_host());
+
+        let (outgoing, mut outgoing_recv) = mpsc::channel::<Vec<u8>>(MAX_OUTGOING);
+        let client = Arc::new(Self {
+            self_address,
+            outgoing,
+            connections: std::sync::Mutex::new(HashMap::new()),
+            accepted: smol::channel::bounded(MAX_PENDING_ACCEPTS),
+        });
+
+        smol::spawn(async move {
+            while let Some(msg) = outgoing_recv.next().await {
+                if let Err(e) = ws_writer.send(WsMessage::Binary(msg)).await {
+                    error!(target: "net::nym", "Failed writing to nym-client: {}", e);
+                    break
+                }
+            }
+        })
+        .detach();
+
+        smol::spawn(client.clone().read_loop(client_url.clone(), ws_reader)).detach();
+
+        Ok(client)
+    }
+
+    /// Read mixnet messages from nym-client and dispatch them to their streams.
+    async fn read_loop(
+        self: Arc<Self>,
+        client_url: Url,
+        mut ws_reader: SplitStream<async_tungstenite::WebSocketStream<TcpStream>>,
+    ) {
+        while let Some(msg) = ws_reader.next().await {
+            let msg = match msg {
+                Ok(WsMessage::Binary(msg)) => msg,
+                Ok(WsMessage::Close(_)) => break,
+                Ok(_) => continue,
+                Err(e) => {
+                    error!(target: "net::nym", "Failed reading from nym-client: {}", e);
+                    break
+                }
+            };
+
+            match msg.first() {
+                Some(&RESPONSE_RECEIVED) => {
+                    // tag | has sender tag | message length | message
+                    if msg.len() < 10 || msg[1] != 0 {
+                        warn!(target: "net::nym", "Unsupported received message from nym-client");
+                        continue
+                    }
+                    let len = u64::from_be_bytes(msg[2..10].try_into().unwrap()) as usize;
+                    if msg.len() != 10 + len {
+                        warn!(target: "net::nym", "Malformed received message from nym-client");
+                        continue
+                    }
+                    self.dispatch(&msg[10..]);
+                }
+                Some(&RESPONSE_ERROR) => {
+                    error!(target: "net::nym", "nym-client error: {}", String::from_utf8_lossy(&msg[1..]));
+                }
+                _ => {}
+            }
+        }
+
+        // The client is gone, so close all of its streams and let the
+        // next dial reconnect.
+        warn!(target: "net::nym", "Disconnected from nym-client at {}", client_url);
+        self.connections.lock().unwrap().clear();
+        self.accepted.0.close();
+        NYM_CLIENTS.lock().await.remove(&client_url);
+    }
+
+    /// Handle a frame received through the mixnet.
+    fn dispatch(self: &Arc<Self>, frame: &[u8]) {
+        if frame.len() < 9 {
+            warn!(target: "net::nym", "Received truncated frame");
+            return
+        }
+
+        let kind = frame[0];
+        let conn_id = u64::from_be_bytes(frame[1..9].try_into().unwrap());
+        let body = &frame[9..];
+
+        match kind {
+            FRAME_OPEN => {
+                if body.len() != NYM_ADDRESS_LEN {
+                    warn!(target: "net::nym", "Received malformed open frame");
+                    return
+                }
+                let mut addr = [0u8; NYM_ADDRESS_LEN];
+                addr.copy_from_slice(body);
+
+                let stream =
+                    match self.new_stream(conn_id, conn_id & !ACCEPTOR_BIT, NymAddress(addr)) {
+                        Ok(stream) => stream,
+                        Err(e) => {
+                            warn!(target: "net::nym", "Refusing connection {}: {}", conn_id, e);
+                            return
+                        }
+                    };
+
+                if self.accepted.0.try_send(stream).is_err() {
+                    warn!(target: "net::nym", "Refusing connection {}: accept queue full", conn_id);
+                    self.connections.lock().unwrap().remove(&conn_id);
+                }
+            }
+            FRAME_DATA => {
+                if body.len() < 8 {
+                    warn!(target: "net::nym", "Received malformed data frame");
+                    return
+                }
+                let seq = u64::from_be_bytes(body[..8].try_into().unwrap());
+
+                let mut connections = self.connections.lock().unwrap();
+                let Some(conn) = connections.get_mut(&conn_id) else {
+                    debug!(target: "net::nym", "Data for unknown connection {}", conn_id);
+                    return
+                };
+
+                if seq < conn.next_seq || conn.pending.contains_key(&seq) {
+                    return
+                }
+
+                // A peer that doesn't respect the window, or sends more than
+                // we're willing to buffer, gets its stream closed.
+                let data = &body[8..];
+                let buffered = conn.pending_bytes + conn.queued_bytes.load(Ordering::Relaxed);
+                if seq - conn.next_seq >= MAX_WINDOW || buffered + data.len() > MAX_BUFFERED_BYTES {
+                    warn!(
+                        target: "net::nym",
+                        "Connection {} overflowed its receive buffer", conn_id
+                    );
+                    connections.remove(&conn_id);
+                    return
+                }
+                conn.pending.insert(seq, data.to_vec());
+                conn.pending_bytes += data.len();
+
+                while let Some(data) = conn.pending.remove(&conn.next_seq) {
+                    conn.next_seq += 1;
+                    conn.pending_bytes -= data.len();
+                    conn.queued_bytes.fetch_add(data.len(), Ordering::Relaxed);
+                    // Either the stream was dropped, or it doesn't keep up
+                    // with the frames the peer sends.
+                    if conn.sender.try_send(data).is_err() {
+                        connections.remove(&conn_id);
+                        return
+                    }
+                }
+            }
+            FRAME_CLOSE => {
+                // Dropping the sender makes the stream read EOF
+                self.connections.lock().unwrap().remove(&conn_id);
+            }
+            _ => warn!(target: "net::nym", "Received unknown frame kind {}", kind),
+        }
+    }
+
+    /// Register a new stream talking to `peer`. Frames are received with
+    /// `local_id` and sent with `remote_id`, the id the peer knows it by.
+    fn new_stream(
+        self: &Arc<Self>,
+        local_id: u64,
+        remote_id: u64,
+        peer: NymAddress,
+    ) -> Result<NymStream> {
+        let mut connections = self.connections.lock().unwrap();
+        if connections.len() >= MAX_STREAMS {
+            return Err(Error::NymError("Too many streams".into()))
+        }
+
+        let (sender, receiver) = smol::channel::bounded(MAX_WINDOW as usize);
+        let queued_bytes = Arc::new(AtomicUsize::new(0));
+        let conn = NymConnection {
+            sender,
+            next_seq: 0,
+            pending: BTreeMap::new(),
+            pending_bytes: 0,
+            queued_bytes: queued_bytes.clone(),
+        };
+        connections.insert(local_id, conn);
+
+        Ok(NymStream {
+            remote_id,
+            peer,
+            outgoing: self.outgoing.clone(),
+            incoming: receiver,
+            queued_bytes,
+            read_buf: vec![],
+            read_pos: 0,
+            next_seq: 0,
+        })
+    }
+
+    /// Wrap a frame into a nym-client send request addressed to `peer`.
+    fn send_request(peer: &NymAddress, conn_id: u64, frame: Vec<u8>) -> Vec<u8> {
+        let mut req = Vec::with_capacity(1 + NYM_ADDRESS_LEN + 16 + frame.len());
+        req.push(REQUEST_SEND);
+        req.extend_from_slice(&peer.0);
+        req.extend_from_slice(&conn_id.to_be_bytes());
+        req.extend_from_slice(&(frame.len() as u64).to_be_bytes());
+        req.extend_from_slice(&frame);
+        req
+    }
+
+    fn frame(kind: u8, conn_id: u64, body: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::with_capacity(9 + body.len());
+        frame.push(kind);
+        frame.extend_from_slice(&conn_id.to_be_bytes());
+        frame.extend_from_slice(body);
+        frame
+    }
+}
+
+/// Bidirectional byte stream multiplexed over the Nym mixnet.
+pub struct NymStream {
+    remote_id: u64,
+    peer: NymAddress,
+    outgoing: mpsc::Sender<Vec<u8>>,
+    incoming: Receiver<Vec<u8>>,
+    queued_bytes: Arc<AtomicUsize>,
+    read_buf: Vec<u8>,
+    read_pos: usize,
+    next_seq: u64,
+}
+
+impl NymStream {
+    fn request(&self, kind: u8, body: &[u8]) -> Vec<u8> {
+        let frame = NymClient::frame(kind, self.remote_id, body);
+        NymClient::send_request(&self.peer, self.remote_id, frame)
+    }
+
+    /// Queue a frame for nym-client, waiting for room in the queue.
+    fn poll_send_frame(
+        &mut self,
+        cx: &mut Context<'_>,
+        kind: u8,
+        body: &[u8],
+    ) -> Poll<io::Result<()>> {
+        let disconnected = |_| io::Error::new(io::ErrorKind::BrokenPipe, "nym-client disconnected");
+        ready!(self.outgoing.poll_ready(cx)).map_err(disconnected)?;
+        let req = self.request(kind, body);
+        Poll::Ready(self.outgoing.start_send(req).map_err(disconnected))
+    }
+}
+
+impl AsyncRead for NymStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.read_pos == self.read_buf.len() {
+            match self.incoming.poll_next_unpin(cx) {
+                Poll::Ready(Some(data)) => {
+                    self.queued_bytes.fetch_sub(data.len(), Ordering::Relaxed);
+                    self.read_buf = data;
+                    self.read_pos = 0;
+                }
+                Poll::Ready(None) => return Poll::Ready(Ok(0)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        let n = std::cmp::min(buf.len(), self.read_buf.len() - self.read_pos);
+        buf[..n].copy_from_slice(&self.read_buf[self.read_pos..self.read_pos + n]);
+        self.read_pos += n;
+        Poll::Ready(Ok(n))
+    }
+}
+
+impl AsyncWrite for NymStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let mut body = Vec::with_capacity(8 + buf.len());
+        body.extend_from_slice(&self.next_seq.to_be_bytes());
+        body.extend_from_slice(buf);
+        ready!(self.poll_send_frame(cx, FRAME_DATA, &body))?;
+        self.next_seq += 1;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // If nym-client is gone there's no one left to tell
+        let _ = ready!(self.poll_send_frame(cx, FRAME_CLOSE, &[]));
+        self.incoming.close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl TransportStream for NymStream {}
+
+/// Accepts streams opened to our Nym address.
+pub struct NymListener {
+    client: Arc<NymClient>,
+}
+
+impl NymListener {
+    /// Return the Nym address remote peers can dial us on.
+    pub fn self_address(&self) -> NymAddress {
+        self.client.self_address
+    }
+
+    pub async fn accept(&self) -> Result<NymStream> {
+        Ok(self.client.accepted.1.recv().await?)
+    }
+}
+
+fn nym_url(addr: &NymAddress, scheme: &str) -> Result<Url> {
+    Ok(Url::parse(&format!("{}://{}", scheme, addr.to_host()))?)
+}
+
+#[async_trait]
+impl TransportListener for NymListener {
+    async fn next(&self) -> Result<(Box<dyn TransportStream>, Url)> {
+        let stream = match self.accept().await {
+            Ok(s) => s,
+            Err(err) => {
+                error!(target: "net::nym", "Error listening for connections: {}", err);
+                return Err(Error::AcceptConnectionFailed(self.self_address().to_host()))
+            }
+        };
+        let url = nym_url(&stream.peer, "nym")?;
+        Ok((Box::new(stream), url))
+    }
+}
+
+#[async_trait]
+impl TransportListener for (TlsAcceptor, NymListener) {
+    async fn next(&self) -> Result<(Box<dyn TransportStream>, Url)> {
+        let stream = match self.1.accept().await {
+            Ok(s) => s,
+            Err(err) => {
+                error!(target: "net::nym", "Error listening for connections: {}", err);
+                return Err(Error::AcceptConnectionFailed(self.1.self_address().to_host()))
+            }
+        };
+
+        let url = nym_url(&stream.peer, "nym+tls")?;
+        let stream = self.0.accept(stream).await;
+
+        if let Err(err) = stream {
+            error!(target: "net::nym", "Error wrapping the connection {} with tls: {}", url, err);
+            return Err(Error::AcceptTlsConnectionFailed(self.1.self_address().to_host()))
+        }
+
+        Ok((Box::new(TlsStream::Server(stream?)), url))
+    }
+}
+
+/// Implements communication through the Nym mixnet, using a local
+/// nym-client over its websocket interface.
+///
+/// ## Dialing
+///
+/// The nym-client must be running for dialing to work, and its websocket
+/// Url has to be passed to the constructor. Dialed Urls carry the remote
+/// recipient in their host, as `nym://<identity>.<encryption>.<gateway>`.
+///
+/// ## Listening
+///
+/// The Url passed to [listen_on][transportlisten] is the websocket address
+/// of the local nym-client. Inbound streams arrive at the client's own Nym
+/// address, which gets logged and should be advertised as external address.
+///
+/// Since the mixnet is message based and unordered, streams are multiplexed
+/// over it with sequenced frames and reassembled on arrival.
+///
+/// [transportlisten]: Transport
+#[derive(Clone)]
+pub struct NymTransport {
+    client_url: Url,
+}
+
+impl NymTransport {
+    /// Creates a new NymTransport
+    ///
+    /// # Arguments
+    ///
+    /// * `client_url` - url of the nym-client websocket. For example ws://127.0.0.1:1977
+    pub fn new(client_url: Url) -> Self {
+        Self { client_url }
+    }
+
+    /// Query the environment for the nym-client websocket Url, or fallback to default
+    pub fn get_client_env() -> Result<Url> {
+        Ok(Url::parse(
+            &std::env::var("DARKFI_NYM_CLIENT_URL")
+                .unwrap_or_else(|_| "ws://127.0.0.1:1977".to_string()),
+        )?)
+    }
+
+    pub async fn do_dial(self, url: Url) -> Result<NymStream> {
+        let Some(host) = url.host_str() else {
+            return Err(Error::NymError(format!("Missing Nym address in {}", url)))
+        };
+        let peer = NymAddress::from_host(host)?;
+
+        let client = NymClient::get(&self.client_url).await?;
+        let conn_id = rand::thread_rng().gen::<u64>() & !ACCEPTOR_BIT;
+        let mut stream = client.new_stream(conn_id, conn_id | ACCEPTOR_BIT, peer)?;
+        let req = stream.request(FRAME_OPEN, &client.self_address.0);
+        if stream.outgoing.send(req).await.is_err() {
+            return Err(Error::NymError("nym-client disconnected".into()))
+        }
+
+        Ok(stream)
+    }
+
+    pub async fn do_listen(self, url: Url) -> Result<NymListener> {
+        let client_url = Url::parse(&format!(
+            "ws://{}:{}",
+            url.host_str().unwrap_or("127.0.0.1"),
+            url.port().unwrap_or(1977)
+        ))?;
+
+        let client = NymClient::get(&client_url).await?;
+        info!(
+            target: "net::nym",
+            "Listening on Nym address: {}",
+            nym_url(&client.self_address, url.scheme())?
+        );
+
+        Ok(NymListener { client })
+    }
+}
+
+impl Transport for NymTransport {
+    type Acceptor = NymListener;
+    type Connector = NymStream;
+
+    type Listener = Pin<Box<dyn Future<Output = Result<Self::Acceptor>> + Send>>;
+    type Dial = Pin<Box<dyn Future<Output = Result<Self::Connector>> + Send>>;
+
+    type TlsListener = Pin<Box<dyn Future<Output = Result<(TlsAcceptor, Self::Acceptor)>> + Send>>;
+    type TlsDialer = Pin<Box<dyn Future<Output = Result<TlsStream<Self::Connector>>> + Send>>;
+
+    fn listen_on(self, url: Url) -> Result<Self::Listener> {
+        match url.scheme() {
+            "nym" | "nym+tls" => {}
+            x => return Err(Error::UnsupportedTransport(x.to_string())),
+        }
+        Ok(Box::pin(self.do_listen(url)))
+    }
+
+    fn upgrade_listener(
+        self,
+        tls: TlsUpgrade,
+        acceptor: Self::Acceptor,
+    ) -> Result<Self::TlsListener> {
+        Ok(Box::pin(tls.upgrade_listener_tls(acceptor)))
+    }
+
+    fn dial(self, url: Url, timeout: Option<Duration>) -> Result<Self::Dial> {
+        match url.scheme() {
+            "nym" | "nym+tls" => {}
+            x => return Err(Error::UnsupportedTransport(x.to_string())),
+        }
+
+        debug!(target: "net::nym", "{} transport: dialing {}", url.scheme(), url);
+        let dial = self.do_dial(url);
+        match timeout {
+            Some(t) => Ok(Box::pin(async move { Ok(async_std::future::timeout(t, dial).await??) })),
+            None => Ok(Box::pin(dial)),
+        }
+    }
+
+    fn upgrade_dialer(
+        self,
+        tls: TlsUpgrade,
+        connector: Self::Connector,
+    ) -> Result<Self::TlsDialer> {
+        Ok(Box::pin(tls.upgrade_dialer_tls(connector)))
+    }
+}
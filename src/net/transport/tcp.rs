@@ -20,17 +20,26 @@ use async_std::net::{TcpListener, TcpStream};
 use std::{io, net::SocketAddr, pin::Pin, time::Duration};
 
 use async_trait::async_trait;
+use ed25519_compact::PublicKey;
 use futures::prelude::*;
 use futures_rustls::{TlsAcceptor, TlsStream};
 use log::{debug, error};
 use socket2::{Domain, Socket, TcpKeepalive, Type};
 use url::Url;
 
-use super::{socket_addr_to_url, TlsUpgrade, Transport, TransportListener, TransportStream};
+use super::{
+    peer_public_key, socket_addr_to_url, TlsUpgrade, Transport, TransportListener, TransportStream,
+};
 use crate::{Error, Result};
 
 impl TransportStream for TcpStream {}
-impl<T: TransportStream> TransportStream for TlsStream<T> {}
+impl<T: TransportStream> TransportStream for TlsStream<T> {
+    fn peer_key(&self) -> Option<PublicKey> {
+        let (_, conn) = self.get_ref();
+        let certs = conn.peer_certificates()?;
+        peer_public_key(&certs.first()?.0).ok()
+    }
+}
 
 #[async_trait]
 impl TransportListener for TcpListener {
@@ -100,9 +109,12 @@ impl Transport for TcpTransport {
         Ok(Box::pin(self.do_listen(socket_addr)))
     }
 
-    fn upgrade_listener(self, acceptor: Self::Acceptor) -> Result<Self::TlsListener> {
-        let tlsupgrade = TlsUpgrade::new();
-        Ok(Box::pin(tlsupgrade.upgrade_listener_tls(acceptor)))
+    fn upgrade_listener(
+        self,
+        tls: TlsUpgrade,
+        acceptor: Self::Acceptor,
+    ) -> Result<Self::TlsListener> {
+        Ok(Box::pin(tls.upgrade_listener_tls(acceptor)))
     }
 
     fn dial(self, url: Url, timeout: Option<Duration>) -> Result<Self::Dial> {
@@ -116,9 +128,12 @@ impl Transport for TcpTransport {
         Ok(Box::pin(self.do_dial(socket_addr, timeout)))
     }
 
-    fn upgrade_dialer(self, connector: Self::Connector) -> Result<Self::TlsDialer> {
-        let tlsupgrade = TlsUpgrade::new();
-        Ok(Box::pin(tlsupgrade.upgrade_dialer_tls(connector)))
+    fn upgrade_dialer(
+        self,
+        tls: TlsUpgrade,
+        connector: Self::Connector,
+    ) -> Result<Self::TlsDialer> {
+        Ok(Box::pin(tls.upgrade_dialer_tls(connector)))
     }
 }
 
@@ -301,9 +301,12 @@ impl Transport for TorTransport {
         Ok(Box::pin(self.do_listen(url)))
     }
 
-    fn upgrade_listener(self, acceptor: Self::Acceptor) -> Result<Self::TlsListener> {
-        let tlsupgrade = TlsUpgrade::new();
-        Ok(Box::pin(tlsupgrade.upgrade_listener_tls(acceptor)))
+    fn upgrade_listener(
+        self,
+        tls: TlsUpgrade,
+        acceptor: Self::Acceptor,
+    ) -> Result<Self::TlsListener> {
+        Ok(Box::pin(tls.upgrade_listener_tls(acceptor)))
     }
 
     fn dial(self, url: Url, _timeout: Option<Duration>) -> Result<Self::Dial> {
@@ -315,8 +318,11 @@ impl Transport for TorTransport {
         Ok(Box::pin(self.do_dial(url)))
     }
 
-    fn upgrade_dialer(self, connector: Self::Connector) -> Result<Self::TlsDialer> {
-        let tlsupgrade = TlsUpgrade::new();
-        Ok(Box::pin(tlsupgrade.upgrade_dialer_tls(connector)))
+    fn upgrade_dialer(
+        self,
+        tls: TlsUpgrade,
+        connector: Self::Connector,
+    ) -> Result<Self::TlsDialer> {
+        Ok(Box::pin(tls.upgrade_dialer_tls(connector)))
     }
 }
@@ -25,7 +25,7 @@ use futures_rustls::{TlsAcceptor, TlsStream};
 use log::{debug, error};
 use url::Url;
 
-use super::{Transport, TransportListener, TransportStream};
+use super::{TlsUpgrade, Transport, TransportListener, TransportStream};
 use crate::{Error, Result};
 
 fn unix_socket_addr_to_string(addr: std::os::unix::net::SocketAddr) -> String {
@@ -87,7 +87,11 @@ impl Transport for UnixTransport {
         Ok(Box::pin(self.do_listen(socket_addr)))
     }
 
-    fn upgrade_listener(self, _acceptor: Self::Acceptor) -> Result<Self::TlsListener> {
+    fn upgrade_listener(
+        self,
+        _tls: TlsUpgrade,
+        _acceptor: Self::Acceptor,
+    ) -> Result<Self::TlsListener> {
         unimplemented!("TLS not supported for Unix sockets");
     }
 
@@ -103,7 +107,11 @@ impl Transport for UnixTransport {
         Ok(Box::pin(self.do_dial(socket_addr, timeout)))
     }
 
-    fn upgrade_dialer(self, _connector: Self::Connector) -> Result<Self::TlsDialer> {
+    fn upgrade_dialer(
+        self,
+        _tls: TlsUpgrade,
+        _connector: Self::Connector,
+    ) -> Result<Self::TlsDialer> {
         unimplemented!("TLS not supported for Unix sockets");
     }
 }
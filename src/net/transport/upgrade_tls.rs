@@ -16,9 +16,17 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::time::SystemTime;
+use std::{
+    collections::HashMap,
+    fs,
+    io::Write,
+    os::unix::fs::OpenOptionsExt,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
 
-use async_std::{net::TcpListener, sync::Arc};
+use async_std::sync::Arc;
+use ed25519_compact::{KeyPair, PublicKey, Signature};
 use futures::prelude::*;
 use futures_rustls::{
     rustls,
@@ -31,12 +39,19 @@ use futures_rustls::{
     },
     TlsAcceptor, TlsConnector, TlsStream,
 };
+use log::{debug, error, info, warn};
 use rustls_pemfile::pkcs8_private_keys;
+use url::Url;
 
-use crate::Result;
+use crate::{Error, Result};
 
 const CIPHER_SUITE: &str = "TLS13_CHACHA20_POLY1305_SHA256";
 
+/// DER encoding of an ed25519 `SubjectPublicKeyInfo` header, which is
+/// followed by the 32 raw public key bytes.
+const ED25519_SPKI_PREFIX: [u8; 12] =
+    [0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00];
+
 fn cipher_suite() -> rustls::SupportedCipherSuite {
     for suite in rustls::ALL_CIPHER_SUITES {
         let sname = format!("{:?}", suite.suite()).to_lowercase();
@@ -49,18 +64,309 @@ fn cipher_suite() -> rustls::SupportedCipherSuite {
     unreachable!()
 }
 
-struct ServerCertificateVerifier;
+/// Read a single DER TLV from `data`. Returns the tag, the whole element
+/// (header included), its contents, and the remaining bytes.
+fn der_next(data: &[u8]) -> Option<(u8, &[u8], &[u8], &[u8])> {
+    if data.len() < 2 {
+        return None
+    }
+
+    let tag = data[0];
+    let (len, hdr_len) = match data[1] {
+        n if n < 0x80 => (n as usize, 2),
+        n if n > 0x80 && n <= 0x84 => {
+            let nbytes = (n & 0x7f) as usize;
+            if data.len() < 2 + nbytes {
+                return None
+            }
+            let mut len = 0usize;
+            for b in &data[2..2 + nbytes] {
+                len = (len << 8) | *b as usize;
+            }
+            (len, 2 + nbytes)
+        }
+        _ => return None,
+    };
+
+    if data.len() < hdr_len + len {
+        return None
+    }
+
+    let (element, rest) = data.split_at(hdr_len + len);
+    Some((tag, element, &element[hdr_len..], rest))
+}
+
+/// Extract the ed25519 public key from a DER-encoded self-signed certificate
+/// and make sure the certificate signature was made with that same key.
+/// This is the only notion of identity we use: a peer is its key.
+pub fn peer_public_key(cert: &[u8]) -> Result<PublicKey> {
+    let err = |e: &str| Error::TlsIdentityError(e.to_string());
+
+    // Certificate ::= SEQUENCE { tbsCertificate, signatureAlgorithm, signatureValue }
+    let (tag, _, cert, _) = der_next(cert).ok_or_else(|| err("Malformed certificate"))?;
+    if tag != 0x30 {
+        return Err(err("Certificate is not a DER sequence"))
+    }
+
+    let (_, tbs, _, rest) = der_next(cert).ok_or_else(|| err("Malformed tbsCertificate"))?;
+    let (_, _, _, rest) = der_next(rest).ok_or_else(|| err("Malformed signatureAlgorithm"))?;
+    let (tag, _, signature, _) = der_next(rest).ok_or_else(|| err("Malformed signature"))?;
+
+    // BIT STRING with zero unused bits followed by the 64 byte signature
+    if tag != 0x03 || signature.len() != 65 || signature[0] != 0 {
+        return Err(err("Certificate signature is not ed25519"))
+    }
+
+    let pos = tbs
+        .windows(ED25519_SPKI_PREFIX.len())
+        .position(|w| w == ED25519_SPKI_PREFIX)
+        .ok_or_else(|| err("Certificate does not carry an ed25519 key"))?;
+
+    let key_start = pos + ED25519_SPKI_PREFIX.len();
+    if tbs.len() < key_start + 32 {
+        return Err(err("Truncated ed25519 public key"))
+    }
+
+    let public_key = PublicKey::from_slice(&tbs[key_start..key_start + 32])
+        .map_err(|_| err("Invalid ed25519 public key"))?;
+
+    let signature =
+        Signature::from_slice(&signature[1..]).map_err(|_| err("Invalid ed25519 signature"))?;
+
+    if public_key.verify(tbs, &signature).is_err() {
+        return Err(err("Certificate is not self-signed by its key"))
+    }
+
+    Ok(public_key)
+}
+
+/// Parse the peer key pinned in an URL in the form of `tls://<pubkey>@host:port`,
+/// where `<pubkey>` is the hex encoding of the peer's ed25519 public key.
+pub fn pinned_peer_key(url: &Url) -> Result<Option<PublicKey>> {
+    if url.username().is_empty() {
+        return Ok(None)
+    }
+
+    let bytes = hex::decode(url.username())?;
+    match PublicKey::from_slice(&bytes) {
+        Ok(pk) => Ok(Some(pk)),
+        Err(_) => Err(Error::TlsIdentityError(format!("Invalid pinned key in {}", url))),
+    }
+}
+
+/// Persistent ed25519 keypair used as the node's TLS identity.
+/// The certificate is self-signed and its key is what peers authenticate us by.
+#[derive(Clone)]
+pub struct TlsIdentity {
+    keypair: KeyPair,
+    secret_key: rustls::PrivateKey,
+    certificate: rustls::Certificate,
+}
+
+impl TlsIdentity {
+    /// Generate a new random identity.
+    pub fn generate() -> Result<Self> {
+        Self::from_keypair(KeyPair::generate())
+    }
+
+    /// Load the identity keypair from the PEM file at the given path. If the
+    /// file does not exist, a new keypair is generated and written there.
+    pub fn load_or_generate(path: &Path) -> Result<Self> {
+        if path.exists() {
+            debug!(target: "net::tls", "Loading TLS identity from {:?}", path);
+            let pem = fs::read_to_string(path)?;
+            let keypair = KeyPair::from_pem(&pem).map_err(|e| {
+                Error::TlsIdentityError(format!("Failed parsing {:?}: {}", path, e))
+            })?;
+            return Self::from_keypair(keypair)
+        }
+
+        let keypair = KeyPair::generate();
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file =
+            fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(path)?;
+        file.write_all(keypair.to_pem().as_bytes())?;
+
+        let identity = Self::from_keypair(keypair)?;
+        info!(
+            target: "net::tls",
+            "Generated new TLS identity {} in {:?}",
+            hex::encode(identity.public_key().as_ref()),
+            path
+        );
+
+        Ok(identity)
+    }
+
+    fn from_keypair(keypair: KeyPair) -> Result<Self> {
+        let keypair_pem = keypair.to_pem();
+        let secret_key = pkcs8_private_keys(&mut keypair_pem.as_bytes())?;
+        if secret_key.is_empty() {
+            return Err(Error::TlsIdentityError("No PKCS8 key found in keypair".to_string()))
+        }
+        let secret_key = rustls::PrivateKey(secret_key[0].clone());
+
+        let altnames = vec![String::from("dark.fi")];
+        let mut cert_params = rcgen::CertificateParams::new(altnames);
+        cert_params.alg = &rcgen::PKCS_ED25519;
+        cert_params.key_pair = Some(
+            rcgen::KeyPair::from_pem(&keypair_pem)
+                .map_err(|e| Error::TlsIdentityError(e.to_string()))?,
+        );
+
+        let certificate = rcgen::Certificate::from_params(cert_params)
+            .map_err(|e| Error::TlsIdentityError(e.to_string()))?;
+        let certificate =
+            certificate.serialize_der().map_err(|e| Error::TlsIdentityError(e.to_string()))?;
+        let certificate = rustls::Certificate(certificate);
+
+        Ok(Self { keypair, secret_key, certificate })
+    }
+
+    /// Return the public key peers identify us by.
+    pub fn public_key(&self) -> PublicKey {
+        self.keypair.pk
+    }
+}
+
+/// Trust-on-first-use store of peer keys, indexed by `host:port`.
+/// The first key seen for a peer gets recorded, and every subsequent
+/// connection to that peer must present the same key.
+pub struct KnownPeers {
+    path: Option<PathBuf>,
+    peers: std::sync::Mutex<HashMap<String, PublicKey>>,
+}
+
+impl KnownPeers {
+    /// Create a new store, loading previously known peers from the given
+    /// file if it exists. With no path, the store only lives in memory.
+    pub fn new(path: Option<PathBuf>) -> Result<Arc<Self>> {
+        let mut peers = HashMap::new();
+
+        if let Some(p) = &path {
+            if p.exists() {
+                for line in fs::read_to_string(p)?.lines() {
+                    let line = line.trim();
+                    if line.is_empty() || line.starts_with('#') {
+                        continue
+                    }
+
+                    let mut parts = line.split_whitespace();
+                    let (Some(peer), Some(key)) = (parts.next(), parts.next()) else {
+                        warn!(target: "net::tls", "Skipping malformed known peers line: {}", line);
+                        continue
+                    };
+
+                    let pk = hex::decode(key).ok().and_then(|k| PublicKey::from_slice(&k).ok());
+                    match pk {
+                        Some(pk) => {
+                            peers.insert(peer.to_string(), pk);
+                        }
+                        None => {
+                            warn!(target: "net::tls", "Skipping invalid key for peer {}", peer)
+                        }
+                    }
+                }
+            }
+        }
+
+        Ok(Arc::new(Self { path, peers: std::sync::Mutex::new(peers) }))
+    }
+
+    /// Return the key recorded for the given peer, if any.
+    pub fn get(&self, peer: &str) -> Option<PublicKey> {
+        self.peers.lock().unwrap().get(peer).copied()
+    }
+
+    /// Check the key presented by a peer. Unknown peers are recorded and
+    /// accepted, known peers are accepted only if their key didn't change.
+    pub fn verify(&self, peer: &str, key: &PublicKey) -> bool {
+        let mut peers = self.peers.lock().unwrap();
+
+        if let Some(known) = peers.get(peer) {
+            return known == key
+        }
+
+        debug!(target: "net::tls", "Trusting {} on first use for {}", hex::encode(key.as_ref()), peer);
+        peers.insert(peer.to_string(), *key);
+
+        if let Err(e) = self.save(&peers) {
+            error!(target: "net::tls", "Failed saving known peers: {}", e);
+        }
+
+        true
+    }
+
+    fn save(&self, peers: &HashMap<String, PublicKey>) -> Result<()> {
+        let Some(path) = &self.path else { return Ok(()) };
+
+        let mut contents = String::new();
+        for (peer, key) in peers {
+            contents.push_str(&format!("{} {}\n", peer, hex::encode(key.as_ref())));
+        }
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, contents)?;
+        Ok(())
+    }
+}
+
+/// Policy used to authenticate the key presented by a remote peer.
+#[derive(Clone)]
+pub enum PeerVerification {
+    /// Accept any well-formed self-signed certificate
+    Any,
+    /// Require the peer to present exactly this key
+    Pinned(PublicKey),
+    /// Require the key previously recorded for the given `host:port`,
+    /// recording it if this is the first connection.
+    TrustOnFirstUse(Arc<KnownPeers>, String),
+}
+
+impl PeerVerification {
+    fn verify(&self, key: &PublicKey) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Pinned(expected) => expected == key,
+            Self::TrustOnFirstUse(known_peers, peer) => known_peers.verify(peer, key),
+        }
+    }
+}
+
+struct ServerCertificateVerifier {
+    verification: PeerVerification,
+}
+
 impl ServerCertVerifier for ServerCertificateVerifier {
     fn verify_server_cert(
         &self,
-        _end_entity: &Certificate,
+        end_entity: &Certificate,
         _intermediates: &[Certificate],
         _server_name: &ServerName,
         _scrs: &mut dyn Iterator<Item = &[u8]>,
         _ocsp_response: &[u8],
         _now: SystemTime,
     ) -> std::result::Result<ServerCertVerified, rustls::Error> {
-        // TODO: upsycle
+        let key = match peer_public_key(&end_entity.0) {
+            Ok(k) => k,
+            Err(e) => return Err(rustls::Error::InvalidCertificateData(e.to_string())),
+        };
+
+        if !self.verification.verify(&key) {
+            error!(
+                target: "net::tls",
+                "Server presented unexpected key {}",
+                hex::encode(key.as_ref())
+            );
+            return Err(rustls::Error::InvalidCertificateData("Peer key mismatch".to_string()))
+        }
+
         Ok(ServerCertVerified::assertion())
     }
 }
@@ -73,11 +379,17 @@ impl ClientCertVerifier for ClientCertificateVerifier {
 
     fn verify_client_cert(
         &self,
-        _end_entity: &Certificate,
+        end_entity: &Certificate,
         _intermediates: &[Certificate],
         _now: SystemTime,
     ) -> std::result::Result<ClientCertVerified, rustls::Error> {
-        // TODO: upsycle
+        // Inbound peers are not known in advance, so we only make sure they
+        // own the key they present. Authorization by key is left to protocols
+        // through `Channel::remote_key()`.
+        if let Err(e) = peer_public_key(&end_entity.0) {
+            return Err(rustls::Error::InvalidCertificateData(e.to_string()))
+        }
+
         Ok(ClientCertVerified::assertion())
     }
 }
@@ -90,21 +402,9 @@ pub struct TlsUpgrade {
 }
 
 impl TlsUpgrade {
-    pub fn new() -> Self {
-        // On each instantiation, generate a new keypair and certificate.
-        let keypair_pem = ed25519_compact::KeyPair::generate().to_pem();
-        let secret_key = pkcs8_private_keys(&mut keypair_pem.as_bytes()).unwrap();
-        let secret_key = rustls::PrivateKey(secret_key[0].clone());
-
-        let altnames = vec![String::from("dark.fi")];
-        let mut cert_params = rcgen::CertificateParams::new(altnames);
-        cert_params.alg = &rcgen::PKCS_ED25519;
-        cert_params.key_pair = Some(rcgen::KeyPair::from_pem(&keypair_pem).unwrap());
-
-        let certificate = rcgen::Certificate::from_params(cert_params).unwrap();
-        let certificate = certificate.serialize_der().unwrap();
-        let certificate = rustls::Certificate(certificate);
-
+    /// Create a new TLS upgrade presenting the given identity and
+    /// authenticating the remote server with the given policy.
+    pub fn new(identity: &TlsIdentity, verification: PeerVerification) -> Self {
         let client_cert_verifier = Arc::new(ClientCertificateVerifier {});
         let server_config = Arc::new(
             ServerConfig::builder()
@@ -113,11 +413,11 @@ impl TlsUpgrade {
                 .with_protocol_versions(&[&TLS13])
                 .unwrap()
                 .with_client_cert_verifier(client_cert_verifier)
-                .with_single_cert(vec![certificate.clone()], secret_key.clone())
+                .with_single_cert(vec![identity.certificate.clone()], identity.secret_key.clone())
                 .unwrap(),
         );
 
-        let server_cert_verifier = Arc::new(ServerCertificateVerifier {});
+        let server_cert_verifier = Arc::new(ServerCertificateVerifier { verification });
         let client_config = Arc::new(
             ClientConfig::builder()
                 .with_cipher_suites(&[cipher_suite()])
@@ -125,17 +425,14 @@ impl TlsUpgrade {
                 .with_protocol_versions(&[&TLS13])
                 .unwrap()
                 .with_custom_certificate_verifier(server_cert_verifier)
-                .with_single_cert(vec![certificate], secret_key)
+                .with_single_cert(vec![identity.certificate.clone()], identity.secret_key.clone())
                 .unwrap(),
         );
 
         Self { server_config, client_config }
     }
 
-    pub async fn upgrade_listener_tls(
-        self,
-        listener: TcpListener,
-    ) -> Result<(TlsAcceptor, TcpListener)> {
+    pub async fn upgrade_listener_tls<L>(self, listener: L) -> Result<(TlsAcceptor, L)> {
         Ok((TlsAcceptor::from(self.server_config), listener))
     }
 
@@ -151,7 +448,71 @@ impl TlsUpgrade {
 }
 
 impl Default for TlsUpgrade {
+    /// Use a fresh ephemeral identity and accept any remote key.
     fn default() -> Self {
-        Self::new()
+        Self::new(&TlsIdentity::generate().unwrap(), PeerVerification::Any)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_peer_public_key() {
+        let identity = TlsIdentity::generate().unwrap();
+        let key = peer_public_key(&identity.certificate.0).unwrap();
+        assert_eq!(key, identity.public_key());
+
+        // Tampering with the certificate must break the self-signature
+        let mut tampered = identity.certificate.0.clone();
+        let idx = tampered.len() / 2;
+        tampered[idx] ^= 0xff;
+        assert!(peer_public_key(&tampered).is_err());
+    }
+
+    #[test]
+    fn test_pinned_peer_key() {
+        let identity = TlsIdentity::generate().unwrap();
+        let pk = hex::encode(identity.public_key().as_ref());
+
+        let url = Url::parse(&format!("tls://{}@dark.fi:26661", pk)).unwrap();
+        assert_eq!(pinned_peer_key(&url).unwrap(), Some(identity.public_key()));
+
+        let url = Url::parse("tls://dark.fi:26661").unwrap();
+        assert_eq!(pinned_peer_key(&url).unwrap(), None);
+
+        let url = Url::parse("tls://deadbeef@dark.fi:26661").unwrap();
+        assert!(pinned_peer_key(&url).is_err());
+    }
+
+    #[test]
+    fn test_known_peers() {
+        let a = TlsIdentity::generate().unwrap().public_key();
+        let b = TlsIdentity::generate().unwrap().public_key();
+
+        let known_peers = KnownPeers::new(None).unwrap();
+        assert!(known_peers.verify("dark.fi:26661", &a));
+        assert!(known_peers.verify("dark.fi:26661", &a));
+        assert!(!known_peers.verify("dark.fi:26661", &b));
+        assert!(known_peers.verify("dark.fi:26662", &b));
+    }
+
+    #[test]
+    fn test_known_peers_skips_bad_lines() {
+        let a = TlsIdentity::generate().unwrap().public_key();
+        let path = std::env::temp_dir().join(format!("known_peers_{}", std::process::id()));
+        let contents = format!(
+            "dark.fi:26661 {}\ndark.fi:26662 not-hex\ndark.fi:26663 deadbeef\n",
+            hex::encode(a.as_ref())
+        );
+        fs::write(&path, contents).unwrap();
+
+        let known_peers = KnownPeers::new(Some(path.clone())).unwrap();
+        fs::remove_file(path).unwrap();
+
+        assert_eq!(known_peers.get("dark.fi:26661"), Some(a));
+        assert_eq!(known_peers.get("dark.fi:26662"), None);
+        assert_eq!(known_peers.get("dark.fi:26663"), None);
     }
 }
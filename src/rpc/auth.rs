@@ -0,0 +1,174 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Authentication of JSON-RPC clients and access control of methods.
+//!
+//! A server configured with [`AuthSettings`] lets anyone call its public
+//! methods, while the others need the client to authenticate, either with
+//! a bearer token or with the key of its TLS client certificate. Over
+//! streaming transports the token is sent once with the `auth` method,
+//! and over HTTP it goes in the `Authorization` header of every request.
+//! Like bitcoind does, a server can write a random token to a cookie file
+//! only readable by its user, so local clients can pick it up.
+use std::{fs, io::Write, os::unix::fs::OpenOptionsExt, path::Path};
+
+use ed25519_compact::PublicKey;
+use rand::{rngs::OsRng, RngCore};
+
+use crate::{net::transport::TlsIdentity, Result};
+
+/// Authentication settings of a JSON-RPC server.
+#[derive(Clone, Debug, Default)]
+pub struct AuthSettings {
+    /// Bearer tokens granting access to all methods
+    pub tokens: Vec<String>,
+    /// Hex-encoded ed25519 keys of TLS client certificates granting
+    /// access to all methods
+    pub client_keys: Vec<String>,
+    /// Methods anyone can call, given as exact names, as `prefix.*`
+    /// patterns, or as `*` for all of them
+    pub public_methods: Vec<String>,
+}
+
+impl AuthSettings {
+    /// Whether the given method can be called without authentication.
+    pub fn is_public(&self, method: &str) -> bool {
+        self.public_methods.iter().any(|pattern| method_matches(pattern, method))
+    }
+
+    /// Whether the given bearer token is valid.
+    pub fn check_token(&self, token: &str) -> bool {
+        // Compare against every token, so timing doesn't tell which matched
+        self.tokens
+            .iter()
+            .fold(false, |valid, t| constant_time_eq(t.as_bytes(), token.as_bytes()) | valid)
+    }
+
+    /// Whether the given TLS client key is allowed.
+    pub fn check_client_key(&self, key: &PublicKey) -> bool {
+        let key = hex::encode(key.as_ref());
+        self.client_keys.iter().any(|k| k.eq_ignore_ascii_case(&key))
+    }
+}
+
+/// Credentials presented by a JSON-RPC client.
+#[derive(Clone, Default)]
+pub struct RpcCredentials {
+    /// Bearer token
+    pub token: Option<String>,
+    /// Identity presented on TLS connections. A fresh one is used if unset.
+    pub tls_identity: Option<TlsIdentity>,
+}
+
+impl RpcCredentials {
+    /// Credentials holding the given bearer token.
+    pub fn token(token: String) -> Self {
+        Self { token: Some(token), tls_identity: None }
+    }
+}
+
+/// Check whether a method matches a pattern of [`AuthSettings::public_methods`].
+fn method_matches(pattern: &str, method: &str) -> bool {
+    match pattern.strip_suffix('*') {
+        Some(prefix) if prefix.is_empty() || prefix.ends_with('.') => method.starts_with(prefix),
+        _ => pattern == method,
+    }
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false
+    }
+
+    a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Generate a random token and write it to a cookie file at the given
+/// path, readable only by the current user. Any previous cookie is
+/// replaced.
+pub fn create_cookie(path: &Path) -> Result<String> {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    let token = hex::encode(bytes);
+
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    // Remove the old cookie first, as the mode only applies to new files
+    if path.exists() {
+        fs::remove_file(path)?;
+    }
+
+    let mut file = fs::OpenOptions::new().write(true).create_new(true).mode(0o600).open(path)?;
+    file.write_all(token.as_bytes())?;
+
+    Ok(token)
+}
+
+/// Read the token from the cookie file at the given path.
+pub fn read_cookie(path: &Path) -> Result<String> {
+    Ok(fs::read_to_string(path)?.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use super::*;
+
+    #[test]
+    fn method_access() {
+        let settings = AuthSettings {
+            tokens: vec!["secret".to_string(), "other".to_string()],
+            client_keys: vec![],
+            public_methods: vec!["ping".to_string(), "blockchain.*".to_string()],
+        };
+
+        assert!(settings.is_public("ping"));
+        assert!(settings.is_public("blockchain.get_slot"));
+        assert!(!settings.is_public("pingpong"));
+        assert!(!settings.is_public("blockchain"));
+        assert!(!settings.is_public("wallet.exec_sql"));
+
+        assert!(settings.check_token("secret"));
+        assert!(settings.check_token("other"));
+        assert!(!settings.check_token("secre"));
+        assert!(!settings.check_token(""));
+
+        assert!(method_matches("*", "wallet.exec_sql"));
+        assert!(!method_matches("wallet*", "wallet_exec_sql"));
+    }
+
+    #[test]
+    fn cookie_file() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("darkfi_rpc_cookie_{}", OsRng.next_u64()));
+
+        let token = create_cookie(&path)?;
+        assert_eq!(read_cookie(&path)?, token);
+        assert_eq!(fs::metadata(&path)?.permissions().mode() & 0o777, 0o600);
+
+        // A new cookie replaces the old one
+        let new_token = create_cookie(&path)?;
+        assert_ne!(new_token, token);
+        assert_eq!(read_cookie(&path)?, new_token);
+
+        fs::remove_file(&path)?;
+        Ok(())
+    }
+}
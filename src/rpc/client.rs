@@ -17,125 +17,219 @@
  */
 
 //! JSON-RPC client-side implementation.
-use std::time::Duration;
-
-use async_std::io::timeout;
-use futures::{select, AsyncReadExt, AsyncWriteExt, FutureExt};
-use log::{debug, error};
+//!
+//! Besides the transports supported by [`crate::net`], the client can
+//! reach servers over plain HTTP with `http://` URLs and over websockets
+//! with `ws://` URLs. Subscriptions need a streaming transport, so they
+//! fail over HTTP.
+//!
+//! Servers restricting access to their methods take the credentials given
+//! with [`RpcClient::new_with_credentials()`], as described in
+//! [`super::auth`].
+use std::{collections::HashMap, time::Duration};
+
+use async_std::{
+    future::timeout,
+    sync::{Arc, Mutex},
+};
+use async_tungstenite::{
+    tungstenite::{protocol::WebSocketConfig, Message},
+    WebSocketStream,
+};
+use futures::{io::BufReader, select, AsyncReadExt, FutureExt, SinkExt, StreamExt};
+use log::{debug, error, warn};
 use serde_json::{json, Value};
 use url::Url;
 
-use super::jsonrpc::{ErrorCode, JsonError, JsonRequest, JsonResult};
+use super::{
+    auth::RpcCredentials,
+    http::{read_body, read_head, tcp_url, write_request},
+    jsonrpc::{
+        read_message, write_message, ErrorCode, JsonError, JsonNotification, JsonRequest,
+        JsonResponse, JsonResult,
+    },
+};
 use crate::{
     net::transport::{
-        TcpTransport, TorTransport, Transport, TransportName, TransportStream, UnixTransport,
+        PeerVerification, TcpTransport, TlsUpgrade, TorTransport, Transport, TransportName,
+        TransportStream, UnixTransport,
     },
     system::SubscriberPtr,
     Error, Result,
 };
 
+/// If we don't get a reply to a request within this duration, we fail.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Maximum size in bytes of an incoming message
+const MAX_MESSAGE_SIZE: usize = 64 * 1024 * 1024;
+
+/// Requests waiting for their reply, keyed by their serialized ID
+type PendingRequests = Arc<Mutex<HashMap<String, smol::channel::Sender<JsonResult>>>>;
+
+/// Subscribers receiving the notifications sent by the server
+type NotificationSubscribers = Arc<Mutex<Vec<SubscriberPtr<JsonResult>>>>;
+
 /// JSON-RPC client implementation using asynchronous channels.
+/// Requests can be sent concurrently, and their replies are matched to
+/// them by ID, while notifications go to the active subscribers.
 pub struct RpcClient {
-    send: smol::channel::Sender<(Value, bool)>,
-    recv: smol::channel::Receiver<JsonResult>,
+    send: smol::channel::Sender<String>,
+    pending: PendingRequests,
+    subscribers: NotificationSubscribers,
     stop_signal: smol::channel::Sender<()>,
+    stopped: smol::channel::Receiver<()>,
     url: Url,
 }
 
 impl RpcClient {
     /// Instantiate a new JSON-RPC client that will connect to the given URL.
     pub async fn new(url: Url) -> Result<Self> {
-        let (send, recv, stop_signal) = Self::open_channels(&url).await?;
-        Ok(Self { send, recv, stop_signal, url })
+        Self::new_with_credentials(url, RpcCredentials::default()).await
+    }
+
+    /// Instantiate a new JSON-RPC client that will connect to the given URL
+    /// and authenticate with the given credentials.
+    pub async fn new_with_credentials(url: Url, credentials: RpcCredentials) -> Result<Self> {
+        let pending = Arc::new(Mutex::new(HashMap::new()));
+        let subscribers = Arc::new(Mutex::new(vec![]));
+        let (send, stop_signal, stopped) =
+            Self::open_channels(&url, &credentials, pending.clone(), subscribers.clone()).await?;
+        let client = Self { send, pending, subscribers, stop_signal, stopped, url };
+
+        // Over HTTP, the token is sent along with every request instead
+        if let Some(token) = credentials.token {
+            if client.url.scheme() != "http" {
+                if let Err(e) = client.request(JsonRequest::new("auth", json!([token]))).await {
+                    error!(target: "rpc::client", "JSON-RPC client failed authenticating to {}: {}", client.url, e);
+                    client.close().await?;
+                    return Err(e)
+                }
+            }
+        }
+
+        Ok(client)
     }
 
     /// Close the channels of an instantiated [`RpcClient`].
     pub async fn close(&self) -> Result<()> {
-        self.stop_signal.send(()).await?;
+        self.stop_signal.close();
         Ok(())
     }
 
     /// Listen instantiated client for notifications.
+    /// The reply to the initial request, holding the subscription ID, is
+    /// consumed here, while notifications are passed to the given
+    /// subscriber until the connection is closed.
     /// NOTE: Subscriber listeners must perform response handling.
     pub async fn subscribe(
         &self,
         req: JsonRequest,
         subscriber: SubscriberPtr<JsonResult>,
     ) -> Result<()> {
-        // Perform initial request.
-        debug!(target: "rpc::client", "--> {}", serde_json::to_string(&req)?);
-        // If the connection is closed, the sender will get an error for sending to a closed channel.
-        if let Err(e) = self.send.send((json!(req), false)).await {
-            error!(target: "rpc::client", "JSON-RPC client unable to send to {} (channels closed): {}", self.url, e);
-            return Err(Error::NetworkOperationFailed)
-        }
-
-        loop {
-            // If the connection is closed, the receiver will get an error for waiting on a closed channel.
-            let notification = self.recv.recv().await;
-            if notification.is_err() {
-                error!(target: "rpc::client", "JSON-RPC client unable to recv from {} (channels closed)", self.url);
-                break
-            }
-
-            // Notify subscribed channels
-            let notification = notification?;
-            debug!(target: "rpc::client", "<-- {}", serde_json::to_string(&notification)?);
+        self.subscribers.lock().await.push(subscriber.clone());
 
-            subscriber.notify(notification.clone()).await;
-
-            // Stop listenning on error
-            match notification {
-                JsonResult::Notification(_) => {}
-                _ => break,
+        match self.request(req.clone()).await {
+            Ok(subscription_id) => {
+                debug!(target: "rpc::client", "Subscribed to {} with ID {}", self.url, subscription_id)
             }
-
-            // Triggering next consume
-            if let Err(e) = self.send.send((json!(req), false)).await {
-                error!(target: "rpc::client", "JSON-RPC client unable to send to {} (channels closed): {}", self.url, e);
-                break
+            Err(e) => {
+                error!(target: "rpc::client", "JSON-RPC client failed subscribing to {}: {}", self.url, e);
+                subscriber
+                    .notify(JsonError::new(ErrorCode::InternalError, None, req.id).into())
+                    .await;
+                return Err(e)
             }
         }
 
-        subscriber.notify(JsonError::new(ErrorCode::InternalError, None, req.id).into()).await;
+        // Wait until the connection is closed
+        let _ = self.stopped.recv().await;
         Err(Error::NetworkOperationFailed)
     }
 
     /// Send a given JSON-RPC request over the instantiated client.
     pub async fn request(&self, value: JsonRequest) -> Result<Value> {
-        let req_id = value.id.clone().as_u64().unwrap();
-
         debug!(target: "rpc::client", "--> {}", serde_json::to_string(&value)?);
+        let reply =
+            self.send_requests(std::slice::from_ref(&value), json!(value)).await?.pop().unwrap();
+        Self::reply_result(reply)
+    }
+
+    /// Send the given JSON-RPC requests as a single batch over the
+    /// instantiated client. The results are in the same order as the
+    /// requests, whose IDs must be unique.
+    pub async fn request_batch(&self, values: Vec<JsonRequest>) -> Result<Vec<Result<Value>>> {
+        debug!(target: "rpc::client", "--> {}", serde_json::to_string(&values)?);
+        let replies = self.send_requests(&values, json!(values)).await?;
+        Ok(replies.into_iter().map(Self::reply_result).collect())
+    }
+
+    /// Oneshot send a given JSON-RPC request over the instantiated client
+    /// and close the channels on reply.
+    pub async fn oneshot_request(&self, value: JsonRequest) -> Result<Value> {
+        let rep = self.request(value).await?;
+        self.close().await?;
+        Ok(rep)
+    }
+
+    /// Write the given message, holding the given requests, and wait for
+    /// their replies.
+    async fn send_requests(&self, reqs: &[JsonRequest], message: Value) -> Result<Vec<JsonResult>> {
+        let mut waiting = Vec::with_capacity(reqs.len());
+        {
+            let mut pending = self.pending.lock().await;
+            for req in reqs {
+                let (reply_send, reply_recv) = smol::channel::bounded(1);
+                pending.insert(req.id.to_string(), reply_send);
+                waiting.push((req.id.to_string(), reply_recv));
+            }
+        }
 
         // If the connection is closed, the sender will get an error for
         // sending to a closed channel.
-        if let Err(e) = self.send.send((json!(value), true)).await {
+        if let Err(e) = self.send.send(message.to_string()).await {
             error!(target: "rpc::client", "JSON-RPC client unable to send to {} (channels closed): {}", self.url, e);
+            self.forget(&waiting).await;
             return Err(Error::NetworkOperationFailed)
         }
 
-        // If the connection is closed, the receiver will get an error for
-        // waiting on a closed channel.
-        let reply = self.recv.recv().await;
-        if reply.is_err() {
-            error!(target: "rpc::client", "JSON-RPC client unable to recv from {} (channels closed)", self.url);
-            return Err(Error::NetworkOperationFailed)
-        }
+        // If the connection is closed, the receivers will get an error
+        // for waiting on a closed channel.
+        let recv_all = async {
+            let mut replies = Vec::with_capacity(waiting.len());
+            for (_, reply_recv) in &waiting {
+                replies.push(reply_recv.recv().await?);
+            }
+            Ok::<_, smol::channel::RecvError>(replies)
+        };
 
-        match reply? {
-            JsonResult::Response(r) => {
-                // Check if the IDs match
-                let resp_id = r.id.as_u64();
-                if resp_id.is_none() {
-                    let e = JsonError::new(ErrorCode::InvalidId, None, r.id);
-                    return Err(Error::JsonRpcError(e.error.message.to_string()))
-                }
+        match timeout(REQUEST_TIMEOUT, recv_all).await {
+            Ok(Ok(replies)) => Ok(replies),
+            Ok(Err(_)) => {
+                error!(target: "rpc::client", "JSON-RPC client unable to recv from {} (channels closed)", self.url);
+                self.forget(&waiting).await;
+                Err(Error::NetworkOperationFailed)
+            }
+            Err(e) => {
+                error!(target: "rpc::client", "JSON-RPC client timed out waiting for {}", self.url);
+                self.forget(&waiting).await;
+                Err(e.into())
+            }
+        }
+    }
 
-                if resp_id.unwrap() != req_id {
-                    let e = JsonError::new(ErrorCode::InvalidId, None, r.id);
-                    return Err(Error::JsonRpcError(e.error.message.to_string()))
-                }
+    /// Stop waiting for the replies of the given requests.
+    async fn forget(&self, waiting: &[(String, smol::channel::Receiver<JsonResult>)]) {
+        let mut pending = self.pending.lock().await;
+        for (id, _) in waiting {
+            pending.remove(id);
+        }
+    }
 
+    /// Turn the reply to a request into its result.
+    fn reply_result(reply: JsonResult) -> Result<Value> {
+        match reply {
+            JsonResult::Response(r) => {
                 debug!(target: "rpc::client", "<-- {}", serde_json::to_string(&r)?);
                 Ok(r.result)
             }
@@ -151,27 +245,20 @@ impl RpcClient {
         }
     }
 
-    /// Oneshot send a given JSON-RPC request over the instantiated client
-    /// and close the channels on reply.
-    pub async fn oneshot_request(&self, value: JsonRequest) -> Result<Value> {
-        let rep = self.request(value).await?;
-        self.stop_signal.send(()).await?;
-        Ok(rep)
-    }
-
     /// Instantiate channels for a new [`RpcClient`].
     async fn open_channels(
         uri: &Url,
+        credentials: &RpcCredentials,
+        pending: PendingRequests,
+        subscribers: NotificationSubscribers,
     ) -> Result<(
-        smol::channel::Sender<(Value, bool)>,
-        smol::channel::Receiver<JsonResult>,
+        smol::channel::Sender<String>,
         smol::channel::Sender<()>,
+        smol::channel::Receiver<()>,
     )> {
         let (data_send, data_recv) = smol::channel::unbounded();
-        let (result_send, result_recv) = smol::channel::unbounded();
         let (stop_send, stop_recv) = smol::channel::unbounded();
-
-        let transport_name = TransportName::try_from(uri.clone())?;
+        let token = credentials.token.clone();
 
         macro_rules! reqrep {
             ($stream:expr, $transport:expr, $upgrade:expr) => {{
@@ -187,21 +274,33 @@ impl RpcClient {
                 }
 
                 let stream = stream?;
+                let stop = (stop_send.clone(), stop_recv.clone());
                 match $upgrade {
                     None => {
-                        smol::spawn(Self::reqrep_loop(stream, result_send, data_recv, stop_recv))
-                            .detach();
+                        Self::spawn_tasks(uri, token, stream, data_recv, stop, pending, subscribers).await?;
                     }
                     Some(u) if u == "tls" => {
-                        let stream = $transport.upgrade_dialer(stream)?.await?;
-                        smol::spawn(Self::reqrep_loop(stream, result_send, data_recv, stop_recv))
-                            .detach();
+                        let upgrade = match &credentials.tls_identity {
+                            Some(identity) => TlsUpgrade::new(identity, PeerVerification::Any),
+                            None => TlsUpgrade::default(),
+                        };
+                        let stream = $transport.upgrade_dialer(upgrade, stream)?.await?;
+                        Self::spawn_tasks(uri, token, stream, data_recv, stop, pending, subscribers).await?;
                     }
                     Some(u) => return Err(Error::UnsupportedTransportUpgrade(u)),
                 }
             }};
         }
 
+        // HTTP and websockets are served over plain TCP
+        if uri.scheme() == "http" || uri.scheme() == "ws" {
+            let transport = TcpTransport::new(None, 1024);
+            let stream = transport.dial(tcp_url(uri)?, None);
+            reqrep!(stream, transport, None);
+            return Ok((data_send, stop_send, stop_recv))
+        }
+
+        let transport_name = TransportName::try_from(uri.clone())?;
         match transport_name {
             TransportName::Tcp(upgrade) => {
                 let transport = TcpTransport::new(None, 1024);
@@ -222,56 +321,293 @@ impl RpcClient {
             _ => unimplemented!(),
         }
 
-        Ok((data_send, result_recv, stop_send))
+        Ok((data_send, stop_send, stop_recv))
     }
 
-    /// Internal function that loops on a given stream and multiplexes the data.
-    async fn reqrep_loop<T: TransportStream>(
-        mut stream: T,
-        result_send: smol::channel::Sender<JsonResult>,
-        data_recv: smol::channel::Receiver<(Value, bool)>,
-        stop_recv: smol::channel::Receiver<()>,
+    /// Spawn the tasks serving a connected stream, carrying messages the
+    /// way the scheme of the given URL calls for.
+    async fn spawn_tasks<T: TransportStream + 'static>(
+        uri: &Url,
+        token: Option<String>,
+        stream: T,
+        data_recv: smol::channel::Receiver<String>,
+        stop: (smol::channel::Sender<()>, smol::channel::Receiver<()>),
+        pending: PendingRequests,
+        subscribers: NotificationSubscribers,
     ) -> Result<()> {
-        // If timeout is enabled and we don't get a reply within 30 seconds, we'll fail.
-        let read_timeout = Duration::from_secs(30);
-
-        loop {
-            // FIXME: Nasty size. 8M
-            let mut buf = vec![0; 1024 * 8192];
-
-            select! {
-                tuple = data_recv.recv().fuse() => {
-                    let (data, with_timeout) = tuple?;
-                    let data_bytes = serde_json::to_vec(&data)?;
-                    stream.write_all(&data_bytes).await?;
-                    // Since we are using async read and write,
-                    // the other side might not have finished writing
-                    // to the stream. To mitigate this, we perform a read
-                    // and check if data can be converted to a JsonResult.
-                    // If data is incomplete, this will fail, therefore,
-                    // we re-execute read and write after previous read in the buffer,
-                    // and repeat until the data in buffer can be converted.
-                    let mut n = 0;
-                    loop {
-                        n += if with_timeout {
-                            timeout(read_timeout, async { stream.read(&mut buf[n..]).await }).await?
-                        } else {
-                            stream.read(&mut buf[n..]).await?
-                        };
-                        match serde_json::from_slice(&buf[0..n]) {
-                            Ok(reply) => {
-                                result_send.send(reply).await?;
-                                break
-                            },
-                            Err(e) => debug!(target: "rpc::client", "JSON-RPC client retrying failed convertion with error: {}", e),
+        match uri.scheme() {
+            "http" => {
+                let uri = uri.clone();
+                Self::spawn_http_reqrep(uri, token, stream, data_recv, stop, pending, subscribers)
+            }
+            "ws" => {
+                let config = WebSocketConfig {
+                    max_message_size: Some(MAX_MESSAGE_SIZE),
+                    max_frame_size: Some(MAX_MESSAGE_SIZE),
+                    ..Default::default()
+                };
+                let (ws, _) =
+                    async_tungstenite::client_async_with_config(uri.as_str(), stream, Some(config))
+                        .await?;
+                Self::spawn_ws_reqrep(ws, data_recv, stop, pending, subscribers)
+            }
+            _ => Self::spawn_reqrep(stream, data_recv, stop, pending, subscribers),
+        }
+
+        Ok(())
+    }
+
+    /// Internal function that spawns the tasks writing outgoing messages
+    /// to the given stream, and dispatching the incoming ones. Closing the
+    /// stop channel ends both tasks, and the reader closes it when the
+    /// connection goes down.
+    fn spawn_reqrep<T: TransportStream + 'static>(
+        stream: T,
+        data_recv: smol::channel::Receiver<String>,
+        (stop_send, stop_recv): (smol::channel::Sender<()>, smol::channel::Receiver<()>),
+        pending: PendingRequests,
+        subscribers: NotificationSubscribers,
+    ) {
+        let (reader, mut writer) = stream.split();
+        let mut reader = BufReader::new(reader);
+
+        let _stop_recv = stop_recv.clone();
+        smol::spawn(async move {
+            loop {
+                let data = select! {
+                    data = data_recv.recv().fuse() => data,
+                    _ = _stop_recv.recv().fuse() => break,
+                };
+                let Ok(data) = data else { break };
+
+                if let Err(e) = write_message(&mut writer, data.as_bytes()).await {
+                    error!(target: "rpc::client", "JSON-RPC client failed writing to socket: {}", e);
+                    break
+                }
+            }
+        })
+        .detach();
+
+        smol::spawn(async move {
+            loop {
+                let message = select! {
+                    message = read_message(&mut reader, MAX_MESSAGE_SIZE).fuse() => message,
+                    _ = stop_recv.recv().fuse() => break,
+                };
+
+                let message = match message {
+                    Ok(Some(v)) => v,
+                    Ok(None) => break,
+                    Err(e) => {
+                        error!(target: "rpc::client", "JSON-RPC client failed reading from socket: {}", e);
+                        break
+                    }
+                };
+
+                Self::dispatch_message(&message, &pending, &subscribers).await;
+            }
+
+            Self::closed(stop_send, pending, subscribers).await;
+        })
+        .detach();
+    }
+
+    /// Same as [`RpcClient::spawn_reqrep()`], for a websocket connection.
+    fn spawn_ws_reqrep<T: TransportStream + 'static>(
+        ws: WebSocketStream<T>,
+        data_recv: smol::channel::Receiver<String>,
+        (stop_send, stop_recv): (smol::channel::Sender<()>, smol::channel::Receiver<()>),
+        pending: PendingRequests,
+        subscribers: NotificationSubscribers,
+    ) {
+        let (mut writer, mut reader) = ws.split();
+
+        let _stop_recv = stop_recv.clone();
+        smol::spawn(async move {
+            loop {
+                let data = select! {
+                    data = data_recv.recv().fuse() => data,
+                    _ = _stop_recv.recv().fuse() => break,
+                };
+                let Ok(data) = data else { break };
+
+                if let Err(e) = writer.send(Message::Text(data)).await {
+                    error!(target: "rpc::client", "JSON-RPC client failed writing to socket: {}", e);
+                    break
+                }
+            }
+            let _ = writer.close().await;
+        })
+        .detach();
+
+        smol::spawn(async move {
+            loop {
+                let message = select! {
+                    message = reader.next().fuse() => message,
+                    _ = stop_recv.recv().fuse() => break,
+                };
+
+                let message = match message {
+                    Some(Ok(Message::Text(v))) => v.into_bytes(),
+                    Some(Ok(Message::Binary(v))) => v,
+                    // Pings are answered by the websocket implementation
+                    Some(Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_))) => continue,
+                    Some(Ok(Message::Close(_))) | None => break,
+                    Some(Err(e)) => {
+                        error!(target: "rpc::client", "JSON-RPC client failed reading from socket: {}", e);
+                        break
+                    }
+                };
+
+                Self::dispatch_message(&message, &pending, &subscribers).await;
+            }
+
+            Self::closed(stop_send, pending, subscribers).await;
+        })
+        .detach();
+    }
+
+    /// Same as [`RpcClient::spawn_reqrep()`], for an HTTP connection.
+    /// Each outgoing message is sent as a `POST` request and its reply
+    /// is read from the response before the next one goes out.
+    fn spawn_http_reqrep<T: TransportStream + 'static>(
+        uri: Url,
+        token: Option<String>,
+        stream: T,
+        data_recv: smol::channel::Receiver<String>,
+        (stop_send, stop_recv): (smol::channel::Sender<()>, smol::channel::Receiver<()>),
+        pending: PendingRequests,
+        subscribers: NotificationSubscribers,
+    ) {
+        let (reader, mut writer) = stream.split();
+        let mut reader = BufReader::new(reader);
+
+        smol::spawn(async move {
+            loop {
+                let data = select! {
+                    data = data_recv.recv().fuse() => data,
+                    _ = stop_recv.recv().fuse() => break,
+                };
+                let Ok(data) = data else { break };
+
+                let exchange = async {
+                    write_request(&mut writer, &uri, token.as_deref(), data.as_bytes()).await?;
+                    let Some(head) = read_head(&mut reader).await? else {
+                        return Err(Error::HttpError("Connection closed".to_string()))
+                    };
+
+                    let len = head.content_length()?;
+                    if len > MAX_MESSAGE_SIZE {
+                        return Err(Error::JsonRpcMessageTooLarge(MAX_MESSAGE_SIZE))
+                    }
+                    let body = read_body(&mut reader, len).await?;
+
+                    if head.start_line[1] != "200" {
+                        let status = head.start_line[1..].join(" ");
+                        return Err(Error::HttpError(format!("Server responded {}", status)))
+                    }
+
+                    Ok::<_, Error>((body, head.keep_alive()))
+                };
+
+                match exchange.await {
+                    Ok((body, keep_alive)) => {
+                        Self::dispatch_message(&body, &pending, &subscribers).await;
+                        if !keep_alive {
+                            break
                         }
                     }
+                    Err(e) => {
+                        error!(target: "rpc::client", "JSON-RPC client failed exchanging with {}: {}", uri, e);
+                        break
+                    }
                 }
+            }
 
-                _ = stop_recv.recv().fuse() => break
+            Self::closed(stop_send, pending, subscribers).await;
+        })
+        .detach();
+    }
+
+    /// Dispatch the replies held in an incoming message.
+    async fn dispatch_message(
+        message: &[u8],
+        pending: &PendingRequests,
+        subscribers: &NotificationSubscribers,
+    ) {
+        // A batch reply holds the replies to several requests
+        let replies = match serde_json::from_slice(message) {
+            Ok(Value::Array(replies)) => replies,
+            Ok(reply) => vec![reply],
+            Err(e) => {
+                warn!(target: "rpc::client", "JSON-RPC client received invalid JSON: {}", e);
+                return
             }
+        };
+
+        for reply in replies {
+            Self::dispatch(reply, pending, subscribers).await;
         }
+    }
 
-        Ok(())
+    /// Fail the requests waiting for a reply once the connection is down,
+    /// and notify subscribers that they won't receive anything else.
+    async fn closed(
+        stop_send: smol::channel::Sender<()>,
+        pending: PendingRequests,
+        subscribers: NotificationSubscribers,
+    ) {
+        stop_send.close();
+        pending.lock().await.clear();
+        for subscriber in subscribers.lock().await.iter() {
+            let err = JsonError::new(ErrorCode::InternalError, None, Value::Null);
+            subscriber.notify(err.into()).await;
+        }
+    }
+
+    /// Pass an incoming reply to the request waiting for it, or a
+    /// notification to the subscribers.
+    async fn dispatch(
+        reply: Value,
+        pending: &PendingRequests,
+        subscribers: &NotificationSubscribers,
+    ) {
+        let reply = if reply.get("method").is_some() {
+            serde_json::from_value::<JsonNotification>(reply).map(JsonResult::from)
+        } else if reply.get("error").is_some() {
+            serde_json::from_value::<JsonError>(reply).map(JsonResult::from)
+        } else {
+            serde_json::from_value::<JsonResponse>(reply).map(JsonResult::from)
+        };
+
+        let reply = match reply {
+            Ok(v) => v,
+            Err(e) => {
+                warn!(target: "rpc::client", "JSON-RPC client received invalid reply: {}", e);
+                return
+            }
+        };
+
+        let id = match &reply {
+            JsonResult::Response(r) => &r.id,
+            JsonResult::Error(e) => &e.id,
+            JsonResult::Notification(n) => {
+                debug!(target: "rpc::client", "<-- {}", serde_json::to_string(n).unwrap());
+                for subscriber in subscribers.lock().await.iter() {
+                    subscriber.notify(reply.clone()).await;
+                }
+                return
+            }
+            JsonResult::Subscriber(_) => unreachable!(),
+        };
+
+        match pending.lock().await.remove(&id.to_string()) {
+            Some(reply_send) => {
+                let _ = reply_send.send(reply).await;
+            }
+            None => {
+                warn!(target: "rpc::client", "JSON-RPC client received reply for unknown request {}", id)
+            }
+        }
     }
 }
@@ -0,0 +1,216 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Minimal HTTP/1.1 support, used to carry JSON-RPC messages in the body
+//! of `POST` requests and their responses.
+use futures::{AsyncBufRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use url::Url;
+
+use super::jsonrpc::read_message;
+use crate::{Error, Result};
+
+/// Maximum size in bytes of the start line and headers of a message
+const MAX_HEAD_SIZE: usize = 16 * 1024;
+
+/// Start line and headers of an HTTP request or response
+#[derive(Debug)]
+pub struct HttpHead {
+    /// Request or status line, split on spaces
+    pub start_line: Vec<String>,
+    /// Headers, with lowercased names
+    pub headers: Vec<(String, String)>,
+}
+
+impl HttpHead {
+    /// Value of the first header with the given lowercase name
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n == name).map(|(_, v)| v.as_str())
+    }
+
+    /// Length of the body following the head, which must be given with
+    /// a `Content-Length` header since chunked bodies are unsupported
+    pub fn content_length(&self) -> Result<usize> {
+        if self.header("transfer-encoding").is_some() {
+            return Err(Error::HttpError("Chunked bodies are unsupported".to_string()))
+        }
+
+        match self.header("content-length") {
+            Some(v) => {
+                v.parse().map_err(|_| Error::HttpError("Invalid Content-Length".to_string()))
+            }
+            None => Ok(0),
+        }
+    }
+
+    /// Whether the connection should stay open after this message
+    pub fn keep_alive(&self) -> bool {
+        let version = match self.start_line.first() {
+            // Status line
+            Some(v) if v.starts_with("HTTP/") => v,
+            // Request line
+            _ => match self.start_line.get(2) {
+                Some(v) => v,
+                None => return false,
+            },
+        };
+
+        match self.header("connection").map(|v| v.to_lowercase()) {
+            Some(v) if v == "close" => false,
+            Some(v) if v == "keep-alive" => true,
+            _ => version == "HTTP/1.1",
+        }
+    }
+}
+
+/// Read the start line and headers of an HTTP message.
+/// Returns `None` if the stream was closed before any data.
+pub async fn read_head<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<Option<HttpHead>> {
+    let Some(line) = read_message(reader, MAX_HEAD_SIZE).await? else { return Ok(None) };
+    let start_line: Vec<String> =
+        String::from_utf8_lossy(&line).split(' ').map(|s| s.to_string()).collect();
+    if start_line.len() < 3 {
+        return Err(Error::HttpError("Malformed start line".to_string()))
+    }
+
+    let mut headers = vec![];
+    let mut size = line.len();
+    loop {
+        let Some(line) = read_message(reader, MAX_HEAD_SIZE).await? else {
+            return Err(Error::HttpError("Connection closed within headers".to_string()))
+        };
+
+        if line.is_empty() {
+            break
+        }
+
+        size += line.len();
+        if size > MAX_HEAD_SIZE {
+            return Err(Error::HttpError("Headers too large".to_string()))
+        }
+
+        let line = String::from_utf8_lossy(&line);
+        let Some((name, value)) = line.split_once(':') else {
+            return Err(Error::HttpError("Malformed header".to_string()))
+        };
+        headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+    }
+
+    Ok(Some(HttpHead { start_line, headers }))
+}
+
+/// Read a body of the given length.
+pub async fn read_body<R: AsyncBufRead + Unpin>(reader: &mut R, len: usize) -> Result<Vec<u8>> {
+    let mut body = vec![0; len];
+    reader.read_exact(&mut body).await?;
+    Ok(body)
+}
+
+/// Write a `POST` request for the given URL, carrying a JSON body and
+/// the given bearer token, if any.
+pub async fn write_request<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    url: &Url,
+    token: Option<&str>,
+    body: &[u8],
+) -> Result<()> {
+    let host = url.host_str().unwrap_or_default();
+    let host = match url.port() {
+        Some(port) => format!("{}:{}", host, port),
+        None => host.to_string(),
+    };
+
+    let mut head = format!("POST {} HTTP/1.1\r\nHost: {}\r\n", url.path(), host);
+    if let Some(token) = token {
+        head.push_str(&format!("Authorization: Bearer {}\r\n", token));
+    }
+    head.push_str(&format!(
+        "Content-Type: application/json\r\nContent-Length: {}\r\n\r\n",
+        body.len()
+    ));
+
+    writer.write_all(head.as_bytes()).await?;
+    writer.write_all(body).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Write a response with the given status, extra headers and JSON body,
+/// which may be empty.
+pub async fn write_response<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    status: u16,
+    reason: &str,
+    headers: &[(&str, &str)],
+    body: &[u8],
+) -> Result<()> {
+    let mut head = format!("HTTP/1.1 {} {}\r\n", status, reason);
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    if !body.is_empty() {
+        head.push_str("Content-Type: application/json\r\n");
+    }
+    head.push_str(&format!("Content-Length: {}\r\n\r\n", body.len()));
+
+    writer.write_all(head.as_bytes()).await?;
+    writer.write_all(body).await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Get the `tcp://` URL of the socket an `http://` or `ws://` URL points to.
+pub fn tcp_url(url: &Url) -> Result<Url> {
+    let host = url.host_str().ok_or_else(|| Error::UrlParse(format!("Missing host in {}", url)))?;
+    let port = url
+        .port_or_known_default()
+        .ok_or_else(|| Error::UrlParse(format!("Missing port in {}", url)))?;
+    Ok(Url::parse(&format!("tcp://{}:{}", host, port))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::io::Cursor;
+
+    use super::*;
+
+    #[async_std::test]
+    async fn http_messages() -> Result<()> {
+        let url = Url::parse("http://127.0.0.1:8340/").unwrap();
+        let mut stream = Cursor::new(vec![]);
+        write_request(&mut stream, &url, Some("secret"), br#"{"a":1}"#).await?;
+        write_response(&mut stream, 200, "OK", &[("Connection", "close")], b"[]").await?;
+        stream.set_position(0);
+
+        let head = read_head(&mut stream).await?.unwrap();
+        assert_eq!(head.start_line, vec!["POST", "/", "HTTP/1.1"]);
+        assert_eq!(head.header("host"), Some("127.0.0.1:8340"));
+        assert_eq!(head.header("authorization"), Some("Bearer secret"));
+        assert!(head.keep_alive());
+        assert_eq!(read_body(&mut stream, head.content_length()?).await?, br#"{"a":1}"#);
+
+        let head = read_head(&mut stream).await?.unwrap();
+        assert_eq!(head.start_line, vec!["HTTP/1.1", "200", "OK"]);
+        assert!(!head.keep_alive());
+        assert_eq!(read_body(&mut stream, head.content_length()?).await?, b"[]");
+        assert!(read_head(&mut stream).await?.is_none());
+
+        assert_eq!(tcp_url(&Url::parse("ws://localhost").unwrap())?.as_str(), "tcp://localhost:80");
+
+        Ok(())
+    }
+}
@@ -17,14 +17,18 @@
  */
 
 //! JSON-RPC 2.0 primitives
+//!
+//! Messages are framed as newline-delimited JSON: every request, response,
+//! notification or batch is serialized on a single line terminated by `\n`.
 use std::fmt;
 
 use async_std::sync::Arc;
+use futures::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
 use rand::Rng;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::{json, Value};
 
-use crate::system::SubscriberPtr;
+use crate::{system::SubscriberPtr, Error};
 
 /// JSON-RPC error codes.
 /// The error codes from and including -32768 to -32000 are reserved for pre-defined errors.
@@ -37,6 +41,7 @@ pub enum ErrorCode {
     InternalError,
     ServerError(i64),
     InvalidId,
+    Unauthorized,
 }
 
 impl ErrorCode {
@@ -50,6 +55,7 @@ impl ErrorCode {
             // -32000 to -32099
             Self::ServerError(c) => c,
             Self::InvalidId => -32001,
+            Self::Unauthorized => -32002,
         }
     }
 
@@ -62,6 +68,7 @@ impl ErrorCode {
             Self::InternalError => "Internal error",
             Self::ServerError(_) => "",
             Self::InvalidId => "Request ID mismatch",
+            Self::Unauthorized => "Unauthorized",
         };
 
         desc.to_string()
@@ -226,6 +233,9 @@ pub struct JsonErrorVal {
     pub code: Value,
     /// Error message
     pub message: Value,
+    /// Additional structured information about the error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
 }
 
 impl JsonError {
@@ -233,8 +243,90 @@ impl JsonError {
         let error = JsonErrorVal {
             code: json!(c.code()),
             message: if m.is_none() { json!(c.desc()) } else { json!(m.unwrap()) },
+            data: None,
         };
 
         Self { jsonrpc: json!("2.0"), error, id }
     }
+
+    /// Attach additional structured information to the error object.
+    pub fn with_data(mut self, data: Value) -> Self {
+        self.error.data = Some(data);
+        self
+    }
+}
+
+/// Read a single newline-delimited message from the given reader, without
+/// its line terminator. Returns `None` if the stream was closed, and an
+/// error if the message is larger than `max_size` bytes.
+pub async fn read_message<R: AsyncBufRead + Unpin>(
+    reader: &mut R,
+    max_size: usize,
+) -> crate::Result<Option<Vec<u8>>> {
+    let mut buf = vec![];
+    let limit = max_size as u64 + 1;
+    if reader.take(limit).read_until(b'\n', &mut buf).await? == 0 {
+        return Ok(None)
+    }
+
+    match buf.last() {
+        Some(b'\n') => {
+            buf.pop();
+            if buf.last() == Some(&b'\r') {
+                buf.pop();
+            }
+        }
+        // The stream was closed after a message without a terminator
+        _ if buf.len() <= max_size => {}
+        _ => return Err(Error::JsonRpcMessageTooLarge(max_size)),
+    }
+
+    Ok(Some(buf))
+}
+
+/// Write a single message to the given writer, terminated by a newline.
+/// The message must not contain any newlines, which holds for JSON
+/// serialized by [`serde_json::to_string`].
+pub async fn write_message<W: AsyncWrite + Unpin>(
+    writer: &mut W,
+    message: &[u8],
+) -> crate::Result<()> {
+    writer.write_all(message).await?;
+    writer.write_all(b"\n").await?;
+    writer.flush().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::io::Cursor;
+
+    use super::*;
+
+    #[async_std::test]
+    async fn message_framing() -> crate::Result<()> {
+        let mut stream = Cursor::new(vec![]);
+        write_message(&mut stream, br#"{"a":1}"#).await?;
+        write_message(&mut stream, br#"[{"b":2},{"c":3}]"#).await?;
+        stream.get_mut().extend_from_slice(b"{\"d\":4}\r\n0123456789\n{\"e\":5}");
+        stream.set_position(0);
+
+        // Pipelined messages are read one at a time
+        assert_eq!(read_message(&mut stream, 16).await?.unwrap(), br#"{"a":1}"#);
+        assert_eq!(read_message(&mut stream, 17).await?.unwrap(), br#"[{"b":2},{"c":3}]"#);
+        assert_eq!(read_message(&mut stream, 16).await?.unwrap(), br#"{"d":4}"#);
+
+        // Messages over the limit are rejected
+        assert!(matches!(
+            read_message(&mut stream, 9).await,
+            Err(Error::JsonRpcMessageTooLarge(9))
+        ));
+
+        // The last message doesn't need a terminator
+        let mut stream = Cursor::new(br#"{"e":5}"#.to_vec());
+        assert_eq!(read_message(&mut stream, 16).await?.unwrap(), br#"{"e":5}"#);
+        assert_eq!(read_message(&mut stream, 16).await?, None);
+
+        Ok(())
+    }
 }
@@ -25,6 +25,15 @@ pub mod client;
 /// Server-side JSON-RPC implementation
 pub mod server;
 
+/// Minimal HTTP/1.1 support for the JSON-RPC client and server
+pub mod http;
+
+/// Authentication and access control for JSON-RPC
+pub mod auth;
+
+/// Typed JSON-RPC method registry
+pub mod registry;
+
 #[cfg(feature = "websockets")]
 /// Websockets client
 pub mod websockets;
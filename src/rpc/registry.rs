@@ -0,0 +1,433 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Typed JSON-RPC method registry.
+//!
+//! Instead of matching on method names and checking parameters by hand,
+//! daemons register their handlers in a [`MethodRegistry`], along with a
+//! description and the names of their parameters. The positional parameters
+//! of a method are declared as a tuple of types deserialized with serde, so
+//! malformed requests get an `InvalidParams` error before reaching the
+//! handler, and trailing `Option` parameters can be left out.
+//!
+//! The registry answers `rpc.discover` with an [OpenRPC](https://spec.open-rpc.org)
+//! document describing every method, which the method docs are generated from.
+use std::{collections::BTreeMap, future::Future};
+
+use async_std::sync::Arc;
+use async_trait::async_trait;
+use futures::future::BoxFuture;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{json, Value};
+
+use super::{
+    jsonrpc::{
+        ErrorCode, JsonError, JsonNotification, JsonRequest, JsonResponse, JsonResult,
+        JsonSubscriber,
+    },
+    server::RequestHandler,
+};
+use crate::system::SubscriberPtr;
+
+/// Version of the OpenRPC specification `rpc.discover` follows
+const OPENRPC_VERSION: &str = "1.2.6";
+
+/// Error returned by a method handler, sent back as a JSON-RPC error.
+#[derive(Clone, Debug)]
+pub struct MethodError {
+    pub code: ErrorCode,
+    pub message: Option<String>,
+}
+
+impl MethodError {
+    pub fn new(code: ErrorCode, message: Option<String>) -> Self {
+        Self { code, message }
+    }
+}
+
+impl From<ErrorCode> for MethodError {
+    fn from(code: ErrorCode) -> Self {
+        Self::new(code, None)
+    }
+}
+
+pub type MethodResult<T> = std::result::Result<T, MethodError>;
+
+/// Types that can describe themselves with a JSON schema, used for the
+/// parameters and results of methods.
+pub trait RpcSchema {
+    fn schema() -> Value;
+
+    /// Whether a parameter of this type has to be given
+    fn required() -> bool {
+        true
+    }
+}
+
+macro_rules! impl_schema {
+    ($schema:tt, $($t:ty),+) => {
+        $(impl RpcSchema for $t {
+            fn schema() -> Value {
+                json!($schema)
+            }
+        })+
+    };
+}
+
+impl_schema!({"type": "boolean"}, bool);
+impl_schema!({"type": "integer", "minimum": 0}, u8, u16, u32, u64, usize);
+impl_schema!({"type": "integer"}, i8, i16, i32, i64);
+impl_schema!({"type": "number"}, f32, f64);
+impl_schema!({"type": "string"}, String);
+impl_schema!({}, Value);
+
+impl<T: RpcSchema> RpcSchema for Vec<T> {
+    fn schema() -> Value {
+        json!({"type": "array", "items": T::schema()})
+    }
+}
+
+impl<T: RpcSchema, const N: usize> RpcSchema for [T; N] {
+    fn schema() -> Value {
+        json!({"type": "array", "items": T::schema(), "minItems": N, "maxItems": N})
+    }
+}
+
+impl<T: RpcSchema> RpcSchema for Option<T> {
+    fn schema() -> Value {
+        json!({"anyOf": [T::schema(), {"type": "null"}]})
+    }
+
+    fn required() -> bool {
+        false
+    }
+}
+
+macro_rules! impl_tuple_schema {
+    ($len:expr; $($t:ident),+) => {
+        impl<$($t: RpcSchema),+> RpcSchema for ($($t,)+) {
+            fn schema() -> Value {
+                json!({
+                    "type": "array",
+                    "prefixItems": [$($t::schema()),+],
+                    "minItems": $len,
+                    "maxItems": $len,
+                })
+            }
+        }
+    };
+}
+
+impl_tuple_schema!(2; A, B);
+impl_tuple_schema!(3; A, B, C);
+
+/// Positional parameters of a method. Implemented for tuples of up to four
+/// types, each deserialized from the parameter at its position.
+pub trait RpcParams: Sized {
+    /// Parse the `params` of a request, or return why they are invalid
+    fn parse(params: Value) -> std::result::Result<Self, String>;
+
+    /// Schema of each parameter, and whether it has to be given
+    fn schemas() -> Vec<(Value, bool)>;
+}
+
+/// Any number of parameters of any type, for methods that take a
+/// variable number of them and check them by hand.
+pub struct RawParams(pub Vec<Value>);
+
+impl RpcParams for RawParams {
+    fn parse(params: Value) -> std::result::Result<Self, String> {
+        match params {
+            Value::Array(params) => Ok(Self(params)),
+            Value::Null => Ok(Self(vec![])),
+            _ => Err("Params must be an array".to_string()),
+        }
+    }
+
+    fn schemas() -> Vec<(Value, bool)> {
+        vec![(json!({}), false)]
+    }
+}
+
+impl RpcParams for () {
+    fn parse(params: Value) -> std::result::Result<Self, String> {
+        positional(params, 0).map(|_| ())
+    }
+
+    fn schemas() -> Vec<(Value, bool)> {
+        vec![]
+    }
+}
+
+macro_rules! impl_params {
+    ($len:expr; $($t:ident $idx:tt),+) => {
+        impl<$($t: DeserializeOwned + RpcSchema),+> RpcParams for ($($t,)+) {
+            fn parse(params: Value) -> std::result::Result<Self, String> {
+                let mut params = positional(params, $len)?.into_iter();
+                Ok(($(parse_param::<$t>($idx, params.next().unwrap())?,)+))
+            }
+
+            fn schemas() -> Vec<(Value, bool)> {
+                vec![$(($t::schema(), $t::required())),+]
+            }
+        }
+    };
+}
+
+impl_params!(1; A 0);
+impl_params!(2; A 0, B 1);
+impl_params!(3; A 0, B 1, C 2);
+impl_params!(4; A 0, B 1, C 2, D 3);
+
+/// Check the `params` of a request hold at most `len` positional
+/// parameters, and pad the missing ones with `null`.
+fn positional(params: Value, len: usize) -> std::result::Result<Vec<Value>, String> {
+    let mut params = match params {
+        Value::Array(params) => params,
+        Value::Null => vec![],
+        _ => return Err("Params must be an array".to_string()),
+    };
+
+    if params.len() > len {
+        return Err(format!("Expected at most {} params, got {}", len, params.len()))
+    }
+
+    params.resize(len, Value::Null);
+    Ok(params)
+}
+
+fn parse_param<T: DeserializeOwned>(idx: usize, param: Value) -> std::result::Result<T, String> {
+    serde_json::from_value(param).map_err(|e| format!("Invalid param {}: {}", idx, e))
+}
+
+type Handler<S> = Box<dyn Fn(Arc<S>, Value, Value) -> BoxFuture<'static, JsonResult> + Send + Sync>;
+
+struct Method<S> {
+    /// OpenRPC method object, without the handler
+    info: Value,
+    handler: Handler<S>,
+}
+
+/// Registry of the JSON-RPC methods served on a state of type `S`.
+pub struct MethodRegistry<S> {
+    title: String,
+    version: String,
+    methods: BTreeMap<String, Method<S>>,
+}
+
+impl<S: Send + Sync + 'static> MethodRegistry<S> {
+    /// Create an empty registry for the API with the given title and version.
+    pub fn new(title: &str, version: &str) -> Self {
+        Self { title: title.to_string(), version: version.to_string(), methods: BTreeMap::new() }
+    }
+
+    /// Register a method with the given description and parameter names.
+    /// The handler gets the parameters parsed into the tuple `P`, and its
+    /// result is serialized as the reply.
+    pub fn method<P, R, H, F>(&mut self, name: &str, description: &str, params: &[&str], handler: H)
+    where
+        P: RpcParams + Send + 'static,
+        R: Serialize + RpcSchema,
+        H: Fn(Arc<S>, P) -> F + Send + Sync + 'static,
+        F: Future<Output = MethodResult<R>> + Send + 'static,
+    {
+        let handler = move |state, params, id| {
+            let call = P::parse(params).map(|params| handler(state, params));
+            Box::pin(async move {
+                match call {
+                    Ok(call) => match call.await {
+                        Ok(result) => JsonResponse::new(json!(result), id).into(),
+                        Err(e) => JsonError::new(e.code, e.message, id).into(),
+                    },
+                    Err(e) => JsonError::new(ErrorCode::InvalidParams, Some(e), id).into(),
+                }
+            }) as BoxFuture<'static, JsonResult>
+        };
+
+        let result = json!({"name": "result", "schema": R::schema()});
+        self.insert::<P>(name, description, params, result, Box::new(handler));
+    }
+
+    /// Register a method creating a subscription. The reply holds the ID
+    /// of the subscription, and the subscriber's notifications are then
+    /// sent on the same connection.
+    pub fn subscription<P, H, F>(
+        &mut self,
+        name: &str,
+        description: &str,
+        params: &[&str],
+        handler: H,
+    ) where
+        P: RpcParams + Send + 'static,
+        H: Fn(Arc<S>, P) -> F + Send + Sync + 'static,
+        F: Future<Output = MethodResult<SubscriberPtr<JsonNotification>>> + Send + 'static,
+    {
+        let handler = move |state, params, id| {
+            let call = P::parse(params).map(|params| handler(state, params));
+            Box::pin(async move {
+                match call {
+                    Ok(call) => match call.await {
+                        Ok(subscriber) => JsonSubscriber::new(subscriber).into(),
+                        Err(e) => JsonError::new(e.code, e.message, id).into(),
+                    },
+                    Err(e) => JsonError::new(ErrorCode::InvalidParams, Some(e), id).into(),
+                }
+            }) as BoxFuture<'static, JsonResult>
+        };
+
+        let result = json!({
+            "name": "subscription",
+            "description": "Subscription ID, which can be passed to `unsubscribe`",
+            "schema": {"type": "integer", "minimum": 0},
+        });
+        self.insert::<P>(name, description, params, result, Box::new(handler));
+    }
+
+    fn insert<P: RpcParams>(
+        &mut self,
+        name: &str,
+        description: &str,
+        param_names: &[&str],
+        result: Value,
+        handler: Handler<S>,
+    ) {
+        let schemas = P::schemas();
+        assert_eq!(param_names.len(), schemas.len(), "Wrong number of param names for {}", name);
+
+        let params: Vec<Value> = param_names
+            .iter()
+            .zip(schemas)
+            .map(|(name, (schema, required))| {
+                json!({"name": name, "required": required, "schema": schema})
+            })
+            .collect();
+
+        let info = json!({
+            "name": name,
+            "description": description,
+            "paramStructure": "by-position",
+            "params": params,
+            "result": result,
+        });
+
+        let method = Method { info, handler };
+        assert!(
+            self.methods.insert(name.to_string(), method).is_none(),
+            "{} registered twice",
+            name
+        );
+    }
+
+    /// Return the OpenRPC document describing the registered methods.
+    pub fn discover(&self) -> Value {
+        let methods: Vec<&Value> = self.methods.values().map(|m| &m.info).collect();
+        json!({
+            "openrpc": OPENRPC_VERSION,
+            "info": {"title": self.title, "version": self.version},
+            "methods": methods,
+        })
+    }
+
+    /// Handle a request with the registered method it calls, or with the
+    /// built-in `rpc.discover`.
+    pub async fn handle(&self, state: Arc<S>, req: JsonRequest) -> JsonResult {
+        let name = req.method.as_str().unwrap_or_default();
+        if name == "rpc.discover" {
+            return JsonResponse::new(self.discover(), req.id).into()
+        }
+
+        match self.methods.get(name) {
+            Some(method) => (method.handler)(state, req.params, req.id).await,
+            None => JsonError::new(ErrorCode::MethodNotFound, None, req.id).into(),
+        }
+    }
+}
+
+/// [`RequestHandler`] serving the methods of a [`MethodRegistry`] on
+/// the given state.
+pub struct RegistryHandler<S> {
+    state: Arc<S>,
+    registry: MethodRegistry<S>,
+}
+
+impl<S> RegistryHandler<S> {
+    pub fn new(state: Arc<S>, registry: MethodRegistry<S>) -> Self {
+        Self { state, registry }
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync + 'static> RequestHandler for RegistryHandler<S> {
+    async fn handle_request(&self, req: JsonRequest) -> JsonResult {
+        self.registry.handle(self.state.clone(), req).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Counter(u64);
+
+    async fn call(handler: &RegistryHandler<Counter>, method: &str, params: Value) -> Value {
+        match handler.handle_request(JsonRequest::new(method, params)).await {
+            JsonResult::Response(r) => r.result,
+            JsonResult::Error(e) => json!({"code": e.error.code}),
+            _ => unreachable!(),
+        }
+    }
+
+    #[async_std::test]
+    async fn typed_methods() {
+        let mut registry = MethodRegistry::new("counter", "0.1.0");
+        registry.method(
+            "add",
+            "Adds to the counter.",
+            &["n", "times"],
+            |c: Arc<Counter>, (n, times): (u64, Option<u64>)| async move {
+                Ok(c.0 + n * times.unwrap_or(1))
+            },
+        );
+        registry.method("fail", "Always fails.", &[], |_, ()| async move {
+            Err::<bool, MethodError>(ErrorCode::ServerError(-32100).into())
+        });
+        let handler = RegistryHandler::new(Arc::new(Counter(1)), registry);
+
+        assert_eq!(call(&handler, "add", json!([2])).await, json!(3));
+        assert_eq!(call(&handler, "add", json!([2, 3])).await, json!(7));
+        assert_eq!(call(&handler, "fail", json!([])).await, json!({"code": -32100}));
+
+        // Parameters are checked before reaching the handler
+        let invalid = json!({"code": -32602});
+        assert_eq!(call(&handler, "add", json!([])).await, invalid);
+        assert_eq!(call(&handler, "add", json!(["2"])).await, invalid);
+        assert_eq!(call(&handler, "add", json!([2, 3, 4])).await, invalid);
+        assert_eq!(call(&handler, "add", json!({"n": 2})).await, invalid);
+        assert_eq!(call(&handler, "fail", json!([1])).await, invalid);
+        assert_eq!(call(&handler, "sub", json!([])).await, json!({"code": -32601}));
+
+        let schema = call(&handler, "rpc.discover", json!([])).await;
+        assert_eq!(schema["info"]["title"], json!("counter"));
+        let methods = schema["methods"].as_array().unwrap();
+        assert_eq!(methods.len(), 2);
+        assert_eq!(methods[0]["name"], json!("add"));
+        assert_eq!(methods[0]["params"][0]["schema"], json!({"type": "integer", "minimum": 0}));
+        assert_eq!(methods[0]["params"][1]["required"], json!(false));
+        assert_eq!(methods[0]["result"]["schema"]["type"], json!("integer"));
+    }
+}
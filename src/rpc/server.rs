@@ -17,18 +17,54 @@
  */
 
 //! JSON-RPC server-side implementation.
-use async_std::sync::Arc;
+//!
+//! Besides the transports supported by [`crate::net`], where messages are
+//! delimited by newlines, the server can be reached over plain HTTP with
+//! `http://` accept URLs, where each `POST` request carries a message, and
+//! over websockets with `ws://` accept URLs. Subscriptions need a streaming
+//! transport, so they are unavailable over HTTP.
+//!
+//! Access to methods can be restricted with [`AuthSettings`], as described
+//! in [`super::auth`].
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use async_std::sync::{Arc, Mutex};
 use async_trait::async_trait;
-use futures::{AsyncReadExt, AsyncWriteExt};
+use async_tungstenite::tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response},
+    http::StatusCode,
+    protocol::WebSocketConfig,
+    Error as WsError, Message,
+};
+use ed25519_compact::PublicKey;
+use futures::{
+    future::join_all, io::BufReader, select, AsyncReadExt, FutureExt, SinkExt, StreamExt,
+};
 use log::{debug, error, info, warn};
+use serde_json::{json, Value};
+use smol::{
+    channel::TrySendError,
+    lock::{Semaphore, SemaphoreGuardArc},
+};
 use url::Url;
 
-use super::jsonrpc::{JsonRequest, JsonResult};
+use super::{
+    auth::AuthSettings,
+    http::{read_body, read_head, tcp_url, write_response},
+    jsonrpc::{
+        read_message, write_message, ErrorCode, JsonError, JsonRequest, JsonResponse, JsonResult,
+        JsonSubscriber,
+    },
+};
 use crate::{
     net::transport::{
-        TcpTransport, TorTransport, Transport, TransportListener, TransportName, TransportStream,
-        UnixTransport,
+        TcpTransport, TlsUpgrade, TorTransport, Transport, TransportListener, TransportName,
+        TransportStream, UnixTransport,
     },
+    system::subscriber::SubscriptionId,
     Error, Result,
 };
 
@@ -40,91 +76,578 @@ pub trait RequestHandler: Sync + Send {
     async fn handle_request(&self, req: JsonRequest) -> JsonResult;
 }
 
+/// Limits applied by the JSON-RPC server to every connection.
+#[derive(Clone, Debug)]
+pub struct ServerSettings {
+    /// Maximum size in bytes of an incoming message, be it a single
+    /// request or a batch
+    pub max_request_size: usize,
+    /// Maximum number of requests in a batch
+    pub max_batch_size: usize,
+    /// Maximum number of messages handled concurrently per connection
+    pub max_in_flight: usize,
+    /// Values of the `Origin` header accepted on HTTP and websocket
+    /// requests. Browsers always send it, so the default empty list
+    /// keeps web pages from reaching the server, while other clients
+    /// don't send it and are unaffected.
+    pub allowed_origins: Vec<String>,
+    /// Authentication required to call non-public methods. Every method
+    /// is open to anyone if unset.
+    pub auth: Option<AuthSettings>,
+}
+
+impl ServerSettings {
+    /// Whether a request with the given `Origin` header is allowed.
+    fn origin_allowed(&self, origin: Option<&str>) -> bool {
+        match origin {
+            Some(origin) => self.allowed_origins.iter().any(|o| o == origin),
+            None => true,
+        }
+    }
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            max_request_size: 8 * 1024 * 1024,
+            max_batch_size: 100,
+            max_in_flight: 16,
+            allowed_origins: vec![],
+            auth: None,
+        }
+    }
+}
+
+/// Notifications a connection may have queued for writing. Clients that
+/// don't read them fast enough are disconnected.
+const MAX_QUEUED_NOTIFICATIONS: usize = 128;
+
+/// A message queued for writing, along with the slot of the message it
+/// replies to, which is only released once the reply is written.
+type Outgoing = (String, Option<SemaphoreGuardArc>);
+
+/// How JSON-RPC messages are carried over an accepted stream.
+#[derive(Clone, Copy, Debug)]
+enum Frontend {
+    /// Newline-delimited messages
+    Raw,
+    /// One message per HTTP `POST` request
+    Http,
+    /// One message per websocket message
+    WebSocket,
+}
+
+/// State shared by the tasks serving a single JSON-RPC connection.
+struct Connection<T: RequestHandler> {
+    peer_addr: Url,
+    rh: Arc<T>,
+    settings: ServerSettings,
+    /// Outgoing messages, written to the stream by a dedicated task.
+    /// Unset when replies go back in lockstep with requests, in which
+    /// case subscriptions are unavailable.
+    writer: Option<smol::channel::Sender<Outgoing>>,
+    /// Active subscriptions of the connection. Dropping the sender stops
+    /// the task forwarding the subscription's notifications.
+    subscriptions: Mutex<HashMap<SubscriptionId, smol::channel::Sender<()>>>,
+    /// Slots for messages being handled
+    in_flight: Arc<Semaphore>,
+    /// Whether the client authenticated, granting it access to all methods
+    authenticated: AtomicBool,
+}
+
+impl<T: RequestHandler + 'static> Connection<T> {
+    fn new(
+        peer_addr: Url,
+        rh: Arc<T>,
+        settings: ServerSettings,
+        writer: Option<smol::channel::Sender<Outgoing>>,
+    ) -> Self {
+        let in_flight = Arc::new(Semaphore::new(settings.max_in_flight));
+        Self {
+            peer_addr,
+            rh,
+            settings,
+            writer,
+            subscriptions: Mutex::new(HashMap::new()),
+            in_flight,
+            authenticated: AtomicBool::new(false),
+        }
+    }
+
+    /// Authenticate the connection if the given TLS client key is allowed.
+    fn authenticate_key(&self, key: Option<PublicKey>) {
+        let (Some(auth), Some(key)) = (&self.settings.auth, key) else { return };
+        if auth.check_client_key(&key) {
+            debug!(target: "rpc::server", "Authenticated {} by its TLS client key", self.peer_addr);
+            self.authenticated.store(true, Ordering::SeqCst);
+        }
+    }
+
+    /// Whether the client may call the given method.
+    fn permitted(&self, method: &str) -> bool {
+        match &self.settings.auth {
+            Some(auth) => self.authenticated.load(Ordering::SeqCst) || auth.is_public(method),
+            None => true,
+        }
+    }
+
+    /// Handle an incoming message in the background, and queue its reply
+    /// for writing. Waits for a slot first, which is held until the reply
+    /// is written, so a client can't pile up an unbounded amount of work
+    /// or of replies it doesn't read.
+    async fn spawn_message<'a>(self: &Arc<Self>, message: Vec<u8>, ex: &Arc<smol::Executor<'a>>) {
+        let Some(writer) = self.writer.clone() else { return };
+        let permit = self.in_flight.clone().acquire_arc().await;
+        let conn = self.clone();
+        let _ex = ex.clone();
+        ex.spawn(async move {
+            let reply = conn.handle_message(&message, &_ex).await;
+            let _ = writer.send((reply, Some(permit))).await;
+        })
+        .detach();
+    }
+
+    /// Handle an incoming message, which is either a single request or
+    /// a batch of them, and return the serialized reply.
+    async fn handle_message(&self, message: &[u8], ex: &Arc<smol::Executor<'_>>) -> String {
+        let value: Value = match serde_json::from_slice(message) {
+            Ok(v) => v,
+            Err(e) => {
+                warn!(target: "rpc::server", "JSON-RPC server received invalid JSON from {}: {}", self.peer_addr, e);
+                return json!(JsonError::new(ErrorCode::ParseError, None, Value::Null)).to_string()
+            }
+        };
+
+        let batch = match value {
+            Value::Array(batch) => batch,
+            value => return self.handle_request(value, ex).await.to_string(),
+        };
+
+        if batch.is_empty() || batch.len() > self.settings.max_batch_size {
+            warn!(target: "rpc::server", "JSON-RPC server received a batch of {} requests from {}", batch.len(), self.peer_addr);
+            let msg = format!("Batches must hold 1 to {} requests", self.settings.max_batch_size);
+            return json!(JsonError::new(ErrorCode::InvalidRequest, Some(msg), Value::Null))
+                .to_string()
+        }
+
+        // Requests of a batch are handled concurrently
+        let replies = join_all(batch.into_iter().map(|req| self.handle_request(req, ex))).await;
+        json!(replies).to_string()
+    }
+
+    /// Handle a single request and return its reply.
+    async fn handle_request(&self, value: Value, ex: &Arc<smol::Executor<'_>>) -> Value {
+        let id = value.get("id").cloned().unwrap_or(Value::Null);
+        let req: JsonRequest = match serde_json::from_value(value) {
+            Ok(v) => v,
+            Err(e) => {
+                warn!(target: "rpc::server", "JSON-RPC server received invalid request from {}: {}", self.peer_addr, e);
+                return json!(JsonError::new(ErrorCode::InvalidRequest, None, id))
+            }
+        };
+
+        let method = req.method.as_str().unwrap_or_default().to_string();
+        match method.as_str() {
+            "unsubscribe" => return self.unsubscribe(req).await,
+            "auth" if self.settings.auth.is_some() => return self.auth(req),
+            _ => {}
+        }
+
+        if !self.permitted(&method) {
+            warn!(target: "rpc::server", "JSON-RPC server denied {} to unauthenticated {}", method, self.peer_addr);
+            return json!(JsonError::new(ErrorCode::Unauthorized, None, id))
+        }
+
+        match self.rh.handle_request(req).await {
+            JsonResult::Subscriber(sub) => {
+                let Some(writer) = &self.writer else {
+                    let msg = "Subscriptions need a streaming transport".to_string();
+                    return json!(JsonError::new(ErrorCode::InvalidRequest, Some(msg), id))
+                };
+                let subscription_id = self.subscribe(sub, writer.clone(), ex).await;
+                json!(JsonResponse::new(json!(subscription_id), id))
+            }
+            reply => json!(reply),
+        }
+    }
+
+    /// Start forwarding the notifications of a subscriber to the given
+    /// writer, and return the ID of the subscription. The connection is
+    /// closed if the writer queue fills up.
+    async fn subscribe(
+        &self,
+        sub: JsonSubscriber,
+        writer: smol::channel::Sender<Outgoing>,
+        ex: &Arc<smol::Executor<'_>>,
+    ) -> SubscriptionId {
+        let subscription = sub.subscriber.subscribe().await;
+        let subscription_id = subscription.get_id();
+        let (stop_send, stop_recv) = smol::channel::bounded::<()>(1);
+        self.subscriptions.lock().await.insert(subscription_id, stop_send);
+        debug!(target: "rpc::server", "Added subscription {} for {}", subscription_id, self.peer_addr);
+
+        let peer_addr = self.peer_addr.clone();
+        ex.spawn(async move {
+            loop {
+                // Listen subscription for notifications
+                let notification = select! {
+                    notification = subscription.receive().fuse() => notification,
+                    _ = stop_recv.recv().fuse() => break,
+                };
+
+                // Push notification
+                let message = serde_json::to_string(&notification).unwrap();
+                match writer.try_send((message, None)) {
+                    Ok(()) => {}
+                    Err(TrySendError::Full(_)) => {
+                        warn!(target: "rpc::server", "JSON-RPC server dropping {}, which doesn't read its notifications", peer_addr);
+                        writer.close();
+                        break
+                    }
+                    Err(TrySendError::Closed(_)) => break,
+                }
+            }
+            subscription.unsubscribe().await;
+        })
+        .detach();
+
+        subscription_id
+    }
+
+    // RPCAPI:
+    // Cancels a subscription of this connection, given the ID returned by
+    // the request that created it. Returns `false` if there was no such
+    // subscription.
+    //
+    // --> {"jsonrpc": "2.0", "method": "unsubscribe", "params": [1234], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": true, "id": 1}
+    async fn unsubscribe(&self, req: JsonRequest) -> Value {
+        let params = req.params.as_array();
+        let Some(subscription_id) = params.filter(|p| p.len() == 1).and_then(|p| p[0].as_u64())
+        else {
+            return json!(JsonError::new(ErrorCode::InvalidParams, None, req.id))
+        };
+
+        let removed = self.subscriptions.lock().await.remove(&subscription_id).is_some();
+        if removed {
+            debug!(target: "rpc::server", "Removed subscription {} for {}", subscription_id, self.peer_addr);
+        }
+
+        json!(JsonResponse::new(json!(removed), req.id))
+    }
+
+    // RPCAPI:
+    // Authenticates this connection with a bearer token, granting it
+    // access to all methods. Over HTTP, the token goes in the
+    // `Authorization` header of every request instead.
+    //
+    // --> {"jsonrpc": "2.0", "method": "auth", "params": ["token"], "id": 1}
+    // <-- {"jsonrpc": "2.0", "result": true, "id": 1}
+    fn auth(&self, req: JsonRequest) -> Value {
+        if self.writer.is_none() {
+            let msg = "Use the Authorization header over HTTP".to_string();
+            return json!(JsonError::new(ErrorCode::InvalidRequest, Some(msg), req.id))
+        }
+
+        let params = req.params.as_array();
+        let Some(token) = params.filter(|p| p.len() == 1).and_then(|p| p[0].as_str()) else {
+            return json!(JsonError::new(ErrorCode::InvalidParams, None, req.id))
+        };
+
+        if !self.settings.auth.as_ref().unwrap().check_token(token) {
+            warn!(target: "rpc::server", "JSON-RPC server got an invalid token from {}", self.peer_addr);
+            return json!(JsonError::new(ErrorCode::Unauthorized, None, req.id))
+        }
+
+        debug!(target: "rpc::server", "Authenticated {} by token", self.peer_addr);
+        self.authenticated.store(true, Ordering::SeqCst);
+        json!(JsonResponse::new(json!(true), req.id))
+    }
+}
+
 /// Internal accept function that runs inside a loop for accepting incoming
-/// JSON-RPC requests and passing them to the [`RequestHandler`].
-async fn accept(
-    mut stream: Box<dyn TransportStream>,
+/// JSON-RPC messages and passing them to the [`RequestHandler`].
+/// Messages are handled concurrently, and replies and subscription
+/// notifications are written back as they become available. The
+/// connection is dropped once the writer stops.
+async fn accept<T: RequestHandler + 'static>(
+    stream: Box<dyn TransportStream>,
     peer_addr: Url,
-    rh: Arc<impl RequestHandler + 'static>,
+    rh: Arc<T>,
+    settings: ServerSettings,
+    ex: Arc<smol::Executor<'_>>,
 ) -> Result<()> {
+    let peer_key = stream.peer_key();
+    let (reader, mut writer) = stream.split();
+    let mut reader = BufReader::new(reader);
+
+    let capacity = settings.max_in_flight + MAX_QUEUED_NOTIFICATIONS;
+    let (writer_send, writer_recv) = smol::channel::bounded::<Outgoing>(capacity);
+    // Closed when the writer stops
+    let (writer_done_send, writer_done) = smol::channel::bounded::<()>(1);
+    let _peer_addr = peer_addr.clone();
+    ex.spawn(async move {
+        // The slot of a reply is released once it's written
+        while let Ok((message, _permit)) = writer_recv.recv().await {
+            debug!(target: "rpc::server", "{} <-- {}", _peer_addr, message);
+            if let Err(e) = write_message(&mut writer, message.as_bytes()).await {
+                error!(target: "rpc::server", "JSON-RPC server failed writing to {} socket: {}", _peer_addr, e);
+                break
+            }
+        }
+        writer_recv.close();
+        drop(writer_done_send);
+    })
+    .detach();
+
+    let conn =
+        Arc::new(Connection::new(peer_addr.clone(), rh, settings, Some(writer_send.clone())));
+    conn.authenticate_key(peer_key);
+
     loop {
-        // FIXME: Nasty size. 8M
-        let mut buf = vec![0; 1024 * 8192];
+        let message = select! {
+            message = read_message(&mut reader, conn.settings.max_request_size).fuse() => message,
+            _ = writer_done.recv().fuse() => break,
+        };
 
-        let n = match stream.read(&mut buf).await {
-            Ok(n) if n == 0 => {
-                debug!(target: "rpc::server", "Closed connection for {}", peer_addr);
+        let message = match message {
+            Ok(Some(v)) => v,
+            Ok(None) => break,
+            Err(Error::JsonRpcMessageTooLarge(max_size)) => {
+                // We can't find the start of the next message anymore,
+                // so we reply and drop the connection.
+                warn!(target: "rpc::server", "JSON-RPC server received a message over {} bytes from {}", max_size, peer_addr);
+                let msg = format!("Messages must not exceed {} bytes", max_size);
+                let reply = JsonError::new(ErrorCode::InvalidRequest, Some(msg), Value::Null);
+                let _ = writer_send.try_send((json!(reply).to_string(), None));
                 break
             }
-            Ok(n) => n,
             Err(e) => {
                 error!(target: "rpc::server", "JSON-RPC server failed reading from {} socket: {}", peer_addr, e);
-                debug!(target: "rpc::server", "Closed connection for {}", peer_addr);
                 break
             }
         };
 
-        let r: JsonRequest = match serde_json::from_slice(&buf[0..n]) {
-            Ok(r) => {
-                debug!(target: "rpc::server", "{} --> {}", peer_addr, String::from_utf8_lossy(&buf));
-                r
-            }
+        if message.iter().all(u8::is_ascii_whitespace) {
+            continue
+        }
+        debug!(target: "rpc::server", "{} --> {}", peer_addr, String::from_utf8_lossy(&message));
+        conn.spawn_message(message, &ex).await;
+    }
+
+    debug!(target: "rpc::server", "Closed connection for {}", peer_addr);
+    // Stop the subscriptions and let the writer finish once in-flight
+    // requests are done.
+    conn.subscriptions.lock().await.clear();
+
+    Ok(())
+}
+
+/// Accept function for HTTP connections, where each `POST` request
+/// carries a JSON-RPC message and the response carries its reply.
+/// Requests of a connection are handled one after the other, as HTTP/1.1
+/// responses must come back in order.
+async fn accept_http<T: RequestHandler + 'static>(
+    stream: Box<dyn TransportStream>,
+    peer_addr: Url,
+    rh: Arc<T>,
+    settings: ServerSettings,
+    ex: Arc<smol::Executor<'_>>,
+) -> Result<()> {
+    let (reader, mut writer) = stream.split();
+    let mut reader = BufReader::new(reader);
+    let conn = Connection::new(peer_addr.clone(), rh, settings, None);
+    let close = [("Connection", "close")];
+
+    loop {
+        let head = match read_head(&mut reader).await {
+            Ok(Some(v)) => v,
+            Ok(None) => break,
             Err(e) => {
-                warn!(target: "rpc::server", "JSON-RPC server received invalid JSON from {}: {}", peer_addr, e);
-                debug!(target: "rpc::server", "Closed connection for {}", peer_addr);
+                warn!(target: "rpc::server", "JSON-RPC server received invalid HTTP request from {}: {}", peer_addr, e);
+                write_response(&mut writer, 400, "Bad Request", &close, &[]).await?;
                 break
             }
         };
 
-        let reply = rh.handle_request(r).await;
-        match reply {
-            JsonResult::Subscriber(sub) => {
-                let subscription = sub.subscriber.subscribe().await;
-                loop {
-                    // Listen subscription for notifications
-                    let notification = subscription.receive().await;
-
-                    // Push notification
-                    let j = serde_json::to_string(&notification).unwrap();
-                    debug!(target: "rpc::server", "{} <-- {}", peer_addr, j);
-
-                    if let Err(e) = stream.write_all(j.as_bytes()).await {
-                        error!(target: "rpc::server", "JSON-RPC server failed writing to {} socket: {}", peer_addr, e);
-                        debug!(target: "rpc::server", "Closed connection for {}", peer_addr);
-                        break
-                    }
-                }
-                subscription.unsubscribe().await;
-            }
-            _ => {
-                let j = serde_json::to_string(&reply).unwrap();
-                debug!(target: "rpc::server", "{} <-- {}", peer_addr, j);
+        if head.start_line[0] != "POST" {
+            let headers = [("Allow", "POST"), ("Connection", "close")];
+            write_response(&mut writer, 405, "Method Not Allowed", &headers, &[]).await?;
+            break
+        }
+
+        if !conn.settings.origin_allowed(head.header("origin")) {
+            warn!(target: "rpc::server", "JSON-RPC server rejected HTTP request from {} with origin {:?}", peer_addr, head.header("origin"));
+            write_response(&mut writer, 403, "Forbidden", &close, &[]).await?;
+            break
+        }
 
-                if let Err(e) = stream.write_all(j.as_bytes()).await {
-                    error!(target: "rpc::server", "JSON-RPC server failed writing to {} socket: {}", peer_addr, e);
-                    debug!(target: "rpc::server", "Closed connection for {}", peer_addr);
+        // Each request carries its own credentials
+        let token = head.header("authorization").and_then(|v| v.strip_prefix("Bearer "));
+        let authenticated = match (&conn.settings.auth, token) {
+            (Some(auth), Some(token)) => {
+                if !auth.check_token(token.trim()) {
+                    warn!(target: "rpc::server", "JSON-RPC server got an invalid token from {}", peer_addr);
+                    let headers = [("WWW-Authenticate", "Bearer"), ("Connection", "close")];
+                    write_response(&mut writer, 401, "Unauthorized", &headers, &[]).await?;
                     break
                 }
+                true
+            }
+            _ => false,
+        };
+        conn.authenticated.store(authenticated, Ordering::SeqCst);
+
+        let len = match head.content_length() {
+            Ok(v) => v,
+            Err(e) => {
+                warn!(target: "rpc::server", "JSON-RPC server received invalid HTTP request from {}: {}", peer_addr, e);
+                write_response(&mut writer, 400, "Bad Request", &close, &[]).await?;
+                break
+            }
+        };
+
+        let max_size = conn.settings.max_request_size;
+        if len > max_size {
+            warn!(target: "rpc::server", "JSON-RPC server received a message over {} bytes from {}", max_size, peer_addr);
+            let msg = format!("Messages must not exceed {} bytes", max_size);
+            let reply = JsonError::new(ErrorCode::InvalidRequest, Some(msg), Value::Null);
+            let reply = json!(reply).to_string();
+            write_response(&mut writer, 413, "Payload Too Large", &close, reply.as_bytes()).await?;
+            break
+        }
+
+        let message = read_body(&mut reader, len).await?;
+        debug!(target: "rpc::server", "{} --> {}", peer_addr, String::from_utf8_lossy(&message));
+        let reply = conn.handle_message(&message, &ex).await;
+        debug!(target: "rpc::server", "{} <-- {}", peer_addr, reply);
+
+        if !head.keep_alive() {
+            write_response(&mut writer, 200, "OK", &close, reply.as_bytes()).await?;
+            break
+        }
+        write_response(&mut writer, 200, "OK", &[], reply.as_bytes()).await?;
+    }
+
+    debug!(target: "rpc::server", "Closed connection for {}", peer_addr);
+    Ok(())
+}
+
+/// Accept function for websocket connections, where each websocket
+/// message carries a JSON-RPC message. Apart from the framing, this
+/// behaves like [`accept()`].
+async fn accept_ws<T: RequestHandler + 'static>(
+    stream: Box<dyn TransportStream>,
+    peer_addr: Url,
+    rh: Arc<T>,
+    settings: ServerSettings,
+    ex: Arc<smol::Executor<'_>>,
+) -> Result<()> {
+    let _settings = settings.clone();
+    let check_origin = move |req: &Request, resp: Response| {
+        let origin = req.headers().get("origin").map(|v| v.to_str().unwrap_or_default());
+        if _settings.origin_allowed(origin) {
+            return Ok(resp)
+        }
+
+        let mut resp = ErrorResponse::new(None);
+        *resp.status_mut() = StatusCode::FORBIDDEN;
+        Err(resp)
+    };
+
+    let config = WebSocketConfig {
+        max_message_size: Some(settings.max_request_size),
+        max_frame_size: Some(settings.max_request_size),
+        ..Default::default()
+    };
+
+    let ws =
+        async_tungstenite::accept_hdr_async_with_config(stream, check_origin, Some(config)).await?;
+    let (mut ws_writer, mut ws_reader) = ws.split();
+
+    let capacity = settings.max_in_flight + MAX_QUEUED_NOTIFICATIONS;
+    let (writer_send, writer_recv) = smol::channel::bounded::<Outgoing>(capacity);
+    // Closed when the writer stops
+    let (writer_done_send, writer_done) = smol::channel::bounded::<()>(1);
+    let _peer_addr = peer_addr.clone();
+    ex.spawn(async move {
+        // The slot of a reply is released once it's written
+        while let Ok((message, _permit)) = writer_recv.recv().await {
+            debug!(target: "rpc::server", "{} <-- {}", _peer_addr, message);
+            if let Err(e) = ws_writer.send(Message::Text(message)).await {
+                error!(target: "rpc::server", "JSON-RPC server failed writing to {} socket: {}", _peer_addr, e);
+                break
             }
         }
+        writer_recv.close();
+        drop(writer_done_send);
+    })
+    .detach();
+
+    let conn =
+        Arc::new(Connection::new(peer_addr.clone(), rh, settings, Some(writer_send.clone())));
+
+    loop {
+        let message = select! {
+            message = ws_reader.next().fuse() => message,
+            _ = writer_done.recv().fuse() => break,
+        };
+
+        let message = match message {
+            Some(Ok(Message::Text(v))) => v.into_bytes(),
+            Some(Ok(Message::Binary(v))) => v,
+            // Pings are answered by the websocket implementation
+            Some(Ok(Message::Ping(_) | Message::Pong(_) | Message::Frame(_))) => continue,
+            Some(Ok(Message::Close(_))) | None => break,
+            Some(Err(WsError::Capacity(e))) => {
+                warn!(target: "rpc::server", "JSON-RPC server received an oversized message from {}: {}", peer_addr, e);
+                let msg =
+                    format!("Messages must not exceed {} bytes", conn.settings.max_request_size);
+                let reply = JsonError::new(ErrorCode::InvalidRequest, Some(msg), Value::Null);
+                let _ = writer_send.try_send((json!(reply).to_string(), None));
+                break
+            }
+            Some(Err(e)) => {
+                error!(target: "rpc::server", "JSON-RPC server failed reading from {} socket: {}", peer_addr, e);
+                break
+            }
+        };
+
+        debug!(target: "rpc::server", "{} --> {}", peer_addr, String::from_utf8_lossy(&message));
+        conn.spawn_message(message, &ex).await;
     }
 
+    debug!(target: "rpc::server", "Closed connection for {}", peer_addr);
+    conn.subscriptions.lock().await.clear();
+
     Ok(())
 }
 
-/// Wrapper function around [`accept()`] to take the incoming connection and
-/// pass it forward.
+/// Wrapper function around [`accept()`], [`accept_http()`] and
+/// [`accept_ws()`] to take the incoming connection and pass it forward.
 async fn run_accept_loop(
     listener: Box<dyn TransportListener>,
     rh: Arc<impl RequestHandler + 'static>,
+    settings: ServerSettings,
+    frontend: Frontend,
     ex: Arc<smol::Executor<'_>>,
 ) -> Result<()> {
     while let Ok((stream, peer_addr)) = listener.next().await {
         info!(target: "rpc::server", "JSON-RPC server accepted connection from {}", peer_addr);
         // Detaching requests handling
         let _rh = rh.clone();
+        let _settings = settings.clone();
+        let _ex = ex.clone();
         ex.spawn(async move {
-            if let Err(e) = accept(stream, peer_addr.clone(), _rh).await {
+            let res = match frontend {
+                Frontend::Raw => accept(stream, peer_addr.clone(), _rh, _settings, _ex).await,
+                Frontend::Http => accept_http(stream, peer_addr.clone(), _rh, _settings, _ex).await,
+                Frontend::WebSocket => {
+                    accept_ws(stream, peer_addr.clone(), _rh, _settings, _ex).await
+                }
+            };
+
+            if let Err(e) = res {
                 error!(target: "rpc::server", "JSON-RPC server error on handling request of {}: {}", peer_addr, e);
             }
         }).detach();
@@ -139,11 +662,22 @@ pub async fn listen_and_serve(
     accept_url: Url,
     rh: Arc<impl RequestHandler + 'static>,
     ex: Arc<smol::Executor<'_>>,
+) -> Result<()> {
+    listen_and_serve_with_settings(accept_url, rh, ServerSettings::default(), ex).await
+}
+
+/// Same as [`listen_and_serve()`], but with the given [`ServerSettings`]
+/// instead of the default ones.
+pub async fn listen_and_serve_with_settings(
+    accept_url: Url,
+    rh: Arc<impl RequestHandler + 'static>,
+    settings: ServerSettings,
+    ex: Arc<smol::Executor<'_>>,
 ) -> Result<()> {
     debug!(target: "rpc::server", "Trying to bind listener on {}", accept_url);
 
     macro_rules! accept {
-        ($listener:expr, $transport:expr, $upgrade:expr) => {{
+        ($listener:expr, $transport:expr, $upgrade:expr, $frontend:expr) => {{
             if let Err(err) = $listener {
                 error!(target: "rpc::server", "JSON-RPC server setup for {} failed: {}", accept_url, err);
                 return Err(Error::BindFailed(accept_url.as_str().into()))
@@ -159,24 +693,38 @@ pub async fn listen_and_serve(
             match $upgrade {
                 None => {
                     info!(target: "rpc::server", "JSON-RPC listener bound to {}", accept_url);
-                    run_accept_loop(Box::new(listener), rh, ex.clone()).await?;
+                    run_accept_loop(Box::new(listener), rh, settings, $frontend, ex.clone()).await?;
                 }
                 Some(u) if u == "tls" => {
-                    let tls_listener = $transport.upgrade_listener(listener)?.await?;
+                    let tls_listener = $transport.upgrade_listener(TlsUpgrade::default(), listener)?.await?;
                     info!(target: "rpc::server", "JSON-RPC listener bound to {}", accept_url);
-                    run_accept_loop(Box::new(tls_listener), rh, ex.clone()).await?;
+                    run_accept_loop(Box::new(tls_listener), rh, settings, $frontend, ex.clone()).await?;
                 }
                 Some(u) => return Err(Error::UnsupportedTransportUpgrade(u)),
             }
         }};
     }
 
+    // HTTP and websockets are served over plain TCP
+    let frontend = match accept_url.scheme() {
+        "http" => Some(Frontend::Http),
+        "ws" => Some(Frontend::WebSocket),
+        _ => None,
+    };
+
+    if let Some(frontend) = frontend {
+        let transport = TcpTransport::new(None, 1024);
+        let listener = transport.listen_on(tcp_url(&accept_url)?);
+        accept!(listener, transport, None, frontend);
+        return Ok(())
+    }
+
     let transport_name = TransportName::try_from(accept_url.clone())?;
     match transport_name {
         TransportName::Tcp(upgrade) => {
             let transport = TcpTransport::new(None, 1024);
             let listener = transport.listen_on(accept_url.clone());
-            accept!(listener, transport, upgrade);
+            accept!(listener, transport, upgrade, Frontend::Raw);
         }
         TransportName::Tor(upgrade) => {
             let (socks5_url, torc_url, auth_cookie) = TorTransport::get_listener_env()?;
@@ -188,12 +736,12 @@ pub async fn listen_and_serve(
             info!(target: "rpc::server", "Created ephemeral hidden service: {}", hurl.to_string());
 
             let listener = transport.clone().listen_on(accept_url.clone());
-            accept!(listener, transport, upgrade);
+            accept!(listener, transport, upgrade, Frontend::Raw);
         }
         TransportName::Unix => {
             let transport = UnixTransport::new();
             let listener = transport.listen_on(accept_url.clone());
-            accept!(listener, transport, None);
+            accept!(listener, transport, None, Frontend::Raw);
         }
         _ => unimplemented!(),
     }
@@ -0,0 +1,391 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::io::Cursor;
+
+use darkfi_sdk::{
+    crypto::{ContractId, PublicKey},
+    error::{CALLER_ACCESS_DENIED, CALL_DEPTH_EXCEEDED, INVOKE_CONTRACT_FAILED},
+    pasta::pallas,
+    tx::ContractCall,
+};
+use darkfi_serial::{serialize, Decodable};
+use log::{debug, error};
+use wasmer::{FunctionEnvMut, WasmPtr};
+
+use crate::runtime::vm_runtime::{
+    consume_gas, remaining_gas, CallOutcome, ContractSection, Env, Runtime, MAX_CALL_DEPTH,
+};
+
+/// Gas charged for every `invoke_contract` call, on top of the gas used by the callee
+pub const INVOKE_CONTRACT_BASE_GAS: u64 = 10000;
+/// Gas charged for every byte of the callee's wasm bincode, which has to be compiled
+pub const INVOKE_CONTRACT_BYTE_GAS: u64 = 10;
+
+/// Only exec() can call this. Runs the metadata and exec sections of another
+/// deployed contract in a new runtime, and returns the index of the object
+/// holding the data the callee's exec returned.
+///
+/// The callee is given the calls of the transaction with the invocation
+/// appended, so it decodes its payload the same way as for a call made by
+/// the transaction. As an invocation carries no ZK proofs or signatures,
+/// callees whose metadata asks for any are refused.
+///
+/// The data the callee's exec returned is its state update. It is kept
+/// along with those of the callee's own invocations, and applied by the
+/// callee's update after the caller's state update. The callee itself
+/// only gets read-only access to the databases.
+pub(crate) fn invoke_contract(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i64 {
+    let env = ctx.data();
+
+    if !matches!(env.contract_section, ContractSection::Exec) {
+        error!(target: "runtime::contract::invoke_contract()", "invoke_contract called in unauthorized section");
+        return CALLER_ACCESS_DENIED
+    }
+
+    if env.call_depth >= MAX_CALL_DEPTH {
+        error!(target: "runtime::contract::invoke_contract()", "Maximum call depth reached");
+        return CALL_DEPTH_EXCEEDED
+    }
+
+    let memory_view = env.memory_view(&ctx);
+
+    let Ok(mem_slice) = ptr.slice(&memory_view, len) else {
+        error!(target: "runtime::contract::invoke_contract()", "Failed to make slice from ptr");
+        return INVOKE_CONTRACT_FAILED
+    };
+
+    let mut buf = vec![0_u8; len as usize];
+    if let Err(e) = mem_slice.read_slice(&mut buf) {
+        error!(target: "runtime::contract::invoke_contract()", "Failed to read from memory slice: {}", e);
+        return INVOKE_CONTRACT_FAILED
+    };
+
+    let mut buf_reader = Cursor::new(buf);
+
+    let callee: ContractId = match Decodable::decode(&mut buf_reader) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::contract::invoke_contract()", "Failed to decode ContractId: {}", e);
+            return INVOKE_CONTRACT_FAILED
+        }
+    };
+
+    let data: Vec<u8> = match Decodable::decode(&mut buf_reader) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::contract::invoke_contract()", "Failed to decode call data: {}", e);
+            return INVOKE_CONTRACT_FAILED
+        }
+    };
+
+    let wasm = match env.blockchain.wasm_bincode.get(callee) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::contract::invoke_contract()", "Could not find wasm bincode for contract {}: {}", callee, e);
+            return INVOKE_CONTRACT_FAILED
+        }
+    };
+
+    let mut calls = env.tx_calls.clone();
+    calls.push(ContractCall { contract_id: callee, data });
+    let payload = serialize(&((calls.len() - 1) as u32, calls));
+
+    let blockchain = env.blockchain.clone();
+    let tx_calls = env.tx_calls.clone();
+    let call_depth = env.call_depth + 1;
+
+    let gas = INVOKE_CONTRACT_BASE_GAS + INVOKE_CONTRACT_BYTE_GAS * wasm.len() as u64;
+    if !consume_gas(&mut ctx, gas) {
+        error!(target: "runtime::contract::invoke_contract()", "Out of gas");
+        return INVOKE_CONTRACT_FAILED
+    }
+
+    debug!(target: "runtime::contract::invoke_contract()", "Invoking contract {}", callee);
+    let mut runtime = match Runtime::new(&wasm, blockchain, callee) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::contract::invoke_contract()", "Failed to instantiate runtime for contract {}: {}", callee, e);
+            return INVOKE_CONTRACT_FAILED
+        }
+    };
+
+    let callee_env = runtime.ctx.as_mut(&mut runtime.store);
+    callee_env.tx_calls = tx_calls;
+    callee_env.call_depth = call_depth;
+
+    // The callee runs on the gas the caller has left, and the caller pays for it
+    runtime.set_gas_limit(remaining_gas(&mut ctx));
+
+    let metadata =
+        match call_callee(&mut ctx, &mut runtime, callee, ContractSection::Metadata, &payload) {
+            Some(v) => v,
+            None => return INVOKE_CONTRACT_FAILED,
+        };
+
+    // Nothing would verify the ZK proofs and signatures the callee asks for
+    let mut decoder = Cursor::new(&metadata.return_data);
+    let zkp_pub: Vec<(String, Vec<pallas::Base>)> = match Decodable::decode(&mut decoder) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::contract::invoke_contract()", "Failed to decode ZK public inputs from metadata: {}", e);
+            return INVOKE_CONTRACT_FAILED
+        }
+    };
+
+    let sig_pub: Vec<PublicKey> = match Decodable::decode(&mut decoder) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::contract::invoke_contract()", "Failed to decode signature pubkeys from metadata: {}", e);
+            return INVOKE_CONTRACT_FAILED
+        }
+    };
+
+    if !zkp_pub.is_empty() || !sig_pub.is_empty() {
+        error!(target: "runtime::contract::invoke_contract()", "Contract {} asks for ZK proofs or signatures, and can't be invoked", callee);
+        let msg = format!("[{}] Invoked calls can't carry ZK proofs or signatures", callee);
+        ctx.data().logs.borrow_mut().push(msg);
+        return INVOKE_CONTRACT_FAILED
+    }
+
+    let outcome = match call_callee(&mut ctx, &mut runtime, callee, ContractSection::Exec, &payload)
+    {
+        Some(v) => v,
+        None => return INVOKE_CONTRACT_FAILED,
+    };
+
+    // The callee's state update is applied after the caller's, followed by
+    // those of the contracts it invoked itself.
+    let env = ctx.data();
+    let mut invoked_updates = env.invoked_updates.borrow_mut();
+    invoked_updates.push((callee, outcome.return_data.clone()));
+    invoked_updates.extend(outcome.invoked_updates);
+    drop(invoked_updates);
+
+    // Copy the return data to the VM
+    let mut objects = env.objects.borrow_mut();
+    objects.push(outcome.return_data);
+    (objects.len() - 1) as i64
+}
+
+/// Call a section of an invoked contract, charging the caller for the gas
+/// it used, and reporting its logs along with those of the caller. Returns
+/// `None` if the call failed.
+fn call_callee(
+    ctx: &mut FunctionEnvMut<Env>,
+    runtime: &mut Runtime,
+    callee: ContractId,
+    section: ContractSection,
+    payload: &[u8],
+) -> Option<CallOutcome> {
+    let outcome = match runtime.call(section, payload) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::contract::invoke_contract()", "Failed to call contract {}: {}", callee, e);
+            return None
+        }
+    };
+
+    if !consume_gas(ctx, outcome.gas_used) {
+        error!(target: "runtime::contract::invoke_contract()", "Out of gas");
+        return None
+    }
+
+    let env = ctx.data();
+    let mut logs = env.logs.borrow_mut();
+    logs.extend(outcome.logs.iter().map(|msg| format!("[{}] {}", callee, msg)));
+
+    if let Some(e) = &outcome.error {
+        error!(target: "runtime::contract::invoke_contract()", "Call to contract {} failed: {}", callee, e);
+        logs.push(format!("[{}] {} failed: {}", callee, section.name(), e));
+        return None
+    }
+
+    Some(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use darkfi_sdk::{
+        crypto::{Keypair, MONEY_CONTRACT_ID},
+        error::ContractError,
+    };
+    use rand::rngs::OsRng;
+
+    use crate::{
+        blockchain::Blockchain, runtime::vm_runtime::CallError, util::time::Timestamp, Result,
+    };
+
+    /// Escape bytes for a data segment of a WAT module
+    fn wat_bytes(bytes: &[u8]) -> String {
+        bytes.iter().map(|b| format!("\\{:02x}", b)).collect()
+    }
+
+    /// Contract invoking `callee` with `data` from each of its sections, and
+    /// returning the error code of a failed invocation.
+    fn caller_wat(callee: ContractId, data: &[u8]) -> String {
+        let args = serialize(&(callee, data.to_vec()));
+        let section = |name: &str| {
+            format!(
+                r#"(func (export "{}") (param i32) (result i64)
+                    (local $ret i64)
+                    (local.set $ret (call $invoke (i32.const 4096) (i32.const {})))
+                    (if (result i64) (i64.lt_s (local.get $ret) (i64.const 0))
+                        (then (local.get $ret))
+                        (else (i64.const 0))))"#,
+                name,
+                args.len()
+            )
+        };
+
+        format!(
+            r#"(module
+                (import "env" "invoke_contract_" (func $invoke (param i32 i32) (result i64)))
+                (memory (export "memory") 1)
+                (data (i32.const 4096) "{}")
+                {} {} {})"#,
+            wat_bytes(&args),
+            section("__metadata"),
+            section("__entrypoint"),
+            section("__update"),
+        )
+    }
+
+    /// Contract whose metadata asks for the given signature keys, and whose
+    /// exec returns `update`.
+    fn callee_wat(sig_pub: &[PublicKey], update: &[u8]) -> String {
+        let zkp_pub: Vec<(String, Vec<pallas::Base>)> = vec![];
+        let metadata = serialize(&(zkp_pub, sig_pub.to_vec()));
+
+        format!(
+            r#"(module
+                (import "env" "set_return_data_" (func $ret (param i32 i32) (result i64)))
+                (memory (export "memory") 1)
+                (data (i32.const 4096) "{}")
+                (data (i32.const 8192) "{}")
+                (func (export "__metadata") (param i32) (result i64)
+                    (drop (call $ret (i32.const 4096) (i32.const {})))
+                    (i64.const 0))
+                (func (export "__entrypoint") (param i32) (result i64)
+                    (drop (call $ret (i32.const 8192) (i32.const {})))
+                    (i64.const 0)))"#,
+            wat_bytes(&metadata),
+            wat_bytes(update),
+            metadata.len(),
+            update.len(),
+        )
+    }
+
+    /// Contract paying `fee` from its exec
+    fn fee_payer_wat(fee: u64) -> String {
+        let zkp_pub: Vec<(String, Vec<pallas::Base>)> = vec![];
+        let metadata = serialize(&(zkp_pub, Vec::<PublicKey>::new()));
+
+        format!(
+            r#"(module
+                (import "env" "set_return_data_" (func $ret (param i32 i32) (result i64)))
+                (import "env" "pay_fee_" (func $pay_fee (param i32 i32) (result i64)))
+                (memory (export "memory") 1)
+                (data (i32.const 4096) "{}")
+                (data (i32.const 8192) "{}")
+                (func (export "__metadata") (param i32) (result i64)
+                    (drop (call $ret (i32.const 4096) (i32.const {})))
+                    (i64.const 0))
+                (func (export "__entrypoint") (param i32) (result i64)
+                    (call $pay_fee (i32.const 8192) (i32.const 8))))"#,
+            wat_bytes(&metadata),
+            wat_bytes(&serialize(&fee)),
+            metadata.len(),
+        )
+    }
+
+    /// Deploy the callee, and call a section of a caller invoking it
+    fn invoke(
+        callee: ContractId,
+        callee_wasm: &str,
+        section: ContractSection,
+    ) -> Result<CallOutcome> {
+        let sled_db = sled::Config::new().temporary(true).open()?;
+        let blockchain =
+            Blockchain::new(&sled_db, Timestamp::current_time(), blake3::hash(b"genesis"))?;
+
+        let caller = ContractId::from(pallas::Base::from(1));
+        blockchain.wasm_bincode.insert(callee, callee_wasm.as_bytes())?;
+
+        let caller_wasm = caller_wat(callee, b"payload");
+        let mut runtime = Runtime::new(caller_wasm.as_bytes(), blockchain, caller)?;
+        runtime.call(section, &serialize(&(0_u32, Vec::<ContractCall>::new())))
+    }
+
+    fn contract_error(outcome: &CallOutcome) -> Option<ContractError> {
+        match &outcome.error {
+            Some(CallError::Contract { error, .. }) => Some(error.clone()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn invoke_from_exec() -> Result<()> {
+        let callee = ContractId::from(pallas::Base::from(2));
+        let outcome = invoke(callee, &callee_wat(&[], b"update"), ContractSection::Exec)?;
+
+        // The callee's exec output is kept to be applied by its own update
+        assert!(outcome.is_ok());
+        assert_eq!(outcome.invoked_updates, vec![(callee, b"update".to_vec())]);
+        Ok(())
+    }
+
+    #[test]
+    fn invoke_denied_outside_exec() -> Result<()> {
+        let callee = ContractId::from(pallas::Base::from(2));
+        let callee_wasm = callee_wat(&[], b"update");
+
+        // Updates may only come from the callee's own exec
+        let outcome = invoke(callee, &callee_wasm, ContractSection::Update)?;
+        assert!(matches!(contract_error(&outcome), Some(ContractError::CallerAccessDenied)));
+        assert!(outcome.invoked_updates.is_empty());
+
+        let outcome = invoke(callee, &callee_wasm, ContractSection::Metadata)?;
+        assert!(matches!(contract_error(&outcome), Some(ContractError::CallerAccessDenied)));
+        Ok(())
+    }
+
+    #[test]
+    fn invoke_refuses_signatures() -> Result<()> {
+        // Nothing would verify a signature the callee asks for
+        let callee = ContractId::from(pallas::Base::from(2));
+        let keypair = Keypair::random(&mut OsRng);
+        let callee_wasm = callee_wat(&[keypair.public], b"update");
+        let outcome = invoke(callee, &callee_wasm, ContractSection::Exec)?;
+
+        assert!(matches!(contract_error(&outcome), Some(ContractError::InvokeContractFailed)));
+        assert!(outcome.invoked_updates.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn invoked_contracts_pay_no_fees() -> Result<()> {
+        // Only the top-level Money calls, whose proofs are verified, pay fees
+        let outcome = invoke(*MONEY_CONTRACT_ID, &fee_payer_wat(10), ContractSection::Exec)?;
+
+        assert!(matches!(contract_error(&outcome), Some(ContractError::InvokeContractFailed)));
+        assert_eq!(outcome.fee_paid, 0);
+        Ok(())
+    }
+}
@@ -16,36 +16,188 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::io::Cursor;
+use std::{collections::BTreeMap, io::Cursor, ops::Bound};
 
 use darkfi_sdk::{
     crypto::ContractId,
     db::{
         CALLER_ACCESS_DENIED, DB_CONTAINS_KEY_FAILED, DB_DEL_FAILED, DB_GET_FAILED, DB_INIT_FAILED,
-        DB_LOOKUP_FAILED, DB_SET_FAILED, DB_SUCCESS,
+        DB_ITER_FAILED, DB_ITER_PAGE_SIZE, DB_LOOKUP_FAILED, DB_SET_FAILED, DB_SUCCESS,
     },
 };
-use darkfi_serial::Decodable;
+use darkfi_serial::{serialize, Decodable};
 use log::{debug, error};
+use sled::{
+    transaction::{ConflictableTransactionError, TransactionError},
+    Transactional,
+};
 use wasmer::{FunctionEnvMut, WasmPtr};
 
 use crate::{
-    runtime::vm_runtime::{ContractSection, Env},
+    runtime::vm_runtime::{consume_gas, remaining_gas, ContractSection, Env},
     Result,
 };
 
+/// Gas charged for every `db_init` and `db_lookup` call
+pub const DB_OPEN_GAS: u64 = 1000;
+/// Gas charged for every `db_get` and `db_contains_key` call
+pub const DB_READ_BASE_GAS: u64 = 500;
+/// Gas charged for every byte of the arguments of, and data returned by, `db_get`
+pub const DB_READ_BYTE_GAS: u64 = 1;
+/// Gas charged for every `db_set` and `db_del` call
+pub const DB_WRITE_BASE_GAS: u64 = 1000;
+/// Gas charged for every byte of the arguments of `db_set` and `db_del`
+pub const DB_WRITE_BYTE_GAS: u64 = 10;
+/// Gas charged for every `db_iter`, `db_range` and `db_prefix` call
+pub const DB_SCAN_BASE_GAS: u64 = 1000;
+/// Gas charged for every entry returned by a scan
+pub const DB_SCAN_ENTRY_GAS: u64 = 100;
+/// Gas charged for every byte of the keys and values returned by a scan
+pub const DB_SCAN_BYTE_GAS: u64 = 1;
+/// Most entries a single scan may ask for
+pub const DB_SCAN_MAX_ENTRIES: u32 = DB_ITER_PAGE_SIZE;
+
+/// Key range of a scan
+type ScanRange = (Bound<Vec<u8>>, Bound<Vec<u8>>);
+
+/// Writes to a sled tree that are pending until the contract call succeeds
+#[derive(Clone, Debug, Default)]
+pub struct DbBatch {
+    /// Pending writes, `None` marks a removed key
+    writes: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+impl DbBatch {
+    pub fn insert(&mut self, key: impl AsRef<[u8]>, value: impl AsRef<[u8]>) {
+        self.writes.insert(key.as_ref().to_vec(), Some(value.as_ref().to_vec()));
+    }
+
+    pub fn remove(&mut self, key: impl AsRef<[u8]>) {
+        self.writes.insert(key.as_ref().to_vec(), None);
+    }
+
+    /// Add the writes of `other` on top of those of the batch
+    pub fn extend(&mut self, other: &DbBatch) {
+        self.writes.extend(other.writes.iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.writes.is_empty()
+    }
+
+    fn to_sled_batch(&self) -> sled::Batch {
+        let mut batch = sled::Batch::default();
+        for (key, value) in self.writes.iter() {
+            match value {
+                Some(value) => batch.insert(key.as_slice(), value.as_slice()),
+                None => batch.remove(key.as_slice()),
+            }
+        }
+        batch
+    }
+}
+
+/// Access a runtime is given to a sled tree
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DbPermission {
+    /// Trees of other contracts, and all trees of invoked contracts
+    ReadOnly,
+    /// The contract's own trees
+    ReadWrite,
+}
+
+/// Writes of the state updates of a contract call that are not written to
+/// the trees yet. The updates of a call and of the contracts it invoked run
+/// one after the other, each seeing the writes of those before it, and are
+/// all written together once every one of them succeeded.
+#[derive(Clone, Debug, Default)]
+pub struct PendingWrites {
+    /// Pending batches, keyed by tree name
+    trees: BTreeMap<Vec<u8>, (sled::Tree, DbBatch)>,
+}
+
+impl PendingWrites {
+    /// Pending writes to the tree named `name`
+    fn batch(&self, name: &[u8]) -> DbBatch {
+        self.trees.get(name).map(|(_, batch)| batch.clone()).unwrap_or_default()
+    }
+
+    /// Add the batches of a successful state update
+    pub fn extend(&mut self, handles: &[DbHandle], batches: &[DbBatch]) {
+        for (handle, batch) in handles.iter().zip(batches.iter()) {
+            if batch.is_empty() {
+                continue
+            }
+
+            self.trees
+                .entry(handle.tree.name().to_vec())
+                .or_insert_with(|| (handle.tree.clone(), DbBatch::default()))
+                .1
+                .extend(batch);
+        }
+    }
+
+    /// Write all the pending batches in a single transaction over their trees
+    pub fn write(&self) -> Result<()> {
+        let (trees, batches): (Vec<_>, Vec<_>) =
+            self.trees.values().map(|(tree, batch)| (tree, batch.to_sled_batch())).unzip();
+
+        let res = trees.as_slice().transaction(|trees| {
+            for (tree, batch) in trees.iter().zip(batches.iter()) {
+                tree.apply_batch(batch)?;
+            }
+            Ok::<_, ConflictableTransactionError<()>>(())
+        });
+
+        match res {
+            Ok(()) => {}
+            Err(TransactionError::Storage(e)) => return Err(e.into()),
+            Err(TransactionError::Abort(())) => unreachable!(),
+        }
+
+        for tree in trees {
+            tree.flush()?;
+        }
+
+        Ok(())
+    }
+}
+
 /// Internal wasm runtime API for sled trees
 pub struct DbHandle {
     pub contract_id: ContractId,
+    pub permission: DbPermission,
     tree: sled::Tree,
+    /// Pending writes of earlier state updates, seen on top of the tree
+    base: DbBatch,
 }
 
 impl DbHandle {
-    pub fn new(contract_id: ContractId, tree: sled::Tree) -> Self {
-        Self { contract_id, tree }
+    pub fn new(contract_id: ContractId, permission: DbPermission, tree: sled::Tree) -> Self {
+        Self { contract_id, permission, tree, base: DbBatch::default() }
+    }
+
+    /// Make the writes in `pending` to this handle's tree visible through it
+    pub fn with_pending(mut self, pending: &PendingWrites) -> Self {
+        self.base = pending.batch(&self.tree.name());
+        self
+    }
+
+    /// Whether the contract with the given ID may write to the tree
+    pub fn writable_by(&self, contract_id: &ContractId) -> bool {
+        self.permission == DbPermission::ReadWrite && &self.contract_id == contract_id
+    }
+
+    /// Whether the handle is for the tree named `name`
+    pub fn is_tree(&self, name: &[u8]) -> bool {
+        self.tree.name() == name
     }
 
     pub fn get(&self, key: &[u8]) -> Result<Option<Vec<u8>>> {
+        if let Some(v) = self.base.writes.get(key) {
+            return Ok(v.clone())
+        }
+
         if let Some(v) = self.tree.get(key)? {
             return Ok(Some(v.to_vec()))
         };
@@ -54,11 +206,79 @@ impl DbHandle {
     }
 
     pub fn contains_key(&self, key: &[u8]) -> Result<bool> {
+        if let Some(v) = self.base.writes.get(key) {
+            return Ok(v.is_some())
+        }
+
         Ok(self.tree.contains_key(key)?)
     }
 
-    pub fn apply_batch(&self, batch: sled::Batch) -> Result<()> {
-        Ok(self.tree.apply_batch(batch)?)
+    pub fn apply_batch(&self, batch: &DbBatch) -> Result<()> {
+        Ok(self.tree.apply_batch(batch.to_sled_batch())?)
+    }
+
+    /// Return up to `limit` entries within `range`, in key order, as seen
+    /// after applying the pending `batch` to the tree.
+    pub fn scan(
+        &self,
+        batch: &DbBatch,
+        range: ScanRange,
+        limit: usize,
+    ) -> Result<Vec<(Vec<u8>, Vec<u8>)>> {
+        let merged;
+        let batch = if self.base.is_empty() {
+            batch
+        } else {
+            let mut base = self.base.clone();
+            base.extend(batch);
+            merged = base;
+            &merged
+        };
+
+        let mut ret = vec![];
+
+        // Both sled and BTreeMap panic on inverted ranges
+        if let (Bound::Included(start), Bound::Excluded(end)) = &range {
+            if start >= end {
+                return Ok(ret)
+            }
+        }
+
+        let mut tree_iter = self.tree.range(range.clone());
+        let mut batch_iter = batch.writes.range(range);
+        let mut tree_next = tree_iter.next().transpose()?;
+        let mut batch_next = batch_iter.next();
+
+        while ret.len() < limit {
+            match (&tree_next, batch_next) {
+                (None, None) => break,
+
+                // Pending writes take precedence over the tree contents
+                (Some((tree_key, _)), Some((key, value))) if key.as_slice() <= &tree_key[..] => {
+                    if key.as_slice() == &tree_key[..] {
+                        tree_next = tree_iter.next().transpose()?;
+                    }
+                    if let Some(value) = value {
+                        ret.push((key.clone(), value.clone()));
+                    }
+                    batch_next = batch_iter.next();
+                }
+
+                (Some((key, value)), _) => {
+                    ret.push((key.to_vec(), value.to_vec()));
+                    tree_next = tree_iter.next().transpose()?;
+                }
+
+                (None, Some((key, value))) => {
+                    if let Some(value) = value {
+                        ret.push((key.clone(), value.clone()));
+                    }
+                    batch_next = batch_iter.next();
+                }
+            }
+        }
+
+        Ok(ret)
     }
 
     pub fn flush(&self) -> Result<()> {
@@ -68,10 +288,15 @@ impl DbHandle {
 }
 
 /// Only deploy() can call this. Creates a new database instance for this contract.
-pub(crate) fn db_init(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i32 {
+pub(crate) fn db_init(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i32 {
+    if !consume_gas(&mut ctx, DB_OPEN_GAS) {
+        error!(target: "runtime::db::db_init()", "Out of gas");
+        return DB_INIT_FAILED
+    }
+
     let env = ctx.data();
     match env.contract_section {
-        ContractSection::Deploy => {
+        ContractSection::Deploy | ContractSection::Migrate => {
             let memory_view = env.memory_view(&ctx);
             let db = &env.blockchain.sled_db;
             let contracts = &env.blockchain.contracts;
@@ -134,8 +359,11 @@ pub(crate) fn db_init(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i
             // from other functions that interface with the databases.
             let mut db_handles = env.db_handles.borrow_mut();
             let mut db_batches = env.db_batches.borrow_mut();
-            db_handles.push(DbHandle::new(cid, tree_handle));
-            db_batches.push(sled::Batch::default());
+            db_handles.push(
+                DbHandle::new(cid, DbPermission::ReadWrite, tree_handle)
+                    .with_pending(&env.pending_writes),
+            );
+            db_batches.push(DbBatch::default());
             (db_handles.len() - 1) as i32
         }
         _ => {
@@ -146,16 +374,23 @@ pub(crate) fn db_init(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i
 }
 
 /// Everyone can call this. Lookups up a database handle from its name.
-pub(crate) fn db_lookup(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i32 {
+pub(crate) fn db_lookup(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i32 {
+    if !consume_gas(&mut ctx, DB_OPEN_GAS) {
+        error!(target: "runtime::db::db_lookup()", "Out of gas");
+        return DB_LOOKUP_FAILED
+    }
+
     let env = ctx.data();
     match env.contract_section {
         ContractSection::Deploy |
+        ContractSection::Migrate |
         ContractSection::Exec |
         ContractSection::Update |
         ContractSection::Metadata => {
             let memory_view = env.memory_view(&ctx);
             let db = &env.blockchain.sled_db;
             let contracts = &env.blockchain.contracts;
+            let contract_id = &env.contract_id;
 
             let Ok(mem_slice) = ptr.slice(&memory_view, len) else {
                 error!(target: "runtime::db::db_lookup()", "Failed to make slice from ptr");
@@ -207,10 +442,20 @@ pub(crate) fn db_lookup(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) ->
             // some simplicity reasons, and also for possible future changes.
             // However, we make sure that unauthorized writes are not available
             // from other functions that interface with the databases.
+            // Contracts may only write to their own trees, and invoked
+            // contracts may not write at all.
+            let permission = if &cid == contract_id && env.call_depth == 0 {
+                DbPermission::ReadWrite
+            } else {
+                DbPermission::ReadOnly
+            };
+
             let mut db_handles = env.db_handles.borrow_mut();
             let mut db_batches = env.db_batches.borrow_mut();
-            db_handles.push(DbHandle::new(cid, tree_handle));
-            db_batches.push(sled::Batch::default());
+            db_handles.push(
+                DbHandle::new(cid, permission, tree_handle).with_pending(&env.pending_writes),
+            );
+            db_batches.push(DbBatch::default());
             (db_handles.len() - 1) as i32
         }
         _ => {
@@ -221,10 +466,15 @@ pub(crate) fn db_lookup(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) ->
 }
 
 /// Only update() can call this. Set a value within the transaction.
-pub(crate) fn db_set(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i32 {
+pub(crate) fn db_set(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i32 {
+    if !consume_gas(&mut ctx, DB_WRITE_BASE_GAS + DB_WRITE_BYTE_GAS * len as u64) {
+        error!(target: "runtime::db::db_set()", "Out of gas");
+        return DB_SET_FAILED
+    }
+
     let env = ctx.data();
     match env.contract_section {
-        ContractSection::Deploy | ContractSection::Update => {
+        ContractSection::Deploy | ContractSection::Migrate | ContractSection::Update => {
             let memory_view = env.memory_view(&ctx);
 
             let Ok(mem_slice) = ptr.slice(&memory_view, len) else {
@@ -285,7 +535,7 @@ pub(crate) fn db_set(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i3
             let db_handle = &db_handles[handle_idx];
             let db_batch = &mut db_batches[handle_idx];
 
-            if db_handle.contract_id != env.contract_id {
+            if !db_handle.writable_by(&env.contract_id) {
                 error!(target: "runtime::db::db_set()", "Unauthorized to write to DbHandle");
                 return CALLER_ACCESS_DENIED
             }
@@ -299,10 +549,15 @@ pub(crate) fn db_set(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i3
 }
 
 /// Only update() can call this. Remove a key from the database.
-pub(crate) fn db_del(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i32 {
+pub(crate) fn db_del(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i32 {
+    if !consume_gas(&mut ctx, DB_WRITE_BASE_GAS + DB_WRITE_BYTE_GAS * len as u64) {
+        error!(target: "runtime::db::db_del()", "Out of gas");
+        return DB_DEL_FAILED
+    }
+
     let env = ctx.data();
     match env.contract_section {
-        ContractSection::Deploy | ContractSection::Update => {
+        ContractSection::Deploy | ContractSection::Migrate | ContractSection::Update => {
             let memory_view = env.memory_view(&ctx);
 
             let Ok(mem_slice) = ptr.slice(&memory_view, len) else {
@@ -355,7 +610,7 @@ pub(crate) fn db_del(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i3
             let db_handle = &db_handles[handle_idx];
             let db_batch = &mut db_batches[handle_idx];
 
-            if db_handle.contract_id != env.contract_id {
+            if !db_handle.writable_by(&env.contract_id) {
                 error!(target: "runtime::db::db_del()", "Unauthorized to write to DbHandle");
                 return CALLER_ACCESS_DENIED
             }
@@ -369,10 +624,18 @@ pub(crate) fn db_del(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i3
 }
 
 /// Everyone can call this. Will read a key from the key-value store.
-pub(crate) fn db_get(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i64 {
+pub(crate) fn db_get(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i64 {
+    if !consume_gas(&mut ctx, DB_READ_BASE_GAS + DB_READ_BYTE_GAS * len as u64) {
+        error!(target: "runtime::db::db_get()", "Out of gas");
+        return DB_GET_FAILED.into()
+    }
+
     let env = ctx.data();
     match env.contract_section {
-        ContractSection::Deploy | ContractSection::Exec | ContractSection::Metadata => {
+        ContractSection::Deploy |
+        ContractSection::Migrate |
+        ContractSection::Exec |
+        ContractSection::Metadata => {
             let memory_view = env.memory_view(&ctx);
 
             let Ok(mem_slice) = ptr.slice(&memory_view, len) else {
@@ -436,8 +699,14 @@ pub(crate) fn db_get(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i6
                 return -127
             };
 
+            drop(db_handles);
+            if !consume_gas(&mut ctx, DB_READ_BYTE_GAS * return_data.len() as u64) {
+                error!(target: "runtime::db::db_get()", "Out of gas");
+                return DB_GET_FAILED.into()
+            }
+
             // Copy Vec<u8> to the VM
-            let mut objects = env.objects.borrow_mut();
+            let mut objects = ctx.data().objects.borrow_mut();
             objects.push(return_data);
             (objects.len() - 1) as i64
         }
@@ -446,10 +715,16 @@ pub(crate) fn db_get(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i6
 }
 
 /// Everyone can call this. Will check if a given db contains given key.
-pub(crate) fn db_contains_key(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i32 {
+pub(crate) fn db_contains_key(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i32 {
+    if !consume_gas(&mut ctx, DB_READ_BASE_GAS) {
+        error!(target: "runtime::db::db_contains_key()", "Out of gas");
+        return DB_CONTAINS_KEY_FAILED
+    }
+
     let env = ctx.data();
     match env.contract_section {
         ContractSection::Deploy |
+        ContractSection::Migrate |
         ContractSection::Exec |
         ContractSection::Update |
         ContractSection::Metadata => {
@@ -514,3 +789,339 @@ pub(crate) fn db_contains_key(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u
         _ => CALLER_ACCESS_DENIED,
     }
 }
+
+/// Smallest key that is greater than all keys starting with `prefix`,
+/// or `None` if there is no such key.
+fn prefix_end(prefix: &[u8]) -> Option<Vec<u8>> {
+    let mut end = prefix.to_vec();
+    while let Some(byte) = end.pop() {
+        if byte < u8::MAX {
+            end.push(byte + 1);
+            return Some(end)
+        }
+    }
+
+    None
+}
+
+/// Read the arguments of a scan from the VM memory
+fn read_scan_args(
+    ctx: &FunctionEnvMut<Env>,
+    target: &str,
+    ptr: WasmPtr<u8>,
+    len: u32,
+) -> Option<Cursor<Vec<u8>>> {
+    let env = ctx.data();
+    match env.contract_section {
+        ContractSection::Deploy |
+        ContractSection::Migrate |
+        ContractSection::Exec |
+        ContractSection::Update |
+        ContractSection::Metadata => {
+            let memory_view = env.memory_view(ctx);
+
+            let Ok(mem_slice) = ptr.slice(&memory_view, len) else {
+                error!(target: target, "Failed to make slice from ptr");
+                return None
+            };
+
+            let mut buf = vec![0_u8; len as usize];
+            if let Err(e) = mem_slice.read_slice(&mut buf) {
+                error!(target: target, "Failed to read from memory slice: {}", e);
+                return None
+            };
+
+            Some(Cursor::new(buf))
+        }
+        _ => {
+            error!(target: target, "Scan called in unauthorized section");
+            None
+        }
+    }
+}
+
+/// Scan `range` of the tree behind `db_handle`, together with its pending
+/// writes, and charge gas for the result. The entries are copied into the
+/// object store and the object index is returned.
+fn db_scan(
+    ctx: &mut FunctionEnvMut<Env>,
+    target: &str,
+    db_handle: usize,
+    range: ScanRange,
+    limit: u32,
+) -> i64 {
+    if limit > DB_SCAN_MAX_ENTRIES {
+        error!(target: target, "Scan limit {} exceeds {}", limit, DB_SCAN_MAX_ENTRIES);
+        return DB_ITER_FAILED.into()
+    }
+
+    if !consume_gas(ctx, DB_SCAN_BASE_GAS) {
+        error!(target: target, "Out of gas");
+        return DB_ITER_FAILED.into()
+    }
+
+    // Don't read more entries than the remaining gas pays for. One more
+    // is enough to know the scan runs out of gas when charged below.
+    let affordable = remaining_gas(ctx) / DB_SCAN_ENTRY_GAS;
+    let limit = (limit as u64).min(affordable + 1) as usize;
+
+    let env = ctx.data();
+    let db_handles = env.db_handles.borrow();
+    let db_batches = env.db_batches.borrow();
+
+    if db_handles.len() <= db_handle || db_batches.len() <= db_handle {
+        error!(target: target, "Requested DbHandle that is out of bounds");
+        return DB_ITER_FAILED.into()
+    }
+
+    let entries = match db_handles[db_handle].scan(&db_batches[db_handle], range, limit) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: target, "Internal error scanning tree: {}", e);
+            return DB_ITER_FAILED.into()
+        }
+    };
+
+    drop(db_handles);
+    drop(db_batches);
+
+    let bytes: usize = entries.iter().map(|(k, v)| k.len() + v.len()).sum();
+    let gas = DB_SCAN_ENTRY_GAS * entries.len() as u64 + DB_SCAN_BYTE_GAS * bytes as u64;
+    if !consume_gas(ctx, gas) {
+        error!(target: target, "Out of gas");
+        return DB_ITER_FAILED.into()
+    }
+
+    // Copy the serialized entries to the VM
+    let mut objects = ctx.data().objects.borrow_mut();
+    objects.push(serialize(&entries));
+    (objects.len() - 1) as i64
+}
+
+/// Everyone can call this. Returns up to `limit` entries of the db, in key
+/// order, starting at the key `start`.
+pub(crate) fn db_iter(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i64 {
+    let Some(mut buf_reader) = read_scan_args(&ctx, "runtime::db::db_iter()", ptr, len) else {
+        return DB_ITER_FAILED.into()
+    };
+
+    let db_handle: u32 = match Decodable::decode(&mut buf_reader) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::db::db_iter()", "Failed to decode DbHandle: {}", e);
+            return DB_ITER_FAILED.into()
+        }
+    };
+    let start: Vec<u8> = match Decodable::decode(&mut buf_reader) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::db::db_iter()", "Failed to decode start key: {}", e);
+            return DB_ITER_FAILED.into()
+        }
+    };
+    let limit: u32 = match Decodable::decode(&mut buf_reader) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::db::db_iter()", "Failed to decode limit: {}", e);
+            return DB_ITER_FAILED.into()
+        }
+    };
+
+    let range = (Bound::Included(start), Bound::Unbounded);
+    db_scan(&mut ctx, "runtime::db::db_iter()", db_handle as usize, range, limit)
+}
+
+/// Everyone can call this. Returns up to `limit` entries of the db, in key
+/// order, with keys in `start..end`.
+pub(crate) fn db_range(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i64 {
+    let Some(mut buf_reader) = read_scan_args(&ctx, "runtime::db::db_range()", ptr, len) else {
+        return DB_ITER_FAILED.into()
+    };
+
+    let db_handle: u32 = match Decodable::decode(&mut buf_reader) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::db::db_range()", "Failed to decode DbHandle: {}", e);
+            return DB_ITER_FAILED.into()
+        }
+    };
+    let start: Vec<u8> = match Decodable::decode(&mut buf_reader) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::db::db_range()", "Failed to decode start key: {}", e);
+            return DB_ITER_FAILED.into()
+        }
+    };
+    let end: Vec<u8> = match Decodable::decode(&mut buf_reader) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::db::db_range()", "Failed to decode end key: {}", e);
+            return DB_ITER_FAILED.into()
+        }
+    };
+    let limit: u32 = match Decodable::decode(&mut buf_reader) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::db::db_range()", "Failed to decode limit: {}", e);
+            return DB_ITER_FAILED.into()
+        }
+    };
+
+    let range = (Bound::Included(start), Bound::Excluded(end));
+    db_scan(&mut ctx, "runtime::db::db_range()", db_handle as usize, range, limit)
+}
+
+/// Everyone can call this. Returns up to `limit` entries of the db, in key
+/// order, with keys starting with `prefix`, starting at the key `start`.
+pub(crate) fn db_prefix(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i64 {
+    let Some(mut buf_reader) = read_scan_args(&ctx, "runtime::db::db_prefix()", ptr, len) else {
+        return DB_ITER_FAILED.into()
+    };
+
+    let db_handle: u32 = match Decodable::decode(&mut buf_reader) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::db::db_prefix()", "Failed to decode DbHandle: {}", e);
+            return DB_ITER_FAILED.into()
+        }
+    };
+    let prefix: Vec<u8> = match Decodable::decode(&mut buf_reader) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::db::db_prefix()", "Failed to decode prefix: {}", e);
+            return DB_ITER_FAILED.into()
+        }
+    };
+    let start: Vec<u8> = match Decodable::decode(&mut buf_reader) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::db::db_prefix()", "Failed to decode start key: {}", e);
+            return DB_ITER_FAILED.into()
+        }
+    };
+    let limit: u32 = match Decodable::decode(&mut buf_reader) {
+        Ok(v) => v,
+        Err(e) => {
+            error!(target: "runtime::db::db_prefix()", "Failed to decode limit: {}", e);
+            return DB_ITER_FAILED.into()
+        }
+    };
+
+    let end = match prefix_end(&prefix) {
+        Some(end) => Bound::Excluded(end),
+        None => Bound::Unbounded,
+    };
+    let range = (Bound::Included(std::cmp::max(prefix, start)), end);
+    db_scan(&mut ctx, "runtime::db::db_prefix()", db_handle as usize, range, limit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use darkfi_sdk::pasta::pallas;
+
+    #[test]
+    fn prefix_end_bounds() {
+        assert_eq!(prefix_end(b"ab"), Some(b"ac".to_vec()));
+        assert_eq!(prefix_end(&[0x01, 0xff]), Some(vec![0x02]));
+        assert_eq!(prefix_end(&[0xff, 0xff]), None);
+        assert_eq!(prefix_end(&[]), None);
+    }
+
+    #[test]
+    fn handle_permissions() -> Result<()> {
+        let db = sled::Config::new().temporary(true).open()?;
+        let owner = ContractId::from(pallas::Base::from(0));
+        let other = ContractId::from(pallas::Base::from(1));
+
+        let handle = DbHandle::new(owner, DbPermission::ReadWrite, db.open_tree("rw")?);
+        assert!(handle.writable_by(&owner));
+        assert!(!handle.writable_by(&other));
+
+        let handle = DbHandle::new(owner, DbPermission::ReadOnly, db.open_tree("ro")?);
+        assert!(!handle.writable_by(&owner));
+        assert!(!handle.writable_by(&other));
+
+        Ok(())
+    }
+
+    #[test]
+    fn scan_merges_pending_writes() -> Result<()> {
+        let db = sled::Config::new().temporary(true).open()?;
+        let tree = db.open_tree("scan")?;
+        for key in ["a", "b", "c", "e"] {
+            tree.insert(key, key)?;
+        }
+
+        let contract_id = ContractId::from(pallas::Base::from(0));
+        let handle = DbHandle::new(contract_id, DbPermission::ReadWrite, tree);
+        let mut batch = DbBatch::default();
+        batch.insert("b", "B");
+        batch.remove("c");
+        batch.insert("d", "d");
+        batch.insert("f", "f");
+
+        let expected: Vec<(Vec<u8>, Vec<u8>)> =
+            [("a", "a"), ("b", "B"), ("d", "d"), ("e", "e"), ("f", "f")]
+                .iter()
+                .map(|(k, v)| (k.as_bytes().to_vec(), v.as_bytes().to_vec()))
+                .collect();
+
+        let all = (Bound::Included(vec![]), Bound::Unbounded);
+        assert_eq!(handle.scan(&batch, all.clone(), usize::MAX)?, expected);
+        assert_eq!(handle.scan(&batch, all.clone(), 2)?, expected[..2]);
+
+        let range = (Bound::Included(b"b".to_vec()), Bound::Excluded(b"e".to_vec()));
+        assert_eq!(handle.scan(&batch, range, usize::MAX)?, expected[1..3]);
+
+        let range = (Bound::Included(b"e".to_vec()), Bound::Excluded(b"b".to_vec()));
+        assert!(handle.scan(&batch, range, usize::MAX)?.is_empty());
+
+        // The pending writes only reach the tree once applied
+        assert_eq!(handle.scan(&DbBatch::default(), all.clone(), usize::MAX)?.len(), 4);
+        handle.apply_batch(&batch)?;
+        assert_eq!(handle.scan(&DbBatch::default(), all, usize::MAX)?, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn pending_writes_seen_by_later_updates() -> Result<()> {
+        let db = sled::Config::new().temporary(true).open()?;
+        let (tree_a, tree_b) = (db.open_tree("a")?, db.open_tree("b")?);
+        tree_a.insert("k", "old")?;
+
+        let owner = ContractId::from(pallas::Base::from(0));
+        let mut pending = PendingWrites::default();
+
+        // A first update overwrites a key and adds another
+        let handle = DbHandle::new(owner, DbPermission::ReadWrite, tree_a.clone());
+        let mut batch = DbBatch::default();
+        batch.insert("k", "new");
+        batch.insert("l", "l");
+        pending.extend(&[handle], &[batch]);
+
+        // A later update sees those writes on top of the tree
+        let handle =
+            DbHandle::new(owner, DbPermission::ReadOnly, tree_a.clone()).with_pending(&pending);
+        assert_eq!(handle.get(b"k")?, Some(b"new".to_vec()));
+        assert!(handle.contains_key(b"l")?);
+        let all = (Bound::Included(vec![]), Bound::Unbounded);
+        assert_eq!(handle.scan(&DbBatch::default(), all, usize::MAX)?.len(), 2);
+
+        let handle = DbHandle::new(owner, DbPermission::ReadWrite, tree_b.clone());
+        let mut batch = DbBatch::default();
+        batch.insert("m", "m");
+        pending.extend(&[handle], &[batch]);
+
+        // Nothing reaches the trees until the pending writes are written
+        assert_eq!(&*tree_a.get("k")?.unwrap(), b"old");
+        assert!(tree_b.is_empty());
+        pending.write()?;
+        assert_eq!(&*tree_a.get("k")?.unwrap(), b"new");
+        assert_eq!(tree_a.len(), 2);
+        assert_eq!(&*tree_b.get("m")?.unwrap(), b"m");
+
+        Ok(())
+    }
+}
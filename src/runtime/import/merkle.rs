@@ -26,11 +26,22 @@ use darkfi_serial::{serialize, Decodable, Encodable, WriteExt};
 use log::{debug, error};
 use wasmer::{FunctionEnvMut, WasmPtr};
 
-use crate::runtime::vm_runtime::{ContractSection, Env};
+use crate::runtime::vm_runtime::{consume_gas, ContractSection, Env};
 
 type MerkleTree = BridgeTree<MerkleNode, { MERKLE_DEPTH }>;
 
-pub(crate) fn merkle_add(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i32 {
+/// Gas charged for every `merkle_add` call
+pub const MERKLE_ADD_BASE_GAS: u64 = 10000;
+/// Gas charged for every byte of the arguments of `merkle_add`.
+/// Coins are 32 bytes each, so this accounts for the hashing of new leaves.
+pub const MERKLE_ADD_BYTE_GAS: u64 = 100;
+
+pub(crate) fn merkle_add(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i32 {
+    if !consume_gas(&mut ctx, MERKLE_ADD_BASE_GAS + MERKLE_ADD_BYTE_GAS * len as u64) {
+        error!(target: "runtime::merkle", "Out of gas");
+        return -2
+    }
+
     let env = ctx.data();
     match env.contract_section {
         ContractSection::Update => {
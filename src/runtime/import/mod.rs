@@ -22,5 +22,8 @@ pub(crate) mod db;
 /// Host functions for merkle tree functions
 pub(crate) mod merkle;
 
+/// Host functions for calling other contracts
+pub(crate) mod contract;
+
 /// Host functions for utilities
 pub(crate) mod util;
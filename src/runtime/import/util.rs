@@ -16,10 +16,21 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::io::Cursor;
+
+use darkfi_sdk::{
+    crypto::MONEY_CONTRACT_ID,
+    error::{CALLER_ACCESS_DENIED, PAY_FEE_FAILED},
+};
+use darkfi_serial::Decodable;
 use log::error;
 use wasmer::{FunctionEnvMut, WasmPtr};
 
-use crate::runtime::vm_runtime::{ContractSection, Env};
+use crate::runtime::vm_runtime::{consume_gas, ContractSection, Env};
+
+/// Gas charged for every byte copied between the host and the VM
+/// through objects and return data
+pub const OBJECT_BYTE_GAS: u64 = 1;
 
 /// Host function for logging strings.
 /// This is injected into the runtime with wasmer's `imports!` macro.
@@ -39,7 +50,28 @@ pub(crate) fn drk_log(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) {
     }
 }
 
-pub(crate) fn set_return_data(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i64 {
+/// Host function for contracts to describe the custom error they're about
+/// to return. The message is reported along with the error code.
+pub(crate) fn set_error_message(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) {
+    let env = ctx.data();
+    let memory_view = env.memory_view(&ctx);
+
+    match ptr.read_utf8_string(&memory_view, len) {
+        Ok(msg) => {
+            env.error_message.replace(Some(msg));
+        }
+        Err(_) => {
+            error!(target: "runtime::util", "Failed to read UTF-8 string from VM memory");
+        }
+    }
+}
+
+pub(crate) fn set_return_data(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i64 {
+    if !consume_gas(&mut ctx, OBJECT_BYTE_GAS * len as u64) {
+        error!(target: "runtime::util", "Out of gas");
+        return darkfi_sdk::error::INTERNAL_ERROR
+    }
+
     let env = ctx.data();
     match env.contract_section {
         ContractSection::Exec | ContractSection::Metadata => {
@@ -64,7 +96,12 @@ pub(crate) fn set_return_data(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u
     }
 }
 
-pub(crate) fn put_object_bytes(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i64 {
+pub(crate) fn put_object_bytes(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i64 {
+    if !consume_gas(&mut ctx, OBJECT_BYTE_GAS * len as u64) {
+        error!(target: "runtime::util", "Out of gas");
+        return -2
+    }
+
     let env = ctx.data();
     let memory_view = env.memory_view(&ctx);
 
@@ -99,7 +136,14 @@ pub(crate) fn put_object_bytes(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len:
     obj_idx as i64
 }
 
-pub(crate) fn get_object_bytes(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, idx: u32) -> i64 {
+pub(crate) fn get_object_bytes(mut ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, idx: u32) -> i64 {
+    // Charge for the bytes we're about to copy into the VM
+    let obj_len = ctx.data().objects.borrow().get(idx as usize).map_or(0, |obj| obj.len());
+    if !consume_gas(&mut ctx, OBJECT_BYTE_GAS * obj_len as u64) {
+        error!(target: "runtime::util", "Out of gas");
+        return -2
+    }
+
     // Get the slice, where we will read the size of the buffer
 
     let env = ctx.data();
@@ -148,3 +192,55 @@ pub(crate) fn get_object_size(ctx: FunctionEnvMut<Env>, idx: u32) -> i64 {
     let obj = &objects[idx as usize];
     obj.len() as i64
 }
+
+/// Host function for the Money contract to pay towards the fee of the
+/// transaction being executed. The amount paid is reported in the
+/// [`CallOutcome`](crate::runtime::vm_runtime::CallOutcome) of the call.
+pub(crate) fn pay_fee(ctx: FunctionEnvMut<Env>, ptr: WasmPtr<u8>, len: u32) -> i64 {
+    let env = ctx.data();
+    match env.contract_section {
+        ContractSection::Exec => {
+            if env.contract_id != *MONEY_CONTRACT_ID {
+                error!(target: "runtime::util::pay_fee()", "Contract {} may not pay fees", env.contract_id);
+                return CALLER_ACCESS_DENIED
+            }
+
+            // Invoked calls carry no ZK proofs, so nothing would back their payment
+            if env.call_depth > 0 {
+                error!(target: "runtime::util::pay_fee()", "Invoked contracts may not pay fees");
+                return CALLER_ACCESS_DENIED
+            }
+
+            let memory_view = env.memory_view(&ctx);
+
+            let Ok(mem_slice) = ptr.slice(&memory_view, len) else {
+                error!(target: "runtime::util::pay_fee()", "Failed to make slice from ptr");
+                return PAY_FEE_FAILED
+            };
+
+            let mut buf = vec![0_u8; len as usize];
+            if let Err(e) = mem_slice.read_slice(&mut buf) {
+                error!(target: "runtime::util::pay_fee()", "Failed to read from memory slice: {}", e);
+                return PAY_FEE_FAILED
+            };
+
+            let mut buf_reader = Cursor::new(buf);
+            let fee: u64 = match Decodable::decode(&mut buf_reader) {
+                Ok(v) => v,
+                Err(e) => {
+                    error!(target: "runtime::util::pay_fee()", "Failed to decode fee: {}", e);
+                    return PAY_FEE_FAILED
+                }
+            };
+
+            let Some(fee_paid) = env.fee_paid.get().checked_add(fee) else {
+                error!(target: "runtime::util::pay_fee()", "Fee paid overflowed");
+                return PAY_FEE_FAILED
+            };
+            env.fee_paid.set(fee_paid);
+
+            0
+        }
+        _ => CALLER_ACCESS_DENIED,
+    }
+}
@@ -18,35 +18,48 @@
 
 use std::{
     cell::{Cell, RefCell},
+    fmt,
+    ops::Bound,
     sync::Arc,
 };
 
-use darkfi_sdk::{crypto::ContractId, entrypoint};
-use darkfi_serial::serialize;
+use darkfi_sdk::{
+    crypto::ContractId,
+    entrypoint,
+    error::ContractError,
+    tx::{ContractCall, GAS_LIMIT},
+};
+use darkfi_serial::{serialize, WriteExt};
 use log::{debug, error, info};
 use wasmer::{
-    imports, wasmparser::Operator, AsStoreRef, CompilerConfig, Function, FunctionEnv, Instance,
-    Memory, MemoryView, Module, Pages, Store, Value, WASM_PAGE_SIZE,
+    imports, wasmparser::Operator, AsStoreRef, CompilerConfig, Function, FunctionEnv,
+    FunctionEnvMut, Instance, Memory, MemoryView, Module, Pages, Store, Value, WASM_PAGE_SIZE,
 };
 use wasmer_compiler_singlepass::Singlepass;
 use wasmer_middlewares::{
-    metering::{get_remaining_points, MeteringPoints},
+    metering::{get_remaining_points, set_remaining_points, MeteringPoints},
     Metering,
 };
 
-use super::{import, import::db::DbHandle, memory::MemoryManipulation};
+use super::{
+    import,
+    import::db::{DbBatch, DbHandle, PendingWrites},
+    memory::MemoryManipulation,
+};
 use crate::{blockchain::Blockchain, Error, Result};
 
 /// Name of the wasm linear memory in our guest module
 const MEMORY: &str = "memory";
 
-/// Gas limit for a contract
-const GAS_LIMIT: u64 = 200000000;
+/// Maximum nesting of contracts calling other contracts
+pub const MAX_CALL_DEPTH: usize = 4;
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug)]
 pub enum ContractSection {
     /// Setup function of a contract
     Deploy,
+    /// State migration of a contract being upgraded
+    Migrate,
     /// Entrypoint function of a contract
     Exec,
     /// Apply function of a contract
@@ -61,6 +74,7 @@ impl ContractSection {
     pub fn name(&self) -> &str {
         match self {
             Self::Deploy => "__initialize",
+            Self::Migrate => "__migrate",
             Self::Exec => "__entrypoint",
             Self::Update => "__update",
             Self::Metadata => "__metadata",
@@ -76,21 +90,35 @@ pub struct Env {
     /// sled tree handles used with `db_*`
     pub db_handles: RefCell<Vec<DbHandle>>,
     /// sled tree batches, indexed the same as `db_handles`.
-    pub db_batches: RefCell<Vec<sled::Batch>>,
+    pub db_batches: RefCell<Vec<DbBatch>>,
+    /// Writes of earlier state updates, not yet written to the trees
+    pub pending_writes: PendingWrites,
     /// The contract ID being executed
     pub contract_id: ContractId,
     /// The compiled wasm bincode being executed,
     pub contract_bincode: Vec<u8>,
     /// The contract section being executed
     pub contract_section: ContractSection,
+    /// The calls of the transaction being executed
+    pub tx_calls: Vec<ContractCall>,
+    /// Number of contract calls this runtime is nested in
+    pub call_depth: usize,
     /// State update produced by a smart contract function call
     pub contract_return_data: Cell<Option<Vec<u8>>>,
     /// Logs produced by the contract
     pub logs: RefCell<Vec<String>>,
+    /// Message describing the last error reported by the contract
+    pub error_message: RefCell<Option<String>>,
     /// Direct memory access to the VM
     pub memory: Option<Memory>,
+    /// The running instance, used by host functions to charge gas
+    pub instance: Option<Instance>,
     /// Object store for transferring memory from the host to VM
     pub objects: RefCell<Vec<Vec<u8>>>,
+    /// Fee the contract paid with `pay_fee` during the current call
+    pub fee_paid: Cell<u64>,
+    /// State updates of the contracts invoked during the current call
+    pub invoked_updates: RefCell<Vec<(ContractId, Vec<u8>)>>,
 }
 
 impl Env {
@@ -111,10 +139,104 @@ impl Env {
     }
 }
 
+/// Subtract `gas` from the gas left to the running instance. Host functions
+/// use this to charge for work done outside of the VM. Returns `false` and
+/// leaves the instance without gas if there is not enough left.
+pub(crate) fn consume_gas(ctx: &mut FunctionEnvMut<Env>, gas: u64) -> bool {
+    let remaining = remaining_gas(ctx);
+    let (env, mut store) = ctx.data_and_store_mut();
+    let instance = env.instance.as_ref().expect("instance should be set");
+
+    if remaining < gas {
+        set_remaining_points(&mut store, instance, 0);
+        return false
+    }
+
+    set_remaining_points(&mut store, instance, remaining - gas);
+    true
+}
+
+/// Gas left to the running instance
+pub(crate) fn remaining_gas(ctx: &mut FunctionEnvMut<Env>) -> u64 {
+    let (env, mut store) = ctx.data_and_store_mut();
+    let instance = env.instance.as_ref().expect("instance should be set");
+
+    match get_remaining_points(&mut store, instance) {
+        MeteringPoints::Remaining(rem) => rem,
+        MeteringPoints::Exhausted => 0,
+    }
+}
+
+/// Why a contract call failed
+#[derive(Clone, Debug)]
+pub enum CallError {
+    /// The contract returned an error code
+    Contract {
+        /// The raw value returned by the contract
+        code: i64,
+        /// The error the value decodes to
+        error: ContractError,
+        /// Message reported by the contract along with a custom error
+        message: Option<String>,
+    },
+    /// The execution trapped, e.g. on a panic, or running out of gas
+    Trap(String),
+}
+
+impl fmt::Display for CallError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Contract { error, message: Some(message), .. } => {
+                write!(f, "{}: {}", error, message)
+            }
+            Self::Contract { error, message: None, .. } => write!(f, "{}", error),
+            Self::Trap(e) => write!(f, "Execution trapped: {}", e),
+        }
+    }
+}
+
+/// Outcome of calling a contract section
+#[derive(Clone, Debug)]
+pub struct CallOutcome {
+    /// The section that was called
+    pub section: ContractSection,
+    /// Data the contract returned with `set_return_data`
+    pub return_data: Vec<u8>,
+    /// Why the call failed, if it did
+    pub error: Option<CallError>,
+    /// Messages the contract logged with `msg!`
+    pub logs: Vec<String>,
+    /// Gas consumed by the call
+    pub gas_used: u64,
+    /// Fee paid by the call with `pay_fee`
+    pub fee_paid: u64,
+    /// State updates of the contracts the call invoked, in the order they
+    /// have to be applied by their own update, after that of the call
+    pub invoked_updates: Vec<(ContractId, Vec<u8>)>,
+}
+
+impl CallOutcome {
+    pub fn is_ok(&self) -> bool {
+        self.error.is_none()
+    }
+
+    /// Turn the outcome into the data returned by the contract, or an error
+    /// if the call failed.
+    pub fn into_result(self) -> Result<Vec<u8>> {
+        match self.error {
+            None => Ok(self.return_data),
+            Some(CallError::Contract { error, .. }) => Err(Error::ContractError(error)),
+            Some(CallError::Trap(e)) => Err(Error::WasmerRuntimeError(e)),
+        }
+    }
+}
+
 pub struct Runtime {
     pub instance: Instance,
     pub store: Store,
     pub ctx: FunctionEnv<Env>,
+    /// Gas the instance was given, shared by all of its calls
+    gas_limit: u64,
 }
 
 impl Runtime {
@@ -125,12 +247,12 @@ impl Runtime {
         // the wasm module execution. It should return the cost of the operator
         // that it received as its first argument.
         // https://docs.rs/wasmparser/latest/wasmparser/enum.Operator.html
+        // Host functions charge for their own work with `consume_gas`.
         let cost_function = |operator: &Operator| -> u64 {
             match operator {
-                Operator::LocalGet { .. } => 1,
-                Operator::I32Const { .. } => 1,
-                Operator::I32Add { .. } => 2,
-                _ => 0,
+                Operator::Call { .. } | Operator::CallIndirect { .. } => 5,
+                Operator::MemoryGrow { .. } => 1000,
+                _ => 1,
             }
         };
 
@@ -160,13 +282,20 @@ impl Runtime {
                 blockchain,
                 db_handles,
                 db_batches,
+                pending_writes: PendingWrites::default(),
                 contract_id,
                 contract_bincode: wasm_bytes.to_vec(),
                 contract_section: ContractSection::Null,
+                tx_calls: vec![],
+                call_depth: 0,
                 contract_return_data: Cell::new(None),
                 logs,
+                error_message: RefCell::new(None),
                 memory: None,
+                instance: None,
                 objects: RefCell::new(vec![]),
+                fee_paid: Cell::new(0),
+                invoked_updates: RefCell::new(vec![]),
             },
         );
 
@@ -184,6 +313,12 @@ impl Runtime {
                     import::util::set_return_data,
                 ),
 
+                "set_error_message_" => Function::new_typed_with_env(
+                    &mut store,
+                    &ctx,
+                    import::util::set_error_message,
+                ),
+
                 "db_init_" => Function::new_typed_with_env(
                     &mut store,
                     &ctx,
@@ -208,6 +343,24 @@ impl Runtime {
                     import::db::db_contains_key,
                 ),
 
+                "db_iter_" => Function::new_typed_with_env(
+                    &mut store,
+                    &ctx,
+                    import::db::db_iter,
+                ),
+
+                "db_range_" => Function::new_typed_with_env(
+                    &mut store,
+                    &ctx,
+                    import::db::db_range,
+                ),
+
+                "db_prefix_" => Function::new_typed_with_env(
+                    &mut store,
+                    &ctx,
+                    import::db::db_prefix,
+                ),
+
                 "db_set_" => Function::new_typed_with_env(
                     &mut store,
                     &ctx,
@@ -243,6 +396,18 @@ impl Runtime {
                     &ctx,
                     import::merkle::merkle_add,
                 ),
+
+                "invoke_contract_" => Function::new_typed_with_env(
+                    &mut store,
+                    &ctx,
+                    import::contract::invoke_contract,
+                ),
+
+                "pay_fee_" => Function::new_typed_with_env(
+                    &mut store,
+                    &ctx,
+                    import::util::pay_fee,
+                ),
             }
         };
 
@@ -251,19 +416,27 @@ impl Runtime {
 
         let mut env_mut = ctx.as_mut(&mut store);
         env_mut.memory = Some(instance.exports.get_with_generics(MEMORY)?);
+        env_mut.instance = Some(instance.clone());
 
-        Ok(Self { instance, store, ctx })
+        Ok(Self { instance, store, ctx, gas_limit: GAS_LIMIT })
     }
 
-    fn call(&mut self, section: ContractSection, payload: &[u8]) -> Result<Vec<u8>> {
+    /// Call a contract section. Contract errors, panics, and running out of
+    /// gas are reported in the returned [`CallOutcome`], while the error is
+    /// reserved for failures of the runtime itself.
+    pub(crate) fn call(&mut self, section: ContractSection, payload: &[u8]) -> Result<CallOutcome> {
         debug!(target: "runtime::vm_runtime", "Calling {} method", section.name());
 
         let mut env_mut = self.ctx.as_mut(&mut self.store);
         env_mut.contract_section = section;
         assert!(env_mut.contract_return_data.take().is_none());
         env_mut.contract_return_data.set(None);
-        // Clear the logs
+        // Clear the logs, any previous error message, the fee paid, and
+        // the updates of invoked contracts
         let _ = env_mut.logs.take();
+        let _ = env_mut.error_message.take();
+        env_mut.fee_paid.set(0);
+        let _ = env_mut.invoked_updates.take();
 
         // Serialize the payload for the format the wasm runtime is expecting.
         let payload = Self::serialize_payload(&env_mut.contract_id, payload);
@@ -277,45 +450,49 @@ impl Runtime {
         let entrypoint = self.instance.exports.get_function(section.name())?;
 
         debug!(target: "runtime::vm_runtime", "Executing wasm");
-        let ret = match entrypoint.call(&mut self.store, &[Value::I32(0_i32)]) {
+        let gas_before = self.remaining_gas();
+        let ret = entrypoint.call(&mut self.store, &[Value::I32(0_i32)]);
+        let gas_used = gas_before - self.remaining_gas();
+        self.print_logs();
+        debug!(target: "runtime::vm_runtime", "{}", self.gas_info());
+
+        let env_mut = self.ctx.as_mut(&mut self.store);
+        env_mut.contract_section = ContractSection::Null;
+        let return_data = env_mut.contract_return_data.take().unwrap_or_default();
+        let logs = env_mut.logs.take();
+        let message = env_mut.error_message.take();
+        let fee_paid = env_mut.fee_paid.take();
+        let invoked_updates = env_mut.invoked_updates.take();
+
+        let error = match ret {
             Ok(retvals) => {
-                self.print_logs();
-                debug!(target: "runtime::vm_runtime", "{}", self.gas_info());
-                retvals
+                debug!(target: "runtime::vm_runtime", "wasm executed successfully");
+                debug!(target: "runtime::vm_runtime", "Contract returned: {:?}", retvals[0]);
+
+                match retvals[0] {
+                    Value::I64(entrypoint::SUCCESS) => None,
+                    Value::I64(code) => {
+                        let error = ContractError::from(code);
+                        // Only custom errors are defined, and described, by the contract
+                        let message =
+                            if let ContractError::Custom(_) = error { message } else { None };
+                        Some(CallError::Contract { code, error, message })
+                    }
+                    _ => unreachable!("Got unexpected result from ret: {:?}", retvals),
+                }
             }
             Err(e) => {
-                self.print_logs();
-                debug!(target: "runtime::vm_runtime", "{}", self.gas_info());
-                // WasmerRuntimeError panics are handled here. Return from run() immediately.
+                // WasmerRuntimeError panics are handled here.
                 error!(target: "runtime::vm_runtime", "Wasmer Runtime Error: {:#?}", e);
-                return Err(e.into())
+                Some(CallError::Trap(e.to_string()))
             }
         };
 
-        debug!(target: "runtime::vm_runtime", "wasm executed successfully");
-        debug!(target: "runtime::vm_runtime", "Contract returned: {:?}", ret[0]);
-
-        let mut env_mut = self.ctx.as_mut(&mut self.store);
-        env_mut.contract_section = ContractSection::Null;
-        let retdata = match env_mut.contract_return_data.take() {
-            Some(retdata) => retdata,
-            None => Vec::new(),
-        };
-
-        let retval = match ret[0] {
-            Value::I64(v) => v,
-            _ => unreachable!("Got unexpected result from ret: {:?}", ret),
-        };
-
-        match retval {
-            entrypoint::SUCCESS => Ok(retdata),
-            // FIXME: we should be able to see the error returned from the contract
-            // We can put sdk::Error inside of this.
-            _ => {
-                let err = darkfi_sdk::error::ContractError::from(retval);
-                Err(Error::ContractError(err))
-            }
+        if let Some(e) = &error {
+            debug!(target: "runtime::vm_runtime", "{} call failed: {}", section.name(), e);
         }
+
+        Ok(CallOutcome { section, return_data, error, logs, gas_used, fee_paid, invoked_updates })
     }
 
     /// This function runs when a smart contract is initially deployed, or re-deployed.
@@ -327,16 +504,74 @@ impl Runtime {
     /// The permissions for this are handled by the `ContractId` in the sled db API so we
     /// assume that the contract is only able to do write operations on its own sled trees.
     pub fn deploy(&mut self, payload: &[u8]) -> Result<()> {
+        self.prepare_deploy(payload)?;
+        self.write_deployment()
+    }
+
+    /// Run `INITIALIZE` like [`Self::deploy`], without writing anything.
+    /// The changes are written with [`Self::write_deployment`].
+    pub fn prepare_deploy(&mut self, payload: &[u8]) -> Result<()> {
         info!(target: "runtime::vm_runtime", "[wasm-runtime] Running deploy");
         debug!(target: "runtime::vm_runtime", "[wasm-runtime] payload: {:?}", payload);
-        let _ = self.call(ContractSection::Deploy, payload)?;
+        let _ = self.call(ContractSection::Deploy, payload)?.into_result()?;
+        Ok(())
+    }
+
+    /// This function runs when a deployed smart contract is upgraded to the wasm
+    /// bincode of this runtime. Like on deploy, `INITIALIZE` is executed with the
+    /// given payload, so new db trees and zkas circuits can be set up. After that,
+    /// if the contract exports a `MIGRATE` symbol, it is executed with the version
+    /// being upgraded from and the payload, so the contract can migrate its state.
+    /// Nothing is written unless both succeed.
+    pub fn upgrade(&mut self, payload: &[u8], from_version: u32) -> Result<()> {
+        self.prepare_upgrade(payload, from_version)?;
+        self.write_deployment()
+    }
+
+    /// Run `INITIALIZE` and `MIGRATE` like [`Self::upgrade`], without writing
+    /// anything. The changes are written with [`Self::write_deployment`].
+    pub fn prepare_upgrade(&mut self, payload: &[u8], from_version: u32) -> Result<()> {
+        info!(target: "runtime::vm_runtime", "[wasm-runtime] Running upgrade from version {}", from_version);
+        debug!(target: "runtime::vm_runtime", "[wasm-runtime] payload: {:?}", payload);
+        let _ = self.call(ContractSection::Deploy, payload)?.into_result()?;
+
+        if self.instance.exports.get_function(ContractSection::Migrate.name()).is_ok() {
+            let mut migrate_data = vec![];
+            migrate_data.write_u32(from_version)?;
+            migrate_data.extend_from_slice(payload);
+            let _ = self.call(ContractSection::Migrate, &migrate_data)?.into_result()?;
+        } else {
+            debug!(target: "runtime::vm_runtime", "[wasm-runtime] Contract has no migrate function");
+        }
+
+        Ok(())
+    }
+
+    /// Entries of the contract's db `db_name` as they will be once the
+    /// pending writes are applied, or `None` if the contract didn't open it.
+    pub fn pending_db(&self, db_name: &str) -> Result<Option<Vec<(Vec<u8>, Vec<u8>)>>> {
+        let env = self.ctx.as_ref(&self.store);
+        let db_handles = env.db_handles.borrow();
+        let db_batches = env.db_batches.borrow();
+
+        // State trees are named after the contract ID and db name
+        let tree_name = env.contract_id.hash_state_id(db_name);
+        let idx = match db_handles.iter().position(|h| h.is_tree(&tree_name)) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+
+        let range = (Bound::Unbounded, Bound::Unbounded);
+        Ok(Some(db_handles[idx].scan(&db_batches[idx], range, usize::MAX)?))
+    }
 
-        // If the above didn't fail, we write the batches.
+    /// Write the db batches of a successful deployment, and update the wasm
+    /// bincode in the `WasmStore`.
+    pub fn write_deployment(&mut self) -> Result<()> {
         // TODO: Make all the writes atomic in a transaction over all trees.
         let env_mut = self.ctx.as_mut(&mut self.store);
         for (idx, db) in env_mut.db_handles.get_mut().iter().enumerate() {
-            let batch = env_mut.db_batches.borrow()[idx].clone();
-            db.apply_batch(batch)?;
+            db.apply_batch(&env_mut.db_batches.borrow()[idx])?;
             db.flush()?;
         }
 
@@ -349,8 +584,9 @@ impl Runtime {
     /// This funcion runs when someone wants to execute a smart contract.
     /// The runtime will look for an `ENTRYPOINT` symbol in the wasm code, and
     /// execute it if found. A payload is also passed as an instruction that can
-    /// be used inside the vm by the runtime.
-    pub fn exec(&mut self, payload: &[u8]) -> Result<Vec<u8>> {
+    /// be used inside the vm by the runtime. On success, the outcome holds the
+    /// state update to pass to `apply`.
+    pub fn exec(&mut self, payload: &[u8]) -> Result<CallOutcome> {
         debug!(target: "runtime::vm_runtime", "exec: {:?}", payload);
         self.call(ContractSection::Exec, payload)
     }
@@ -360,23 +596,38 @@ impl Runtime {
     /// The runtime will lok for an `UPDATE` symbol in the wasm code, and execute
     /// it if found. The function does not take an arbitrary payload, but just takes
     /// a state update from `env` and passes it into the wasm runtime.
-    pub fn apply(&mut self, update: &[u8]) -> Result<()> {
+    /// If the call succeeds, its writes are added to the pending writes of the
+    /// runtime, which the caller writes with [`PendingWrites::write`].
+    pub fn apply(&mut self, update: &[u8]) -> Result<CallOutcome> {
         debug!(target: "runtime::vm_runtime", "apply: {:?}", update);
-        let _ = self.call(ContractSection::Update, update)?;
+        let outcome = self.call(ContractSection::Update, update)?;
+        if !outcome.is_ok() {
+            return Ok(outcome)
+        }
 
-        // If the above didn't fail, we write the batches.
-        // TODO: Make all the writes atomic in a transaction over all trees.
         let env_mut = self.ctx.as_mut(&mut self.store);
-        for (idx, db) in env_mut.db_handles.get_mut().iter().enumerate() {
-            let batch = env_mut.db_batches.borrow()[idx].clone();
-            db.apply_batch(batch)?;
-            db.flush()?;
-        }
+        let handles = env_mut.db_handles.get_mut();
+        let batches = env_mut.db_batches.get_mut();
+        env_mut.pending_writes.extend(handles, batches);
 
-        Ok(())
+        Ok(outcome)
+    }
+
+    /// Make the pending writes of earlier state updates visible to the
+    /// contract's state update.
+    pub fn set_pending_writes(&mut self, pending: PendingWrites) {
+        self.ctx.as_mut(&mut self.store).pending_writes = pending;
     }
 
-    pub fn metadata(&mut self, payload: &[u8]) -> Result<Vec<u8>> {
+    /// Take the pending writes, including those of the last successful
+    /// state update.
+    pub fn take_pending_writes(&mut self) -> PendingWrites {
+        std::mem::take(&mut self.ctx.as_mut(&mut self.store).pending_writes)
+    }
+
+    /// This function runs before `exec`, and returns the public inputs of the
+    /// ZK proofs and the public keys of the signatures the call needs verified.
+    pub fn metadata(&mut self, payload: &[u8]) -> Result<CallOutcome> {
         self.call(ContractSection::Metadata, payload)
     }
 
@@ -387,15 +638,36 @@ impl Runtime {
         }
     }
 
+    /// Make the calls of the transaction being executed available to the
+    /// contracts this contract calls.
+    pub fn set_tx_calls(&mut self, calls: &[ContractCall]) {
+        self.ctx.as_mut(&mut self.store).tx_calls = calls.to_vec();
+    }
+
+    /// Set the gas the instance may use from now on. The limit is shared by
+    /// all further calls made on the instance.
+    pub fn set_gas_limit(&mut self, limit: u64) {
+        set_remaining_points(&mut self.store, &self.instance, limit);
+        self.gas_limit = limit;
+    }
+
+    /// Gas left to the instance, which is shared by all of its calls
+    fn remaining_gas(&mut self) -> u64 {
+        match get_remaining_points(&mut self.store, &self.instance) {
+            MeteringPoints::Remaining(rem) => rem,
+            MeteringPoints::Exhausted => 0,
+        }
+    }
+
     fn gas_info(&mut self) -> String {
         let remaining_points = get_remaining_points(&mut self.store, &self.instance);
 
         match remaining_points {
             MeteringPoints::Remaining(rem) => {
-                format!("Gas used: {}/{}", GAS_LIMIT - rem, GAS_LIMIT)
+                format!("Gas used: {}/{}", self.gas_limit - rem, self.gas_limit)
             }
             MeteringPoints::Exhausted => {
-                format!("Gas fully exhausted: {}/{}", GAS_LIMIT + 1, GAS_LIMIT)
+                format!("Gas fully exhausted: {}/{}", self.gas_limit + 1, self.gas_limit)
             }
         }
     }
@@ -16,7 +16,7 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use darkfi_serial::Encodable;
+use darkfi_serial::{deserialize, Encodable};
 
 use super::{
     crypto::ContractId,
@@ -37,8 +37,12 @@ pub const DB_GET_FAILED: i32 = -4;
 pub const DB_CONTAINS_KEY_FAILED: i32 = -5;
 pub const DB_SET_FAILED: i32 = -6;
 pub const DB_DEL_FAILED: i32 = -7;
+pub const DB_ITER_FAILED: i32 = -8;
 
-/// Only deploy() can call this. Creates a new database instance for this contract.
+/// Number of entries a [`DbIter`] fetches from the host at a time
+pub const DB_ITER_PAGE_SIZE: u32 = 64;
+
+/// Only deploy() and migrate() can call this. Creates a new database instance for this contract.
 ///
 /// ```
 ///     type DbHandle = u32;
@@ -185,6 +189,125 @@ pub fn db_del(db_handle: DbHandle, key: &[u8]) -> GenericResult<()> {
     }
 }
 
+/// The kind of scan performed by a [`DbIter`]
+enum DbScan {
+    /// All keys
+    Iter,
+    /// Keys lower than the given end key
+    Range(Vec<u8>),
+    /// Keys starting with the given prefix
+    Prefix(Vec<u8>),
+}
+
+/// Iterator over the entries of a db, in key order, as returned by
+/// [`db_iter`], [`db_range`] and [`db_prefix`]. Entries are fetched from
+/// the host in pages of [`DB_ITER_PAGE_SIZE`], and every page costs gas.
+/// Writes made earlier in the same call are visible to the iterator.
+pub struct DbIter {
+    db_handle: DbHandle,
+    scan: DbScan,
+    /// Key the next page starts at, `None` once the scan is exhausted
+    next_key: Option<Vec<u8>>,
+    page: std::vec::IntoIter<(Vec<u8>, Vec<u8>)>,
+}
+
+impl DbIter {
+    fn new(db_handle: DbHandle, scan: DbScan, start: Vec<u8>) -> Self {
+        Self { db_handle, scan, next_key: Some(start), page: vec![].into_iter() }
+    }
+
+    fn fetch_page(&self, start: &[u8]) -> GenericResult<Vec<(Vec<u8>, Vec<u8>)>> {
+        let mut len = 0;
+        let mut buf = vec![];
+        len += self.db_handle.encode(&mut buf)?;
+
+        let ret = match &self.scan {
+            DbScan::Iter => {
+                len += start.to_vec().encode(&mut buf)?;
+                len += DB_ITER_PAGE_SIZE.encode(&mut buf)?;
+                unsafe { db_iter_(buf.as_ptr(), len as u32) }
+            }
+            DbScan::Range(end) => {
+                len += start.to_vec().encode(&mut buf)?;
+                len += end.encode(&mut buf)?;
+                len += DB_ITER_PAGE_SIZE.encode(&mut buf)?;
+                unsafe { db_range_(buf.as_ptr(), len as u32) }
+            }
+            DbScan::Prefix(prefix) => {
+                len += prefix.encode(&mut buf)?;
+                len += start.to_vec().encode(&mut buf)?;
+                len += DB_ITER_PAGE_SIZE.encode(&mut buf)?;
+                unsafe { db_prefix_(buf.as_ptr(), len as u32) }
+            }
+        };
+
+        if ret < 0 {
+            match ret as i32 {
+                CALLER_ACCESS_DENIED => return Err(ContractError::CallerAccessDenied),
+                DB_ITER_FAILED => return Err(ContractError::DbIterFailed),
+                _ => unimplemented!(),
+            }
+        }
+
+        let obj = ret as u32;
+        let obj_size = get_object_size(obj);
+        let mut buf = vec![0u8; obj_size as usize];
+        get_object_bytes(&mut buf, obj);
+
+        Ok(deserialize(&buf)?)
+    }
+}
+
+impl Iterator for DbIter {
+    type Item = GenericResult<(Vec<u8>, Vec<u8>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(entry) = self.page.next() {
+            return Some(Ok(entry))
+        }
+
+        let start = self.next_key.take()?;
+        let page = match self.fetch_page(&start) {
+            Ok(v) => v,
+            Err(e) => return Some(Err(e)),
+        };
+
+        // A full page means there may be more entries. The next page starts
+        // right after the last key, which is the key with a zero appended.
+        if page.len() == DB_ITER_PAGE_SIZE as usize {
+            let mut next_key = page[page.len() - 1].0.clone();
+            next_key.push(0);
+            self.next_key = Some(next_key);
+        }
+
+        self.page = page.into_iter();
+        self.page.next().map(Ok)
+    }
+}
+
+/// Everyone can call this. Iterates over all the entries of the db.
+///
+/// ```ignore
+/// for entry in db_iter(db_handle) {
+///     let (key, value) = entry?;
+/// }
+/// ```
+pub fn db_iter(db_handle: DbHandle) -> DbIter {
+    DbIter::new(db_handle, DbScan::Iter, vec![])
+}
+
+/// Everyone can call this. Iterates over the entries of the db with keys
+/// in `start..end`.
+pub fn db_range(db_handle: DbHandle, start: &[u8], end: &[u8]) -> DbIter {
+    DbIter::new(db_handle, DbScan::Range(end.to_vec()), start.to_vec())
+}
+
+/// Everyone can call this. Iterates over the entries of the db with keys
+/// starting with `prefix`.
+pub fn db_prefix(db_handle: DbHandle, prefix: &[u8]) -> DbIter {
+    DbIter::new(db_handle, DbScan::Prefix(prefix.to_vec()), prefix.to_vec())
+}
+
 extern "C" {
     fn db_init_(ptr: *const u8, len: u32) -> i32;
     fn db_lookup_(ptr: *const u8, len: u32) -> i32;
@@ -192,4 +315,7 @@ extern "C" {
     fn db_contains_key_(ptr: *const u8, len: u32) -> i32;
     fn db_set_(ptr: *const u8, len: u32) -> i32;
     fn db_del_(ptr: *const u8, len: u32) -> i32;
+    fn db_iter_(ptr: *const u8, len: u32) -> i64;
+    fn db_range_(ptr: *const u8, len: u32) -> i64;
+    fn db_prefix_(ptr: *const u8, len: u32) -> i64;
 }
@@ -0,0 +1,89 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Calls to [`DEPLOY_CONTRACT_ID`](crate::crypto::DEPLOY_CONTRACT_ID) are
+//! not executed by any wasm contract, but by the node itself. They deploy a
+//! contract, upgrade it to new wasm bincode, or freeze it so it can't be
+//! upgraded anymore. The contract ID is derived from the public key of the
+//! deploy key with [`ContractId::derive_public`], and the call has to be
+//! signed with the deploy key.
+
+use darkfi_serial::{SerialDecodable, SerialEncodable};
+
+use crate::{
+    crypto::{ContractId, PublicKey},
+    error::ContractError,
+};
+
+/// Largest wasm bincode a deployment may carry. Every node compiles it
+/// when verifying the call, so it has to stay cheap to do so.
+pub const MAX_WASM_BINCODE_SIZE: usize = 2 * 1024 * 1024;
+
+/// Functions available for calls to the deploy contract ID.
+#[derive(Copy, Clone, Debug, Eq, PartialEq)]
+#[repr(u8)]
+pub enum DeployFunction {
+    /// Deploy a contract, or upgrade it if it was deployed before
+    Deploy = 0x00,
+    /// Permanently disallow upgrades of a contract
+    Freeze = 0x01,
+}
+
+impl TryFrom<u8> for DeployFunction {
+    type Error = ContractError;
+
+    fn try_from(b: u8) -> core::result::Result<DeployFunction, Self::Error> {
+        match b {
+            0x00 => Ok(Self::Deploy),
+            0x01 => Ok(Self::Freeze),
+            _ => Err(ContractError::InvalidFunction),
+        }
+    }
+}
+
+/// Parameters for `DeployFunction::Deploy`
+#[derive(Clone, Debug, SerialEncodable, SerialDecodable)]
+pub struct DeployParams {
+    /// Public key of the deploy key the contract ID is derived from
+    pub public_key: PublicKey,
+    /// Compiled wasm bincode of the contract
+    pub wasm_bincode: Vec<u8>,
+    /// Payload passed to `__initialize`, and to `__migrate` on upgrades
+    pub payload: Vec<u8>,
+}
+
+impl DeployParams {
+    /// ID of the contract being deployed
+    pub fn contract_id(&self) -> ContractId {
+        ContractId::derive_public(self.public_key)
+    }
+}
+
+/// Parameters for `DeployFunction::Freeze`
+#[derive(Clone, Debug, SerialEncodable, SerialDecodable)]
+pub struct FreezeParams {
+    /// Public key of the deploy key the contract ID is derived from
+    pub public_key: PublicKey,
+}
+
+impl FreezeParams {
+    /// ID of the contract being frozen
+    pub fn contract_id(&self) -> ContractId {
+        ContractId::derive_public(self.public_key)
+    }
+}
@@ -23,8 +23,47 @@ use crate::crypto::ContractId;
 /// Success exit code for a contract
 pub const SUCCESS: i64 = 0;
 
+/// Define the exported functions of a contract. The optional `migrate`
+/// function runs after `init` when the contract gets upgraded. Its input is
+/// the previous version number of the contract as a `u32`, followed by the
+/// deploy payload.
+///
+/// ```ignore
+/// define_contract!(
+///     init: init_contract,
+///     exec: process_instruction,
+///     apply: process_update,
+///     metadata: get_metadata,
+///     migrate: migrate_contract
+/// );
+/// ```
 #[macro_export]
 macro_rules! define_contract {
+    (
+        init: $init_func:ident,
+        exec: $exec_func:ident,
+        apply: $apply_func:ident,
+        metadata: $metadata_func:ident,
+        migrate: $migrate_func:ident
+    ) => {
+        $crate::define_contract!(
+            init: $init_func,
+            exec: $exec_func,
+            apply: $apply_func,
+            metadata: $metadata_func
+        );
+
+        /// # Safety
+        #[no_mangle]
+        pub unsafe extern "C" fn __migrate(input: *mut u8) -> i64 {
+            let (contract_id, migrate_data) = $crate::entrypoint::deserialize(input);
+
+            match $migrate_func(contract_id, &migrate_data) {
+                Ok(()) => $crate::entrypoint::SUCCESS,
+                Err(e) => e.into(),
+            }
+        }
+    };
     (
         init: $init_func:ident,
         exec: $exec_func:ident,
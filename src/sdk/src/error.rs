@@ -72,10 +72,48 @@ pub enum ContractError {
     #[error("Db contains_key failed")]
     DbContainsKeyFailed,
 
+    #[error("Db iteration failed")]
+    DbIterFailed,
+
+    #[error("Maximum contract call depth exceeded")]
+    CallDepthExceeded,
+
+    #[error("Contract invocation failed")]
+    InvokeContractFailed,
+
+    #[error("Paying the transaction fee failed")]
+    PayFeeFailed,
+
     #[error("Invalid function call")]
     InvalidFunction,
 }
 
+impl ContractError {
+    /// Create a contract-defined error. Only the `code` travels through the
+    /// contract's `i64` return value, so the `message` describing it is
+    /// handed to the runtime separately, which reports both together.
+    pub fn custom(code: u32, message: &str) -> Self {
+        set_error_message(message);
+        Self::Custom(code)
+    }
+}
+
+#[inline]
+fn set_error_message(message: &str) {
+    #[cfg(target_arch = "wasm32")]
+    unsafe {
+        set_error_message_(message.as_ptr(), message.len());
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    let _ = message;
+}
+
+#[cfg(target_arch = "wasm32")]
+extern "C" {
+    fn set_error_message_(ptr: *const u8, len: usize);
+}
+
 /// Builtin return values occupy the upper 32 bits
 macro_rules! to_builtin {
     ($error:expr) => {
@@ -99,6 +137,10 @@ pub const DB_GET_FAILED: i64 = to_builtin!(13);
 pub const DB_CONTAINS_KEY_FAILED: i64 = to_builtin!(14);
 pub const INVALID_FUNCTION: i64 = to_builtin!(15);
 pub const DB_DEL_FAILED: i64 = to_builtin!(16);
+pub const DB_ITER_FAILED: i64 = to_builtin!(17);
+pub const CALL_DEPTH_EXCEEDED: i64 = to_builtin!(18);
+pub const INVOKE_CONTRACT_FAILED: i64 = to_builtin!(19);
+pub const PAY_FEE_FAILED: i64 = to_builtin!(20);
 
 impl From<ContractError> for i64 {
     fn from(err: ContractError) -> Self {
@@ -118,6 +160,10 @@ impl From<ContractError> for i64 {
             ContractError::DbContainsKeyFailed => DB_CONTAINS_KEY_FAILED,
             ContractError::InvalidFunction => INVALID_FUNCTION,
             ContractError::DbDelFailed => DB_DEL_FAILED,
+            ContractError::DbIterFailed => DB_ITER_FAILED,
+            ContractError::CallDepthExceeded => CALL_DEPTH_EXCEEDED,
+            ContractError::InvokeContractFailed => INVOKE_CONTRACT_FAILED,
+            ContractError::PayFeeFailed => PAY_FEE_FAILED,
             ContractError::Custom(error) => {
                 if error == 0 {
                     CUSTOM_ZERO
@@ -148,6 +194,10 @@ impl From<i64> for ContractError {
             DB_CONTAINS_KEY_FAILED => Self::DbContainsKeyFailed,
             INVALID_FUNCTION => Self::InvalidFunction,
             DB_DEL_FAILED => Self::DbDelFailed,
+            DB_ITER_FAILED => Self::DbIterFailed,
+            CALL_DEPTH_EXCEEDED => Self::CallDepthExceeded,
+            INVOKE_CONTRACT_FAILED => Self::InvokeContractFailed,
+            PAY_FEE_FAILED => Self::PayFeeFailed,
             _ => Self::Custom(error as u32),
         }
     }
@@ -22,6 +22,9 @@ pub use pasta_curves as pasta;
 /// Database functions
 pub mod db;
 
+/// Contract deployment calls
+pub mod deploy;
+
 /// Entrypoint used for the wasm binaries
 pub mod entrypoint;
 
@@ -20,6 +20,10 @@ use darkfi_serial::{SerialDecodable, SerialEncodable};
 
 use super::crypto::ContractId;
 
+/// Gas limit of a contract call, used when the transaction does not set one.
+/// Transactions may not set a limit above this.
+pub const GAS_LIMIT: u64 = 200000000;
+
 // ANCHOR: contractcall
 /// A ContractCall is the part of a transaction that executes a certain
 /// `contract_id` with `data` as the call's payload.
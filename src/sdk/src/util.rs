@@ -16,7 +16,12 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use super::error::ContractError;
+use darkfi_serial::Encodable;
+
+use super::{
+    crypto::ContractId,
+    error::{ContractError, GenericResult},
+};
 
 pub fn set_return_data(data: &[u8]) -> Result<(), ContractError> {
     unsafe {
@@ -39,9 +44,54 @@ pub fn get_object_size(object_index: u32) -> i64 {
     unsafe { get_object_size_(object_index as u32) }
 }
 
+/// Call another deployed contract from `exec`, and return the data its `exec`
+/// returned. The callee runs on the gas the caller has left, and the gas it
+/// uses is charged to the caller.
+///
+/// The callee is given the calls of the transaction with a call holding
+/// `data` appended, so it decodes its payload the same way as for a call
+/// made by the transaction. Its `metadata` may not ask for ZK proofs or
+/// signatures, as an invocation carries none. The data its `exec` returned
+/// is applied by its `update` after the caller's state update.
+pub fn invoke_contract(contract_id: ContractId, data: &[u8]) -> GenericResult<Vec<u8>> {
+    let mut len = 0;
+    let mut buf = vec![];
+    len += contract_id.encode(&mut buf)?;
+    len += data.to_vec().encode(&mut buf)?;
+
+    let ret = unsafe { invoke_contract_(buf.as_ptr(), len as u32) };
+
+    if ret < 0 {
+        return Err(ContractError::from(ret))
+    }
+
+    let obj = ret as u32;
+    let obj_size = get_object_size(obj);
+    let mut buf = vec![0u8; obj_size as usize];
+    get_object_bytes(&mut buf, obj);
+
+    Ok(buf)
+}
+
+/// Pay `fee` towards the fee of the transaction being executed. Only the
+/// Money contract may pay fees, from the `exec` section of a call made by
+/// the transaction, and not when invoked by another contract. The fees paid
+/// by all the calls of a transaction must add up to the fee it declares.
+pub fn pay_fee(fee: u64) -> GenericResult<()> {
+    let mut buf = vec![];
+    let len = fee.encode(&mut buf)?;
+
+    match unsafe { pay_fee_(buf.as_ptr(), len as u32) } {
+        0 => Ok(()),
+        errcode => Err(ContractError::from(errcode)),
+    }
+}
+
 extern "C" {
     fn set_return_data_(ptr: *const u8, len: u32) -> i64;
     fn put_object_bytes_(ptr: *const u8, len: u32) -> i64;
     fn get_object_bytes_(ptr: *mut u8, len: u32) -> i64;
     fn get_object_size_(len: u32) -> i64;
+    fn invoke_contract_(ptr: *const u8, len: u32) -> i64;
+    fn pay_fee_(ptr: *const u8, len: u32) -> i64;
 }
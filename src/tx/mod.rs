@@ -22,10 +22,10 @@ use async_std::sync::{Arc, RwLock};
 use darkfi_sdk::{
     crypto::{
         schnorr::{SchnorrPublic, SchnorrSecret, Signature},
-        PublicKey, SecretKey,
+        ContractId, PublicKey, SecretKey,
     },
     pasta::pallas,
-    tx::ContractCall,
+    tx::{ContractCall, GAS_LIMIT},
 };
 use darkfi_serial::{Encodable, SerialDecodable, SerialEncodable};
 use log::{debug, error};
@@ -50,6 +50,10 @@ macro_rules! zip {
 pub struct Transaction {
     /// Calls executed in this transaction
     pub calls: Vec<ContractCall>,
+    /// Gas limit of each call. When empty, every call gets [`GAS_LIMIT`].
+    pub gas_limits: Vec<u64>,
+    /// Fee the calls of this transaction must pay with the Money contract
+    pub fee: u64,
     /// Attached ZK proofs
     pub proofs: Vec<Vec<Proof>>,
     /// Attached Schnorr signatures
@@ -59,67 +63,196 @@ pub struct Transaction {
 
 type VerifyingKeyMap = Arc<RwLock<HashMap<[u8; 32], Vec<(String, VerifyingKey)>>>>;
 
+/// ZK proof namespaces and public inputs of every call in a transaction
+pub type ZkpTable = Vec<Vec<(String, Vec<pallas::Base>)>>;
+
+/// A proof waiting for verification: transaction, call, and proof index,
+/// contract ID, proof, and public inputs.
+type PendingProof<'a> = (usize, usize, usize, ContractId, &'a Proof, &'a [pallas::Base]);
+
+/// A transaction that failed ZK proof verification in a batch
+#[derive(Debug)]
+pub struct ZkpFailure {
+    /// Index of the transaction in the batch
+    pub tx_idx: usize,
+    /// Call index and index within the call of the offending proof, when known
+    pub proof: Option<(usize, usize)>,
+    /// Why verification failed
+    pub error: Error,
+    /// Whether the transaction's proofs are at fault, rather than e.g. a
+    /// missing verifying key
+    pub malformed: bool,
+}
+
 impl Transaction {
+    /// Gas limit of the call at `call_idx`
+    pub fn gas_limit(&self, call_idx: usize) -> u64 {
+        self.gas_limits.get(call_idx).copied().unwrap_or(GAS_LIMIT)
+    }
+
+    /// Verify that the gas limits are either unset, or set for every call
+    /// and within [`GAS_LIMIT`].
+    pub fn verify_gas_limits(&self) -> Result<()> {
+        if self.gas_limits.is_empty() {
+            return Ok(())
+        }
+
+        if self.gas_limits.len() != self.calls.len() {
+            let e = format!("{} limits for {} calls", self.gas_limits.len(), self.calls.len());
+            return Err(VerifyFailed::InvalidGasLimits(e).into())
+        }
+
+        if let Some(idx) = self.gas_limits.iter().position(|limit| *limit > GAS_LIMIT) {
+            let e = format!("Limit of call {} is above {}", idx, GAS_LIMIT);
+            return Err(VerifyFailed::InvalidGasLimits(e).into())
+        }
+
+        Ok(())
+    }
+
     /// Verify ZK proofs for the entire transaction.
     pub async fn verify_zkps(
         &self,
         verifying_keys: VerifyingKeyMap,
-        zkp_table: Vec<Vec<(String, Vec<pallas::Base>)>>,
+        zkp_table: ZkpTable,
     ) -> Result<()> {
-        // TODO: Are we sure we should assert here?
-        assert_eq!(self.calls.len(), self.proofs.len());
-        assert_eq!(self.calls.len(), zkp_table.len());
-
-        for (call, (proofs, pubvals)) in zip!(self.calls, self.proofs, zkp_table) {
-            assert_eq!(proofs.len(), pubvals.len());
-
-            for (proof, (zk_ns, public_vals)) in proofs.iter().zip(pubvals.iter()) {
-                if let Some(vks) = verifying_keys.read().await.get(&call.contract_id.to_bytes()) {
-                    if let Some(vk) = vks.iter().find(|x| &x.0 == zk_ns) {
-                        // We have a verifying key for this
-                        debug!("public inputs: {:#?}", public_vals);
-                        if let Err(e) = proof.verify(&vk.1, public_vals) {
-                            error!(
-                                target: "",
-                                "Failed verifying {}::{} ZK proof: {:#?}",
-                                call.contract_id, zk_ns, e
-                            );
-                            return Err(VerifyFailed::ProofVerifyFailed(e.to_string()).into())
-                        }
-                        debug!("Successfully verified {}::{} ZK proof", call.contract_id, zk_ns);
-                        continue
-                    }
+        match Self::verify_zkps_batch(&[(self, &zkp_table)], verifying_keys).await.pop() {
+            Some(failure) => Err(failure.error),
+            None => Ok(()),
+        }
+    }
+
+    /// Verify ZK proofs for several transactions at once. Proofs sharing a
+    /// verifying key are verified together in a batch, and if a batch fails,
+    /// its proofs are verified one by one to find the offending calls.
+    /// Returns a [`ZkpFailure`] for every transaction that failed.
+    pub async fn verify_zkps_batch(
+        txs: &[(&Transaction, &ZkpTable)],
+        verifying_keys: VerifyingKeyMap,
+    ) -> Vec<ZkpFailure> {
+        let verifying_keys = verifying_keys.read().await;
+        let mut failed: Vec<ZkpFailure> = vec![];
+
+        // Proofs grouped by the verifying key they need
+        let mut batches: HashMap<([u8; 32], &str), (&VerifyingKey, Vec<PendingProof>)> =
+            HashMap::new();
+
+        'txs: for (tx_idx, (tx, zkp_table)) in txs.iter().enumerate() {
+            if tx.calls.len() != tx.proofs.len() || tx.calls.len() != zkp_table.len() {
+                let e = "Mismatched number of calls and proofs".to_string();
+                error!(target: "tx::verify_zkps", "{}", e);
+                let error = VerifyFailed::ProofVerifyFailed(e).into();
+                failed.push(ZkpFailure { tx_idx, proof: None, error, malformed: true });
+                continue
+            }
+
+            let mut pending = vec![];
+            for (call_idx, (call, (proofs, pubvals))) in
+                zip!(tx.calls, tx.proofs, zkp_table).enumerate()
+            {
+                if proofs.len() != pubvals.len() {
+                    let e = format!("Call {} has a mismatched number of proofs", call_idx);
+                    error!(target: "tx::verify_zkps", "{}", e);
+                    let error = VerifyFailed::ProofVerifyFailed(e).into();
+                    failed.push(ZkpFailure { tx_idx, proof: None, error, malformed: true });
+                    continue 'txs
                 }
 
-                let e = format!("{}:{} circuit VK nonexistent", call.contract_id, zk_ns);
-                error!("{}", e);
-                return Err(VerifyFailed::ProofVerifyFailed(e).into())
+                for (proof_idx, (proof, (zk_ns, public_vals))) in
+                    proofs.iter().zip(pubvals.iter()).enumerate()
+                {
+                    let contract_id = call.contract_id.to_bytes();
+                    let vk = verifying_keys
+                        .get(&contract_id)
+                        .and_then(|vks| vks.iter().find(|x| &x.0 == zk_ns));
+
+                    let Some((_, vk)) = vk else {
+                        let e = format!("{}:{} circuit VK nonexistent", call.contract_id, zk_ns);
+                        error!(target: "tx::verify_zkps", "{}", e);
+                        let error = VerifyFailed::ProofVerifyFailed(e).into();
+                        let proof = Some((call_idx, proof_idx));
+                        failed.push(ZkpFailure { tx_idx, proof, error, malformed: false });
+                        continue 'txs
+                    };
+
+                    debug!("public inputs: {:#?}", public_vals);
+                    let item =
+                        (tx_idx, call_idx, proof_idx, call.contract_id, proof, &public_vals[..]);
+                    pending.push(((contract_id, zk_ns.as_str()), vk, item));
+                }
+            }
+
+            for (key, vk, item) in pending {
+                batches.entry(key).or_insert_with(|| (vk, vec![])).1.push(item);
             }
         }
 
-        Ok(())
+        for ((_, zk_ns), (vk, items)) in batches {
+            let proofs: Vec<_> = items.iter().map(|i| (i.4, i.5)).collect();
+            if Proof::verify_batch(vk, &proofs) {
+                debug!(
+                    target: "tx::verify_zkps",
+                    "Successfully verified {} {} ZK proof(s)",
+                    items.len(),
+                    zk_ns
+                );
+                continue
+            }
+
+            // The batch failed, so find out which proofs are invalid
+            for (tx_idx, call_idx, proof_idx, contract_id, proof, public_vals) in items {
+                if failed.iter().any(|f| f.tx_idx == tx_idx) {
+                    continue
+                }
+
+                if let Err(e) = proof.verify(vk, public_vals) {
+                    error!(
+                        target: "tx::verify_zkps",
+                        "Failed verifying {}::{} ZK proof of call {}: {:#?}",
+                        contract_id, zk_ns, call_idx, e
+                    );
+                    let error = VerifyFailed::ProofVerifyFailed(e.to_string()).into();
+                    let proof = Some((call_idx, proof_idx));
+                    failed.push(ZkpFailure { tx_idx, proof, error, malformed: true });
+                }
+            }
+        }
+
+        failed.sort_by_key(|f| f.tx_idx);
+        failed
     }
 
     /// Verify Schnorr signatures for the entire transaction.
     pub fn verify_sigs(&self, pub_table: Vec<Vec<PublicKey>>) -> Result<()> {
+        self.verify_call_sigs(&pub_table)?.into_iter().collect()
+    }
+
+    /// Verify the Schnorr signatures of each call in the transaction, and
+    /// return the result for every call.
+    pub fn verify_call_sigs(&self, pub_table: &[Vec<PublicKey>]) -> Result<Vec<Result<()>>> {
         let tx_data = self.encode_without_sigs()?;
         let data_hash = blake3::hash(&tx_data);
         debug!("tx.verify_sigs: data_hash: {:?}", data_hash.as_bytes());
 
         assert!(pub_table.len() == self.signatures.len());
 
+        let mut ret = Vec::with_capacity(pub_table.len());
         for (i, (sigs, pubkeys)) in self.signatures.iter().zip(pub_table.iter()).enumerate() {
-            for (pubkey, signature) in pubkeys.iter().zip(sigs) {
+            let verified = pubkeys.iter().zip(sigs).all(|(pubkey, signature)| {
                 debug!("Verifying signature with public key: {}", pubkey);
-                if !pubkey.verify(&data_hash.as_bytes()[..], signature) {
-                    error!("tx::verify_sigs[{}] failed to verify", i);
-                    return Err(Error::InvalidSignature)
-                }
+                pubkey.verify(&data_hash.as_bytes()[..], signature)
+            });
+
+            if verified {
+                debug!("tx::verify_sigs[{}] passed", i);
+                ret.push(Ok(()));
+            } else {
+                error!("tx::verify_sigs[{}] failed to verify", i);
+                ret.push(Err(Error::InvalidSignature));
             }
-            debug!("tx::verify_sigs[{}] passed", i);
         }
 
-        Ok(())
+        Ok(ret)
     }
 
     /// Create Schnorr signatures for the entire transaction.
@@ -146,6 +279,8 @@ impl Transaction {
     pub fn encode_without_sigs(&self) -> Result<Vec<u8>> {
         let mut buf = vec![];
         self.calls.encode(&mut buf)?;
+        self.gas_limits.encode(&mut buf)?;
+        self.fee.encode(&mut buf)?;
         self.proofs.encode(&mut buf)?;
         Ok(buf)
     }
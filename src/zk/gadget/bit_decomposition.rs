@@ -0,0 +1,354 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Chip, Layouter, Value},
+    pasta::{
+        group::ff::{Field, PrimeField, PrimeFieldBits},
+        pallas,
+    },
+    plonk,
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Expression, Selector},
+    poly::Rotation,
+};
+
+/// Decomposes a field element into its little-endian bits, using a running
+/// sum `z_{i+1} = (z_i - b_i) / 2` with `z_0` the value and every `b_i`
+/// boolean. The final `z` is constrained to zero, which also enforces that
+/// the value fits in the requested number of bits.
+///
+/// A full-width decomposition has two bit representations for every value
+/// below `2^255 - p`, so it is additionally checked to be canonical, i.e.
+/// the bits encode an integer smaller than `p`.
+#[derive(Clone, Debug)]
+pub struct BitDecompositionConfig {
+    z: Column<Advice>,
+    bit: Column<Advice>,
+    s_decompose: Selector,
+    s_canonicity: Selector,
+}
+
+pub struct BitDecompositionChip {
+    config: BitDecompositionConfig,
+}
+
+impl Chip<pallas::Base> for BitDecompositionChip {
+    type Config = BitDecompositionConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl BitDecompositionChip {
+    pub fn construct(config: BitDecompositionConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<pallas::Base>,
+        z: Column<Advice>,
+        bit: Column<Advice>,
+    ) -> BitDecompositionConfig {
+        meta.enable_equality(z);
+        meta.enable_equality(bit);
+
+        let s_decompose = meta.selector();
+
+        meta.create_gate("Bit decomposition: z_cur = 2 * z_next + bit", |meta| {
+            let s_decompose = meta.query_selector(s_decompose);
+            let z_cur = meta.query_advice(z, Rotation::cur());
+            let z_next = meta.query_advice(z, Rotation::next());
+            let bit = meta.query_advice(bit, Rotation::cur());
+
+            let one = Expression::Constant(pallas::Base::one());
+            let two = Expression::Constant(pallas::Base::from(2));
+
+            Constraints::with_selector(
+                s_decompose,
+                [
+                    ("bool check bit", bit.clone() * (one - bit.clone())),
+                    ("running sum", z_cur - z_next * two - bit),
+                ],
+            )
+        });
+
+        // p = 2^254 + t_p, with 2^125 < t_p < 2^126. A 255-bit decomposition
+        // with bit 254 set is canonical iff bits 126..254 are zero and the low
+        // 126 bits encode a value below t_p. The latter is checked by range
+        // checking lo + 2^126 - t_p to 126 bits, gated by bit 254.
+        //
+        // | z        | bit          |
+        // |----------|--------------|
+        // | value    | b_254        |
+        // | z_126    | offset_lo    |
+        let s_canonicity = meta.selector();
+
+        meta.create_gate("Bit decomposition: canonicity", |meta| {
+            let s_canonicity = meta.query_selector(s_canonicity);
+            let value = meta.query_advice(z, Rotation::cur());
+            let b_254 = meta.query_advice(bit, Rotation::cur());
+            let z_126 = meta.query_advice(z, Rotation::next());
+            let offset_lo = meta.query_advice(bit, Rotation::next());
+
+            let two_pow_126 = Expression::Constant(Self::two_pow(126));
+            let two_pow_128 = Expression::Constant(Self::two_pow(128));
+
+            // lo = value - z_126 * 2^126
+            let lo = value - z_126.clone() * two_pow_126;
+
+            Constraints::with_selector(
+                s_canonicity,
+                [
+                    ("bits 126..254 are zero", b_254.clone() * (z_126 - two_pow_128)),
+                    (
+                        "offset_lo = b_254 * (lo + 2^126 - t_p)",
+                        offset_lo -
+                            b_254 * (lo + Expression::Constant(Self::two_pow_126_minus_t_p())),
+                    ),
+                ],
+            )
+        });
+
+        BitDecompositionConfig { z, bit, s_decompose, s_canonicity }
+    }
+
+    fn two_pow(n: u64) -> pallas::Base {
+        pallas::Base::from(2).pow_vartime([n])
+    }
+
+    /// `2^126 - t_p`, where `p = 2^254 + t_p`. In the field `t_p = -2^254`.
+    fn two_pow_126_minus_t_p() -> pallas::Base {
+        Self::two_pow(126) + Self::two_pow(254)
+    }
+
+    /// Decompose `value` into `num_bits` bits, least significant first.
+    /// Fails to verify if the value does not fit in `num_bits` bits. With
+    /// `num_bits` equal to `pallas::Base::NUM_BITS` every value fits, and the
+    /// decomposition is constrained to be canonical. Needs a column enabled
+    /// for constants in the circuit.
+    pub fn decompose(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        value: &AssignedCell<pallas::Base, pallas::Base>,
+        num_bits: usize,
+    ) -> Result<Vec<AssignedCell<pallas::Base, pallas::Base>>, plonk::Error> {
+        assert!(num_bits <= pallas::Base::NUM_BITS as usize);
+
+        let (bits, zs) = self.running_sum(layouter.namespace(|| "running sum"), value, num_bits)?;
+
+        if num_bits == pallas::Base::NUM_BITS as usize {
+            let offset_lo = layouter.assign_region(
+                || "canonicity check",
+                |mut region| {
+                    self.config.s_canonicity.enable(&mut region, 0)?;
+
+                    value.copy_advice(|| "value", &mut region, self.config.z, 0)?;
+                    let b_254 =
+                        bits[254].copy_advice(|| "b_254", &mut region, self.config.bit, 0)?;
+                    let z_126 = zs[126].copy_advice(|| "z_126", &mut region, self.config.z, 1)?;
+
+                    let offset_lo = value.value().zip(z_126.value()).zip(b_254.value()).map(
+                        |((value, z_126), b_254)| {
+                            let lo = *value - *z_126 * Self::two_pow(126);
+                            *b_254 * (lo + Self::two_pow_126_minus_t_p())
+                        },
+                    );
+
+                    region.assign_advice(|| "offset_lo", self.config.bit, 1, || offset_lo)
+                },
+            )?;
+
+            self.running_sum(layouter.namespace(|| "offset_lo range check"), &offset_lo, 126)?;
+        }
+
+        Ok(bits)
+    }
+
+    /// Returns the bits and the running sum `z_0..=z_num_bits`, with the
+    /// final `z` constrained to zero.
+    #[allow(clippy::type_complexity)]
+    fn running_sum(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        value: &AssignedCell<pallas::Base, pallas::Base>,
+        num_bits: usize,
+    ) -> Result<
+        (
+            Vec<AssignedCell<pallas::Base, pallas::Base>>,
+            Vec<AssignedCell<pallas::Base, pallas::Base>>,
+        ),
+        plonk::Error,
+    > {
+        layouter.assign_region(
+            || format!("{}-bit decomposition", num_bits),
+            |mut region| {
+                let bit_values: Value<Vec<bool>> =
+                    value.value().map(|v| v.to_le_bits().into_iter().take(num_bits).collect());
+                let bit_values = bit_values.transpose_vec(num_bits);
+
+                let mut z = value.copy_advice(|| "z_0", &mut region, self.config.z, 0)?;
+                let mut zs = vec![z.clone()];
+                let mut bits = Vec::with_capacity(num_bits);
+
+                for (i, bit_value) in bit_values.into_iter().enumerate() {
+                    self.config.s_decompose.enable(&mut region, i)?;
+
+                    let bit_value = bit_value.map(pallas::Base::from);
+                    let bit = region.assign_advice(
+                        || format!("bit_{}", i),
+                        self.config.bit,
+                        i,
+                        || bit_value,
+                    )?;
+
+                    // z_next = (z_cur - bit) / 2
+                    let two_inv = pallas::Base::from(2).invert().unwrap();
+                    let z_next = (z.value().copied() - bit_value) * Value::known(two_inv);
+                    z = region.assign_advice(
+                        || format!("z_{}", i + 1),
+                        self.config.z,
+                        i + 1,
+                        || z_next,
+                    )?;
+
+                    zs.push(z.clone());
+                    bits.push(bit);
+                }
+
+                // All bits have been consumed
+                region.constrain_constant(z.cell(), pallas::Base::zero())?;
+
+                Ok((bits, zs))
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zk::assign_free_advice;
+    use halo2_proofs::{
+        circuit::floor_planner,
+        dev::MockProver,
+        plonk::{Circuit, Instance as InstanceColumn},
+    };
+
+    #[derive(Default)]
+    struct BitDecompositionCircuit {
+        value: Value<pallas::Base>,
+        num_bits: usize,
+    }
+
+    impl Circuit<pallas::Base> for BitDecompositionCircuit {
+        type Config = (BitDecompositionConfig, Column<Advice>, Column<InstanceColumn>);
+        type FloorPlanner = floor_planner::V1;
+
+        fn without_witnesses(&self) -> Self {
+            Self { value: Value::unknown(), num_bits: self.num_bits }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let z = meta.advice_column();
+            let bit = meta.advice_column();
+            let w = meta.advice_column();
+            meta.enable_equality(w);
+
+            let constants = meta.fixed_column();
+            meta.enable_constant(constants);
+
+            let primary = meta.instance_column();
+            meta.enable_equality(primary);
+
+            let config = BitDecompositionChip::configure(meta, z, bit);
+            (config, w, primary)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), plonk::Error> {
+            let chip = BitDecompositionChip::construct(config.0);
+            let value = assign_free_advice(layouter.namespace(|| "value"), config.1, self.value)?;
+
+            let bits = chip.decompose(layouter.namespace(|| "decompose"), &value, self.num_bits)?;
+            for (i, bit) in bits.iter().enumerate() {
+                layouter.constrain_instance(bit.cell(), config.2, i)?;
+            }
+
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn bit_decomposition() {
+        let k = 5;
+        let (zero, one) = (pallas::Base::zero(), pallas::Base::one());
+
+        // 0b10110001
+        let circuit =
+            BitDecompositionCircuit { value: Value::known(pallas::Base::from(177)), num_bits: 8 };
+        let bits = vec![one, zero, zero, zero, one, one, zero, one];
+        let prover = MockProver::run(k, &circuit, vec![bits.clone()]).unwrap();
+        prover.assert_satisfied();
+
+        // Values wider than the decomposition fail
+        let circuit =
+            BitDecompositionCircuit { value: Value::known(pallas::Base::from(433)), num_bits: 8 };
+        let prover = MockProver::run(k, &circuit, vec![bits]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    #[test]
+    fn bit_decomposition_full_width() {
+        let k = 9;
+        let num_bits = pallas::Base::NUM_BITS as usize;
+
+        // Values at and above 2^253, including the largest one, p - 1
+        let values = [
+            pallas::Base::zero(),
+            pallas::Base::one(),
+            pallas::Base::from(2).pow_vartime([253]),
+            pallas::Base::from(2).pow_vartime([254]),
+            -pallas::Base::one(),
+            pallas::Base::random(&mut rand::rngs::OsRng),
+        ];
+
+        for value in values {
+            let bits: Vec<pallas::Base> =
+                value.to_le_bits().into_iter().take(num_bits).map(pallas::Base::from).collect();
+
+            let circuit = BitDecompositionCircuit { value: Value::known(value), num_bits };
+            let prover = MockProver::run(k, &circuit, vec![bits.clone()]).unwrap();
+            prover.assert_satisfied();
+
+            // The bits are unique, so flipping any of them fails
+            let mut wrong_bits = bits;
+            wrong_bits[0] = pallas::Base::one() - wrong_bits[0];
+            let prover = MockProver::run(k, &circuit, vec![wrong_bits]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+    }
+}
@@ -0,0 +1,210 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Chip, Layouter},
+    pasta::pallas,
+    plonk,
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Expression, Selector},
+    poly::Rotation,
+};
+
+/// Selects one of two field elements given a boolean condition:
+/// `out = cond ? a : b`. The condition is constrained to be 0 or 1.
+#[derive(Clone, Debug)]
+pub struct CondSelectConfig {
+    cond: Column<Advice>,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    out: Column<Advice>,
+    s_select: Selector,
+}
+
+pub struct CondSelectChip {
+    config: CondSelectConfig,
+}
+
+impl Chip<pallas::Base> for CondSelectChip {
+    type Config = CondSelectConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl CondSelectChip {
+    pub fn construct(config: CondSelectConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<pallas::Base>,
+        cond: Column<Advice>,
+        a: Column<Advice>,
+        b: Column<Advice>,
+        out: Column<Advice>,
+    ) -> CondSelectConfig {
+        for column in [cond, a, b, out] {
+            meta.enable_equality(column);
+        }
+
+        let s_select = meta.selector();
+
+        meta.create_gate("Conditional selection: out = cond * a + (1 - cond) * b", |meta| {
+            let s_select = meta.query_selector(s_select);
+            let cond = meta.query_advice(cond, Rotation::cur());
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::cur());
+            let out = meta.query_advice(out, Rotation::cur());
+
+            let one = Expression::Constant(pallas::Base::one());
+
+            Constraints::with_selector(
+                s_select,
+                [
+                    ("bool check cond", cond.clone() * (one - cond.clone())),
+                    ("select", b.clone() + cond * (a - b) - out),
+                ],
+            )
+        });
+
+        CondSelectConfig { cond, a, b, out, s_select }
+    }
+
+    pub fn conditional_select(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        cond: &AssignedCell<pallas::Base, pallas::Base>,
+        a: &AssignedCell<pallas::Base, pallas::Base>,
+        b: &AssignedCell<pallas::Base, pallas::Base>,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, plonk::Error> {
+        layouter.assign_region(
+            || "out = cond ? a : b",
+            |mut region| {
+                self.config.s_select.enable(&mut region, 0)?;
+
+                cond.copy_advice(|| "copy cond", &mut region, self.config.cond, 0)?;
+                a.copy_advice(|| "copy a", &mut region, self.config.a, 0)?;
+                b.copy_advice(|| "copy b", &mut region, self.config.b, 0)?;
+
+                let out = cond.value().zip(a.value()).zip(b.value()).map(|((cond, a), b)| {
+                    if *cond == pallas::Base::one() {
+                        *a
+                    } else {
+                        *b
+                    }
+                });
+
+                region.assign_advice(|| "out", self.config.out, 0, || out)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zk::assign_free_advice;
+    use halo2_proofs::{
+        circuit::{floor_planner, Value},
+        dev::MockProver,
+        plonk::{Circuit, Instance as InstanceColumn},
+    };
+
+    #[derive(Default)]
+    struct CondSelectCircuit {
+        cond: Value<pallas::Base>,
+        a: Value<pallas::Base>,
+        b: Value<pallas::Base>,
+    }
+
+    impl Circuit<pallas::Base> for CondSelectCircuit {
+        type Config = (CondSelectConfig, Column<Advice>, Column<InstanceColumn>);
+        type FloorPlanner = floor_planner::V1;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advices = [meta.advice_column(), meta.advice_column(), meta.advice_column()];
+            let out = meta.advice_column();
+            let w = meta.advice_column();
+            meta.enable_equality(w);
+
+            let primary = meta.instance_column();
+            meta.enable_equality(primary);
+
+            let config = CondSelectChip::configure(meta, advices[0], advices[1], advices[2], out);
+            (config, w, primary)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), plonk::Error> {
+            let chip = CondSelectChip::construct(config.0);
+            let cond = assign_free_advice(layouter.namespace(|| "cond"), config.1, self.cond)?;
+            let a = assign_free_advice(layouter.namespace(|| "a"), config.1, self.a)?;
+            let b = assign_free_advice(layouter.namespace(|| "b"), config.1, self.b)?;
+
+            let out = chip.conditional_select(layouter.namespace(|| "select"), &cond, &a, &b)?;
+            layouter.constrain_instance(out.cell(), config.2, 0)
+        }
+    }
+
+    #[test]
+    fn cond_select() {
+        let k = 4;
+        let (a, b) = (pallas::Base::from(42), pallas::Base::from(69));
+
+        for (cond, expected) in [(1, a), (0, b)] {
+            let circuit = CondSelectCircuit {
+                cond: Value::known(pallas::Base::from(cond)),
+                a: Value::known(a),
+                b: Value::known(b),
+            };
+            let prover = MockProver::run(k, &circuit, vec![vec![expected]]).unwrap();
+            prover.assert_satisfied();
+        }
+
+        // Selecting the wrong value fails
+        let circuit = CondSelectCircuit {
+            cond: Value::known(pallas::Base::one()),
+            a: Value::known(a),
+            b: Value::known(b),
+        };
+        let prover = MockProver::run(k, &circuit, vec![vec![b]]).unwrap();
+        assert!(prover.verify().is_err());
+
+        // A non-boolean condition fails
+        let circuit = CondSelectCircuit {
+            cond: Value::known(pallas::Base::from(2)),
+            a: Value::known(a),
+            b: Value::known(b),
+        };
+        let prover = MockProver::run(k, &circuit, vec![vec![b]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
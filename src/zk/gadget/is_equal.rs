@@ -0,0 +1,234 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Chip, Layouter},
+    pasta::pallas,
+    plonk,
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Selector},
+    poly::Rotation,
+};
+
+use super::is_zero::{IsZeroChip, IsZeroConfig};
+
+/// Compares two field elements, returning 1 if they are equal and 0
+/// otherwise. Comparing against zero gives an `is_zero` check.
+#[derive(Clone, Debug)]
+pub struct IsEqualConfig {
+    a: Column<Advice>,
+    b: Column<Advice>,
+    out: Column<Advice>,
+    s_is_eq: Selector,
+    is_zero: IsZeroConfig<pallas::Base>,
+}
+
+pub struct IsEqualChip {
+    config: IsEqualConfig,
+}
+
+impl Chip<pallas::Base> for IsEqualChip {
+    type Config = IsEqualConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl IsEqualChip {
+    pub fn construct(config: IsEqualConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<pallas::Base>,
+        a: Column<Advice>,
+        b: Column<Advice>,
+        diff_inv: Column<Advice>,
+        out: Column<Advice>,
+    ) -> IsEqualConfig {
+        for column in [a, b, out] {
+            meta.enable_equality(column);
+        }
+
+        let s_is_eq = meta.selector();
+
+        // Enforces (a - b) * is_zero(a - b) = 0
+        let is_zero = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(s_is_eq),
+            |meta| meta.query_advice(a, Rotation::cur()) - meta.query_advice(b, Rotation::cur()),
+            diff_inv,
+        );
+
+        meta.create_gate("Equality check: out = (a == b)", |meta| {
+            let s_is_eq = meta.query_selector(s_is_eq);
+            let out = meta.query_advice(out, Rotation::cur());
+
+            Constraints::with_selector(s_is_eq, Some(out - is_zero.expr()))
+        });
+
+        IsEqualConfig { a, b, out, s_is_eq, is_zero }
+    }
+
+    pub fn is_equal(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedCell<pallas::Base, pallas::Base>,
+        b: &AssignedCell<pallas::Base, pallas::Base>,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, plonk::Error> {
+        layouter.assign_region(
+            || "out = (a == b)",
+            |mut region| {
+                self.config.s_is_eq.enable(&mut region, 0)?;
+
+                a.copy_advice(|| "copy a", &mut region, self.config.a, 0)?;
+                b.copy_advice(|| "copy b", &mut region, self.config.b, 0)?;
+
+                let is_zero_chip = IsZeroChip::construct(self.config.is_zero.clone());
+                is_zero_chip.assign(
+                    &mut region,
+                    0,
+                    a.value().zip(b.value()).map(|(a, b)| a - b),
+                )?;
+
+                let out = a.value().zip(b.value()).map(|(a, b)| pallas::Base::from(a == b));
+                region.assign_advice(|| "out", self.config.out, 0, || out)
+            },
+        )
+    }
+
+    /// Returns 1 if `a` is zero, and 0 otherwise. Needs a column enabled
+    /// for constants in the circuit.
+    pub fn is_zero(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedCell<pallas::Base, pallas::Base>,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, plonk::Error> {
+        layouter.assign_region(
+            || "out = (a == 0)",
+            |mut region| {
+                self.config.s_is_eq.enable(&mut region, 0)?;
+
+                a.copy_advice(|| "copy a", &mut region, self.config.a, 0)?;
+                region.assign_advice_from_constant(
+                    || "zero",
+                    self.config.b,
+                    0,
+                    pallas::Base::zero(),
+                )?;
+
+                let is_zero_chip = IsZeroChip::construct(self.config.is_zero.clone());
+                is_zero_chip.assign(&mut region, 0, a.value().copied())?;
+
+                let out = a.value().map(|a| pallas::Base::from(*a == pallas::Base::zero()));
+                region.assign_advice(|| "out", self.config.out, 0, || out)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zk::assign_free_advice;
+    use halo2_proofs::{
+        circuit::{floor_planner, Value},
+        dev::MockProver,
+        plonk::{Circuit, Instance as InstanceColumn},
+    };
+
+    #[derive(Default)]
+    struct IsEqualCircuit {
+        a: Value<pallas::Base>,
+        b: Value<pallas::Base>,
+    }
+
+    impl Circuit<pallas::Base> for IsEqualCircuit {
+        type Config = (IsEqualConfig, Column<Advice>, Column<InstanceColumn>);
+        type FloorPlanner = floor_planner::V1;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advices = [meta.advice_column(), meta.advice_column(), meta.advice_column()];
+            let out = meta.advice_column();
+            let w = meta.advice_column();
+            meta.enable_equality(w);
+
+            let constants = meta.fixed_column();
+            meta.enable_constant(constants);
+
+            let primary = meta.instance_column();
+            meta.enable_equality(primary);
+
+            let config = IsEqualChip::configure(meta, advices[0], advices[1], advices[2], out);
+            (config, w, primary)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), plonk::Error> {
+            let chip = IsEqualChip::construct(config.0);
+            let a = assign_free_advice(layouter.namespace(|| "a"), config.1, self.a)?;
+            let b = assign_free_advice(layouter.namespace(|| "b"), config.1, self.b)?;
+
+            let eq = chip.is_equal(layouter.namespace(|| "a == b"), &a, &b)?;
+            layouter.constrain_instance(eq.cell(), config.2, 0)?;
+
+            let zero = chip.is_zero(layouter.namespace(|| "a == 0"), &a)?;
+            layouter.constrain_instance(zero.cell(), config.2, 1)
+        }
+    }
+
+    #[test]
+    fn is_equal() {
+        let k = 4;
+        let (zero, one) = (pallas::Base::zero(), pallas::Base::one());
+
+        for (a, b, public_inputs) in [
+            (42, 42, vec![one, zero]),
+            (42, 69, vec![zero, zero]),
+            (0, 0, vec![one, one]),
+            (0, 69, vec![zero, one]),
+        ] {
+            let circuit = IsEqualCircuit {
+                a: Value::known(pallas::Base::from(a)),
+                b: Value::known(pallas::Base::from(b)),
+            };
+            let prover = MockProver::run(k, &circuit, vec![public_inputs]).unwrap();
+            prover.assert_satisfied();
+        }
+
+        // Claiming inequal values are equal fails
+        let circuit = IsEqualCircuit {
+            a: Value::known(pallas::Base::from(42)),
+            b: Value::known(pallas::Base::from(69)),
+        };
+        let prover = MockProver::run(k, &circuit, vec![vec![one, zero]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
@@ -30,3 +30,15 @@ pub mod less_than;
 
 /// is_zero comparison gadget
 pub mod is_zero;
+
+/// is_equal and is_zero comparison gadget returning a boolean
+pub mod is_equal;
+
+/// Conditional selection between two field elements
+pub mod cond_select;
+
+/// Conditional selection between zero and a field element
+pub mod zero_cond;
+
+/// Bit decomposition of a field element
+pub mod bit_decomposition;
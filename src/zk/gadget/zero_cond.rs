@@ -0,0 +1,194 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use halo2_proofs::{
+    circuit::{AssignedCell, Chip, Layouter},
+    pasta::pallas,
+    plonk,
+    plonk::{Advice, Column, ConstraintSystem, Constraints, Expression, Selector},
+    poly::Rotation,
+};
+
+use super::is_zero::{IsZeroChip, IsZeroConfig};
+
+/// Selects zero or a field element depending on whether a condition
+/// value is zero: `out = (a == 0) ? 0 : b`.
+#[derive(Clone, Debug)]
+pub struct ZeroCondConfig {
+    a: Column<Advice>,
+    b: Column<Advice>,
+    out: Column<Advice>,
+    s_zero_cond: Selector,
+    is_zero: IsZeroConfig<pallas::Base>,
+}
+
+pub struct ZeroCondChip {
+    config: ZeroCondConfig,
+}
+
+impl Chip<pallas::Base> for ZeroCondChip {
+    type Config = ZeroCondConfig;
+    type Loaded = ();
+
+    fn config(&self) -> &Self::Config {
+        &self.config
+    }
+
+    fn loaded(&self) -> &Self::Loaded {
+        &()
+    }
+}
+
+impl ZeroCondChip {
+    pub fn construct(config: ZeroCondConfig) -> Self {
+        Self { config }
+    }
+
+    pub fn configure(
+        meta: &mut ConstraintSystem<pallas::Base>,
+        a: Column<Advice>,
+        b: Column<Advice>,
+        a_inv: Column<Advice>,
+        out: Column<Advice>,
+    ) -> ZeroCondConfig {
+        for column in [a, b, out] {
+            meta.enable_equality(column);
+        }
+
+        let s_zero_cond = meta.selector();
+
+        // Enforces a * is_zero(a) = 0
+        let is_zero = IsZeroChip::configure(
+            meta,
+            |meta| meta.query_selector(s_zero_cond),
+            |meta| meta.query_advice(a, Rotation::cur()),
+            a_inv,
+        );
+
+        meta.create_gate("Zero condition: out = (a == 0) ? 0 : b", |meta| {
+            let s_zero_cond = meta.query_selector(s_zero_cond);
+            let b = meta.query_advice(b, Rotation::cur());
+            let out = meta.query_advice(out, Rotation::cur());
+
+            let one = Expression::Constant(pallas::Base::one());
+
+            Constraints::with_selector(s_zero_cond, Some(b * (one - is_zero.expr()) - out))
+        });
+
+        ZeroCondConfig { a, b, out, s_zero_cond, is_zero }
+    }
+
+    pub fn zero_cond(
+        &self,
+        mut layouter: impl Layouter<pallas::Base>,
+        a: &AssignedCell<pallas::Base, pallas::Base>,
+        b: &AssignedCell<pallas::Base, pallas::Base>,
+    ) -> Result<AssignedCell<pallas::Base, pallas::Base>, plonk::Error> {
+        layouter.assign_region(
+            || "out = (a == 0) ? 0 : b",
+            |mut region| {
+                self.config.s_zero_cond.enable(&mut region, 0)?;
+
+                a.copy_advice(|| "copy a", &mut region, self.config.a, 0)?;
+                b.copy_advice(|| "copy b", &mut region, self.config.b, 0)?;
+
+                let is_zero_chip = IsZeroChip::construct(self.config.is_zero.clone());
+                is_zero_chip.assign(&mut region, 0, a.value().copied())?;
+
+                let out = a.value().zip(b.value()).map(|(a, b)| {
+                    if *a == pallas::Base::zero() {
+                        pallas::Base::zero()
+                    } else {
+                        *b
+                    }
+                });
+
+                region.assign_advice(|| "out", self.config.out, 0, || out)
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::zk::assign_free_advice;
+    use halo2_proofs::{
+        circuit::{floor_planner, Value},
+        dev::MockProver,
+        plonk::{Circuit, Instance as InstanceColumn},
+    };
+
+    #[derive(Default)]
+    struct ZeroCondCircuit {
+        a: Value<pallas::Base>,
+        b: Value<pallas::Base>,
+    }
+
+    impl Circuit<pallas::Base> for ZeroCondCircuit {
+        type Config = (ZeroCondConfig, Column<Advice>, Column<InstanceColumn>);
+        type FloorPlanner = floor_planner::V1;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<pallas::Base>) -> Self::Config {
+            let advices = [meta.advice_column(), meta.advice_column(), meta.advice_column()];
+            let out = meta.advice_column();
+            let w = meta.advice_column();
+            meta.enable_equality(w);
+
+            let primary = meta.instance_column();
+            meta.enable_equality(primary);
+
+            let config = ZeroCondChip::configure(meta, advices[0], advices[1], advices[2], out);
+            (config, w, primary)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<pallas::Base>,
+        ) -> Result<(), plonk::Error> {
+            let chip = ZeroCondChip::construct(config.0);
+            let a = assign_free_advice(layouter.namespace(|| "a"), config.1, self.a)?;
+            let b = assign_free_advice(layouter.namespace(|| "b"), config.1, self.b)?;
+
+            let out = chip.zero_cond(layouter.namespace(|| "zero_cond"), &a, &b)?;
+            layouter.constrain_instance(out.cell(), config.2, 0)
+        }
+    }
+
+    #[test]
+    fn zero_cond() {
+        let k = 4;
+        let b = pallas::Base::from(69);
+
+        for (a, expected) in [(0, pallas::Base::zero()), (42, b)] {
+            let circuit =
+                ZeroCondCircuit { a: Value::known(pallas::Base::from(a)), b: Value::known(b) };
+            let prover = MockProver::run(k, &circuit, vec![vec![expected]]).unwrap();
+            prover.assert_satisfied();
+        }
+
+        let circuit = ZeroCondCircuit { a: Value::known(pallas::Base::zero()), b: Value::known(b) };
+        let prover = MockProver::run(k, &circuit, vec![vec![b]]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+}
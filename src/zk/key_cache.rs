@@ -0,0 +1,257 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Cache of proving and verifying keys for zkas circuits.
+//!
+//! Generating the polynomial commitment parameters for a given `k` is the
+//! most expensive part of building a key, and they're shared among all
+//! circuits of the same size. The cache keeps them in a directory on disk,
+//! so they're only generated once per machine. The circuit size `k` is the
+//! one recorded in the binary, see [`circuit_k`].
+//!
+//! Only the parameters are persisted. `halo2_proofs` 0.2 can't serialize
+//! `plonk::VerifyingKey` and `plonk::ProvingKey`, so the keys are derived
+//! from the cached parameters on first use, and kept in memory only,
+//! content-addressed by the blake3 hash of the zkas binary, which covers
+//! its `k`. Every process start still runs `keygen_vk` and `keygen_pk`
+//! once per circuit. Writing the keys to disk next to the parameters needs
+//! a `halo2_proofs` release able to serialize them.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+};
+
+use halo2_proofs::{pasta::vesta, poly::commitment::Params};
+use log::{debug, warn};
+
+use super::{
+    proof::{ProvingKey, VerifyingKey},
+    stats::circuit_k,
+    vm::ZkCircuit,
+    vm_stack::empty_witnesses,
+};
+use crate::{
+    zkas::{ZkBinary, MAX_K},
+    Error, Result,
+};
+
+/// Content address of a circuit key: the zkas binary hash
+type KeyId = [u8; 32];
+
+/// Counter making temporary file names unique within the process
+static TMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+pub struct KeyCache {
+    /// Directory holding the serialized parameters, if persistent
+    path: Option<PathBuf>,
+    // TODO: Persist the keys as well, once halo2_proofs can serialize them
+    params: Mutex<HashMap<u32, Params<vesta::Affine>>>,
+    verifying_keys: Mutex<HashMap<KeyId, VerifyingKey>>,
+    proving_keys: Mutex<HashMap<KeyId, ProvingKey>>,
+}
+
+impl KeyCache {
+    /// Create a new cache. Parameters are stored in `path` if given,
+    /// otherwise the cache only lives in memory.
+    pub fn new(path: Option<PathBuf>) -> Result<Self> {
+        if let Some(path) = &path {
+            fs::create_dir_all(path)?;
+        }
+
+        Ok(Self {
+            path,
+            params: Mutex::new(HashMap::new()),
+            verifying_keys: Mutex::new(HashMap::new()),
+            proving_keys: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Fetch the parameters for circuits of size `2^k`, generating them
+    /// if they're neither in memory nor on disk. Sizes above [`MAX_K`]
+    /// are rejected. The lock is not held while reading or generating,
+    /// so concurrent misses may both do the work; the first one to finish
+    /// is kept.
+    pub fn params(&self, k: u32) -> Result<Params<vesta::Affine>> {
+        if k > MAX_K {
+            return Err(Error::Custom(format!(
+                "Circuit size k = {} exceeds the maximum of {}",
+                k, MAX_K
+            )))
+        }
+
+        if let Some(p) = self.params.lock().unwrap().get(&k) {
+            return Ok(p.clone())
+        }
+
+        let p = match self.read_params(k) {
+            Some(p) => p,
+            None => {
+                debug!(target: "zk::key_cache", "Generating params for k={}", k);
+                let p = Params::new(k);
+                self.write_params(k, &p)?;
+                p
+            }
+        };
+
+        Ok(self.params.lock().unwrap().entry(k).or_insert(p).clone())
+    }
+
+    /// Fetch the verifying key for the zkas binary `bincode`.
+    pub fn verifying_key(&self, bincode: &[u8]) -> Result<VerifyingKey> {
+        let hash = blake3::hash(bincode);
+        let id = *hash.as_bytes();
+        if let Some(vk) = self.verifying_keys.lock().unwrap().get(&id) {
+            return Ok(vk.clone())
+        }
+
+        let (k, circuit) = Self::circuit(bincode)?;
+        debug!(target: "zk::key_cache", "Building VerifyingKey for {} (k={})", hash, k);
+        let vk = VerifyingKey::build_with_params(self.params(k)?, &circuit)?;
+        self.verifying_keys.lock().unwrap().insert(id, vk.clone());
+        Ok(vk)
+    }
+
+    /// Fetch the proving key for the zkas binary `bincode`.
+    pub fn proving_key(&self, bincode: &[u8]) -> Result<ProvingKey> {
+        let hash = blake3::hash(bincode);
+        let id = *hash.as_bytes();
+        if let Some(pk) = self.proving_keys.lock().unwrap().get(&id) {
+            return Ok(pk.clone())
+        }
+
+        let (k, circuit) = Self::circuit(bincode)?;
+        debug!(target: "zk::key_cache", "Building ProvingKey for {} (k={})", hash, k);
+        let pk = ProvingKey::build_with_params(self.params(k)?, &circuit)?;
+        self.proving_keys.lock().unwrap().insert(id, pk.clone());
+        Ok(pk)
+    }
+
+    fn circuit(bincode: &[u8]) -> Result<(u32, ZkCircuit)> {
+        let zkbin = ZkBinary::decode(bincode)?;
+        let k = circuit_k(&zkbin)?;
+        Ok((k, ZkCircuit::new(empty_witnesses(&zkbin), zkbin)))
+    }
+
+    fn params_path(&self, k: u32) -> Option<PathBuf> {
+        self.path.as_ref().map(|p| p.join(format!("params_{}.bin", k)))
+    }
+
+    /// Read cached parameters from disk. The file holds the serialized
+    /// parameters followed by their blake3 checksum. Missing or corrupt
+    /// files are treated as a cache miss.
+    fn read_params(&self, k: u32) -> Option<Params<vesta::Affine>> {
+        let path = self.params_path(k)?;
+
+        let mut bytes = vec![];
+        if BufReader::new(File::open(&path).ok()?).read_to_end(&mut bytes).is_err() ||
+            bytes.len() < blake3::OUT_LEN
+        {
+            return None
+        }
+
+        let (data, checksum) = bytes.split_at(bytes.len() - blake3::OUT_LEN);
+        if blake3::hash(data).as_bytes() != checksum {
+            warn!(target: "zk::key_cache", "Checksum mismatch in {:?}, regenerating", path);
+            return None
+        }
+
+        match Params::read(&mut &data[..]) {
+            Ok(p) => Some(p),
+            Err(_) => {
+                warn!(target: "zk::key_cache", "Invalid params in {:?}, regenerating", path);
+                None
+            }
+        }
+    }
+
+    /// Write parameters to disk, going through a temporary file so that
+    /// readers never observe a partial write. The temporary file name is
+    /// unique per process and call, so concurrent writers don't clobber
+    /// each other's files.
+    fn write_params(&self, k: u32, params: &Params<vesta::Affine>) -> Result<()> {
+        let path = match self.params_path(k) {
+            Some(p) => p,
+            None => return Ok(()),
+        };
+
+        let mut data = vec![];
+        params.write(&mut data)?;
+        let checksum = blake3::hash(&data);
+
+        let tmp_path = path.with_extension(format!(
+            "{}.{}.tmp",
+            std::process::id(),
+            TMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+
+        let res = Self::write_file(&tmp_path, &data, checksum.as_bytes())
+            .and_then(|()| fs::rename(&tmp_path, path).map_err(Error::from));
+        if res.is_err() {
+            let _ = fs::remove_file(&tmp_path);
+        }
+        res
+    }
+
+    fn write_file(path: &Path, data: &[u8], checksum: &[u8]) -> Result<()> {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(data)?;
+        file.write_all(checksum)?;
+        file.flush()?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn params_roundtrip_through_disk() {
+        let path = std::env::temp_dir().join(format!("darkfi_key_cache_{}", std::process::id()));
+        let k = 4;
+
+        let cache = KeyCache::new(Some(path.clone())).unwrap();
+        let params = cache.params(k).unwrap();
+        assert!(path.join("params_4.bin").exists());
+        assert_eq!(fs::read_dir(&path).unwrap().count(), 1);
+
+        // A fresh cache reads the parameters back from disk
+        let cache = KeyCache::new(Some(path.clone())).unwrap();
+        let mut expected = vec![];
+        params.write(&mut expected).unwrap();
+        let mut got = vec![];
+        cache.read_params(k).unwrap().write(&mut got).unwrap();
+        assert_eq!(expected, got);
+
+        // Corrupt files are a cache miss
+        let file = path.join("params_4.bin");
+        let mut bytes = fs::read(&file).unwrap();
+        bytes[8] ^= 1;
+        fs::write(&file, bytes).unwrap();
+        assert!(cache.read_params(k).is_none());
+
+        fs::remove_dir_all(path).unwrap();
+    }
+}
@@ -30,6 +30,14 @@ pub mod gadget;
 pub mod proof;
 pub use proof::{Proof, ProvingKey, VerifyingKey};
 
+/// Proving and verifying key cache
+pub mod key_cache;
+pub use key_cache::KeyCache;
+
+/// Circuit resource usage and size selection
+pub mod stats;
+pub use stats::{circuit_k, CircuitStats};
+
 pub mod halo2 {
     pub use halo2_proofs::{
         arithmetic::Field,
@@ -20,7 +20,7 @@ use darkfi_serial::{SerialDecodable, SerialEncodable};
 use halo2_proofs::{
     pasta::{pallas, vesta},
     plonk,
-    plonk::{Circuit, SingleVerifier},
+    plonk::{BatchVerifier, Circuit, SingleVerifier},
     poly::commitment::Params,
     transcript::{Blake2bRead, Blake2bWrite},
 };
@@ -33,10 +33,21 @@ pub struct VerifyingKey {
 }
 
 impl VerifyingKey {
+    /// Build the key for a circuit known to fit in `2^k` rows.
+    /// Panics if it doesn't, use [`Self::build_with_params`] for
+    /// circuits that weren't checked.
     pub fn build(k: u32, c: &impl Circuit<pallas::Base>) -> Self {
-        let params = Params::new(k);
-        let vk = plonk::keygen_vk(&params, c).unwrap();
-        VerifyingKey { params, vk }
+        Self::build_with_params(Params::new(k), c).unwrap()
+    }
+
+    /// Build the key using already generated parameters,
+    /// e.g. from a [`KeyCache`](super::KeyCache).
+    pub fn build_with_params(
+        params: Params<vesta::Affine>,
+        c: &impl Circuit<pallas::Base>,
+    ) -> std::result::Result<Self, plonk::Error> {
+        let vk = plonk::keygen_vk(&params, c)?;
+        Ok(VerifyingKey { params, vk })
     }
 }
 
@@ -47,11 +58,22 @@ pub struct ProvingKey {
 }
 
 impl ProvingKey {
+    /// Build the key for a circuit known to fit in `2^k` rows.
+    /// Panics if it doesn't, use [`Self::build_with_params`] for
+    /// circuits that weren't checked.
     pub fn build(k: u32, c: &impl Circuit<pallas::Base>) -> Self {
-        let params = Params::new(k);
-        let vk = plonk::keygen_vk(&params, c).unwrap();
-        let pk = plonk::keygen_pk(&params, vk, c).unwrap();
-        ProvingKey { params, pk }
+        Self::build_with_params(Params::new(k), c).unwrap()
+    }
+
+    /// Build the key using already generated parameters,
+    /// e.g. from a [`KeyCache`](super::KeyCache).
+    pub fn build_with_params(
+        params: Params<vesta::Affine>,
+        c: &impl Circuit<pallas::Base>,
+    ) -> std::result::Result<Self, plonk::Error> {
+        let vk = plonk::keygen_vk(&params, c)?;
+        let pk = plonk::keygen_pk(&params, vk, c)?;
+        Ok(ProvingKey { params, pk })
     }
 }
 
@@ -101,6 +123,18 @@ impl Proof {
         plonk::verify_proof(&vk.params, &vk.vk, strategy, &[&[instances]], &mut transcript)
     }
 
+    /// Verify several proofs created with the same circuit at once.
+    /// This is cheaper than verifying them one by one, but only tells
+    /// whether all of them are valid.
+    pub fn verify_batch(vk: &VerifyingKey, proofs: &[(&Proof, &[pallas::Base])]) -> bool {
+        let mut batch = BatchVerifier::new();
+        for (proof, instances) in proofs {
+            batch.add_proof(vec![vec![instances.to_vec()]], proof.0.clone());
+        }
+
+        batch.finalize(&vk.params, &vk.vk)
+    }
+
     pub fn new(bytes: Vec<u8>) -> Self {
         Proof(bytes)
     }
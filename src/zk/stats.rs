@@ -0,0 +1,346 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+//! Resource usage of zkas circuits.
+//!
+//! A circuit is measured by running it through its floor planner with an
+//! [`Assignment`] backend that records which cells get assigned instead of
+//! storing any values, like key generation does. From this we know how many
+//! rows the circuit occupies, and so the smallest `k` it fits in, as well
+//! as what every opcode of the zkas binary contributes to it.
+
+use std::collections::BTreeSet;
+
+use halo2_proofs::{
+    circuit::Value,
+    pasta::pallas,
+    plonk,
+    plonk::{
+        Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Fixed, FloorPlanner,
+        Instance, Selector,
+    },
+};
+
+use super::{vm::ZkCircuit, vm_stack::empty_witnesses};
+use crate::{
+    zkas::{Opcode, ZkBinary, MAX_K},
+    Error, Result,
+};
+
+/// Namespace the zkvm synthesizes the opcode at index `idx` in
+pub(super) fn opcode_namespace(idx: usize) -> String {
+    format!("opcode {}", idx)
+}
+
+fn parse_opcode_namespace(namespace: &str) -> Option<usize> {
+    namespace.strip_prefix("opcode ")?.parse().ok()
+}
+
+/// Circuit size of a zkas binary as `2^k` rows. This is the one recorded
+/// in the binary, or for legacy binaries which don't record it, the
+/// measured minimum. Sizes the circuit doesn't fit in, or above
+/// [`MAX_K`], are rejected.
+pub fn circuit_k(zkbin: &ZkBinary) -> Result<u32> {
+    let min_k = CircuitStats::measure(zkbin)?.k;
+    let k = zkbin.k.unwrap_or(min_k);
+
+    if k < min_k {
+        return Err(Error::ZkasDecoderError(format!(
+            "Circuit needs k >= {}, but the binary records k = {}",
+            min_k, k
+        )))
+    }
+
+    if k > MAX_K {
+        return Err(Error::ZkasDecoderError(format!(
+            "Circuit size k = {} exceeds the maximum of {}",
+            k, MAX_K
+        )))
+    }
+
+    Ok(k)
+}
+
+/// Resources used by an opcode, or by the circuit setup
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Usage {
+    /// Number of regions assigned
+    pub regions: usize,
+    /// Rows spanned by those regions. The floor planner may lay regions
+    /// out side by side, so these don't add up to the circuit's rows.
+    pub rows: usize,
+    /// Number of advice cells assigned
+    pub advice_cells: usize,
+    /// Number of distinct advice columns assigned to
+    pub advice_columns: usize,
+    /// Rows enabling a complex selector, i.e. one that lookups can query
+    pub lookup_rows: usize,
+}
+
+/// Resource usage of a zkas circuit
+#[derive(Clone, Debug)]
+pub struct CircuitStats {
+    /// Smallest `k` such that the circuit fits in `2^k` rows
+    pub k: u32,
+    /// Rows holding assignments, including lookup tables and constants
+    pub rows: usize,
+    /// Rows halo2 reserves on top of those, e.g. for blinding factors
+    pub reserved_rows: usize,
+    pub advice_columns: usize,
+    pub fixed_columns: usize,
+    pub instance_columns: usize,
+    pub selectors: usize,
+    pub gates: usize,
+    pub lookups: usize,
+    /// Usage of the circuit setup: lookup tables, constants and witnesses
+    pub setup: Usage,
+    /// Usage of every opcode, in circuit order
+    pub opcodes: Vec<(Opcode, Usage)>,
+}
+
+impl CircuitStats {
+    /// Measure the circuit of a zkas binary.
+    pub fn measure(zkbin: &ZkBinary) -> Result<Self> {
+        let mut cs = ConstraintSystem::default();
+        let config = ZkCircuit::configure(&mut cs);
+        let circuit = ZkCircuit::new(empty_witnesses(zkbin), zkbin.clone());
+
+        let mut measurement = Measurement::new(zkbin.opcodes.len());
+        <ZkCircuit as Circuit<pallas::Base>>::FloorPlanner::synthesize(
+            &mut measurement,
+            &circuit,
+            config,
+            cs.constants().clone(),
+        )?;
+
+        let reserved_rows = cs.minimum_rows();
+        let k = (measurement.rows + reserved_rows).next_power_of_two().trailing_zeros();
+
+        let opcodes = zkbin
+            .opcodes
+            .iter()
+            .zip(measurement.opcodes)
+            .map(|((opcode, _), tally)| (*opcode, tally.into()))
+            .collect();
+
+        Ok(Self {
+            k,
+            rows: measurement.rows,
+            reserved_rows,
+            advice_columns: cs.num_advice_columns(),
+            fixed_columns: cs.num_fixed_columns(),
+            instance_columns: cs.num_instance_columns(),
+            selectors: cs.num_selectors(),
+            gates: cs.gates().len(),
+            lookups: cs.lookups().len(),
+            setup: measurement.setup.into(),
+            opcodes,
+        })
+    }
+}
+
+/// Region being assigned
+struct Region {
+    /// Index of the opcode it belongs to, if any
+    owner: Option<usize>,
+    /// First and last row touched
+    span: Option<(usize, usize)>,
+    advice_cells: usize,
+    advice_columns: BTreeSet<usize>,
+    lookup_rows: BTreeSet<usize>,
+}
+
+/// Accumulated usage of the regions of an opcode
+#[derive(Default)]
+struct Tally {
+    usage: Usage,
+    advice_columns: BTreeSet<usize>,
+}
+
+impl Tally {
+    fn add(&mut self, region: Region) {
+        self.usage.regions += 1;
+        self.usage.rows += region.span.map_or(0, |(first, last)| last - first + 1);
+        self.usage.advice_cells += region.advice_cells;
+        self.usage.lookup_rows += region.lookup_rows.len();
+        self.advice_columns.extend(region.advice_columns);
+    }
+}
+
+impl From<Tally> for Usage {
+    fn from(tally: Tally) -> Self {
+        Self { advice_columns: tally.advice_columns.len(), ..tally.usage }
+    }
+}
+
+/// [`Assignment`] backend recording the cells a circuit assigns
+struct Measurement {
+    namespaces: Vec<String>,
+    region: Option<Region>,
+    /// Highest row touched, plus one
+    rows: usize,
+    setup: Tally,
+    opcodes: Vec<Tally>,
+}
+
+impl Measurement {
+    fn new(opcodes: usize) -> Self {
+        Self {
+            namespaces: vec![],
+            region: None,
+            rows: 0,
+            setup: Tally::default(),
+            opcodes: (0..opcodes).map(|_| Tally::default()).collect(),
+        }
+    }
+
+    fn touch(&mut self, row: usize) {
+        self.rows = self.rows.max(row + 1);
+        if let Some(region) = &mut self.region {
+            region.span = match region.span {
+                Some((first, last)) => Some((first.min(row), last.max(row))),
+                None => Some((row, row)),
+            };
+        }
+    }
+}
+
+impl Assignment<pallas::Base> for Measurement {
+    fn enter_region<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Opcodes are synthesized in a top-level namespace of their own
+        let owner = self.namespaces.first().and_then(|ns| parse_opcode_namespace(ns));
+        self.region = Some(Region {
+            owner,
+            span: None,
+            advice_cells: 0,
+            advice_columns: BTreeSet::new(),
+            lookup_rows: BTreeSet::new(),
+        });
+    }
+
+    fn exit_region(&mut self) {
+        if let Some(region) = self.region.take() {
+            match region.owner.and_then(|idx| self.opcodes.get_mut(idx)) {
+                Some(tally) => tally.add(region),
+                None => self.setup.add(region),
+            }
+        }
+    }
+
+    fn enable_selector<A, AR>(
+        &mut self,
+        _: A,
+        selector: &Selector,
+        row: usize,
+    ) -> std::result::Result<(), plonk::Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        if let Some(region) = &mut self.region {
+            if !selector.is_simple() {
+                region.lookup_rows.insert(row);
+            }
+        }
+        Ok(())
+    }
+
+    fn query_instance(
+        &self,
+        _: Column<Instance>,
+        _: usize,
+    ) -> std::result::Result<Value<pallas::Base>, plonk::Error> {
+        Ok(Value::unknown())
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        column: Column<Advice>,
+        row: usize,
+        _: V,
+    ) -> std::result::Result<(), plonk::Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<pallas::Base>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        if let Some(region) = &mut self.region {
+            region.advice_cells += 1;
+            region.advice_columns.insert(column.index());
+        }
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Fixed>,
+        row: usize,
+        _: V,
+    ) -> std::result::Result<(), plonk::Error>
+    where
+        V: FnOnce() -> Value<VR>,
+        VR: Into<Assigned<pallas::Base>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.touch(row);
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        _: Column<Any>,
+        _: usize,
+        _: Column<Any>,
+        _: usize,
+    ) -> std::result::Result<(), plonk::Error> {
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        _: Column<Fixed>,
+        _: usize,
+        _: Value<Assigned<pallas::Base>>,
+    ) -> std::result::Result<(), plonk::Error> {
+        // Fills the rest of a lookup table column with its default value,
+        // which takes no rows of its own.
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        self.namespaces.push(name_fn().into());
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {
+        self.namespaces.pop();
+    }
+}
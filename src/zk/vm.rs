@@ -16,6 +16,8 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
+use std::collections::HashMap;
+
 use darkfi_sdk::crypto::constants::{
     sinsemilla::{OrchardCommitDomains, OrchardHashDomains},
     util::gen_const_array,
@@ -42,7 +44,10 @@ use halo2_gadgets::{
 };
 use halo2_proofs::{
     circuit::{floor_planner, AssignedCell, Layouter, Value},
-    pasta::{group::Curve, pallas, Fp},
+    pasta::{
+        group::{ff::PrimeField, Curve},
+        pallas, Fp,
+    },
     plonk,
     plonk::{Advice, Circuit, Column, ConstraintSystem, Instance as InstanceColumn},
 };
@@ -53,10 +58,15 @@ use super::{
     assign_free_advice,
     gadget::{
         arithmetic::{ArithChip, ArithConfig, ArithInstruction},
+        bit_decomposition::{BitDecompositionChip, BitDecompositionConfig},
+        cond_select::{CondSelectChip, CondSelectConfig},
+        is_equal::{IsEqualChip, IsEqualConfig},
         less_than::{LessThanChip, LessThanConfig},
         native_range_check::{NativeRangeCheckChip, NativeRangeCheckConfig},
         small_range_check::{SmallRangeCheckChip, SmallRangeCheckConfig},
+        zero_cond::{ZeroCondChip, ZeroCondConfig},
     },
+    stats::opcode_namespace,
 };
 use crate::zkas::{
     types::{LitType, StackType},
@@ -79,6 +89,10 @@ pub struct VmConfig {
     native_253_range_check_config: NativeRangeCheckConfig<3, 253, 85>,
     lessthan_config: LessThanConfig<3, 253, 85>,
     boolcheck_config: SmallRangeCheckConfig,
+    bit_decomposition_config: BitDecompositionConfig,
+    cond_select_config: CondSelectConfig,
+    zero_cond_config: ZeroCondConfig,
+    is_equal_config: IsEqualConfig,
 }
 
 impl VmConfig {
@@ -263,6 +277,18 @@ impl Circuit<pallas::Base> for ZkCircuit {
         // chip with a range of 2, which enforces one bit, i.e. 0 or 1.
         let boolcheck_config = SmallRangeCheckChip::configure(meta, advices[9], 2);
 
+        // Configuration for bit decomposition, using a running sum
+        let bit_decomposition_config =
+            BitDecompositionChip::configure(meta, advices[6], advices[7]);
+
+        // Configurations for conditional selection and equality checks
+        let cond_select_config =
+            CondSelectChip::configure(meta, advices[6], advices[7], advices[8], advices[9]);
+        let zero_cond_config =
+            ZeroCondChip::configure(meta, advices[6], advices[7], advices[8], advices[9]);
+        let is_equal_config =
+            IsEqualChip::configure(meta, advices[6], advices[7], advices[8], advices[9]);
+
         VmConfig {
             primary,
             advices,
@@ -277,6 +303,10 @@ impl Circuit<pallas::Base> for ZkCircuit {
             native_253_range_check_config,
             lessthan_config,
             boolcheck_config,
+            bit_decomposition_config,
+            cond_select_config,
+            zero_cond_config,
+            is_equal_config,
         }
     }
 
@@ -335,6 +365,19 @@ impl Circuit<pallas::Base> for ZkCircuit {
         // Construct the boolean check chip.
         let boolcheck_chip = SmallRangeCheckChip::construct(config.boolcheck_config.clone());
 
+        // Construct the bit decomposition chip.
+        let bit_decomposition_chip =
+            BitDecompositionChip::construct(config.bit_decomposition_config.clone());
+
+        // Bit decompositions, keyed by the stack index of the decomposed
+        // value, so it is only decomposed once.
+        let mut decomposed_bits: HashMap<usize, Vec<AssignedCell<Fp, Fp>>> = HashMap::new();
+
+        // Construct the conditional selection and equality chips.
+        let cond_select_chip = CondSelectChip::construct(config.cond_select_config.clone());
+        let zero_cond_chip = ZeroCondChip::construct(config.zero_cond_config.clone());
+        let is_equal_chip = IsEqualChip::construct(config.is_equal_config.clone());
+
         // ==========================
         // Constants setup
         // ==========================
@@ -475,7 +518,11 @@ impl Circuit<pallas::Base> for ZkCircuit {
         // And now, work through opcodes
         // =============================
         // TODO: Copy constraints
-        for opcode in &self.opcodes {
+        for (idx, opcode) in self.opcodes.iter().enumerate() {
+            // Every opcode gets its own namespace, so the resources it uses
+            // can be told apart when measuring the circuit.
+            let mut layouter = layouter.namespace(|| opcode_namespace(idx));
+
             match opcode.0 {
                 Opcode::EcAdd => {
                     trace!(target: "zk::vm", "Executing `EcAdd{:?}` opcode", opcode.1);
@@ -798,6 +845,93 @@ impl Circuit<pallas::Base> for ZkCircuit {
                         .small_range_check(layouter.namespace(|| "copy boolean check"), w)?;
                 }
 
+                Opcode::GetBit => {
+                    trace!(target: "zk::vm", "Executing `GetBit{:?}` opcode", opcode.1);
+                    let args = &opcode.1;
+
+                    let lit = litstack[literals_offset];
+                    literals_offset += 1;
+
+                    if lit >= Fp::NUM_BITS as u64 {
+                        error!(target: "zk::vm", "Unsupported bit index {} for get_bit", lit);
+                        return Err(plonk::Error::Synthesis)
+                    }
+
+                    let idx = args[0].1;
+                    if !decomposed_bits.contains_key(&idx) {
+                        let value: AssignedCell<Fp, Fp> = stack[idx].clone().into();
+                        let bits = bit_decomposition_chip.decompose(
+                            layouter.namespace(|| "GetBit() decomposition"),
+                            &value,
+                            Fp::NUM_BITS as usize,
+                        )?;
+                        decomposed_bits.insert(idx, bits);
+                    }
+
+                    let bit = decomposed_bits[&idx][lit as usize].clone();
+
+                    trace!(target: "zk::vm", "Pushing bit to stack index {}", stack.len());
+                    stack.push(StackVar::Base(bit));
+                }
+
+                Opcode::CondSelect => {
+                    trace!(target: "zk::vm", "Executing `CondSelect{:?}` opcode", opcode.1);
+                    let args = &opcode.1;
+
+                    let cond = &stack[args[0].1].clone().into();
+                    let a = &stack[args[1].1].clone().into();
+                    let b = &stack[args[2].1].clone().into();
+
+                    let out = cond_select_chip.conditional_select(
+                        layouter.namespace(|| "CondSelect()"),
+                        cond,
+                        a,
+                        b,
+                    )?;
+
+                    trace!(target: "zk::vm", "Pushing selection to stack index {}", stack.len());
+                    stack.push(StackVar::Base(out));
+                }
+
+                Opcode::ZeroCondSelect => {
+                    trace!(target: "zk::vm", "Executing `ZeroCondSelect{:?}` opcode", opcode.1);
+                    let args = &opcode.1;
+
+                    let a = &stack[args[0].1].clone().into();
+                    let b = &stack[args[1].1].clone().into();
+
+                    let out =
+                        zero_cond_chip.zero_cond(layouter.namespace(|| "ZeroCond()"), a, b)?;
+
+                    trace!(target: "zk::vm", "Pushing selection to stack index {}", stack.len());
+                    stack.push(StackVar::Base(out));
+                }
+
+                Opcode::IsZero => {
+                    trace!(target: "zk::vm", "Executing `IsZero{:?}` opcode", opcode.1);
+                    let args = &opcode.1;
+
+                    let a = &stack[args[0].1].clone().into();
+
+                    let out = is_equal_chip.is_zero(layouter.namespace(|| "IsZero()"), a)?;
+
+                    trace!(target: "zk::vm", "Pushing boolean to stack index {}", stack.len());
+                    stack.push(StackVar::Base(out));
+                }
+
+                Opcode::IsEqual => {
+                    trace!(target: "zk::vm", "Executing `IsEqual{:?}` opcode", opcode.1);
+                    let args = &opcode.1;
+
+                    let a = &stack[args[0].1].clone().into();
+                    let b = &stack[args[1].1].clone().into();
+
+                    let out = is_equal_chip.is_equal(layouter.namespace(|| "IsEqual()"), a, b)?;
+
+                    trace!(target: "zk::vm", "Pushing boolean to stack index {}", stack.len());
+                    stack.push(StackVar::Base(out));
+                }
+
                 Opcode::ConstrainEqualBase => {
                     trace!(target: "zk::vm", "Executing `ConstrainEqualBase{:?}` opcode", opcode.1);
                     let args = &opcode.1;
@@ -16,14 +16,11 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::{
-    io::{stdin, stdout, Read, Write},
-    str::Chars,
-};
+use std::io::{stdin, stdout, Read, Write};
 
 use super::{
     ast::{Arg, Constant, Literal, Statement, StatementType, Var, Variable, Witness},
-    error::ErrorEmitter,
+    error::{Diagnostic, ErrorEmitter, ZkasResult},
     Opcode, VarType,
 };
 
@@ -38,39 +35,55 @@ pub struct Analyzer {
 
 impl Analyzer {
     pub fn new(
-        filename: &str,
-        source: Chars,
         constants: Vec<Constant>,
         witnesses: Vec<Witness>,
         statements: Vec<Statement>,
     ) -> Self {
-        // For nice error reporting, we'll load everything into a string
-        // vector so we have references to lines.
-        let lines: Vec<String> = source.as_str().lines().map(|x| x.to_string()).collect();
-        let error = ErrorEmitter::new("Semantic", filename, lines);
-
+        let error = ErrorEmitter::new("Semantic");
         Self { constants, witnesses, statements, literals: vec![], stack: vec![], error }
     }
 
-    pub fn analyze_types(&mut self) {
+    /// Diagnostics emitted so far, including warnings of a successful run.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.error.diagnostics()
+    }
+
+    /// Type-check the statements and assign types to their variables.
+    /// Every statement is checked, so all type errors are returned together.
+    pub fn analyze_types(&mut self) -> ZkasResult<()> {
         // To work around the pedantic safety, we'll make new vectors and then
         // replace the `statements` and `stack` vectors from the `Analyzer`
         // object when we are done.
         let mut statements = vec![];
         let mut stack = vec![];
 
-        for statement in &self.statements {
+        'statements: for statement in &self.statements {
             //println!("{:?}", statement);
             let mut stmt = statement.clone();
 
             let (return_types, arg_types) = statement.opcode.arg_types();
             let mut rhs = vec![];
 
+            // Discard a statement that can't be analyzed any further. Its
+            // result still goes on the stack, so statements using it don't
+            // report unknown variables.
+            macro_rules! skip_statement {
+                () => {
+                    if statement.typ == StatementType::Assign && !return_types.is_empty() {
+                        let mut var = statement.lhs.clone().unwrap();
+                        var.typ = return_types[0];
+                        stack.push(var);
+                        self.stack = stack.clone();
+                    }
+                    continue 'statements
+                };
+            }
+
             // This handling is kinda limiting, but it'll do for now.
             if !(arg_types[0] == VarType::BaseArray || arg_types[0] == VarType::ScalarArray) {
                 // Check that number of args is correct
                 if statement.rhs.len() != arg_types.len() {
-                    self.error.abort(
+                    self.error.error(
                         &format!(
                             "Incorrect number of arguments for statement. Expected {}, got {}.",
                             arg_types.len(),
@@ -79,32 +92,33 @@ impl Analyzer {
                         statement.line,
                         1,
                     );
+                    skip_statement!();
                 }
             } else {
                 // In case of arrays, check there's at least one element.
                 if statement.rhs.is_empty() {
-                    self.error.abort(
+                    self.error.error(
                         "Expected at least one element for statement using arrays.",
                         statement.line,
                         1,
                     );
+                    skip_statement!();
                 }
             }
 
             // Edge-cases for some opcodes
-            #[allow(clippy::single_match)]
             match &statement.opcode {
                 Opcode::RangeCheck => {
                     if let Arg::Lit(arg0) = &statement.rhs[0] {
                         if &arg0.name != "64" && &arg0.name != "253" {
-                            self.error.abort(
+                            self.error.error(
                                 "Supported range checks are only 64 and 253 bits.",
                                 arg0.line,
                                 arg0.column,
                             );
                         }
                     } else {
-                        self.error.abort(
+                        self.error.error(
                             "Invalid argument for range_check opcode.",
                             statement.line,
                             0,
@@ -112,6 +126,24 @@ impl Analyzer {
                     }
                 }
 
+                Opcode::GetBit => {
+                    if let Arg::Lit(arg1) = &statement.rhs[1] {
+                        if arg1.name.parse::<u64>().map_or(true, |i| i >= 255) {
+                            self.error.error(
+                                "Bit index for get_bit must be below 255.",
+                                arg1.line,
+                                arg1.column,
+                            );
+                        }
+                    } else {
+                        self.error.error(
+                            "Invalid argument for get_bit opcode, bit index must be a literal.",
+                            statement.line,
+                            0,
+                        );
+                    }
+                }
+
                 _ => {}
             }
 
@@ -127,7 +159,7 @@ impl Analyzer {
                 if let Arg::Func(func) = arg {
                     let (f_return_types, f_arg_types) = func.opcode.arg_types();
                     if f_return_types.is_empty() {
-                        self.error.abort(
+                        self.error.error(
                             &format!(
                                 "Used a function argument which doesn't have a return value: {:?}",
                                 func.opcode
@@ -135,6 +167,22 @@ impl Analyzer {
                             statement.line,
                             1,
                         );
+                        skip_statement!();
+                    }
+
+                    let is_array = f_arg_types[0] == VarType::BaseArray ||
+                        f_arg_types[0] == VarType::ScalarArray;
+                    if !is_array && func.rhs.len() != f_arg_types.len() {
+                        self.error.error(
+                            &format!(
+                                "Incorrect number of arguments for function argument. Expected {}, got {}.",
+                                f_arg_types.len(),
+                                func.rhs.len()
+                            ),
+                            func.line,
+                            1,
+                        );
+                        skip_statement!();
                     }
 
                     let v = Variable {
@@ -147,7 +195,7 @@ impl Analyzer {
                     // FIXME: Needs better *Array handling.
                     if arg_types[0] == VarType::BaseArray {
                         if f_return_types[0] != VarType::Base {
-                            self.error.abort(
+                            self.error.error(
                                 &format!(
                                     "Function passed as argument returns wrong type. Expected `{:?}`, got `{:?}`.",
                                     VarType::Base,
@@ -159,7 +207,7 @@ impl Analyzer {
                         }
                     } else if arg_types[0] == VarType::ScalarArray {
                         if f_return_types[0] != VarType::Scalar {
-                            self.error.abort(
+                            self.error.error(
                                 &format!(
                                     "Function passed as argument returns wrong type. Expected `{:?}`, got `{:?}`.",
                                     VarType::Scalar,
@@ -170,7 +218,7 @@ impl Analyzer {
                             );
                         }
                     } else if f_return_types[0] != arg_types[idx] {
-                        self.error.abort(
+                        self.error.error(
                             &format!(
                                 "Function passed as argument returns wrong type. Expected `{:?}`, got `{:?}`.",
                                 arg_types[idx],
@@ -196,7 +244,7 @@ impl Analyzer {
                                 };
 
                                 if var_type != f_arg_types[inner_idx] {
-                                    self.error.abort(
+                                    self.error.error(
                                         &format!(
                                             "Incorrect argument type. Expected `{:?}`, got `{:?}`.",
                                             f_arg_types[inner_idx], var_type
@@ -214,13 +262,17 @@ impl Analyzer {
                                 continue
                             }
 
-                            self.error.abort(
+                            self.error.error(
                                 &format!("Unknown variable reference `{}`.", v.name),
                                 v.line,
                                 v.column,
                             );
                         } else {
-                            unimplemented!()
+                            self.error.error(
+                                "Nested function calls only support variables as arguments.",
+                                func.line,
+                                1,
+                            );
                         }
                     }
 
@@ -256,7 +308,7 @@ impl Analyzer {
                     // type checking.
                     let var_type = v.typ.to_vartype();
                     if var_type != arg_types[idx] {
-                        self.error.abort(
+                        self.error.error(
                             &format!(
                                 "Incorrect argument type. Expected `{:?}`, got `{:?}`.",
                                 arg_types[idx], var_type
@@ -283,7 +335,7 @@ impl Analyzer {
                         // FIXME: Better array handling
                         if arg_types[0] == VarType::BaseArray {
                             if var_type != VarType::Base {
-                                self.error.abort(
+                                self.error.error(
                                     &format!(
                                         "Incorrect argument type. Expected `{:?}`, got `{:?}`.",
                                         VarType::Base,
@@ -295,7 +347,7 @@ impl Analyzer {
                             }
                         } else if arg_types[0] == VarType::ScalarArray {
                             if var_type != VarType::Scalar {
-                                self.error.abort(
+                                self.error.error(
                                     &format!(
                                         "Incorrect argument type. Expected `{:?}`, got `{:?}`.",
                                         VarType::Scalar,
@@ -306,7 +358,7 @@ impl Analyzer {
                                 );
                             }
                         } else if var_type != arg_types[idx] {
-                            self.error.abort(
+                            self.error.error(
                                 &format!(
                                     "Incorrect argument type. Expected `{:?}`, got `{:?}`.",
                                     arg_types[idx], var_type
@@ -323,7 +375,7 @@ impl Analyzer {
                         continue
                     }
 
-                    self.error.abort(
+                    self.error.error(
                         &format!("Unknown variable reference `{}`.", v.name),
                         v.line,
                         v.column,
@@ -358,6 +410,8 @@ impl Analyzer {
         //println!("=================STATEMENTS===============\n{:#?}", self.statements);
         //println!("===================STACK==================\n{:#?}", self.stack);
         //println!("==================LITERALS================\n{:#?}", self.literals);
+
+        self.error.finish(())
     }
 
     fn lookup_var(&self, name: &str) -> Option<Var> {
@@ -406,7 +460,8 @@ impl Analyzer {
         None
     }
 
-    pub fn analyze_semantic(&mut self) {
+    /// Interactively step through the circuit execution.
+    pub fn analyze_semantic(&mut self) -> ZkasResult<()> {
         let mut stack = vec![];
 
         println!("Loading constants...\n-----");
@@ -445,7 +500,7 @@ impl Analyzer {
                     if let Some(index) = stack.iter().position(|&r| r == &arg.name) {
                         println!("Found at stack index {}", index);
                     } else {
-                        self.error.abort(
+                        self.error.error(
                             &format!("Could not find `{}` on the stack", arg.name),
                             arg.line,
                             arg.column,
@@ -472,6 +527,8 @@ impl Analyzer {
                 _ => unreachable!(),
             }
         }
+
+        self.error.finish(())
     }
 
     fn pause() {
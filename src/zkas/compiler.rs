@@ -16,23 +16,27 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::str::Chars;
-
 use darkfi_serial::{serialize, VarInt};
 
 use super::{
     ast::{Arg, Constant, Literal, Statement, StatementType, Witness},
-    error::ErrorEmitter,
+    error::{Diagnostic, ErrorEmitter, ZkasResult},
     types::StackType,
 };
 
 /// Version of the binary
-pub const BINARY_VERSION: u8 = 2;
+pub const BINARY_VERSION: u8 = 3;
 /// Magic bytes prepended to the binary
 pub const MAGIC_BYTES: [u8; 4] = [0x0b, 0x01, 0xb1, 0x35];
+/// Sections of the binary, in the order they must appear.
+/// All of them are required, except for `.debug`.
+pub const SECTIONS: [&str; 5] = [".constant", ".literal", ".contract", ".circuit", ".debug"];
+/// Length of the blake3 checksum appended to the binary
+pub const CHECKSUM_LEN: usize = 32;
 
 pub struct Compiler {
     namespace: String,
+    k: u32,
     constants: Vec<Constant>,
     witnesses: Vec<Witness>,
     statements: Vec<Statement>,
@@ -42,65 +46,62 @@ pub struct Compiler {
 }
 
 impl Compiler {
-    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        filename: &str,
-        source: Chars,
         namespace: String,
+        k: u32,
         constants: Vec<Constant>,
         witnesses: Vec<Witness>,
         statements: Vec<Statement>,
         literals: Vec<Literal>,
         debug_info: bool,
     ) -> Self {
-        // For nice error reporting, we'll load everything into a string
-        // vector so we have references to lines.
-        let lines: Vec<String> = source.as_str().lines().map(|x| x.to_string()).collect();
-        let error = ErrorEmitter::new("Compiler", filename, lines);
-
-        Self { namespace, constants, witnesses, statements, literals, debug_info, error }
+        let error = ErrorEmitter::new("Compiler");
+        Self { namespace, k, constants, witnesses, statements, literals, debug_info, error }
     }
 
-    pub fn compile(&self) -> Vec<u8> {
-        let mut bincode = vec![];
-
-        // Write the magic bytes and version
-        bincode.extend_from_slice(&MAGIC_BYTES);
-        bincode.push(BINARY_VERSION);
+    /// Set the circuit size recorded in the binary, e.g. once it has been
+    /// measured from a previous compilation.
+    pub fn set_k(&mut self, k: u32) {
+        self.k = k;
+    }
 
-        // Write the circuit's namespace
-        bincode.extend_from_slice(&serialize(&self.namespace));
+    /// Diagnostics emitted so far, including warnings of a successful run.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.error.diagnostics()
+    }
 
+    /// Compile the analyzed circuit into its binary representation.
+    pub fn compile(&self) -> ZkasResult<Vec<u8>> {
         // Temporaty stack vector for lookups
         let mut tmp_stack = vec![];
 
         // In the .constant section of the binary, we write the constant's type,
         // and the name so the VM can look it up from `src/crypto/constants/`.
-        bincode.extend_from_slice(b".constant");
+        let mut constants = vec![];
         for i in &self.constants {
             tmp_stack.push(i.name.as_str());
-            bincode.push(i.typ as u8);
-            bincode.extend_from_slice(&serialize(&i.name));
+            constants.push(i.typ as u8);
+            constants.extend_from_slice(&serialize(&i.name));
         }
 
         // Currently, our literals are only Uint64 types, in the binary we'll
         // add them here in the .literal section. In the VM, they will be on
         // their own stack, used for reference by opcodes.
-        bincode.extend_from_slice(b".literal");
+        let mut literals = vec![];
         for i in &self.literals {
-            bincode.push(i.typ as u8);
-            bincode.extend_from_slice(&serialize(&i.name));
+            literals.push(i.typ as u8);
+            literals.extend_from_slice(&serialize(&i.name));
         }
 
         // In the .contract section, we write all our witness types, on the stack
         // they're in order of appearance.
-        bincode.extend_from_slice(b".contract");
+        let mut contract = vec![];
         for i in &self.witnesses {
             tmp_stack.push(i.name.as_str());
-            bincode.push(i.typ as u8);
+            contract.push(i.typ as u8);
         }
 
-        bincode.extend_from_slice(b".circuit");
+        let mut circuit = vec![];
         for i in &self.statements {
             match i.typ {
                 StatementType::Assign => tmp_stack.push(&i.lhs.as_ref().unwrap().name),
@@ -110,19 +111,19 @@ impl Compiler {
                 _ => unreachable!(),
             }
 
-            bincode.push(i.opcode as u8);
-            bincode.extend_from_slice(&serialize(&VarInt(i.rhs.len() as u64)));
+            circuit.push(i.opcode as u8);
+            circuit.extend_from_slice(&serialize(&VarInt(i.rhs.len() as u64)));
 
             for arg in &i.rhs {
                 match arg {
                     Arg::Var(arg) => {
                         if let Some(found) = Compiler::lookup_stack(&tmp_stack, &arg.name) {
-                            bincode.push(StackType::Var as u8);
-                            bincode.extend_from_slice(&serialize(&VarInt(found as u64)));
+                            circuit.push(StackType::Var as u8);
+                            circuit.extend_from_slice(&serialize(&VarInt(found as u64)));
                             continue
                         }
 
-                        self.error.abort(
+                        self.error.error(
                             &format!("Failed finding a stack reference for `{}`", arg.name),
                             arg.line,
                             arg.column,
@@ -130,12 +131,12 @@ impl Compiler {
                     }
                     Arg::Lit(lit) => {
                         if let Some(found) = Compiler::lookup_literal(&self.literals, &lit.name) {
-                            bincode.push(StackType::Lit as u8);
-                            bincode.extend_from_slice(&serialize(&VarInt(found as u64)));
+                            circuit.push(StackType::Lit as u8);
+                            circuit.extend_from_slice(&serialize(&VarInt(found as u64)));
                             continue
                         }
 
-                        self.error.abort(
+                        self.error.error(
                             &format!("Failed finding literal `{}`", lit.name),
                             lit.line,
                             lit.column,
@@ -146,14 +147,63 @@ impl Compiler {
             }
         }
 
-        // If we're not doing debug info, we're done here and can return.
-        if !self.debug_info {
-            return bincode
+        let mut sections = vec![
+            (SECTIONS[0], constants),
+            (SECTIONS[1], literals),
+            (SECTIONS[2], contract),
+            (SECTIONS[3], circuit),
+        ];
+
+        // The .debug section holds the source names of the witnesses, and
+        // the source line and assigned variable name of every statement,
+        // so that the binary can be disassembled back into readable code.
+        if self.debug_info {
+            let mut debug = vec![];
+            debug.extend_from_slice(&serialize(&VarInt(self.witnesses.len() as u64)));
+            for i in &self.witnesses {
+                debug.extend_from_slice(&serialize(&i.name));
+            }
+
+            debug.extend_from_slice(&serialize(&VarInt(self.statements.len() as u64)));
+            for i in &self.statements {
+                let name = i.lhs.as_ref().map_or("", |lhs| lhs.name.as_str());
+                debug.extend_from_slice(&serialize(&VarInt(i.line as u64)));
+                debug.extend_from_slice(&serialize(&name.to_string()));
+            }
+
+            sections.push((SECTIONS[4], debug));
+        }
+
+        // Write the header: magic bytes, version, circuit size, and namespace
+        let mut bincode = vec![];
+        bincode.extend_from_slice(&MAGIC_BYTES);
+        bincode.push(BINARY_VERSION);
+        bincode.extend_from_slice(&serialize(&self.k));
+        bincode.extend_from_slice(&serialize(&self.namespace));
+
+        // Then the section table, holding the name, absolute offset, and
+        // length of every section. The bodies follow right after it.
+        bincode.extend_from_slice(&serialize(&VarInt(sections.len() as u64)));
+        let table_len: usize =
+            sections.iter().map(|(name, _)| serialize(&name.to_string()).len() + 16).sum();
+
+        let mut offset = (bincode.len() + table_len) as u64;
+        for (name, body) in &sections {
+            bincode.extend_from_slice(&serialize(&name.to_string()));
+            bincode.extend_from_slice(&serialize(&offset));
+            bincode.extend_from_slice(&serialize(&(body.len() as u64)));
+            offset += body.len() as u64;
+        }
+
+        for (_, body) in &sections {
+            bincode.extend_from_slice(body);
         }
 
-        // TODO: Otherwise, we proceed appending debug info.
+        // Finally, the checksum of everything preceding it
+        let checksum = blake3::hash(&bincode);
+        bincode.extend_from_slice(checksum.as_bytes());
 
-        bincode
+        self.error.finish(bincode)
     }
 
     fn lookup_stack(stack: &[&str], name: &str) -> Option<usize> {
@@ -16,20 +16,48 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use darkfi_serial::{deserialize_partial, VarInt};
+use darkfi_serial::{deserialize_partial, Decodable, VarInt};
 
-use super::{compiler::MAGIC_BYTES, types::StackType, LitType, Opcode, VarType};
+use super::{
+    compiler::{BINARY_VERSION, CHECKSUM_LEN, MAGIC_BYTES, SECTIONS},
+    types::StackType,
+    LitType, Opcode, VarType,
+};
 use crate::{Error::ZkasDecoderError as ZkasErr, Result};
 
+/// Binary version predating the section table, which can still be decoded
+const LEGACY_BINARY_VERSION: u8 = 2;
+
+/// Largest circuit size, as `2^k` rows, a binary may record. Keys have to
+/// be built for every deployed circuit, so this bounds the work a binary
+/// can ask of the node.
+pub const MAX_K: u32 = 16;
+
 /// A ZkBinary decoded from compiled zkas code.
 /// This is used by the zkvm.
 #[derive(Clone, Debug)]
 pub struct ZkBinary {
     pub namespace: String,
+    /// Format version the binary was encoded with
+    pub version: u8,
+    /// Circuit size as `2^k` rows, unknown for legacy binaries
+    pub k: Option<u32>,
     pub constants: Vec<(VarType, String)>,
     pub literals: Vec<(LitType, String)>,
     pub witnesses: Vec<VarType>,
     pub opcodes: Vec<(Opcode, Vec<(StackType, usize)>)>,
+    /// Source information, unless the binary was stripped
+    pub debug_info: Option<DebugInfo>,
+}
+
+/// Contents of the optional `.debug` section
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DebugInfo {
+    /// Source names of the witnesses, in `.contract` order
+    pub witnesses: Vec<String>,
+    /// Source line and assigned variable name of every opcode.
+    /// The name is empty for opcodes whose result is not assigned.
+    pub opcodes: Vec<(usize, String)>,
 }
 
 // https://stackoverflow.com/questions/35901547/how-can-i-find-a-subsequence-in-a-u8-slice
@@ -37,15 +65,137 @@ fn find_subslice(haystack: &[u8], needle: &[u8]) -> Option<usize> {
     haystack.windows(needle.len()).position(|window| window == needle)
 }
 
+/// Deserialize a `T` found at `offset` in `bytes`, and advance the offset.
+fn read<T: Decodable>(bytes: &[u8], offset: &mut usize) -> Result<T> {
+    let slice = match bytes.get(*offset..) {
+        Some(v) => v,
+        None => return Err(ZkasErr("Unexpected end of binary".to_string())),
+    };
+
+    let (value, len) = deserialize_partial::<T>(slice)?;
+    *offset += len;
+    Ok(value)
+}
+
 impl ZkBinary {
     pub fn decode(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 5 {
+            return Err(ZkasErr("Binary is too short".to_string()))
+        }
+
         let magic_bytes = &bytes[0..4];
         if magic_bytes != MAGIC_BYTES {
             return Err(ZkasErr("Magic bytes are incorrect.".to_string()))
         }
 
-        let _binary_version = &bytes[4];
+        match bytes[4] {
+            BINARY_VERSION => Self::decode_sections(bytes),
+            LEGACY_BINARY_VERSION => Self::decode_legacy(bytes),
+            v => Err(ZkasErr(format!("Unsupported binary version {}", v))),
+        }
+    }
+
+    /// Decode a binary carrying a section table and checksum.
+    /// Every byte of the binary has to be accounted for.
+    fn decode_sections(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() < 5 + CHECKSUM_LEN {
+            return Err(ZkasErr("Binary is too short".to_string()))
+        }
+
+        let (bytes, checksum) = bytes.split_at(bytes.len() - CHECKSUM_LEN);
+        if blake3::hash(bytes).as_bytes() != checksum {
+            return Err(ZkasErr("Checksum mismatch".to_string()))
+        }
+
+        let mut offset = 5;
+        let k: u32 = read(bytes, &mut offset)?;
+        if k > MAX_K {
+            return Err(ZkasErr(format!("Circuit size k = {} exceeds the maximum of {}", k, MAX_K)))
+        }
+        let namespace: String = read(bytes, &mut offset)?;
+
+        let num_sections: VarInt = read(bytes, &mut offset)?;
+        if num_sections.0 > SECTIONS.len() as u64 {
+            return Err(ZkasErr(format!("Too many sections: {}", num_sections.0)))
+        }
+
+        let mut table = vec![];
+        for _ in 0..num_sections.0 {
+            let name: String = read(bytes, &mut offset)?;
+            let section_offset: u64 = read(bytes, &mut offset)?;
+            let section_len: u64 = read(bytes, &mut offset)?;
+            table.push((name, section_offset, section_len));
+        }
+
+        // Sections must appear in order, and follow each other right after
+        // the table until the checksum.
+        let mut sections: [Option<&[u8]>; SECTIONS.len()] = [None; SECTIONS.len()];
+        let mut last_idx = None;
+        for (name, section_offset, section_len) in table {
+            let idx = match SECTIONS.iter().position(|s| *s == name) {
+                Some(v) => v,
+                None => return Err(ZkasErr(format!("Unknown section {}", name))),
+            };
+
+            if last_idx.map_or(false, |last| idx <= last) {
+                return Err(ZkasErr(format!("Section {} is duplicate or out of order", name)))
+            }
+
+            if section_offset != offset as u64 {
+                return Err(ZkasErr(format!("Section {} has an invalid offset", name)))
+            }
 
+            let end = match section_offset.checked_add(section_len) {
+                Some(end) if end <= bytes.len() as u64 => end as usize,
+                _ => return Err(ZkasErr(format!("Section {} is out of bounds", name))),
+            };
+
+            sections[idx] = Some(&bytes[offset..end]);
+            last_idx = Some(idx);
+            offset = end;
+        }
+
+        if offset != bytes.len() {
+            return Err(ZkasErr("Trailing bytes after the last section".to_string()))
+        }
+
+        let required = |i: usize| {
+            sections[i].ok_or_else(|| ZkasErr(format!("Could not find {} section", SECTIONS[i])))
+        };
+
+        let constants = ZkBinary::parse_constants(required(0)?)?;
+        let literals = ZkBinary::parse_literals(required(1)?)?;
+        let witnesses = ZkBinary::parse_contract(required(2)?)?;
+        let opcodes = ZkBinary::parse_circuit(required(3)?)?;
+
+        let debug_info = match sections[4] {
+            Some(section) => {
+                let debug_info = ZkBinary::parse_debug(section)?;
+                if debug_info.witnesses.len() != witnesses.len() ||
+                    debug_info.opcodes.len() != opcodes.len()
+                {
+                    return Err(ZkasErr(".debug section does not match the circuit".to_string()))
+                }
+                Some(debug_info)
+            }
+            None => None,
+        };
+
+        Ok(Self {
+            namespace,
+            version: BINARY_VERSION,
+            k: Some(k),
+            constants,
+            literals,
+            witnesses,
+            opcodes,
+            debug_info,
+        })
+    }
+
+    /// Decode a binary from before the section table was introduced,
+    /// where sections are found by searching for their names.
+    fn decode_legacy(bytes: &[u8]) -> Result<Self> {
         // After the binary version, we're supposed to have the contract namespace
         let (namespace, _) = deserialize_partial(&bytes[5..])?;
 
@@ -100,9 +250,16 @@ impl ZkBinary {
         let witnesses = ZkBinary::parse_contract(contract_section)?;
         let opcodes = ZkBinary::parse_circuit(circuit_section)?;
 
-        // TODO: Debug info
-
-        Ok(Self { namespace, constants, literals, witnesses, opcodes })
+        Ok(Self {
+            namespace,
+            version: LEGACY_BINARY_VERSION,
+            k: None,
+            constants,
+            literals,
+            witnesses,
+            opcodes,
+            debug_info: None,
+        })
     }
 
     fn parse_constants(bytes: &[u8]) -> Result<Vec<(VarType, String)>> {
@@ -198,7 +355,10 @@ impl ZkBinary {
 
             let mut args = vec![];
             for _ in 0..arg_num.0 {
-                let stack_type = bytes[iter_offset];
+                let stack_type = match bytes.get(iter_offset) {
+                    Some(v) => *v,
+                    None => return Err(ZkasErr("Truncated opcode arguments".to_string())),
+                };
                 iter_offset += 1;
                 let (stack_index, offset) = deserialize_partial::<VarInt>(&bytes[iter_offset..])?;
                 iter_offset += offset;
@@ -219,4 +379,27 @@ impl ZkBinary {
 
         Ok(opcodes)
     }
+    fn parse_debug(bytes: &[u8]) -> Result<DebugInfo> {
+        let mut iter_offset = 0;
+
+        let num_witnesses: VarInt = read(bytes, &mut iter_offset)?;
+        let mut witnesses = vec![];
+        for _ in 0..num_witnesses.0 {
+            witnesses.push(read::<String>(bytes, &mut iter_offset)?);
+        }
+
+        let num_opcodes: VarInt = read(bytes, &mut iter_offset)?;
+        let mut opcodes = vec![];
+        for _ in 0..num_opcodes.0 {
+            let line: VarInt = read(bytes, &mut iter_offset)?;
+            let name: String = read(bytes, &mut iter_offset)?;
+            opcodes.push((line.0 as usize, name));
+        }
+
+        if iter_offset != bytes.len() {
+            return Err(ZkasErr("Trailing bytes in .debug section".to_string()))
+        }
+
+        Ok(DebugInfo { witnesses, opcodes })
+    }
 }
@@ -0,0 +1,95 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::fmt::Write;
+
+use super::{decoder::ZkBinary, types::StackType};
+
+impl ZkBinary {
+    /// Render the binary back into zkas source code. Names from the
+    /// `.debug` section are used when available, otherwise witnesses
+    /// and intermediate values get generic names.
+    pub fn disassemble(&self) -> String {
+        let mut out = String::new();
+        let debug = self.debug_info.as_ref();
+
+        let k = match self.k {
+            Some(k) => k.to_string(),
+            None => "unknown".to_string(),
+        };
+        writeln!(out, "# zkas binary version {}, k = {}", self.version, k).unwrap();
+
+        // The variable stack holds the constants, then the witnesses,
+        // then the return values of the opcodes, in that order.
+        let mut stack = vec![];
+
+        writeln!(out, "constant \"{}\" {{", self.namespace).unwrap();
+        for (typ, name) in &self.constants {
+            writeln!(out, "\t{:?} {},", typ, name).unwrap();
+            stack.push(name.clone());
+        }
+        writeln!(out, "}}\n").unwrap();
+
+        writeln!(out, "contract \"{}\" {{", self.namespace).unwrap();
+        for (i, typ) in self.witnesses.iter().enumerate() {
+            let name = match debug {
+                Some(d) => d.witnesses[i].clone(),
+                None => format!("witness_{}", i),
+            };
+            writeln!(out, "\t{:?} {},", typ, name).unwrap();
+            stack.push(name);
+        }
+        writeln!(out, "}}\n").unwrap();
+
+        writeln!(out, "circuit \"{}\" {{", self.namespace).unwrap();
+        for (i, (opcode, args)) in self.opcodes.iter().enumerate() {
+            let args: Vec<String> = args
+                .iter()
+                .map(|(typ, idx)| {
+                    let arg = match typ {
+                        StackType::Var => stack.get(*idx),
+                        StackType::Lit => self.literals.get(*idx).map(|(_, value)| value),
+                    };
+                    arg.cloned().unwrap_or_else(|| format!("<invalid {:?} {}>", typ, idx))
+                })
+                .collect();
+
+            let call = format!("{}({})", opcode.name(), args.join(", "));
+
+            let line = match debug {
+                Some(d) => format!(" # line {}", d.opcodes[i].0),
+                None => String::new(),
+            };
+
+            if opcode.arg_types().0.is_empty() {
+                writeln!(out, "\t{};{}", call, line).unwrap();
+                continue
+            }
+
+            let name = match debug {
+                Some(d) if !d.opcodes[i].1.is_empty() => d.opcodes[i].1.clone(),
+                _ => format!("var_{}", i),
+            };
+            writeln!(out, "\t{} = {};{}", name, call, line).unwrap();
+            stack.push(name);
+        }
+        writeln!(out, "}}").unwrap();
+
+        out
+    }
+}
@@ -16,60 +16,122 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::{io, io::Write, process};
+use std::{cell::RefCell, fmt};
 
+/// Result of a zkas compiler stage. On failure, all diagnostics gathered
+/// during the stage are returned, errors and warnings alike.
+pub type ZkasResult<T> = std::result::Result<T, Vec<Diagnostic>>;
+
+/// Severity of a diagnostic.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// Location in the source a diagnostic points at. Lines and columns start
+/// at 1, and a line of 0 means the diagnostic concerns the whole source.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+}
+
+/// A problem found in the source by one of the compiler stages.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagnostic {
+    /// Compiler stage that emitted the diagnostic
+    pub stage: &'static str,
+    pub severity: Severity,
+    pub span: Span,
+    pub message: String,
+    /// Additional hints about the problem
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.stage, self.severity, self.message)?;
+        if self.span.line != 0 {
+            write!(f, " (line {}, column {})", self.span.line, self.span.column)?;
+        }
+        for note in &self.notes {
+            write!(f, "\n  note: {}", note)?;
+        }
+        Ok(())
+    }
+}
+
+/// Collects the diagnostics of a compiler stage, so it can keep going after
+/// an error and report every problem it finds in a single run.
 pub(super) struct ErrorEmitter {
-    namespace: String,
-    file: String,
-    lines: Vec<String>,
+    stage: &'static str,
+    diagnostics: RefCell<Vec<Diagnostic>>,
 }
 
 impl ErrorEmitter {
-    pub fn new(namespace: &str, file: &str, lines: Vec<String>) -> Self {
-        Self { namespace: namespace.to_string(), file: file.to_string(), lines }
+    pub fn new(stage: &'static str) -> Self {
+        Self { stage, diagnostics: RefCell::new(vec![]) }
     }
 
-    fn fmt(&self, msg: String, ln: usize, col: usize) -> String {
-        let (err_msg, dbg_msg, caret) = match ln {
-            0 => (msg, "".to_string(), "".to_string()),
-            _ => {
-                let err_msg = format!("{} (line {}, column {})", msg, ln, col);
-                let dbg_msg = format!("{}:{}:{}: {}", self.file, ln, col, self.lines[ln - 1]);
-                let pad = dbg_msg.split(": ").next().unwrap().len() + col + 1;
-                let caret = format!("{:width$}^", "", width = pad);
-                (err_msg, dbg_msg, caret)
-            }
-        };
-        format!("{}\n{}\n{}\n", err_msg, dbg_msg, caret)
+    fn push(&self, severity: Severity, msg: &str, ln: usize, col: usize, notes: Vec<String>) {
+        self.diagnostics.borrow_mut().push(Diagnostic {
+            stage: self.stage,
+            severity,
+            span: Span::new(ln, col),
+            message: msg.to_string(),
+            notes,
+        });
     }
 
-    pub fn abort(&self, msg: &str, ln: usize, col: usize) {
-        let m = self.fmt(msg.to_string(), ln, col);
-        self.emit("error", &m);
-        process::exit(1);
+    pub fn error(&self, msg: &str, ln: usize, col: usize) {
+        self.push(Severity::Error, msg, ln, col, vec![]);
     }
 
-    pub fn warn(&self, msg: &str, ln: usize, col: usize) {
-        let m = self.fmt(msg.to_string(), ln, col);
-        self.emit("warning", &m);
+    pub fn error_with_notes(&self, msg: &str, ln: usize, col: usize, notes: Vec<String>) {
+        self.push(Severity::Error, msg, ln, col, notes);
     }
 
-    pub fn emit(&self, typ: &str, msg: &str) {
-        let stderr = io::stderr();
-        let mut handle = stderr.lock();
-
-        match typ {
-            "error" => {
-                write!(handle, "\x1b[31;1m{} error:\x1b[0m {}", self.namespace, msg).unwrap()
-            }
+    pub fn warn(&self, msg: &str, ln: usize, col: usize) {
+        self.push(Severity::Warning, msg, ln, col, vec![]);
+    }
 
-            "warning" => {
-                write!(handle, "\x1b[33;1m{} warning:\x1b[0m {}", self.namespace, msg).unwrap()
-            }
+    /// Number of errors emitted so far. Stages compare it before and after
+    /// processing an item to know whether to discard the item.
+    pub fn error_count(&self) -> usize {
+        self.diagnostics.borrow().iter().filter(|d| d.is_error()).count()
+    }
 
-            _ => unreachable!(),
-        };
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.borrow().clone()
+    }
 
-        handle.flush().unwrap();
+    /// Return `value` if no errors were emitted, otherwise all diagnostics.
+    pub fn finish<T>(&self, value: T) -> ZkasResult<T> {
+        match self.error_count() {
+            0 => Ok(value),
+            _ => Err(self.diagnostics()),
+        }
     }
 }
@@ -18,7 +18,7 @@
 
 use std::str::Chars;
 
-use super::error::ErrorEmitter;
+use super::error::{Diagnostic, ErrorEmitter, ZkasResult};
 
 const SPECIAL_CHARS: [char; 7] = ['{', '}', '(', ')', ',', ';', '='];
 
@@ -64,16 +64,18 @@ pub struct Lexer<'a> {
 }
 
 impl<'a> Lexer<'a> {
-    pub fn new(filename: &str, source: Chars<'a>) -> Self {
-        // For nice error reporting, we'll load everything into a string
-        // vector so we have references to lines.
-        let lines: Vec<String> = source.as_str().lines().map(|x| x.to_string()).collect();
-        let error = ErrorEmitter::new("Lexer", filename, lines);
+    pub fn new(source: Chars<'a>) -> Self {
+        Self { source, error: ErrorEmitter::new("Lexer") }
+    }
 
-        Self { source, error }
+    /// Diagnostics emitted so far, including warnings of a successful run.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.error.diagnostics()
     }
 
-    pub fn lex(&self) -> Vec<Token> {
+    /// Tokenize the source. Invalid input is reported and skipped, so all
+    /// lexing errors are returned together.
+    pub fn lex(&self) -> ZkasResult<Vec<Token>> {
         let mut tokens = vec![];
         let mut lineno = 1;
         let mut column = 0;
@@ -120,11 +122,15 @@ impl<'a> Lexer<'a> {
                 }
 
                 if in_string {
-                    self.error.abort("Strings can't contain newlines", lineno, column);
+                    self.error.error("Strings can't contain newlines", lineno, column);
+                    in_string = false;
+                    buf = String::new();
                 }
 
                 if in_number {
-                    self.error.abort("Numbers can't contain newlines", lineno, column);
+                    self.error.error("Numbers can't contain newlines", lineno, column);
+                    in_number = false;
+                    buf = String::new();
                 }
 
                 in_comment = false;
@@ -204,7 +210,9 @@ impl<'a> Lexer<'a> {
             if in_string && c == '"' {
                 // " I need to fix my vis lexer
                 if buf.is_empty() {
-                    self.error.abort("String cannot be empty", lineno, column);
+                    self.error.error("String cannot be empty", lineno, column);
+                    in_string = false;
+                    continue
                 }
                 new_string!();
                 continue
@@ -252,14 +260,14 @@ impl<'a> Lexer<'a> {
                         tokens.push(Token::new("=", TokenType::Assign, lineno, column));
                         continue
                     }
-                    _ => self.error.abort(&format!("Invalid token `{}`", c), lineno, column - 1),
+                    _ => self.error.error(&format!("Invalid token `{}`", c), lineno, column - 1),
                 }
                 continue
             }
 
-            self.error.abort(&format!("Invalid token `{}`", c), lineno, column - 1);
+            self.error.error(&format!("Invalid token `{}`", c), lineno, column - 1);
         }
 
-        tokens
+        self.error.finish(tokens)
     }
 }
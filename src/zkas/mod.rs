@@ -17,11 +17,12 @@
  */
 
 //! `src/zkas` is the library holding the zkas toolchain, consisting of a
-//! lexer, parser, static/semantic analyzers, a binary compiler, and a
-//! binary decoder.
+//! lexer, parser, static/semantic analyzers, a binary compiler, a
+//! binary decoder, and a disassembler.
 
-/// Error emitter
-mod error;
+/// Compiler diagnostics
+pub mod error;
+pub use error::{Diagnostic, Severity, Span, ZkasResult};
 
 /// Language opcodes
 pub mod opcode;
@@ -52,4 +53,78 @@ pub use compiler::Compiler;
 
 /// Decoder module
 pub mod decoder;
-pub use decoder::ZkBinary;
+pub use decoder::{DebugInfo, ZkBinary, MAX_K};
+
+/// Disassembler module
+pub mod disassembler;
+
+/// Lex, parse and type-check zkas `source`. Returns the analyzer holding the
+/// typed program, along with the warnings of all stages. On failure, the
+/// diagnostics of the failing stage are returned.
+pub fn analyze(source: &str) -> ZkasResult<(Analyzer, Vec<Diagnostic>)> {
+    let mut warnings = vec![];
+    let (_, analyzer) = analyze_source(source, &mut warnings)?;
+    Ok((analyzer, warnings))
+}
+
+/// Run the whole zkas pipeline on `source`, producing the binary of a circuit
+/// of `2^k` rows, with debug info if `debug_info` is set. Returns the binary
+/// along with the warnings of all stages. On failure, the diagnostics of the
+/// failing stage are returned.
+pub fn compile(source: &str, k: u32, debug_info: bool) -> ZkasResult<(Vec<u8>, Vec<Diagnostic>)> {
+    let mut warnings = vec![];
+    let (namespace, analyzer) = analyze_source(source, &mut warnings)?;
+
+    // ANCHOR: compile
+    // The compiler takes the analyzed program and encodes it into the
+    // binary format the zkvm decodes.
+    let compiler = Compiler::new(
+        namespace,
+        k,
+        analyzer.constants,
+        analyzer.witnesses,
+        analyzer.statements,
+        analyzer.literals,
+        debug_info,
+    );
+    let bincode = stage(compiler.compile(), compiler.diagnostics(), &mut warnings)?;
+    // ANCHOR_END: compile
+
+    Ok((bincode, warnings))
+}
+
+fn analyze_source(source: &str, warnings: &mut Vec<Diagnostic>) -> ZkasResult<(String, Analyzer)> {
+    // ANCHOR: analyze
+    // The lexer goes over the input file and separates its content into
+    // tokens that get fed into a parser.
+    let lexer = Lexer::new(source.chars());
+    let tokens = stage(lexer.lex(), lexer.diagnostics(), warnings)?;
+
+    // The parser goes over the tokens provided by the lexer and builds
+    // the initial AST, not caring much about the semantics, just enforcing
+    // syntax and general structure.
+    let parser = Parser::new(tokens);
+    let (namespace, constants, witnesses, statements) =
+        stage(parser.parse(), parser.diagnostics(), warnings)?;
+
+    // The analyzer goes through the initial AST provided by the parser and
+    // converts return and variable types to their correct forms, and also
+    // checks that the semantics of the ZK script are correct.
+    let mut analyzer = Analyzer::new(constants, witnesses, statements);
+    let result = analyzer.analyze_types();
+    stage(result, analyzer.diagnostics(), warnings)?;
+    // ANCHOR_END: analyze
+
+    Ok((namespace, analyzer))
+}
+
+/// Keep the warnings of a stage that succeeded
+fn stage<T>(
+    result: ZkasResult<T>,
+    diagnostics: Vec<Diagnostic>,
+    warnings: &mut Vec<Diagnostic>,
+) -> ZkasResult<T> {
+    let value = result?;
+    warnings.extend(diagnostics);
+    Ok(value)
+}
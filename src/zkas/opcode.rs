@@ -78,6 +78,23 @@ pub enum Opcode {
     /// Check if a field element fits in a boolean (Either 0 or 1)
     BoolCheck = 0x53,
 
+    /// Get a bit of a Base field element, given the bit index (up to 254).
+    /// The bits are those of the canonical little-endian encoding.
+    GetBit = 0x54,
+
+    /// Conditionally select between two Base field elements given a boolean
+    CondSelect = 0x60,
+
+    /// Conditionally select between zero and a Base field element,
+    /// returning zero if the condition is zero
+    ZeroCondSelect = 0x61,
+
+    /// Check if a Base field element is zero, returning a boolean
+    IsZero = 0x62,
+
+    /// Check if two Base field elements are equal, returning a boolean
+    IsEqual = 0x63,
+
     /// Constrain equality of two Base field elements inside the circuit
     ConstrainEqualBase = 0xe0,
 
@@ -111,6 +128,11 @@ impl Opcode {
             "less_than_strict" => Some(Self::LessThanStrict),
             "less_than_loose" => Some(Self::LessThanLoose),
             "bool_check" => Some(Self::BoolCheck),
+            "get_bit" => Some(Self::GetBit),
+            "cond_select" => Some(Self::CondSelect),
+            "zero_cond" => Some(Self::ZeroCondSelect),
+            "is_zero" => Some(Self::IsZero),
+            "is_equal" => Some(Self::IsEqual),
             "constrain_equal_base" => Some(Self::ConstrainEqualBase),
             "constrain_equal_point" => Some(Self::ConstrainEqualPoint),
             "constrain_instance" => Some(Self::ConstrainInstance),
@@ -119,6 +141,39 @@ impl Opcode {
         }
     }
 
+    /// Return the name of the opcode as written in zkas source code.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Noop => "noop",
+            Self::EcAdd => "ec_add",
+            Self::EcMul => "ec_mul",
+            Self::EcMulBase => "ec_mul_base",
+            Self::EcMulShort => "ec_mul_short",
+            Self::EcMulVarBase => "ec_mul_var_base",
+            Self::EcGetX => "ec_get_x",
+            Self::EcGetY => "ec_get_y",
+            Self::PoseidonHash => "poseidon_hash",
+            Self::MerkleRoot => "merkle_root",
+            Self::BaseAdd => "base_add",
+            Self::BaseMul => "base_mul",
+            Self::BaseSub => "base_sub",
+            Self::WitnessBase => "witness_base",
+            Self::RangeCheck => "range_check",
+            Self::LessThanStrict => "less_than_strict",
+            Self::LessThanLoose => "less_than_loose",
+            Self::BoolCheck => "bool_check",
+            Self::GetBit => "get_bit",
+            Self::CondSelect => "cond_select",
+            Self::ZeroCondSelect => "zero_cond",
+            Self::IsZero => "is_zero",
+            Self::IsEqual => "is_equal",
+            Self::ConstrainEqualBase => "constrain_equal_base",
+            Self::ConstrainEqualPoint => "constrain_equal_point",
+            Self::ConstrainInstance => "constrain_instance",
+            Self::DebugPrint => "debug",
+        }
+    }
+
     pub fn from_repr(b: u8) -> Option<Self> {
         match b {
             0x01 => Some(Self::EcAdd),
@@ -138,6 +193,11 @@ impl Opcode {
             0x51 => Some(Self::LessThanStrict),
             0x52 => Some(Self::LessThanLoose),
             0x53 => Some(Self::BoolCheck),
+            0x54 => Some(Self::GetBit),
+            0x60 => Some(Self::CondSelect),
+            0x61 => Some(Self::ZeroCondSelect),
+            0x62 => Some(Self::IsZero),
+            0x63 => Some(Self::IsEqual),
             0xe0 => Some(Self::ConstrainEqualBase),
             0xe1 => Some(Self::ConstrainEqualPoint),
             0xf0 => Some(Self::ConstrainInstance),
@@ -194,6 +254,18 @@ impl Opcode {
 
             Opcode::BoolCheck => (vec![], vec![VarType::Base]),
 
+            Opcode::GetBit => (vec![VarType::Base], vec![VarType::Base, VarType::Uint64]),
+
+            Opcode::CondSelect => {
+                (vec![VarType::Base], vec![VarType::Base, VarType::Base, VarType::Base])
+            }
+
+            Opcode::ZeroCondSelect => (vec![VarType::Base], vec![VarType::Base, VarType::Base]),
+
+            Opcode::IsZero => (vec![VarType::Base], vec![VarType::Base]),
+
+            Opcode::IsEqual => (vec![VarType::Base], vec![VarType::Base, VarType::Base]),
+
             Opcode::ConstrainEqualBase => (vec![], vec![VarType::Base, VarType::Base]),
 
             Opcode::ConstrainEqualPoint => (vec![], vec![VarType::EcPoint, VarType::EcPoint]),
@@ -16,14 +16,14 @@
  * along with this program.  If not, see <https://www.gnu.org/licenses/>.
  */
 
-use std::{iter::Peekable, str::Chars};
+use std::iter::Peekable;
 
 use indexmap::IndexMap;
 use itertools::Itertools;
 
 use super::{
     ast::{Arg, Constant, Literal, Statement, StatementType, Variable, Witness},
-    error::ErrorEmitter,
+    error::{Diagnostic, ErrorEmitter, ZkasResult},
     lexer::{Token, TokenType},
     LitType, Opcode, VarType,
 };
@@ -50,16 +50,19 @@ pub struct Parser {
 }
 
 impl Parser {
-    pub fn new(filename: &str, source: Chars, tokens: Vec<Token>) -> Self {
-        // For nice error reporting, we'll load everything into a string
-        // vector so we have references to lines.
-        let lines: Vec<String> = source.as_str().lines().map(|x| x.to_string()).collect();
-        let error = ErrorEmitter::new("Parser", filename, lines);
+    pub fn new(tokens: Vec<Token>) -> Self {
+        Self { tokens, error: ErrorEmitter::new("Parser") }
+    }
 
-        Self { tokens, error }
+    /// Diagnostics emitted so far, including warnings of a successful run.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.error.diagnostics()
     }
 
-    pub fn parse(&self) -> (String, Vec<Constant>, Vec<Witness>, Vec<Statement>) {
+    /// Parse the tokens into the namespace, constants, witnesses and
+    /// statements of the circuit. Invalid sections, entries and statements
+    /// are reported and skipped, so all parsing errors are returned together.
+    pub fn parse(&self) -> ZkasResult<(String, Vec<Constant>, Vec<Witness>, Vec<Statement>)> {
         // We use these to keep state while parsing.
         let mut namespace = None;
         let (mut declaring_constant, mut declared_constant) = (false, false);
@@ -77,14 +80,16 @@ impl Parser {
         let mut circuit_stmts = vec![];
         // Contains constant and contract sections
         let mut ast_inner = IndexMap::new();
-        let mut ast = IndexMap::new();
+        // Set when the circuit section is well-formed
+        let mut circuit_ok = false;
 
-        if self.tokens[0].token_type != TokenType::Symbol {
-            self.error.abort(
+        if self.tokens.is_empty() || self.tokens[0].token_type != TokenType::Symbol {
+            self.error.error(
                 "Source file does not start with a section. Expected `constant/contract/circuit`.",
                 0,
                 0,
             );
+            return Err(self.diagnostics())
         }
 
         let mut iter = self.tokens.iter();
@@ -107,7 +112,7 @@ impl Parser {
                             if KEYWORDS.contains(&inner.token.as_str()) &&
                                 inner.token_type == TokenType::Symbol
                             {
-                                self.error.abort(
+                                self.error.error(
                                     &format!("Keyword '{}' used in improper place.", inner.token),
                                     inner.line,
                                     inner.column,
@@ -136,11 +141,17 @@ impl Parser {
                         absorb_inner_tokens!(circuit_tokens);
                     }
 
-                    x => self.error.abort(
-                        &format!("Section `{}` is not a valid section", x),
-                        t.line,
-                        t.column,
-                    ),
+                    x => {
+                        self.error.error(
+                            &format!("Section `{}` is not a valid section", x),
+                            t.line,
+                            t.column,
+                        );
+                        // Skip over the invalid section
+                        let mut skipped = vec![];
+                        absorb_inner_tokens!(skipped);
+                        continue
+                    }
                 }
             }
 
@@ -150,7 +161,7 @@ impl Parser {
                 ($t:ident) => {
                     if let Some(ns) = namespace.clone() {
                         if ns != $t[0].token {
-                            self.error.abort(
+                            self.error.error(
                                 &format!("Found '{}' namespace, expected '{}'.", $t[0].token, ns),
                                 $t[0].line,
                                 $t[0].column,
@@ -158,7 +169,7 @@ impl Parser {
                         }
                     } else {
                         if NOPE_NS.contains(&$t[0].token.as_str()) {
-                            self.error.abort(
+                            self.error.error(
                                 &format!("'{}' cannot be a namespace.", $t[0].token),
                                 $t[0].line,
                                 $t[0].column,
@@ -172,10 +183,17 @@ impl Parser {
             // Parse the constant section into the AST.
             if declaring_constant {
                 if declared_constant {
-                    self.error.abort("Duplicate `constant` section found.", t.line, t.column);
+                    self.error.error("Duplicate `constant` section found.", t.line, t.column);
+                    declaring_constant = false;
+                    continue
                 }
 
-                self.check_section_structure("constant", constant_tokens.clone());
+                declaring_constant = false;
+                declared_constant = true;
+
+                if !self.check_section_structure("constant", &constant_tokens) {
+                    continue
+                }
                 check_namespace!(constant_tokens);
 
                 let mut constants_map = IndexMap::new();
@@ -183,12 +201,13 @@ impl Parser {
                 let mut constant_inner = constant_tokens[2..constant_tokens.len() - 1].iter();
                 while let Some((typ, name, comma)) = constant_inner.next_tuple() {
                     if comma.token_type != TokenType::Comma {
-                        self.error.abort("Separator is not a comma.", comma.line, comma.column);
+                        self.error.error("Separator is not a comma.", comma.line, comma.column);
+                        continue
                     }
 
                     // No variable shadowing
                     if constants_map.contains_key(name.token.as_str()) {
-                        self.error.abort(
+                        self.error.error(
                             &format!(
                                 "Section `constant` already contains the token `{}`.",
                                 &name.token
@@ -196,27 +215,33 @@ impl Parser {
                             name.line,
                             name.column,
                         );
+                        continue
                     }
 
                     constants_map.insert(name.token.clone(), (name.clone(), typ.clone()));
                 }
 
                 if constant_inner.next().is_some() {
-                    self.error.abort("Internal error, leftovers in 'constant' iterator", 0, 0);
+                    self.error.error("Internal error, leftovers in 'constant' iterator", 0, 0);
                 }
 
                 ast_inner.insert("constant".to_string(), constants_map);
-                declaring_constant = false;
-                declared_constant = true;
             }
 
             // Parse the contract section into the AST.
             if declaring_contract {
                 if declared_contract {
-                    self.error.abort("Duplicate `contract` section found.", t.line, t.column);
+                    self.error.error("Duplicate `contract` section found.", t.line, t.column);
+                    declaring_contract = false;
+                    continue
                 }
 
-                self.check_section_structure("contract", contract_tokens.clone());
+                declaring_contract = false;
+                declared_contract = true;
+
+                if !self.check_section_structure("contract", &contract_tokens) {
+                    continue
+                }
                 check_namespace!(contract_tokens);
 
                 let mut witnesses_map = IndexMap::new();
@@ -224,12 +249,13 @@ impl Parser {
                 let mut contract_inner = contract_tokens[2..contract_tokens.len() - 1].iter();
                 while let Some((typ, name, comma)) = contract_inner.next_tuple() {
                     if comma.token_type != TokenType::Comma {
-                        self.error.abort("Separator is not a comma.", comma.line, comma.column);
+                        self.error.error("Separator is not a comma.", comma.line, comma.column);
+                        continue
                     }
 
                     // No variable shadowing
                     if witnesses_map.contains_key(name.token.as_str()) {
-                        self.error.abort(
+                        self.error.error(
                             &format!(
                                 "Section `contract` already contains the token `{}`.",
                                 &name.token
@@ -237,28 +263,35 @@ impl Parser {
                             name.line,
                             name.column,
                         );
+                        continue
                     }
 
                     witnesses_map.insert(name.token.clone(), (name.clone(), typ.clone()));
                 }
 
                 if contract_inner.next().is_some() {
-                    self.error.abort("Internal error, leftovers in 'contract' iterator", 0, 0);
+                    self.error.error("Internal error, leftovers in 'contract' iterator", 0, 0);
                 }
 
                 ast_inner.insert("contract".to_string(), witnesses_map);
-                declaring_contract = false;
-                declared_contract = true;
             }
 
             // Parse the circuit section into the AST.
             if declaring_circuit {
                 if declared_circuit {
-                    self.error.abort("Duplicate `circuit` section found.", t.line, t.column);
+                    self.error.error("Duplicate `circuit` section found.", t.line, t.column);
+                    declaring_circuit = false;
+                    continue
                 }
 
-                self.check_section_structure("circuit", circuit_tokens.clone());
+                declaring_circuit = false;
+                declared_circuit = true;
+
+                if !self.check_section_structure("circuit", &circuit_tokens) {
+                    continue
+                }
                 check_namespace!(circuit_tokens);
+                circuit_ok = true;
 
                 // Grab tokens for each statement
                 for i in circuit_tokens[2..circuit_tokens.len() - 1].iter() {
@@ -270,51 +303,68 @@ impl Parser {
                     }
                     circuit_stmt.push(i.clone());
                 }
-
-                declaring_circuit = false;
-                declared_circuit = true;
             }
         }
 
         // Tokens have been processed and ast is complete
 
-        let ns = namespace.unwrap();
-        ast.insert(ns.clone(), ast_inner);
-
-        let constants = {
-            let c = match ast.get(&ns).unwrap().get("constant") {
-                Some(c) => c,
-                None => {
-                    self.error.abort("Missing `constant` section in .zk source.", 0, 0);
-                    unreachable!();
+        let constants = match ast_inner.get("constant") {
+            Some(c) => self.parse_ast_constants(c),
+            None => {
+                // A malformed section was already reported.
+                if !declared_constant {
+                    self.error.error("Missing `constant` section in .zk source.", 0, 0);
                 }
-            };
-            self.parse_ast_constants(c)
+                vec![]
+            }
         };
 
-        let witnesses = {
-            let c = match ast.get(&ns).unwrap().get("contract") {
-                Some(c) => c,
-                None => {
-                    self.error.abort("Missing `contract` section in .zk source.", 0, 0);
-                    unreachable!();
+        let witnesses = match ast_inner.get("contract") {
+            Some(c) => self.parse_ast_contract(c),
+            None => {
+                if !declared_contract {
+                    self.error.error("Missing `contract` section in .zk source.", 0, 0);
                 }
-            };
-            self.parse_ast_contract(c)
+                vec![]
+            }
         };
 
+        let errors = self.error.error_count();
         let statements = self.parse_ast_circuit(circuit_stmts);
-        if statements.is_empty() {
-            self.error.abort("Circuit section is empty.", 0, 0);
+        if !declared_circuit {
+            self.error.error("Missing `circuit` section in .zk source.", 0, 0);
+        } else if circuit_ok && statements.is_empty() && self.error.error_count() == errors {
+            self.error.error("Circuit section is empty.", 0, 0);
         }
 
-        (ns, constants, witnesses, statements)
+        // The namespace is unset only if every section was malformed.
+        let Some(ns) = namespace else { return Err(self.diagnostics()) };
+
+        self.error.finish((ns, constants, witnesses, statements))
     }
 
-    /// Routine checks on section structure
-    fn check_section_structure(&self, section: &str, tokens: Vec<Token>) {
+    /// Routine checks on section structure. Returns `false` if the section
+    /// is malformed and should not be parsed any further.
+    fn check_section_structure(&self, section: &str, tokens: &[Token]) -> bool {
+        let Some(last) = tokens.last() else {
+            self.error.error(&format!("Missing body of '{}' section.", section), 0, 0);
+            return false
+        };
+
+        if tokens.len() < 3 {
+            self.error.error(
+                &format!("Section '{}' is incomplete.", section),
+                tokens[0].line,
+                tokens[0].column,
+            );
+            return false
+        }
+
+        let mut ok = true;
+
         if tokens[0].token_type != TokenType::String {
-            self.error.abort(
+            ok = false;
+            self.error.error(
                 "Section declaration must start with a naming string.",
                 tokens[0].line,
                 tokens[0].column,
@@ -322,15 +372,17 @@ impl Parser {
         }
 
         if tokens[1].token_type != TokenType::LeftBrace {
-            self.error.abort(
+            ok = false;
+            self.error.error(
                 "Section must be opened with a left brace '{'",
                 tokens[0].line,
                 tokens[0].column,
             );
         }
 
-        if tokens.last().unwrap().token_type != TokenType::RightBrace {
-            self.error.abort(
+        if last.token_type != TokenType::RightBrace {
+            ok = false;
+            self.error.error(
                 "Section must be closed with a right brace '}'",
                 tokens[0].line,
                 tokens[0].column,
@@ -344,7 +396,8 @@ impl Parser {
                 }
 
                 if tokens[2..tokens.len() - 1].len() % 3 != 0 {
-                    self.error.abort(
+                    ok = false;
+                    self.error.error(
                         &format!("Invalid number of elements in '{}' section. Must be pairs of '<Type> <name>' separated with a comma ','.", section),
                         tokens[0].line,
                         tokens[0].column
@@ -353,11 +406,13 @@ impl Parser {
             }
             "circuit" => {
                 if tokens.len() == 3 {
-                    self.error.abort("circuit section is empty.", 0, 0);
+                    self.error.error("circuit section is empty.", 0, 0);
+                    return false
                 }
 
                 if tokens[tokens.len() - 2].token_type != TokenType::Semicolon {
-                    self.error.abort(
+                    ok = false;
+                    self.error.error(
                         "Circuit section does not end with a semicolon. Would never finish parsing.",
                         tokens[tokens.len()-2].line,
                         tokens[tokens.len()-2].column,
@@ -366,6 +421,8 @@ impl Parser {
             }
             _ => unreachable!(),
         };
+
+        ok
     }
 
     fn parse_ast_constants(&self, ast: &IndexMap<String, (Token, Token)>) -> Vec<Constant> {
@@ -375,27 +432,30 @@ impl Parser {
         // v = (name, type)
         for (k, v) in ast {
             if &v.0.token != k {
-                self.error.abort(
+                self.error.error(
                     &format!("Constant name `{}` doesn't match token `{}`.", v.0.token, k),
                     v.0.line,
                     v.0.column,
                 );
+                continue
             }
 
             if v.0.token_type != TokenType::Symbol {
-                self.error.abort(
+                self.error.error(
                     &format!("Constant name `{}` is not a symbol.", v.0.token),
                     v.0.line,
                     v.0.column,
                 );
+                continue
             }
 
             if v.1.token_type != TokenType::Symbol {
-                self.error.abort(
+                self.error.error(
                     &format!("Constant type `{}` is not a symbol.", v.1.token),
                     v.1.line,
                     v.1.column,
                 );
+                continue
             }
 
             // Valid constant types, these are the constants/generators supported
@@ -403,15 +463,16 @@ impl Parser {
             match v.1.token.as_str() {
                 "EcFixedPoint" => {
                     if !VALID_ECFIXEDPOINT.contains(&v.0.token.as_str()) {
-                        self.error.abort(
+                        self.error.error_with_notes(
                             &format!(
-                                "`{}` is not a valid EcFixedPoint constant. Supported: {:?}",
-                                v.0.token.as_str(),
-                                VALID_ECFIXEDPOINT
+                                "`{}` is not a valid EcFixedPoint constant.",
+                                v.0.token.as_str()
                             ),
                             v.0.line,
                             v.0.column,
+                            vec![format!("Supported: {:?}", VALID_ECFIXEDPOINT)],
                         );
+                        continue
                     }
 
                     ret.push(Constant {
@@ -424,15 +485,16 @@ impl Parser {
 
                 "EcFixedPointShort" => {
                     if !VALID_ECFIXEDPOINTSHORT.contains(&v.0.token.as_str()) {
-                        self.error.abort(
+                        self.error.error_with_notes(
                             &format!(
-                                "`{}` is not a valid EcFixedPointShort constant. Supported: {:?}",
-                                v.0.token.as_str(),
-                                VALID_ECFIXEDPOINTSHORT
+                                "`{}` is not a valid EcFixedPointShort constant.",
+                                v.0.token.as_str()
                             ),
                             v.0.line,
                             v.0.column,
+                            vec![format!("Supported: {:?}", VALID_ECFIXEDPOINTSHORT)],
                         );
+                        continue
                     }
 
                     ret.push(Constant {
@@ -445,15 +507,16 @@ impl Parser {
 
                 "EcFixedPointBase" => {
                     if !VALID_ECFIXEDPOINTBASE.contains(&v.0.token.as_str()) {
-                        self.error.abort(
+                        self.error.error_with_notes(
                             &format!(
-                                "`{}` is not a valid EcFixedPointBase constant. Supported: {:?}",
-                                v.0.token.as_str(),
-                                VALID_ECFIXEDPOINTBASE
+                                "`{}` is not a valid EcFixedPointBase constant.",
+                                v.0.token.as_str()
                             ),
                             v.0.line,
                             v.0.column,
+                            vec![format!("Supported: {:?}", VALID_ECFIXEDPOINTBASE)],
                         );
+                        continue
                     }
 
                     ret.push(Constant {
@@ -465,7 +528,7 @@ impl Parser {
                 }
 
                 x => {
-                    self.error.abort(
+                    self.error.error(
                         &format!("`{}` is an unsupported constant type.", x),
                         v.1.line,
                         v.1.column,
@@ -484,27 +547,30 @@ impl Parser {
         // v = (name, type)
         for (k, v) in ast {
             if &v.0.token != k {
-                self.error.abort(
+                self.error.error(
                     &format!("Witness name `{}` doesn't match token `{}`.", v.0.token, k),
                     v.0.line,
                     v.0.column,
                 );
+                continue
             }
 
             if v.0.token_type != TokenType::Symbol {
-                self.error.abort(
+                self.error.error(
                     &format!("Witness name `{}` is not a symbol.", v.0.token),
                     v.0.line,
                     v.0.column,
                 );
+                continue
             }
 
             if v.1.token_type != TokenType::Symbol {
-                self.error.abort(
+                self.error.error(
                     &format!("Witness type `{}` is not a symbol.", v.1.token),
                     v.1.line,
                     v.1.column,
                 );
+                continue
             }
 
             // Valid witness types
@@ -573,7 +639,7 @@ impl Parser {
                 }
 
                 x => {
-                    self.error.abort(
+                    self.error.error(
                         &format!("`{}` is an unsupported witness type.", x),
                         v.1.line,
                         v.1.column,
@@ -639,7 +705,7 @@ impl Parser {
         // semicolons (;) in the source file. This iterator contains each
         // of those statements as an array of tokens we then consume and
         // build the AST further.
-        for statement in statements {
+        'statements: for statement in statements {
             if statement.is_empty() {
                 continue
             }
@@ -654,13 +720,17 @@ impl Parser {
             }
 
             if left_paren != right_paren || (left_paren == 0 || right_paren == 0) {
-                self.error.abort(
+                self.error.error(
                     "Incorrect number of left and right parenthesis for statement.",
                     statement[0].line,
                     statement[0].column,
                 );
+                continue
             }
 
+            // Statements with errors are discarded
+            let errors = self.error.error_count();
+
             // Peekable iterator so we can see tokens in advance
             // without consuming the iterator.
             let mut iter = statement.iter().peekable();
@@ -702,11 +772,12 @@ impl Parser {
                         }
 
                         if !parsing {
-                            self.error.abort(
+                            self.error.error(
                                 &format!("Illegal token `{}`.", next_token.token),
                                 next_token.line,
                                 next_token.column,
                             );
+                            continue 'statements
                         }
                     }
                 }
@@ -731,11 +802,16 @@ impl Parser {
                     stmt.opcode = op;
                     stmt.rhs = rhs;
                 } else {
-                    self.error.abort(
+                    self.error.error(
                         &format!("Unimplemented opcode `{}`.", func_name),
                         token.line,
                         token.column,
                     );
+                    continue 'statements
+                }
+
+                if self.error.error_count() != errors {
+                    continue 'statements
                 }
 
                 ret.push(stmt);
@@ -753,16 +829,18 @@ impl Parser {
     ) -> Vec<Arg> {
         if let Some(next_token) = iter.peek() {
             if next_token.token_type != TokenType::LeftParen {
-                self.error.abort(
+                self.error.error(
                     "Invalid function call opening. Must start with a '('.",
                     next_token.line,
                     next_token.column,
                 );
+                return vec![]
             }
             // Skip the opening parenthesis
             iter.next();
         } else {
-            self.error.abort("Premature ending of statement.", token.line, token.column);
+            self.error.error("Premature ending of statement.", token.line, token.column);
+            return vec![]
         }
 
         let mut ret = vec![];
@@ -778,11 +856,12 @@ impl Parser {
             if let Some(op_inner) = Opcode::from_name(&arg.token) {
                 if let Some(paren) = iter.peek() {
                     if paren.token_type != TokenType::LeftParen {
-                        self.error.abort(
+                        self.error.error(
                             "Invalid function call opening. Must start with a '('.",
                             paren.line,
                             paren.column,
                         );
+                        return ret
                     }
 
                     // Recurse this function to get the params of the nested one.
@@ -809,11 +888,12 @@ impl Parser {
                     continue
                 }
 
-                self.error.abort(
+                self.error.error(
                     "Missing tokens in statement, there's a syntax error here.",
                     arg.line,
                     arg.column,
                 );
+                return ret
             }
 
             // ==========================================
@@ -832,22 +912,20 @@ impl Parser {
                     TokenType::Number => {
                         // Check if we can actually convert this into a number.
                         match arg.token.parse::<u64>() {
-                            Ok(_) => {}
+                            Ok(_) => ret.push(Arg::Lit(Literal {
+                                name: arg.token.clone(),
+                                typ: LitType::Uint64,
+                                line: arg.line,
+                                column: arg.column,
+                            })),
                             Err(e) => {
-                                self.error.abort(
+                                self.error.error(
                                     &format!("Failed to convert literal into u64: {}", e),
                                     arg.line,
                                     arg.column,
                                 );
                             }
                         };
-
-                        ret.push(Arg::Lit(Literal {
-                            name: arg.token.clone(),
-                            typ: LitType::Uint64,
-                            line: arg.line,
-                            column: arg.column,
-                        }))
                     }
 
                     TokenType::RightParen => {
@@ -859,7 +937,14 @@ impl Parser {
                         break
                     }
 
-                    x => unimplemented!("{:#?}", x),
+                    _ => {
+                        self.error.error(
+                            &format!("Unexpected token `{}` in function arguments.", arg.token),
+                            arg.line,
+                            arg.column,
+                        );
+                        return ret
+                    }
                 };
 
                 if sep.token_type == TokenType::RightParen {
@@ -873,11 +958,12 @@ impl Parser {
                 }
 
                 if sep.token_type != TokenType::Comma {
-                    self.error.abort(
+                    self.error.error(
                         "Argument separator is not a comma (`,`)",
                         sep.line,
                         sep.column,
                     );
+                    return ret
                 }
             }
         }
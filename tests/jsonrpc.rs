@@ -0,0 +1,248 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::time::Instant;
+
+use async_std::{net::TcpStream, sync::Arc};
+use async_trait::async_trait;
+use futures::{AsyncReadExt, AsyncWriteExt};
+use serde_json::json;
+use url::Url;
+
+use darkfi::{
+    rpc::{
+        auth::{AuthSettings, RpcCredentials},
+        client::RpcClient,
+        jsonrpc::{
+            ErrorCode::MethodNotFound, JsonError, JsonNotification, JsonRequest, JsonResponse,
+            JsonResult, JsonSubscriber,
+        },
+        server::{listen_and_serve_with_settings, RequestHandler, ServerSettings},
+    },
+    system::{Subscriber, SubscriberPtr},
+    util::async_util::{msleep, sleep},
+    Result,
+};
+
+struct Handler {
+    subscriber: SubscriberPtr<JsonNotification>,
+}
+
+#[async_trait]
+impl RequestHandler for Handler {
+    async fn handle_request(&self, req: JsonRequest) -> JsonResult {
+        match req.method.as_str() {
+            Some("echo") => JsonResponse::new(req.params, req.id).into(),
+            Some("sleep") => {
+                sleep(1).await;
+                JsonResponse::new(json!("awake"), req.id).into()
+            }
+            Some("subscribe") => JsonSubscriber::new(self.subscriber.clone()).into(),
+            _ => JsonError::new(MethodNotFound, None, req.id).into(),
+        }
+    }
+}
+
+#[async_std::test]
+async fn jsonrpc_server() -> Result<()> {
+    let ex = Arc::new(smol::Executor::new());
+    let (signal, shutdown) = smol::channel::unbounded::<()>();
+    let _ex = ex.clone();
+    let executor = std::thread::spawn(move || smol::block_on(_ex.run(shutdown.recv())));
+
+    let url = Url::parse("tcp://127.0.0.1:28340").unwrap();
+    let handler = Arc::new(Handler { subscriber: Subscriber::new() });
+    let settings = ServerSettings { max_request_size: 2 * 1024 * 1024, ..Default::default() };
+    ex.spawn(listen_and_serve_with_settings(url.clone(), handler.clone(), settings, ex.clone()))
+        .detach();
+    msleep(500).await;
+
+    let client = RpcClient::new(url.clone()).await?;
+
+    // Requests larger than a single read make it through whole
+    let big = "a".repeat(1024 * 1024);
+    let rep = client.request(JsonRequest::new("echo", json!([big]))).await?;
+    assert_eq!(rep, json!([big]));
+
+    // Requests on the same connection are handled concurrently
+    let start = Instant::now();
+    let (a, b) = futures::join!(
+        client.request(JsonRequest::new("sleep", json!([]))),
+        client.request(JsonRequest::new("sleep", json!([]))),
+    );
+    assert_eq!(a?, json!("awake"));
+    assert_eq!(b?, json!("awake"));
+    assert!(start.elapsed().as_millis() < 1900);
+
+    // Batches get a reply for each request
+    let reps = client
+        .request_batch(vec![
+            JsonRequest::new("echo", json!([1])),
+            JsonRequest::new("nonexistent", json!([])),
+        ])
+        .await?;
+    assert_eq!(reps.len(), 2);
+    assert_eq!(reps[0].as_ref().unwrap(), &json!([1]));
+    assert!(reps[1].is_err());
+
+    // Notifications are multiplexed with normal replies
+    let subscription_id = client.request(JsonRequest::new("subscribe", json!([]))).await?;
+    handler.subscriber.notify(JsonNotification::new("subscribe", json!([42]))).await;
+    let rep = client.request(JsonRequest::new("echo", json!([2]))).await?;
+    assert_eq!(rep, json!([2]));
+
+    let unsubscribe = JsonRequest::new("unsubscribe", json!([subscription_id]));
+    assert_eq!(client.request(unsubscribe.clone()).await?, json!(true));
+    assert_eq!(client.request(unsubscribe).await?, json!(false));
+
+    // Requests over the size limit are rejected
+    let client = RpcClient::new(url).await?;
+    let huge = "a".repeat(3 * 1024 * 1024);
+    assert!(client.request(JsonRequest::new("echo", json!([huge]))).await.is_err());
+
+    signal.send(()).await.unwrap();
+    executor.join().unwrap();
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn jsonrpc_http_and_websockets() -> Result<()> {
+    let ex = Arc::new(smol::Executor::new());
+    let (signal, shutdown) = smol::channel::unbounded::<()>();
+    let _ex = ex.clone();
+    let executor = std::thread::spawn(move || smol::block_on(_ex.run(shutdown.recv())));
+
+    let http_url = Url::parse("http://127.0.0.1:28341").unwrap();
+    let ws_url = Url::parse("ws://127.0.0.1:28342").unwrap();
+    let handler = Arc::new(Handler { subscriber: Subscriber::new() });
+    for url in [&http_url, &ws_url] {
+        let settings = ServerSettings::default();
+        ex.spawn(listen_and_serve_with_settings(
+            url.clone(),
+            handler.clone(),
+            settings,
+            ex.clone(),
+        ))
+        .detach();
+    }
+    msleep(500).await;
+
+    // Requests and batches work the same over HTTP
+    let client = RpcClient::new(http_url.clone()).await?;
+    let rep = client.request(JsonRequest::new("echo", json!([1]))).await?;
+    assert_eq!(rep, json!([1]));
+    let reps = client
+        .request_batch(vec![
+            JsonRequest::new("echo", json!([2])),
+            JsonRequest::new("nonexistent", json!([])),
+        ])
+        .await?;
+    assert_eq!(reps[0].as_ref().unwrap(), &json!([2]));
+    assert!(reps[1].is_err());
+
+    // But subscriptions need a streaming transport
+    assert!(client.request(JsonRequest::new("subscribe", json!([]))).await.is_err());
+
+    // Browsers from unknown origins are turned away
+    let mut stream = TcpStream::connect("127.0.0.1:28341").await?;
+    let request = "POST / HTTP/1.1\r\nOrigin: http://example.com\r\nContent-Length: 2\r\n\r\n{}";
+    stream.write_all(request.as_bytes()).await?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await?;
+    assert!(response.starts_with("HTTP/1.1 403"));
+
+    // Websockets carry both replies and notifications
+    let client = Arc::new(RpcClient::new(ws_url).await?);
+    let rep = client.request(JsonRequest::new("echo", json!([3]))).await?;
+    assert_eq!(rep, json!([3]));
+
+    let notifications = Subscriber::new();
+    let subscription = notifications.clone().subscribe().await;
+    let _client = client.clone();
+    ex.spawn(async move {
+        let _ = _client.subscribe(JsonRequest::new("subscribe", json!([])), notifications).await;
+    })
+    .detach();
+    msleep(500).await;
+
+    handler.subscriber.notify(JsonNotification::new("subscribe", json!([42]))).await;
+    let JsonResult::Notification(notification) = subscription.receive().await else {
+        panic!("Expected a notification")
+    };
+    assert_eq!(notification.params, json!([42]));
+
+    client.close().await?;
+    signal.send(()).await.unwrap();
+    executor.join().unwrap();
+
+    Ok(())
+}
+
+#[async_std::test]
+async fn jsonrpc_auth() -> Result<()> {
+    let ex = Arc::new(smol::Executor::new());
+    let (signal, shutdown) = smol::channel::unbounded::<()>();
+    let _ex = ex.clone();
+    let executor = std::thread::spawn(move || smol::block_on(_ex.run(shutdown.recv())));
+
+    let tcp_url = Url::parse("tcp://127.0.0.1:28343").unwrap();
+    let http_url = Url::parse("http://127.0.0.1:28344").unwrap();
+    let handler = Arc::new(Handler { subscriber: Subscriber::new() });
+    let auth = AuthSettings {
+        tokens: vec!["secret".to_string()],
+        client_keys: vec![],
+        public_methods: vec!["echo".to_string()],
+    };
+    for url in [&tcp_url, &http_url] {
+        let settings = ServerSettings { auth: Some(auth.clone()), ..Default::default() };
+        ex.spawn(listen_and_serve_with_settings(
+            url.clone(),
+            handler.clone(),
+            settings,
+            ex.clone(),
+        ))
+        .detach();
+    }
+    msleep(500).await;
+
+    let credentials = RpcCredentials::token("secret".to_string());
+    let wrong_credentials = RpcCredentials::token("wrong".to_string());
+
+    for url in [tcp_url, http_url] {
+        // Public methods are open to anyone, the others aren't
+        let client = RpcClient::new(url.clone()).await?;
+        assert_eq!(client.request(JsonRequest::new("echo", json!([1]))).await?, json!([1]));
+        assert!(client.request(JsonRequest::new("sleep", json!([]))).await.is_err());
+
+        // Unless the client authenticates
+        let client = RpcClient::new_with_credentials(url.clone(), credentials.clone()).await?;
+        assert_eq!(client.request(JsonRequest::new("sleep", json!([]))).await?, json!("awake"));
+
+        // But not with a wrong token, which streaming transports reject
+        // right away, and HTTP on the first request
+        if let Ok(client) = RpcClient::new_with_credentials(url, wrong_credentials.clone()).await {
+            assert!(client.request(JsonRequest::new("sleep", json!([]))).await.is_err());
+        }
+    }
+
+    signal.send(()).await.unwrap();
+    executor.join().unwrap();
+
+    Ok(())
+}
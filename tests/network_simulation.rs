@@ -0,0 +1,192 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use std::{collections::HashSet, sync::Arc, time::Duration};
+
+use url::Url;
+
+use darkfi::net::{
+    simulation::Simulation,
+    transport::{LinkConditions, MemoryNetwork, MemoryTransport, Transport},
+    P2pPtr, SESSION_OUTBOUND,
+};
+use futures::{AsyncReadExt, AsyncWriteExt};
+
+/// Node names of the channels a node is connected to.
+async fn peers(node: &P2pPtr) -> HashSet<String> {
+    node.channels()
+        .lock()
+        .await
+        .keys()
+        .filter_map(|url| url.host_str().and_then(|h| h.split('.').next()).map(String::from))
+        .collect()
+}
+
+/// Number of outbound channels of a node.
+async fn outbound_count(node: &P2pPtr) -> usize {
+    node.channels()
+        .lock()
+        .await
+        .values()
+        .filter(|c| c.session_type_id() == SESSION_OUTBOUND)
+        .count()
+}
+
+#[test]
+fn seed_bootstrap() {
+    let ex = Arc::new(smol::Executor::new());
+
+    smol::block_on(ex.clone().run(async {
+        // node0 is the seed, everyone else bootstraps from it
+        let sim = Simulation::new("seed_bootstrap", 5, ex.clone(), |i, settings| {
+            if i > 0 {
+                settings.seeds = vec![Simulation::node_address("seed_bootstrap", 0)];
+                settings.outbound_connections = 2;
+            }
+        })
+        .await;
+        sim.start().await.unwrap();
+        let sim = &sim;
+
+        // The seed handed out the addresses of the others
+        for i in 1..5 {
+            assert!(!sim.node(i).hosts().is_empty().await);
+        }
+
+        sim.wait_for(Duration::from_secs(20), || async move {
+            for i in 1..5 {
+                if outbound_count(&sim.node(i)).await < 2 {
+                    return false
+                }
+            }
+            true
+        })
+        .await
+        .unwrap();
+
+        sim.stop().await;
+    }));
+}
+
+#[test]
+fn partition_and_heal() {
+    let ex = Arc::new(smol::Executor::new());
+
+    smol::block_on(ex.clone().run(async {
+        // Everyone manually connects to everyone with a lower index
+        let sim = Simulation::new("partition_and_heal", 4, ex.clone(), |i, settings| {
+            settings.peers =
+                (0..i).map(|j| Simulation::node_address("partition_and_heal", j)).collect();
+        })
+        .await;
+        sim.network()
+            .set_conditions(LinkConditions { latency: Duration::from_millis(5), loss: 0.0 });
+        sim.start().await.unwrap();
+        let sim = &sim;
+        sim.wait_for_connections(Duration::from_secs(20), 3).await.unwrap();
+
+        sim.network().partition(&[&["node0", "node1"], &["node2", "node3"]]);
+        sim.wait_for(Duration::from_secs(20), || async move {
+            peers(&sim.node(0)).await == HashSet::from(["node1".to_string()]) &&
+                peers(&sim.node(3)).await == HashSet::from(["node2".to_string()])
+        })
+        .await
+        .unwrap();
+
+        // Manual sessions reconnect once the network heals
+        sim.network().heal();
+        sim.wait_for_connections(Duration::from_secs(30), 3).await.unwrap();
+
+        sim.stop().await;
+    }));
+}
+
+#[test]
+fn lossy_links_are_reproducible() {
+    // The same seed makes the same loss decisions on every run
+    let dial_outcomes = |seed: u64| {
+        smol::block_on(async {
+            MemoryNetwork::remove("lossy_dials");
+            let network = MemoryNetwork::get("lossy_dials");
+            network.set_seed(seed);
+            network.set_conditions(LinkConditions { latency: Duration::ZERO, loss: 0.5 });
+
+            let url = Url::parse("memory://server.lossy_dials:1").unwrap();
+            let _listener =
+                MemoryTransport::new(None).listen_on(url.clone()).unwrap().await.unwrap();
+
+            let mut outcomes = vec![];
+            for _ in 0..16 {
+                let dial = MemoryTransport::new(Some("client".into())).dial(url.clone(), None);
+                outcomes.push(dial.unwrap().await.is_ok());
+            }
+            outcomes
+        })
+    };
+
+    let outcomes = dial_outcomes(7);
+    assert!(outcomes.contains(&true) && outcomes.contains(&false));
+    assert_eq!(outcomes, dial_outcomes(7));
+}
+
+#[test]
+fn lossy_links_are_reliable() {
+    smol::block_on(async {
+        let network = MemoryNetwork::get("lossy_streams");
+        let url = Url::parse("memory://server.lossy_streams:1").unwrap();
+        let listener = MemoryTransport::new(None).listen_on(url.clone()).unwrap().await.unwrap();
+
+        let mut client = MemoryTransport::new(Some("client".into()))
+            .dial(url.clone(), None)
+            .unwrap()
+            .await
+            .unwrap();
+        let (mut server, peer) = listener.accept().await.unwrap();
+        assert_eq!(peer.host_str(), Some("client.lossy_streams"));
+
+        // Lost chunks get retransmitted, and never overtake later ones
+        network.set_conditions(LinkConditions { latency: Duration::from_millis(1), loss: 0.5 });
+        let payload: Vec<u8> = (0..=255).collect();
+        for chunk in payload.chunks(16) {
+            client.write_all(chunk).await.unwrap();
+        }
+
+        // Nothing arrives until the simulated clock moves
+        let mut buf = vec![0u8; payload.len()];
+        assert!(futures::poll!(Box::pin(server.read(&mut buf))).is_pending());
+
+        // Even the unluckiest chunk arrives within 16 retransmissions
+        network.advance(Duration::from_millis(33));
+        server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, payload);
+        network.set_conditions(LinkConditions::default());
+
+        // Partitions cut the link
+        network.partition(&[&["client"], &["server"]]);
+        assert_eq!(server.read(&mut buf).await.unwrap(), 0);
+        assert!(client.write_all(b"ohai").await.is_err());
+        assert!(MemoryTransport::new(Some("client".into()))
+            .dial(url.clone(), None)
+            .unwrap()
+            .await
+            .is_err());
+
+        network.heal();
+        assert!(MemoryTransport::new(Some("client".into())).dial(url, None).unwrap().await.is_ok());
+    });
+}
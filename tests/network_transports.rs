@@ -21,12 +21,15 @@ use std::{env::var, fs};
 use async_std::{
     io,
     io::{ReadExt, WriteExt},
+    net::TcpListener,
     stream::StreamExt,
     task,
 };
 use url::Url;
 
-use darkfi::net::transport::{TcpTransport, TorTransport, Transport, UnixTransport};
+use darkfi::net::transport::{
+    NymTransport, TcpTransport, TlsUpgrade, TorTransport, Transport, UnixTransport,
+};
 
 #[async_std::test]
 async fn unix_transport() {
@@ -87,7 +90,8 @@ async fn tcp_tls_transport() {
     let url = Url::parse("tcp+tls://127.0.0.1:5433").unwrap();
 
     let listener = tcp.listen_on(url.clone()).unwrap().await.unwrap();
-    let (acceptor, listener) = tcp.upgrade_listener(listener).unwrap().await.unwrap();
+    let (acceptor, listener) =
+        tcp.upgrade_listener(TlsUpgrade::default(), listener).unwrap().await.unwrap();
 
     let _ = task::spawn(async move {
         let mut incoming = listener.incoming();
@@ -102,7 +106,61 @@ async fn tcp_tls_transport() {
     let payload = b"ohai tls";
 
     let client = tcp.dial(url, None).unwrap().await.unwrap();
-    let mut client = tcp.upgrade_dialer(client).unwrap().await.unwrap();
+    let mut client = tcp.upgrade_dialer(TlsUpgrade::default(), client).unwrap().await.unwrap();
+    client.write_all(payload).await.unwrap();
+    let mut buf = vec![0_u8; 8];
+    client.read_exact(&mut buf).await.unwrap();
+
+    assert_eq!(buf, payload);
+}
+
+/// Mock nym-client websocket that delivers every sent message back to us,
+/// since it's the only client in this mixnet.
+async fn mock_nym_client(listener: TcpListener) {
+    use async_tungstenite::tungstenite::Message;
+    use futures::{SinkExt, StreamExt};
+
+    let (stream, _) = listener.accept().await.unwrap();
+    let ws = async_tungstenite::accept_async(stream).await.unwrap();
+    let (mut writer, mut reader) = StreamExt::split(ws);
+
+    while let Some(Ok(Message::Binary(msg))) = StreamExt::next(&mut reader).await {
+        let reply = match msg[0] {
+            // Self address request
+            0x03 => [&[0x02][..], &[7u8; 96]].concat(),
+            // Send request: tag | recipient | connection id | length | message
+            0x00 => {
+                let data = &msg[1 + 96 + 8 + 8..];
+                [&[0x01, 0x00][..], &(data.len() as u64).to_be_bytes(), data].concat()
+            }
+            _ => continue,
+        };
+        SinkExt::send(&mut writer, Message::Binary(reply)).await.unwrap();
+    }
+}
+
+#[async_std::test]
+async fn nym_transport() {
+    let mock = TcpListener::bind("127.0.0.1:5434").await.unwrap();
+    let _ = task::spawn(mock_nym_client(mock));
+
+    let nym = NymTransport::new(Url::parse("ws://127.0.0.1:5434").unwrap());
+    let url = Url::parse("nym://127.0.0.1:5434").unwrap();
+
+    let listener = nym.clone().listen_on(url).unwrap().await.unwrap();
+    let self_address = listener.self_address();
+
+    let _ = task::spawn(async move {
+        while let Ok(stream) = listener.accept().await {
+            let (mut reader, mut writer) = smol::io::split(stream);
+            io::copy(&mut reader, &mut writer).await.unwrap();
+        }
+    });
+
+    let payload = b"ohai nym";
+
+    let url = Url::parse(&format!("nym://{}", self_address.to_host())).unwrap();
+    let mut client = nym.dial(url, None).unwrap().await.unwrap();
     client.write_all(payload).await.unwrap();
     let mut buf = vec![0_u8; 8];
     client.read_exact(&mut buf).await.unwrap();
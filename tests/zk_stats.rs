@@ -0,0 +1,57 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use darkfi::{
+    zk::{circuit_k, vm::ZkCircuit, vm_stack::Witness, CircuitStats},
+    zkas::{compile, Opcode, ZkBinary, MAX_K},
+};
+use halo2_proofs::{circuit::Value, dev::MockProver, pasta::pallas};
+
+#[test]
+fn zk_stats_minimal_k() {
+    let source = include_str!("../proof/arithmetic.zk");
+    let zkbin = ZkBinary::decode(&compile(source, 0, true).unwrap().0).unwrap();
+    let stats = CircuitStats::measure(&zkbin).unwrap();
+
+    assert!(stats.rows + stats.reserved_rows <= 1 << stats.k);
+    assert_eq!(stats.opcodes.len(), zkbin.opcodes.len());
+    assert!(matches!(stats.opcodes[0].0, Opcode::BaseAdd));
+    assert!(stats.opcodes[0].1.regions > 0);
+    assert!(stats.opcodes[0].1.advice_cells >= 3);
+
+    // The circuit can be synthesized with k, but not with anything smaller
+    let (a, b) = (pallas::Base::from(42), pallas::Base::from(69));
+    let public_inputs = vec![a + b, a * b, a - b];
+    let witnesses = vec![Witness::Base(Value::known(a)), Witness::Base(Value::known(b))];
+
+    let circuit = ZkCircuit::new(witnesses.clone(), zkbin.clone());
+    let prover = MockProver::run(stats.k, &circuit, vec![public_inputs.clone()]).unwrap();
+    prover.assert_satisfied();
+
+    let circuit = ZkCircuit::new(witnesses, zkbin.clone());
+    assert!(MockProver::run(stats.k - 1, &circuit, vec![public_inputs]).is_err());
+
+    // Binaries record the size they were compiled with, which has to
+    // fit the circuit and stay within MAX_K
+    assert!(circuit_k(&zkbin).is_err());
+    let zkbin = ZkBinary::decode(&compile(source, stats.k, true).unwrap().0).unwrap();
+    assert_eq!(circuit_k(&zkbin).unwrap(), stats.k);
+    let zkbin = ZkBinary::decode(&compile(source, MAX_K, true).unwrap().0).unwrap();
+    assert_eq!(circuit_k(&zkbin).unwrap(), MAX_K);
+    assert!(ZkBinary::decode(&compile(source, MAX_K + 1, true).unwrap().0).is_err());
+}
@@ -0,0 +1,101 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use darkfi::zkas::{compile, compiler::MAGIC_BYTES, VarType, ZkBinary};
+use darkfi_serial::{serialize, VarInt};
+
+#[test]
+fn zkas_binary_roundtrip() {
+    let source = include_str!("../proof/opcodes.zk");
+    let zkbin = ZkBinary::decode(&compile(source, 11, true).unwrap().0).unwrap();
+
+    assert_eq!(zkbin.namespace, "Opcodes");
+    assert_eq!(zkbin.k, Some(11));
+
+    let debug_info = zkbin.debug_info.as_ref().unwrap();
+    assert_eq!(debug_info.witnesses[0], "value");
+    assert_eq!(debug_info.opcodes[0], (26, "vcv".to_string()));
+
+    // The disassembly is valid zkas source for the same circuit
+    let disassembly = zkbin.disassemble();
+    assert!(disassembly.contains("\tvcv = ec_mul_short(value, VALUE_COMMIT_VALUE); # line 26\n"));
+
+    let zkbin2 = ZkBinary::decode(&compile(&disassembly, 11, true).unwrap().0).unwrap();
+    assert_eq!(format!("{:?}", zkbin.opcodes), format!("{:?}", zkbin2.opcodes));
+    assert_eq!(zkbin.debug_info.unwrap().witnesses, zkbin2.debug_info.unwrap().witnesses);
+}
+
+#[test]
+fn zkas_binary_stripped() {
+    let source = include_str!("../proof/opcodes.zk");
+    let zkbin = ZkBinary::decode(&compile(source, 13, false).unwrap().0).unwrap();
+
+    assert!(zkbin.debug_info.is_none());
+    assert!(zkbin
+        .disassemble()
+        .contains("\tvar_0 = ec_mul_short(witness_0, VALUE_COMMIT_VALUE);\n"));
+}
+
+#[test]
+fn zkas_binary_rejects_corruption() {
+    let source = include_str!("../proof/opcodes.zk");
+    let bincode = compile(source, 13, true).unwrap().0;
+
+    // Flipped bit in the circuit section
+    let mut corrupted = bincode.clone();
+    corrupted[bincode.len() / 2] ^= 1;
+    assert!(ZkBinary::decode(&corrupted).is_err());
+
+    // Trailing garbage
+    let mut trailing = bincode.clone();
+    trailing.push(0x00);
+    assert!(ZkBinary::decode(&trailing).is_err());
+
+    // Truncated
+    assert!(ZkBinary::decode(&bincode[..bincode.len() - 1]).is_err());
+    assert!(ZkBinary::decode(&bincode[..3]).is_err());
+
+    // Unknown version
+    let mut version = bincode;
+    version[4] = 0xff;
+    assert!(ZkBinary::decode(&version).is_err());
+}
+
+#[test]
+fn zkas_binary_decodes_legacy() {
+    let mut bincode = MAGIC_BYTES.to_vec();
+    bincode.push(2);
+    bincode.extend_from_slice(&serialize(&"Legacy".to_string()));
+    bincode.extend_from_slice(b".constant");
+    bincode.extend_from_slice(b".literal");
+    bincode.extend_from_slice(b".contract");
+    bincode.push(VarType::Base as u8);
+    bincode.extend_from_slice(b".circuit");
+    // constrain_instance(witness_0)
+    bincode.push(0xf0);
+    bincode.extend_from_slice(&serialize(&VarInt(1)));
+    bincode.push(0x00);
+    bincode.extend_from_slice(&serialize(&VarInt(0)));
+
+    let zkbin = ZkBinary::decode(&bincode).unwrap();
+    assert_eq!(zkbin.version, 2);
+    assert_eq!(zkbin.k, None);
+    assert_eq!(zkbin.witnesses, vec![VarType::Base]);
+    assert_eq!(zkbin.opcodes.len(), 1);
+    assert!(zkbin.debug_info.is_none());
+}
@@ -0,0 +1,129 @@
+/* This file is part of DarkFi (https://dark.fi)
+ *
+ * Copyright (C) 2020-2023 Dyne.org foundation
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU Affero General Public License as
+ * published by the Free Software Foundation, either version 3 of the
+ * License, or (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU Affero General Public License for more details.
+ *
+ * You should have received a copy of the GNU Affero General Public License
+ * along with this program.  If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use darkfi::zkas::{compile, Diagnostic, Lexer, Parser, Severity, ZkBinary};
+
+fn errors(diagnostics: &[Diagnostic]) -> Vec<(&str, usize)> {
+    diagnostics
+        .iter()
+        .filter(|d| d.severity == Severity::Error)
+        .map(|d| (d.message.as_str(), d.span.line))
+        .collect()
+}
+
+#[test]
+fn zkas_compiles_source() {
+    let source = include_str!("../proof/opcodes.zk");
+    let (bincode, _) = compile(source, 13, true).unwrap();
+    assert!(ZkBinary::decode(&bincode).is_ok());
+}
+
+#[test]
+fn zkas_lexer_collects_errors() {
+    let source = "constant \"Foo\" {}\ncontract \"Foo\" { Base @a, }\ncircuit \"Foo\" { $ }\n";
+    let diagnostics = compile(source, 13, true).unwrap_err();
+
+    assert_eq!(errors(&diagnostics), vec![("Invalid token `@`", 2), ("Invalid token `$`", 3)]);
+    assert!(diagnostics.iter().all(|d| d.stage == "Lexer"));
+}
+
+#[test]
+fn zkas_parser_collects_errors() {
+    let source = r#"
+constant "Foo" {
+    EcFixedPoint FOO,
+}
+
+contract "Foo" {
+    Base a,
+    Float b,
+}
+
+circuit "Foo" {
+    c = base_add(a, a);
+    d = base_frobnicate(c, c);
+    constrain_instance(c);
+}
+"#;
+    let diagnostics = compile(source, 13, true).unwrap_err();
+
+    assert_eq!(
+        errors(&diagnostics),
+        vec![
+            ("`FOO` is not a valid EcFixedPoint constant.", 3),
+            ("`Float` is an unsupported witness type.", 8),
+            ("Unimplemented opcode `base_frobnicate`.", 13),
+        ]
+    );
+
+    // Hints are attached as notes
+    assert_eq!(diagnostics[0].notes, vec!["Supported: [\"VALUE_COMMIT_RANDOM\"]".to_string()]);
+}
+
+#[test]
+fn zkas_analyzer_collects_errors() {
+    let source = r#"
+constant "Foo" {}
+
+contract "Foo" {
+    Base a,
+    Scalar s,
+}
+
+circuit "Foo" {
+    b = base_add(a, s);
+    c = base_add(a);
+    d = base_mul(c, c);
+    constrain_instance(e);
+    constrain_instance(d);
+}
+"#;
+    let diagnostics = compile(source, 13, true).unwrap_err();
+
+    // `c` is still known after its statement failed, so `d` type-checks.
+    assert_eq!(
+        errors(&diagnostics),
+        vec![
+            ("Incorrect argument type. Expected `Base`, got `Scalar`.", 10),
+            ("Incorrect number of arguments for statement. Expected 2, got 1.", 11),
+            ("Unknown variable reference `e`.", 13),
+        ]
+    );
+    assert!(diagnostics.iter().all(|d| d.stage == "Semantic"));
+}
+
+#[test]
+fn zkas_warnings_do_not_fail() {
+    let source = r#"
+constant "Foo" {}
+contract "Foo" { Base a, }
+circuit "Foo" { constrain_instance(a); }
+"#;
+    let tokens = Lexer::new(source.chars()).lex().unwrap();
+    let parser = Parser::new(tokens);
+    assert!(parser.parse().is_ok());
+
+    let diagnostics = parser.diagnostics();
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Severity::Warning);
+    assert_eq!(diagnostics[0].message, "constant section is empty.");
+
+    // Warnings of all the stages come with the binary
+    let (_, warnings) = compile(source, 13, true).unwrap();
+    assert!(warnings.contains(&diagnostics[0]));
+}
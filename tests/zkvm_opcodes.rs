@@ -34,6 +34,7 @@ use simplelog::{ColorChoice, Config, LevelFilter, TermLogger, TerminalMode};
 
 use darkfi::{
     zk::{
+        circuit_k,
         proof::{ProvingKey, VerifyingKey},
         vm::ZkCircuit,
         vm_stack::{empty_witnesses, Witness},
@@ -50,6 +51,7 @@ fn zkvm_opcodes() -> Result<()> {
 
     let bincode = include_bytes!("../proof/opcodes.zk.bin");
     let zkbin = ZkBinary::decode(bincode)?;
+    let k = circuit_k(&zkbin)?;
 
     // Values for the proof
     let value = 666_u64;
@@ -116,15 +118,20 @@ fn zkvm_opcodes() -> Result<()> {
         pub_y,
         ephem_x,
         ephem_y,
+        b,
+        pallas::Base::zero(),
+        b,
+        pallas::Base::zero(),
+        pallas::Base::one(),
     ];
 
     let circuit = ZkCircuit::new(prover_witnesses, zkbin.clone());
-    let proving_key = ProvingKey::build(13, &circuit);
+    let proving_key = ProvingKey::build(k, &circuit);
     let proof = Proof::create(&proving_key, &[circuit], &public_inputs, &mut OsRng)?;
 
     let verifier_witnesses = empty_witnesses(&zkbin);
     let circuit = ZkCircuit::new(verifier_witnesses, zkbin);
-    let verifying_key = VerifyingKey::build(13, &circuit);
+    let verifying_key = VerifyingKey::build(k, &circuit);
     proof.verify(&verifying_key, &public_inputs)?;
 
     Ok(())